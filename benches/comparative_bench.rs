@@ -277,11 +277,58 @@ fn bench_many_files_comparison(c: &mut Criterion) {
     group.finish();
 }
 
+/// Upload a 64MB file through each SSH backend to the scratch directory in
+/// `SY_BENCH_SSH_DEST` (e.g., `user@host:/tmp/sy-bench`); skipped without it
+fn bench_ssh_backends(c: &mut Criterion) {
+    let Ok(remote) = std::env::var("SY_BENCH_SSH_DEST") else {
+        return;
+    };
+    let mut group = c.benchmark_group("ssh_backend_large_file");
+    group.sample_size(10);
+
+    // Pseudorandom content, so neither backend gets to compress it
+    let source = TempDir::new().unwrap();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let content: Vec<u8> = (0..64 * 1024 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    fs::write(source.path().join("large.bin"), &content).unwrap();
+
+    for backend in ["libssh2", "system"] {
+        let mut run = 0;
+        group.bench_function(backend, |b| {
+            b.iter(|| {
+                // A fresh destination each time, so every run uploads
+                run += 1;
+                let output = Command::new(env!("CARGO_BIN_EXE_sy"))
+                    .args([
+                        "--ssh-backend",
+                        backend,
+                        &format!("{}/", source.path().display()),
+                        &format!("{}/{}-{}", remote, backend, run),
+                    ])
+                    .output()
+                    .unwrap();
+                assert!(output.status.success());
+                black_box(output);
+            });
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_sy_vs_rsync_vs_cp,
     bench_sy_vs_rsync_large_file,
     bench_idempotent_comparison,
-    bench_many_files_comparison
+    bench_many_files_comparison,
+    bench_ssh_backends
 );
 criterion_main!(benches);
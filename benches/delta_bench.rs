@@ -1,9 +1,111 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use std::fs;
 use std::io::{Seek, SeekFrom, Write};
 use std::process::Command;
+use sy::delta::checksum::compute_checksums_batched;
+use sy::delta::generator::generate_delta_ops;
+use sy::delta::{calculate_block_size, Adler32, Buzhash, RollingHash, WeakHash};
 use tempfile::TempDir;
 
+/// Pseudo-random bytes (xorshift), so the weak hashes rarely collide by accident
+fn random_data(len: usize) -> Vec<u8> {
+    let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u8
+        })
+        .collect()
+}
+
+fn roll_all<H: RollingHash>(data: &[u8], block_size: usize) -> u32 {
+    let mut hasher = H::new(block_size);
+    hasher.update_block(&data[..block_size]);
+    let mut acc = 0;
+    for i in block_size..data.len() {
+        hasher.roll(data[i - block_size], data[i]);
+        acc ^= hasher.digest();
+    }
+    acc
+}
+
+fn bench_rolling_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rolling_hash");
+    let data = random_data(16 * 1024 * 1024);
+    let block_size = 4096;
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("adler32_roll", |b| {
+        b.iter(|| roll_all::<Adler32>(black_box(&data), block_size))
+    });
+    group.bench_function("buzhash_roll", |b| {
+        b.iter(|| roll_all::<Buzhash>(black_box(&data), block_size))
+    });
+    group.bench_function("adler32_blocks", |b| {
+        b.iter(|| {
+            data.chunks(block_size)
+                .fold(0, |acc, block| acc ^ Adler32::hash(black_box(block)))
+        })
+    });
+    group.bench_function("buzhash_blocks", |b| {
+        b.iter(|| {
+            data.chunks(block_size)
+                .fold(0, |acc, block| acc ^ Buzhash::hash(black_box(block)))
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_delta_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("delta_generation_64MB");
+    group.sample_size(10);
+
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("source.bin");
+    let dest = temp.path().join("dest.bin");
+
+    // One inserted byte every 4MB shifts every block after it, so most of the
+    // file is matched by rolling rather than at block boundaries
+    let old = random_data(64 * 1024 * 1024);
+    let mut new = Vec::with_capacity(old.len() + 16);
+    for chunk in old.chunks(4 * 1024 * 1024) {
+        new.push(0xAB);
+        new.extend_from_slice(chunk);
+    }
+    fs::write(&dest, &old).unwrap();
+    fs::write(&source, &new).unwrap();
+
+    let block_size = calculate_block_size(old.len() as u64);
+    group.throughput(Throughput::Bytes(new.len() as u64));
+
+    for weak_hash in [WeakHash::Adler32, WeakHash::Buzhash] {
+        let mut checksums = Vec::new();
+        compute_checksums_batched(&dest, block_size, weak_hash, |batch| {
+            checksums.extend_from_slice(batch);
+            Ok(())
+        })
+        .unwrap();
+
+        group.bench_function(weak_hash.as_str(), |b| {
+            b.iter(|| {
+                let mut ops = 0usize;
+                generate_delta_ops(&source, &checksums, block_size, weak_hash, false, |op| {
+                    black_box(op);
+                    ops += 1;
+                    Ok(())
+                })
+                .unwrap();
+                ops
+            })
+        });
+    }
+
+    group.finish();
+}
+
 fn create_sparse_file(path: &std::path::Path, size_mb: usize, modification_offset_mb: usize) {
     let mut file = fs::File::create(path).unwrap();
 
@@ -227,6 +329,8 @@ criterion_group!(
     benches,
     bench_delta_sync_small_change,
     bench_delta_sync_vs_full_copy,
-    bench_delta_sync_large_file
+    bench_delta_sync_large_file,
+    bench_rolling_hash,
+    bench_delta_generation
 );
 criterion_main!(benches);
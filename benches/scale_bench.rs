@@ -38,6 +38,9 @@ fn bench_deletion_planning(c: &mut Criterion) {
                         nlink: 1,
                         acls: None,
                         bsd_flags: None,
+                        atime: None,
+                        crtime: None,
+                        perms: None,
                     })
                     .collect();
 
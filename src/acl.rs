@@ -0,0 +1,528 @@
+use crate::error::{Result, SyncError};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// How ACLs from a different ACL model than the destination's are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AclMapPolicy {
+    /// Translate entries to the destination's model, dropping ones with no equivalent (default)
+    #[default]
+    Translate,
+
+    /// Leave ACLs from a different model unapplied
+    Skip,
+
+    /// Fail the file if its ACLs use a different model
+    Strict,
+}
+
+/// ACL model a set of entries is written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AclModel {
+    /// POSIX.1e draft ACLs (Linux): owner/group/other/mask plus named entries
+    Posix,
+
+    /// NFSv4-style ACLs (macOS, SMB): ordered allow/deny entries with inheritance flags
+    Nfs4,
+}
+
+impl AclModel {
+    /// Model used by this platform's filesystems
+    pub fn native() -> Self {
+        if cfg!(target_os = "macos") {
+            Self::Nfs4
+        } else {
+            Self::Posix
+        }
+    }
+}
+
+impl fmt::Display for AclModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Posix => write!(f, "POSIX"),
+            Self::Nfs4 => write!(f, "NFSv4"),
+        }
+    }
+}
+
+/// Flag marking a POSIX default (inheritable) entry
+const POSIX_DEFAULT_FLAG: &str = "default";
+
+/// NFSv4 flags that make an entry inheritable
+const NFS4_INHERIT_FLAGS: [&str; 2] = ["file_inherit", "directory_inherit"];
+
+/// NFSv4 flag for entries that only apply to children
+const NFS4_ONLY_INHERIT_FLAG: &str = "only_inherit";
+
+/// The only permissions POSIX ACLs know about
+const POSIX_PERMS: [&str; 3] = ["read", "write", "execute"];
+
+/// One ACL entry in the text form used by the scanner: `allow|deny:flags:kind:name:perms`
+///
+/// Flags and perms are comma-separated; the name is empty for the owner,
+/// owning group, mask and other entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AclRule {
+    pub allow: bool,
+    pub flags: Vec<String>,
+    pub kind: String,
+    pub name: String,
+    pub perms: Vec<String>,
+}
+
+impl AclRule {
+    /// Parse one line of ACL text, returning None if it is malformed
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.trim().splitn(4, ':');
+        let allow = match parts.next()? {
+            "allow" => true,
+            "deny" => false,
+            _ => return None,
+        };
+        let flags = split_list(parts.next()?);
+        let kind = parts.next()?.to_string();
+        let (name, perms) = parts.next()?.rsplit_once(':')?;
+        if kind.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            allow,
+            flags,
+            kind,
+            name: name.to_string(),
+            perms: split_list(perms),
+        })
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    fn has_perm(&self, perm: &str) -> bool {
+        self.perms.iter().any(|p| p == perm)
+    }
+
+    /// Owner, owning group, mask or other entry (carried by the mode bits elsewhere)
+    fn is_posix_base(&self) -> bool {
+        matches!(self.kind.as_str(), "mask" | "other") || self.name.is_empty()
+    }
+
+    /// POSIX permission bits (rwx as 4/2/1) granted by this entry
+    fn posix_bits(&self) -> u32 {
+        let mut bits = 0;
+        if self.has_perm("read") {
+            bits |= 4;
+        }
+        // Appending is the closest NFSv4 right to a partial write
+        if self.has_perm("write") || self.has_perm("append") {
+            bits |= 2;
+        }
+        if self.has_perm("execute") {
+            bits |= 1;
+        }
+        bits
+    }
+}
+
+impl fmt::Display for AclRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}",
+            if self.allow { "allow" } else { "deny" },
+            self.flags.join(","),
+            self.kind,
+            self.name,
+            self.perms.join(",")
+        )
+    }
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Detect which model a set of entries was read from
+///
+/// Deny entries, inheritance flags and fine-grained rights only exist in
+/// NFSv4 ACLs; a POSIX ACL always carries its base (owner/group/other) entries.
+/// Entries with neither are treated as NFSv4, since they can't be a complete
+/// POSIX ACL.
+pub fn detect_model(rules: &[AclRule]) -> AclModel {
+    let nfs4_only = rules.iter().any(|r| {
+        !r.allow
+            || r.flags.iter().any(|f| f != POSIX_DEFAULT_FLAG)
+            || r.perms.iter().any(|p| !POSIX_PERMS.contains(&p.as_str()))
+    });
+    if !nfs4_only && rules.iter().any(AclRule::is_posix_base) {
+        AclModel::Posix
+    } else {
+        AclModel::Nfs4
+    }
+}
+
+/// Entries converted to another model
+#[derive(Debug, Default)]
+pub struct Translated {
+    pub rules: Vec<AclRule>,
+    /// Entries with no equivalent in the target model (e.g. NFSv4 deny entries on POSIX)
+    pub dropped: usize,
+}
+
+/// Translate entries to `to`
+///
+/// `mode` supplies the owner/group/other permissions POSIX ACLs require and
+/// NFSv4 ACLs leave to the mode bits. Default (inheritable) entries only
+/// apply to directories.
+pub fn translate(rules: &[AclRule], to: AclModel, mode: u32, is_dir: bool) -> Translated {
+    match to {
+        AclModel::Nfs4 => posix_to_nfs4(rules, is_dir),
+        AclModel::Posix => nfs4_to_posix(rules, mode, is_dir),
+    }
+}
+
+fn posix_to_nfs4(rules: &[AclRule], is_dir: bool) -> Translated {
+    // Named entries are limited by the mask of the same (access or default) ACL
+    let mask = |default: bool| {
+        rules
+            .iter()
+            .find(|r| r.kind == "mask" && r.has_flag(POSIX_DEFAULT_FLAG) == default)
+            .map(AclRule::posix_bits)
+            .unwrap_or(7)
+    };
+
+    let mut result = Translated::default();
+    for rule in rules.iter().filter(|r| !r.is_posix_base()) {
+        let default = rule.has_flag(POSIX_DEFAULT_FLAG);
+        if default && !is_dir {
+            result.dropped += 1;
+            continue;
+        }
+
+        let bits = rule.posix_bits() & mask(default);
+        let mut perms = Vec::new();
+        if bits & 4 != 0 {
+            perms.extend(["read", "readattr", "readextattr", "readsecurity"]);
+        }
+        if bits & 2 != 0 {
+            perms.extend(["write", "append", "writeattr", "writeextattr"]);
+            if is_dir {
+                perms.push("delete_child");
+            }
+        }
+        if bits & 1 != 0 {
+            perms.push("execute");
+        }
+        if perms.is_empty() {
+            // Fully masked out: grants nothing
+            continue;
+        }
+
+        let flags: &[&str] = if default {
+            &["file_inherit", "directory_inherit", NFS4_ONLY_INHERIT_FLAG]
+        } else {
+            &[]
+        };
+        result.rules.push(AclRule {
+            allow: true,
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            kind: rule.kind.clone(),
+            name: rule.name.clone(),
+            perms: perms.into_iter().map(String::from).collect(),
+        });
+    }
+    result
+}
+
+fn nfs4_to_posix(rules: &[AclRule], mode: u32, is_dir: bool) -> Translated {
+    let mut result = Translated::default();
+    // (kind, name, default) -> rwx bits, in first-seen order
+    let mut named: Vec<(String, String, bool, u32)> = Vec::new();
+
+    for rule in rules {
+        let bits = rule.posix_bits();
+        if !rule.allow || rule.is_posix_base() || bits == 0 {
+            // Deny entries, special principals and rights-only entries have no POSIX form
+            result.dropped += 1;
+            continue;
+        }
+
+        let inherits = is_dir && NFS4_INHERIT_FLAGS.iter().any(|f| rule.has_flag(f));
+        let mut targets = Vec::new();
+        if !rule.has_flag(NFS4_ONLY_INHERIT_FLAG) {
+            targets.push(false);
+        }
+        if inherits {
+            targets.push(true);
+        }
+        if targets.is_empty() {
+            // Inherit-only entry on a file
+            result.dropped += 1;
+            continue;
+        }
+
+        for default in targets {
+            match named
+                .iter_mut()
+                .find(|(k, n, d, _)| *k == rule.kind && *n == rule.name && *d == default)
+            {
+                Some(existing) => existing.3 |= bits,
+                None => named.push((rule.kind.clone(), rule.name.clone(), default, bits)),
+            }
+        }
+    }
+
+    for default in [false, true] {
+        let entries: Vec<_> = named.iter().filter(|(_, _, d, _)| *d == default).collect();
+        if default && entries.is_empty() {
+            continue;
+        }
+
+        let base = |kind: &str, bits: u32| posix_rule(kind, "", default, bits);
+        let group_bits = (mode >> 3) & 7;
+        result.rules.push(base("user", (mode >> 6) & 7));
+        for kind in ["user", "group"] {
+            if kind == "group" {
+                result.rules.push(base("group", group_bits));
+            }
+            for (_, name, _, bits) in entries.iter().filter(|(k, ..)| k == kind) {
+                result.rules.push(posix_rule(kind, name, default, *bits));
+            }
+        }
+        if !entries.is_empty() {
+            let mask = entries
+                .iter()
+                .fold(group_bits, |acc, (.., bits)| acc | bits);
+            result.rules.push(base("mask", mask));
+        }
+        result.rules.push(base("other", mode & 7));
+    }
+    result
+}
+
+fn posix_rule(kind: &str, name: &str, default: bool, bits: u32) -> AclRule {
+    AclRule {
+        allow: true,
+        flags: if default {
+            vec![POSIX_DEFAULT_FLAG.to_string()]
+        } else {
+            Vec::new()
+        },
+        kind: kind.to_string(),
+        name: name.to_string(),
+        perms: POSIX_PERMS
+            .iter()
+            .zip([4, 2, 1])
+            .filter(|(_, bit)| bits & bit != 0)
+            .map(|(p, _)| p.to_string())
+            .collect(),
+    }
+}
+
+/// Prepare entries read from the source for writing to `path` in the `target` model
+///
+/// Entries already in the target model are returned unchanged. Otherwise
+/// `policy` decides: translate them, skip them (returns no entries), or fail
+/// with [`SyncError::AclModelMismatch`]. Entries translation had to drop are
+/// counted in `dropped`, for the caller to report.
+pub fn map_acls(
+    path: &Path,
+    rules: Vec<AclRule>,
+    policy: AclMapPolicy,
+    target: AclModel,
+    mode: u32,
+    is_dir: bool,
+) -> Result<Translated> {
+    let source = detect_model(&rules);
+    if rules.is_empty() || source == target {
+        return Ok(Translated { rules, dropped: 0 });
+    }
+
+    match policy {
+        AclMapPolicy::Translate => Ok(translate(&rules, target, mode, is_dir)),
+        AclMapPolicy::Skip => {
+            tracing::debug!(
+                "Skipping {} ACL on {} ({} destination)",
+                source,
+                path.display(),
+                target
+            );
+            Ok(Translated {
+                rules: Vec::new(),
+                dropped: 0,
+            })
+        }
+        AclMapPolicy::Strict => Err(SyncError::AclModelMismatch {
+            path: path.to_path_buf(),
+            source_model: source.to_string(),
+            dest_model: target.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(text: &str) -> Vec<AclRule> {
+        text.lines().filter_map(AclRule::parse).collect()
+    }
+
+    fn text(rules: &[AclRule]) -> Vec<String> {
+        rules.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let line = "allow:file_inherit,directory_inherit:user:alice:read,write";
+        let rule = AclRule::parse(line).unwrap();
+        assert!(rule.allow);
+        assert_eq!(rule.flags, vec!["file_inherit", "directory_inherit"]);
+        assert_eq!(rule.name, "alice");
+        assert_eq!(rule.to_string(), line);
+
+        assert_eq!(
+            AclRule::parse("allow::user::read,write")
+                .unwrap()
+                .to_string(),
+            "allow::user::read,write"
+        );
+        assert!(AclRule::parse("not-an-acl").is_none());
+        assert!(AclRule::parse("maybe::user:bob:read").is_none());
+    }
+
+    #[test]
+    fn test_detect_model() {
+        let posix = rules(
+            "allow::user::read,write\nallow::user:bob:read\nallow::group::read\n\
+             allow::mask::read\nallow::other::",
+        );
+        assert_eq!(detect_model(&posix), AclModel::Posix);
+
+        let nfs4 = rules("deny::user:bob:write\nallow:file_inherit:group:staff:read,readattr");
+        assert_eq!(detect_model(&nfs4), AclModel::Nfs4);
+
+        // Named entries alone can't be a POSIX ACL
+        assert_eq!(detect_model(&rules("allow::user:bob:read")), AclModel::Nfs4);
+    }
+
+    #[test]
+    fn test_posix_to_nfs4() {
+        let posix = rules(
+            "allow::user::read,write,execute\nallow::user:bob:read,write\n\
+             allow::group::read\nallow::mask::read\nallow::other::read\n\
+             allow:default:group:staff:read,execute",
+        );
+
+        let translated = translate(&posix, AclModel::Nfs4, 0o754, true);
+        assert_eq!(translated.dropped, 0);
+        assert_eq!(
+            text(&translated.rules),
+            vec![
+                // Write is masked out
+                "allow::user:bob:read,readattr,readextattr,readsecurity",
+                "allow:file_inherit,directory_inherit,only_inherit:group:staff:\
+                 read,readattr,readextattr,readsecurity,execute",
+            ]
+        );
+
+        // Default entries don't apply to files
+        let translated = translate(&posix, AclModel::Nfs4, 0o644, false);
+        assert_eq!(translated.rules.len(), 1);
+        assert_eq!(translated.dropped, 1);
+    }
+
+    #[test]
+    fn test_nfs4_to_posix() {
+        let nfs4 = rules(
+            "deny::user:eve:write\n\
+             allow::user:bob:read,readattr\n\
+             allow:file_inherit,directory_inherit:group:staff:read,write,execute\n\
+             allow::user:carol:readattr",
+        );
+
+        let translated = translate(&nfs4, AclModel::Posix, 0o750, true);
+        // Deny entry and rights-only entry have no POSIX form
+        assert_eq!(translated.dropped, 2);
+        assert_eq!(
+            text(&translated.rules),
+            vec![
+                "allow::user::read,write,execute",
+                "allow::user:bob:read",
+                "allow::group::read,execute",
+                "allow::group:staff:read,write,execute",
+                "allow::mask::read,write,execute",
+                "allow::other::",
+                "allow:default:user::read,write,execute",
+                "allow:default:group::read,execute",
+                "allow:default:group:staff:read,write,execute",
+                "allow:default:mask::read,write,execute",
+                "allow:default:other::",
+            ]
+        );
+        assert_eq!(detect_model(&translated.rules), AclModel::Posix);
+    }
+
+    #[test]
+    fn test_map_acls_policies() {
+        let path = Path::new("file");
+        let nfs4 = rules("allow::user:bob:read\ndeny::user:eve:read");
+
+        let mapped = map_acls(
+            path,
+            nfs4.clone(),
+            AclMapPolicy::Translate,
+            AclModel::Posix,
+            0o640,
+            false,
+        )
+        .unwrap();
+        assert_eq!(detect_model(&mapped.rules), AclModel::Posix);
+        // The deny entry has no POSIX equivalent
+        assert_eq!(mapped.dropped, 1);
+
+        let skipped = map_acls(
+            path,
+            nfs4.clone(),
+            AclMapPolicy::Skip,
+            AclModel::Posix,
+            0o640,
+            false,
+        )
+        .unwrap();
+        assert!(skipped.rules.is_empty());
+
+        let err = map_acls(
+            path,
+            nfs4.clone(),
+            AclMapPolicy::Strict,
+            AclModel::Posix,
+            0o640,
+            false,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SyncError::AclModelMismatch { .. }));
+
+        // Same model is passed through regardless of policy
+        let same = map_acls(
+            path,
+            nfs4.clone(),
+            AclMapPolicy::Strict,
+            AclModel::Nfs4,
+            0,
+            false,
+        )
+        .unwrap();
+        assert_eq!(same.rules, nfs4);
+        assert_eq!(same.dropped, 0);
+    }
+}
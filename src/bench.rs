@@ -0,0 +1,456 @@
+//! Built-in benchmarks (`sy bench`)
+//!
+//! Generates synthetic trees in a temporary directory, syncs each one to a
+//! local temporary destination (and to a remote, if given) with performance
+//! monitoring on, then syncs it again with nothing to do. The numbers come
+//! from the same `PerformanceMonitor` that `--perf` reports.
+
+use crate::cli::{BenchWorkload, DanglingMode, SymlinkMode};
+use crate::compress::CompressionDetection;
+use crate::error::{Result, SyncError};
+use crate::filter::FilterEngine;
+use crate::integrity::ChecksumType;
+use crate::path::SyncPath;
+use crate::ssh::config::ClientOptions;
+use crate::sync::{SyncEngine, SyncOptions};
+use crate::transport::router::TransportRouter;
+use crate::transport::{Transport, TransportOptions};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+/// Files per directory in the small-files workload
+const FILES_PER_DIR: u64 = 100;
+
+/// Files in the large-files and sparse workloads
+const BIG_FILES: u64 = 4;
+
+/// What one workload generated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Generated {
+    pub files: u64,
+    /// Apparent size of all files (sparse holes included)
+    pub bytes: u64,
+}
+
+/// Timings of one workload synced to one target
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub workload: &'static str,
+    /// `local`, or the remote the workload was synced to
+    pub target: String,
+    pub files: u64,
+    pub bytes: u64,
+    pub bytes_transferred: u64,
+    pub scan_secs: f64,
+    pub plan_secs: f64,
+    pub transfer_secs: f64,
+    pub total_secs: f64,
+    /// Second sync of the same tree, with nothing left to transfer
+    pub resync_secs: f64,
+}
+
+impl BenchResult {
+    /// Bytes per second over the whole first sync
+    pub fn bytes_per_sec(&self) -> f64 {
+        rate(self.bytes as f64, self.total_secs)
+    }
+
+    /// Files per second over the whole first sync
+    pub fn files_per_sec(&self) -> f64 {
+        rate(self.files as f64, self.total_secs)
+    }
+}
+
+fn rate(amount: f64, secs: f64) -> f64 {
+    if secs > 0.0 {
+        amount / secs
+    } else {
+        0.0
+    }
+}
+
+/// Name of a workload, as given to --workload
+pub fn workload_name(workload: BenchWorkload) -> &'static str {
+    match workload {
+        BenchWorkload::SmallFiles => "small-files",
+        BenchWorkload::LargeFiles => "large-files",
+        BenchWorkload::Sparse => "sparse",
+    }
+}
+
+/// `amount` multiplied by `scale`, but never below `min`
+fn scaled(amount: u64, scale: f64, min: u64) -> u64 {
+    ((amount as f64 * scale).round() as u64).max(min)
+}
+
+/// Write the files of `workload` under `root`
+///
+/// At scale 1.0: 10,000 files of 4 KiB in directories of 100 (small-files),
+/// 4 files of 128 MiB (large-files), or 4 files of 256 MiB apparent size with
+/// four 1 MiB data regions each (sparse). `scale` multiplies the file count
+/// of small-files and the file size of the others. Contents are
+/// pseudo-random, so compression can't flatter the results.
+pub fn generate(workload: BenchWorkload, root: &Path, scale: f64) -> io::Result<Generated> {
+    fs::create_dir_all(root)?;
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let mut generated = Generated::default();
+
+    match workload {
+        BenchWorkload::SmallFiles => {
+            let mut buf = vec![0u8; (4 * KIB) as usize];
+            for i in 0..scaled(10_000, scale, 1) {
+                let dir = root.join(format!("d{:03}", i / FILES_PER_DIR));
+                if i % FILES_PER_DIR == 0 {
+                    fs::create_dir_all(&dir)?;
+                }
+                rng.fill(&mut buf);
+                fs::write(dir.join(format!("f{:05}.dat", i)), &buf)?;
+                generated.files += 1;
+                generated.bytes += buf.len() as u64;
+            }
+        }
+        BenchWorkload::LargeFiles => {
+            let size = scaled(128 * MIB, scale, MIB);
+            let mut buf = vec![0u8; MIB as usize];
+            for i in 0..BIG_FILES {
+                let mut file = File::create(root.join(format!("large{}.dat", i)))?;
+                let mut written = 0;
+                while written < size {
+                    let len = (size - written).min(MIB) as usize;
+                    rng.fill(&mut buf[..len]);
+                    file.write_all(&buf[..len])?;
+                    written += len as u64;
+                }
+                generated.files += 1;
+                generated.bytes += size;
+            }
+        }
+        BenchWorkload::Sparse => {
+            let size = scaled(256 * MIB, scale, 4 * MIB);
+            let region = (size / 8).min(MIB);
+            let mut buf = vec![0u8; region as usize];
+            for i in 0..BIG_FILES {
+                let mut file = File::create(root.join(format!("sparse{}.img", i)))?;
+                file.set_len(size)?;
+                for quarter in 0..4 {
+                    rng.fill(&mut buf);
+                    file.seek(SeekFrom::Start(size / 4 * quarter))?;
+                    file.write_all(&buf)?;
+                }
+                generated.files += 1;
+                generated.bytes += size;
+            }
+        }
+    }
+    Ok(generated)
+}
+
+/// Fast deterministic filler; the data only has to be incompressible
+struct XorShift(u64);
+
+impl XorShift {
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            chunk.copy_from_slice(&self.0.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+/// `base` with `dir` appended to its path
+pub fn target_dir(base: &SyncPath, dir: &str) -> SyncPath {
+    let mut target = base.clone();
+    match &mut target {
+        SyncPath::Local(path) | SyncPath::Remote { path, .. } | SyncPath::Daemon { path, .. } => {
+            *path = path.join(dir)
+        }
+        SyncPath::S3 { key, .. } => {
+            *key = format!("{}/{}", key.trim_end_matches('/'), dir);
+        }
+    }
+    target
+}
+
+/// Sync the tree at `source` to `destination` twice and time both
+///
+/// The destination directory is removed afterwards, also when a sync fails.
+pub async fn measure(
+    workload: BenchWorkload,
+    source: &Path,
+    generated: Generated,
+    destination: &SyncPath,
+    label: &str,
+    parallel: usize,
+    remote_binary: Option<&str>,
+) -> Result<BenchResult> {
+    let transport = Arc::new(
+        TransportRouter::new(
+            &SyncPath::Local(source.to_path_buf()),
+            destination,
+            ChecksumType::Fast,
+            false,
+            parallel,
+            false,
+            DanglingMode::Preserve,
+            None,
+            CompressionDetection::Auto,
+            remote_binary,
+            TransportOptions::default(),
+            &ClientOptions::default(),
+            None,
+        )
+        .await?,
+    );
+    let dest = destination.path();
+    transport.create_dir_all(dest).await?;
+
+    // Keep the benchmark's state out of the user's state directory
+    let state_dir = tempfile::tempdir()?;
+    let result = sync_twice(&transport, source, dest, state_dir.path(), parallel).await;
+    if let Err(e) = transport.remove(dest, true).await {
+        tracing::warn!("Failed to remove benchmark data {}: {}", dest.display(), e);
+    }
+    let (metrics, resync) = result?;
+
+    Ok(BenchResult {
+        workload: workload_name(workload),
+        target: label.to_string(),
+        files: generated.files,
+        bytes: generated.bytes,
+        bytes_transferred: metrics.bytes_transferred,
+        scan_secs: metrics.scan_duration.as_secs_f64(),
+        plan_secs: metrics.plan_duration.as_secs_f64(),
+        transfer_secs: metrics.transfer_duration.as_secs_f64(),
+        total_secs: metrics.total_duration.as_secs_f64(),
+        resync_secs: resync.as_secs_f64(),
+    })
+}
+
+async fn sync_twice(
+    transport: &Arc<TransportRouter>,
+    source: &Path,
+    dest: &Path,
+    state_dir: &Path,
+    parallel: usize,
+) -> Result<(crate::perf::PerformanceMetrics, Duration)> {
+    let engine = engine(Arc::clone(transport), state_dir, parallel);
+    engine.sync(source, dest).await?;
+    let metrics = engine
+        .get_performance_metrics()
+        .ok_or_else(|| SyncError::Io(io::Error::other("Performance monitor not enabled")))?;
+
+    let engine = engine(Arc::clone(transport), state_dir, parallel);
+    let start = Instant::now();
+    engine.sync(source, dest).await?;
+    Ok((metrics, start.elapsed()))
+}
+
+/// A sync engine with default settings, quiet and with --perf on
+fn engine(
+    transport: Arc<TransportRouter>,
+    state_dir: &Path,
+    parallel: usize,
+) -> SyncEngine<Arc<TransportRouter>> {
+    SyncEngine::new(
+        transport,
+        false,               // dry_run
+        false,               // diff_mode
+        false,               // delete
+        50,                  // delete_threshold
+        None,                // trash
+        false,               // force_delete
+        true,                // quiet
+        parallel,            // max_concurrent
+        100,                 // max_errors
+        None,                // min_size
+        None,                // max_size
+        FilterEngine::new(), // filter_engine
+        None,                // bwlimit
+        false,               // resume
+        0,                   // checkpoint_files
+        0,                   // checkpoint_bytes
+        false,               // json
+        ChecksumType::Fast,  // --mode standard
+        false,               // verify_on_write
+        SymlinkMode::Preserve,
+        false,            // preserve_xattrs
+        false,            // preserve_hardlinks
+        false,            // preserve_acls
+        false,            // preserve_flags
+        false,            // ignore_times
+        false,            // size_only
+        false,            // checksum
+        false,            // verify_only
+        false,            // use_cache
+        false,            // clear_cache
+        false,            // checksum_db
+        false,            // clear_checksum_db
+        false,            // prune_checksum_db
+        state_dir.into(), // state_dir
+        true,             // perf
+        SyncOptions::default(),
+    )
+}
+
+/// Run `workloads` locally, and against `remote` if given
+///
+/// Each workload is generated once and synced to every target. Remote data
+/// goes to `sy-bench-<pid>/` under the remote path and is removed afterwards.
+pub async fn run(
+    workloads: &[BenchWorkload],
+    scale: f64,
+    remote: Option<&SyncPath>,
+    parallel: usize,
+    remote_binary: Option<&str>,
+) -> Result<Vec<BenchResult>> {
+    let temp = tempfile::tempdir()?;
+    let run_dir = format!("sy-bench-{}", std::process::id());
+    let mut results = Vec::new();
+
+    for &workload in workloads {
+        let name = workload_name(workload);
+        let source = temp.path().join("source").join(name);
+        tracing::info!("Generating {} workload in {}", name, source.display());
+        let generated = generate(workload, &source, scale)?;
+
+        let local = SyncPath::Local(temp.path().join("dest").join(name));
+        results.push(
+            measure(
+                workload, &source, generated, &local, "local", parallel, None,
+            )
+            .await?,
+        );
+
+        if let Some(remote) = remote {
+            let destination = target_dir(&target_dir(remote, &run_dir), name);
+            results.push(
+                measure(
+                    workload,
+                    &source,
+                    generated,
+                    &destination,
+                    &remote.to_string(),
+                    parallel,
+                    remote_binary,
+                )
+                .await?,
+            );
+        }
+
+        fs::remove_dir_all(&source)?;
+    }
+
+    // Only the emptied per-run directory is left on the remote
+    if let Some(remote) = remote {
+        let run_path = target_dir(remote, &run_dir);
+        let transport = TransportRouter::new(
+            &SyncPath::Local(temp.path().to_path_buf()),
+            &run_path,
+            ChecksumType::None,
+            false,
+            1,
+            false,
+            DanglingMode::Preserve,
+            None,
+            CompressionDetection::Auto,
+            remote_binary,
+            TransportOptions::default(),
+            &ClientOptions::default(),
+            None,
+        )
+        .await?;
+        if let Err(e) = transport.remove(run_path.path(), true).await {
+            tracing::warn!("Failed to remove {}: {}", run_path, e);
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_small_files() {
+        let temp = TempDir::new().unwrap();
+        let generated = generate(BenchWorkload::SmallFiles, temp.path(), 0.015).unwrap();
+
+        assert_eq!(generated.files, 150);
+        assert_eq!(generated.bytes, 150 * 4 * KIB);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 2);
+        assert_eq!(
+            fs::metadata(temp.path().join("d001/f00149.dat"))
+                .unwrap()
+                .len(),
+            4 * KIB
+        );
+    }
+
+    #[test]
+    fn test_generate_sparse() {
+        let temp = TempDir::new().unwrap();
+        let generated = generate(BenchWorkload::Sparse, temp.path(), 0.0).unwrap();
+
+        assert_eq!(generated.files, BIG_FILES);
+        assert_eq!(generated.bytes, BIG_FILES * 4 * MIB);
+        let data = fs::read(temp.path().join("sparse0.img")).unwrap();
+        assert_eq!(data.len() as u64, 4 * MIB);
+        // Holes between the data regions read as zeroes
+        assert!(data[(MIB / 2) as usize..MIB as usize]
+            .iter()
+            .all(|&b| b == 0));
+        assert!(data[..(MIB / 2) as usize].iter().any(|&b| b != 0));
+    }
+
+    #[test]
+    fn test_target_dir() {
+        let remote = SyncPath::parse("user@nas:/tmp");
+        assert_eq!(
+            target_dir(&remote, "sy-bench-1").to_string(),
+            "user@nas:/tmp/sy-bench-1"
+        );
+        let bucket = SyncPath::parse("s3://bucket/bench/");
+        assert_eq!(
+            target_dir(&bucket, "sparse").path(),
+            Path::new("bench/sparse")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_measure_local() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let generated = generate(BenchWorkload::SmallFiles, &source, 0.001).unwrap();
+        let dest = SyncPath::Local(temp.path().join("dest"));
+
+        let result = measure(
+            BenchWorkload::SmallFiles,
+            &source,
+            generated,
+            &dest,
+            "local",
+            2,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.workload, "small-files");
+        assert_eq!(result.files, 10);
+        assert_eq!(result.bytes_transferred, 10 * 4 * KIB);
+        assert!(result.total_secs > 0.0);
+        // Cleaned up
+        assert!(!temp.path().join("dest").exists());
+    }
+}
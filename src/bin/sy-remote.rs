@@ -1,11 +1,28 @@
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
-use sy::compress::{decompress, Compression};
-use sy::delta::{apply_delta, compute_checksums, Delta};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use sy::acl::AclMapPolicy;
+use sy::compress::{
+    compress_stream, compression_decision, decompress, Compression, CompressionDetection,
+    CompressionReason, DEFAULT_PROBE_SIZE,
+};
+use sy::config::Config;
+use sy::delta::pipeline::serve_delta_sync;
+use sy::delta::{apply_delta, compute_checksums, Delta, WeakHash};
+use sy::owner::NamedPerms;
+use sy::quic::{self, Listener, ServerOptions};
 use sy::sparse::DataRegion;
+use sy::ssh::restrict::{self, Builtin, Request, Restriction};
+use sy::sync::lock::{self, LockInfo};
+use sy::sync::output::MetadataError;
 use sy::sync::scanner::Scanner;
+use sy::sync::staging::{self, StagedMove};
+use sy::temp_file::write_atomically;
+use sy::wire::{
+    self, BatchFile, Capabilities, ScanOutput, WireFileEntry, WireFormat, WireScanError,
+};
 
 #[derive(Parser)]
 #[command(name = "sy-remote")]
@@ -17,10 +34,18 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Scan a directory and output file list as JSON
+    /// Print supported wire formats and delta checksums as JSON (for negotiation)
+    Capabilities,
+    /// Scan a directory and output the file list
     Scan {
         /// Directory to scan
         path: PathBuf,
+        /// Maximum recursion depth (unlimited if omitted)
+        #[arg(long)]
+        max_depth: Option<usize>,
+        /// Output encoding
+        #[arg(long, value_enum, default_value_t)]
+        format: WireFormat,
     },
     /// Compute block checksums for a file
     Checksums {
@@ -37,6 +62,18 @@ enum Commands {
         /// Output file path
         output_file: PathBuf,
     },
+    /// Pipelined delta sync: stream block checksums of a file to stdout, then
+    /// apply the delta read from stdin and replace the file
+    DeltaSync {
+        /// File to update
+        path: PathBuf,
+        /// Frame encoding in both directions
+        #[arg(long, value_enum, default_value_t)]
+        format: WireFormat,
+        /// Weak rolling checksum for the block checksums
+        #[arg(long, value_enum, default_value_t)]
+        weak_hash: WeakHash,
+    },
     /// Receive a file (potentially compressed) from stdin and write to disk
     ReceiveFile {
         /// Output file path
@@ -44,7 +81,65 @@ enum Commands {
         /// Optional modification time (seconds since epoch)
         #[arg(long)]
         mtime: Option<u64>,
+        /// Write directly to the output path instead of a temp file renamed into place
+        #[arg(long)]
+        inplace: bool,
+    },
+    /// Receive many small files at once (reads a payload of path, mode, mtime
+    /// and contents records from stdin) and print a JSON array with an error
+    /// message per file that failed, null where it was written
+    ReceiveBatch {
+        /// Payload encoding
+        #[arg(long, value_enum, default_value_t)]
+        format: WireFormat,
+        /// Write directly to the output paths instead of temp files renamed into place
+        #[arg(long)]
+        inplace: bool,
+    },
+    /// Send a file to stdout (compressed if beneficial), preceded by a JSON header line
+    SendFile {
+        /// File to send
+        path: PathBuf,
+        /// Compression detection mode chosen by the client
+        #[arg(long, value_enum, default_value = "auto")]
+        compression_detection: CompressionDetection,
+        /// Size of each content probe for auto detection, in bytes
+        #[arg(long, default_value_t = DEFAULT_PROBE_SIZE)]
+        compression_probe_size: usize,
+        /// Send the file from this byte on, to continue an interrupted download
+        #[arg(long, default_value_t = 0)]
+        offset: u64,
+        /// Compress the payload as it is sent, leaving its size out of the header
+        #[arg(long)]
+        stream: bool,
+    },
+    /// Apply xattrs, ACLs and access/creation times to a file (reads metadata JSON from stdin)
+    SetMetadata {
+        /// File to apply metadata to
+        path: PathBuf,
+    },
+    /// Take the sync lock on a directory (reads owner JSON from stdin)
+    Lock {
+        /// Destination directory to lock
+        path: PathBuf,
+    },
+    /// Release the sync lock if still held by the owner (reads owner JSON from stdin)
+    Unlock {
+        /// Destination directory to unlock
+        path: PathBuf,
+    },
+    /// Check that files can be created in a destination before syncing into it
+    CheckWritable {
+        /// Destination directory (or its nearest existing ancestor) to probe
+        path: PathBuf,
     },
+    /// Stat many paths at once (reads a JSON array of paths from stdin)
+    StatBatch,
+    /// Create directories and their parents (reads a JSON array of paths from stdin)
+    Mkdirs,
+    /// Move files staged by --delay-updates into place (reads a JSON array of
+    /// moves from stdin) and print how many were moved
+    CommitStaged,
     /// Receive a sparse file with specified data regions
     ReceiveSparseFile {
         /// Output file path
@@ -58,89 +153,492 @@ enum Commands {
         /// Optional modification time (seconds since epoch)
         #[arg(long)]
         mtime: Option<u64>,
+        /// Write directly to the output path instead of a temp file renamed into place
+        #[arg(long)]
+        inplace: bool,
+    },
+    /// Serve SSH_ORIGINAL_COMMAND confined to one directory tree, for
+    /// `command="sy-remote restrict --root DIR"` in authorized_keys
+    Restrict {
+        /// Directory tree the SSH key may access
+        #[arg(long)]
+        root: PathBuf,
+        /// Refuse everything that writes, so the tree can only be synced from
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Serve the [modules] of sy's config file over QUIC, for
+    /// `sy --quic host::module/path` (experimental). Clients prove they know
+    /// the pre-shared key in SY_QUIC_PSK, if it is set
+    ServeQuic {
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0")]
+        address: IpAddr,
+        /// UDP port to listen on
+        #[arg(long, default_value_t = quic::DEFAULT_PORT)]
+        port: u16,
+        /// PEM certificate chain to present; without it, a self-signed
+        /// certificate is used and SY_QUIC_PSK is required
+        #[arg(long, requires = "key")]
+        cert: Option<PathBuf>,
+        /// PEM private key of --cert
+        #[arg(long, requires = "cert")]
+        key: Option<PathBuf>,
     },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ScanOutput {
-    entries: Vec<FileEntryJson>,
+/// Metadata read by set-metadata from stdin
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MetadataInput {
+    #[serde(default)]
+    xattrs: Option<Vec<(String, String)>>, // (key, base64-encoded value)
+    #[serde(default)]
+    acls: Option<String>, // ACL text format (one per line)
+    #[serde(default)]
+    acl_map: Option<AclMapPolicy>, // Handling of ACLs from a different ACL model
+    #[serde(default)]
+    atime: Option<i64>, // Access time (seconds since epoch)
+    #[serde(default)]
+    crtime: Option<i64>, // Creation time (seconds since epoch), where settable
+    #[serde(default)]
+    perms: Option<NamedPerms>, // Mode, owner and group, owners matched by name
 }
 
+/// Result printed by set-metadata
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MetadataOutput {
+    xattrs_applied: usize,
+    acl_entries_applied: usize,
+    /// Privileged xattrs (e.g. security.capability) skipped for lack of privileges
+    #[serde(default)]
+    xattrs_skipped_privileged: Vec<String>,
+    /// Xattrs and ACL entries that couldn't be applied, and why
+    #[serde(default)]
+    metadata_failed: Vec<MetadataError>,
+}
+
+/// Apply xattrs, ACLs, times and permissions to `path`
+fn set_metadata(path: &Path, input: MetadataInput) -> anyhow::Result<MetadataOutput> {
+    if std::fs::symlink_metadata(path).is_err() {
+        anyhow::bail!("File not found: {}", path.display());
+    }
+
+    let mut output = MetadataOutput::default();
+    if let Some(xattrs) = input.xattrs {
+        use base64::{engine::general_purpose, Engine as _};
+        let mut decoded = std::collections::HashMap::new();
+        for (key, encoded) in xattrs {
+            decoded.insert(key, general_purpose::STANDARD.decode(encoded)?);
+        }
+        let result = sy::fs_util::apply_xattrs(path, &decoded);
+        output.xattrs_applied = result.applied;
+        output.xattrs_skipped_privileged = result.skipped_privileged;
+        output.metadata_failed.extend(result.failed);
+    }
+
+    let acl_map = input.acl_map.unwrap_or_default();
+    if let Some(acls) = input.acls {
+        let result = sy::fs_util::apply_acls(path, acls.as_bytes(), acl_map)?;
+        output.acl_entries_applied = result.applied;
+        output.metadata_failed.extend(result.failed);
+    }
+
+    let from_secs = |secs: i64| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs as u64);
+    if input.atime.is_some() || input.crtime.is_some() {
+        sy::fs_util::apply_times(
+            path,
+            input.atime.map(from_secs),
+            input.crtime.map(from_secs),
+        )?;
+    }
+
+    // After the ACLs: the mode's group bits are the ACL mask
+    if let Some(perms) = input.perms {
+        sy::owner::apply(path, &perms.resolve())?;
+    }
+
+    Ok(output)
+}
+
+/// Header line written by send-file before the file payload
 #[derive(Debug, Serialize, Deserialize)]
-struct FileEntryJson {
-    path: String,
+struct SendFileHeader {
+    /// Compression applied to the payload ("none", "lz4", "zstd")
+    compression: String,
+    /// Uncompressed file size in bytes
+    size: u64,
+    /// Payload size in bytes (as sent over the wire), None for a payload
+    /// compressed as it is sent
+    payload_size: Option<u64>,
+    /// Modification time (seconds since epoch)
+    mtime: Option<u64>,
+    /// Why this compression was chosen
+    reason: Option<CompressionReason>,
+    /// Compressed/original size of the sampled content (auto detection only)
+    sample_ratio: Option<f64>,
+}
+
+/// Write a file to `out` as a JSON header line followed by the (possibly compressed) payload
+///
+/// Uses the same smart compression detection as uploads (in the client's
+/// `detection` mode), so compressible files pulled over slow links shrink on
+/// the wire. Incompressible files are streamed as-is. The payload holds the
+/// file from `offset` on; with `stream` it is zstd-compressed as it is
+/// written, otherwise it is compressed to a temp file first so the header
+/// can give its size (for clients from before streamed downloads). The file
+/// is never held in memory.
+fn send_file<W: Write>(
+    path: &Path,
+    detection: CompressionDetection,
+    probe_size: usize,
+    offset: u64,
+    stream: bool,
+    out: &mut W,
+) -> anyhow::Result<SendFileHeader> {
+    let mut file = std::fs::File::open(path)?;
+    let metadata = file.metadata()?;
+    let size = metadata.len();
+    if offset > size {
+        anyhow::bail!(
+            "Offset {} is past the end of {} ({} bytes)",
+            offset,
+            path.display(),
+            size
+        );
+    }
+    file.seek(SeekFrom::Start(offset))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+    let decision = compression_decision(
+        Some(path),
+        filename,
+        size,
+        false, // Sending over SSH is always remote
+        detection,
+        probe_size,
+        false,
+    );
+    // Compressed payloads are always zstd, which decodes as a stream
+    let compression = match decision.compression {
+        Compression::None => Compression::None,
+        Compression::Lz4 | Compression::Zstd => Compression::Zstd,
+    };
+    let mut header = SendFileHeader {
+        compression: compression.as_str().to_string(),
+        size,
+        payload_size: None,
+        mtime,
+        reason: Some(decision.reason),
+        sample_ratio: decision.sample_ratio,
+    };
+
+    let mut file = file.take(size - offset);
+    match compression {
+        Compression::None => {
+            header.payload_size = Some(size - offset);
+            writeln!(out, "{}", serde_json::to_string(&header)?)?;
+            std::io::copy(&mut file, out)?;
+        }
+        _ if stream => {
+            writeln!(out, "{}", serde_json::to_string(&header)?)?;
+            compress_stream(&mut file, &mut *out)?;
+        }
+        _ => {
+            let mut spool = tempfile::tempfile()?;
+            compress_stream(&mut file, &mut spool)?;
+            header.payload_size = Some(spool.stream_position()?);
+            spool.rewind()?;
+            writeln!(out, "{}", serde_json::to_string(&header)?)?;
+            std::io::copy(&mut spool, out)?;
+        }
+    }
+    out.flush()?;
+    Ok(header)
+}
+
+/// Size, mtime and type of one path, printed by stat-batch
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct StatEntry {
     size: u64,
     mtime: i64,
     is_dir: bool,
-    // Extended metadata for full preservation
-    is_symlink: bool,
-    symlink_target: Option<String>,
-    is_sparse: bool,
-    allocated_size: u64,
-    #[serde(default)]
-    xattrs: Option<Vec<(String, String)>>, // (key, base64-encoded value)
-    inode: Option<u64>,
-    nlink: u64,
-    #[serde(default)]
-    acls: Option<String>, // ACL text format (one per line)
+}
+
+/// Stat each path, with None for paths that don't exist or can't be read
+fn stat_batch(paths: &[PathBuf]) -> Vec<Option<StatEntry>> {
+    paths.iter().map(|path| stat_path(path)).collect()
+}
+
+/// Create each directory and its parents, in order
+///
+/// Under a restriction every path is checked first, so a refused batch
+/// creates nothing.
+fn mkdirs(paths: &[PathBuf], restriction: Option<&Restriction>) -> anyhow::Result<()> {
+    if let Some(restriction) = restriction {
+        for path in paths {
+            restriction.check(path)?;
+        }
+    }
+    for path in paths {
+        std::fs::create_dir_all(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Move staged files into place, checking every path first under a
+/// restriction
+fn commit_staged(moves: &[StagedMove], restriction: Option<&Restriction>) -> anyhow::Result<usize> {
+    if let Some(restriction) = restriction {
+        for m in moves {
+            restriction.check(&m.staged)?;
+            restriction.check(&m.dest)?;
+            if let Some(backup) = &m.backup {
+                restriction.check(backup)?;
+            }
+        }
+    }
+    Ok(staging::commit_moves(moves)?)
+}
+
+/// Write each file of a receive-batch payload, through a temp file unless
+/// `inplace` (--inplace)
+///
+/// Returns an error message per file that failed (None where it was
+/// written), so one bad file doesn't fail the rest of the batch.
+fn receive_batch(
+    files: Vec<BatchFile>,
+    inplace: bool,
+    restriction: Option<&Restriction>,
+) -> Vec<Option<String>> {
+    files
+        .into_iter()
+        .map(|file| {
+            write_batch_file(&file, inplace, restriction)
+                .err()
+                .map(|e| format!("{}: {:#}", file.path, e))
+        })
+        .collect()
+}
+
+fn write_batch_file(
+    file: &BatchFile,
+    inplace: bool,
+    restriction: Option<&Restriction>,
+) -> anyhow::Result<()> {
+    let path = Path::new(&file.path);
+    if let Some(restriction) = restriction {
+        restriction.check(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    write_atomically(path, inplace, |write_path| -> std::io::Result<()> {
+        std::fs::write(write_path, &file.data)?;
+        #[cfg(unix)]
+        if let Some(mode) = file.mode {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(write_path, std::fs::Permissions::from_mode(mode))?;
+        }
+        filetime::set_file_mtime(
+            write_path,
+            filetime::FileTime::from_unix_time(file.mtime, 0),
+        )
+    })?;
+    Ok(())
+}
+
+fn stat_path(path: &Path) -> Option<StatEntry> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    Some(StatEntry {
+        size: metadata.len(),
+        mtime,
+        is_dir: metadata.is_dir(),
+    })
+}
+
+/// Read the lock owner sent by the client on stdin
+fn read_lock_owner() -> anyhow::Result<LockInfo> {
+    let mut stdin_data = String::new();
+    std::io::stdin().read_to_string(&mut stdin_data)?;
+    Ok(serde_json::from_str(&stdin_data)?)
+}
+
+/// Check a sy-remote subcommand against the restriction before running it
+fn check_restricted(command: &Commands, restriction: &Restriction) -> anyhow::Result<()> {
+    match command {
+        Commands::Capabilities | Commands::StatBatch => {}
+        Commands::Scan { path, .. }
+        | Commands::Checksums { path, .. }
+        | Commands::SendFile { path, .. } => restriction.check(path)?,
+        Commands::ApplyDelta {
+            base_file,
+            output_file,
+        } => {
+            restriction.check_write("apply-delta")?;
+            restriction.check(base_file)?;
+            restriction.check(output_file)?;
+        }
+        Commands::DeltaSync { path, .. } => {
+            restriction.check_write("delta-sync")?;
+            restriction.check(path)?;
+        }
+        Commands::ReceiveFile { output_path, .. }
+        | Commands::ReceiveSparseFile { output_path, .. } => {
+            restriction.check_write("receive-file")?;
+            restriction.check(output_path)?;
+        }
+        Commands::SetMetadata { path } => {
+            restriction.check_write("set-metadata")?;
+            restriction.check(path)?;
+        }
+        Commands::Lock { path } | Commands::Unlock { path } => {
+            restriction.check_write("lock")?;
+            restriction.check(path)?;
+        }
+        Commands::CheckWritable { path } => {
+            restriction.check_write("check-writable")?;
+            restriction.check(path)?;
+        }
+        // The paths come on stdin and are checked as they are read
+        Commands::Mkdirs => restriction.check_write("mkdirs")?,
+        Commands::CommitStaged => restriction.check_write("commit-staged")?,
+        Commands::ReceiveBatch { .. } => restriction.check_write("receive-batch")?,
+        Commands::Restrict { .. } => anyhow::bail!("restrict cannot be nested"),
+        Commands::ServeQuic { .. } => anyhow::bail!("serve-quic cannot run restricted"),
+    }
+    Ok(())
+}
+
+/// Run SSH_ORIGINAL_COMMAND under a restriction, the way the shell would
+///
+/// Every command in the request is checked before any of them runs, so a
+/// refused request changes nothing.
+fn serve_restricted(restriction: &Restriction) -> anyhow::Result<()> {
+    enum Step {
+        SyRemote(Commands),
+        Builtin(Builtin),
+    }
+
+    let line = std::env::var("SSH_ORIGINAL_COMMAND").map_err(|_| {
+        anyhow::anyhow!(
+            "SSH_ORIGINAL_COMMAND is not set; use restrict as the forced command of an SSH key"
+        )
+    })?;
+
+    let mut steps = Vec::new();
+    for (operator, request) in restrict::parse(&line, restriction.home())? {
+        let step = match request {
+            Request::SyRemote(args) => {
+                let command = Cli::try_parse_from(args)?.command;
+                check_restricted(&command, restriction)?;
+                Step::SyRemote(command)
+            }
+            Request::Builtin(builtin) => {
+                restriction.check_builtin(&builtin)?;
+                Step::Builtin(builtin)
+            }
+        };
+        steps.push((operator, step));
+    }
+
+    let mut succeeded = true;
+    for (operator, step) in steps {
+        if !operator.runs_after(succeeded) {
+            continue;
+        }
+        let status = match step {
+            Step::SyRemote(command) => run(command, Some(restriction)).map(|()| true),
+            Step::Builtin(builtin) => builtin.run().map_err(anyhow::Error::from),
+        };
+        succeeded = status.unwrap_or_else(|e| {
+            eprintln!("sy-remote: {:#}", e);
+            false
+        });
+    }
+
+    if !succeeded {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Serve the config file's modules over QUIC until stopped
+fn serve_quic(address: SocketAddr, options: &ServerOptions) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    if config.modules.is_empty() {
+        anyhow::bail!(
+            "No modules to serve; add a [modules.NAME] table with a path to {}",
+            Config::config_path()?.display()
+        );
+    }
+
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let listener = Listener::bind(address, options)?;
+        let mut names: Vec<&str> = config.modules.keys().map(String::as_str).collect();
+        names.sort();
+        println!(
+            "Serving {} over QUIC on {}",
+            names.join(", "),
+            listener.local_addr()?
+        );
+        listener.serve(&config.modules).await?;
+        Ok::<_, anyhow::Error>(())
+    })
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    run(cli.command, None)
+}
 
-    match cli.command {
-        Commands::Scan { path } => {
-            let scanner = Scanner::new(&path);
-            let entries = scanner.scan()?;
-
-            let json_entries: Vec<FileEntryJson> = entries
-                .into_iter()
-                .map(|e| {
-                    let mtime = e
-                        .modified
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs() as i64;
-
-                    // Encode xattrs to base64 for transport
-                    let xattrs = e.xattrs.map(|xattrs_map| {
-                        use base64::{engine::general_purpose, Engine as _};
-                        xattrs_map
-                            .into_iter()
-                            .map(|(key, value)| {
-                                let encoded = general_purpose::STANDARD.encode(&value);
-                                (key, encoded)
-                            })
-                            .collect()
-                    });
-
-                    // Convert ACLs from bytes to string
-                    let acls = e
-                        .acls
-                        .and_then(|acl_bytes| String::from_utf8(acl_bytes).ok());
-
-                    FileEntryJson {
-                        path: e.path.to_string_lossy().to_string(),
-                        size: e.size,
-                        mtime,
-                        is_dir: e.is_dir,
-                        is_symlink: e.is_symlink,
-                        symlink_target: e.symlink_target.map(|p| p.to_string_lossy().to_string()),
-                        is_sparse: e.is_sparse,
-                        allocated_size: e.allocated_size,
-                        xattrs,
-                        inode: e.inode,
-                        nlink: e.nlink,
-                        acls,
-                    }
-                })
-                .collect();
-
-            let output = ScanOutput {
-                entries: json_entries,
+/// Run one subcommand, confined to `restriction` when serving a restricted key
+fn run(command: Commands, restriction: Option<&Restriction>) -> anyhow::Result<()> {
+    match command {
+        Commands::Capabilities => {
+            let capabilities = Capabilities {
+                restricted: restriction.is_some(),
+                clock_ms: Some(chrono::Utc::now().timestamp_millis()),
+                ..Capabilities::current()
             };
+            println!("{}", serde_json::to_string(&capabilities)?);
+        }
+        Commands::Scan {
+            path,
+            max_depth,
+            format,
+        } => {
+            let scanner = Scanner::new(&path).max_depth(max_depth);
+            let (entries, errors) = scanner.scan_with_errors()?;
 
-            println!("{}", serde_json::to_string(&output)?);
+            let output = ScanOutput::new(
+                entries.into_iter().map(WireFileEntry::from).collect(),
+                errors
+                    .into_iter()
+                    .map(|e| WireScanError {
+                        path: e.path.to_string_lossy().to_string(),
+                        error: e.error,
+                        special: e.special,
+                    })
+                    .collect(),
+            );
+
+            let stdout = std::io::stdout();
+            let mut out = stdout.lock();
+            out.write_all(&wire::encode(format, &output)?)?;
+            out.flush()?;
         }
         Commands::Checksums { path, block_size } => {
             let checksums = compute_checksums(&path, block_size)?;
@@ -178,7 +676,29 @@ fn main() -> anyhow::Result<()> {
                 stats.operations_count, stats.literal_bytes
             );
         }
-        Commands::ReceiveFile { output_path, mtime } => {
+        Commands::DeltaSync {
+            path,
+            format,
+            weak_hash,
+        } => {
+            let temp_path = PathBuf::from(format!("{}.sy-tmp", path.display()));
+            let stdout = std::io::stdout();
+            let mut out = std::io::BufWriter::new(stdout.lock());
+
+            serve_delta_sync(
+                format,
+                weak_hash,
+                &path,
+                &temp_path,
+                std::io::stdin().lock(),
+                &mut out,
+            )?;
+        }
+        Commands::ReceiveFile {
+            output_path,
+            mtime,
+            inplace,
+        } => {
             // Read file data from stdin (may be compressed)
             let mut stdin_data = Vec::new();
             std::io::stdin().read_to_end(&mut stdin_data)?;
@@ -203,9 +723,11 @@ fn main() -> anyhow::Result<()> {
             }
 
             // Write file
-            let mut output_file = std::fs::File::create(&output_path)?;
-            output_file.write_all(&file_data)?;
-            output_file.flush()?;
+            write_atomically(&output_path, inplace, |path| {
+                let mut output_file = std::fs::File::create(path)?;
+                output_file.write_all(&file_data)?;
+                output_file.flush()
+            })?;
 
             // Set mtime if provided
             if let Some(mtime_secs) = mtime {
@@ -220,11 +742,88 @@ fn main() -> anyhow::Result<()> {
             // Report success with bytes written
             println!("{{\"bytes_written\": {}}}", file_data.len());
         }
+        Commands::SendFile {
+            path,
+            compression_detection,
+            compression_probe_size,
+            offset,
+            stream,
+        } => {
+            let stdout = std::io::stdout();
+            let mut out = stdout.lock();
+            send_file(
+                &path,
+                compression_detection,
+                compression_probe_size,
+                offset,
+                stream,
+                &mut out,
+            )?;
+        }
+        Commands::SetMetadata { path } => {
+            let mut stdin_data = String::new();
+            std::io::stdin().read_to_string(&mut stdin_data)?;
+            let input: MetadataInput = serde_json::from_str(&stdin_data)?;
+
+            let output = set_metadata(&path, input)?;
+            println!("{}", serde_json::to_string(&output)?);
+        }
+        Commands::Lock { path } => {
+            let owner = read_lock_owner()?;
+            let holder = lock::try_lock(&path, &owner)?;
+            println!("{}", serde_json::json!({ "holder": holder }));
+        }
+        Commands::Unlock { path } => {
+            let owner = read_lock_owner()?;
+            let released = lock::unlock(&path, &owner)?;
+            println!("{{\"released\": {}}}", released);
+        }
+        Commands::CheckWritable { path } => {
+            let unwritable = sy::fs_util::unwritable_reason(&path)
+                .map(|(path, reason)| serde_json::json!({ "path": path, "reason": reason }));
+            println!("{}", serde_json::json!({ "unwritable": unwritable }));
+        }
+        Commands::StatBatch => {
+            let mut stdin_data = String::new();
+            std::io::stdin().read_to_string(&mut stdin_data)?;
+            let paths: Vec<PathBuf> = serde_json::from_str(&stdin_data)?;
+
+            let stats = match restriction {
+                // Paths outside the restricted root read as missing
+                Some(restriction) => paths
+                    .iter()
+                    .map(|path| restriction.check(path).ok().and_then(|()| stat_path(path)))
+                    .collect(),
+                None => stat_batch(&paths),
+            };
+            println!("{}", serde_json::to_string(&stats)?);
+        }
+        Commands::Mkdirs => {
+            let mut stdin_data = String::new();
+            std::io::stdin().read_to_string(&mut stdin_data)?;
+            let paths: Vec<PathBuf> = serde_json::from_str(&stdin_data)?;
+            mkdirs(&paths, restriction)?;
+        }
+        Commands::CommitStaged => {
+            let mut stdin_data = String::new();
+            std::io::stdin().read_to_string(&mut stdin_data)?;
+            let moves: Vec<StagedMove> = serde_json::from_str(&stdin_data)?;
+            println!("{}", commit_staged(&moves, restriction)?);
+        }
+        Commands::ReceiveBatch { format, inplace } => {
+            let mut stdin_data = Vec::new();
+            std::io::stdin().read_to_end(&mut stdin_data)?;
+            let files: Vec<BatchFile> = wire::decode(format, &stdin_data)?;
+
+            let errors = receive_batch(files, inplace, restriction);
+            println!("{}", serde_json::to_string(&errors)?);
+        }
         Commands::ReceiveSparseFile {
             output_path,
             total_size,
             regions,
             mtime,
+            inplace,
         } => {
             // Parse data regions from JSON
             let data_regions: Vec<DataRegion> = serde_json::from_str(&regions)?;
@@ -234,29 +833,32 @@ fn main() -> anyhow::Result<()> {
                 std::fs::create_dir_all(parent)?;
             }
 
-            // Create file and set its size (creates sparse file with holes)
-            let mut output_file = std::fs::File::create(&output_path)?;
-            output_file.set_len(total_size)?;
+            let total_bytes_written = write_atomically(&output_path, inplace, |path| {
+                // Create file and set its size (creates sparse file with holes)
+                let mut output_file = std::fs::File::create(path)?;
+                output_file.set_len(total_size)?;
 
-            // Read and write each data region from stdin
-            let mut stdin = std::io::stdin();
-            let mut total_bytes_written = 0u64;
+                // Read and write each data region from stdin
+                let mut stdin = std::io::stdin();
+                let mut total_bytes_written = 0u64;
 
-            for region in &data_regions {
-                // Seek to the region's offset
-                output_file.seek(SeekFrom::Start(region.offset))?;
+                for region in &data_regions {
+                    // Seek to the region's offset
+                    output_file.seek(SeekFrom::Start(region.offset))?;
 
-                // Read exactly `region.length` bytes from stdin
-                let mut buffer = vec![0u8; region.length as usize];
-                stdin.read_exact(&mut buffer)?;
+                    // Read exactly `region.length` bytes from stdin
+                    let mut buffer = vec![0u8; region.length as usize];
+                    stdin.read_exact(&mut buffer)?;
 
-                // Write to file
-                output_file.write_all(&buffer)?;
-                total_bytes_written += region.length;
-            }
+                    // Write to file
+                    output_file.write_all(&buffer)?;
+                    total_bytes_written += region.length;
+                }
 
-            output_file.flush()?;
-            output_file.sync_all()?;
+                output_file.flush()?;
+                output_file.sync_all()?;
+                Ok::<_, std::io::Error>(total_bytes_written)
+            })?;
 
             // Set mtime if provided
             if let Some(mtime_secs) = mtime {
@@ -276,6 +878,22 @@ fn main() -> anyhow::Result<()> {
                 data_regions.len()
             );
         }
+        Commands::Restrict { root, read_only } => {
+            serve_restricted(&Restriction::new(&root, read_only)?)?;
+        }
+        Commands::ServeQuic {
+            address,
+            port,
+            cert,
+            key,
+        } => {
+            let options = ServerOptions {
+                cert,
+                key,
+                psk: std::env::var(quic::PSK_ENV).ok(),
+            };
+            serve_quic(SocketAddr::new(address, port), &options)?;
+        }
     }
 
     Ok(())
@@ -432,4 +1050,378 @@ mod tests {
         assert_eq!(regions[1].offset, deserialized[1].offset);
         assert_eq!(regions[1].length, deserialized[1].length);
     }
+
+    #[test]
+    fn test_send_file_compresses_compressible_content() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("log.txt");
+        // 2MB of repetitive text (above the 1MB compression threshold)
+        let content = b"sy remote send-file test line\n".repeat(70_000);
+        std::fs::write(&path, &content).unwrap();
+
+        let mut output = Vec::new();
+        let header = send_file(
+            &path,
+            CompressionDetection::Auto,
+            DEFAULT_PROBE_SIZE,
+            0,
+            false,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(header.compression, "zstd");
+        assert_eq!(header.size, content.len() as u64);
+        assert!(header.payload_size.unwrap() < header.size);
+
+        // Output is a header line followed by the compressed payload
+        let newline = output.iter().position(|&b| b == b'\n').unwrap();
+        let parsed: SendFileHeader = serde_json::from_slice(&output[..newline]).unwrap();
+        assert_eq!(parsed.payload_size, header.payload_size);
+
+        let payload = &output[newline + 1..];
+        assert_eq!(Some(payload.len() as u64), header.payload_size);
+        let decompressed = decompress(payload, Compression::Zstd).unwrap();
+        assert_eq!(decompressed, content);
+    }
+
+    #[test]
+    fn test_send_file_streamed_from_offset() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("log.txt");
+        let content = b"sy remote send-file test line\n".repeat(70_000);
+        std::fs::write(&path, &content).unwrap();
+
+        let mut output = Vec::new();
+        let header = send_file(
+            &path,
+            CompressionDetection::Auto,
+            DEFAULT_PROBE_SIZE,
+            1000,
+            true,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(header.compression, "zstd");
+        // The size is the whole file's; the streamed payload's isn't known up front
+        assert_eq!(header.size, content.len() as u64);
+        assert_eq!(header.payload_size, None);
+
+        let newline = output.iter().position(|&b| b == b'\n').unwrap();
+        let decompressed = decompress(&output[newline + 1..], Compression::Zstd).unwrap();
+        assert_eq!(decompressed, &content[1000..]);
+
+        let mut output = Vec::new();
+        let header = send_file(
+            &path,
+            CompressionDetection::Never,
+            DEFAULT_PROBE_SIZE,
+            1000,
+            true,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(header.payload_size, Some(content.len() as u64 - 1000));
+        assert!(output.ends_with(&content[1000..]));
+
+        assert!(send_file(
+            &path,
+            CompressionDetection::Auto,
+            DEFAULT_PROBE_SIZE,
+            content.len() as u64 + 1,
+            true,
+            &mut Vec::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_send_file_small_file_uncompressed() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("small.txt");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let mut output = Vec::new();
+        let header = send_file(
+            &path,
+            CompressionDetection::Auto,
+            DEFAULT_PROBE_SIZE,
+            0,
+            false,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(header.compression, "none");
+        assert_eq!(header.size, 5);
+        assert_eq!(header.payload_size, Some(5));
+        assert!(header.mtime.is_some());
+        assert!(output.ends_with(b"\nhello"));
+    }
+
+    #[test]
+    fn test_send_file_detection_override() {
+        let temp = TempDir::new().unwrap();
+        let small = temp.path().join("small.txt");
+        std::fs::write(&small, b"hello hello hello hello").unwrap();
+        let large = temp.path().join("log.txt");
+        std::fs::write(&large, b"sy remote send-file test line\n".repeat(70_000)).unwrap();
+
+        let mut output = Vec::new();
+        let header = send_file(
+            &small,
+            CompressionDetection::Always,
+            DEFAULT_PROBE_SIZE,
+            0,
+            false,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(header.compression, "zstd");
+        assert_eq!(header.reason, Some(CompressionReason::Forced));
+
+        let mut output = Vec::new();
+        let header = send_file(
+            &large,
+            CompressionDetection::Never,
+            DEFAULT_PROBE_SIZE,
+            0,
+            false,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(header.compression, "none");
+        assert_eq!(header.reason, Some(CompressionReason::Disabled));
+        assert_eq!(header.payload_size, Some(header.size));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_set_metadata_xattrs() {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("file.txt");
+        std::fs::write(&path, b"data").unwrap();
+
+        let input = MetadataInput {
+            xattrs: Some(vec![(
+                "user.sy_test".to_string(),
+                general_purpose::STANDARD.encode(b"remote value"),
+            )]),
+            acls: None,
+            acl_map: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+
+        let output = set_metadata(&path, input).unwrap();
+        assert_eq!(output.acl_entries_applied, 0);
+        assert!(output.xattrs_skipped_privileged.is_empty());
+
+        // Filesystem may not support user xattrs
+        if output.xattrs_applied == 1 {
+            let value = xattr::get(&path, "user.sy_test").unwrap().unwrap();
+            assert_eq!(value, b"remote value");
+        }
+    }
+
+    #[test]
+    fn test_set_metadata_times() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("file.txt");
+        std::fs::write(&path, b"data").unwrap();
+
+        let input = MetadataInput {
+            atime: Some(1_000_000_000),
+            ..Default::default()
+        };
+        set_metadata(&path, input).unwrap();
+
+        let accessed = std::fs::metadata(&path).unwrap().accessed().unwrap();
+        assert_eq!(
+            accessed,
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_set_metadata_perms() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("file.txt");
+        std::fs::write(&path, b"data").unwrap();
+        let gid = std::fs::metadata(&path).unwrap().gid();
+
+        // A group name unknown here keeps the id it came with
+        let input = MetadataInput {
+            perms: Some(NamedPerms {
+                mode: Some(0o600),
+                gid: Some(gid),
+                group: Some("sy-no-such-group".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        set_metadata(&path, input).unwrap();
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.mode() & 0o7777, 0o600);
+        assert_eq!(metadata.gid(), gid);
+    }
+
+    #[test]
+    fn test_set_metadata_missing_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("missing.txt");
+
+        assert!(set_metadata(&path, MetadataInput::default()).is_err());
+    }
+
+    #[test]
+    fn test_stat_batch() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        std::fs::write(&file, b"hello").unwrap();
+
+        let stats = stat_batch(&[
+            file,
+            temp.path().to_path_buf(),
+            temp.path().join("missing.txt"),
+        ]);
+        assert_eq!(stats.len(), 3);
+
+        let file_stat = stats[0].as_ref().unwrap();
+        assert_eq!(file_stat.size, 5);
+        assert!(!file_stat.is_dir);
+        assert!(file_stat.mtime > 0);
+        assert!(stats[1].as_ref().unwrap().is_dir);
+        assert!(stats[2].is_none());
+    }
+
+    #[test]
+    fn test_mkdirs() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+
+        mkdirs(&[root.join("a"), root.join("a/b/c"), root.join("a")], None).unwrap();
+        assert!(root.join("a/b/c").is_dir());
+
+        std::fs::write(root.join("file"), b"").unwrap();
+        assert!(mkdirs(&[root.join("file/sub")], None).is_err());
+
+        // A path outside the root refuses the whole batch
+        let restriction = Restriction::new(&root, false).unwrap();
+        let outside = temp.path().join("outside");
+        assert!(mkdirs(&[root.join("d"), outside.clone()], Some(&restriction)).is_err());
+        assert!(!root.join("d").exists());
+        assert!(!outside.exists());
+    }
+
+    #[test]
+    fn test_commit_staged() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        let staging = staging::Staging::new(&root);
+        std::fs::create_dir_all(staging.dir().join("new")).unwrap();
+        let restriction = Restriction::new(&root, false).unwrap();
+
+        // A move out of the root refuses the whole batch
+        let outside = temp.path().join("outside");
+        let escape = StagedMove {
+            dest: outside.clone(),
+            ..staging.staged_move(&root.join("escape"), false)
+        };
+        let moves = vec![staging.staged_move(&root.join("a"), false), escape];
+        for m in &moves {
+            std::fs::write(&m.staged, b"new").unwrap();
+        }
+        assert!(commit_staged(&moves, Some(&restriction)).is_err());
+        assert!(!root.join("a").exists());
+        assert!(!outside.exists());
+
+        assert_eq!(commit_staged(&moves[..1], Some(&restriction)).unwrap(), 1);
+        assert_eq!(std::fs::read(root.join("a")).unwrap(), b"new");
+    }
+
+    #[test]
+    fn test_receive_batch() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        let file = |path: &Path, data: &[u8]| BatchFile {
+            path: path.to_string_lossy().to_string(),
+            mode: Some(0o600),
+            mtime: 1_700_000_000,
+            data: data.to_vec(),
+        };
+
+        let payload = wire::encode(
+            WireFormat::Binary,
+            &vec![
+                file(&root.join("a.txt"), b"alpha"),
+                file(&root.join("new/dir/b.txt"), b"beta"),
+                file(&temp.path().join("outside.txt"), b"nope"),
+            ],
+        )
+        .unwrap();
+        let files: Vec<BatchFile> = wire::decode(WireFormat::Binary, &payload).unwrap();
+
+        let restriction = Restriction::new(&root, false).unwrap();
+        let errors = receive_batch(files, false, Some(&restriction));
+        assert_eq!(errors[0], None);
+        assert_eq!(errors[1], None);
+        assert!(errors[2].as_ref().unwrap().contains("outside.txt"));
+
+        assert_eq!(std::fs::read(root.join("a.txt")).unwrap(), b"alpha");
+        assert_eq!(std::fs::read(root.join("new/dir/b.txt")).unwrap(), b"beta");
+        assert!(!temp.path().join("outside.txt").exists());
+        let metadata = std::fs::metadata(root.join("a.txt")).unwrap();
+        assert_eq!(
+            filetime::FileTime::from_last_modification_time(&metadata).unix_seconds(),
+            1_700_000_000
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_check_restricted() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        let command = |args: &[&str]| {
+            Cli::try_parse_from(std::iter::once("sy-remote").chain(args.iter().copied()))
+                .unwrap()
+                .command
+        };
+        let inside = root.join("file.txt");
+        let inside = inside.to_str().unwrap();
+
+        let restriction = Restriction::new(&root, false).unwrap();
+        assert!(
+            check_restricted(&command(&["scan", root.to_str().unwrap()]), &restriction).is_ok()
+        );
+        assert!(check_restricted(&command(&["receive-file", inside]), &restriction).is_ok());
+        assert!(check_restricted(&command(&["scan", "/etc"]), &restriction).is_err());
+        assert!(check_restricted(
+            &command(&["restrict", "--root", root.to_str().unwrap()]),
+            &restriction
+        )
+        .is_err());
+
+        let read_only = Restriction::new(&root, true).unwrap();
+        assert!(check_restricted(&command(&["send-file", inside]), &read_only).is_ok());
+        assert!(check_restricted(&command(&["receive-file", inside]), &read_only).is_err());
+        assert!(check_restricted(&command(&["lock", inside]), &read_only).is_err());
+        assert!(check_restricted(&command(&["mkdirs"]), &read_only).is_err());
+        assert!(check_restricted(&command(&["receive-batch"]), &read_only).is_err());
+    }
 }
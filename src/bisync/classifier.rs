@@ -253,6 +253,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         }
     }
 
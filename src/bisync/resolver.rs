@@ -28,6 +28,18 @@ impl ConflictResolution {
             _ => None,
         }
     }
+
+    /// Name of the strategy, as given to --conflict-resolve
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Newer => "newer",
+            Self::Larger => "larger",
+            Self::Smaller => "smaller",
+            Self::Source => "source",
+            Self::Dest => "dest",
+            Self::Rename => "rename",
+        }
+    }
 }
 
 /// Resolution action to take
@@ -272,6 +284,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         }
     }
 
@@ -464,5 +479,6 @@ mod tests {
         assert_eq!(ConflictResolution::from_str("dest"), Some(ConflictResolution::Dest));
         assert_eq!(ConflictResolution::from_str("rename"), Some(ConflictResolution::Rename));
         assert_eq!(ConflictResolution::from_str("invalid"), None);
+        assert_eq!(ConflictResolution::from_str(ConflictResolution::Dest.as_str()), Some(ConflictResolution::Dest));
     }
 }
@@ -1,5 +1,23 @@
+use crate::acl::AclMapPolicy;
+use crate::bisync::ConflictResolution;
+use crate::owner::PreservePerms;
 use crate::path::SyncPath;
-use clap::{Parser, ValueEnum};
+use crate::snapshot::SnapshotKind;
+use crate::ssh::config::parse_ssh_option;
+use crate::ssh::system::SshBackend;
+use crate::stdin_source::STDIN_SOURCE;
+use crate::stdout_dest::STDOUT_DEST;
+use crate::sync::control::ControlCommand;
+use crate::sync::fault::{self, FaultSpec};
+use crate::sync::names::NameMode;
+use crate::sync::priority::PriorityRule;
+use crate::sync::quota::QuotaMode;
+use crate::sync::sandbox::DestConfinement;
+use crate::sync::transfer::ImmutableMode;
+use crate::sync::trash::{TrashTarget, TRASH_DIR};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::Path;
+use std::time::Duration;
 
 // Import integrity types for verification modes
 use crate::integrity::ChecksumType;
@@ -11,6 +29,19 @@ fn parse_sync_path(s: &str) -> Result<SyncPath, String> {
     Ok(SyncPath::parse(s))
 }
 
+fn parse_destination(s: &str) -> Result<SyncPath, String> {
+    crate::path::expand_template(s).map(|expanded| SyncPath::parse(&expanded))
+}
+
+fn parse_conflict_resolution(s: &str) -> Result<ConflictResolution, String> {
+    ConflictResolution::from_str(s).ok_or_else(|| {
+        format!(
+            "Invalid strategy '{}'. Valid options: newer, larger, smaller, source, dest, rename",
+            s
+        )
+    })
+}
+
 pub fn parse_size(s: &str) -> Result<u64, String> {
     let s = s.trim().to_uppercase();
 
@@ -39,6 +70,31 @@ pub fn parse_size(s: &str) -> Result<u64, String> {
     Ok((num * multiplier as f64) as u64)
 }
 
+/// Parse a duration like "30s", "5m", "2h" or "1d" (bare numbers are seconds)
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (num_str, multiplier) = match s.find(|c: char| c.is_alphabetic()) {
+        Some(pos) => {
+            let multiplier = match &s[pos..] {
+                "s" => 1,
+                "m" => 60,
+                "h" => 60 * 60,
+                "d" => 24 * 60 * 60,
+                unit => return Err(format!("Unknown unit '{}'. Use s, m, h, or d", unit)),
+            };
+            (&s[..pos], multiplier)
+        }
+        None => (s, 1),
+    };
+
+    let num: u64 = num_str
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid number '{}': {}", num_str, e))?;
+
+    Ok(Duration::from_secs(num * multiplier))
+}
+
 /// Verification mode for file integrity
 #[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
 pub enum VerificationMode {
@@ -82,6 +138,18 @@ pub enum SymlinkMode {
 
     /// Skip all symlinks
     Skip,
+
+    /// Preserve symlinks within the tree, skip ones pointing outside it
+    Safe,
+
+    /// Preserve symlinks within the tree, copy the targets of ones pointing outside it
+    CopyUnsafe,
+
+    /// Store symlinks as placeholder files, for destinations that can't hold symlinks
+    Munge,
+
+    /// Preserve symlinks and turn placeholder files from `munge` back into symlinks
+    Unmunge,
 }
 
 impl Default for SymlinkMode {
@@ -90,10 +158,281 @@ impl Default for SymlinkMode {
     }
 }
 
+/// How unreadable entries found while scanning the source are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanErrorMode {
+    /// Skip unreadable entries and report them as sync errors (default)
+    #[default]
+    Report,
+
+    /// Skip unreadable entries silently (--ignore-scan-errors)
+    Ignore,
+
+    /// Abort the sync on the first unreadable entry (--strict-scan)
+    Strict,
+}
+
+/// How sockets, FIFOs and device nodes in the source are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SpecialsMode {
+    /// Skip them with a warning each, and count them in the summary (default)
+    #[default]
+    Warn,
+
+    /// Skip them quietly, only counting them in the summary
+    Skip,
+
+    /// Abort the sync on the first one
+    Fail,
+}
+
+/// How symlinks in the source whose target doesn't exist are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DanglingMode {
+    /// Copy them as symlinks like any other (default)
+    #[default]
+    Preserve,
+
+    /// Leave them out with a warning each
+    Skip,
+
+    /// Abort the sync on the first one
+    Fail,
+}
+
+/// Maintenance subcommands (run instead of a sync)
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Report whether DESTINATION is in sync with SOURCE, without changing anything
+    ///
+    /// Exits 0 when in sync, 1 on drift and 2 on error.
+    Check {
+        /// Source of the sync pair
+        #[arg(value_parser = parse_sync_path)]
+        source: SyncPath,
+
+        /// Destination of the sync pair
+        #[arg(value_parser = parse_sync_path)]
+        destination: SyncPath,
+
+        /// Compare file contents by checksum instead of size and mtime
+        #[arg(short = 'c', long)]
+        checksum: bool,
+
+        /// Don't count files that only exist in the destination as drift
+        #[arg(long)]
+        ignore_extra: bool,
+
+        /// Exclude files matching pattern (can be repeated)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// List drifted paths, not just the summary
+        #[arg(short, long)]
+        list: bool,
+
+        /// Print nothing; report through the exit code only
+        #[arg(short, long, conflicts_with_all = ["list", "json"])]
+        quiet: bool,
+
+        /// Print the report as a single JSON object
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Inspect or maintain checksum databases (created by --checksum-db)
+    ChecksumDb {
+        /// Operation to run
+        #[arg(value_enum)]
+        action: ChecksumDbAction,
+
+        /// Source of the sync pair (all pairs if omitted)
+        #[arg(value_parser = parse_sync_path, requires = "destination")]
+        source: Option<SyncPath>,
+
+        /// Destination of the sync pair
+        #[arg(value_parser = parse_sync_path)]
+        destination: Option<SyncPath>,
+    },
+
+    /// Remove temp files, stale locks and resume state that crashed syncs left in DESTINATION
+    ///
+    /// Refuses while a sync into DESTINATION is running.
+    Clean {
+        /// Local destination to clean up after
+        #[arg(value_parser = parse_sync_path)]
+        destination: SyncPath,
+
+        /// List what would be removed without removing it
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Pause, resume or query a sync running into DESTINATION
+    Ctl {
+        /// Control command to send
+        #[arg(value_enum)]
+        action: ControlCommand,
+
+        /// Destination of the running sync
+        #[arg(value_parser = parse_sync_path)]
+        destination: SyncPath,
+    },
+
+    /// List or restore what --trash moved to .sy-trash/ in DESTINATION
+    ///
+    /// Entries moved to your own trash (local destinations without
+    /// --trash-dir) are restored from your file manager instead.
+    Trash {
+        /// Operation to run
+        #[arg(value_enum)]
+        action: TrashAction,
+
+        /// Destination the sync deleted from
+        #[arg(value_parser = parse_sync_path)]
+        destination: SyncPath,
+
+        /// Trash folder to restore, as listed (default: the newest)
+        #[arg(long, value_name = "NAME")]
+        run: Option<String>,
+
+        /// Trash directory the sync used, relative to DESTINATION (default: .sy-trash)
+        #[arg(long, value_name = "DIR")]
+        trash_dir: Option<std::path::PathBuf>,
+
+        /// Show what would be restored without moving anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Show recent sync runs, or bandwidth used per host with --bandwidth
+    History {
+        /// Show total bytes sent/received per remote host and profile
+        #[arg(long)]
+        bandwidth: bool,
+
+        /// Only include runs from the last DAYS days
+        #[arg(long, value_name = "DAYS")]
+        days: Option<u64>,
+
+        /// Number of recent runs to list (without --bandwidth)
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Check that a sync from SOURCE to DESTINATION can run, without changing anything
+    ///
+    /// Checks the connection, sy-remote, the source, destination permissions,
+    /// free space, clock skew and filter syntax, then prints a go/no-go
+    /// report. Exits 0 for go and 1 for no-go.
+    Preflight {
+        /// Source of the sync pair
+        #[arg(value_parser = parse_sync_path)]
+        source: SyncPath,
+
+        /// Destination of the sync pair
+        #[arg(value_parser = parse_sync_path)]
+        destination: SyncPath,
+
+        /// Filter rule to validate (can be repeated)
+        #[arg(long)]
+        filter: Vec<String>,
+
+        /// Include pattern to validate (can be repeated)
+        #[arg(long)]
+        include: Vec<String>,
+
+        /// Exclude pattern to validate (can be repeated)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Include pattern list to validate (file, `-` for stdin, or URL)
+        #[arg(long, value_name = "SOURCE")]
+        include_from: Option<String>,
+
+        /// Exclude pattern list to validate (file, `-` for stdin, or URL)
+        #[arg(long, value_name = "SOURCE")]
+        exclude_from: Option<String>,
+
+        /// Path of sy-remote on the remote host
+        #[arg(long, value_name = "PATH")]
+        remote_binary: Option<String>,
+
+        /// Print the report as a single JSON object
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Measure scan, plan and transfer speed on generated workloads
+    ///
+    /// Syncs each workload to a local temporary directory and, if REMOTE is
+    /// given, to a directory under it that is removed afterwards, then
+    /// prints a comparison table.
+    Bench {
+        /// Remote (or other) directory to benchmark against, e.g. user@host:/tmp
+        #[arg(value_parser = parse_sync_path)]
+        remote: Option<SyncPath>,
+
+        /// Workload to run (can be repeated; default: all)
+        #[arg(long, value_enum)]
+        workload: Vec<BenchWorkload>,
+
+        /// Multiply the workload sizes, e.g. 0.1 for a quick run
+        #[arg(long, default_value = "1.0")]
+        scale: f64,
+
+        /// Number of parallel file transfers
+        #[arg(short = 'j', long, default_value = "10")]
+        parallel: usize,
+
+        /// Path of sy-remote on the remote host
+        #[arg(long, value_name = "PATH")]
+        remote_binary: Option<String>,
+
+        /// Print one JSON object per result
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Trash operation (`sy trash`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TrashAction {
+    /// List the syncs with entries in the trash, oldest first
+    List,
+
+    /// Move one sync's trashed entries back (the newest, or --run)
+    Restore,
+}
+
+/// Synthetic workload for `sy bench`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BenchWorkload {
+    /// 10,000 files of 4 KiB
+    SmallFiles,
+
+    /// 4 files of 128 MiB
+    LargeFiles,
+
+    /// 4 sparse files of 256 MiB, mostly holes
+    Sparse,
+}
+
+/// Checksum database maintenance operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ChecksumDbAction {
+    /// Show entry counts and database size
+    Stats,
+
+    /// Reclaim space left by deleted or pruned entries
+    Vacuum,
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "sy")]
 #[command(about = "Modern file synchronization tool", long_about = None)]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true)]
 #[command(after_help = "EXAMPLES:
     # Basic sync
     sy /source /destination
@@ -120,23 +459,46 @@ impl Default for SymlinkMode {
     # Bandwidth limiting
     sy /source /destination --bwlimit 1MB     # Limit to 1 MB/s
     sy /source user@host:/dest --bwlimit 500KB  # Limit to 500 KB/s
+    sy /source /destination --bwlimit 4MB --bwlimit-per-file 1MB  # 4 MB/s total, 1 MB/s per file
 
     # Verification modes
     sy /source /destination --verify            # BLAKE3 cryptographic verification
     sy /source /destination --mode paranoid     # Maximum reliability
 
+    # Checksum database maintenance
+    sy checksum-db stats                        # All sync pairs
+    sy checksum-db vacuum /source /destination  # One sync pair
+
+    # Pause and resume a long-running sync (from another terminal)
+    sy ctl pause /destination
+    sy ctl status /destination
+    sy ctl resume /destination
+
+    # Bandwidth used per remote host (e.g. for metered egress)
+    sy history --bandwidth --days 30
+
+    # Go/no-go check before a long initial sync
+    sy preflight /source user@host:/dest
+
 For more information: https://github.com/nijaru/sy")]
 pub struct Cli {
-    /// Source path (local: /path or remote: user@host:/path)
+    /// Source path (local: /path or remote: user@host:/path, or - for stdin)
     /// Optional when using --profile
     #[arg(value_parser = parse_sync_path)]
     pub source: Option<SyncPath>,
 
-    /// Destination path (local: /path or remote: user@host:/path)
+    /// Destination path (local: /path or remote: user@host:/path, or - for
+    /// stdout when the source is a single file)
     /// Optional when using --profile
-    #[arg(value_parser = parse_sync_path)]
+    ///
+    /// `{hostname}`, `{date}` and `{date:FORMAT}` (strftime, e.g. `{date:%Y/%m}`)
+    /// are expanded at run time: backups/{hostname}/{date}/
+    #[arg(value_parser = parse_destination)]
     pub destination: Option<SyncPath>,
 
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Show changes without applying them (dry-run)
     #[arg(short = 'n', long)]
     pub dry_run: bool,
@@ -146,6 +508,12 @@ pub struct Cli {
     #[arg(long)]
     pub diff: bool,
 
+    /// Exit with code 1 if any files would be (or were) created, updated or deleted
+    /// Exit code 0 means source and destination were already in sync; use with
+    /// --dry-run to check for pending changes (like `terraform plan -detailed-exitcode`)
+    #[arg(long)]
+    pub exit_code_on_change: bool,
+
     /// Delete files in destination not present in source
     #[arg(short, long)]
     pub delete: bool,
@@ -156,13 +524,44 @@ pub struct Cli {
     pub delete_threshold: u8,
 
     /// Move deleted files to trash instead of permanent deletion
+    ///
+    /// Local destinations use your trash (XDG trash, or ~/.Trash on macOS);
+    /// remote ones, and files the trash can't take, use .sy-trash/ in the
+    /// destination with a timestamped folder per sync (see `sy trash`).
     #[arg(long)]
     pub trash: bool,
 
+    /// Trash directory for --trash, relative to the destination root (default: .sy-trash)
+    ///
+    /// Also used for local destinations instead of your trash.
+    #[arg(long, value_name = "DIR", requires = "trash")]
+    pub trash_dir: Option<std::path::PathBuf>,
+
     /// Skip deletion safety checks (dangerous - use with caution)
     #[arg(long)]
     pub force_delete: bool,
 
+    /// Sync even if the destination is inside the source, or the source inside the destination
+    /// - The nested tree is left alone: never copied from the source, never
+    ///   deleted from the destination
+    #[arg(long)]
+    pub allow_nested: bool,
+
+    /// Write changed files to a staging directory and move them into place together at the end
+    /// - The destination changes in one quick step, and not at all if any
+    ///   file fails; deletions happen after the move
+    /// - Files are staged in .sy-staging/ in the destination; updates are
+    ///   full copies and --resume is off
+    #[arg(long, visible_alias = "atomic")]
+    pub delay_updates: bool,
+
+    /// Hard-link files that are unchanged in DIR instead of copying them (like rsync)
+    /// - DIR is an earlier copy of the destination, e.g. yesterday's dated
+    ///   backup; relative paths are relative to the destination
+    /// - Linked files share data and metadata with DIR's copy
+    #[arg(long, value_name = "DIR")]
+    pub link_dest: Option<std::path::PathBuf>,
+
     /// Verbosity level (can be repeated: -v, -vv, -vvv)
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
@@ -175,14 +574,86 @@ pub struct Cli {
     #[arg(long)]
     pub perf: bool,
 
+    /// Write a Chrome trace of scan, plan, transfer and verify spans to FILE
+    /// (open in Perfetto or chrome://tracing)
+    #[arg(long, value_name = "FILE")]
+    pub trace_out: Option<std::path::PathBuf>,
+
+    /// Export sync spans and per-run metrics to an OTLP/HTTP collector
+    /// (e.g. http://localhost:4318; requires the `otel` feature)
+    #[arg(long, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
     /// Number of parallel file transfers (default: 10)
     #[arg(short = 'j', long, default_value = "10")]
     pub parallel: usize,
 
+    /// Threads for checksum verification, compression and delta hashing
+    /// (0 = one per core, default: 0)
+    ///
+    /// Separate from --parallel, so CPU-heavy work on a small machine can't
+    /// starve the file transfers of I/O.
+    #[arg(long, default_value = "0", value_name = "N")]
+    pub threads: usize,
+
     /// Maximum number of errors before aborting (0 = unlimited, default: 100)
     #[arg(long, default_value = "100")]
     pub max_errors: usize,
 
+    /// Never abort on per-file errors: failed files are summarized at the end
+    /// and the exit code is 23 (partial transfer, as in rsync) if any failed
+    ///
+    /// For best-effort mirrors of sources that change underfoot (e.g. /var)
+    #[arg(long, conflicts_with_all = ["max_errors", "strict_scan"])]
+    pub ignore_errors: bool,
+
+    /// Passes over files that failed, retried at the end of the run (0 = no retries)
+    #[arg(long, default_value = "1")]
+    pub retry_failed: usize,
+
+    /// Say why each file is copied, updated or deleted in the per-file lines
+    /// (e.g. "size differs 1.20 MB→1.30 MB", "mtime newer by 42s"; skips with -v)
+    #[arg(long)]
+    pub why: bool,
+
+    /// Expected size of the stream when the source is - (stdin), e.g. 20GB
+    ///
+    /// Stdin is spooled to a temp file before it is synced; the hint sizes
+    /// the progress bar and checks the temp directory has room up front.
+    #[arg(long, value_parser = parse_size, value_name = "SIZE")]
+    pub size_hint: Option<u64>,
+
+    /// Fail transfers at random to test retry and resume settings
+    /// (e.g. transfer=10,disconnect=2,seed=7; percentages)
+    ///
+    /// `disconnect` drops the connection, failing every later transfer of
+    /// the run. Only accepted with SY_FAULT_INJECTION=1 in the environment.
+    #[arg(long, hide = true, value_name = "SPEC", value_parser = FaultSpec::parse)]
+    pub fault_inject: Option<FaultSpec>,
+
+    /// Transfer matching files ahead of others: PATTERN:LEVEL with LEVEL high,
+    /// normal or low (e.g. '*.db:high'; repeatable, first match wins)
+    #[arg(long = "priority-pattern", value_name = "PATTERN:LEVEL", value_parser = PriorityRule::parse)]
+    pub priority_pattern: Vec<PriorityRule>,
+
+    /// Skip unreadable files and directories during the scan without reporting them
+    #[arg(long)]
+    pub ignore_scan_errors: bool,
+
+    /// Abort if any file or directory can't be read during the scan
+    #[arg(long)]
+    pub strict_scan: bool,
+
+    /// What to do with sockets, FIFOs and device nodes, which are never copied
+    #[arg(long, value_enum, default_value = "warn")]
+    pub specials: SpecialsMode,
+
+    /// What to do with symlinks whose target doesn't exist
+    /// - With --links follow there is nothing to copy, so they are per-file
+    ///   errors unless skipped or failed on
+    #[arg(long, value_enum, default_value = "preserve")]
+    pub dangling_links: DanglingMode,
+
     /// Minimum file size to sync (e.g., "1MB", "500KB")
     #[arg(long, value_parser = parse_size)]
     pub min_size: Option<u64>,
@@ -191,6 +662,11 @@ pub struct Cli {
     #[arg(long, value_parser = parse_size)]
     pub max_size: Option<u64>,
 
+    /// Limit recursion depth of the source scan (1 = top-level entries only)
+    /// Useful for syncing just the top levels of huge trees
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
     /// Exclude files matching pattern (can be repeated)
     /// Examples: "*.log", "node_modules", "target/"
     #[arg(long)]
@@ -203,31 +679,96 @@ pub struct Cli {
 
     /// Filter rules in rsync syntax: "+ pattern" (include) or "- pattern" (exclude)
     /// Can be repeated. Rules processed in order, first match wins.
-    /// Examples: "+ *.rs", "- *.log", "- target/*"
+    /// "P pattern" protects destination files from --delete, "R pattern" undoes that.
+    /// Examples: "+ *.rs", "- *.log", "- target/*", "P .env"
     #[arg(long)]
     pub filter: Vec<String>,
 
-    /// Read exclude patterns from file (one pattern per line)
+    /// Read exclude patterns (one per line) from a file, `-` for stdin, or an
+    /// http(s):// URL (fetched with curl)
     #[arg(long)]
-    pub exclude_from: Option<std::path::PathBuf>,
+    pub exclude_from: Option<String>,
 
-    /// Read include patterns from file (one pattern per line)
+    /// Read include patterns (one per line) from a file, `-` for stdin, or an
+    /// http(s):// URL (fetched with curl)
     #[arg(long)]
-    pub include_from: Option<std::path::PathBuf>,
+    pub include_from: Option<String>,
 
     /// Apply ignore template from ~/.config/sy/templates/ (can be repeated)
     /// Examples: "rust", "node", "python"
     #[arg(long)]
     pub ignore_template: Vec<String>,
 
-    /// Bandwidth limit in bytes per second (e.g., "1MB", "500KB")
+    /// Don't apply the global ignore file (~/.config/sy/ignore)
+    #[arg(long)]
+    pub no_global_ignore: bool,
+
+    /// Bandwidth limit for the whole sync in bytes per second (e.g., "1MB", "500KB")
+    ///
+    /// Shared evenly by the transfers in flight, so a large file can't hold
+    /// up small ones.
     #[arg(long, value_parser = parse_size)]
     pub bwlimit: Option<u64>,
 
+    /// Bandwidth limit for each file in bytes per second (e.g., "200KB")
+    ///
+    /// Applies on top of --bwlimit: a file gets the lower of this and its
+    /// share of the global limit.
+    #[arg(long, value_parser = parse_size, value_name = "BWLIMIT")]
+    pub bwlimit_per_file: Option<u64>,
+
+    /// Keep the destination tree under this size (e.g., "500GB")
+    ///
+    /// Projected usage (current destination size plus planned copies, minus
+    /// deletions) is checked before any transfer starts.
+    #[arg(long, value_parser = parse_size)]
+    pub dest_quota: Option<u64>,
+
+    /// What to do when --dest-quota would be exceeded
+    /// - stop: Refuse to start the sync (default)
+    /// - skip-largest: Leave out the largest new/growing files until the rest fits
+    #[arg(long, value_enum, default_value = "stop")]
+    pub quota_mode: QuotaMode,
+
+    /// Apply Windows naming rules (reserved names like CON and aux, trailing
+    /// dots or spaces, characters like `:`, paths over 259 characters)
+    /// - sanitize: Rename to the closest valid name (`aux.txt` -> `aux_.txt`)
+    /// - skip: Leave them out and list them in the summary
+    /// - fail: Refuse to start the sync
+    ///
+    /// Local destinations on Windows or SMB shares use skip unless set.
+    #[arg(long, value_enum)]
+    pub windows_names: Option<NameMode>,
+
+    /// Refuse every write that would land outside the destination root
+    ///
+    /// Paths are checked before each write, with `..` and symlinks in the
+    /// destination resolved, so a malicious or corrupted source tree can't
+    /// escape it. Local destinations only.
+    #[arg(long)]
+    pub sandbox_dest: bool,
+
+    /// Write through destination symlinks that lead outside the tree
+    ///
+    /// By default, a write whose path passes through such a symlink (say, a
+    /// destination directory replaced by a link to /etc) is refused instead of
+    /// clobbering files elsewhere. Local destinations only check this.
+    #[arg(long)]
+    pub keep_dirlinks: bool,
+
     /// Enable resume support (auto-resume if state file found, default: true)
     #[arg(long, default_value = "true", action = clap::ArgAction::Set)]
     pub resume: bool,
 
+    /// Resume even if the saved state was recorded with different flags
+    ///
+    /// Instead of starting fresh, completed files are re-checked against the
+    /// new flags and the destination: those still in the source at the same
+    /// size and present in the destination stay done, the rest are synced
+    /// again. Useful after adding an exclude or changing --min-size.
+    #[arg(long)]
+    pub resume_force: bool,
+
     /// Checkpoint every N files (default: 10)
     #[arg(long, default_value = "10")]
     pub checkpoint_files: usize,
@@ -240,6 +781,30 @@ pub struct Cli {
     #[arg(long)]
     pub clean_state: bool,
 
+    /// Directory for resume state, directory cache and checksum database
+    /// (default: $XDG_STATE_HOME/sy or ~/.local/state/sy, one subdirectory per source/destination pair)
+    #[arg(long, global = true)]
+    pub state_dir: Option<std::path::PathBuf>,
+
+    /// Don't lock the destination against concurrent syncs
+    /// (by default a .sy-lock file in the destination makes a second sync fail fast)
+    #[arg(long)]
+    pub no_lock: bool,
+
+    /// Skip files that are in use by another process instead of failing
+    /// (sharing violations on Windows, EBUSY/ETXTBSY on Unix; reported in the summary)
+    #[arg(long)]
+    pub skip_busy: bool,
+
+    /// Retry files that are in use for up to SECS before skipping (--skip-busy) or failing
+    #[arg(long, value_name = "SECS")]
+    pub wait_busy: Option<u64>,
+
+    /// Defer files modified within DURATION (e.g. 30s, 5m) to a later run
+    /// instead of copying logs or databases mid-write (reported in the summary)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with_all = ["watch", "bidirectional"])]
+    pub skip_hot: Option<Duration>,
+
     /// Use directory cache for faster re-syncs (default: false)
     /// The cache stores directory mtimes to skip unchanged directories
     #[arg(long, default_value = "false", action = clap::ArgAction::Set)]
@@ -249,6 +814,12 @@ pub struct Cli {
     #[arg(long)]
     pub clear_cache: bool,
 
+    /// Keep the directory cache as a scan journal (implies --use-cache): later
+    /// syncs re-read only directories that changed instead of walking the tree.
+    /// Files in other directories are still stat'ed, so edits in place are seen
+    #[arg(long)]
+    pub scan_journal: bool,
+
     /// Use checksum database for faster --checksum re-syncs (default: false)
     /// The database stores checksums to avoid recomputation for unchanged files
     #[arg(long, default_value = "false", action = clap::ArgAction::Set)]
@@ -270,7 +841,7 @@ pub struct Cli {
     #[arg(long)]
     pub verify: bool,
 
-    /// Enable compression for network transfers (auto-detects based on file type)
+    /// Compress every network transfer (shortcut for --compression-detection always)
     #[arg(long)]
     pub compress: bool,
 
@@ -282,7 +853,56 @@ pub struct Cli {
     #[arg(long, value_enum, default_value = "auto")]
     pub compression_detection: CompressionDetection,
 
-    /// Symlink handling mode (preserve, follow, skip)
+    /// Size of each content probe for auto compression detection (e.g., "64KB")
+    /// - Files are sampled at the start, middle and end
+    #[arg(long, value_parser = parse_size, default_value = "64KB")]
+    pub compression_probe_size: u64,
+
+    /// Path of sy-remote on the remote host (e.g., "/opt/sy/sy-remote")
+    /// - Default: remote PATH, then common install locations
+    #[arg(long, value_name = "PATH")]
+    pub remote_binary: Option<String>,
+
+    /// Probe SSH hosts again instead of using what earlier runs cached
+    /// - Cached for a day: sy-remote's location and capabilities, clock skew,
+    ///   measured bandwidth and the upload chunk size picked for it
+    #[arg(long)]
+    pub refresh_host_info: bool,
+
+    /// Bytes of SFTP writes kept in flight per SSH upload (e.g., "1MB"), instead of tuning it
+    /// - Default: tuned per host to the measured round trip and throughput,
+    ///   from 64KB on slow or nearby links up to 4MB on fast distant ones
+    #[arg(long, value_parser = parse_size, value_name = "SIZE")]
+    pub chunk_size: Option<u64>,
+
+    /// SSH connections a large upload is split across at once (default: 1)
+    /// - Each file is cut into up to N parts of at least 16MB, sent in parallel
+    ///   over separate pooled connections, to get past the throughput ceiling
+    ///   of one TCP connection on long, fast links (like bbcp or aria2)
+    /// - Only for SFTP uploads, not with --ssh-backend system (one connection)
+    #[arg(long, default_value = "1", value_name = "N")]
+    pub streams: usize,
+
+    /// SSH implementation for remote paths (libssh2, system)
+    /// - libssh2: Built in, with SFTP for hosts without sy-remote (default)
+    /// - system: The `ssh` binary over one shared ControlMaster connection;
+    ///   faster on fast links and honours all of ssh_config, needs sy-remote
+    #[arg(long, value_enum, default_value = "libssh2", value_name = "BACKEND")]
+    pub ssh_backend: SshBackend,
+
+    /// SSH option in ssh_config form (e.g., "Port=2222", "Ciphers=aes128-gcm@openssh.com"), repeatable
+    /// - Port, User, IdentityFile and ProxyJump override ssh_config
+    /// - libssh2 applies Ciphers, MACs, KexAlgorithms and HostKeyAlgorithms;
+    ///   the system backend hands every option to ssh
+    #[arg(short = 'e', long = "ssh-option", value_name = "OPTION", value_parser = parse_ssh_option)]
+    pub ssh_options: Vec<(String, String)>,
+
+    /// Command run instead of `ssh` (e.g., "tsh ssh"), implies --ssh-backend system
+    /// - For setups only a wrapper or another client can authenticate
+    #[arg(long, value_name = "COMMAND")]
+    pub ssh_command: Option<String>,
+
+    /// Symlink handling mode (preserve, follow, skip, safe, copy-unsafe, munge, unmunge)
     #[arg(long, value_enum, default_value = "preserve")]
     pub links: SymlinkMode,
 
@@ -290,6 +910,22 @@ pub struct Cli {
     #[arg(short = 'L', long)]
     pub copy_links: bool,
 
+    /// Skip symlinks pointing outside the source tree (shortcut for --links safe)
+    #[arg(long)]
+    pub safe_links: bool,
+
+    /// Copy the targets of symlinks pointing outside the source tree (shortcut for --links copy-unsafe)
+    #[arg(long)]
+    pub copy_unsafe_links: bool,
+
+    /// Store symlinks as placeholder files, e.g. on FAT or object stores (shortcut for --links munge)
+    #[arg(long)]
+    pub munge_links: bool,
+
+    /// Turn placeholder files back into symlinks (shortcut for --links unmunge)
+    #[arg(long)]
+    pub unmunge_links: bool,
+
     /// Preserve extended attributes (xattrs)
     #[arg(short = 'X', long)]
     pub preserve_xattrs: bool,
@@ -302,10 +938,75 @@ pub struct Cli {
     #[arg(short = 'A', long)]
     pub preserve_acls: bool,
 
+    /// ACL handling when source and destination use different ACL models (translate, skip, strict)
+    /// - translate: Convert between POSIX and NFSv4/macOS ACLs (default)
+    /// - skip: Leave ACLs from the other model unapplied
+    /// - strict: Fail files whose ACLs use the other model
+    #[arg(long, value_enum, default_value = "translate")]
+    pub acl_map: AclMapPolicy,
+
     /// Preserve BSD file flags (macOS only: hidden, immutable, nodump, etc.; no-op on other platforms)
     #[arg(short = 'F', long)]
     pub preserve_flags: bool,
 
+    /// Update and delete destination files that are immutable or append-only
+    ///
+    /// The flags (chattr +i/+a, chflags uchg/schg) are cleared for the change
+    /// and restored afterwards. Needs root or CAP_LINUX_IMMUTABLE on Linux;
+    /// without it, such files are reported as errors. Local destinations only.
+    #[arg(long)]
+    pub force_change: bool,
+
+    /// Preserve access times (not implied by --archive)
+    #[arg(short = 'U', long = "atimes")]
+    pub preserve_atimes: bool,
+
+    /// Preserve creation times (macOS and Windows destinations; ignored elsewhere)
+    #[arg(short = 'N', long = "crtimes")]
+    pub preserve_crtimes: bool,
+
+    /// Read source files without updating their access times (O_NOATIME)
+    ///
+    /// Linux only, and only for files you own (or with CAP_FOWNER); other files
+    /// are read normally. `--perf` reports how many opens were protected.
+    #[arg(long)]
+    pub open_noatime: bool,
+
+    /// Write files directly to their final path instead of a temp file renamed into place
+    ///
+    /// By default a replaced file stays intact until its new version is complete.
+    /// `--inplace` saves the space of a second copy and keeps destination hard
+    /// links intact, but an interrupted transfer leaves a partial file. Delta
+    /// updates always use a temp file.
+    #[arg(long)]
+    pub inplace: bool,
+
+    /// Copy updated files in full instead of sending only their changed blocks
+    #[arg(short = 'W', long, conflicts_with = "no_whole_file")]
+    pub whole_file: bool,
+
+    /// Send only the changed blocks of every updated file, whatever its size
+    ///
+    /// By default local copies use delta updates for files of 10 MB or more
+    /// that mostly stayed the same. On a network filesystem (NFS, SMB, FUSE)
+    /// the changed blocks are rewritten in the destination file itself, since
+    /// writing a complete new copy is the slow part; an interrupted update
+    /// then leaves a partly updated file for the next sync to finish.
+    #[arg(long)]
+    pub no_whole_file: bool,
+
+    /// Sync from a temporary filesystem snapshot of the source, deleted afterwards
+    ///
+    /// Every file is read as it was at one instant, so a tree that changes
+    /// during the sync (databases, VM images, mail stores) arrives
+    /// crash-consistent. Local directory sources only; snapshots usually need root.
+    /// - auto: Whichever of the below supports the source's filesystem
+    /// - btrfs, zfs: Snapshot the subvolume or dataset
+    /// - lvm: Snapshot the logical volume and mount it read-only
+    /// - apfs: Take an APFS local snapshot (macOS) and mount it read-only
+    #[arg(long, value_enum, value_name = "PROVIDER", conflicts_with_all = ["watch", "bidirectional"])]
+    pub snapshot_source: Option<SnapshotKind>,
+
     /// Preserve permissions
     #[arg(short = 'p', long)]
     pub preserve_permissions: bool,
@@ -322,6 +1023,13 @@ pub struct Cli {
     #[arg(short = 'o', long)]
     pub preserve_owner: bool,
 
+    /// Keep owners and groups by number instead of matching them by name
+    ///
+    /// Between machines, owners and groups are matched by user and group name
+    /// by default, like rsync; ids without a name on either side stay numbers.
+    #[arg(long)]
+    pub numeric_ids: bool,
+
     /// Preserve device files and special files (requires root)
     #[arg(short = 'D', long)]
     pub preserve_devices: bool,
@@ -377,15 +1085,39 @@ pub struct Cli {
     #[arg(long)]
     pub show_profile: Option<String>,
 
-    /// Bidirectional sync mode - sync changes in both directions
+    /// Serve the [modules] of the config file over TCP instead of syncing
+    /// (clients sync with `host::module/path`)
+    #[arg(long, conflicts_with_all = ["source", "destination", "profile"])]
+    pub daemon: bool,
+
+    /// Address --daemon listens on
+    #[arg(long, default_value = "0.0.0.0", value_name = "ADDR")]
+    pub address: String,
+
+    /// TCP port --daemon listens on
+    #[arg(long, default_value_t = crate::daemon::DEFAULT_PORT)]
+    pub port: u16,
+
+    /// Reach daemon paths (host::module/path) over QUIC, served by
+    /// `sy-remote serve-quic` (experimental; requires the `quic` feature)
+    /// The server is checked with --quic-cert, or a pre-shared key in SY_QUIC_PSK
+    #[arg(long)]
+    pub quic: bool,
+
+    /// PEM certificate (or CA bundle) to check the --quic server's certificate against
+    #[arg(long, value_name = "FILE", requires = "quic")]
+    pub quic_cert: Option<std::path::PathBuf>,
+
+    /// Bidirectional sync mode - sync changes in both directions (also --bisync)
     /// Detects and resolves conflicts automatically based on --conflict-resolve strategy
-    #[arg(short = 'b', long)]
+    #[arg(short = 'b', long, visible_alias = "bisync")]
     pub bidirectional: bool,
 
     /// Conflict resolution strategy for bidirectional sync
     /// Options: newer (default), larger, smaller, source, dest, rename
-    #[arg(long, default_value = "newer")]
-    pub conflict_resolve: String,
+    /// - With --json, each conflict is reported as a "conflict" event
+    #[arg(long, default_value = "newer", value_parser = parse_conflict_resolution)]
+    pub conflict_resolve: ConflictResolution,
 
     /// Maximum percentage of files that can be deleted in bidirectional sync (0-100)
     /// Set to 0 for unlimited deletions (default: 50)
@@ -400,6 +1132,17 @@ pub struct Cli {
 
 impl Cli {
     pub fn validate(&self) -> anyhow::Result<()> {
+        if self.fault_inject.is_some() && std::env::var(fault::ENABLE_VAR).as_deref() != Ok("1") {
+            anyhow::bail!(
+                "--fault-inject is for testing; set {}=1 to enable it",
+                fault::ENABLE_VAR
+            );
+        }
+
+        if self.include_from.as_deref() == Some("-") && self.exclude_from.as_deref() == Some("-") {
+            anyhow::bail!("Only one of --include-from and --exclude-from can read from stdin (-)");
+        }
+
         // Validate size filters first (independent of source path)
         if let (Some(min), Some(max)) = (self.min_size, self.max_size) {
             if min > max {
@@ -418,6 +1161,30 @@ impl Cli {
             anyhow::bail!("--ignore-times, --size-only, and --checksum are mutually exclusive");
         }
 
+        if self.ignore_scan_errors && self.strict_scan {
+            anyhow::bail!("--ignore-scan-errors and --strict-scan are mutually exclusive");
+        }
+
+        if self.max_depth == Some(0) {
+            anyhow::bail!("--max-depth must be at least 1");
+        }
+
+        if self.resume_force && !self.resume {
+            anyhow::bail!("--resume-force cannot be used with --resume false");
+        }
+
+        if self.compression_probe_size == 0 {
+            anyhow::bail!("--compression-probe-size must be at least 1 byte");
+        }
+
+        if self.chunk_size == Some(0) {
+            anyhow::bail!("--chunk-size must be at least 1 byte");
+        }
+
+        if self.streams == 0 {
+            anyhow::bail!("--streams must be at least 1");
+        }
+
         // Validate deletion threshold (0-100)
         if self.delete_threshold > 100 {
             anyhow::bail!(
@@ -439,6 +1206,21 @@ impl Cli {
             }
         }
 
+        if self.snapshot_source.is_some() {
+            if self.source.as_ref().is_some_and(|s| !s.is_local()) {
+                anyhow::bail!("--snapshot-source only supports local sources");
+            }
+            if self.is_single_file() {
+                anyhow::bail!("--snapshot-source needs a source directory, not a single file");
+            }
+        }
+
+        if self.exit_code_on_change && self.watch {
+            anyhow::bail!(
+                "--exit-code-on-change cannot be used with --watch (watch mode never exits)"
+            );
+        }
+
         // Bidirectional sync validation
         if self.bidirectional {
             // Validate max_delete percentage
@@ -449,16 +1231,6 @@ impl Cli {
                 );
             }
 
-            // Validate conflict resolution strategy
-            let valid_strategies = ["newer", "larger", "smaller", "source", "dest", "rename"];
-            if !valid_strategies.contains(&self.conflict_resolve.as_str()) {
-                anyhow::bail!(
-                    "Invalid --conflict-resolve strategy '{}'. Valid options: {}",
-                    self.conflict_resolve,
-                    valid_strategies.join(", ")
-                );
-            }
-
             // Bidirectional conflicts with certain flags
             if self.verify_only {
                 anyhow::bail!("--bidirectional cannot be used with --verify-only (conflicts with sync logic)");
@@ -466,6 +1238,15 @@ impl Cli {
             if self.watch {
                 anyhow::bail!("--bidirectional with --watch is not yet supported (deferred to future version)");
             }
+            if self.allow_nested {
+                anyhow::bail!("--bidirectional cannot be used with --allow-nested");
+            }
+            if self.delay_updates {
+                anyhow::bail!("--bidirectional cannot be used with --delay-updates");
+            }
+            if self.link_dest.is_some() {
+                anyhow::bail!("--bidirectional cannot be used with --link-dest");
+            }
         }
 
         // --list-profiles and --show-profile don't need source/destination
@@ -473,14 +1254,64 @@ impl Cli {
             return Ok(());
         }
 
+        // --daemon serves the config file's modules instead of syncing
+        if self.daemon {
+            return Ok(());
+        }
+
         // If using --profile, source/destination come from profile (validated later)
         // Otherwise, source and destination must be provided
         if self.profile.is_none() && (self.source.is_none() || self.destination.is_none()) {
             anyhow::bail!("Source and destination are required (or use --profile)");
         }
 
+        if self.is_stdin_source() {
+            if self.watch || self.bidirectional || self.delete {
+                anyhow::bail!(
+                    "Reading from stdin (-) cannot be used with --watch, --bidirectional or --delete"
+                );
+            }
+            if self.include_from.as_deref() == Some("-")
+                || self.exclude_from.as_deref() == Some("-")
+            {
+                anyhow::bail!("stdin (-) can't be both the source and a pattern list");
+            }
+            if self
+                .destination
+                .as_ref()
+                .is_some_and(|d| d.is_local() && d.path().is_dir())
+            {
+                anyhow::bail!(
+                    "Reading from stdin (-) needs a destination file path, not a directory"
+                );
+            }
+        } else if self.size_hint.is_some() {
+            anyhow::bail!("--size-hint only applies when the source is - (stdin)");
+        }
+
+        if self.is_stdout_destination() {
+            if self.is_stdin_source() {
+                anyhow::bail!("The source and destination can't both be -");
+            }
+            if self.watch || self.bidirectional || self.delete || self.dry_run {
+                anyhow::bail!(
+                    "Writing to stdout (-) cannot be used with --watch, --bidirectional, --delete or --dry-run"
+                );
+            }
+            if self.json {
+                anyhow::bail!("Writing to stdout (-) cannot be used with --json");
+            }
+            if self
+                .source
+                .as_ref()
+                .is_some_and(|s| s.is_local() && s.path().is_dir())
+            {
+                anyhow::bail!("Writing to stdout (-) needs a source file, not a directory");
+            }
+        }
+
         // Only validate local source paths (remote paths are validated during connection)
-        if let Some(source) = &self.source {
+        if let Some(source) = self.source.as_ref().filter(|_| !self.is_stdin_source()) {
             if source.is_local() {
                 let path = source.path();
                 if !path.exists() {
@@ -489,6 +1320,36 @@ impl Cli {
             }
         }
 
+        // Destination symlinks can only be resolved on this machine
+        if self.sandbox_dest && self.destination.as_ref().is_some_and(|d| !d.is_local()) {
+            anyhow::bail!("--sandbox-dest requires a local destination");
+        }
+        if self.force_change && self.destination.as_ref().is_some_and(|d| !d.is_local()) {
+            anyhow::bail!("--force-change requires a local destination");
+        }
+        if self.sandbox_dest && self.keep_dirlinks {
+            anyhow::bail!("--sandbox-dest and --keep-dirlinks cannot be used together");
+        }
+        let link_flags = [
+            self.copy_links,
+            self.safe_links,
+            self.copy_unsafe_links,
+            self.munge_links,
+            self.unmunge_links,
+        ];
+        if link_flags.iter().filter(|&&flag| flag).count() > 1 {
+            anyhow::bail!(
+                "Only one of --copy-links, --safe-links, --copy-unsafe-links, --munge-links and --unmunge-links can be used"
+            );
+        }
+
+        // Placeholders are written through the destination's file API, which SSH lacks
+        if self.symlink_mode() == SymlinkMode::Munge
+            && self.destination.as_ref().is_some_and(|d| d.is_remote())
+        {
+            anyhow::bail!("--munge-links requires a local or S3 destination");
+        }
+
         Ok(())
     }
 
@@ -501,20 +1362,139 @@ impl Cli {
         }
     }
 
-    /// Get the effective symlink mode (applying --copy-links flag override)
+    /// Get the effective compression detection mode (applying --compress flag override)
+    pub fn compression_detection_mode(&self) -> CompressionDetection {
+        if self.compress {
+            CompressionDetection::Always
+        } else {
+            self.compression_detection
+        }
+    }
+
+    /// Get the effective symlink mode (applying --copy-links, --copy-unsafe-links,
+    /// --safe-links, --munge-links and --unmunge-links flag overrides)
     pub fn symlink_mode(&self) -> SymlinkMode {
         if self.copy_links {
             SymlinkMode::Follow
+        } else if self.copy_unsafe_links {
+            SymlinkMode::CopyUnsafe
+        } else if self.safe_links {
+            SymlinkMode::Safe
+        } else if self.munge_links {
+            SymlinkMode::Munge
+        } else if self.unmunge_links {
+            SymlinkMode::Unmunge
         } else {
             self.links
         }
     }
 
+    /// Get where deleted files go with --trash, None without it
+    pub fn trash_target(&self) -> Option<TrashTarget> {
+        if !self.trash {
+            return None;
+        }
+        match (&self.trash_dir, &self.destination) {
+            (Some(dir), _) => Some(TrashTarget::Dir(dir.clone())),
+            (None, Some(destination)) if destination.is_local() => Some(TrashTarget::Os),
+            (None, _) => Some(TrashTarget::Dir(TRASH_DIR.into())),
+        }
+    }
+
+    /// Get how destination entries with immutable flags are handled
+    pub fn immutable_mode(&self) -> ImmutableMode {
+        match &self.destination {
+            Some(destination) if destination.is_local() => {
+                if self.force_change {
+                    ImmutableMode::ForceChange
+                } else {
+                    ImmutableMode::Report
+                }
+            }
+            _ => ImmutableMode::Ignore,
+        }
+    }
+
+    /// Get the Windows naming rules to apply, if any
+    ///
+    /// Without `--windows-names`, local destinations on Windows or SMB shares
+    /// skip names they would reject.
+    pub fn windows_names(&self) -> Option<NameMode> {
+        self.windows_names.or_else(|| match &self.destination {
+            Some(destination) if destination.is_local() => {
+                crate::fs_util::needs_windows_names(destination.path()).then_some(NameMode::Skip)
+            }
+            _ => None,
+        })
+    }
+
+    /// Get how far writes are kept inside the destination root
+    pub fn dest_confinement(&self) -> DestConfinement {
+        match &self.destination {
+            Some(destination) if destination.is_local() => {
+                if self.sandbox_dest {
+                    DestConfinement::Strict
+                } else if self.keep_dirlinks {
+                    DestConfinement::Off
+                } else {
+                    DestConfinement::Links
+                }
+            }
+            _ => DestConfinement::Off,
+        }
+    }
+
+    /// Whole-file override: Some(true) for --whole-file, Some(false) for
+    /// --no-whole-file, None to let the transport choose
+    pub fn whole_file_mode(&self) -> Option<bool> {
+        match (self.whole_file, self.no_whole_file) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Get the error count that aborts the sync (0 = never abort)
+    pub fn max_errors(&self) -> usize {
+        if self.ignore_errors {
+            0
+        } else {
+            self.max_errors
+        }
+    }
+
+    /// Get the scan error handling mode
+    pub fn scan_error_mode(&self) -> ScanErrorMode {
+        if self.strict_scan {
+            ScanErrorMode::Strict
+        } else if self.ignore_scan_errors {
+            ScanErrorMode::Ignore
+        } else {
+            ScanErrorMode::Report
+        }
+    }
+
     /// Check if source is a file (not a directory)
     pub fn is_single_file(&self) -> bool {
+        self.is_stdin_source()
+            || self
+                .source
+                .as_ref()
+                .is_some_and(|s| s.is_local() && s.path().is_file())
+    }
+
+    /// Whether the source is `-`, reading one file's data from stdin
+    pub fn is_stdin_source(&self) -> bool {
         self.source
             .as_ref()
-            .is_some_and(|s| s.is_local() && s.path().is_file())
+            .is_some_and(|s| s.is_local() && s.path() == Path::new(STDIN_SOURCE))
+    }
+
+    /// Whether the destination is `-`, writing the source file's data to stdout
+    pub fn is_stdout_destination(&self) -> bool {
+        self.destination
+            .as_ref()
+            .is_some_and(|d| d.is_local() && d.path() == Path::new(STDOUT_DEST))
     }
 
     pub fn log_level(&self) -> tracing::Level {
@@ -530,7 +1510,6 @@ impl Cli {
     }
 
     /// Check if permissions should be preserved (archive mode or explicit flag)
-    #[allow(dead_code)] // Public API for permission preservation (planned feature)
     pub fn should_preserve_permissions(&self) -> bool {
         self.archive || self.preserve_permissions
     }
@@ -542,17 +1521,24 @@ impl Cli {
     }
 
     /// Check if group should be preserved (archive mode or explicit flag)
-    #[allow(dead_code)] // Public API for group preservation (planned feature)
     pub fn should_preserve_group(&self) -> bool {
         self.archive || self.preserve_group
     }
 
     /// Check if owner should be preserved (archive mode or explicit flag)
-    #[allow(dead_code)] // Public API for owner preservation (planned feature)
     pub fn should_preserve_owner(&self) -> bool {
         self.archive || self.preserve_owner
     }
 
+    /// Which of permissions, owner and group are copied
+    pub fn preserve_perms(&self) -> PreservePerms {
+        PreservePerms {
+            mode: self.should_preserve_permissions(),
+            owner: self.should_preserve_owner(),
+            group: self.should_preserve_group(),
+        }
+    }
+
     /// Check if device files should be preserved (archive mode or explicit flag)
     #[allow(dead_code)] // Public API for device preservation (planned feature)
     pub fn should_preserve_devices(&self) -> bool {
@@ -567,7 +1553,13 @@ impl Cli {
         if self.archive && !self.copy_links {
             true
         } else {
-            self.symlink_mode() == SymlinkMode::Preserve
+            matches!(
+                self.symlink_mode(),
+                SymlinkMode::Preserve
+                    | SymlinkMode::Safe
+                    | SymlinkMode::CopyUnsafe
+                    | SymlinkMode::Unmunge
+            )
         }
     }
 }
@@ -587,6 +1579,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -594,8 +1587,17 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             min_size: None,
             max_size: None,
             exclude: vec![],
@@ -604,9 +1606,18 @@ mod tests {
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -615,10 +1626,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -636,15 +1659,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
         };
         assert!(cli.validate().is_ok());
     }
@@ -656,6 +1709,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -663,8 +1717,17 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             min_size: None,
             max_size: None,
             exclude: vec![],
@@ -673,9 +1736,18 @@ mod tests {
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -684,10 +1756,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -705,15 +1789,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
         };
         let result = cli.validate();
         assert!(result.is_err());
@@ -731,6 +1845,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -738,17 +1853,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -757,10 +1890,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -778,15 +1923,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -807,6 +1982,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -814,17 +1990,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -833,10 +2027,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -854,15 +2060,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -876,6 +2112,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -883,17 +2120,35 @@ mod tests {
             verbose: 0,
             quiet: true,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -902,10 +2157,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -923,15 +2190,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -945,6 +2242,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -952,17 +2250,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -971,10 +2287,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -992,15 +2320,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1014,6 +2372,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1021,17 +2380,35 @@ mod tests {
             verbose: 1,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1040,10 +2417,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1061,15 +2450,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1083,6 +2502,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1090,17 +2510,35 @@ mod tests {
             verbose: 2,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1109,10 +2547,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1130,15 +2580,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1164,6 +2644,17 @@ mod tests {
         assert_eq!(parse_size("1G").unwrap(), 1024 * 1024 * 1024);
     }
 
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
     #[test]
     fn test_size_filter_validation() {
         let cli = Cli {
@@ -1171,6 +2662,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1178,17 +2670,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1197,10 +2707,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1218,15 +2740,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: Some(1024 * 1024), // 1MB
             max_size: Some(500 * 1024),  // 500KB (smaller than min)
         };
@@ -1243,6 +2795,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1250,17 +2803,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1269,10 +2840,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1290,15 +2873,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1312,6 +2925,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1319,17 +2933,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Fast, // Set to Fast
             verify: true,                 // But --verify flag should override
             resume: true,
@@ -1338,10 +2970,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1359,15 +3003,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1407,6 +3081,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1414,17 +3089,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1433,10 +3126,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1454,15 +3159,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1476,6 +3211,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1483,17 +3219,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1502,10 +3256,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Skip, // Should be overridden
             copy_links: true,         // Override to Follow
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1523,15 +3289,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1545,6 +3341,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1552,17 +3349,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1571,10 +3386,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Skip,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1592,15 +3419,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1608,12 +3465,13 @@ mod tests {
     }
 
     #[test]
-    fn test_archive_mode_enables_all_flags() {
+    fn test_symlink_mode_unsafe_links_overrides() {
         let cli = Cli {
             source: Some(SyncPath::Local(PathBuf::from("/tmp/src"))),
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1621,17 +3479,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1640,16 +3516,28 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: true,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
             preserve_owner: false,
             preserve_devices: false,
-            archive: true, // Archive mode enabled
+            archive: false,
             ignore_times: false,
             size_only: false,
             checksum: false,
@@ -1661,35 +3549,66 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
-
-        // Archive mode should enable all these flags
-        assert!(cli.should_preserve_permissions());
-        assert!(cli.should_preserve_times());
-        assert!(cli.should_preserve_group());
-        assert!(cli.should_preserve_owner());
-        assert!(cli.should_preserve_devices());
+        assert_eq!(cli.symlink_mode(), SymlinkMode::Safe);
         assert!(cli.should_preserve_symlinks());
+
+        let cli = Cli {
+            copy_unsafe_links: true,
+            ..cli
+        };
+        assert_eq!(cli.symlink_mode(), SymlinkMode::CopyUnsafe);
     }
 
     #[test]
-    fn test_individual_preserve_flags() {
+    fn test_dest_confinement() {
         let cli = Cli {
             source: Some(SyncPath::Local(PathBuf::from("/tmp/src"))),
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1697,17 +3616,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1716,11 +3653,23 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
-            preserve_permissions: true, // Only permissions enabled
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
+            preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
             preserve_owner: false,
@@ -1737,34 +3686,547 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
+        assert_eq!(cli.dest_confinement(), DestConfinement::Links);
 
-        // Only permissions should be enabled
-        assert!(cli.should_preserve_permissions());
-        assert!(!cli.should_preserve_times());
-        assert!(!cli.should_preserve_group());
-        assert!(!cli.should_preserve_owner());
-        assert!(!cli.should_preserve_devices());
+        let cli = Cli {
+            keep_dirlinks: true,
+            ..cli
+        };
+        assert_eq!(cli.dest_confinement(), DestConfinement::Off);
+
+        let cli = Cli {
+            keep_dirlinks: false,
+            sandbox_dest: true,
+            ..cli
+        };
+        assert_eq!(cli.dest_confinement(), DestConfinement::Strict);
+
+        // Destination symlinks can only be checked locally
+        let cli = Cli {
+            sandbox_dest: false,
+            destination: Some(SyncPath::parse("user@host:/dest")),
+            ..cli
+        };
+        assert_eq!(cli.dest_confinement(), DestConfinement::Off);
     }
 
     #[test]
-    fn test_explicit_flag_overrides_with_archive() {
+    fn test_stdin_source() {
+        let temp = tempfile::TempDir::new().unwrap();
         let cli = Cli {
-            source: Some(SyncPath::Local(PathBuf::from("/tmp/src"))),
+            source: Some(SyncPath::parse("-")),
+            destination: Some(SyncPath::Local(temp.path().join("db.sql"))),
+            dry_run: false,
+            diff: false,
+            exit_code_on_change: false,
+            delete: false,
+            delete_threshold: 50,
+            trash: false,
+            force_delete: false,
+            verbose: 0,
+            quiet: false,
+            perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
+            parallel: 10,
+            threads: 0,
+            max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
+            exclude: vec![],
+            include: vec![],
+            filter: vec![],
+            exclude_from: None,
+            include_from: None,
+            ignore_template: vec![],
+            no_global_ignore: false,
+            bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
+            compress: false,
+            compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
+            mode: VerificationMode::Standard,
+            verify: false,
+            resume: true,
+            checkpoint_files: 10,
+            checkpoint_bytes: 104857600,
+            clean_state: false,
+            links: SymlinkMode::Preserve,
+            copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
+            preserve_xattrs: false,
+            preserve_hardlinks: false,
+            preserve_acls: false,
+            preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
+            preserve_permissions: false,
+            preserve_times: false,
+            preserve_group: false,
+            preserve_owner: false,
+            preserve_devices: false,
+            archive: false,
+            ignore_times: false,
+            size_only: false,
+            checksum: false,
+            verify_only: false,
+            json: false,
+            watch: false,
+            no_hooks: false,
+            abort_on_hook_failure: false,
+            profile: None,
+            list_profiles: false,
+            show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
+            bidirectional: false,
+            conflict_resolve: ConflictResolution::Newer,
+            max_delete: 50,
+            clear_bisync_state: false,
+            use_cache: false,
+            clear_cache: false,
+            scan_journal: false,
+            checksum_db: false,
+            clear_checksum_db: false,
+            prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
+            min_size: None,
+            max_size: None,
+        };
+        assert!(cli.is_stdin_source());
+        assert!(cli.is_single_file());
+        assert!(cli.validate().is_ok());
+
+        let cli = Cli {
+            size_hint: Some(1024),
+            ..cli
+        };
+        assert!(cli.validate().is_ok());
+
+        let cli = Cli {
+            delete: true,
+            ..cli
+        };
+        assert!(cli.validate().is_err());
+
+        // A directory destination would need a name for the stream
+        let cli = Cli {
+            delete: false,
+            destination: Some(SyncPath::Local(temp.path().to_path_buf())),
+            ..cli
+        };
+        assert!(cli.validate().is_err());
+
+        // The hint only means something for stdin
+        let cli = Cli {
+            source: Some(SyncPath::Local(temp.path().to_path_buf())),
+            ..cli
+        };
+        assert!(!cli.is_stdin_source());
+        assert!(cli.validate().is_err());
+
+        // Writing to stdout takes a single file
+        let file = temp.path().join("db.sql");
+        std::fs::write(&file, "dump").unwrap();
+        let cli = Cli {
+            source: Some(SyncPath::Local(file)),
+            destination: Some(SyncPath::parse("-")),
+            size_hint: None,
+            ..cli
+        };
+        assert!(cli.is_stdout_destination());
+        assert!(cli.validate().is_ok());
+
+        let cli = Cli {
+            source: Some(SyncPath::Local(temp.path().to_path_buf())),
+            ..cli
+        };
+        assert!(cli.validate().is_err());
+
+        let cli = Cli {
+            source: Some(SyncPath::parse("-")),
+            ..cli
+        };
+        assert!(cli.validate().is_err());
+    }
+
+    #[test]
+    fn test_archive_mode_enables_all_flags() {
+        let cli = Cli {
+            source: Some(SyncPath::Local(PathBuf::from("/tmp/src"))),
+            destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
+            dry_run: false,
+            diff: false,
+            exit_code_on_change: false,
+            delete: false,
+            delete_threshold: 50,
+            trash: false,
+            force_delete: false,
+            verbose: 0,
+            quiet: false,
+            perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
+            parallel: 10,
+            threads: 0,
+            max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
+            exclude: vec![],
+            include: vec![],
+            filter: vec![],
+            exclude_from: None,
+            include_from: None,
+            ignore_template: vec![],
+            no_global_ignore: false,
+            bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
+            compress: false,
+            compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
+            mode: VerificationMode::Standard,
+            verify: false,
+            resume: true,
+            checkpoint_files: 10,
+            checkpoint_bytes: 104857600,
+            clean_state: false,
+            links: SymlinkMode::Preserve,
+            copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
+            preserve_xattrs: false,
+            preserve_hardlinks: false,
+            preserve_acls: false,
+            preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
+            preserve_permissions: false,
+            preserve_times: false,
+            preserve_group: false,
+            preserve_owner: false,
+            preserve_devices: false,
+            archive: true, // Archive mode enabled
+            ignore_times: false,
+            size_only: false,
+            checksum: false,
+            verify_only: false,
+            json: false,
+            watch: false,
+            no_hooks: false,
+            abort_on_hook_failure: false,
+            profile: None,
+            list_profiles: false,
+            show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
+            bidirectional: false,
+            conflict_resolve: ConflictResolution::Newer,
+            max_delete: 50,
+            clear_bisync_state: false,
+            use_cache: false,
+            clear_cache: false,
+            scan_journal: false,
+            checksum_db: false,
+            clear_checksum_db: false,
+            prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
+            min_size: None,
+            max_size: None,
+        };
+
+        // Archive mode should enable all these flags
+        assert!(cli.should_preserve_permissions());
+        assert!(cli.should_preserve_times());
+        assert!(cli.should_preserve_group());
+        assert!(cli.should_preserve_owner());
+        assert!(cli.should_preserve_devices());
+        assert!(cli.should_preserve_symlinks());
+        assert_eq!(
+            cli.preserve_perms(),
+            PreservePerms {
+                mode: true,
+                owner: true,
+                group: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_individual_preserve_flags() {
+        let cli = Cli {
+            source: Some(SyncPath::Local(PathBuf::from("/tmp/src"))),
+            destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
+            dry_run: false,
+            diff: false,
+            exit_code_on_change: false,
+            delete: false,
+            delete_threshold: 50,
+            trash: false,
+            force_delete: false,
+            verbose: 0,
+            quiet: false,
+            perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
+            parallel: 10,
+            threads: 0,
+            max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
+            exclude: vec![],
+            include: vec![],
+            filter: vec![],
+            exclude_from: None,
+            include_from: None,
+            ignore_template: vec![],
+            no_global_ignore: false,
+            bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
+            compress: false,
+            compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
+            mode: VerificationMode::Standard,
+            verify: false,
+            resume: true,
+            checkpoint_files: 10,
+            checkpoint_bytes: 104857600,
+            clean_state: false,
+            links: SymlinkMode::Preserve,
+            copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
+            preserve_xattrs: false,
+            preserve_hardlinks: false,
+            preserve_acls: false,
+            preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
+            preserve_permissions: true, // Only permissions enabled
+            preserve_times: false,
+            preserve_group: false,
+            preserve_owner: false,
+            preserve_devices: false,
+            archive: false,
+            ignore_times: false,
+            size_only: false,
+            checksum: false,
+            verify_only: false,
+            json: false,
+            watch: false,
+            no_hooks: false,
+            abort_on_hook_failure: false,
+            profile: None,
+            list_profiles: false,
+            show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
+            bidirectional: false,
+            conflict_resolve: ConflictResolution::Newer,
+            max_delete: 50,
+            clear_bisync_state: false,
+            use_cache: false,
+            clear_cache: false,
+            scan_journal: false,
+            checksum_db: false,
+            clear_checksum_db: false,
+            prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
+            min_size: None,
+            max_size: None,
+        };
+
+        // Only permissions should be enabled
+        assert!(cli.should_preserve_permissions());
+        assert!(!cli.should_preserve_times());
+        assert!(!cli.should_preserve_group());
+        assert!(!cli.should_preserve_owner());
+        assert!(!cli.should_preserve_devices());
+        assert!(!cli.preserve_perms().owner);
+    }
+
+    #[test]
+    fn test_explicit_flag_overrides_with_archive() {
+        let cli = Cli {
+            source: Some(SyncPath::Local(PathBuf::from("/tmp/src"))),
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1772,17 +4234,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1791,10 +4271,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: true, // Explicit flag also enabled
             preserve_times: false,
             preserve_group: false,
@@ -1812,15 +4304,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1841,6 +4363,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1848,17 +4371,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1867,10 +4408,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1888,19 +4441,187 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
+            bidirectional: false,
+            conflict_resolve: ConflictResolution::Newer,
+            max_delete: 50,
+            clear_bisync_state: false,
+            use_cache: false,
+            clear_cache: false,
+            scan_journal: false,
+            checksum_db: false,
+            clear_checksum_db: false,
+            prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
+            min_size: None,
+            max_size: None,
+        };
+
+        let result = cli.validate();
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("mutually exclusive"));
+    }
+
+    #[test]
+    fn test_scan_error_flags_mutually_exclusive() {
+        let cli = Cli {
+            source: Some(SyncPath::Local(PathBuf::from("/tmp/src"))),
+            destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
+            dry_run: false,
+            diff: false,
+            exit_code_on_change: false,
+            delete: false,
+            delete_threshold: 50,
+            trash: false,
+            force_delete: false,
+            verbose: 0,
+            quiet: false,
+            perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
+            parallel: 10,
+            threads: 0,
+            max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
+            exclude: vec![],
+            include: vec![],
+            filter: vec![],
+            exclude_from: None,
+            include_from: None,
+            ignore_template: vec![],
+            no_global_ignore: false,
+            bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
+            compress: false,
+            compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
+            mode: VerificationMode::Standard,
+            verify: false,
+            resume: true,
+            checkpoint_files: 10,
+            checkpoint_bytes: 104857600,
+            clean_state: false,
+            links: SymlinkMode::Preserve,
+            copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
+            preserve_xattrs: false,
+            preserve_hardlinks: false,
+            preserve_acls: false,
+            preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
+            preserve_permissions: false,
+            preserve_times: false,
+            preserve_group: false,
+            preserve_owner: false,
+            preserve_devices: false,
+            archive: false,
+            ignore_times: false,
+            size_only: false,
+            checksum: false,
+            verify_only: false,
+            json: false,
+            watch: false,
+            no_hooks: false,
+            abort_on_hook_failure: false,
+            profile: None,
+            list_profiles: false,
+            show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: true, // Both enabled - should fail
+            strict_scan: true,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
 
+        assert_eq!(cli.scan_error_mode(), ScanErrorMode::Strict);
+
         let result = cli.validate();
         assert!(result.is_err());
         assert!(result
@@ -1917,6 +4638,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1924,17 +4646,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -1943,10 +4683,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -1964,15 +4716,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1990,6 +4772,7 @@ mod tests {
             destination: Some(SyncPath::Local(PathBuf::from("/tmp/dest"))),
             dry_run: false,
             diff: false,
+            exit_code_on_change: false,
             delete: false,
             delete_threshold: 50,
             trash: false,
@@ -1997,17 +4780,35 @@ mod tests {
             verbose: 0,
             quiet: false,
             perf: false,
+            trace_out: None,
+            otlp_endpoint: None,
             parallel: 10,
+            threads: 0,
             max_errors: 100,
+            ignore_errors: false,
+            retry_failed: 1,
+            why: false,
+            size_hint: None,
+            fault_inject: None,
+            priority_pattern: Vec::new(),
             exclude: vec![],
             include: vec![],
             filter: vec![],
             exclude_from: None,
             include_from: None,
             ignore_template: vec![],
+            no_global_ignore: false,
             bwlimit: None,
+            bwlimit_per_file: None,
+            dest_quota: None,
+            quota_mode: QuotaMode::Stop,
+            windows_names: None,
             compress: false,
             compression_detection: CompressionDetection::Auto,
+            compression_probe_size: 64 * 1024,
+            remote_binary: None,
+            sandbox_dest: false,
+            keep_dirlinks: false,
             mode: VerificationMode::Standard,
             verify: false,
             resume: true,
@@ -2016,10 +4817,22 @@ mod tests {
             clean_state: false,
             links: SymlinkMode::Preserve,
             copy_links: false,
+            safe_links: false,
+            copy_unsafe_links: false,
+            munge_links: false,
+            unmunge_links: false,
             preserve_xattrs: false,
             preserve_hardlinks: false,
             preserve_acls: false,
             preserve_flags: false,
+            force_change: false,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            open_noatime: false,
+            inplace: false,
+            whole_file: false,
+            no_whole_file: false,
+            snapshot_source: None,
             preserve_permissions: false,
             preserve_times: false,
             preserve_group: false,
@@ -2037,15 +4850,45 @@ mod tests {
             profile: None,
             list_profiles: false,
             show_profile: None,
+            ssh_options: Vec::new(),
+            ssh_command: None,
+            allow_nested: false,
+            delay_updates: false,
+            link_dest: None,
+            numeric_ids: false,
+            ssh_backend: SshBackend::Libssh2,
+            chunk_size: None,
+            streams: 1,
+            trash_dir: None,
+            refresh_host_info: false,
+            resume_force: false,
+            daemon: false,
+            address: "0.0.0.0".to_string(),
+            port: 8730,
+            quic: false,
+            quic_cert: None,
             bidirectional: false,
-            conflict_resolve: "newer".to_string(),
+            conflict_resolve: ConflictResolution::Newer,
             max_delete: 50,
             clear_bisync_state: false,
             use_cache: false,
             clear_cache: false,
+            scan_journal: false,
             checksum_db: false,
             clear_checksum_db: false,
             prune_checksum_db: false,
+            ignore_scan_errors: false,
+            strict_scan: false,
+            specials: SpecialsMode::Warn,
+            dangling_links: DanglingMode::Preserve,
+            max_depth: None,
+            state_dir: None,
+            command: None,
+            no_lock: false,
+            skip_busy: false,
+            wait_busy: None,
+            skip_hot: None,
+            acl_map: AclMapPolicy::default(),
             min_size: None,
             max_size: None,
         };
@@ -1,10 +1,48 @@
-use std::fs::File;
-use std::io::{self, Read, Write};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Uncompressed bytes (de)compressed by this process and CPU time spent doing it
+static CODEC_BYTES: AtomicU64 = AtomicU64::new(0);
+static CODEC_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Compression work done so far, for `--perf`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub bytes: u64,
+    pub duration: Duration,
+}
+
+impl CompressionStats {
+    /// Work done between `earlier` and this snapshot
+    pub fn since(&self, earlier: &CompressionStats) -> CompressionStats {
+        CompressionStats {
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+            duration: self.duration.saturating_sub(earlier.duration),
+        }
+    }
+}
+
+/// Snapshot of the process-wide compression counters
+pub fn compression_stats() -> CompressionStats {
+    CompressionStats {
+        bytes: CODEC_BYTES.load(Ordering::Relaxed),
+        duration: Duration::from_nanos(CODEC_NANOS.load(Ordering::Relaxed)),
+    }
+}
+
+fn record_codec(bytes: usize, started: Instant) {
+    CODEC_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+    CODEC_NANOS.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+}
 
 /// Compression algorithm
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Compression {
     None,
     /// LZ4: 23 GB/s, lower compression ratio (good for low-CPU scenarios)
@@ -39,21 +77,42 @@ impl Compression {
 
 /// Compress data
 pub fn compress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
-    match compression {
-        Compression::None => Ok(data.to_vec()),
+    let started = Instant::now();
+    let compressed = match compression {
+        Compression::None => return Ok(data.to_vec()),
         Compression::Lz4 => compress_lz4(data),
         Compression::Zstd => compress_zstd(data),
-    }
+    }?;
+    record_codec(data.len(), started);
+    Ok(compressed)
 }
 
-/// Decompress data (used by sy-remote binary)
-#[allow(dead_code)] // Used by sy-remote binary, not library code
+/// Decompress data (used by sy-remote and SSH downloads)
 pub fn decompress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
-    match compression {
-        Compression::None => Ok(data.to_vec()),
+    let started = Instant::now();
+    let decompressed = match compression {
+        Compression::None => return Ok(data.to_vec()),
         Compression::Lz4 => decompress_lz4(data),
         Compression::Zstd => decompress_zstd(data),
-    }
+    }?;
+    record_codec(decompressed.len(), started);
+    Ok(decompressed)
+}
+
+/// Zstd-compress `input` into `output` as it is read, so a file of any size
+/// goes through a fixed-size buffer; returns the bytes read
+pub fn compress_stream(input: &mut impl Read, output: impl Write) -> io::Result<u64> {
+    let started = Instant::now();
+    let mut encoder = zstd::Encoder::new(output, 3)?;
+    let bytes = io::copy(input, &mut encoder)?;
+    encoder.finish()?;
+    record_codec(bytes as usize, started);
+    Ok(bytes)
+}
+
+/// Reader decompressing the zstd stream read from `input`
+pub fn decompress_stream<'a, R: io::BufRead>(input: R) -> io::Result<zstd::Decoder<'a, R>> {
+    zstd::Decoder::with_buffer(input)
 }
 
 fn compress_lz4(data: &[u8]) -> io::Result<Vec<u8>> {
@@ -61,7 +120,6 @@ fn compress_lz4(data: &[u8]) -> io::Result<Vec<u8>> {
     Ok(lz4_flex::compress_prepend_size(data))
 }
 
-#[allow(dead_code)] // Called by decompress() which is used by sy-remote
 fn decompress_lz4(data: &[u8]) -> io::Result<Vec<u8>> {
     lz4_flex::decompress_size_prepended(data)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
@@ -74,7 +132,6 @@ fn compress_zstd(data: &[u8]) -> io::Result<Vec<u8>> {
     encoder.finish()
 }
 
-#[allow(dead_code)] // Called by decompress() which is used by sy-remote
 fn decompress_zstd(data: &[u8]) -> io::Result<Vec<u8>> {
     let mut decoder = zstd::Decoder::new(data)?;
     let mut result = Vec::new();
@@ -96,7 +153,8 @@ const COMPRESSED_EXTENSIONS: &[&str] = &[
 
 /// Check if file extension indicates already-compressed data
 pub fn is_compressed_extension(filename: &str) -> bool {
-    if let Some(ext) = filename.rsplit('.').next() {
+    // Extensionless names (`README`, or a file literally called `zip`) have no format hint
+    if let Some((_, ext)) = filename.rsplit_once('.') {
         COMPRESSED_EXTENSIONS
             .iter()
             .any(|&e| ext.eq_ignore_ascii_case(e))
@@ -151,33 +209,81 @@ pub fn should_compress(filename: &str, file_size: u64) -> Compression {
     should_compress_adaptive(filename, file_size, false, None)
 }
 
-/// Detect file compressibility by sampling first 64KB with LZ4
+/// Default size of each content probe (`--compression-probe-size`)
+pub const DEFAULT_PROBE_SIZE: usize = 64 * 1024;
+
+/// Probes sampled per file: start, middle and end
+const PROBE_COUNT: u64 = 3;
+
+/// Detect file compressibility by sampling it with LZ4
 ///
 /// Returns compression ratio (compressed_size / original_size)
 /// - Ratio < 0.9 means compressible (>10% savings)
 /// - Ratio >= 0.9 means incompressible (<10% savings)
 ///
 /// Uses LZ4 for fast testing (23 GB/s throughput)
-/// Inspired by BorgBackup's auto-compression heuristic
-pub fn detect_compressibility(file_path: &Path) -> io::Result<f64> {
-    const SAMPLE_SIZE: usize = 64 * 1024; // 64KB sample
+/// Inspired by BorgBackup's auto-compression heuristic. `noatime` opens the
+/// file with `O_NOATIME` (--open-noatime).
+#[allow(dead_code)] // Public API; compression_decision samples with a set probe size
+pub fn detect_compressibility(file_path: &Path, noatime: bool) -> io::Result<f64> {
+    sample_compressibility(file_path, DEFAULT_PROBE_SIZE, noatime)
+}
 
-    let mut file = File::open(file_path)?;
-    let mut buffer = vec![0u8; SAMPLE_SIZE];
-    let bytes_read = file.read(&mut buffer)?;
+/// Compressibility of up to three `probe_size` probes (start, middle, end)
+///
+/// A single probe at the start is fooled by files whose header differs from
+/// the body, such as archives with text headers or extensionless files with
+/// compressed payloads. The median probe ratio is returned, so one
+/// unrepresentative region doesn't decide for the whole file.
+pub fn sample_compressibility(
+    file_path: &Path,
+    probe_size: usize,
+    noatime: bool,
+) -> io::Result<f64> {
+    let mut file = crate::fs_util::open_source(file_path, noatime)?;
+    let len = file.metadata()?.len();
+    let probe = probe_size as u64;
+
+    // Small files are read once from the start
+    let last = len.saturating_sub(probe);
+    let mut offsets: Vec<u64> = (0..PROBE_COUNT)
+        .map(|i| last * i / (PROBE_COUNT - 1))
+        .collect();
+    offsets.dedup();
+
+    let mut buffer = vec![0u8; probe_size];
+    let mut ratios = Vec::with_capacity(offsets.len());
+    for offset in offsets {
+        file.seek(SeekFrom::Start(offset))?;
+        let bytes_read = read_full(&mut file, &mut buffer)?;
+        if bytes_read == 0 {
+            continue;
+        }
+        let compressed = compress_lz4(&buffer[..bytes_read])?;
+        ratios.push(compressed.len() as f64 / bytes_read as f64);
+    }
 
     // Empty file or very small file
-    if bytes_read == 0 {
+    if ratios.is_empty() {
         return Ok(1.0); // No benefit
     }
 
-    let sample = &buffer[..bytes_read];
-    let compressed = compress_lz4(sample)?;
-
-    // Calculate compression ratio
-    let ratio = compressed.len() as f64 / sample.len() as f64;
+    ratios.sort_by(f64::total_cmp);
+    Ok(ratios[ratios.len() / 2])
+}
 
-    Ok(ratio)
+/// Read until `buf` is full or EOF, returning the bytes read
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
 }
 
 /// Compression detection mode
@@ -202,9 +308,116 @@ impl Default for CompressionDetection {
     }
 }
 
+impl CompressionDetection {
+    /// Name as accepted by `--compression-detection`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Extension => "extension",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+}
+
+/// Why [`compression_decision`] picked a codec for a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionReason {
+    /// Local transfer (disk I/O is the bottleneck, not network/CPU)
+    Local,
+    /// `--compression-detection always` (or `--compress`)
+    Forced,
+    /// `--compression-detection never`
+    Disabled,
+    /// Under 1MB, where overhead outweighs the savings
+    TooSmall,
+    /// Known compressed format (jpg, mp4, zip, ...)
+    CompressedExtension,
+    /// `--compression-detection extension` and not a known compressed format
+    Extension,
+    /// Sampled content compresses by more than 10%
+    Compressible,
+    /// Sampled content compresses by 10% or less
+    Incompressible,
+    /// Content couldn't be sampled, so compression is attempted anyway
+    SampleFailed,
+    /// Only the file name was available, so compression is attempted anyway
+    NoSample,
+}
+
+impl CompressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Forced => "forced",
+            Self::Disabled => "disabled",
+            Self::TooSmall => "too_small",
+            Self::CompressedExtension => "compressed_extension",
+            Self::Extension => "extension",
+            Self::Compressible => "compressible",
+            Self::Incompressible => "incompressible",
+            Self::SampleFailed => "sample_failed",
+            Self::NoSample => "no_sample",
+        }
+    }
+}
+
+/// Codec chosen for one file and why (`-v` logs and `--json` file events)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompressionDecision {
+    pub compression: Compression,
+    pub reason: CompressionReason,
+    /// Compressed/original size of the sampled content (auto mode only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_ratio: Option<f64>,
+}
+
+impl CompressionDecision {
+    fn new(compression: Compression, reason: CompressionReason) -> Self {
+        Self {
+            compression,
+            reason,
+            sample_ratio: None,
+        }
+    }
+}
+
+impl fmt::Display for CompressionDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.compression.as_str(), self.reason.as_str())?;
+        if let Some(ratio) = self.sample_ratio {
+            write!(f, " (sample ratio {:.2})", ratio)?;
+        }
+        Ok(())
+    }
+}
+
 /// Smart compression detection using content sampling
 ///
-/// This function extends should_compress_adaptive() with content-based detection
+/// Shorthand for [`compression_decision`] when only the codec matters.
+pub fn should_compress_smart(
+    file_path: Option<&Path>,
+    filename: &str,
+    file_size: u64,
+    is_local: bool,
+    detection_mode: CompressionDetection,
+) -> Compression {
+    compression_decision(
+        file_path,
+        filename,
+        file_size,
+        is_local,
+        detection_mode,
+        DEFAULT_PROBE_SIZE,
+        false,
+    )
+    .compression
+}
+
+/// Pick a codec for one file, recording the reason
+///
+/// This extends should_compress_adaptive() with content-based detection
 /// for improved accuracy. It follows BorgBackup's proven approach of sampling
 /// file content to determine compressibility.
 ///
@@ -214,67 +427,82 @@ impl Default for CompressionDetection {
 /// * `file_size` - Size in bytes
 /// * `is_local` - Whether this is a local transfer
 /// * `detection_mode` - Detection mode (Auto, Extension, Always, Never)
+/// * `probe_size` - Bytes read by each content probe (`--compression-probe-size`)
+/// * `noatime` - Sample the file without updating its access time
 ///
 /// # Detection Strategy
 /// 1. Fast path: Skip if local transfer, small file, or known compressed extension
-/// 2. Content sampling: Read first 64KB, test with LZ4, measure ratio
+/// 2. Content sampling: Read `probe_size` probes at the start, middle and end,
+///    test with LZ4, measure ratio
 /// 3. Decision: Ratio <0.9 → compress with Zstd, ≥0.9 → skip compression
-pub fn should_compress_smart(
+pub fn compression_decision(
     file_path: Option<&Path>,
     filename: &str,
     file_size: u64,
     is_local: bool,
     detection_mode: CompressionDetection,
-) -> Compression {
+    probe_size: usize,
+    noatime: bool,
+) -> CompressionDecision {
+    use CompressionReason as Reason;
+
     // LOCAL: Never compress (disk I/O is bottleneck, not network/CPU)
     if is_local {
-        return Compression::None;
+        return CompressionDecision::new(Compression::None, Reason::Local);
     }
 
     // Handle explicit overrides
     match detection_mode {
-        CompressionDetection::Always => return Compression::Zstd,
-        CompressionDetection::Never => return Compression::None,
+        CompressionDetection::Always => {
+            return CompressionDecision::new(Compression::Zstd, Reason::Forced)
+        }
+        CompressionDetection::Never => {
+            return CompressionDecision::new(Compression::None, Reason::Disabled)
+        }
         _ => {} // Continue with detection
     }
 
     // Skip small files (overhead > benefit)
     if file_size < 1024 * 1024 {
-        return Compression::None;
+        return CompressionDecision::new(Compression::None, Reason::TooSmall);
     }
 
     // Skip known compressed extensions (fast path)
     if is_compressed_extension(filename) {
-        return Compression::None;
+        return CompressionDecision::new(Compression::None, Reason::CompressedExtension);
     }
 
     // Extension-only mode (legacy behavior)
     if detection_mode == CompressionDetection::Extension {
-        return Compression::Zstd;
+        return CompressionDecision::new(Compression::Zstd, Reason::Extension);
     }
 
     // Content sampling (auto mode)
     // This is the new smart detection that tests actual compressibility
-    if let Some(path) = file_path {
-        match detect_compressibility(path) {
-            Ok(ratio) if ratio < 0.9 => {
-                // Compressible: >10% savings achieved
-                Compression::Zstd
-            }
-            Ok(_ratio) => {
-                // Incompressible: <10% savings, not worth CPU overhead
-                Compression::None
-            }
-            Err(_) => {
-                // Error reading file, fall back to trying compression
-                // Better to compress and waste some CPU than skip and lose bandwidth
-                Compression::Zstd
-            }
-        }
-    } else {
+    let Some(path) = file_path else {
         // No file path available, fall back to extension-based heuristic
         // This happens when we only have filename/size but not actual file
-        Compression::Zstd
+        return CompressionDecision::new(Compression::Zstd, Reason::NoSample);
+    };
+    match sample_compressibility(path, probe_size, noatime) {
+        Ok(ratio) => {
+            // Compressible: >10% savings achieved; otherwise not worth CPU overhead
+            let (compression, reason) = if ratio < 0.9 {
+                (Compression::Zstd, Reason::Compressible)
+            } else {
+                (Compression::None, Reason::Incompressible)
+            };
+            CompressionDecision {
+                compression,
+                reason,
+                sample_ratio: Some(ratio),
+            }
+        }
+        Err(_) => {
+            // Error reading file, fall back to trying compression
+            // Better to compress and waste some CPU than skip and lose bandwidth
+            CompressionDecision::new(Compression::Zstd, Reason::SampleFailed)
+        }
     }
 }
 
@@ -282,6 +510,27 @@ pub fn should_compress_smart(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_compress_stream() {
+        let original = b"Streamed through a fixed-size buffer. ".repeat(10_000);
+        let mut compressed = Vec::new();
+        let read = compress_stream(&mut &original[..], &mut compressed).unwrap();
+        assert_eq!(read, original.len() as u64);
+        assert!(compressed.len() < original.len());
+
+        // A one-shot zstd frame decodes the same way
+        assert_eq!(
+            decompress(&compressed, Compression::Zstd).unwrap(),
+            original
+        );
+        let mut decompressed = Vec::new();
+        decompress_stream(&compressed[..])
+            .unwrap()
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, original);
+    }
+
     #[test]
     fn test_compress_decompress_lz4() {
         let original = b"Hello, world! This is a test of LZ4 compression. ".repeat(100);
@@ -343,6 +592,10 @@ mod tests {
         assert!(!is_compressed_extension("file.txt"));
         assert!(!is_compressed_extension("code.rs"));
         assert!(!is_compressed_extension("data.csv"));
+
+        // No extension, even when the name looks like one
+        assert!(!is_compressed_extension("zip"));
+        assert!(!is_compressed_extension("Makefile"));
     }
 
     #[test]
@@ -465,7 +718,7 @@ mod tests {
         temp_file.write_all(repetitive_text.as_bytes()).unwrap();
         temp_file.flush().unwrap();
 
-        let ratio = detect_compressibility(temp_file.path()).unwrap();
+        let ratio = detect_compressibility(temp_file.path(), false).unwrap();
 
         // Repetitive text should compress very well (ratio should be < 0.5)
         assert!(ratio < 0.5, "Ratio: {}", ratio);
@@ -489,13 +742,42 @@ mod tests {
         temp_file.write_all(&random_data).unwrap();
         temp_file.flush().unwrap();
 
-        let ratio = detect_compressibility(temp_file.path()).unwrap();
+        let ratio = detect_compressibility(temp_file.path(), false).unwrap();
 
         // High-entropy data should not compress well (ratio should be > 0.85)
         // Note: Even good pseudo-random may compress slightly, so we use 0.85 threshold
         assert!(ratio > 0.85, "Ratio: {}", ratio);
     }
 
+    #[test]
+    fn test_sample_compressibility_probes_whole_file() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        // Text header in front of a high-entropy body (like an archive)
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&b"header text ".repeat(6_000)).unwrap();
+        let body: Vec<u8> = (0u32..2_000_000)
+            .map(|i| {
+                let x = i.wrapping_mul(2654435761);
+                ((x ^ (x >> 16)) & 0xFF) as u8
+            })
+            .collect();
+        temp_file.write_all(&body).unwrap();
+        temp_file.flush().unwrap();
+
+        // The start alone looks compressible; the middle and end don't
+        let probe = 64 * 1024;
+        let start = compress_lz4(&std::fs::read(temp_file.path()).unwrap()[..probe]).unwrap();
+        assert!((start.len() as f64 / probe as f64) < 0.9);
+        let ratio = sample_compressibility(temp_file.path(), probe, false).unwrap();
+        assert!(ratio > 0.85, "Ratio: {}", ratio);
+
+        // A probe larger than the file reads it once
+        let ratio = sample_compressibility(temp_file.path(), 4 * 1024 * 1024, false).unwrap();
+        assert!(ratio > 0.0 && ratio <= 1.1, "Ratio: {}", ratio);
+    }
+
     #[test]
     fn test_detect_compressibility_empty() {
         use tempfile::NamedTempFile;
@@ -503,7 +785,7 @@ mod tests {
         // Create an empty file
         let temp_file = NamedTempFile::new().unwrap();
 
-        let ratio = detect_compressibility(temp_file.path()).unwrap();
+        let ratio = detect_compressibility(temp_file.path(), false).unwrap();
 
         // Empty file should return 1.0 (no benefit)
         assert_eq!(ratio, 1.0);
@@ -669,4 +951,47 @@ mod tests {
         // Should default to compressing when path not available
         assert_eq!(result, Compression::Zstd);
     }
+
+    #[test]
+    fn test_compression_decision_reasons() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let decide = |path, name, size, mode| {
+            compression_decision(path, name, size, false, mode, DEFAULT_PROBE_SIZE, false)
+        };
+        assert_eq!(
+            decide(None, "a.txt", 100, CompressionDetection::Auto).reason,
+            CompressionReason::TooSmall
+        );
+        assert_eq!(
+            decide(None, "a.zip", 10_000_000, CompressionDetection::Auto).reason,
+            CompressionReason::CompressedExtension
+        );
+        assert_eq!(
+            decide(None, "a.zip", 100, CompressionDetection::Always).reason,
+            CompressionReason::Forced
+        );
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all("Compressible text data! ".repeat(50000).as_bytes())
+            .unwrap();
+        temp_file.flush().unwrap();
+        let decision = decide(
+            Some(temp_file.path()),
+            "a.txt",
+            1_200_000,
+            CompressionDetection::Auto,
+        );
+        assert_eq!(decision.compression, Compression::Zstd);
+        assert_eq!(decision.reason, CompressionReason::Compressible);
+        assert!(decision.sample_ratio.unwrap() < 0.9);
+
+        let json = serde_json::to_string(&decision).unwrap();
+        assert!(json.contains(r#""compression":"zstd","reason":"compressible","sample_ratio":"#));
+        assert!(decision
+            .to_string()
+            .starts_with("zstd, compressible (sample ratio 0."));
+    }
 }
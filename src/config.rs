@@ -10,6 +10,9 @@ pub struct Config {
     pub defaults: Defaults,
     #[serde(default)]
     pub profiles: HashMap<String, Profile>,
+    /// Directory trees served by `sy --daemon`, by module name
+    #[serde(default)]
+    pub modules: HashMap<String, DaemonModule>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -34,6 +37,20 @@ pub struct Profile {
     pub dry_run: Option<bool>,
     pub quiet: Option<bool>,
     pub verbose: Option<u8>,
+    /// sy-remote path on the remote host (--remote-binary)
+    pub remote_binary: Option<String>,
+}
+
+/// A directory tree served by `sy --daemon`, reached as `host::NAME/path`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DaemonModule {
+    pub path: PathBuf,
+    /// Refuse every write, so the module can only be synced from
+    #[serde(default)]
+    pub read_only: bool,
+    /// Users allowed in, with their secrets; anyone may connect if empty
+    #[serde(default)]
+    pub users: HashMap<String, String>,
 }
 
 impl Config {
@@ -107,6 +124,7 @@ delete = true
 exclude = ["*.log"]
 bwlimit = "10MB"
 resume = true
+remote_binary = "/opt/sy/sy-remote"
         "#;
 
         let config: Config = toml::from_str(toml).unwrap();
@@ -123,6 +141,7 @@ resume = true
         assert_eq!(profile.delete, Some(true));
         assert_eq!(profile.bwlimit, Some("10MB".to_string()));
         assert_eq!(profile.resume, Some(true));
+        assert_eq!(profile.remote_binary, Some("/opt/sy/sy-remote".to_string()));
     }
 
     #[test]
@@ -145,6 +164,30 @@ source = "~/c"
         assert_eq!(profiles, vec!["profile-a", "profile-b", "profile-c"]);
     }
 
+    #[test]
+    fn test_parse_modules() {
+        let toml = r#"
+[modules.photos]
+path = "/srv/photos"
+read_only = true
+
+[modules.backups]
+path = "/srv/backups"
+users = { alice = "hunter2" }
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+
+        let photos = &config.modules["photos"];
+        assert_eq!(photos.path, PathBuf::from("/srv/photos"));
+        assert!(photos.read_only);
+        assert!(photos.users.is_empty());
+
+        let backups = &config.modules["backups"];
+        assert!(!backups.read_only);
+        assert_eq!(backups.users["alice"], "hunter2");
+    }
+
     #[test]
     fn test_get_profile_missing() {
         let config = Config::default();
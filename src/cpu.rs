@@ -0,0 +1,74 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::OnceLock;
+
+/// Thread pool for CPU-heavy work: checksum verification, compression and
+/// delta block hashing
+///
+/// Kept apart from the `--parallel` transfer slots and tokio's blocking
+/// pool, so hashing a large file can't hold up the file I/O of other
+/// transfers on a small machine.
+static POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+
+/// Size the pool (`--threads`); call once at startup, before any CPU work
+///
+/// Without it, or when called too late, the pool has one thread per core.
+pub fn set_threads(threads: usize) {
+    if POOL.set(build(threads)).is_err() {
+        tracing::debug!("CPU thread pool already started, ignoring --threads");
+    }
+}
+
+fn pool() -> &'static rayon::ThreadPool {
+    POOL.get_or_init(|| build(0))
+}
+
+/// A pool of `threads` threads, one per core if 0
+fn build(threads: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("sy-cpu-{}", i))
+        .build()
+        .expect("failed to start CPU thread pool")
+}
+
+/// Run `work` on the pool from blocking code and wait for it
+///
+/// Parallel iterators inside `work` stay on the pool too.
+pub fn install<T: Send>(work: impl FnOnce() -> T + Send) -> T {
+    pool().install(work)
+}
+
+/// Run `work` on the pool from async code, without tying up a runtime thread
+pub async fn run<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    pool().spawn(move || {
+        let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(work)));
+    });
+    match rx.await.expect("CPU pool dropped a task") {
+        Ok(result) => result,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_on_pool() {
+        let name = run(|| std::thread::current().name().map(str::to_string)).await;
+        assert!(name.unwrap().starts_with("sy-cpu-"));
+
+        let sum = install(|| {
+            use rayon::prelude::*;
+            (1..=100u64).into_par_iter().sum::<u64>()
+        });
+        assert_eq!(sum, 5050);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "boom")]
+    async fn test_run_propagates_panics() {
+        run(|| panic!("boom")).await
+    }
+}
@@ -0,0 +1,688 @@
+//! Native sy protocol over TCP (`sy --daemon`, `sy host::module/path`)
+//!
+//! Like rsyncd, the daemon serves named modules, each a directory tree from
+//! the `[modules]` table of the config file, so LAN backups don't pay for SSH
+//! encryption or need a login shell. Every message is a big-endian `u32`
+//! length followed by a binary-encoded [`Request`] or [`Response`]; file
+//! contents follow a `Put` request or a `File` response as raw bytes.
+//!
+//! A connection starts with `Hello`, naming the module. Modules with users
+//! answer with a nonce, and the client proves it knows the user's secret
+//! with a keyed BLAKE3 hash of it, so the secret never crosses the network.
+//! Nothing else is encrypted: use SSH across untrusted networks, or the same
+//! protocol over QUIC (see [`crate::quic`]).
+
+use crate::config::DaemonModule;
+use crate::error::{Result, SyncError};
+use crate::owner::NamedPerms;
+use crate::ssh::restrict::Restriction;
+use crate::sync::lock::LockInfo;
+use crate::transport::local::LocalTransport;
+use crate::transport::{PathStat, Transport};
+use crate::wire::{self, ScanOutput, WireFileEntry, WireFormat, WireScanError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// TCP port `sy --daemon` listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 8730;
+
+/// Version of the request and response set, checked in `Hello`
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Environment variable the client reads the user's secret from
+pub const SECRET_ENV: &str = "SY_DAEMON_SECRET";
+
+/// Messages larger than this are treated as corrupt
+const MAX_MESSAGE_SIZE: u32 = 256 * 1024 * 1024;
+
+/// Limit for `Hello` and `Auth`, which arrive before the client is trusted
+const MAX_HANDSHAKE_SIZE: u32 = 64 * 1024;
+
+/// How long a client has to send each of `Hello` and `Auth`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections served at once; further ones wait to be accepted
+const MAX_CONNECTIONS: usize = 256;
+
+/// File contents are copied through a buffer of this size
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Request from a client; paths are relative to the module root
+///
+/// `Hello` must stay the first variant, so any version can read it.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+    Hello {
+        protocol: u32,
+        module: String,
+        user: String,
+    },
+    /// Answer to `Challenge`: the proof for its nonce, if one was sent
+    Auth {
+        proof: Option<String>,
+    },
+    Scan {
+        path: PathBuf,
+        max_depth: Option<usize>,
+    },
+    StatBatch {
+        paths: Vec<PathBuf>,
+    },
+    Exists {
+        path: PathBuf,
+    },
+    Mkdirs {
+        paths: Vec<PathBuf>,
+    },
+    Remove {
+        path: PathBuf,
+        is_dir: bool,
+    },
+    Symlink {
+        target: PathBuf,
+        path: PathBuf,
+    },
+    Hardlink {
+        source: PathBuf,
+        path: PathBuf,
+    },
+    SetTimes {
+        path: PathBuf,
+        atime: Option<SystemTime>,
+        crtime: Option<SystemTime>,
+    },
+    SetPermissions {
+        path: PathBuf,
+        perms: NamedPerms,
+    },
+    /// Followed by `size` bytes of file contents
+    Put {
+        path: PathBuf,
+        size: u64,
+        mtime: SystemTime,
+        inplace: bool,
+    },
+    /// Answered by `File` and the file contents
+    Get {
+        path: PathBuf,
+    },
+    Lock {
+        path: PathBuf,
+        owner: LockInfo,
+    },
+    Unlock {
+        path: PathBuf,
+        owner: LockInfo,
+    },
+    CheckWritable {
+        path: PathBuf,
+    },
+}
+
+/// Response from the daemon
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+    /// Answer to `Hello`; `nonce` is set when the module needs a secret
+    Challenge {
+        version: String,
+        nonce: Option<String>,
+    },
+    /// Authenticated; `clock_ms` is the daemon's clock, in milliseconds
+    /// since the Unix epoch
+    Welcome {
+        read_only: bool,
+        clock_ms: i64,
+    },
+    Done,
+    Error {
+        message: String,
+    },
+    Scan(ScanOutput),
+    Stats(Vec<Option<PathStat>>),
+    Exists(bool),
+    Locked(Option<LockInfo>),
+    Unlocked(bool),
+    /// The destination can't be written; `path` is relative to the module root
+    Unwritable {
+        path: PathBuf,
+        reason: String,
+    },
+    /// Followed by `size` bytes of file contents
+    File {
+        size: u64,
+        mtime: SystemTime,
+    },
+}
+
+impl Response {
+    fn error(error: impl std::fmt::Display) -> Self {
+        Response::Error {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A byte stream carrying the protocol: a TCP connection, or one stream of
+/// a QUIC connection
+pub trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+fn protocol_error(message: impl Into<String>) -> SyncError {
+    SyncError::NetworkError {
+        message: message.into(),
+    }
+}
+
+/// Proof that the client knows `secret`, for the daemon's `nonce`
+pub fn proof(secret: &str, nonce: &str) -> blake3::Hash {
+    let key = blake3::hash(secret.as_bytes());
+    blake3::keyed_hash(key.as_bytes(), nonce.as_bytes())
+}
+
+/// Unpredictable one-time challenge for a connection, from the OS's CSPRNG
+fn nonce() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(std::io::Error::from)?;
+    Ok(hex::encode(bytes))
+}
+
+/// Send one length-prefixed message
+pub async fn send<W: AsyncWrite + Unpin, T: Serialize>(output: &mut W, message: &T) -> Result<()> {
+    let body = wire::encode(WireFormat::Binary, message)?;
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&body);
+    output.write_all(&frame).await?;
+    output.flush().await?;
+    Ok(())
+}
+
+/// Receive one length-prefixed message
+pub async fn receive<R: AsyncRead + Unpin, T: DeserializeOwned>(input: &mut R) -> Result<T> {
+    receive_at_most(input, MAX_MESSAGE_SIZE).await
+}
+
+/// Receive one length-prefixed message of at most `max` bytes
+async fn receive_at_most<R: AsyncRead + Unpin, T: DeserializeOwned>(
+    input: &mut R,
+    max: u32,
+) -> Result<T> {
+    let len = input.read_u32().await?;
+    if len > max {
+        return Err(protocol_error(format!(
+            "Message of {} bytes is too large (corrupt stream?)",
+            len
+        )));
+    }
+    let mut body = vec![0; len as usize];
+    input.read_exact(&mut body).await?;
+    Ok(wire::decode(WireFormat::Binary, &body)?)
+}
+
+/// Copy exactly `size` bytes of file contents from `input` to `output`
+///
+/// Fails if `input` ends early, which leaves the connection unusable.
+pub async fn copy_contents<R, W>(input: &mut R, output: &mut W, size: u64) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let copied = tokio::io::copy(&mut input.take(size), output).await?;
+    if copied < size {
+        return Err(protocol_error(format!(
+            "File contents ended after {} of {} bytes",
+            copied, size
+        )));
+    }
+    output.flush().await?;
+    Ok(())
+}
+
+/// A module being served, confined to its root
+struct Module {
+    root: PathBuf,
+    restriction: Restriction,
+    read_only: bool,
+    users: HashMap<String, String>,
+}
+
+impl Module {
+    fn new(name: &str, config: &DaemonModule) -> Result<Self> {
+        let invalid = |e: std::io::Error| {
+            SyncError::Config(format!(
+                "Module '{}': {}: {}",
+                name,
+                config.path.display(),
+                e
+            ))
+        };
+        let root = config.path.canonicalize().map_err(invalid)?;
+        Ok(Self {
+            restriction: Restriction::new(&root, config.read_only)?,
+            root,
+            read_only: config.read_only,
+            users: config.users.clone(),
+        })
+    }
+
+    /// Where a module-relative path lives on this host
+    ///
+    /// `..` is refused outright: after a symlink it would lead somewhere else
+    /// than the restriction checks. Symlinks are left to the checks.
+    fn resolve(&self, path: &Path) -> Result<PathBuf> {
+        let mut resolved = self.root.clone();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => resolved.push(name),
+                Component::ParentDir => {
+                    return Err(SyncError::Restricted(format!(
+                        "{} is outside the module",
+                        path.display()
+                    )))
+                }
+                Component::RootDir | Component::Prefix(_) | Component::CurDir => {}
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve a path that is read, refusing it outside the root
+    fn readable(&self, path: &Path) -> Result<PathBuf> {
+        let resolved = self.resolve(path)?;
+        self.restriction.check(&resolved)?;
+        Ok(resolved)
+    }
+
+    /// Resolve a path that `what` writes, refusing it outside the root or
+    /// in a read-only module
+    fn writable(&self, path: &Path, what: &str) -> Result<PathBuf> {
+        self.restriction.check_write(what)?;
+        self.readable(path)
+    }
+
+    /// Like [`writable`](Self::writable), for an entry that is itself
+    /// replaced or removed (a symlink there isn't followed)
+    fn writable_entry(&self, path: &Path, what: &str) -> Result<PathBuf> {
+        self.restriction.check_write(what)?;
+        let resolved = self.resolve(path)?;
+        self.restriction.check_entry(&resolved)?;
+        Ok(resolved)
+    }
+
+    /// Module-relative form of a path below the root
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).to_path_buf()
+    }
+
+    /// Check the proof `given` by the client for `nonce`
+    fn authenticate(&self, user: &str, nonce: Option<&str>, given: Option<&str>) -> Result<()> {
+        if self.users.is_empty() {
+            return Ok(());
+        }
+        let expected = self
+            .users
+            .get(user)
+            .zip(nonce)
+            .map(|(secret, nonce)| proof(secret, nonce));
+        let given = given.and_then(|given| blake3::Hash::from_hex(given).ok());
+        match (expected, given) {
+            // blake3::Hash compares in constant time
+            (Some(expected), Some(given)) if expected == given => Ok(()),
+            _ => Err(SyncError::Restricted(format!(
+                "authentication failed for user '{}'",
+                user
+            ))),
+        }
+    }
+
+    /// Answer a request that carries no file contents
+    async fn handle(&self, request: Request) -> Result<Response> {
+        let local = LocalTransport::new();
+        let response = match request {
+            Request::Hello { .. } | Request::Auth { .. } => {
+                return Err(protocol_error("Already connected"))
+            }
+            Request::Scan { path, max_depth } => {
+                let dir = self.readable(&path)?;
+                let (entries, errors) = local.max_depth(max_depth).scan_with_errors(&dir).await?;
+                Response::Scan(ScanOutput::new(
+                    entries
+                        .into_iter()
+                        .map(|mut entry| {
+                            entry.path = self.relative(&entry.path);
+                            WireFileEntry::from(entry)
+                        })
+                        .collect(),
+                    errors
+                        .into_iter()
+                        .map(|e| WireScanError {
+                            path: self.relative(&e.path).to_string_lossy().to_string(),
+                            error: e.error,
+                            special: e.special,
+                        })
+                        .collect(),
+                ))
+            }
+            Request::StatBatch { paths } => {
+                // Paths outside the root look like missing ones
+                let allowed: Vec<Option<PathBuf>> =
+                    paths.iter().map(|path| self.readable(path).ok()).collect();
+                let checked: Vec<PathBuf> = allowed.iter().flatten().cloned().collect();
+                let mut stats = local.stat_batch(&checked).await?.into_iter();
+                Response::Stats(
+                    allowed
+                        .iter()
+                        .map(|path| path.as_ref().and_then(|_| stats.next().flatten()))
+                        .collect(),
+                )
+            }
+            Request::Exists { path } => {
+                Response::Exists(local.exists(&self.readable(&path)?).await?)
+            }
+            Request::Mkdirs { paths } => {
+                let dirs = paths
+                    .iter()
+                    .map(|path| self.writable(path, "mkdirs"))
+                    .collect::<Result<Vec<_>>>()?;
+                local.create_dirs(&dirs).await?;
+                Response::Done
+            }
+            Request::Remove { path, is_dir } => {
+                let resolved = self.writable_entry(&path, "remove")?;
+                if resolved == self.root {
+                    return Err(SyncError::Restricted(
+                        "the module root can't be removed".to_string(),
+                    ));
+                }
+                local.remove(&resolved, is_dir).await?;
+                Response::Done
+            }
+            Request::Symlink { target, path } => {
+                let dest = self.writable_entry(&path, "symlink")?;
+                local.create_symlink(&target, &dest).await?;
+                Response::Done
+            }
+            Request::Hardlink { source, path } => {
+                let dest = self.writable_entry(&path, "hardlink")?;
+                local
+                    .create_hardlink(&self.readable(&source)?, &dest)
+                    .await?;
+                Response::Done
+            }
+            Request::SetTimes {
+                path,
+                atime,
+                crtime,
+            } => {
+                let path = self.writable(&path, "set-times")?;
+                local.set_times(&path, atime, crtime).await?;
+                Response::Done
+            }
+            Request::SetPermissions { path, perms } => {
+                let path = self.writable(&path, "set-permissions")?;
+                local.set_permissions(&path, perms.resolve()).await?;
+                Response::Done
+            }
+            Request::Lock { path, owner } => {
+                let path = self.writable(&path, "lock")?;
+                Response::Locked(local.try_lock(&path, &owner).await?)
+            }
+            Request::Unlock { path, owner } => {
+                let path = self.writable(&path, "lock")?;
+                Response::Unlocked(local.unlock(&path, &owner).await?)
+            }
+            Request::CheckWritable { path } => {
+                let path = self.writable(&path, "check-writable")?;
+                match local.check_writable(&path).await {
+                    Err(SyncError::DestinationNotWritable { path, reason }) => {
+                        Response::Unwritable {
+                            path: self.relative(&path),
+                            reason,
+                        }
+                    }
+                    result => result.map(|()| Response::Done)?,
+                }
+            }
+            Request::Put { .. } | Request::Get { .. } => {
+                return Err(protocol_error("File transfers carry contents"))
+            }
+        };
+        Ok(response)
+    }
+
+    /// Receive the contents of a `Put` into `path`
+    ///
+    /// The contents are read off the stream even when the file can't be
+    /// written, so the connection stays usable; only a broken connection is
+    /// returned as an error.
+    async fn put<S: Connection>(
+        &self,
+        stream: &mut S,
+        path: &Path,
+        size: u64,
+        mtime: SystemTime,
+        inplace: bool,
+    ) -> Result<Response> {
+        let dest = self.writable(path, "put");
+        let temp = match &dest {
+            Ok(dest) if !inplace => Some(crate::temp_file::temp_path(dest)),
+            _ => None,
+        };
+        let guard = temp.as_ref().map(crate::temp_file::TempFileGuard::new);
+        let file = match &dest {
+            Ok(dest) => open_for_write(temp.as_deref().unwrap_or(dest)).await,
+            Err(e) => Err(std::io::Error::other(e.to_string())),
+        };
+
+        let written = receive_contents(stream, file, size).await?;
+        let dest = match (dest, written) {
+            (Ok(dest), Ok(())) => dest,
+            (Err(e), _) => return Ok(Response::error(e)),
+            (_, Err(e)) => return Ok(Response::error(e)),
+        };
+
+        let finish = || -> std::io::Result<()> {
+            let written = temp.as_deref().unwrap_or(&dest);
+            filetime::set_file_mtime(written, filetime::FileTime::from_system_time(mtime))?;
+            if let Some(temp) = &temp {
+                std::fs::rename(temp, &dest)?;
+            }
+            Ok(())
+        };
+        Ok(match finish() {
+            Ok(()) => {
+                if let Some(guard) = guard {
+                    guard.defuse();
+                }
+                Response::Done
+            }
+            Err(e) => Response::error(format!("Failed to write {}: {}", path.display(), e)),
+        })
+    }
+
+    /// Answer a `Get` with the file and its contents
+    async fn get<S: Connection>(&self, stream: &mut S, path: &Path) -> Result<()> {
+        let opened = async {
+            let file = tokio::fs::File::open(self.readable(path)?).await?;
+            let metadata = file.metadata().await?;
+            Ok::<_, SyncError>((file, metadata.len(), metadata.modified()?))
+        };
+        let (mut file, size, mtime) = match opened.await {
+            Ok(opened) => opened,
+            Err(e) => return send(stream, &Response::error(e)).await,
+        };
+
+        send(stream, &Response::File { size, mtime }).await?;
+        // A file that shrank meanwhile ends the connection
+        copy_contents(&mut file, stream, size).await
+    }
+}
+
+async fn open_for_write(path: &Path) -> std::io::Result<tokio::fs::File> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::File::create(path).await
+}
+
+/// Read `size` bytes of file contents from `input` into `file`
+///
+/// Keeps reading after a write error, which is returned as the inner result.
+async fn receive_contents<R: AsyncRead + Unpin>(
+    input: &mut R,
+    mut file: std::io::Result<tokio::fs::File>,
+    size: u64,
+) -> Result<std::io::Result<()>> {
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = remaining.min(CHUNK_SIZE as u64) as usize;
+        input.read_exact(&mut buffer[..len]).await?;
+        remaining -= len as u64;
+        if let Ok(output) = &mut file {
+            if let Err(e) = output.write_all(&buffer[..len]).await {
+                file = Err(e);
+            }
+        }
+    }
+    Ok(match file {
+        Ok(mut output) => output.flush().await,
+        Err(e) => Err(e),
+    })
+}
+
+/// The modules of the config file, ready to serve
+pub struct Modules(HashMap<String, Module>);
+
+impl Modules {
+    /// Check every module in `config`, so a typo in it fails at startup
+    pub fn new(config: &HashMap<String, DaemonModule>) -> Result<Self> {
+        config
+            .iter()
+            .map(|(name, config)| Ok((name.clone(), Module::new(name, config)?)))
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+/// Serve `modules` on `listener` until the process is stopped
+///
+/// Every module is checked before the first connection is accepted, so a
+/// typo in the config fails at startup.
+pub async fn serve(listener: TcpListener, modules: &HashMap<String, DaemonModule>) -> Result<()> {
+    let modules = Arc::new(Modules::new(modules)?);
+    let slots = Arc::new(tokio::sync::Semaphore::new(MAX_CONNECTIONS));
+
+    loop {
+        let slot = Arc::clone(&slots)
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        let (stream, peer) = listener.accept().await?;
+        let modules = Arc::clone(&modules);
+        tokio::spawn(async move {
+            let _slot = slot;
+            let served = match stream.set_nodelay(true) {
+                Ok(()) => serve_connection(stream, &modules).await,
+                Err(e) => Err(e.into()),
+            };
+            match served {
+                Ok(()) => tracing::debug!("{}: disconnected", peer),
+                Err(e) => tracing::warn!("{}: {}", peer, e),
+            }
+        });
+    }
+}
+
+/// Receive `Hello` or `Auth` from a client that isn't trusted yet, which
+/// mustn't hold a connection open by never sending it
+async fn receive_handshake<S: Connection>(stream: &mut S, expected: &str) -> Result<Request> {
+    tokio::time::timeout(
+        HANDSHAKE_TIMEOUT,
+        receive_at_most(stream, MAX_HANDSHAKE_SIZE),
+    )
+    .await
+    .map_err(|_| protocol_error(format!("Timed out waiting for {}", expected)))?
+}
+
+/// Run one client connection from `Hello` until it disconnects
+pub async fn serve_connection<S: Connection>(mut stream: S, modules: &Modules) -> Result<()> {
+    let (name, user) = match receive_handshake(&mut stream, "Hello").await? {
+        Request::Hello {
+            protocol,
+            module,
+            user,
+        } => {
+            if protocol != PROTOCOL_VERSION {
+                let message = format!(
+                    "Protocol version {} not supported (daemon speaks {})",
+                    protocol, PROTOCOL_VERSION
+                );
+                send(&mut stream, &Response::error(&message)).await?;
+                return Err(protocol_error(message));
+            }
+            (module, user)
+        }
+        _ => return Err(protocol_error("Expected Hello")),
+    };
+    let Some(module) = modules.0.get(&name) else {
+        let message = format!("Unknown module '{}'", name);
+        send(&mut stream, &Response::error(&message)).await?;
+        return Err(protocol_error(message));
+    };
+
+    let nonce = (!module.users.is_empty()).then(nonce).transpose()?;
+    send(
+        &mut stream,
+        &Response::Challenge {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            nonce: nonce.clone(),
+        },
+    )
+    .await?;
+    let Request::Auth { proof } = receive_handshake(&mut stream, "Auth").await? else {
+        return Err(protocol_error("Expected Auth"));
+    };
+    if let Err(e) = module.authenticate(&user, nonce.as_deref(), proof.as_deref()) {
+        send(&mut stream, &Response::error(&e)).await?;
+        return Err(e);
+    }
+    send(
+        &mut stream,
+        &Response::Welcome {
+            read_only: module.read_only,
+            clock_ms: chrono::Utc::now().timestamp_millis(),
+        },
+    )
+    .await?;
+    tracing::info!("Serving module '{}' to user '{}'", name, user);
+
+    loop {
+        let request = match receive(&mut stream).await {
+            Ok(request) => request,
+            Err(SyncError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let response = match request {
+            Request::Put {
+                path,
+                size,
+                mtime,
+                inplace,
+            } => module.put(&mut stream, &path, size, mtime, inplace).await?,
+            Request::Get { path } => {
+                module.get(&mut stream, &path).await?;
+                continue;
+            }
+            request => module.handle(request).await.unwrap_or_else(Response::error),
+        };
+        send(&mut stream, &response).await?;
+    }
+}
@@ -1,6 +1,6 @@
 use super::{Delta, DeltaOp};
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
 /// Statistics about delta application
@@ -20,39 +20,74 @@ pub struct DeltaStats {
 /// Returns statistics about the delta application.
 #[allow(dead_code)] // Reserved for future remote sync implementation
 pub fn apply_delta(old_file: &Path, delta: &Delta, new_file: &Path) -> io::Result<DeltaStats> {
-    let mut old = File::open(old_file)?;
-    let mut new = File::create(new_file)?;
+    let mut writer = DeltaWriter::create(old_file, new_file)?;
+    for op in &delta.ops {
+        writer.apply(op)?;
+    }
+    writer.finish()
+}
 
-    let mut literal_bytes = 0u64;
-    let mut bytes_written = 0u64;
+/// Incremental delta application, one op at a time
+///
+/// Lets ops be applied as they arrive (e.g. from a stream) instead of
+/// collecting the whole delta first.
+#[allow(dead_code)] // Used by sy-remote
+pub struct DeltaWriter {
+    old: File,
+    new: BufWriter<File>,
+    buffer: Vec<u8>,
+    stats: DeltaStats,
+}
 
-    for op in &delta.ops {
+#[allow(dead_code)] // Used by sy-remote
+impl DeltaWriter {
+    /// Open `old_file` as the Copy source and create `new_file` for output
+    pub fn create(old_file: &Path, new_file: &Path) -> io::Result<Self> {
+        Ok(Self {
+            old: File::open(old_file)?,
+            new: BufWriter::new(File::create(new_file)?),
+            buffer: Vec::new(),
+            stats: DeltaStats {
+                operations_count: 0,
+                literal_bytes: 0,
+                bytes_written: 0,
+            },
+        })
+    }
+
+    pub fn apply(&mut self, op: &DeltaOp) -> io::Result<()> {
         match op {
             DeltaOp::Copy { offset, size } => {
                 // Seek to position in old file
-                old.seek(SeekFrom::Start(*offset))?;
+                self.old.seek(SeekFrom::Start(*offset))?;
 
                 // Copy block
-                let mut buffer = vec![0u8; *size];
-                old.read_exact(&mut buffer)?;
-                new.write_all(&buffer)?;
-                bytes_written += *size as u64;
+                self.buffer.resize(*size, 0);
+                self.old.read_exact(&mut self.buffer)?;
+                self.new.write_all(&self.buffer)?;
+                self.stats.bytes_written += *size as u64;
             }
             DeltaOp::Data(data) => {
                 // Write literal data
-                new.write_all(data)?;
-                literal_bytes += data.len() as u64;
-                bytes_written += data.len() as u64;
+                self.new.write_all(data)?;
+                self.stats.literal_bytes += data.len() as u64;
+                self.stats.bytes_written += data.len() as u64;
             }
         }
+        self.stats.operations_count += 1;
+        Ok(())
     }
 
-    new.flush()?;
-    Ok(DeltaStats {
-        operations_count: delta.ops.len(),
-        literal_bytes,
-        bytes_written,
-    })
+    /// Bytes written to the new file so far
+    pub fn bytes_written(&self) -> u64 {
+        self.stats.bytes_written
+    }
+
+    /// Flush the new file and return the totals
+    pub fn finish(mut self) -> io::Result<DeltaStats> {
+        self.new.flush()?;
+        Ok(self.stats)
+    }
 }
 
 /// Apply delta when there's no old file (full reconstruction from literals)
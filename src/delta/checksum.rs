@@ -1,4 +1,4 @@
-use super::Adler32;
+use super::WeakHash;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
@@ -14,7 +14,7 @@ pub struct BlockChecksum {
     pub offset: u64,
     /// Block size in bytes
     pub size: usize,
-    /// Weak rolling checksum (Adler-32)
+    /// Weak rolling checksum (Adler-32 unless the exchange negotiated otherwise)
     pub weak: u32,
     /// Strong checksum (xxHash3)
     pub strong: u64,
@@ -40,43 +40,85 @@ pub fn compute_checksums(path: &Path, block_size: usize) -> io::Result<Vec<Block
     // Calculate number of blocks
     let num_blocks = file_size.div_ceil(block_size as u64);
 
-    // Process blocks in parallel using rayon
+    // Process blocks in parallel on the CPU pool (--threads)
     // Each thread gets its own file handle for independent I/O
-    let path_buf = path.to_path_buf();
-    let checksums: io::Result<Vec<BlockChecksum>> = (0..num_blocks)
-        .into_par_iter()
-        .map(|index| {
-            // Each thread opens its own file handle
-            let mut file = File::open(&path_buf)?;
-            let offset = index * block_size as u64;
-
-            // Seek to block position
-            file.seek(io::SeekFrom::Start(offset))?;
-
-            // Read block (may be partial for last block)
-            let mut buffer = vec![0u8; block_size];
-            let bytes_read = file.read(&mut buffer)?;
-            let block = &buffer[..bytes_read];
-
-            // Compute weak checksum (Adler-32)
-            let weak = Adler32::hash(block);
-
-            // Compute strong checksum (xxHash3)
-            let mut hasher = xxhash_rust::xxh3::Xxh3::new();
-            hasher.update(block);
-            let strong = hasher.digest();
-
-            Ok(BlockChecksum {
-                index,
-                offset,
-                size: bytes_read,
-                weak,
-                strong,
-            })
-        })
-        .collect();
+    crate::cpu::install(|| {
+        (0..num_blocks)
+            .into_par_iter()
+            .map(|index| checksum_block(path, index, block_size, WeakHash::Adler32))
+            .collect()
+    })
+}
+
+/// Blocks checksummed in parallel before a batch is handed out
+const CHECKSUM_BATCH_BLOCKS: u64 = 1024;
+
+/// Compute checksums for all blocks in a file, handing them out in order as
+/// batches complete
+///
+/// Lets the checksums be sent while later blocks are still being read
+/// (`sy-remote delta-sync`). Each batch is computed in parallel like
+/// `compute_checksums`, with `weak_hash` as the weak checksum.
+#[allow(dead_code)] // Used by sy-remote
+pub fn compute_checksums_batched<F>(
+    path: &Path,
+    block_size: usize,
+    weak_hash: WeakHash,
+    mut emit: F,
+) -> io::Result<()>
+where
+    F: FnMut(&[BlockChecksum]) -> io::Result<()>,
+{
+    let num_blocks = std::fs::metadata(path)?.len().div_ceil(block_size as u64);
+
+    let mut start = 0;
+    while start < num_blocks {
+        let end = (start + CHECKSUM_BATCH_BLOCKS).min(num_blocks);
+        let batch = crate::cpu::install(|| {
+            (start..end)
+                .into_par_iter()
+                .map(|index| checksum_block(path, index, block_size, weak_hash))
+                .collect::<io::Result<Vec<_>>>()
+        })?;
+        emit(&batch)?;
+        start = end;
+    }
+    Ok(())
+}
 
-    checksums
+/// Checksum block `index` (may be partial for the last block)
+fn checksum_block(
+    path: &Path,
+    index: u64,
+    block_size: usize,
+    weak_hash: WeakHash,
+) -> io::Result<BlockChecksum> {
+    let mut file = File::open(path)?;
+    let offset = index * block_size as u64;
+
+    // Seek to block position
+    file.seek(io::SeekFrom::Start(offset))?;
+
+    // Read block (may be partial for last block)
+    let mut buffer = vec![0u8; block_size];
+    let bytes_read = file.read(&mut buffer)?;
+    let block = &buffer[..bytes_read];
+
+    // Compute weak checksum
+    let weak = weak_hash.hash(block);
+
+    // Compute strong checksum (xxHash3)
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    hasher.update(block);
+    let strong = hasher.digest();
+
+    Ok(BlockChecksum {
+        index,
+        offset,
+        size: bytes_read,
+        weak,
+        strong,
+    })
 }
 
 #[cfg(test)]
@@ -144,4 +186,22 @@ mod tests {
         assert_eq!(checksums_small.len(), 10); // 100 / 10 = 10 blocks
         assert_eq!(checksums_large.len(), 2); // 100 / 50 = 2 blocks
     }
+
+    #[test]
+    fn test_batched_matches_compute_checksums() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        temp_file.write_all(&data).unwrap();
+        temp_file.flush().unwrap();
+
+        // 1429 blocks of 7 bytes span two batches
+        let mut batched = Vec::new();
+        compute_checksums_batched(temp_file.path(), 7, WeakHash::Adler32, |batch| {
+            batched.extend_from_slice(batch);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(batched, compute_checksums(temp_file.path(), 7).unwrap());
+    }
 }
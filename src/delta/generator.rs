@@ -1,4 +1,5 @@
-use super::{Adler32, BlockChecksum};
+use super::rolling::{Adler32, Buzhash, RollingHash, WeakHash};
+use super::BlockChecksum;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
@@ -63,35 +64,135 @@ impl Delta {
 /// 3. Slide window through data using rolling hash
 /// 4. Generate Copy ops for matches, Data ops for literals
 ///
-/// Memory usage: ~512KB regardless of file size
+/// Memory usage: ~512KB plus the collected ops. Use `generate_delta_ops` to
+/// consume ops as they are produced.
+#[allow(dead_code)] // SSH delta sync streams ops via generate_delta_ops
 pub fn generate_delta_streaming(
     source_path: &Path,
     dest_checksums: &[BlockChecksum],
     block_size: usize,
 ) -> io::Result<Delta> {
-    const CHUNK_SIZE: usize = 256 * 1024; // 256KB chunks
+    let mut ops = Vec::new();
+    let source_size = generate_delta_ops(
+        source_path,
+        dest_checksums,
+        block_size,
+        WeakHash::Adler32,
+        false,
+        |op| {
+            ops.push(op);
+            Ok(())
+        },
+    )?;
 
-    // Build hash map for O(1) lookup
-    let mut checksum_map: HashMap<u32, Vec<&BlockChecksum>> = HashMap::new();
-    for checksum in dest_checksums {
-        checksum_map
-            .entry(checksum.weak)
-            .or_default()
-            .push(checksum);
+    Ok(Delta {
+        ops,
+        source_size,
+        block_size,
+    })
+}
+
+/// Literal runs longer than this are split into several Data ops, so a
+/// mostly-changed file never has to be buffered whole before it is emitted
+pub const MAX_LITERAL_SIZE: usize = 256 * 1024;
+
+/// Destination block checksums indexed by weak hash
+///
+/// A bitmap of weak hashes sits in front of the map: most source offsets
+/// match no block, and a bit test is much cheaper than a hash map probe.
+struct BlockIndex<'a> {
+    filter: Vec<u64>,
+    shift: u32,
+    map: HashMap<u32, Vec<&'a BlockChecksum>>,
+}
+
+impl<'a> BlockIndex<'a> {
+    fn new(checksums: &'a [BlockChecksum]) -> Self {
+        // ~16 bits per block keeps false positives rare; 8KB up to 2MB
+        let bits = (checksums.len() * 16)
+            .next_power_of_two()
+            .clamp(1 << 16, 1 << 24);
+        let mut index = Self {
+            filter: vec![0; bits / 64],
+            shift: 32 - bits.trailing_zeros(),
+            map: HashMap::with_capacity(checksums.len()),
+        };
+
+        for checksum in checksums {
+            let slot = index.slot(checksum.weak);
+            index.filter[slot / 64] |= 1 << (slot % 64);
+            index.map.entry(checksum.weak).or_default().push(checksum);
+        }
+        index
     }
 
-    let mut source_file = File::open(source_path)?;
+    /// Filter bit for a weak hash (multiplicative hashing spreads Adler-32's
+    /// clustered low half across the bitmap)
+    fn slot(&self, weak: u32) -> usize {
+        (weak.wrapping_mul(0x9E37_79B9) >> self.shift) as usize
+    }
+
+    fn get(&self, weak: u32) -> Option<&[&'a BlockChecksum]> {
+        let slot = self.slot(weak);
+        if self.filter[slot / 64] & (1 << (slot % 64)) == 0 {
+            return None;
+        }
+        self.map.get(&weak).map(Vec::as_slice)
+    }
+}
+
+/// Generate delta operations, handing each one to `emit` as soon as it is known
+///
+/// Same algorithm and memory bound as `generate_delta_streaming`, but ops are
+/// not collected, so callers can send them over the wire while the rest of the
+/// source is still being read. `weak_hash` must match the one `dest_checksums`
+/// were computed with; `noatime` opens the source with `O_NOATIME`. Returns
+/// the source file size.
+pub fn generate_delta_ops<F>(
+    source_path: &Path,
+    dest_checksums: &[BlockChecksum],
+    block_size: usize,
+    weak_hash: WeakHash,
+    noatime: bool,
+    emit: F,
+) -> io::Result<u64>
+where
+    F: FnMut(DeltaOp) -> io::Result<()>,
+{
+    match weak_hash {
+        WeakHash::Adler32 => {
+            generate_ops::<Adler32, F>(source_path, dest_checksums, block_size, noatime, emit)
+        }
+        WeakHash::Buzhash => {
+            generate_ops::<Buzhash, F>(source_path, dest_checksums, block_size, noatime, emit)
+        }
+    }
+}
+
+/// `generate_delta_ops` for a concrete rolling hash, so `roll` is inlined
+/// into the per-byte loop
+fn generate_ops<H, F>(
+    source_path: &Path,
+    dest_checksums: &[BlockChecksum],
+    block_size: usize,
+    noatime: bool,
+    mut emit: F,
+) -> io::Result<u64>
+where
+    H: RollingHash,
+    F: FnMut(DeltaOp) -> io::Result<()>,
+{
+    const CHUNK_SIZE: usize = 256 * 1024; // 256KB chunks
+
+    let index = BlockIndex::new(dest_checksums);
+
+    let mut source_file = crate::fs_util::open_source(source_path, noatime)?;
     let source_size = source_file.metadata()?.len();
 
     if source_size == 0 {
-        return Ok(Delta {
-            ops: vec![],
-            source_size: 0,
-            block_size,
-        });
+        return Ok(0);
     }
 
-    let mut ops = Vec::new();
     let mut literal_buffer = Vec::new();
 
     // Sliding window buffer: large enough for rolling hash + read ahead
@@ -105,7 +206,7 @@ pub fn generate_delta_streaming(
     }
 
     // Initialize rolling hash
-    let mut rolling = Adler32::new(block_size);
+    let mut rolling = H::new(block_size);
     if window.len() >= block_size {
         rolling.update_block(&window[0..block_size]);
     }
@@ -121,7 +222,7 @@ pub fn generate_delta_streaming(
         if remaining >= block_size {
             let weak = rolling.digest();
 
-            if let Some(candidates) = checksum_map.get(&weak) {
+            if let Some(candidates) = index.get(weak) {
                 let block = &window[window_pos..window_pos + block_size];
 
                 // Verify with strong hash
@@ -133,13 +234,13 @@ pub fn generate_delta_streaming(
                     if checksum.strong == strong {
                         // Match found! Flush literals and add Copy
                         if !literal_buffer.is_empty() {
-                            ops.push(DeltaOp::Data(std::mem::take(&mut literal_buffer)));
+                            emit(DeltaOp::Data(std::mem::take(&mut literal_buffer)))?;
                         }
 
-                        ops.push(DeltaOp::Copy {
+                        emit(DeltaOp::Copy {
                             offset: checksum.offset,
                             size: checksum.size,
-                        });
+                        })?;
 
                         window_pos += block_size;
                         _file_pos += block_size as u64;
@@ -156,9 +257,9 @@ pub fn generate_delta_streaming(
         } else if remaining > 0 {
             // Partial block at end
             let partial = &window[window_pos..];
-            let weak = Adler32::hash(partial);
+            let weak = H::hash(partial);
 
-            if let Some(candidates) = checksum_map.get(&weak) {
+            if let Some(candidates) = index.get(weak) {
                 let mut hasher = xxhash_rust::xxh3::Xxh3::new();
                 hasher.update(partial);
                 let strong = hasher.digest();
@@ -166,13 +267,13 @@ pub fn generate_delta_streaming(
                 for checksum in candidates {
                     if checksum.size == partial.len() && checksum.strong == strong {
                         if !literal_buffer.is_empty() {
-                            ops.push(DeltaOp::Data(std::mem::take(&mut literal_buffer)));
+                            emit(DeltaOp::Data(std::mem::take(&mut literal_buffer)))?;
                         }
 
-                        ops.push(DeltaOp::Copy {
+                        emit(DeltaOp::Copy {
                             offset: checksum.offset,
                             size: checksum.size,
-                        });
+                        })?;
 
                         window_pos += partial.len();
                         _file_pos += partial.len() as u64;
@@ -186,6 +287,9 @@ pub fn generate_delta_streaming(
         if !found_match && window_pos < window.len() {
             // No match - add byte to literal buffer
             literal_buffer.push(window[window_pos]);
+            if literal_buffer.len() >= MAX_LITERAL_SIZE {
+                emit(DeltaOp::Data(std::mem::take(&mut literal_buffer)))?;
+            }
 
             // Update rolling hash for next position
             if window_pos + block_size < window.len() {
@@ -217,14 +321,10 @@ pub fn generate_delta_streaming(
 
     // Flush remaining literals
     if !literal_buffer.is_empty() {
-        ops.push(DeltaOp::Data(literal_buffer));
+        emit(DeltaOp::Data(literal_buffer))?;
     }
 
-    Ok(Delta {
-        ops,
-        source_size,
-        block_size,
-    })
+    Ok(source_size)
 }
 
 /// Generate delta operations by comparing source file against destination checksums
@@ -602,4 +702,59 @@ mod tests {
         assert_eq!(delta.ops.len(), 0);
         assert_eq!(delta.source_size, 0);
     }
+
+    #[test]
+    fn test_buzhash_ops_match_adler32() {
+        let mut source = NamedTempFile::new().unwrap();
+        let mut dest = NamedTempFile::new().unwrap();
+        let old: Vec<u8> = (0..300_000u32).map(|i| (i * 7 % 253) as u8).collect();
+        let mut new = old.clone();
+        new.splice(100_000..100_000, b"inserted bytes".iter().copied());
+        new.truncate(new.len() - 777);
+        source.write_all(&new).unwrap();
+        dest.write_all(&old).unwrap();
+        source.flush().unwrap();
+        dest.flush().unwrap();
+
+        let block_size = 2048;
+        let mut ops_by_hash = Vec::new();
+        for weak_hash in [WeakHash::Adler32, WeakHash::Buzhash] {
+            let mut checksums = Vec::new();
+            crate::delta::checksum::compute_checksums_batched(
+                dest.path(),
+                block_size,
+                weak_hash,
+                |batch| {
+                    checksums.extend_from_slice(batch);
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+            let mut ops = Vec::new();
+            generate_delta_ops(
+                source.path(),
+                &checksums,
+                block_size,
+                weak_hash,
+                false,
+                |op| {
+                    ops.push(op);
+                    Ok(())
+                },
+            )
+            .unwrap();
+            ops_by_hash.push(ops);
+        }
+
+        // Both hashes find the same blocks after the insertion point
+        assert_eq!(ops_by_hash[0], ops_by_hash[1]);
+        assert!(
+            ops_by_hash[1]
+                .iter()
+                .filter(|op| matches!(op, DeltaOp::Copy { .. }))
+                .count()
+                > 100
+        );
+    }
 }
@@ -1,6 +1,7 @@
 pub mod applier;
 pub mod checksum;
 pub mod generator;
+pub mod pipeline;
 pub mod ratio;
 pub mod rolling;
 
@@ -13,7 +14,8 @@ pub use checksum::{compute_checksums, BlockChecksum};
 pub use generator::{generate_delta, generate_delta_streaming, Delta, DeltaOp};
 #[allow(unused_imports)]
 pub use ratio::{estimate_change_ratio, ChangeRatioResult};
-pub use rolling::Adler32;
+#[allow(unused_imports)]
+pub use rolling::{Adler32, Buzhash, RollingHash, WeakHash};
 
 /// Default block size calculation: sqrt(filesize)
 /// Capped between 512 bytes and 128KB
@@ -0,0 +1,368 @@
+use super::applier::{DeltaStats, DeltaWriter};
+use super::calculate_block_size;
+use super::checksum::{compute_checksums_batched, BlockChecksum};
+use super::generator::{generate_delta_ops, DeltaOp};
+use super::rolling::WeakHash;
+use crate::wire::{self, WireFormat};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// Destinations smaller than this are copied in full instead of delta-synced
+pub const MIN_DELTA_DEST_SIZE: u64 = 4096;
+
+/// Zstd level for the delta frame stream
+const ZSTD_LEVEL: i32 = 3;
+
+/// First frame printed by `sy-remote delta-sync`, ahead of the block checksums
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeltaSyncHeader {
+    /// Destination size in bytes (0 if it doesn't exist)
+    pub dest_size: u64,
+    pub block_size: usize,
+    /// Number of checksum frames that follow
+    pub blocks: u64,
+}
+
+/// One frame of the delta stream sent to `sy-remote delta-sync`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeltaFrame {
+    Op(DeltaOp),
+    /// Last frame; if the stream ends without it the output is discarded
+    End {
+        source_size: u64,
+    },
+}
+
+/// Last frame printed by `sy-remote delta-sync` once the new file is in place
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeltaSyncResult {
+    pub operations_count: usize,
+    pub literal_bytes: u64,
+}
+
+/// Remote side of a pipelined delta sync (`sy-remote delta-sync`)
+///
+/// 1. Writes the header, then streams the block checksums of `dest` to
+///    `output`, flushing after every batch so the sender can start on them
+/// 2. Applies the delta frames read from `input` to `temp` as they arrive
+/// 3. Renames `temp` over `dest` after the end frame, once the size checks
+///    out, and writes the result frame
+///
+/// The temp file is removed if the stream fails or ends early, leaving `dest`
+/// untouched. All frames use `format` in both directions; the checksums use
+/// `weak_hash`, which the sender must pass to `send_delta` as well.
+#[allow(dead_code)] // Used by sy-remote
+pub fn serve_delta_sync<R: Read, W: Write>(
+    format: WireFormat,
+    weak_hash: WeakHash,
+    dest: &Path,
+    temp: &Path,
+    input: R,
+    output: &mut W,
+) -> io::Result<DeltaSyncResult> {
+    let dest_size = match std::fs::metadata(dest) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e),
+    };
+    let block_size = calculate_block_size(dest_size);
+    let header = DeltaSyncHeader {
+        dest_size,
+        block_size,
+        blocks: dest_size.div_ceil(block_size as u64),
+    };
+    wire::write_stream_start(format, output)?;
+    wire::write_frame(format, output, &header)?;
+    output.flush()?;
+
+    if dest_size > 0 {
+        compute_checksums_batched(dest, block_size, weak_hash, |batch| {
+            for checksum in batch {
+                wire::write_frame(format, output, checksum)?;
+            }
+            output.flush()
+        })?;
+    }
+
+    let result = match apply_frames(format, dest, temp, input) {
+        Ok(result) => result,
+        Err(e) => {
+            let _ = std::fs::remove_file(temp);
+            return Err(e);
+        }
+    };
+    wire::write_frame(format, output, &result)?;
+    output.flush()?;
+    Ok(result)
+}
+
+/// Apply delta frames from `input` to `temp`, then move it over `dest`
+fn apply_frames<R: Read>(
+    format: WireFormat,
+    dest: &Path,
+    temp: &Path,
+    input: R,
+) -> io::Result<DeltaSyncResult> {
+    let mut frames = BufReader::new(zstd::stream::read::Decoder::new(input)?);
+    wire::read_stream_start(format, &mut frames)?;
+    let mut writer = DeltaWriter::create(dest, temp)?;
+
+    loop {
+        let Some(frame) = wire::read_frame(format, &mut frames)? else {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Delta stream ended before the end frame",
+            ));
+        };
+
+        match frame {
+            DeltaFrame::Op(op) => writer.apply(&op)?,
+            DeltaFrame::End { source_size } => {
+                if writer.bytes_written() != source_size {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Delta produced {} bytes, expected {}",
+                            writer.bytes_written(),
+                            source_size
+                        ),
+                    ));
+                }
+
+                let stats = writer.finish()?;
+                std::fs::rename(temp, dest)?;
+                return Ok(DeltaSyncResult {
+                    operations_count: stats.operations_count,
+                    literal_bytes: stats.literal_bytes,
+                });
+            }
+        }
+    }
+}
+
+/// Read the header and block checksums streamed by `sy-remote delta-sync`
+pub fn read_checksums<R: BufRead>(
+    format: WireFormat,
+    reader: &mut R,
+) -> io::Result<(DeltaSyncHeader, Vec<BlockChecksum>)> {
+    wire::read_stream_start(format, reader)?;
+    let header: DeltaSyncHeader = read_required(format, reader)?;
+    let mut checksums = Vec::with_capacity(header.blocks as usize);
+    for _ in 0..header.blocks {
+        checksums.push(read_required(format, reader)?);
+    }
+    Ok((header, checksums))
+}
+
+/// Read the result frame `sy-remote delta-sync` writes after replacing the file
+pub fn read_result<R: BufRead>(format: WireFormat, reader: &mut R) -> io::Result<DeltaSyncResult> {
+    read_required(format, reader)
+}
+
+/// Stream the delta of `source` against `checksums` to `output`
+///
+/// Frames are zstd-compressed and written as each op is generated, so the
+/// remote side applies them while the rest of the source is still being
+/// read. Ends with the end frame; `output` is returned so the caller can
+/// close it. `noatime` opens `source` with `O_NOATIME`.
+pub fn send_delta<W: Write>(
+    format: WireFormat,
+    weak_hash: WeakHash,
+    source: &Path,
+    checksums: &[BlockChecksum],
+    block_size: usize,
+    noatime: bool,
+    output: W,
+) -> io::Result<(W, DeltaStats)> {
+    let mut encoder = zstd::stream::write::Encoder::new(output, ZSTD_LEVEL)?;
+    wire::write_stream_start(format, &mut encoder)?;
+    let mut stats = DeltaStats {
+        operations_count: 0,
+        literal_bytes: 0,
+        bytes_written: 0,
+    };
+
+    let source_size =
+        generate_delta_ops(source, checksums, block_size, weak_hash, noatime, |op| {
+            stats.operations_count += 1;
+            if let DeltaOp::Data(data) = &op {
+                stats.literal_bytes += data.len() as u64;
+            }
+            wire::write_frame(format, &mut encoder, &DeltaFrame::Op(op))
+        })?;
+    stats.bytes_written = source_size;
+    wire::write_frame(format, &mut encoder, &DeltaFrame::End { source_size })?;
+
+    Ok((encoder.finish()?, stats))
+}
+
+fn read_required<R: BufRead, T: for<'de> Deserialize<'de>>(
+    format: WireFormat,
+    reader: &mut R,
+) -> io::Result<T> {
+    wire::read_frame(format, reader)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Delta sync stream ended early",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const FORMATS: [WireFormat; 2] = [WireFormat::Json, WireFormat::Binary];
+    const WEAK_HASHES: [WeakHash; 2] = [WeakHash::Adler32, WeakHash::Buzhash];
+
+    fn checksums(dest: &Path, block_size: usize, weak_hash: WeakHash) -> Vec<BlockChecksum> {
+        let mut checksums = Vec::new();
+        compute_checksums_batched(dest, block_size, weak_hash, |batch| {
+            checksums.extend_from_slice(batch);
+            Ok(())
+        })
+        .unwrap();
+        checksums
+    }
+
+    /// Build the sender's half of an exchange against `dest` up front
+    fn delta_input(format: WireFormat, weak_hash: WeakHash, source: &Path, dest: &Path) -> Vec<u8> {
+        let block_size = calculate_block_size(std::fs::metadata(dest).unwrap().len());
+        let checksums = checksums(dest, block_size, weak_hash);
+        send_delta(
+            format,
+            weak_hash,
+            source,
+            &checksums,
+            block_size,
+            false,
+            Vec::new(),
+        )
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn test_pipelined_delta_roundtrip() {
+        for (format, weak_hash) in FORMATS.into_iter().zip(WEAK_HASHES) {
+            let temp = TempDir::new().unwrap();
+            let source = temp.path().join("source");
+            let dest = temp.path().join("dest");
+            let temp_file = temp.path().join("dest.sy-tmp");
+
+            let old: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+            let mut new = old.clone();
+            new[50_000..50_100].fill(0xFF);
+            new.extend_from_slice(b"appended");
+            std::fs::write(&dest, &old).unwrap();
+            std::fs::write(&source, &new).unwrap();
+            let expected = checksums(&dest, calculate_block_size(old.len() as u64), weak_hash);
+
+            let input = delta_input(format, weak_hash, &source, &dest);
+            let mut output = Vec::new();
+            let result = serve_delta_sync(
+                format,
+                weak_hash,
+                &dest,
+                &temp_file,
+                &input[..],
+                &mut output,
+            )
+            .unwrap();
+
+            assert_eq!(std::fs::read(&dest).unwrap(), new);
+            assert!(!temp_file.exists());
+            assert!(result.literal_bytes < new.len() as u64 / 10);
+
+            // Checksums of the old contents stream back, followed by the result
+            let mut reader = &output[..];
+            let (header, checksums) = read_checksums(format, &mut reader).unwrap();
+            assert_eq!(header.dest_size, old.len() as u64);
+            assert_eq!(checksums.len() as u64, header.blocks);
+            assert_eq!(checksums, expected);
+            assert_eq!(read_result(format, &mut reader).unwrap(), result);
+        }
+    }
+
+    #[test]
+    fn test_binary_delta_is_smaller_than_json() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        // Incompressible literals, so zstd can't hide the JSON overhead
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let data: Vec<u8> = (0..200_000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                seed as u8
+            })
+            .collect();
+        std::fs::write(&dest, vec![0u8; 200_000]).unwrap();
+        std::fs::write(&source, &data).unwrap();
+
+        let json = delta_input(WireFormat::Json, WeakHash::Buzhash, &source, &dest);
+        let binary = delta_input(WireFormat::Binary, WeakHash::Buzhash, &source, &dest);
+        assert!(binary.len() < json.len());
+    }
+
+    #[test]
+    fn test_truncated_stream_leaves_dest_untouched() {
+        for format in FORMATS {
+            let temp = TempDir::new().unwrap();
+            let source = temp.path().join("source");
+            let dest = temp.path().join("dest");
+            let temp_file = temp.path().join("dest.sy-tmp");
+            std::fs::write(&dest, vec![1u8; 10_000]).unwrap();
+            std::fs::write(&source, vec![2u8; 10_000]).unwrap();
+
+            // Sender gave up before the end frame (e.g. destination too small)
+            let weak_hash = WeakHash::Buzhash;
+            let mut output = Vec::new();
+            assert!(serve_delta_sync(
+                format,
+                weak_hash,
+                &dest,
+                &temp_file,
+                io::empty(),
+                &mut output
+            )
+            .is_err());
+
+            // Stream cut off partway through
+            let input = delta_input(format, weak_hash, &source, &dest);
+            let cut = &input[..input.len() / 2];
+            assert!(
+                serve_delta_sync(format, weak_hash, &dest, &temp_file, cut, &mut Vec::new())
+                    .is_err()
+            );
+
+            assert_eq!(std::fs::read(&dest).unwrap(), vec![1u8; 10_000]);
+            assert!(!temp_file.exists());
+        }
+    }
+
+    #[test]
+    fn test_missing_dest_reports_no_blocks() {
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("missing");
+        let mut output = Vec::new();
+        let tmp = temp.path().join("tmp");
+        let _ = serve_delta_sync(
+            WireFormat::Json,
+            WeakHash::Adler32,
+            &dest,
+            &tmp,
+            io::empty(),
+            &mut output,
+        );
+
+        let (header, checksums) = read_checksums(WireFormat::Json, &mut &output[..]).unwrap();
+        assert_eq!(header.dest_size, 0);
+        assert!(checksums.is_empty());
+    }
+}
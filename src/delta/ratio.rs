@@ -58,6 +58,7 @@ impl ChangeRatioResult {
 /// * `block_size` - Size of each block in bytes
 /// * `sample_count` - Number of blocks to sample (default: 20)
 /// * `threshold` - Change ratio threshold (default: 0.75 = 75%)
+/// * `noatime` - Open the source as `fs_util::open_source` does (--open-noatime)
 ///
 /// # Returns
 /// * `Ok(ChangeRatioResult)` - Sampling result with decision
@@ -81,12 +82,14 @@ pub fn estimate_change_ratio(
     block_size: usize,
     sample_count: Option<usize>,
     threshold: Option<f64>,
+    noatime: bool,
 ) -> std::io::Result<ChangeRatioResult> {
     let sample_count = sample_count.unwrap_or(20);
     let threshold = threshold.unwrap_or(0.75);
 
     // Open files
-    let mut source_file = BufReader::with_capacity(256 * 1024, File::open(source)?);
+    let mut source_file =
+        BufReader::with_capacity(256 * 1024, crate::fs_util::open_source(source, noatime)?);
     let mut dest_file = BufReader::with_capacity(256 * 1024, File::open(dest)?);
 
     // Get file sizes
@@ -207,7 +210,7 @@ mod tests {
         std::fs::write(&source, &data).unwrap();
         std::fs::write(&dest, &data).unwrap();
 
-        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None).unwrap();
+        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None, false).unwrap();
 
         assert_eq!(result.blocks_changed, 0);
         assert_eq!(result.change_ratio, 0.0);
@@ -226,7 +229,7 @@ mod tests {
         std::fs::write(&source, &source_data).unwrap();
         std::fs::write(&dest, &dest_data).unwrap();
 
-        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None).unwrap();
+        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None, false).unwrap();
 
         assert_eq!(result.blocks_changed, result.blocks_sampled);
         assert_eq!(result.change_ratio, 1.0);
@@ -251,7 +254,7 @@ mod tests {
         std::fs::write(&source, &source_data).unwrap();
         std::fs::write(&dest, &dest_data).unwrap();
 
-        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None).unwrap();
+        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None, false).unwrap();
 
         // Should detect some changes, but not all
         assert!(result.blocks_changed > 0);
@@ -280,11 +283,12 @@ mod tests {
         std::fs::write(&dest, &dest_data).unwrap();
 
         // With default threshold (75%), should recommend full copy
-        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None).unwrap();
+        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None, false).unwrap();
         assert!(!result.use_delta);
 
         // With higher threshold (90%), should use delta
-        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, Some(0.90)).unwrap();
+        let result =
+            estimate_change_ratio(&source, &dest, 64 * 1024, None, Some(0.90), false).unwrap();
         assert!(result.use_delta);
     }
 
@@ -300,7 +304,7 @@ mod tests {
         std::fs::write(&source, &source_data).unwrap();
         std::fs::write(&dest, &dest_data).unwrap();
 
-        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None).unwrap();
+        let result = estimate_change_ratio(&source, &dest, 64 * 1024, None, None, false).unwrap();
 
         // >50% size difference should trigger high change ratio
         assert!(!result.use_delta);
@@ -317,7 +321,8 @@ mod tests {
         std::fs::write(&dest, &data).unwrap();
 
         // Sample only 5 blocks
-        let result = estimate_change_ratio(&source, &dest, 64 * 1024, Some(5), None).unwrap();
+        let result =
+            estimate_change_ratio(&source, &dest, 64 * 1024, Some(5), None, false).unwrap();
 
         assert_eq!(result.blocks_sampled, 5);
         assert_eq!(result.blocks_changed, 0);
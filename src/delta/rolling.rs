@@ -1,3 +1,52 @@
+/// Weak rolling checksum used to find candidate block matches
+///
+/// Both sides of a delta sync must use the same implementation: the
+/// destination hashes its blocks with `hash`, the source slides a window
+/// with `update_block`/`roll` and looks up `digest` at every offset.
+pub trait RollingHash {
+    /// Create a hasher for windows of `block_size` bytes
+    fn new(block_size: usize) -> Self;
+
+    /// Hash a block of data (non-rolling)
+    fn hash(data: &[u8]) -> u32;
+
+    /// Initialize with a full block
+    fn update_block(&mut self, block: &[u8]);
+
+    /// Roll the hash: remove old byte, add new byte
+    fn roll(&mut self, old_byte: u8, new_byte: u8);
+
+    /// Get the current hash value
+    fn digest(&self) -> u32;
+}
+
+/// Which weak rolling checksum a set of block checksums was computed with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WeakHash {
+    /// rsync-style Adler-32, understood by every sy-remote version
+    #[default]
+    Adler32,
+    /// Cyclic polynomial hash: no modulo, table-driven, lane-parallel block hashing
+    Buzhash,
+}
+
+impl WeakHash {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WeakHash::Adler32 => "adler32",
+            WeakHash::Buzhash => "buzhash",
+        }
+    }
+
+    /// Hash a block of data with this checksum
+    pub fn hash(self, data: &[u8]) -> u32 {
+        match self {
+            WeakHash::Adler32 => Adler32::hash(data),
+            WeakHash::Buzhash => Buzhash::hash(data),
+        }
+    }
+}
+
 /// Adler-32 rolling hash implementation
 ///
 /// This is the weak checksum used by rsync for fast matching.
@@ -21,9 +70,38 @@ pub struct Adler32 {
 
 const MOD_ADLER: u32 = 65521; // Largest prime < 2^16
 
+/// Bytes that can be summed before `b` could overflow u32 (zlib's NMAX)
+const ADLER_NMAX: usize = 5552;
+
 impl Adler32 {
+    /// Reset the hasher
+    #[allow(dead_code)]
+    pub fn reset(&mut self) {
+        self.a = 1;
+        self.b = 0;
+    }
+
+    /// Sum `data` into (a, b), reducing modulo only once per NMAX bytes
+    fn sums(data: &[u8]) -> (u32, u32) {
+        let mut a: u32 = 1;
+        let mut b: u32 = 0;
+
+        for chunk in data.chunks(ADLER_NMAX) {
+            for &byte in chunk {
+                a += byte as u32;
+                b += a;
+            }
+            a %= MOD_ADLER;
+            b %= MOD_ADLER;
+        }
+
+        (a, b)
+    }
+}
+
+impl RollingHash for Adler32 {
     /// Create a new Adler-32 hasher
-    pub fn new(block_size: usize) -> Self {
+    fn new(block_size: usize) -> Self {
         Self {
             a: 1,
             b: 0,
@@ -31,31 +109,15 @@ impl Adler32 {
         }
     }
 
-    /// Hash a block of data (non-rolling)
-    pub fn hash(data: &[u8]) -> u32 {
-        let mut a: u32 = 1;
-        let mut b: u32 = 0;
-
-        for &byte in data {
-            a = (a + byte as u32) % MOD_ADLER;
-            b = (b + a) % MOD_ADLER;
-        }
-
+    fn hash(data: &[u8]) -> u32 {
+        let (a, b) = Self::sums(data);
         (b << 16) | a
     }
 
-    /// Initialize with a full block
-    pub fn update_block(&mut self, block: &[u8]) {
-        self.a = 1;
-        self.b = 0;
-
-        for &byte in block {
-            self.a = (self.a + byte as u32) % MOD_ADLER;
-            self.b = (self.b + self.a) % MOD_ADLER;
-        }
+    fn update_block(&mut self, block: &[u8]) {
+        (self.a, self.b) = Self::sums(block);
     }
 
-    /// Roll the hash: remove old byte, add new byte
     /// This is the key operation for rsync algorithm
     ///
     /// True O(1) incremental update using Adler-32 rolling formula:
@@ -63,10 +125,10 @@ impl Adler32 {
     /// - B_new = (B_old - block_size * old_byte + A_new - 1) mod M
     ///
     /// No window maintenance needed - hash state (a, b) is sufficient.
-    pub fn roll(&mut self, old_byte: u8, new_byte: u8) {
+    fn roll(&mut self, old_byte: u8, new_byte: u8) {
         let old = old_byte as u32;
         let new = new_byte as u32;
-        let n = self.block_size as u32;
+        let n = (self.block_size as u32) % MOD_ADLER;
 
         // Update A: remove old byte, add new byte
         // Add MOD_ADLER*2 to avoid underflow in modular arithmetic
@@ -78,16 +140,87 @@ impl Adler32 {
         self.b = (self.b + MOD_ADLER * 3 - n_old + self.a - 1) % MOD_ADLER;
     }
 
-    /// Get the current hash value
-    pub fn digest(&self) -> u32 {
+    fn digest(&self) -> u32 {
         (self.b << 16) | self.a
     }
+}
 
-    /// Reset the hasher
-    #[allow(dead_code)]
-    pub fn reset(&mut self) {
-        self.a = 1;
-        self.b = 0;
+/// Random per-byte values for Buzhash, fixed at compile time (splitmix64)
+///
+/// Part of the wire protocol: both sides must use the same table.
+const BUZHASH_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        table[i] = (z >> 32) as u32;
+        i += 1;
+    }
+    table
+};
+
+/// Independent accumulators used to hash a block without a serial dependency
+const BUZHASH_LANES: usize = 32;
+
+/// Buzhash (cyclic polynomial) rolling hash
+///
+/// The hash of a window is the XOR of each byte's table value, rotated by
+/// its distance from the end of the window. Rolling is two table lookups,
+/// two rotates and two XORs - no modulo. Byte `i` of an `n`-byte block is
+/// rotated by `(n - 1 - i) mod 32`, so whole blocks are hashed in 32
+/// independent lanes that the compiler can vectorize, then combined.
+#[derive(Debug, Clone)]
+pub struct Buzhash {
+    hash: u32,
+    /// Rotation of a byte leaving the window (block_size mod 32)
+    out_rotation: u32,
+}
+
+impl RollingHash for Buzhash {
+    fn new(block_size: usize) -> Self {
+        Self {
+            hash: 0,
+            out_rotation: (block_size % 32) as u32,
+        }
+    }
+
+    fn hash(data: &[u8]) -> u32 {
+        let mut lanes = [0u32; BUZHASH_LANES];
+        let mut chunks = data.chunks_exact(BUZHASH_LANES);
+        for chunk in &mut chunks {
+            for (lane, &byte) in lanes.iter_mut().zip(chunk) {
+                *lane ^= BUZHASH_TABLE[byte as usize];
+            }
+        }
+        for (lane, &byte) in lanes.iter_mut().zip(chunks.remainder()) {
+            *lane ^= BUZHASH_TABLE[byte as usize];
+        }
+
+        // Lane r holds bytes at offsets r, r+32, ..., all rotated by (n-1-r) mod 32
+        let n = data.len();
+        lanes.iter().enumerate().fold(0, |hash, (r, &lane)| {
+            let rotation = (n + BUZHASH_LANES - 1 - r) % BUZHASH_LANES;
+            hash ^ lane.rotate_left(rotation as u32)
+        })
+    }
+
+    fn update_block(&mut self, block: &[u8]) {
+        self.hash = Self::hash(block);
+    }
+
+    fn roll(&mut self, old_byte: u8, new_byte: u8) {
+        self.hash = self.hash.rotate_left(1)
+            ^ BUZHASH_TABLE[old_byte as usize].rotate_left(self.out_rotation)
+            ^ BUZHASH_TABLE[new_byte as usize];
+    }
+
+    fn digest(&self) -> u32 {
+        self.hash
     }
 }
 
@@ -263,4 +396,72 @@ mod tests {
             );
         }
     }
+
+    /// Serial definition of Buzhash, to check the lane-parallel version against
+    fn buzhash_serial(data: &[u8]) -> u32 {
+        data.iter()
+            .fold(0u32, |h, &b| h.rotate_left(1) ^ BUZHASH_TABLE[b as usize])
+    }
+
+    #[test]
+    fn test_buzhash_lanes_match_serial() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 31 % 256) as u8).collect();
+        for len in [0, 1, 31, 32, 33, 64, 100, 512, 1000] {
+            assert_eq!(
+                Buzhash::hash(&data[..len]),
+                buzhash_serial(&data[..len]),
+                "Lane mismatch for length {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_buzhash_rolling_correctness() {
+        let data: Vec<u8> = (0..2000u32).map(|i| (i * 131 % 251) as u8).collect();
+        for block_size in [8, 32, 33, 100, 512] {
+            let mut hasher = Buzhash::new(block_size);
+            hasher.update_block(&data[0..block_size]);
+
+            for i in 1..=(data.len() - block_size) {
+                hasher.roll(data[i - 1], data[i + block_size - 1]);
+                assert_eq!(
+                    hasher.digest(),
+                    Buzhash::hash(&data[i..i + block_size]),
+                    "Rolling mismatch at position {} (block size {})",
+                    i,
+                    block_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_buzhash_table_is_stable() {
+        // The table is part of the wire protocol; changing it breaks delta sync
+        // between versions
+        assert_eq!(BUZHASH_TABLE[0], 0xe220a839);
+        assert_ne!(Buzhash::hash(b"abc"), Buzhash::hash(b"acb"));
+    }
+
+    #[test]
+    fn test_adler32_deferred_modulo_matches_naive() {
+        let naive = |data: &[u8]| {
+            let (mut a, mut b) = (1u32, 0u32);
+            for &byte in data {
+                a = (a + byte as u32) % MOD_ADLER;
+                b = (b + a) % MOD_ADLER;
+            }
+            (b << 16) | a
+        };
+        let data = vec![0xFF; ADLER_NMAX * 3 + 17];
+        assert_eq!(Adler32::hash(&data), naive(&data));
+    }
+
+    #[test]
+    fn test_weak_hash_dispatch() {
+        assert_eq!(WeakHash::Adler32.hash(b"data"), Adler32::hash(b"data"));
+        assert_eq!(WeakHash::Buzhash.hash(b"data"), Buzhash::hash(b"data"));
+        assert_eq!(WeakHash::default(), WeakHash::Adler32);
+    }
 }
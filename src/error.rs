@@ -44,6 +44,38 @@ pub enum SyncError {
     #[error("Invalid path: {path}\nPaths must be valid UTF-8 and not contain invalid characters.")]
     InvalidPath { path: PathBuf },
 
+    #[error("Destination is locked by another sync: {path}\nHolder: {holder}\nWait for it to finish, or remove {lock_file} if no sync is running. Use --no-lock to skip locking.")]
+    DestinationLocked {
+        path: PathBuf,
+        holder: String,
+        lock_file: PathBuf,
+    },
+
+    #[error("Destination is not writable: {path}\n{reason}")]
+    DestinationNotWritable { path: PathBuf, reason: String },
+
+    #[error("ACL model mismatch for {path}: source uses {source_model} ACLs, destination uses {dest_model}\nUse --acl-map translate to convert them, or --acl-map skip to leave them unapplied.")]
+    AclModelMismatch {
+        path: PathBuf,
+        source_model: String,
+        dest_model: String,
+    },
+
+    #[error("Symlink loop detected: {path}\nThe directory links back to one of its ancestors and was skipped.")]
+    SymlinkLoop { path: PathBuf },
+
+    #[error("Dangling symlink in source: {path} -> {target}\nIts target doesn't exist. Use --dangling-links preserve to copy the link as it is, or --dangling-links skip to leave it out.")]
+    DanglingSymlink { path: PathBuf, target: PathBuf },
+
+    #[error("Special file in source ({kind}): {path}\nsy doesn't copy sockets, FIFOs or device nodes. Exclude it, or use --specials warn or --specials skip.")]
+    SpecialFile { path: PathBuf, kind: String },
+
+    #[error("Name not valid on Windows ({problem}): {path}\nRename or exclude it, or use --windows-names sanitize or --windows-names skip.")]
+    WindowsName { path: PathBuf, problem: String },
+
+    #[error("Source file changed during transfer: {path}\nIt was modified while being read, so the copy may be torn. It is retried with --retry-failed, or copied again by the next sync.")]
+    SourceChanged { path: PathBuf },
+
     #[error("Insufficient disk space: {path}\nRequired: {required} bytes ({required_fmt})\nAvailable: {available} bytes ({available_fmt})\nFree up space or reduce the amount of data to sync.",
         required_fmt = format_bytes(*required),
         available_fmt = format_bytes(*available))]
@@ -53,19 +85,54 @@ pub enum SyncError {
         available: u64,
     },
 
-    #[allow(dead_code)] // Used in future phases (network sync)
+    #[error("Destination quota exceeded: {path}\nProjected usage: {projected} bytes ({projected_fmt})\nQuota: {quota} bytes ({quota_fmt})\nRaise --dest-quota, free up space, or use --quota-mode skip-largest to sync what fits.",
+        projected_fmt = format_bytes(*projected),
+        quota_fmt = format_bytes(*quota))]
+    QuotaExceeded {
+        path: PathBuf,
+        projected: u64,
+        quota: u64,
+    },
+
     #[error("Network error: {message}\nCheck your network connection and try again.")]
     NetworkError { message: String },
 
+    #[error("Failed to snapshot the source {path}: {message}\nSnapshots usually need root. Run without --snapshot-source to sync the live tree.")]
+    Snapshot { path: PathBuf, message: String },
+
     #[error("Hook execution failed: {0}\nCheck your hook script for errors or use --no-hooks to disable.")]
     Hook(String),
 
     #[error("Configuration error: {0}")]
     Config(String),
 
+    #[error("Source and destination are the same file: {path} and {dest}\nThey resolve to the same device and inode (a hard link, bind mount, symlinked directory or the same path twice), so writing would destroy the source. Check the paths.")]
+    SameFile { path: PathBuf, dest: PathBuf },
+
+    #[error("Destination {dest} is inside the source {path}\nEvery sync would copy the destination into itself again. Use --allow-nested to sync anyway, leaving the destination out of the source.")]
+    DestInsideSource { path: PathBuf, dest: PathBuf },
+
+    #[error("Source {path} is inside the destination {dest}\nWith --delete the source itself would be deleted. Use --allow-nested to sync anyway, keeping the source out of deletions.")]
+    SourceInsideDest { path: PathBuf, dest: PathBuf },
+
+    #[error("Refusing to write outside the destination: {path}\nIt leads out of {root} through `..` or a destination symlink. Check the source tree for unexpected paths, or use --keep-dirlinks if the symlink is intended.")]
+    SandboxEscape { path: PathBuf, root: PathBuf },
+
+    #[error("Destination is immutable or append-only: {path}\n{hint}")]
+    Immutable { path: PathBuf, hint: String },
+
+    #[error("Refused by restricted sy-remote: {0}\nThe SSH key only allows sy access to one directory tree (sy-remote restrict).")]
+    Restricted(String),
+
     #[error("Database error: {0}\nCheck that the destination directory is writable.")]
     Database(String),
 
+    #[error("Sync cancelled before it finished\nEntries already transferred are kept; run the sync again to complete it.")]
+    Cancelled,
+
+    #[error("Sync not committed: {failed} files failed, so the destination was left unchanged\n--delay-updates only moves files into place once all of them are staged. Fix the errors above and sync again.")]
+    NotCommitted { failed: usize },
+
     #[error("Data corruption detected: {path}\nBlock {block_number} checksum mismatch after write.\nExpected: {expected_checksum}\nActual: {actual_checksum}\nThis indicates storage or memory corruption. The transfer has been aborted.")]
     BlockCorruption {
         path: PathBuf,
@@ -1,7 +1,52 @@
 use anyhow::{Context, Result};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Global ignore file name in the sy config directory (like git's core.excludesFile)
+pub const GLOBAL_IGNORE_FILE_NAME: &str = "ignore";
+
+/// Path of the global ignore file (~/.config/sy/ignore)
+pub fn global_ignore_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("sy").join(GLOBAL_IGNORE_FILE_NAME))
+}
+
+/// Read a pattern list for `--include-from`/`--exclude-from`
+///
+/// `source` is a file path, `-` for stdin, or an `http://`/`https://` URL, so
+/// centrally managed lists can be pulled at run time. URLs are fetched with
+/// `curl`, which must be on the PATH.
+pub fn read_pattern_list(source: &str) -> Result<String> {
+    use std::io::Read;
+
+    if source == "-" {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read patterns from stdin")?;
+        return Ok(text);
+    }
+
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let output = std::process::Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location"])
+            .args(["--max-time", "60"])
+            .arg(source)
+            .output()
+            .with_context(|| format!("Failed to run curl to fetch {}", source))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to fetch {}: {}",
+                source,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        return String::from_utf8(output.stdout)
+            .with_context(|| format!("Pattern list at {} is not UTF-8", source));
+    }
+
+    std::fs::read_to_string(source).with_context(|| format!("Failed to open {}", source))
+}
 
 /// Filter rule action
 #[derive(Debug, Clone, PartialEq)]
@@ -10,6 +55,18 @@ pub enum FilterAction {
     Include,
     /// Exclude the file
     Exclude,
+    /// Never delete the matching destination entry (`P`, with --delete)
+    Protect,
+    /// Undo a later protect rule for the matching entry (`R`)
+    Risk,
+}
+
+impl FilterAction {
+    /// Whether rules with this action decide what is transferred, rather
+    /// than what may be deleted
+    fn is_transfer(&self) -> bool {
+        matches!(self, FilterAction::Include | FilterAction::Exclude)
+    }
 }
 
 /// A single filter rule
@@ -155,6 +212,8 @@ impl FilterEngine {
     /// Rules can be:
     /// - "+ pattern" - Include rule
     /// - "- pattern" - Exclude rule
+    /// - "P pattern" or "protect pattern" - Protect destination entries from deletion
+    /// - "R pattern" or "risk pattern" - Don't protect them after all
     /// - "pattern" - Defaults to exclude
     pub fn add_rule(&mut self, rule: &str) -> Result<()> {
         let rule = rule.trim();
@@ -172,6 +231,16 @@ impl FilterEngine {
             (FilterAction::Exclude, pattern.trim())
         } else if let Some(pattern) = rule.strip_prefix("-") {
             (FilterAction::Exclude, pattern.trim())
+        } else if let Some(pattern) = rule
+            .strip_prefix("P ")
+            .or_else(|| rule.strip_prefix("protect "))
+        {
+            (FilterAction::Protect, pattern.trim())
+        } else if let Some(pattern) = rule
+            .strip_prefix("R ")
+            .or_else(|| rule.strip_prefix("risk "))
+        {
+            (FilterAction::Risk, pattern.trim())
         } else {
             // Default to exclude if no prefix
             (FilterAction::Exclude, rule)
@@ -200,6 +269,27 @@ impl FilterEngine {
         Ok(())
     }
 
+    /// Exclude the directory at `relative` (from the root, not any name that
+    /// matches) and its contents, ahead of every other rule
+    pub fn add_exclude_path(&mut self, relative: &Path) -> Result<()> {
+        let path = relative
+            .to_str()
+            .with_context(|| format!("Path is not UTF-8: {}", relative.display()))?;
+        let pattern = glob::Pattern::new(&glob::Pattern::escape(path))
+            .with_context(|| format!("Invalid filter path: {}", path))?;
+        self.rules.insert(
+            0,
+            FilterRule {
+                action: FilterAction::Exclude,
+                pattern,
+                pattern_str: path.to_string(),
+                has_slash: true,
+                is_dir_only: true,
+            },
+        );
+        Ok(())
+    }
+
     /// Load filter rules from a file
     pub fn add_rules_from_file(&mut self, file_path: &Path) -> Result<()> {
         let file = File::open(file_path)
@@ -255,13 +345,18 @@ impl FilterEngine {
     ///
     /// Returns Ok(true) if file was loaded, Ok(false) if file doesn't exist
     pub fn add_syignore_if_exists(&mut self, directory: &Path) -> Result<bool> {
-        let syignore_path = directory.join(".syignore");
+        self.add_rules_from_file_if_exists(&directory.join(".syignore"))
+    }
 
-        if !syignore_path.exists() {
+    /// Load filter rules from a file if it exists
+    ///
+    /// Returns Ok(true) if file was loaded, Ok(false) if file doesn't exist
+    pub fn add_rules_from_file_if_exists(&mut self, file_path: &Path) -> Result<bool> {
+        if !file_path.exists() {
             return Ok(false);
         }
 
-        self.add_rules_from_file(&syignore_path)?;
+        self.add_rules_from_file(file_path)?;
         Ok(true)
     }
 
@@ -276,7 +371,7 @@ impl FilterEngine {
         }
 
         // Find first matching rule
-        for rule in &self.rules {
+        for rule in self.rules.iter().filter(|rule| rule.action.is_transfer()) {
             if rule.matches(path, is_dir) {
                 return rule.action == FilterAction::Include;
             }
@@ -291,6 +386,19 @@ impl FilterEngine {
         !self.should_include(path, is_dir)
     }
 
+    /// Check if a destination path must survive --delete
+    ///
+    /// The first matching protect or risk rule decides, so a risk rule carves
+    /// exceptions out of a protect rule after it. Paths neither matches
+    /// aren't protected.
+    pub fn is_protected(&self, path: &Path, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.action.is_transfer())
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| rule.action == FilterAction::Protect)
+    }
+
     /// Get number of rules
     #[allow(dead_code)] // Public API for filter introspection
     pub fn rule_count(&self) -> usize {
@@ -539,4 +647,74 @@ mod tests {
         assert!(filter2.should_include(Path::new("build/output.txt"), false)); // basename is "output.txt", not "build"
         assert!(filter2.should_include(Path::new("building"), false)); // basename is "building", not "build"
     }
+
+    #[test]
+    fn test_exclude_path() {
+        let mut filter = FilterEngine::new();
+        filter.add_include("*").unwrap();
+        filter.add_exclude_path(Path::new("backup")).unwrap();
+
+        // Only the tree at the root, despite the earlier include
+        assert!(filter.should_exclude(Path::new("backup"), true));
+        assert!(filter.should_exclude(Path::new("backup/daily/a.txt"), false));
+        assert!(filter.should_include(Path::new("docs/backup"), true));
+        assert!(filter.should_include(Path::new("backup.txt"), false));
+
+        filter.add_exclude_path(Path::new("out [1]")).unwrap();
+        assert!(filter.should_exclude(Path::new("out [1]/a.txt"), false));
+        assert!(filter.should_include(Path::new("out 1/a.txt"), false));
+    }
+
+    #[test]
+    fn test_rules_from_missing_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut filter = FilterEngine::new();
+
+        let path = temp.path().join(GLOBAL_IGNORE_FILE_NAME);
+        assert!(!filter.add_rules_from_file_if_exists(&path).unwrap());
+
+        std::fs::write(&path, "# editor files\n*.swp\n.DS_Store\n").unwrap();
+        assert!(filter.add_rules_from_file_if_exists(&path).unwrap());
+        assert_eq!(filter.rule_count(), 2);
+        assert!(filter.should_exclude(Path::new("src/.main.rs.swp"), false));
+        assert!(filter.should_include(Path::new("src/main.rs"), false));
+    }
+
+    #[test]
+    fn test_read_pattern_list() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("excludes.txt");
+        std::fs::write(&path, "*.log\ntarget/\n").unwrap();
+
+        let text = read_pattern_list(path.to_str().unwrap()).unwrap();
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec!["*.log", "target/"]);
+        assert!(read_pattern_list(temp.path().join("missing").to_str().unwrap()).is_err());
+        // Unreachable URLs fail instead of silently filtering nothing
+        assert!(read_pattern_list("http://127.0.0.1:1/excludes.txt").is_err());
+    }
+
+    #[test]
+    fn test_protect_and_risk_rules() {
+        let mut filter = FilterEngine::new();
+        filter.add_rule("R logs/old/").unwrap();
+        filter.add_rule("P logs/").unwrap();
+        filter.add_rule("protect .env").unwrap();
+        filter.add_rule("- *.tmp").unwrap();
+
+        assert!(filter.is_protected(Path::new(".env"), false));
+        assert!(filter.is_protected(Path::new("app/.env"), false));
+        assert!(filter.is_protected(Path::new("logs"), true));
+        assert!(filter.is_protected(Path::new("logs/app.log"), false));
+        // The earlier risk rule wins for its subtree
+        assert!(!filter.is_protected(Path::new("logs/old/app.log"), false));
+        assert!(!filter.is_protected(Path::new("build.tmp"), false));
+
+        // Protect and risk rules don't filter what is transferred
+        assert!(filter.should_include(Path::new(".env"), false));
+        assert!(filter.should_include(Path::new("logs/app.log"), false));
+        assert!(filter.should_exclude(Path::new("build.tmp"), false));
+        // Without a P/R prefix a name is still an exclude pattern
+        filter.add_rule("Pictures").unwrap();
+        assert!(filter.should_exclude(Path::new("Pictures"), true));
+    }
 }
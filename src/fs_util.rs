@@ -2,7 +2,11 @@
 ///
 /// This module provides platform-specific filesystem detection to enable intelligent
 /// strategy selection in delta sync operations.
-use std::path::Path;
+use crate::sync::output::MetadataError;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Check if a filesystem supports copy-on-write (COW) reflinks
 ///
@@ -123,6 +127,147 @@ pub fn supports_cow_reflinks(_path: &Path) -> bool {
     false
 }
 
+/// Whether files under `path` land on Windows or an SMB share, whose naming
+/// rules reject names other systems allow (`--windows-names`)
+///
+/// `path` doesn't need to exist yet; its nearest existing ancestor is checked.
+#[cfg(windows)]
+pub fn needs_windows_names(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(target_os = "linux")]
+pub fn needs_windows_names(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return false;
+    };
+    let path_c = match CString::new(existing.as_os_str().as_bytes()) {
+        Ok(p) => p,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        let mut stat: std::mem::MaybeUninit<libc::statfs> = std::mem::MaybeUninit::uninit();
+        if libc::statfs(path_c.as_ptr(), stat.as_mut_ptr()) == 0 {
+            let stat = stat.assume_init();
+            // CIFS_SUPER_MAGIC = 0xFF534D42
+            // SMB2_SUPER_MAGIC = 0xFE534D42
+            // SMB_SUPER_MAGIC = 0x517B
+            matches!(stat.f_type, 0xFF534D42 | 0xFE534D42 | 0x517B)
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn needs_windows_names(path: &Path) -> bool {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return false;
+    };
+    let path_c = match CString::new(existing.as_os_str().as_bytes()) {
+        Ok(p) => p,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        let mut stat: std::mem::MaybeUninit<libc::statfs> = std::mem::MaybeUninit::uninit();
+        if libc::statfs(path_c.as_ptr(), stat.as_mut_ptr()) == 0 {
+            let stat = stat.assume_init();
+            CStr::from_ptr(stat.f_fstypename.as_ptr()).to_bytes() == b"smbfs"
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+pub fn needs_windows_names(_path: &Path) -> bool {
+    false
+}
+
+/// Whether `path` is on a network filesystem (NFS, SMB, FUSE, ...), where
+/// writing a whole file costs far more than reading one
+///
+/// `path` doesn't need to exist yet; its nearest existing ancestor is checked.
+#[cfg(target_os = "linux")]
+pub fn is_network_filesystem(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return false;
+    };
+    let path_c = match CString::new(existing.as_os_str().as_bytes()) {
+        Ok(p) => p,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        let mut stat: std::mem::MaybeUninit<libc::statfs> = std::mem::MaybeUninit::uninit();
+        if libc::statfs(path_c.as_ptr(), stat.as_mut_ptr()) == 0 {
+            let stat = stat.assume_init();
+            // NFS_SUPER_MAGIC = 0x6969
+            // SMB_SUPER_MAGIC = 0x517B, CIFS = 0xFF534D42, SMB2 = 0xFE534D42
+            // FUSE_SUPER_MAGIC = 0x65735546
+            // V9FS_MAGIC = 0x01021997, CEPH_SUPER_MAGIC = 0x00C36400
+            // AFS_FS_MAGIC = 0x6B414653, CODA_SUPER_MAGIC = 0x73757245
+            matches!(
+                stat.f_type,
+                0x6969
+                    | 0x517B
+                    | 0xFF534D42
+                    | 0xFE534D42
+                    | 0x65735546
+                    | 0x01021997
+                    | 0x00C36400
+                    | 0x6B414653
+                    | 0x73757245
+            )
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_network_filesystem(path: &Path) -> bool {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return false;
+    };
+    let path_c = match CString::new(existing.as_os_str().as_bytes()) {
+        Ok(p) => p,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        let mut stat: std::mem::MaybeUninit<libc::statfs> = std::mem::MaybeUninit::uninit();
+        if libc::statfs(path_c.as_ptr(), stat.as_mut_ptr()) == 0 {
+            let stat = stat.assume_init();
+            matches!(
+                CStr::from_ptr(stat.f_fstypename.as_ptr()).to_bytes(),
+                b"nfs" | b"smbfs" | b"afpfs" | b"webdav" | b"macfuse" | b"osxfuse"
+            )
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn is_network_filesystem(_path: &Path) -> bool {
+    false
+}
+
 /// Check if two paths are on the same filesystem
 ///
 /// COW reflinks only work within the same filesystem.
@@ -173,6 +318,66 @@ pub fn same_filesystem(_path1: &Path, _path2: &Path) -> bool {
     false
 }
 
+/// Check if two paths are the same file or directory (same device and inode)
+///
+/// Symlinks are followed, so bind mounts, symlinked directories and one path
+/// spelled two ways all match. Returns false if either path can't be read.
+#[cfg(unix)]
+pub fn same_file(path1: &Path, path2: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (std::fs::metadata(path1), std::fs::metadata(path2)) {
+        (Ok(meta1), Ok(meta2)) => meta1.dev() == meta2.dev() && meta1.ino() == meta2.ino(),
+        _ => false,
+    }
+}
+
+/// No inode numbers: compare the canonical paths
+#[cfg(not(unix))]
+pub fn same_file(path1: &Path, path2: &Path) -> bool {
+    match (path1.canonicalize(), path2.canonicalize()) {
+        (Ok(path1), Ok(path2)) => path1 == path2,
+        _ => false,
+    }
+}
+
+/// Where `inner` lies inside the directory `outer`, relative to it
+///
+/// Each ancestor of `inner` is compared with `outer` using `same_file`, so
+/// symlinked directories and bind mounts are seen through. `inner` need not
+/// exist yet. Returns None unless `inner` is strictly inside `outer`.
+pub fn nested_in(outer: &Path, inner: &Path) -> Option<PathBuf> {
+    if !outer.is_dir() {
+        return None;
+    }
+    let inner = canonicalize_existing(inner)?;
+    for ancestor in inner.ancestors().skip(1) {
+        if same_file(ancestor, outer) {
+            return inner.strip_prefix(ancestor).ok().map(Path::to_path_buf);
+        }
+    }
+    None
+}
+
+/// `path` made absolute, with symlinks resolved in the part of it that exists
+fn canonicalize_existing(path: &Path) -> Option<PathBuf> {
+    let path = std::env::current_dir().ok()?.join(path);
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return Some(
+                missing
+                    .iter()
+                    .rev()
+                    .fold(canonical, |path, name| path.join(name)),
+            );
+        }
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+}
+
 /// Check if a file has hard links (nlink > 1)
 ///
 /// If a file has hard links, COW cloning would break the link relationship.
@@ -221,12 +426,727 @@ pub fn has_hard_links(_path: &Path) -> bool {
     false
 }
 
+/// Result of applying extended attributes to a file
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct XattrsApplied {
+    /// Number of attributes written
+    pub applied: usize,
+    /// Privileged attributes (e.g. `security.capability`) not written because
+    /// the process lacks the required privileges
+    pub skipped_privileged: Vec<String>,
+    /// Attributes that failed to write, and why
+    pub failed: Vec<MetadataError>,
+}
+
+/// Result of applying ACLs to a file
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AclsApplied {
+    /// Number of entries written
+    pub applied: usize,
+    /// Entries that couldn't be parsed, translated or written, and why
+    pub failed: Vec<MetadataError>,
+}
+
+/// Whether an xattr lives in a namespace that needs privileges to write
+///
+/// `trusted.*` requires CAP_SYS_ADMIN; `security.capability` (file
+/// capabilities) requires CAP_SETFCAP. Other `security.*` attributes, such as
+/// SELinux labels, are governed by policy and may or may not be writable.
+pub fn is_privileged_xattr(name: &str) -> bool {
+    name.starts_with("security.") || name.starts_with("trusted.")
+}
+
+/// Whether writing a privileged xattr can succeed (root or the matching capability)
+#[cfg(target_os = "linux")]
+fn may_set_privileged_xattr(name: &str) -> bool {
+    const CAP_SYS_ADMIN: u32 = 21;
+    const CAP_SETFCAP: u32 = 31;
+
+    if unsafe { libc::geteuid() } == 0 {
+        return true;
+    }
+
+    let cap = if name == "security.capability" {
+        CAP_SETFCAP
+    } else if name.starts_with("trusted.") {
+        CAP_SYS_ADMIN
+    } else {
+        // Policy-controlled (e.g. SELinux): only an attempt can tell
+        return true;
+    };
+
+    effective_capabilities().is_some_and(|caps| caps & (1 << cap) != 0)
+}
+
+/// Effective capability set of this process (CapEff in /proc/self/status)
+#[cfg(target_os = "linux")]
+fn effective_capabilities() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let hex = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))?
+        .trim();
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// Other Unix platforms have no capability namespaces; let the write decide
+#[cfg(all(unix, not(target_os = "linux")))]
+fn may_set_privileged_xattr(_name: &str) -> bool {
+    true
+}
+
+/// Apply extended attributes to a file
+///
+/// Best-effort: attributes that fail to apply (unsupported namespace, permission
+/// denied) are skipped and returned in `failed`. Privileged attributes that can't be written
+/// without root/CAP_SETFCAP are not attempted; they are returned in
+/// `skipped_privileged` so callers can report them once per sync instead of
+/// warning for every file.
+#[cfg(unix)]
+pub fn apply_xattrs(path: &Path, xattrs: &HashMap<String, Vec<u8>>) -> XattrsApplied {
+    use crate::sync::output::{MetadataFailure, WarningCategory};
+
+    let mut result = XattrsApplied::default();
+
+    // Privileged attributes go last: file capabilities are cleared by the
+    // kernel whenever the file is modified
+    let mut names: Vec<&String> = xattrs.keys().collect();
+    names.sort_by_key(|name| is_privileged_xattr(name));
+
+    for name in names {
+        let privileged = is_privileged_xattr(name);
+        if privileged && !may_set_privileged_xattr(name) {
+            tracing::debug!(
+                "Not setting privileged xattr {} on {}: insufficient privileges",
+                name,
+                path.display()
+            );
+            result.skipped_privileged.push(name.clone());
+            continue;
+        }
+
+        match xattr::set(path, name, &xattrs[name]) {
+            Ok(()) => {
+                tracing::debug!("Set xattr {} on {}", name, path.display());
+                result.applied += 1;
+            }
+            Err(e) if privileged && e.kind() == std::io::ErrorKind::PermissionDenied => {
+                tracing::debug!(
+                    "Refused privileged xattr {} on {}: {}",
+                    name,
+                    path.display(),
+                    e
+                );
+                result.skipped_privileged.push(name.clone());
+            }
+            Err(e) => result.failed.push(MetadataError::new(
+                WarningCategory::Xattr,
+                MetadataFailure::from_io_error(&e),
+                format!("Failed to set xattr {} on {}: {}", name, path.display(), e),
+            )),
+        }
+    }
+    result
+}
+
+#[cfg(not(unix))]
+pub fn apply_xattrs(_path: &Path, _xattrs: &HashMap<String, Vec<u8>>) -> XattrsApplied {
+    // xattrs not supported on non-Unix platforms
+    XattrsApplied::default()
+}
+
+/// Apply ACLs to a file from their text representation (one entry per line)
+///
+/// This is the format produced by the scanner (e.g. "allow::user:alice:read").
+/// Entries that fail to parse are skipped and returned in `failed`, as are
+/// ACLs the filesystem refuses. Entries from a different ACL model than this
+/// platform's (POSIX vs NFSv4) are handled per `policy`; only
+/// `AclMapPolicy::Strict` returns an error.
+#[cfg(unix)]
+pub fn apply_acls(
+    path: &Path,
+    acls: &[u8],
+    policy: crate::acl::AclMapPolicy,
+) -> crate::error::Result<AclsApplied> {
+    use crate::acl::{detect_model, map_acls, AclModel, AclRule};
+    use crate::sync::output::{MetadataFailure, WarningCategory};
+    use exacl::{setfacl, AclEntry};
+    use std::os::unix::fs::PermissionsExt;
+    use std::str::FromStr;
+
+    let unparsable =
+        |message| MetadataError::new(WarningCategory::Acl, MetadataFailure::Other, message);
+    let mut result = AclsApplied::default();
+
+    let acls_text = match std::str::from_utf8(acls) {
+        Ok(text) => text,
+        Err(e) => {
+            result.failed.push(unparsable(format!(
+                "Failed to parse ACL text for {}: {}",
+                path.display(),
+                e
+            )));
+            return Ok(result);
+        }
+    };
+
+    // Parse each line as an ACL entry
+    let mut rules = Vec::new();
+    for line in acls_text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match AclRule::parse(line) {
+            Some(rule) => rules.push(rule),
+            None => result.failed.push(unparsable(format!(
+                "Failed to parse ACL entry '{}' for {}",
+                line,
+                path.display()
+            ))),
+        }
+    }
+
+    if rules.is_empty() {
+        tracing::debug!("No valid ACL entries to write for {}", path.display());
+        return Ok(result);
+    }
+
+    // Translation needs the mode bits (POSIX base entries) and file type
+    let metadata = std::fs::symlink_metadata(path)?;
+    let source_model = detect_model(&rules);
+    let mapped = map_acls(
+        path,
+        rules,
+        policy,
+        AclModel::native(),
+        metadata.permissions().mode(),
+        metadata.is_dir(),
+    )?;
+    if mapped.dropped > 0 {
+        result.failed.push(MetadataError::new(
+            WarningCategory::Acl,
+            MetadataFailure::Unsupported,
+            format!(
+                "Dropped {} {} ACL entries with no {} equivalent on {}",
+                mapped.dropped,
+                source_model,
+                AclModel::native(),
+                path.display()
+            ),
+        ));
+    }
+
+    let mut acl_entries = Vec::new();
+    for rule in mapped.rules {
+        match AclEntry::from_str(&rule.to_string()) {
+            Ok(entry) => acl_entries.push(entry),
+            Err(e) => result.failed.push(unparsable(format!(
+                "Failed to parse ACL entry '{}' for {}: {}",
+                rule,
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    if acl_entries.is_empty() {
+        tracing::debug!("No valid ACL entries to write for {}", path.display());
+        return Ok(result);
+    }
+
+    match setfacl(&[path], &acl_entries, None) {
+        Ok(_) => {
+            tracing::debug!(
+                "Successfully applied {} ACL entries to {}",
+                acl_entries.len(),
+                path.display()
+            );
+            result.applied = acl_entries.len();
+        }
+        Err(e) => result.failed.push(MetadataError::new(
+            WarningCategory::Acl,
+            MetadataFailure::from_io_error(&e),
+            format!("Failed to apply ACLs to {}: {}", path.display(), e),
+        )),
+    }
+    Ok(result)
+}
+
+#[cfg(not(unix))]
+pub fn apply_acls(
+    _path: &Path,
+    _acls: &[u8],
+    _policy: crate::acl::AclMapPolicy,
+) -> crate::error::Result<AclsApplied> {
+    // ACLs not supported on non-Unix platforms
+    Ok(AclsApplied::default())
+}
+
+/// Whether this platform can set file creation times (macOS, Windows)
+pub const CAN_SET_CRTIME: bool = cfg!(any(target_os = "macos", target_os = "ios", windows));
+
+/// Restore access and/or creation time on a file or directory
+///
+/// The modification time is left as is. Creation times can only be set on
+/// macOS (APFS/HFS+ birthtime) and Windows; elsewhere `crtime` is ignored.
+pub fn apply_times(
+    path: &Path,
+    atime: Option<std::time::SystemTime>,
+    crtime: Option<std::time::SystemTime>,
+) -> std::io::Result<()> {
+    if let Some(atime) = atime {
+        filetime::set_file_atime(path, filetime::FileTime::from_system_time(atime))?;
+    }
+
+    if let Some(crtime) = crtime {
+        set_crtime(path, crtime)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn set_crtime(path: &Path, crtime: std::time::SystemTime) -> std::io::Result<()> {
+    #[cfg(target_os = "ios")]
+    use std::os::ios::fs::FileTimesExt;
+    #[cfg(target_os = "macos")]
+    use std::os::macos::fs::FileTimesExt;
+
+    // setattrlist(ATTR_CMN_CRTIME) on the open file
+    let file = std::fs::File::open(path)?;
+    file.set_times(std::fs::FileTimes::new().set_created(crtime))
+}
+
+#[cfg(windows)]
+fn set_crtime(path: &Path, crtime: std::time::SystemTime) -> std::io::Result<()> {
+    use std::os::windows::fs::{FileTimesExt, OpenOptionsExt};
+
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000; // Needed to open directories
+
+    // SetFileTime with only the creation time set
+    let file = std::fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?;
+    file.set_times(std::fs::FileTimes::new().set_created(crtime))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios", windows)))]
+fn set_crtime(path: &Path, _crtime: std::time::SystemTime) -> std::io::Result<()> {
+    // Linux and most other Unixes record a birth time but offer no way to set it
+    tracing::trace!(
+        "Creation time can't be set on this platform: {}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Immutable and append-only flags (`chattr +i`, `chattr +a`)
+#[cfg(target_os = "linux")]
+const IMMUTABLE_FLAGS: u32 = 0x10 | 0x20; // FS_IMMUTABLE_FL | FS_APPEND_FL
+
+/// User and system immutable and append-only flags (`chflags uchg`, `schg`, ...)
+#[cfg(target_os = "macos")]
+const IMMUTABLE_FLAGS: u32 =
+    libc::UF_IMMUTABLE | libc::UF_APPEND | libc::SF_IMMUTABLE | libc::SF_APPEND;
+
+/// Immutable or append-only flags on `path`, which make updates and deletes fail
+///
+/// Returns 0 for ordinary entries, missing paths and platforms without such
+/// flags. Symlinks aren't followed.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn immutable_flags(path: &Path) -> u32 {
+    entry_flags(path).map_or(0, |flags| flags & IMMUTABLE_FLAGS)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn immutable_flags(_path: &Path) -> u32 {
+    0
+}
+
+/// Replace the immutable and append-only flags on `path`, keeping its other flags
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn set_immutable_flags(path: &Path, immutable: u32) -> std::io::Result<()> {
+    let flags = entry_flags(path)?;
+    let new_flags = (flags & !IMMUTABLE_FLAGS) | (immutable & IMMUTABLE_FLAGS);
+    if new_flags == flags {
+        return Ok(());
+    }
+    set_entry_flags(path, new_flags)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn set_immutable_flags(_path: &Path, _immutable: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Whether this process may clear immutable flags (root or CAP_LINUX_IMMUTABLE)
+///
+/// macOS lets owners clear the user flags, and only a try can tell for the
+/// system ones.
+#[cfg(target_os = "linux")]
+pub fn may_clear_immutable() -> bool {
+    const CAP_LINUX_IMMUTABLE: u32 = 9;
+
+    if unsafe { libc::geteuid() } == 0 {
+        return true;
+    }
+    effective_capabilities().is_some_and(|caps| caps & (1 << CAP_LINUX_IMMUTABLE) != 0)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn may_clear_immutable() -> bool {
+    true
+}
+
+/// Inode flags (FS_IOC_GETFLAGS) of a regular file or directory
+#[cfg(target_os = "linux")]
+fn entry_flags(path: &Path) -> std::io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = open_for_flags(path)?;
+    let mut flags: libc::c_int = 0;
+    if unsafe {
+        libc::ioctl(
+            file.as_raw_fd(),
+            libc::FS_IOC_GETFLAGS,
+            &mut flags as *mut libc::c_int,
+        )
+    } != 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(flags as u32)
+}
+
+#[cfg(target_os = "linux")]
+fn set_entry_flags(path: &Path, flags: u32) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = open_for_flags(path)?;
+    let flags = flags as libc::c_int;
+    if unsafe {
+        libc::ioctl(
+            file.as_raw_fd(),
+            libc::FS_IOC_SETFLAGS,
+            &flags as *const libc::c_int,
+        )
+    } != 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Open a regular file or directory just to read or change its inode flags
+#[cfg(target_os = "linux")]
+fn open_for_flags(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // Devices, FIFOs and sockets aren't opened: that can have side effects
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_file() && !metadata.is_dir() {
+        return Err(std::io::Error::from(std::io::ErrorKind::Unsupported));
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+        .open(path)
+}
+
+/// BSD file flags (st_flags) of a regular file or directory
+#[cfg(target_os = "macos")]
+fn entry_flags(path: &Path) -> std::io::Result<u32> {
+    use std::os::darwin::fs::MetadataExt;
+
+    // chflags follows symlinks, so theirs are left alone
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.is_symlink() {
+        return Ok(0);
+    }
+    Ok(metadata.st_flags())
+}
+
+#[cfg(target_os = "macos")]
+fn set_entry_flags(path: &Path, flags: u32) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    if unsafe { libc::chflags(c_path.as_ptr(), flags as _) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+static NOATIME_OPENS: AtomicU64 = AtomicU64::new(0);
+static NOATIME_FALLBACKS: AtomicU64 = AtomicU64::new(0);
+
+/// Whether this platform has `O_NOATIME` (Linux)
+pub const CAN_OPEN_NOATIME: bool = cfg!(any(target_os = "linux", target_os = "android"));
+
+/// Process-wide `--open-noatime` counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct NoatimeStats {
+    /// Source files opened with `O_NOATIME`
+    pub opened: u64,
+    /// Opens that fell back to a plain open (`O_NOATIME` needs file ownership)
+    pub fallbacks: u64,
+}
+
+impl NoatimeStats {
+    /// Counters accumulated since `start`
+    pub fn since(&self, start: &NoatimeStats) -> NoatimeStats {
+        NoatimeStats {
+            opened: self.opened.saturating_sub(start.opened),
+            fallbacks: self.fallbacks.saturating_sub(start.fallbacks),
+        }
+    }
+}
+
+pub fn noatime_stats() -> NoatimeStats {
+    NoatimeStats {
+        opened: NOATIME_OPENS.load(Ordering::Relaxed),
+        fallbacks: NOATIME_FALLBACKS.load(Ordering::Relaxed),
+    }
+}
+
+/// Open a source file for reading
+///
+/// With `noatime` (`--open-noatime`) on Linux this uses `O_NOATIME`, which the
+/// kernel only allows for the file's owner (or with CAP_FOWNER); otherwise it
+/// falls back to a plain open and the access time is updated as usual.
+pub fn open_source(path: &Path, noatime: bool) -> std::io::Result<File> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if noatime {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOATIME)
+            .open(path)
+        {
+            Ok(file) => {
+                NOATIME_OPENS.fetch_add(1, Ordering::Relaxed);
+                return Ok(file);
+            }
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => {
+                NOATIME_FALLBACKS.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    File::open(path)
+}
+
+/// Copy a source file like `std::fs::copy`, honoring `noatime` as
+/// `open_source` does
+///
+/// Without `O_NOATIME` this is `std::fs::copy` (clonefile/copy_file_range).
+/// With it the source is opened here instead; `io::copy` between files still
+/// uses copy_file_range on Linux.
+pub fn copy_source(source: &Path, dest: &Path, noatime: bool) -> std::io::Result<u64> {
+    if !CAN_OPEN_NOATIME || !noatime {
+        return std::fs::copy(source, dest);
+    }
+
+    let mut src_file = open_source(source, true)?;
+    let permissions = src_file.metadata()?.permissions();
+    let mut dst_file = File::create(dest)?;
+    let bytes = std::io::copy(&mut src_file, &mut dst_file)?;
+    dst_file.set_permissions(permissions)?;
+    Ok(bytes)
+}
+
+/// Where `path` really leads, resolving symlinks as far as the path exists
+///
+/// `.` and `..` are resolved lexically first. The part that doesn't exist yet
+/// can't contain symlinks, so it is appended as is. With `follow_last` off, a
+/// symlink as the last component is kept rather than followed (for removing
+/// or replacing the link itself).
+pub fn resolve_path(path: &Path, follow_last: bool) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+
+    let name = (!follow_last).then(|| normal.file_name()).flatten();
+    let dir = match name {
+        Some(_) => normal.parent().unwrap_or(&normal),
+        None => &normal,
+    };
+
+    let mut missing = Vec::new();
+    let mut existing = dir;
+    let mut resolved = loop {
+        match existing.canonicalize() {
+            Ok(resolved) => break resolved,
+            Err(_) => match (existing.file_name(), existing.parent()) {
+                (Some(component), Some(parent)) => {
+                    missing.push(component);
+                    existing = parent;
+                }
+                _ => break existing.to_path_buf(),
+            },
+        }
+    };
+    resolved.extend(missing.iter().rev());
+    resolved.extend(name);
+    resolved
+}
+
+/// Why files can't be written into `dest`, or `None` if they can
+///
+/// Creates and removes a probe file in `dest`, or in its nearest existing
+/// ancestor when the sync would create it. Only a read-only filesystem or a
+/// missing permission counts; other failures are left to the transfers, and
+/// immutable directories to `--immutable`. Returns the directory probed and
+/// what to do about it.
+pub fn unwritable_reason(dest: &Path) -> Option<(PathBuf, String)> {
+    let mut dir = dest;
+    while !dir.is_dir() {
+        if dir.exists() {
+            // A single-file destination lands in its parent
+            dir = dir.parent()?;
+            break;
+        }
+        dir = match dir.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+            Some(parent) => parent,
+            None => return None,
+        };
+    }
+
+    let probe = dir.join(format!(
+        ".sy-write-probe.{}{}",
+        std::process::id(),
+        crate::temp_file::TEMP_SUFFIX
+    ));
+    let err = match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            return None;
+        }
+        Err(e) => e,
+    };
+
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(libc::EROFS) {
+        return Some((
+            dir.to_path_buf(),
+            "It is on a read-only filesystem. Remount it read-write, or pick another destination."
+                .to_string(),
+        ));
+    }
+    if err.kind() == std::io::ErrorKind::PermissionDenied && immutable_flags(dir) == 0 {
+        return Some((
+            dir.to_path_buf(),
+            format!(
+                "You ({}) lack permission to create files in it. Check its owner and mode, or run sy as a user who can write there.",
+                whoami::username()
+            ),
+        ));
+    }
+    tracing::debug!("Write probe in {} failed: {}", dir.display(), err);
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_path() {
+        let temp = TempDir::new().unwrap();
+        let base = temp.path().canonicalize().unwrap();
+        fs::create_dir(base.join("real")).unwrap();
+        std::os::unix::fs::symlink(base.join("real"), base.join("link")).unwrap();
+
+        assert_eq!(
+            resolve_path(&base.join("link/new/file"), true),
+            base.join("real/new/file")
+        );
+        assert_eq!(resolve_path(&base.join("link"), false), base.join("link"));
+        assert_eq!(
+            resolve_path(&base.join("a/../link/."), true),
+            base.join("real")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unwritable_reason() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("dest");
+        // A destination the sync would create is probed in its existing ancestor
+        assert_eq!(unwritable_reason(&dest.join("a/b")), None);
+
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("file.txt"), "data").unwrap();
+        assert_eq!(unwritable_reason(&dest), None);
+        assert_eq!(unwritable_reason(&dest.join("file.txt")), None);
+        // The probe file doesn't stay behind
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 1);
+
+        // Root ignores permission bits
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        fs::set_permissions(&dest, fs::Permissions::from_mode(0o555)).unwrap();
+        let unwritable = unwritable_reason(&dest.join("new/dir"));
+        fs::set_permissions(&dest, fs::Permissions::from_mode(0o755)).unwrap();
+        let (path, reason) = unwritable.unwrap();
+        assert_eq!(path, dest);
+        assert!(reason.contains("permission"), "{}", reason);
+    }
+
+    #[test]
+    fn test_apply_times_restores_atime() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, b"data").unwrap();
+        let mtime = fs::metadata(&file).unwrap().modified().unwrap();
+
+        let atime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        apply_times(&file, Some(atime), None).unwrap();
+
+        let metadata = fs::metadata(&file).unwrap();
+        assert_eq!(metadata.accessed().unwrap(), atime);
+        assert_eq!(metadata.modified().unwrap(), mtime);
+    }
+
+    #[test]
+    fn test_apply_times_crtime() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, b"data").unwrap();
+
+        let crtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        apply_times(&file, None, Some(crtime)).unwrap();
+
+        if CAN_SET_CRTIME {
+            assert_eq!(fs::metadata(&file).unwrap().created().unwrap(), crtime);
+        }
+    }
+
     #[test]
     fn test_cow_detection() {
         let temp = TempDir::new().unwrap();
@@ -527,6 +1447,48 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_same_file() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("dir");
+        fs::create_dir(&dir).unwrap();
+        std::os::unix::fs::symlink(&dir, temp.path().join("link")).unwrap();
+
+        // A symlinked directory and a path spelled another way are the same
+        assert!(same_file(&dir, &temp.path().join("link")));
+        assert!(same_file(&dir, &dir.join("..").join("dir")));
+        assert!(!same_file(&dir, temp.path()));
+        assert!(!same_file(&dir, &temp.path().join("missing")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_nested_in() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        fs::create_dir_all(source.join("backup")).unwrap();
+        std::os::unix::fs::symlink(&source, temp.path().join("link")).unwrap();
+
+        assert_eq!(
+            nested_in(&source, &source.join("backup")),
+            Some(PathBuf::from("backup"))
+        );
+        // Through a symlink, and not created yet
+        assert_eq!(
+            nested_in(&source, &temp.path().join("link/backup/daily")),
+            Some(PathBuf::from("backup/daily"))
+        );
+        assert_eq!(
+            nested_in(&temp.path().join("link"), &source.join("new")),
+            Some(PathBuf::from("new"))
+        );
+        // Not strictly inside
+        assert_eq!(nested_in(&source, &source), None);
+        assert_eq!(nested_in(&source.join("backup"), &source), None);
+        assert_eq!(nested_in(&source, &temp.path().join("sourcefoo")), None);
+    }
+
     #[test]
     #[cfg(unix)]
     fn test_has_hard_links_nonexistent() {
@@ -615,4 +1577,122 @@ mod tests {
         assert_eq!(HFS_PLUS_TYPE_NAME, "hfs");
         assert_ne!(HFS_PLUS_TYPE_NAME, "apfs");
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_apply_xattrs() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, b"data").unwrap();
+
+        let mut xattrs = HashMap::new();
+        xattrs.insert("user.sy_test".to_string(), b"value".to_vec());
+
+        // Some filesystems (e.g. tmpfs on older kernels) don't support user xattrs
+        if apply_xattrs(&file, &xattrs).applied == 0 {
+            return;
+        }
+
+        let value = xattr::get(&file, "user.sy_test").unwrap().unwrap();
+        assert_eq!(value, b"value");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_privileged_xattr_skipped_without_privileges() {
+        if may_set_privileged_xattr("security.capability") {
+            // Running as root or with CAP_SETFCAP
+            return;
+        }
+
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, b"data").unwrap();
+
+        let mut xattrs = HashMap::new();
+        xattrs.insert("security.capability".to_string(), vec![0; 20]);
+
+        let result = apply_xattrs(&file, &xattrs);
+        assert_eq!(result.applied, 0);
+        assert_eq!(result.skipped_privileged, vec!["security.capability"]);
+    }
+
+    #[test]
+    fn test_is_privileged_xattr() {
+        assert!(is_privileged_xattr("security.capability"));
+        assert!(is_privileged_xattr("trusted.overlay.opaque"));
+        assert!(!is_privileged_xattr("user.comment"));
+        assert!(!is_privileged_xattr("com.apple.quarantine"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_apply_acls_invalid_text() {
+        use crate::sync::output::{MetadataFailure, WarningCategory};
+
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, b"data").unwrap();
+
+        // Garbage entries are skipped rather than failing, and reported
+        let policy = crate::acl::AclMapPolicy::Strict;
+        for acls in [&b"not-an-acl\n\n"[..], &[0xff, 0xfe][..]] {
+            let result = apply_acls(&file, acls, policy).unwrap();
+            assert_eq!(result.applied, 0);
+            assert_eq!(result.failed.len(), 1);
+            assert_eq!(result.failed[0].category, WarningCategory::Acl);
+            assert_eq!(result.failed[0].reason, MetadataFailure::Other);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_acls_model_mismatch() {
+        use crate::acl::AclMapPolicy;
+
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, b"data").unwrap();
+
+        // Deny entries only exist in NFSv4 ACLs
+        let nfs4 = b"deny::user:root:write\n";
+        assert!(matches!(
+            apply_acls(&file, nfs4, AclMapPolicy::Strict),
+            Err(crate::error::SyncError::AclModelMismatch { .. })
+        ));
+        assert_eq!(
+            apply_acls(&file, nfs4, AclMapPolicy::Skip).unwrap(),
+            AclsApplied::default()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_open_source_noatime() {
+        use std::io::Read;
+
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, b"data").unwrap();
+        let atime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        apply_times(&file, Some(atime), None).unwrap();
+
+        let before = noatime_stats();
+        let mut contents = Vec::new();
+        open_source(&file, true)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        let copied = temp.path().join("copy.txt");
+        copy_source(&file, &copied, true).unwrap();
+
+        assert_eq!(contents, b"data");
+        assert_eq!(fs::read(&copied).unwrap(), b"data");
+        let stats = noatime_stats().since(&before);
+        assert!(stats.opened + stats.fallbacks >= 2);
+        // Files we own are always opened with O_NOATIME
+        if stats.fallbacks == 0 {
+            assert_eq!(fs::metadata(&file).unwrap().accessed().unwrap(), atime);
+        }
+    }
 }
@@ -0,0 +1,280 @@
+use crate::error::{Result, SyncError};
+use crate::path::SyncPath;
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sync history file name, one JSON record per line in the state base directory
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// Host label used for local-to-local syncs
+pub const LOCAL_HOST: &str = "local";
+
+/// One completed sync run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: u64, // Seconds since UNIX epoch
+    pub source: String,
+    pub destination: String,
+    /// Remote host the data went to or came from (`local` if none)
+    pub host: String,
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub files_transferred: usize,
+    /// File bytes written at the destination
+    pub bytes_transferred: u64,
+    /// Bytes sent to the host (after delta sync and compression savings)
+    pub bytes_sent: u64,
+    /// Bytes received from the host (after delta sync and compression savings)
+    pub bytes_received: u64,
+    pub duration_secs: f64,
+    pub errors: usize,
+}
+
+impl HistoryEntry {
+    /// Build a record for a finished sync of `source` into `destination`
+    ///
+    /// Wire bytes are attributed to the remote side: uploads count as sent,
+    /// downloads as received.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        source: &SyncPath,
+        destination: &SyncPath,
+        profile: Option<String>,
+        files_transferred: usize,
+        bytes_transferred: u64,
+        bytes_on_wire: u64,
+        duration_secs: f64,
+        errors: usize,
+    ) -> Self {
+        let (host, bytes_sent, bytes_received) = match (host_of(source), host_of(destination)) {
+            (_, Some(host)) => (host, bytes_on_wire, 0),
+            (Some(host), None) => (host, 0, bytes_on_wire),
+            (None, None) => (LOCAL_HOST.to_string(), 0, 0),
+        };
+
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            source: source.to_string(),
+            destination: destination.to_string(),
+            host,
+            profile,
+            files_transferred,
+            bytes_transferred,
+            bytes_sent,
+            bytes_received,
+            duration_secs,
+            errors,
+        }
+    }
+}
+
+/// Host label for one side of a sync (None for local paths)
+fn host_of(path: &SyncPath) -> Option<String> {
+    match path {
+        SyncPath::Local(_) => None,
+        SyncPath::Remote { host, .. } | SyncPath::Daemon { host, .. } => Some(host.clone()),
+        SyncPath::S3 { bucket, .. } => Some(format!("s3://{}", bucket)),
+    }
+}
+
+/// Path of the history file (`<state base>/history.jsonl`)
+pub fn history_path(state_dir_override: Option<&Path>) -> Result<PathBuf> {
+    Ok(state::base_dir(state_dir_override)?.join(HISTORY_FILE_NAME))
+}
+
+/// Append a record to the history file, creating it if needed
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_vec(entry).map_err(|e| {
+        SyncError::Io(std::io::Error::other(format!(
+            "Failed to serialize history entry: {}",
+            e
+        )))
+    })?;
+    line.push(b'\n');
+
+    // Single write per record so concurrent syncs don't interleave lines
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(&line)?;
+    Ok(())
+}
+
+/// Load all records, oldest first (a missing file is an empty history)
+///
+/// Lines that fail to parse (e.g. a partial write) are skipped.
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => tracing::debug!("Skipping malformed history line: {}", e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Bandwidth used with one host/profile combination
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BandwidthUsage {
+    pub host: String,
+    pub profile: Option<String>,
+    pub runs: usize,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Aggregate wire bytes per host and profile for runs at or after `since`
+///
+/// Sorted by total bytes, largest first.
+pub fn bandwidth_by_host(entries: &[HistoryEntry], since: Option<u64>) -> Vec<BandwidthUsage> {
+    let mut usage: BTreeMap<(String, Option<String>), BandwidthUsage> = BTreeMap::new();
+    for entry in entries
+        .iter()
+        .filter(|e| since.is_none_or(|since| e.timestamp >= since))
+    {
+        let total = usage
+            .entry((entry.host.clone(), entry.profile.clone()))
+            .or_insert_with(|| BandwidthUsage {
+                host: entry.host.clone(),
+                profile: entry.profile.clone(),
+                ..Default::default()
+            });
+        total.runs += 1;
+        total.bytes_sent += entry.bytes_sent;
+        total.bytes_received += entry.bytes_received;
+    }
+
+    let mut usage: Vec<_> = usage.into_values().collect();
+    usage.sort_by_key(|u| std::cmp::Reverse(u.bytes_sent + u.bytes_received));
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn remote(host: &str) -> SyncPath {
+        SyncPath::Remote {
+            host: host.to_string(),
+            user: None,
+            path: PathBuf::from("/data"),
+        }
+    }
+
+    fn local() -> SyncPath {
+        SyncPath::Local(PathBuf::from("/data"))
+    }
+
+    #[test]
+    fn test_entry_attributes_bytes_to_remote_side() {
+        let upload = HistoryEntry::new(&local(), &remote("a"), None, 1, 100, 60, 1.0, 0);
+        assert_eq!(upload.host, "a");
+        assert_eq!((upload.bytes_sent, upload.bytes_received), (60, 0));
+
+        let download = HistoryEntry::new(&remote("b"), &local(), None, 1, 100, 60, 1.0, 0);
+        assert_eq!(download.host, "b");
+        assert_eq!((download.bytes_sent, download.bytes_received), (0, 60));
+
+        let copy = HistoryEntry::new(&local(), &local(), None, 1, 100, 100, 1.0, 0);
+        assert_eq!(copy.host, LOCAL_HOST);
+        assert_eq!((copy.bytes_sent, copy.bytes_received), (0, 0));
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state").join(HISTORY_FILE_NAME);
+        assert!(load(&path).unwrap().is_empty());
+
+        let first = HistoryEntry::new(&local(), &remote("a"), None, 1, 10, 10, 0.5, 0);
+        let second = HistoryEntry::new(
+            &remote("b"),
+            &local(),
+            Some("backup".to_string()),
+            2,
+            20,
+            5,
+            1.5,
+            1,
+        );
+        append(&path, &first).unwrap();
+        append(&path, &second).unwrap();
+
+        // Partial trailing line from an interrupted write is ignored
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"timestamp\":")
+            .unwrap();
+
+        assert_eq!(load(&path).unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn test_bandwidth_by_host() {
+        let mut entries = vec![
+            HistoryEntry::new(&local(), &remote("a"), None, 1, 100, 100, 1.0, 0),
+            HistoryEntry::new(&remote("a"), &local(), None, 1, 50, 50, 1.0, 0),
+            HistoryEntry::new(&local(), &remote("b"), None, 1, 500, 500, 1.0, 0),
+            HistoryEntry::new(
+                &local(),
+                &remote("a"),
+                Some("nightly".to_string()),
+                1,
+                10,
+                10,
+                1.0,
+                0,
+            ),
+        ];
+        entries[0].timestamp = 1_000;
+
+        let usage = bandwidth_by_host(&entries, None);
+        assert_eq!(usage.len(), 3);
+        assert_eq!(usage[0].host, "b");
+        assert_eq!(
+            usage[1],
+            BandwidthUsage {
+                host: "a".to_string(),
+                profile: None,
+                runs: 2,
+                bytes_sent: 100,
+                bytes_received: 50,
+            }
+        );
+        assert_eq!(usage[2].profile.as_deref(), Some("nightly"));
+
+        // Old runs are excluded by --days
+        let recent = bandwidth_by_host(&entries, Some(2_000));
+        let a = recent
+            .iter()
+            .find(|u| u.host == "a" && u.profile.is_none())
+            .unwrap();
+        assert_eq!((a.runs, a.bytes_sent), (1, 0));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::error::Result;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 mod blake3;
 mod xxhash3;
@@ -7,6 +9,93 @@ mod xxhash3;
 pub use self::blake3::Blake3Hasher;
 pub use self::xxhash3::XxHash3Hasher;
 
+/// Read buffer for file hashing (per thread, reused across files)
+const HASH_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Bytes checksummed by this process and time spent doing it
+static HASHED_BYTES: AtomicU64 = AtomicU64::new(0);
+static HASH_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Checksum work done so far, for `--perf`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HashStats {
+    pub bytes: u64,
+    pub duration: Duration,
+}
+
+impl HashStats {
+    /// Work done between `earlier` and this snapshot
+    pub fn since(&self, earlier: &HashStats) -> HashStats {
+        HashStats {
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+            duration: self.duration.saturating_sub(earlier.duration),
+        }
+    }
+
+    /// Hash throughput in bytes/sec (summed across threads)
+    pub fn throughput(&self) -> f64 {
+        if self.duration.is_zero() {
+            0.0
+        } else {
+            self.bytes as f64 / self.duration.as_secs_f64()
+        }
+    }
+}
+
+/// Snapshot of the process-wide checksum counters
+pub fn hash_stats() -> HashStats {
+    HashStats {
+        bytes: HASHED_BYTES.load(Ordering::Relaxed),
+        duration: Duration::from_nanos(HASH_NANOS.load(Ordering::Relaxed)),
+    }
+}
+
+fn record_hash(bytes: u64, started: Instant) {
+    HASHED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+    HASH_NANOS.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+}
+
+/// SIMD implementations the checksums run on, e.g. "xxh3 sse2, blake3 avx2"
+///
+/// xxh3's is fixed at compile time (build with `-C target-cpu=native` for
+/// AVX2); BLAKE3 picks the widest one the CPU supports at runtime.
+pub fn simd_backends() -> String {
+    let xxh3 = if cfg!(target_feature = "avx2") {
+        "avx2"
+    } else if cfg!(target_feature = "sse2") {
+        "sse2"
+    } else if cfg!(target_arch = "aarch64") {
+        "neon"
+    } else {
+        "scalar"
+    };
+
+    format!("xxh3 {}, blake3 {}", xxh3, blake3_backend())
+}
+
+#[cfg(target_arch = "x86_64")]
+fn blake3_backend() -> &'static str {
+    if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512vl") {
+        "avx512"
+    } else if is_x86_feature_detected!("avx2") {
+        "avx2"
+    } else if is_x86_feature_detected!("sse4.1") {
+        "sse4.1"
+    } else {
+        "sse2"
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn blake3_backend() -> &'static str {
+    "neon"
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn blake3_backend() -> &'static str {
+    "portable"
+}
+
 /// Type of checksum to compute
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChecksumType {
@@ -74,6 +163,8 @@ impl Checksum {
 pub struct IntegrityVerifier {
     checksum_type: ChecksumType,
     verify_on_write: bool,
+    /// Open files without updating their access time (--open-noatime)
+    noatime: bool,
 }
 
 #[allow(dead_code)] // Public API for integrity verification
@@ -83,9 +174,16 @@ impl IntegrityVerifier {
         Self {
             checksum_type,
             verify_on_write,
+            noatime: false,
         }
     }
 
+    /// Open the files it hashes with `O_NOATIME` where possible (--open-noatime)
+    pub fn open_noatime(mut self, enabled: bool) -> Self {
+        self.noatime = enabled;
+        self
+    }
+
     /// Get the checksum type
     pub fn checksum_type(&self) -> ChecksumType {
         self.checksum_type
@@ -101,11 +199,11 @@ impl IntegrityVerifier {
         match self.checksum_type {
             ChecksumType::None => Ok(Checksum::None),
             ChecksumType::Fast => {
-                let hash = XxHash3Hasher::hash_file(path)?;
+                let hash = XxHash3Hasher::hash_file(path, self.noatime)?;
                 Ok(Checksum::Fast(hash.to_le_bytes().to_vec()))
             }
             ChecksumType::Cryptographic => {
-                let hash = Blake3Hasher::hash_file(path)?;
+                let hash = Blake3Hasher::hash_file(path, self.noatime)?;
                 Ok(Checksum::Cryptographic(hash.as_bytes().to_vec()))
             }
         }
@@ -113,17 +211,21 @@ impl IntegrityVerifier {
 
     /// Compute checksum for data in memory
     pub fn compute_data_checksum(&self, data: &[u8]) -> Result<Checksum> {
-        match self.checksum_type {
-            ChecksumType::None => Ok(Checksum::None),
+        let started = Instant::now();
+        let checksum = match self.checksum_type {
+            ChecksumType::None => return Ok(Checksum::None),
             ChecksumType::Fast => {
                 let hash = XxHash3Hasher::hash_data(data);
-                Ok(Checksum::Fast(hash.to_le_bytes().to_vec()))
+                Checksum::Fast(hash.to_le_bytes().to_vec())
             }
             ChecksumType::Cryptographic => {
                 let hash = Blake3Hasher::hash_data(data);
-                Ok(Checksum::Cryptographic(hash.as_bytes().to_vec()))
+                Checksum::Cryptographic(hash.as_bytes().to_vec())
             }
-        }
+        };
+
+        record_hash(data.len() as u64, started);
+        Ok(checksum)
     }
 
     /// Verify that source and destination files match
@@ -324,6 +426,25 @@ mod tests {
         assert!(!verifier.verify_block(expected, actual).unwrap());
     }
 
+    #[test]
+    fn test_hash_stats_count_checksummed_bytes() {
+        let before = hash_stats();
+        let verifier = IntegrityVerifier::new(ChecksumType::Fast, false);
+        verifier.compute_data_checksum(&[7u8; 4096]).unwrap();
+
+        // Other tests hash concurrently, so only a lower bound holds
+        let stats = hash_stats().since(&before);
+        assert!(stats.bytes >= 4096);
+        assert!(stats.throughput() > 0.0 || stats.duration.is_zero());
+    }
+
+    #[test]
+    fn test_simd_backends() {
+        let backends = simd_backends();
+        assert!(backends.starts_with("xxh3 "));
+        assert!(backends.contains(", blake3 "));
+    }
+
     #[test]
     fn test_verify_block_fast_checksum() {
         let verifier = IntegrityVerifier::new(ChecksumType::Fast, true);
@@ -1,10 +1,18 @@
 #[allow(dead_code)] // Public API and hasher infrastructure
+use super::{record_hash, HASH_BUFFER_SIZE};
 use crate::error::Result;
-use std::fs::File;
+use std::cell::RefCell;
 use std::io::Read;
 use std::path::Path;
+use std::time::Instant;
 use xxhash_rust::xxh3::Xxh3;
 
+thread_local! {
+    /// Hasher and read buffer reused by every file hashed on this thread
+    static FILE_HASHER: RefCell<(Xxh3, Vec<u8>)> =
+        RefCell::new((Xxh3::new(), vec![0u8; HASH_BUFFER_SIZE]));
+}
+
 /// Wrapper around xxHash3 hasher
 pub struct XxHash3Hasher;
 
@@ -14,21 +22,31 @@ impl XxHash3Hasher {
     ///
     /// This reads the entire file and computes its hash.
     /// xxHash3 is much faster than cryptographic hashes but not suitable for security.
-    pub fn hash_file(path: &Path) -> Result<u64> {
-        let mut file = File::open(path)?;
-        let mut hasher = Xxh3::new();
-
-        // Read and hash in chunks to avoid loading entire file into memory
-        let mut buffer = vec![0u8; 1024 * 1024]; // 1MB chunks
-        loop {
-            let bytes_read = file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
+    /// The hasher and buffer are per-thread and reused, so hashing millions of
+    /// small files doesn't allocate and initialize a hasher for each one.
+    /// `noatime` opens it as `fs_util::open_source` does.
+    pub fn hash_file(path: &Path, noatime: bool) -> Result<u64> {
+        let mut file = crate::fs_util::open_source(path, noatime)?;
+        let started = Instant::now();
+
+        FILE_HASHER.with(|state| {
+            let (hasher, buffer) = &mut *state.borrow_mut();
+            hasher.reset();
+            let mut total = 0u64;
+
+            // Read and hash in chunks to avoid loading entire file into memory
+            loop {
+                let bytes_read = file.read(buffer)?;
+                if bytes_read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..bytes_read]);
+                total += bytes_read as u64;
             }
-            hasher.update(&buffer[..bytes_read]);
-        }
 
-        Ok(hasher.digest())
+            record_hash(total, started);
+            Ok(hasher.digest())
+        })
     }
 
     /// Compute xxHash3 hash of data in memory
@@ -45,7 +63,7 @@ impl XxHash3Hasher {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
+    use std::fs::{self, File};
     use tempfile::TempDir;
 
     #[test]
@@ -77,7 +95,7 @@ mod tests {
         let content = b"File content for xxHash3";
         fs::write(&file_path, content).unwrap();
 
-        let hash = XxHash3Hasher::hash_file(&file_path).unwrap();
+        let hash = XxHash3Hasher::hash_file(&file_path, false).unwrap();
         assert_ne!(hash, 0);
 
         // Should match hash of raw data
@@ -100,7 +118,7 @@ mod tests {
         drop(file);
 
         // Hash it
-        let hash = XxHash3Hasher::hash_file(&file_path).unwrap();
+        let hash = XxHash3Hasher::hash_file(&file_path, false).unwrap();
         assert_ne!(hash, 0);
     }
 
@@ -128,11 +146,27 @@ mod tests {
         let file_path = temp_dir.path().join("empty.txt");
         fs::write(&file_path, b"").unwrap();
 
-        let file_hash = XxHash3Hasher::hash_file(&file_path).unwrap();
+        let file_hash = XxHash3Hasher::hash_file(&file_path, false).unwrap();
         let data_hash = XxHash3Hasher::hash_data(b"");
         assert_eq!(file_hash, data_hash);
     }
 
+    #[test]
+    fn test_reused_hasher_is_reset() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        fs::write(&first, vec![0x11u8; HASH_BUFFER_SIZE + 7]).unwrap();
+        fs::write(&second, b"short").unwrap();
+
+        // Second file on the same thread must not see state from the first
+        XxHash3Hasher::hash_file(&first, false).unwrap();
+        assert_eq!(
+            XxHash3Hasher::hash_file(&second, false).unwrap(),
+            XxHash3Hasher::hash_data(b"short")
+        );
+    }
+
     #[test]
     fn test_known_hash() {
         // Test with known input/output for regression testing
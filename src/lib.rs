@@ -1,18 +1,31 @@
+pub mod acl;
+pub mod bench;
 pub mod bisync;
 pub mod cli;
 pub mod compress;
 pub mod config;
+pub mod cpu;
+pub mod daemon;
 pub mod delta;
 pub mod error;
 pub mod filter;
 pub mod fs_util;
+pub mod history;
 pub mod hooks;
 pub mod integrity;
+pub mod owner;
 pub mod path;
 pub mod perf;
+pub mod quic;
 pub mod resource;
+pub mod snapshot;
 pub mod sparse;
 pub mod ssh;
+pub mod state;
+pub mod stdin_source;
+pub mod stdout_dest;
 pub mod sync;
+pub mod telemetry;
 pub mod temp_file;
 pub mod transport;
+pub mod wire;
@@ -1,21 +1,34 @@
+mod acl;
+mod bench;
 mod bisync;
 mod cli;
 mod compress;
 mod config;
+mod cpu;
+mod daemon;
 mod delta;
 mod error;
 mod filter;
 mod fs_util;
+mod history;
 mod hooks;
 mod integrity;
+mod owner;
 mod path;
 mod perf;
+mod quic;
 mod resource;
+mod snapshot;
 mod sparse;
 mod ssh;
+mod state;
+mod stdin_source;
+mod stdout_dest;
 mod sync;
+mod telemetry;
 mod temp_file;
 mod transport;
+mod wire;
 
 use anyhow::{Context as _, Result};
 use clap::Parser;
@@ -26,11 +39,15 @@ use filter::FilterEngine;
 use hooks::{HookContext, HookExecutor, HookType};
 use path::SyncPath;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
-use sync::{watch::WatchMode, SyncEngine};
-use tracing_subscriber::{fmt, EnvFilter};
+use sync::{watch::WatchMode, SyncEngine, SyncOptions};
+use tracing_subscriber::{filter::Targets, fmt, fmt::writer::BoxMakeWriter, prelude::*, EnvFilter};
 use transport::router::TransportRouter;
 
+/// Exit code when some files failed but the run went on (--ignore-errors), as in rsync
+const PARTIAL_TRANSFER_EXIT_CODE: i32 = 23;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse CLI arguments
@@ -66,6 +83,145 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Maintenance subcommands run instead of a sync
+    if let Some(cli::Command::ChecksumDb {
+        action,
+        ref source,
+        ref destination,
+    }) = cli.command
+    {
+        return run_checksum_db(
+            action,
+            cli.state_dir.as_deref(),
+            source.as_ref().zip(destination.as_ref()),
+        );
+    }
+
+    if let Some(cli::Command::Clean {
+        ref destination,
+        dry_run,
+    }) = cli.command
+    {
+        return run_clean(cli.state_dir.as_deref(), destination, dry_run, cli.json);
+    }
+
+    if let Some(cli::Command::Ctl {
+        action,
+        ref destination,
+    }) = cli.command
+    {
+        return run_ctl(action, cli.state_dir.as_deref(), destination, cli.json);
+    }
+
+    if let Some(cli::Command::History {
+        bandwidth,
+        days,
+        limit,
+    }) = cli.command
+    {
+        return run_history(cli.state_dir.as_deref(), bandwidth, days, limit, cli.json);
+    }
+
+    if let Some(cli::Command::Trash {
+        action,
+        ref destination,
+        ref run,
+        ref trash_dir,
+        dry_run,
+    }) = cli.command
+    {
+        return run_trash(
+            action,
+            destination,
+            run.as_deref(),
+            trash_dir.as_deref(),
+            dry_run,
+            cli.json,
+        )
+        .await;
+    }
+
+    if let Some(cli::Command::Check {
+        ref source,
+        ref destination,
+        checksum,
+        ignore_extra,
+        ref exclude,
+        list,
+        quiet,
+        json,
+    }) = cli.command
+    {
+        let in_sync = match run_check(
+            source,
+            destination,
+            checksum,
+            ignore_extra,
+            exclude,
+            list,
+            quiet,
+            json,
+        )
+        .await
+        {
+            Ok(in_sync) => in_sync,
+            Err(e) => {
+                if !quiet {
+                    eprintln!("sy check: {:#}", e);
+                }
+                std::process::exit(2);
+            }
+        };
+        std::process::exit(if in_sync { 0 } else { 1 });
+    }
+
+    if let Some(cli::Command::Preflight {
+        ref source,
+        ref destination,
+        ref filter,
+        ref include,
+        ref exclude,
+        ref include_from,
+        ref exclude_from,
+        ref remote_binary,
+        json,
+    }) = cli.command
+    {
+        let go = run_preflight(
+            source,
+            destination,
+            filter,
+            include,
+            exclude,
+            include_from.as_deref(),
+            exclude_from.as_deref(),
+            remote_binary.as_deref(),
+            json,
+        )
+        .await?;
+        std::process::exit(if go { 0 } else { 1 });
+    }
+
+    if let Some(cli::Command::Bench {
+        ref remote,
+        ref workload,
+        scale,
+        parallel,
+        ref remote_binary,
+        json,
+    }) = cli.command
+    {
+        return run_bench(
+            remote.as_ref(),
+            workload,
+            scale,
+            parallel,
+            remote_binary.as_deref(),
+            json,
+        )
+        .await;
+    }
+
     // Merge profile with CLI args if --profile is set
     if let Some(ref profile_name) = cli.profile {
         let profile = config
@@ -80,7 +236,9 @@ async fn main() -> Result<()> {
         }
         if cli.destination.is_none() {
             if let Some(ref dest_str) = profile.destination {
-                cli.destination = Some(SyncPath::parse(dest_str));
+                let dest_str = path::expand_template(dest_str)
+                    .map_err(|e| anyhow::anyhow!("Profile '{}': {}", profile_name, e))?;
+                cli.destination = Some(SyncPath::parse(&dest_str));
             }
         }
 
@@ -120,24 +278,71 @@ async fn main() -> Result<()> {
         if let Some(resume) = profile.resume {
             cli.resume = resume;
         }
+        if cli.remote_binary.is_none() {
+            cli.remote_binary = profile.remote_binary.clone();
+        }
+    }
+
+    // With stdout carrying the file's data, only errors are logged, to stderr
+    if cli.is_stdout_destination() {
+        cli.quiet = true;
     }
 
     // Setup logging
     let filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(cli.log_level().as_str()));
 
-    fmt()
-        .with_env_filter(filter)
+    let writer = if cli.is_stdout_destination() {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let fmt_layer = fmt::layer()
+        .with_writer(writer)
         .with_target(false)
         .with_thread_ids(false)
         .with_file(false)
         .with_line_number(false)
         .compact()
-        .init();
+        .with_filter(filter);
+
+    // --trace-out records every span sy emits, whatever the log level;
+    // the guard flushes the trace file when dropped
+    let (trace_layer, trace_guard) = match cli.trace_out {
+        Some(ref path) => {
+            let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+                .file(path)
+                .trace_style(tracing_chrome::TraceStyle::Async)
+                .include_args(true)
+                .build();
+            let targets = Targets::new().with_target("sy", tracing::Level::TRACE);
+            (Some(layer.with_filter(targets)), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    // --otlp-endpoint exports the same spans plus per-run metrics; dropping
+    // the exporters flushes them
+    let telemetry = cli
+        .otlp_endpoint
+        .as_deref()
+        .map(telemetry::init)
+        .transpose()?;
+
+    let registry = tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(trace_layer);
+    #[cfg(feature = "otel")]
+    let registry = registry.with(telemetry.as_ref().map(telemetry::Telemetry::layer));
+    registry.init();
 
     // Validate arguments
     cli.validate()?;
 
+    if cli.daemon {
+        return run_daemon(&config, &cli.address, cli.port).await;
+    }
+
     // After validation, source and destination must be present
     let source = cli
         .source
@@ -148,6 +353,48 @@ async fn main() -> Result<()> {
         .as_ref()
         .expect("destination required after validation");
 
+    // Writing would truncate the files being read
+    if source.is_local()
+        && destination.is_local()
+        && !cli.is_stdin_source()
+        && fs_util::same_file(source.path(), destination.path())
+    {
+        return Err(error::SyncError::SameFile {
+            path: source.path().to_path_buf(),
+            dest: destination.path().to_path_buf(),
+        }
+        .into());
+    }
+
+    // Nested trees would copy the destination into itself, or delete the source
+    let mut nested_dest = None;
+    let mut nested_source = None;
+    if source.is_local()
+        && destination.is_local()
+        && !cli.is_stdin_source()
+        && source.path().is_dir()
+    {
+        if let Some(nested) = fs_util::nested_in(source.path(), destination.path()) {
+            if !cli.allow_nested {
+                return Err(error::SyncError::DestInsideSource {
+                    path: source.path().to_path_buf(),
+                    dest: destination.path().to_path_buf(),
+                }
+                .into());
+            }
+            nested_dest = Some(nested);
+        } else if let Some(nested) = fs_util::nested_in(destination.path(), source.path()) {
+            if !cli.allow_nested {
+                return Err(error::SyncError::SourceInsideDest {
+                    path: source.path().to_path_buf(),
+                    dest: destination.path().to_path_buf(),
+                }
+                .into());
+            }
+            nested_source = Some(nested);
+        }
+    }
+
     // Create hook executor (unless disabled)
     let hook_executor = if cli.no_hooks {
         None
@@ -157,10 +404,13 @@ async fn main() -> Result<()> {
             .map(|e| e.with_abort_on_failure(cli.abort_on_hook_failure))
     };
 
+    // Per-pair state directory (resume state, directory cache, checksum DB)
+    let state_dir = state::sync_state_dir(cli.state_dir.as_deref(), source, destination)?;
+
     // Clean state files if requested
     if cli.clean_state {
         use sync::resume::ResumeState;
-        if let Err(e) = ResumeState::delete(destination.path()) {
+        if let Err(e) = ResumeState::delete(&state_dir) {
             tracing::warn!("Failed to clean state file: {}", e);
         } else if !cli.quiet && !cli.json {
             tracing::info!("Cleaned existing state files");
@@ -170,7 +420,7 @@ async fn main() -> Result<()> {
     // Clear cache if requested (before creating engine)
     if cli.clear_cache {
         use sync::dircache::DirectoryCache;
-        if let Err(e) = DirectoryCache::delete(destination.path()) {
+        if let Err(e) = DirectoryCache::delete(&state_dir) {
             tracing::warn!("Failed to clear directory cache: {}", e);
         } else if !cli.quiet && !cli.json {
             tracing::info!("Cleared directory cache");
@@ -192,6 +442,37 @@ async fn main() -> Result<()> {
     let checksum_type = verification_mode.checksum_type();
     let verify_on_write = verification_mode.verify_blocks();
 
+    // Get symlink mode
+    let symlink_mode = cli.symlink_mode();
+
+    // What earlier runs learned about SSH hosts saves probing them again
+    if let Ok(base) = state::base_dir(cli.state_dir.as_deref()) {
+        ssh::host_info::set_cache_dir(base.join("hosts"), cli.refresh_host_info);
+    }
+
+    // Daemon paths are reached over QUIC with --quic
+    let quic_options = cli.quic.then(|| quic::ClientOptions {
+        trusted_cert: cli.quic_cert.clone(),
+        psk: std::env::var(quic::PSK_ENV).ok(),
+    });
+
+    // SSH hosts are reached with --ssh-option, --ssh-backend and --ssh-command
+    let ssh_command = cli
+        .ssh_command
+        .as_deref()
+        .map(ssh::system::parse_command)
+        .transpose()
+        .map_err(anyhow::Error::msg)?;
+    let ssh_options = ssh::config::ClientOptions {
+        options: cli.ssh_options.clone(),
+        backend: if ssh_command.is_some() {
+            ssh::system::SshBackend::System
+        } else {
+            cli.ssh_backend
+        },
+        program: ssh_command,
+    };
+
     // Create transport router based on source and destination
     // Use worker count for SSH connection pool size to enable true parallel transfers
     let transport = TransportRouter::new(
@@ -199,12 +480,42 @@ async fn main() -> Result<()> {
         destination,
         checksum_type,
         verify_on_write,
-        cli.parallel, // SSH connection pool size = number of workers
+        cli.parallel.max(cli.streams), // SSH connection pool: one per worker or stream
+        symlink_mode == cli::SymlinkMode::Follow,
+        cli.dangling_links,
+        cli.max_depth,
+        cli.compression_detection_mode(),
+        cli.remote_binary.as_deref(),
+        transport::TransportOptions {
+            inplace: cli.inplace,
+            always_delta: cli.whole_file_mode() == Some(false),
+            open_noatime: cli.open_noatime,
+            numeric_ids: cli.numeric_ids,
+            compression_probe_size: cli.compression_probe_size as usize,
+        },
+        &ssh_options,
+        quic_options.as_ref(),
     )
     .await?;
 
-    // Get symlink mode
-    let symlink_mode = cli.symlink_mode();
+    // `sy host:/path/file -` pulls one file into a pipeline
+    if cli.is_stdout_destination() {
+        let verifier =
+            (source.is_local() && checksum_type != integrity::ChecksumType::None).then(|| {
+                integrity::IntegrityVerifier::new(checksum_type, false)
+                    .open_noatime(cli.open_noatime)
+            });
+        stdout_dest::pull(
+            transport.source_transport(),
+            source.path(),
+            &std::env::temp_dir(),
+            verifier.as_ref(),
+            std::io::stdout().lock(),
+        )
+        .await
+        .with_context(|| format!("Failed to write {} to stdout", source))?;
+        return Ok(());
+    }
 
     // Build filter engine from CLI arguments
     let mut filter_engine = FilterEngine::new();
@@ -230,70 +541,33 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Load --include-from file
-    if let Some(ref include_from) = cli.include_from {
-        // Read as include patterns (not rsync rules)
-        use std::fs::File;
-        use std::io::{BufRead, BufReader};
-
-        let file = File::open(include_from)
-            .with_context(|| format!("Failed to open include file: {}", include_from.display()))?;
-        let reader = BufReader::new(file);
-
-        for (line_num, line) in reader.lines().enumerate() {
-            let line = line.with_context(|| {
-                format!(
-                    "Failed to read line {} from {}",
-                    line_num + 1,
-                    include_from.display()
-                )
-            })?;
-            let line = line.trim();
-
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
-
-            if let Err(e) = filter_engine.add_include(line) {
-                anyhow::bail!(
-                    "Invalid include pattern at line {} in {}: {}",
-                    line_num + 1,
-                    include_from.display(),
-                    e
-                );
-            }
-        }
-    }
-
-    // Load --exclude-from file
-    if let Some(ref exclude_from) = cli.exclude_from {
-        // Read as exclude patterns (not rsync rules)
-        use std::fs::File;
-        use std::io::{BufRead, BufReader};
-
-        let file = File::open(exclude_from)
-            .with_context(|| format!("Failed to open exclude file: {}", exclude_from.display()))?;
-        let reader = BufReader::new(file);
+    // Load --include-from and --exclude-from lists (files, `-` for stdin, or URLs)
+    for (list, include) in [(&cli.include_from, true), (&cli.exclude_from, false)] {
+        let Some(list) = list else {
+            continue;
+        };
+        let kind = if include { "include" } else { "exclude" };
+        let text = filter::read_pattern_list(list)
+            .with_context(|| format!("Failed to load {} patterns from {}", kind, list))?;
 
-        for (line_num, line) in reader.lines().enumerate() {
-            let line = line.with_context(|| {
-                format!(
-                    "Failed to read line {} from {}",
-                    line_num + 1,
-                    exclude_from.display()
-                )
-            })?;
+        for (line_num, line) in text.lines().enumerate() {
             let line = line.trim();
 
             if line.is_empty() || line.starts_with('#') {
                 continue;
             }
 
-            if let Err(e) = filter_engine.add_exclude(line) {
+            let added = if include {
+                filter_engine.add_include(line)
+            } else {
+                filter_engine.add_exclude(line)
+            };
+            if let Err(e) = added {
                 anyhow::bail!(
-                    "Invalid exclude pattern at line {} in {}: {}",
+                    "Invalid {} pattern at line {} in {}: {}",
+                    kind,
                     line_num + 1,
-                    exclude_from.display(),
+                    list,
                     e
                 );
             }
@@ -310,7 +584,7 @@ async fn main() -> Result<()> {
     }
 
     // Load .syignore from source directory (if local)
-    if source.is_local() {
+    if source.is_local() && !cli.is_stdin_source() {
         let source_dir = if source.path().is_file() {
             source.path().parent().unwrap_or(source.path())
         } else {
@@ -332,22 +606,54 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Load the global ignore file last so per-sync rules take precedence
+    if !cli.no_global_ignore {
+        if let Some(global_ignore) = filter::global_ignore_path() {
+            match filter_engine.add_rules_from_file_if_exists(&global_ignore) {
+                Ok(true) => {
+                    if !cli.quiet && !cli.json {
+                        tracing::info!("Loaded global ignore file {}", global_ignore.display());
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to load global ignore file: {}", e);
+                }
+            }
+        }
+    }
+
+    // --allow-nested: the destination is no part of the source
+    if let Some(nested) = &nested_dest {
+        filter_engine.add_exclude_path(nested)?;
+    }
+
+    if cli.preserve_crtimes && destination.is_local() && !fs_util::CAN_SET_CRTIME {
+        tracing::warn!("Creation times can't be set on this platform; --crtimes has no effect");
+    }
+    cpu::set_threads(cli.threads);
+    transport::ssh::set_chunk_size(cli.chunk_size.map(|size| size as usize));
+    transport::ssh::set_streams(cli.streams);
+    sync::output::set_json_warnings(cli.json);
+    sync::output::set_why(cli.why);
+
+    let control = Arc::new(sync::control::SyncControl::new());
     let engine = SyncEngine::new(
         transport,
         cli.dry_run,
         cli.diff,
         cli.delete,
         cli.delete_threshold,
-        cli.trash,
+        cli.trash_target(),
         cli.force_delete,
         cli.quiet || cli.json, // JSON mode implies quiet
         cli.parallel,
-        cli.max_errors,
+        cli.max_errors(),
         cli.min_size,
         cli.max_size,
         filter_engine,
         cli.bwlimit,
-        cli.resume,
+        cli.resume && !cli.delay_updates, // Staged files aren't checkpointed
         cli.checkpoint_files,
         cli.checkpoint_bytes,
         cli.json,
@@ -362,14 +668,63 @@ async fn main() -> Result<()> {
         cli.size_only,
         cli.checksum,
         cli.verify_only,
-        cli.use_cache,
+        cli.use_cache || cli.scan_journal,
         cli.clear_cache,
         cli.checksum_db,
         cli.clear_checksum_db,
         cli.prune_checksum_db,
+        state_dir,
         cli.perf,
+        SyncOptions {
+            nested_source,
+            delay_updates: cli.delay_updates,
+            link_dest: cli.link_dest.clone(),
+            bwlimit_per_file: cli.bwlimit_per_file,
+            resume_force: cli.resume_force,
+            preserve_atimes: cli.preserve_atimes,
+            preserve_crtimes: cli.preserve_crtimes,
+            preserve_perms: cli.preserve_perms(),
+            scan_journal: cli.scan_journal,
+            scan_error_mode: cli.scan_error_mode(),
+            specials_mode: cli.specials,
+            max_depth: cli.max_depth,
+            lock: !cli.no_lock,
+            skip_busy: cli.skip_busy,
+            wait_busy: cli.wait_busy.map(Duration::from_secs),
+            skip_hot: cli.skip_hot,
+            acl_map: cli.acl_map,
+            dest_quota: cli.dest_quota.map(|limit| sync::quota::DestQuota {
+                limit,
+                mode: cli.quota_mode,
+            }),
+            dest_confinement: cli.dest_confinement(),
+            immutable_mode: cli.immutable_mode(),
+            windows_names: cli.windows_names(),
+            retry_passes: cli.retry_failed,
+            priority_rules: cli.priority_pattern.clone(),
+            check_source_changes: source.is_local(),
+            whole_file: cli.whole_file_mode() == Some(true),
+            open_noatime: cli.open_noatime,
+            control: Some(Arc::clone(&control)),
+            fault_injector: cli
+                .fault_inject
+                .map(|spec| Arc::new(sync::fault::FaultInjector::new(spec))),
+            cancel: None, // Not cancellable; Ctrl+C ends the process
+        },
     );
 
+    // Serve `sy ctl pause|resume|status` for this destination while the sync runs
+    #[cfg(unix)]
+    let _control_server = match sync::control::socket_path(cli.state_dir.as_deref(), destination)
+        .and_then(|path| sync::control::ControlServer::start(Arc::clone(&control), path))
+    {
+        Ok(server) => Some(server),
+        Err(e) => {
+            tracing::warn!("Control socket unavailable (sy ctl disabled): {}", e);
+            None
+        }
+    };
+
     // Execute pre-sync hook
     if let Some(ref executor) = hook_executor {
         let pre_context = HookContext {
@@ -499,6 +854,65 @@ async fn main() -> Result<()> {
         return Ok(()); // Watch mode handles its own output
     }
 
+    // Read a snapshot instead of the live source; deleted when dropped
+    let snapshot = match cli.snapshot_source {
+        Some(kind) if !cli.dry_run => {
+            let snapshot = snapshot::Snapshot::create(kind, source.path())?;
+            if !cli.quiet && !cli.json {
+                println!(
+                    "Source snapshot: {} ({})\n",
+                    snapshot.source().display(),
+                    snapshot.provider()
+                );
+            }
+            Some(snapshot)
+        }
+        _ => None,
+    };
+    let sync_source = snapshot.as_ref().map_or(source.path(), |s| s.source());
+
+    // Spool stdin to a temp file so it syncs like any single file; deleted when dropped
+    let stdin_spool = if cli.is_stdin_source() {
+        let pb = if cli.quiet || cli.json {
+            indicatif::ProgressBar::hidden()
+        } else if let Some(hint) = cli.size_hint {
+            let pb = indicatif::ProgressBar::new(hint);
+            pb.set_style(
+                indicatif::ProgressStyle::default_bar()
+                    .template("Reading stdin [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+                    .unwrap()
+                    .progress_chars("#>-"),
+            );
+            pb
+        } else {
+            let pb = indicatif::ProgressBar::new_spinner();
+            pb.set_style(
+                indicatif::ProgressStyle::default_spinner()
+                    .template("{spinner:.green} Reading stdin: {bytes} ({bytes_per_sec})")
+                    .unwrap(),
+            );
+            pb
+        };
+        let spool = stdin_source::StdinSpool::read(
+            std::io::stdin().lock(),
+            &std::env::temp_dir(),
+            cli.size_hint,
+            |n| pb.set_position(n),
+        )
+        .context("Failed to read the source from stdin")?;
+        pb.finish_and_clear();
+        if let Some(hint) = cli.size_hint.filter(|&hint| hint != spool.size()) {
+            tracing::warn!(
+                "Read {} from stdin, --size-hint said {}",
+                format_bytes(spool.size()),
+                format_bytes(hint)
+            );
+        }
+        Some(spool)
+    } else {
+        None
+    };
+
     // Run sync (single file, directory, or bidirectional)
     let stats = if cli.bidirectional {
         // Bidirectional sync mode
@@ -509,14 +923,13 @@ async fn main() -> Result<()> {
         if !cli.quiet && !cli.json {
             println!("sy v{}", env!("CARGO_PKG_VERSION"));
             println!("Mode: Bidirectional sync");
-            println!("Strategy: {}", cli.conflict_resolve);
+            println!("Strategy: {}", cli.conflict_resolve.as_str());
             println!("{} ↔ {}\n", source, destination);
         }
 
         let bisync_engine = bisync::BisyncEngine::new();
         let bisync_opts = bisync::BisyncOptions {
-            conflict_resolution: bisync::ConflictResolution::from_str(&cli.conflict_resolve)
-                .ok_or_else(|| anyhow::anyhow!("Invalid conflict resolution strategy"))?,
+            conflict_resolution: cli.conflict_resolve,
             max_delete_percent: cli.max_delete,
             dry_run: cli.dry_run,
             clear_state: cli.clear_bisync_state,
@@ -525,7 +938,24 @@ async fn main() -> Result<()> {
         let bisync_result = bisync_engine.sync(source.path(), destination.path(), bisync_opts)?;
 
         // Print conflicts if any
-        if !bisync_result.conflicts.is_empty() && !cli.quiet && !cli.json {
+        if cli.json {
+            for conflict in &bisync_result.conflicts {
+                let unix_secs = |time: Option<std::time::SystemTime>| {
+                    time.and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                        .map(|d| d.as_secs())
+                };
+                sync::output::SyncEvent::Conflict {
+                    path: conflict.path.clone(),
+                    resolution: conflict.resolution.as_str(),
+                    action: conflict.action.clone(),
+                    source_size: conflict.source_size,
+                    source_modified: unix_secs(conflict.source_mtime),
+                    dest_size: conflict.dest_size,
+                    dest_modified: unix_secs(conflict.dest_mtime),
+                }
+                .emit();
+            }
+        } else if !bisync_result.conflicts.is_empty() && !cli.quiet {
             println!("\n{} conflicts detected:", bisync_result.conflicts.len());
             for conflict in &bisync_result.conflicts {
                 println!("  {} - {}", conflict.path.display(), conflict.action);
@@ -558,17 +988,27 @@ async fn main() -> Result<()> {
                 error: e,
                 action: "bidirectional sync".to_string(),
             }).collect(),
+            skipped_busy: Vec::new(),
+            deferred_hot: Vec::new(),
+            skipped_specials: Vec::new(),
+            names: sync::names::NameReport::default(),
+            files_retried: 0,
+            changed_during_transfer: Vec::new(),
+            metadata_not_preserved: sync::output::MetadataReport::default(),
         }
     } else if cli.is_single_file() {
         if !cli.quiet && !cli.json {
             println!("Mode: Single file sync\n");
         }
+        let single_source = stdin_spool.as_ref().map_or(source.path(), |s| s.path());
         engine
-            .sync_single_file(source.path(), destination.path())
+            .sync_single_file(single_source, destination.path())
             .await?
     } else {
-        engine.sync(source.path(), destination.path()).await?
+        engine.sync(sync_source, destination.path()).await?
     };
+    drop(snapshot);
+    drop(stdin_spool);
 
     // Execute post-sync hook
     if let Some(ref executor) = hook_executor {
@@ -591,6 +1031,40 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Record the run for `sy history` (bandwidth accounting per host)
+    if !cli.dry_run {
+        let network_bytes = stats
+            .bytes_transferred
+            .saturating_sub(stats.delta_bytes_saved)
+            .saturating_sub(stats.compression_bytes_saved);
+        let entry = history::HistoryEntry::new(
+            source,
+            destination,
+            cli.profile.clone(),
+            stats.files_created + stats.files_updated,
+            stats.bytes_transferred,
+            network_bytes,
+            stats.duration.as_secs_f64(),
+            stats.errors.len(),
+        );
+        if let Err(e) = history::history_path(cli.state_dir.as_deref())
+            .and_then(|path| history::append(&path, &entry))
+        {
+            tracing::warn!("Failed to record sync history: {}", e);
+        }
+
+        // The bandwidth measured picks the SSH upload chunk size next time
+        for path in [source, destination] {
+            if let SyncPath::Remote { host, user, .. } = path {
+                ssh::host_info::HostInfo::record_bandwidth(
+                    &ssh::host_info::host_key(host, user.as_deref()),
+                    network_bytes,
+                    stats.duration.as_secs_f64(),
+                );
+            }
+        }
+    }
+
     // Print summary (skip if JSON mode - already emitted JSON summary)
     if !cli.quiet && !cli.json {
         if cli.dry_run {
@@ -598,6 +1072,16 @@ async fn main() -> Result<()> {
                 "\n{}\n",
                 "✓ Dry-run complete (no changes made)".green().bold()
             );
+        } else if cli.ignore_errors && !stats.errors.is_empty() {
+            println!(
+                "\n{}\n",
+                format!(
+                    "⚠ Sync partially complete ({} files failed)",
+                    stats.errors.len()
+                )
+                .yellow()
+                .bold()
+            );
         } else {
             println!("\n{}\n", "✓ Sync complete".green().bold());
         }
@@ -679,6 +1163,58 @@ async fn main() -> Result<()> {
                 "  Files skipped:     {}",
                 stats.files_skipped.to_string().bright_black()
             );
+            if !stats.skipped_busy.is_empty() {
+                println!(
+                    "  Skipped (busy):    {}",
+                    stats.skipped_busy.len().to_string().yellow()
+                );
+            }
+            if !stats.deferred_hot.is_empty() {
+                println!(
+                    "  Deferred (hot):    {}",
+                    stats.deferred_hot.len().to_string().yellow()
+                );
+            }
+            if !stats.skipped_specials.is_empty() {
+                println!(
+                    "  Special files:     {} not copied",
+                    stats.skipped_specials.len().to_string().yellow()
+                );
+            }
+            if stats.files_retried > 0 {
+                println!(
+                    "  Retried:           {}",
+                    stats.files_retried.to_string().yellow()
+                );
+            }
+            if !stats.changed_during_transfer.is_empty() {
+                println!(
+                    "  Changed in flight: {}",
+                    stats.changed_during_transfer.len().to_string().yellow()
+                );
+            }
+            if !stats.names.renamed.is_empty() {
+                println!(
+                    "  Renamed (Windows): {}",
+                    stats.names.renamed.len().to_string().yellow()
+                );
+            }
+            if !stats.names.skipped.is_empty() {
+                println!(
+                    "  Skipped (Windows): {}",
+                    stats.names.skipped.len().to_string().yellow()
+                );
+            }
+            if !stats.metadata_not_preserved.is_empty() {
+                println!(
+                    "  Metadata missing:  {}",
+                    stats
+                        .metadata_not_preserved
+                        .file_count()
+                        .to_string()
+                        .yellow()
+                );
+            }
             if cli.delete && stats.files_deleted > 0 {
                 println!(
                     "  Files deleted:     {}",
@@ -692,6 +1228,53 @@ async fn main() -> Result<()> {
             }
         }
 
+        // Files left alone because another process had them open
+        if !stats.skipped_busy.is_empty() {
+            println!(
+                "\n  {}",
+                "Skipped busy files (in use by another process):".yellow()
+            );
+            for path in &stats.skipped_busy {
+                println!("    {}", path.display());
+            }
+        }
+
+        // Files modified too recently to copy safely (--skip-hot)
+        if !stats.deferred_hot.is_empty() {
+            println!(
+                "\n  {}",
+                "Deferred hot files (modified recently, will sync on a later run):".yellow()
+            );
+            for path in &stats.deferred_hot {
+                println!("    {}", path.display());
+            }
+        }
+
+        // Names a Windows or SMB destination would reject (--windows-names)
+        if !stats.names.renamed.is_empty() {
+            println!("\n  {}", "Renamed for Windows:".yellow());
+            for (from, to) in &stats.names.renamed {
+                println!("    {} -> {}", from.display(), to.display());
+            }
+        }
+        if !stats.names.skipped.is_empty() {
+            println!("\n  {}", "Skipped, not valid on Windows:".yellow());
+            for (path, problem) in &stats.names.skipped {
+                println!("    {} ({})", path.display(), problem);
+            }
+        }
+
+        // Xattrs, ACLs, owners, permissions and flags that couldn't be applied
+        if !stats.metadata_not_preserved.is_empty() {
+            println!(
+                "\n  {}",
+                "Metadata not preserved (-v lists each file):".yellow()
+            );
+            for (_, line) in stats.metadata_not_preserved.lines() {
+                println!("    {}", line);
+            }
+        }
+
         // Transfer stats
         println!();
         println!(
@@ -770,6 +1353,657 @@ async fn main() -> Result<()> {
         }
     }
 
+    // 23 = some files failed (--ignore-errors, rsync's partial transfer code)
+    // 0 = already in sync, 1 = changes pending (dry-run) or made
+    let exit_code = if cli.ignore_errors && !stats.errors.is_empty() {
+        Some(PARTIAL_TRANSFER_EXIT_CODE)
+    } else if cli.exit_code_on_change
+        && stats.files_created + stats.files_updated + stats.files_deleted > 0
+    {
+        Some(1)
+    } else {
+        None
+    };
+    if let Some(code) = exit_code {
+        // process::exit skips destructors; remove the control socket and
+        // flush the trace and telemetry first
+        #[cfg(unix)]
+        drop(_control_server);
+        drop(trace_guard);
+        drop(telemetry);
+        std::process::exit(code);
+    }
+
+    Ok(())
+}
+
+/// Run `sy checksum-db <action>` for one sync pair, or every pair in the state directory
+/// Serve the config file's modules over TCP until stopped (--daemon)
+async fn run_daemon(config: &Config, address: &str, port: u16) -> Result<()> {
+    if config.modules.is_empty() {
+        anyhow::bail!(
+            "No modules to serve; add a [modules.NAME] table with a path to {}",
+            Config::config_path()?.display()
+        );
+    }
+
+    let listener = tokio::net::TcpListener::bind((address, port))
+        .await
+        .with_context(|| format!("Failed to listen on {}:{}", address, port))?;
+    let mut names: Vec<&str> = config.modules.keys().map(String::as_str).collect();
+    names.sort();
+    println!("Serving {} on {}", names.join(", "), listener.local_addr()?);
+
+    daemon::serve(listener, &config.modules).await?;
+    Ok(())
+}
+
+fn run_checksum_db(
+    action: cli::ChecksumDbAction,
+    state_dir_override: Option<&std::path::Path>,
+    pair: Option<(&SyncPath, &SyncPath)>,
+) -> Result<()> {
+    use sync::checksumdb::ChecksumDatabase;
+
+    let state_dirs = if let Some((source, destination)) = pair {
+        vec![state::sync_state_dir(
+            state_dir_override,
+            source,
+            destination,
+        )?]
+    } else {
+        let base = state::base_dir(state_dir_override)?;
+        let mut dirs = Vec::new();
+        if base.is_dir() {
+            for entry in std::fs::read_dir(&base)
+                .with_context(|| format!("Failed to read state directory {}", base.display()))?
+            {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                }
+            }
+        }
+        dirs.sort();
+        dirs
+    };
+
+    // Never create a database just to report on it
+    let state_dirs: Vec<_> = state_dirs
+        .into_iter()
+        .filter(|dir| ChecksumDatabase::db_path(dir).exists())
+        .collect();
+
+    if state_dirs.is_empty() {
+        println!("No checksum databases found");
+        return Ok(());
+    }
+
+    for dir in state_dirs {
+        let db = ChecksumDatabase::open(&dir)?;
+        println!("{}", ChecksumDatabase::db_path(&dir).display());
+
+        match action {
+            cli::ChecksumDbAction::Stats => {
+                let stats = db.stats()?;
+                println!("  Entries:           {}", stats.total_entries);
+                println!("    Fast:            {}", stats.fast_checksums);
+                println!("    Cryptographic:   {}", stats.cryptographic_checksums);
+                println!("  Generation:        {}", stats.generation);
+                println!("  Size:              {}", format_bytes(stats.size_bytes));
+                println!("  Reclaimable:       {}", format_bytes(stats.free_bytes));
+            }
+            cli::ChecksumDbAction::Vacuum => {
+                let (before, after) = db.vacuum()?;
+                println!(
+                    "  Vacuumed: {} -> {}",
+                    format_bytes(before),
+                    format_bytes(after)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `sy check`, returning whether the pair is in sync
+#[allow(clippy::too_many_arguments)]
+async fn run_check(
+    source: &SyncPath,
+    destination: &SyncPath,
+    checksum: bool,
+    ignore_extra: bool,
+    exclude: &[String],
+    list: bool,
+    quiet: bool,
+    json: bool,
+) -> Result<bool> {
+    let transport = TransportRouter::new(
+        source,
+        destination,
+        integrity::ChecksumType::None,
+        false,
+        1,
+        false,
+        cli::DanglingMode::Preserve,
+        None,
+        compress::CompressionDetection::Auto,
+        None,
+        transport::TransportOptions::default(),
+        &ssh::config::ClientOptions::default(),
+        None,
+    )
+    .await?;
+
+    // Same ignore sources as a sync, so ignored files never count as drift
+    let mut filter_engine = FilterEngine::new();
+    for pattern in exclude {
+        if let Err(e) = filter_engine.add_exclude(pattern) {
+            anyhow::bail!("Invalid exclude pattern '{}': {}", pattern, e);
+        }
+    }
+    if source.is_local() && source.path().is_dir() {
+        filter_engine.add_syignore_if_exists(source.path())?;
+    }
+    if let Some(global_ignore) = filter::global_ignore_path() {
+        filter_engine.add_rules_from_file_if_exists(&global_ignore)?;
+    }
+
+    let report = sync::check::check(
+        &transport,
+        source.path(),
+        destination.path(),
+        &filter_engine,
+        checksum,
+        ignore_extra,
+    )
+    .await?;
+
+    if json {
+        println!("{}", serde_json::to_string(&report)?);
+    } else if !quiet {
+        if report.in_sync() {
+            println!("in sync ({} files)", report.files_checked);
+        } else {
+            println!(
+                "drift: {} missing, {} changed, {} extra",
+                report.missing.len(),
+                report.changed.len(),
+                report.extra.len()
+            );
+        }
+        if list {
+            for path in &report.missing {
+                println!("+ {}", path.display());
+            }
+            for path in &report.changed {
+                println!("~ {}", path.display());
+            }
+            for path in &report.extra {
+                println!("- {}", path.display());
+            }
+        }
+    }
+
+    Ok(report.in_sync())
+}
+
+/// Run `sy preflight`, returning whether a sync from `source` to `destination` can go
+#[allow(clippy::too_many_arguments)]
+async fn run_preflight(
+    source: &SyncPath,
+    destination: &SyncPath,
+    filter: &[String],
+    include: &[String],
+    exclude: &[String],
+    include_from: Option<&str>,
+    exclude_from: Option<&str>,
+    remote_binary: Option<&str>,
+    json: bool,
+) -> Result<bool> {
+    use sync::preflight::{self, Check, PreflightReport, Status};
+
+    let mut report = PreflightReport::default();
+    let (filter_engine, filters) =
+        preflight::load_filters(filter, include, exclude, include_from, exclude_from);
+
+    match TransportRouter::new(
+        source,
+        destination,
+        integrity::ChecksumType::None,
+        false,
+        1,
+        false,
+        cli::DanglingMode::Preserve,
+        None,
+        compress::CompressionDetection::Auto,
+        remote_binary,
+        transport::TransportOptions::default(),
+        &ssh::config::ClientOptions::default(),
+        None,
+    )
+    .await
+    {
+        Ok(transport) => {
+            report.push(Check::new(
+                "connection",
+                Status::Pass,
+                if source.is_local() && destination.is_local() {
+                    "local paths"
+                } else {
+                    "connected"
+                },
+            ));
+            preflight::check_pair(
+                &transport,
+                source.path(),
+                destination.path(),
+                &filter_engine,
+                destination.is_local(),
+                &mut report,
+            )
+            .await;
+        }
+        Err(e) => report.push(Check::new(
+            "connection",
+            Status::Fail,
+            e.to_string().lines().next().unwrap_or_default(),
+        )),
+    }
+    report.push(filters);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "go": report.go(), "checks": report.checks })
+        );
+    } else {
+        println!("Preflight {} → {}", source, destination);
+        for check in &report.checks {
+            println!(
+                "  {:<5} {:<12} {}",
+                check.status.label(),
+                check.name,
+                check.detail
+            );
+        }
+        let failed = report
+            .checks
+            .iter()
+            .filter(|c| c.status == Status::Fail)
+            .count();
+        if failed == 0 {
+            println!("GO: the sync can start");
+        } else {
+            println!("NO-GO: {} checks failed", failed);
+        }
+    }
+
+    Ok(report.go())
+}
+
+/// Run `sy clean`: find and remove what crashed syncs into `destination` left behind
+fn run_clean(
+    state_dir_override: Option<&std::path::Path>,
+    destination: &SyncPath,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if !destination.is_local() {
+        anyhow::bail!("sy clean only supports local destinations");
+    }
+
+    let leftovers = sync::clean::find(
+        destination.path(),
+        &state::base_dir(state_dir_override)?,
+        &sync::control::socket_path(state_dir_override, destination)?,
+    )?;
+    let failed = if dry_run {
+        Vec::new()
+    } else {
+        sync::clean::remove(&leftovers)
+    };
+    let reclaimed: u64 = leftovers
+        .iter()
+        .filter(|l| !failed.iter().any(|(path, _)| *path == l.path))
+        .map(|l| l.size)
+        .sum();
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "leftovers": leftovers,
+                "dry_run": dry_run,
+                "bytes_reclaimed": if dry_run { 0 } else { reclaimed },
+                "failed": failed
+                    .iter()
+                    .map(|(path, e)| serde_json::json!({ "path": path, "error": e.to_string() }))
+                    .collect::<Vec<_>>(),
+            })
+        );
+    } else if leftovers.is_empty() {
+        println!("Nothing to clean in {}", destination);
+    } else {
+        for leftover in &leftovers {
+            println!(
+                "  {:<15} {} ({})",
+                leftover.kind,
+                leftover.path.display(),
+                format_bytes(leftover.size)
+            );
+        }
+        if dry_run {
+            println!(
+                "Would remove {} leftovers ({})",
+                leftovers.len(),
+                format_bytes(reclaimed)
+            );
+        } else {
+            for (path, e) in &failed {
+                eprintln!("Failed to remove {}: {}", path.display(), e);
+            }
+            println!(
+                "Removed {} leftovers, reclaimed {}",
+                leftovers.len() - failed.len(),
+                format_bytes(reclaimed)
+            );
+        }
+    }
+
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn run_ctl(
+    action: sync::control::ControlCommand,
+    state_dir_override: Option<&std::path::Path>,
+    destination: &SyncPath,
+    json: bool,
+) -> Result<()> {
+    let path = sync::control::socket_path(state_dir_override, destination)?;
+    let status = sync::control::send(&path, action)?;
+
+    if json {
+        println!("{}", serde_json::to_string(&status)?);
+        return Ok(());
+    }
+
+    println!(
+        "Sync into {} (pid {}): {}",
+        destination,
+        status.pid,
+        if status.paused { "paused" } else { "running" }
+    );
+    println!(
+        "  Progress: {}/{} files, {}",
+        status.files_processed,
+        status.files_total,
+        format_bytes(status.bytes_transferred)
+    );
+    Ok(())
+}
+
+fn run_history(
+    state_dir_override: Option<&std::path::Path>,
+    bandwidth: bool,
+    days: Option<u64>,
+    limit: usize,
+    json: bool,
+) -> Result<()> {
+    let entries = history::load(&history::history_path(state_dir_override)?)?;
+    let since = days.map(|days| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        now.saturating_sub(days * 24 * 60 * 60)
+    });
+
+    if bandwidth {
+        let usage = history::bandwidth_by_host(&entries, since);
+        if json {
+            for u in &usage {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "host": u.host,
+                        "profile": u.profile,
+                        "runs": u.runs,
+                        "bytes_sent": u.bytes_sent,
+                        "bytes_received": u.bytes_received,
+                    })
+                );
+            }
+            return Ok(());
+        }
+
+        if usage.is_empty() {
+            println!("No sync history recorded");
+            return Ok(());
+        }
+        println!(
+            "{:<30} {:<15} {:>6} {:>12} {:>12}",
+            "HOST", "PROFILE", "RUNS", "SENT", "RECEIVED"
+        );
+        for u in &usage {
+            println!(
+                "{:<30} {:<15} {:>6} {:>12} {:>12}",
+                u.host,
+                u.profile.as_deref().unwrap_or("-"),
+                u.runs,
+                format_bytes(u.bytes_sent),
+                format_bytes(u.bytes_received)
+            );
+        }
+        return Ok(());
+    }
+
+    let recent: Vec<_> = entries
+        .iter()
+        .filter(|e| since.is_none_or(|since| e.timestamp >= since))
+        .rev()
+        .take(limit)
+        .collect();
+    if json {
+        for entry in &recent {
+            println!("{}", serde_json::to_string(entry)?);
+        }
+        return Ok(());
+    }
+
+    if recent.is_empty() {
+        println!("No sync history recorded");
+        return Ok(());
+    }
+    for entry in recent {
+        let when = chrono::DateTime::from_timestamp(entry.timestamp as i64, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        println!("{}  {} → {}", when, entry.source, entry.destination);
+        println!(
+            "  {} files, {} ({} sent, {} received), {:.1}s{}",
+            entry.files_transferred,
+            format_bytes(entry.bytes_transferred),
+            format_bytes(entry.bytes_sent),
+            format_bytes(entry.bytes_received),
+            entry.duration_secs,
+            if entry.errors > 0 {
+                format!(", {} errors", entry.errors)
+            } else {
+                String::new()
+            }
+        );
+    }
+    Ok(())
+}
+
+async fn run_trash(
+    action: cli::TrashAction,
+    destination: &SyncPath,
+    run: Option<&str>,
+    trash_dir: Option<&std::path::Path>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    use sync::trash;
+
+    // Only the destination side of the transport is used
+    let transport = TransportRouter::new(
+        &SyncPath::Local(PathBuf::from(".")),
+        destination,
+        integrity::ChecksumType::None,
+        false,
+        1,
+        false,
+        cli::DanglingMode::Preserve,
+        None,
+        compress::CompressionDetection::Auto,
+        None,
+        transport::TransportOptions::default(),
+        &ssh::config::ClientOptions::default(),
+        None,
+    )
+    .await?;
+    let dir = trash::trash_dir(destination.path(), trash_dir);
+
+    match action {
+        cli::TrashAction::List => {
+            let runs = trash::list(&transport, &dir).await?;
+            if json {
+                println!("{}", serde_json::json!({ "trash": dir, "runs": runs }));
+            } else if runs.is_empty() {
+                println!("Nothing in the trash at {}", dir.display());
+            } else {
+                for run in &runs {
+                    println!(
+                        "  {}  {} files, {}",
+                        run.name,
+                        run.files,
+                        format_bytes(run.bytes)
+                    );
+                }
+            }
+        }
+        cli::TrashAction::Restore => {
+            let report = trash::restore(&transport, destination.path(), &dir, run, dry_run).await?;
+            if json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "report": report, "dry_run": dry_run })
+                );
+                return Ok(());
+            }
+            let verb = if dry_run { "Would restore" } else { "Restored" };
+            for path in &report.restored {
+                println!("  {} {}", verb, path.display());
+            }
+            for path in &report.skipped {
+                println!(
+                    "  {} {} (exists in the destination)",
+                    "Kept in trash".yellow(),
+                    path.display()
+                );
+            }
+            println!(
+                "{} {} entries from {}{}",
+                verb,
+                report.restored.len(),
+                report.run,
+                if report.skipped.is_empty() {
+                    String::new()
+                } else {
+                    format!(", {} left in the trash", report.skipped.len())
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run `sy bench` and print its results as a table, or as JSON lines
+async fn run_bench(
+    remote: Option<&SyncPath>,
+    workloads: &[cli::BenchWorkload],
+    scale: f64,
+    parallel: usize,
+    remote_binary: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    if scale.is_nan() || scale <= 0.0 {
+        anyhow::bail!("--scale must be greater than 0, got {}", scale);
+    }
+    let workloads = if workloads.is_empty() {
+        vec![
+            cli::BenchWorkload::SmallFiles,
+            cli::BenchWorkload::LargeFiles,
+            cli::BenchWorkload::Sparse,
+        ]
+    } else {
+        workloads.to_vec()
+    };
+
+    if !json {
+        println!("sy v{}", env!("CARGO_PKG_VERSION"));
+        println!(
+            "Benchmarking {} locally{} (scale {})\n",
+            workloads
+                .iter()
+                .map(|&w| bench::workload_name(w))
+                .collect::<Vec<_>>()
+                .join(", "),
+            remote
+                .map(|r| format!(" and against {}", r))
+                .unwrap_or_default(),
+            scale
+        );
+    }
+
+    let results = bench::run(&workloads, scale, remote, parallel, remote_binary).await?;
+    if json {
+        for result in &results {
+            println!("{}", serde_json::to_string(result)?);
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:<24} {:>7} {:>10} {:>8} {:>8} {:>9} {:>8} {:>8} {:>12} {:>9}",
+        "WORKLOAD",
+        "TARGET",
+        "FILES",
+        "SIZE",
+        "SCAN",
+        "PLAN",
+        "TRANSFER",
+        "TOTAL",
+        "RESYNC",
+        "THROUGHPUT",
+        "FILES/S"
+    );
+    for r in &results {
+        println!(
+            "{:<12} {:<24} {:>7} {:>10} {:>7.2}s {:>7.2}s {:>8.2}s {:>7.2}s {:>7.2}s {:>12} {:>9.0}",
+            r.workload,
+            r.target,
+            r.files,
+            format_bytes(r.bytes),
+            r.scan_secs,
+            r.plan_secs,
+            r.transfer_secs,
+            r.total_secs,
+            r.resync_secs,
+            perf::PerformanceMetrics::format_speed(r.bytes_per_sec()),
+            r.files_per_sec()
+        );
+    }
     Ok(())
 }
 
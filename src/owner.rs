@@ -0,0 +1,412 @@
+//! Permission bits, owners and groups (`-p`, `-o`, `-g`, `-a`)
+//!
+//! Scanned entries carry their mode, uid and gid in this machine's id space:
+//! ids scanned on another machine are mapped by user and group name, as rsync
+//! does, unless `--numeric-ids` is set.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Names of user and group ids, and ids of user and group names, looked up so far
+static USER_NAMES: Mutex<BTreeMap<u32, Option<String>>> = Mutex::new(BTreeMap::new());
+static GROUP_NAMES: Mutex<BTreeMap<u32, Option<String>>> = Mutex::new(BTreeMap::new());
+static USER_IDS: Mutex<BTreeMap<String, Option<u32>>> = Mutex::new(BTreeMap::new());
+static GROUP_IDS: Mutex<BTreeMap<String, Option<u32>>> = Mutex::new(BTreeMap::new());
+
+/// Permission bits, owner and group of a scanned entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilePerms {
+    pub mode: u32, // Permission bits, with setuid, setgid and sticky (no file type)
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl FilePerms {
+    #[cfg(unix)]
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        Some(Self {
+            mode: metadata.mode() & 0o7777,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+        })
+    }
+
+    /// Windows has no Unix permissions or owners
+    #[cfg(not(unix))]
+    pub fn from_metadata(_metadata: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+}
+
+/// Which of a source entry's permissions, owner and group are copied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreservePerms {
+    pub mode: bool,  // -p
+    pub owner: bool, // -o
+    pub group: bool, // -g
+}
+
+impl PreservePerms {
+    pub fn any(&self) -> bool {
+        self.mode || self.owner || self.group
+    }
+
+    /// What to set on the destination of an entry with `perms`, None if nothing
+    pub fn update(&self, perms: &FilePerms) -> Option<PermsUpdate> {
+        let update = PermsUpdate {
+            mode: self.mode.then_some(perms.mode),
+            uid: self.owner.then_some(perms.uid),
+            gid: self.group.then_some(perms.gid),
+        };
+        (!update.is_empty()).then_some(update)
+    }
+}
+
+/// Permission bits, owner and group to set on an entry; None leaves one as is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermsUpdate {
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
+impl PermsUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.mode.is_none() && self.uid.is_none() && self.gid.is_none()
+    }
+}
+
+/// A `PermsUpdate` sent to another machine, with the owner and group also
+/// named so it can set its own ids for them (unless `--numeric-ids`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedPerms {
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub user: Option<String>,
+    pub group: Option<String>,
+}
+
+impl NamedPerms {
+    /// `update` with its owner and group named, unless `numeric_ids`
+    pub fn new(update: &PermsUpdate, numeric_ids: bool) -> Self {
+        let named = !numeric_ids;
+        Self {
+            mode: update.mode,
+            uid: update.uid,
+            gid: update.gid,
+            user: update.uid.filter(|_| named).and_then(user_name),
+            group: update.gid.filter(|_| named).and_then(group_name),
+        }
+    }
+
+    /// The update in this machine's ids: named owners and groups that exist
+    /// here get their id here, others keep the id they came with
+    pub fn resolve(&self) -> PermsUpdate {
+        PermsUpdate {
+            mode: self.mode,
+            uid: self.user.as_deref().and_then(user_id).or(self.uid),
+            gid: self.group.as_deref().and_then(group_id).or(self.gid),
+        }
+    }
+}
+
+/// Apply `update` to `path`, without following symlinks
+///
+/// Like rsync, only root changes owners; other users can still hand an entry
+/// to one of their own groups, and a group they aren't in is skipped rather
+/// than failing the entry. The mode is set last, since chown clears the
+/// setuid and setgid bits.
+#[cfg(unix)]
+pub fn apply(path: &Path, update: &PermsUpdate) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let is_root = unsafe { libc::geteuid() } == 0;
+    let uid = update.uid.filter(|_| is_root);
+    if uid.is_some() || update.gid.is_some() {
+        match std::os::unix::fs::lchown(path, uid, update.gid) {
+            Ok(()) => {}
+            Err(e) if !is_root && e.kind() == std::io::ErrorKind::PermissionDenied => {
+                tracing::debug!("Not changing the group of {}: {}", path.display(), e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if let Some(mode) = update.mode {
+        // Symlinks have no mode of their own, and chmod would follow them
+        let metadata = std::fs::symlink_metadata(path)?;
+        if !metadata.is_symlink() && metadata.permissions().mode() & 0o7777 != mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn apply(_path: &Path, _update: &PermsUpdate) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Maps the user and group ids of another machine to this one's, by name
+///
+/// Ids the other machine has no name for, or whose name is unknown here, are
+/// kept as they are. With `--numeric-ids` the default (empty) map is used.
+#[derive(Debug, Default)]
+pub struct IdMap {
+    users: BTreeMap<u32, u32>,
+    groups: BTreeMap<u32, u32>,
+}
+
+impl IdMap {
+    /// From the names the other machine gave its ids (see `names_of`)
+    pub fn new(users: &[(u32, String)], groups: &[(u32, String)]) -> Self {
+        Self {
+            users: users
+                .iter()
+                .filter_map(|(id, name)| Some((*id, user_id(name)?)))
+                .collect(),
+            groups: groups
+                .iter()
+                .filter_map(|(id, name)| Some((*id, group_id(name)?)))
+                .collect(),
+        }
+    }
+
+    pub fn map(&self, perms: FilePerms) -> FilePerms {
+        FilePerms {
+            uid: self.users.get(&perms.uid).copied().unwrap_or(perms.uid),
+            gid: self.groups.get(&perms.gid).copied().unwrap_or(perms.gid),
+            ..perms
+        }
+    }
+}
+
+/// (id, name) pairs of users, and of groups
+pub type IdNames = (Vec<(u32, String)>, Vec<(u32, String)>);
+
+/// Names of the owners and groups among `perms`, for another machine's `IdMap`
+pub fn names_of(perms: impl IntoIterator<Item = FilePerms>) -> IdNames {
+    let mut uids = BTreeSet::new();
+    let mut gids = BTreeSet::new();
+    for perms in perms {
+        uids.insert(perms.uid);
+        gids.insert(perms.gid);
+    }
+    (
+        uids.into_iter()
+            .filter_map(|uid| Some((uid, user_name(uid)?)))
+            .collect(),
+        gids.into_iter()
+            .filter_map(|gid| Some((gid, group_name(gid)?)))
+            .collect(),
+    )
+}
+
+/// Name of user `uid` on this machine
+pub fn user_name(uid: u32) -> Option<String> {
+    cached(&USER_NAMES, uid, |uid| sys::user_name(*uid))
+}
+
+/// Name of group `gid` on this machine
+pub fn group_name(gid: u32) -> Option<String> {
+    cached(&GROUP_NAMES, gid, |gid| sys::group_name(*gid))
+}
+
+/// Id of the user called `name` on this machine
+pub fn user_id(name: &str) -> Option<u32> {
+    cached(&USER_IDS, name.to_string(), |name| sys::user_id(name))
+}
+
+/// Id of the group called `name` on this machine
+pub fn group_id(name: &str) -> Option<u32> {
+    cached(&GROUP_IDS, name.to_string(), |name| sys::group_id(name))
+}
+
+fn cached<K: Ord, V: Clone>(
+    cache: &Mutex<BTreeMap<K, Option<V>>>,
+    key: K,
+    lookup: impl FnOnce(&K) -> Option<V>,
+) -> Option<V> {
+    let mut cache = cache.lock().unwrap();
+    if let Some(value) = cache.get(&key) {
+        return value.clone();
+    }
+    let value = lookup(&key);
+    cache.insert(key, value.clone());
+    value
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{CStr, CString};
+
+    /// Largest buffer offered to the passwd and group lookups
+    const MAX_BUFFER: usize = 1024 * 1024;
+
+    pub fn user_name(uid: u32) -> Option<String> {
+        lookup(
+            |entry, buf, len, result| unsafe { libc::getpwuid_r(uid, entry, buf, len, result) },
+            |pwd: &libc::passwd| string(pwd.pw_name),
+        )
+    }
+
+    pub fn group_name(gid: u32) -> Option<String> {
+        lookup(
+            |entry, buf, len, result| unsafe { libc::getgrgid_r(gid, entry, buf, len, result) },
+            |grp: &libc::group| string(grp.gr_name),
+        )
+    }
+
+    pub fn user_id(name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        lookup(
+            |entry, buf, len, result| unsafe {
+                libc::getpwnam_r(name.as_ptr(), entry, buf, len, result)
+            },
+            |pwd: &libc::passwd| pwd.pw_uid,
+        )
+    }
+
+    pub fn group_id(name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        lookup(
+            |entry, buf, len, result| unsafe {
+                libc::getgrnam_r(name.as_ptr(), entry, buf, len, result)
+            },
+            |grp: &libc::group| grp.gr_gid,
+        )
+    }
+
+    fn string(ptr: *const libc::c_char) -> String {
+        unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Run one of the reentrant passwd or group lookups, growing the buffer
+    /// its strings go in while it's too small, and read the entry found
+    fn lookup<T, R>(
+        call: impl Fn(*mut T, *mut libc::c_char, libc::size_t, *mut *mut T) -> libc::c_int,
+        read: impl FnOnce(&T) -> R,
+    ) -> Option<R> {
+        let mut buf: Vec<libc::c_char> = vec![0; 1024];
+        loop {
+            let mut entry = std::mem::MaybeUninit::<T>::uninit();
+            let mut result = std::ptr::null_mut();
+            let rc = call(entry.as_mut_ptr(), buf.as_mut_ptr(), buf.len(), &mut result);
+            if rc == libc::ERANGE && buf.len() < MAX_BUFFER {
+                buf.resize(buf.len() * 2, 0);
+                continue;
+            }
+            if rc != 0 || result.is_null() {
+                return None;
+            }
+            // The entry was filled in, its strings pointing into buf
+            return Some(read(unsafe { &*result }));
+        }
+    }
+}
+
+/// Windows has no Unix users or groups
+#[cfg(not(unix))]
+mod sys {
+    pub fn user_name(_uid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn group_name(_gid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn user_id(_name: &str) -> Option<u32> {
+        None
+    }
+
+    pub fn group_id(_name: &str) -> Option<u32> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preserve_update() {
+        let perms = FilePerms {
+            mode: 0o4755,
+            uid: 1000,
+            gid: 100,
+        };
+        assert_eq!(PreservePerms::default().update(&perms), None);
+
+        let preserve = PreservePerms {
+            mode: true,
+            owner: false,
+            group: true,
+        };
+        assert_eq!(
+            preserve.update(&perms),
+            Some(PermsUpdate {
+                mode: Some(0o4755),
+                uid: None,
+                gid: Some(100),
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_mode_and_own_group() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("file");
+        std::fs::write(&path, b"data").unwrap();
+        let gid = std::fs::metadata(&path).unwrap().gid();
+
+        let update = PermsUpdate {
+            mode: Some(0o640),
+            uid: None,
+            gid: Some(gid),
+        };
+        apply(&path, &update).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.mode() & 0o7777, 0o640);
+        assert_eq!(metadata.gid(), gid);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_names_roundtrip() {
+        let uid = unsafe { libc::geteuid() };
+        let gid = unsafe { libc::getegid() };
+        let (users, groups) = names_of([FilePerms {
+            mode: 0o644,
+            uid,
+            gid,
+        }]);
+
+        // Containers may run as ids without names
+        if let [(_, name)] = users.as_slice() {
+            assert_eq!(user_id(name), Some(uid));
+        }
+        if let [(_, name)] = groups.as_slice() {
+            assert_eq!(group_id(name), Some(gid));
+        }
+
+        // Names unknown here keep the ids they came with
+        let map = IdMap::new(&[(4242, "sy-no-such-user".to_string())], &[]);
+        let perms = FilePerms {
+            mode: 0o644,
+            uid: 4242,
+            gid: 4242,
+        };
+        assert_eq!(map.map(perms), perms);
+    }
+}
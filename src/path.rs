@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-/// Represents a sync path that can be either local, remote (SSH), or S3
+/// Represents a sync path that can be either local, remote (SSH), a sy daemon module, or S3
 #[derive(Debug, Clone, PartialEq)]
 pub enum SyncPath {
     Local(PathBuf),
@@ -9,6 +9,14 @@ pub enum SyncPath {
         user: Option<String>,
         path: PathBuf,
     },
+    /// Path inside a module served by `sy --daemon`, relative to the module root
+    Daemon {
+        host: String,
+        port: Option<u16>,
+        user: Option<String>,
+        module: String,
+        path: PathBuf,
+    },
     S3 {
         bucket: String,
         key: String,
@@ -23,6 +31,7 @@ impl SyncPath {
     /// Supported formats:
     /// - Local: `/path/to/dir`, `./relative/path`, `relative/path`
     /// - Remote: `user@host:/path`, `host:/path`
+    /// - Daemon: `host::module/path`, `user@host:port::module/path`
     /// - S3: `s3://bucket/key/path`, `s3://bucket/key?region=us-west-2`, `s3://bucket/key?endpoint=https://...`
     pub fn parse(s: &str) -> Self {
         // Check for S3 URL format
@@ -72,6 +81,11 @@ impl SyncPath {
             }
         }
 
+        // Check for daemon path format (contains :: before any /)
+        if let Some(daemon_path) = Self::parse_daemon(s) {
+            return daemon_path;
+        }
+
         // Check for remote path format (contains : before any /)
         if let Some(colon_pos) = s.find(':') {
             // Check if this is a remote path (no / before the :)
@@ -111,11 +125,41 @@ impl SyncPath {
         SyncPath::Local(PathBuf::from(s))
     }
 
+    /// Parse `[user@]host[:port]::module[/path]`
+    fn parse_daemon(s: &str) -> Option<Self> {
+        let (address, rest) = s.split_once("::")?;
+        if address.is_empty() || address.contains('/') {
+            return None;
+        }
+        let (module, path) = rest.split_once('/').unwrap_or((rest, ""));
+        if module.is_empty() {
+            return None;
+        }
+
+        let (user, host) = match address.split_once('@') {
+            Some((user, host)) => (Some(user.to_string()), host),
+            None => (None, address),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((name, port)) => (name, Some(port.parse().ok()?)),
+            None => (host, None),
+        };
+
+        Some(SyncPath::Daemon {
+            host: host.to_string(),
+            port,
+            user,
+            module: module.to_string(),
+            path: PathBuf::from(path),
+        })
+    }
+
     /// Get the path component
     pub fn path(&self) -> &Path {
         match self {
             SyncPath::Local(path) => path,
             SyncPath::Remote { path, .. } => path,
+            SyncPath::Daemon { path, .. } => path,
             SyncPath::S3 { key, .. } => Path::new(key),
         }
     }
@@ -149,6 +193,26 @@ impl std::fmt::Display for SyncPath {
                     write!(f, "{}:{}", host, path.display())
                 }
             }
+            SyncPath::Daemon {
+                host,
+                port,
+                user,
+                module,
+                path,
+            } => {
+                if let Some(u) = user {
+                    write!(f, "{}@", u)?;
+                }
+                write!(f, "{}", host)?;
+                if let Some(p) = port {
+                    write!(f, ":{}", p)?;
+                }
+                write!(f, "::{}", module)?;
+                if !path.as_os_str().is_empty() {
+                    write!(f, "/{}", path.display())?;
+                }
+                Ok(())
+            }
             SyncPath::S3 {
                 bucket,
                 key,
@@ -172,10 +236,109 @@ impl std::fmt::Display for SyncPath {
     }
 }
 
+/// Expand `{hostname}`, `{date}` and `{date:FORMAT}` in a destination
+///
+/// `{date}` is today's local date as `2024-01-31`; FORMAT takes strftime
+/// specifiers (`{date:%Y/%m}`, `{date:%Y-%m-%d_%H%M}`). Literal braces are
+/// written `{{` and `}}`. Unknown variables are an error, so a typo can't
+/// quietly become a directory name.
+///
+/// The hostname is only looked up for a template that uses it.
+pub fn expand_template(template: &str) -> Result<String, String> {
+    let hostname = || whoami::fallible::hostname().map_err(|e| format!("hostname: {}", e));
+    expand_template_with(template, &chrono::Local::now().fixed_offset(), &hostname)
+}
+
+fn expand_template_with(
+    template: &str,
+    now: &chrono::DateTime<chrono::FixedOffset>,
+    hostname: &dyn Fn() -> Result<String, String>,
+) -> Result<String, String> {
+    use chrono::format::{Item, StrftimeItems};
+
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if rest[pos..].starts_with("{{") || rest[pos..].starts_with("}}") {
+            expanded.push_str(&rest[pos..pos + 1]);
+            rest = &after[1..];
+            continue;
+        }
+        if rest[pos..].starts_with('}') {
+            return Err(format!(
+                "Unmatched '}}' in '{}' (write '}}}}' for a literal brace)",
+                template
+            ));
+        }
+
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("Unclosed '{{' in '{}'", template))?;
+        let variable = &after[..end];
+        match variable.split_once(':') {
+            None if variable == "hostname" => expanded.push_str(&hostname()?),
+            None if variable == "date" => expanded.push_str(&now.format("%Y-%m-%d").to_string()),
+            Some(("date", format)) => {
+                let items: Vec<Item> = StrftimeItems::new(format).collect();
+                if format.is_empty() || items.contains(&Item::Error) {
+                    return Err(format!("Invalid date format '{}' in '{}'", format, template));
+                }
+                expanded.push_str(&now.format_with_items(items.into_iter()).to_string());
+            }
+            _ => {
+                return Err(format!(
+                    "Unknown variable '{{{}}}' in '{}' (expected {{hostname}}, {{date}} or {{date:FORMAT}})",
+                    variable, template
+                ))
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_expand_template() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-01-31T09:05:00+01:00").unwrap();
+        let expand = |template| expand_template_with(template, &now, &|| Ok("laptop".into()));
+
+        assert_eq!(expand("/backups/plain").unwrap(), "/backups/plain");
+        assert_eq!(
+            expand("backups/{hostname}/{date}/").unwrap(),
+            "backups/laptop/2024-01-31/"
+        );
+        assert_eq!(
+            expand("nas:/srv/{date:%Y/%m}/{date:%H%M}").unwrap(),
+            "nas:/srv/2024/01/0905"
+        );
+        assert_eq!(expand("/data/{{literal}}").unwrap(), "/data/{literal}");
+
+        for bad in [
+            "/b/{host}",
+            "/b/{date",
+            "/b/}",
+            "/b/{date:%Q}",
+            "/b/{date:}",
+        ] {
+            assert!(expand(bad).is_err(), "{}", bad);
+        }
+
+        // A failing hostname lookup only matters where {hostname} is used
+        let no_hostname = || Err("no hostname".to_string());
+        assert_eq!(
+            expand_template_with("/b/{date}", &now, &no_hostname).unwrap(),
+            "/b/2024-01-31"
+        );
+        assert!(expand_template_with("/b/{hostname}", &now, &no_hostname).is_err());
+    }
+
     #[test]
     fn test_parse_local_absolute() {
         let path = SyncPath::parse("/home/user/docs");
@@ -225,6 +388,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_daemon() {
+        let path = SyncPath::parse("nas::photos/2024/trip");
+        assert!(!path.is_remote() && !path.is_local());
+        assert_eq!(path.path(), Path::new("2024/trip"));
+        match &path {
+            SyncPath::Daemon {
+                host,
+                port,
+                user,
+                module,
+                ..
+            } => {
+                assert_eq!(host, "nas");
+                assert_eq!(*port, None);
+                assert_eq!(*user, None);
+                assert_eq!(module, "photos");
+            }
+            _ => panic!("Expected daemon path"),
+        }
+        assert_eq!(path.to_string(), "nas::photos/2024/trip");
+
+        let path = SyncPath::parse("nick@nas:9000::backups");
+        assert_eq!(path.path(), Path::new(""));
+        match &path {
+            SyncPath::Daemon {
+                host,
+                port,
+                user,
+                module,
+                ..
+            } => {
+                assert_eq!(host, "nas");
+                assert_eq!(*port, Some(9000));
+                assert_eq!(user.as_deref(), Some("nick"));
+                assert_eq!(module, "backups");
+            }
+            _ => panic!("Expected daemon path"),
+        }
+        assert_eq!(path.to_string(), "nick@nas:9000::backups");
+
+        // No module, or a :: after a slash, isn't a daemon path
+        assert!(matches!(SyncPath::parse("nas::"), SyncPath::Remote { .. }));
+        assert!(SyncPath::parse("./a::b").is_local());
+    }
+
     #[test]
     fn test_parse_windows_drive_letter() {
         // C:/path should be treated as local, not remote
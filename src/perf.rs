@@ -6,7 +6,12 @@
 //! - Time breakdown by operation
 //! - Resource usage (memory, CPU)
 
+use crate::compress::{self, CompressionStats};
+use crate::fs_util::{self, NoatimeStats};
+use crate::integrity::{self, HashStats};
+use crate::transport::ssh::{self, RoundTrips};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -58,6 +63,49 @@ pub struct PerformanceMetrics {
 
     /// Bandwidth utilization percentage (if rate limit set)
     pub bandwidth_utilization: Option<f64>,
+
+    /// Bytes checksummed (verification, checksum planning)
+    #[serde(default)]
+    pub hash_bytes: u64,
+
+    /// Time spent checksumming, summed across threads
+    #[serde(default)]
+    pub hash_duration: Duration,
+
+    /// Whether source files were opened with `--open-noatime`
+    #[serde(default)]
+    pub open_noatime: bool,
+
+    /// Source opens that used O_NOATIME / fell back to a plain open
+    #[serde(default)]
+    pub noatime: NoatimeStats,
+
+    /// Tasks run and busy time per transfer worker
+    #[serde(default)]
+    pub workers: Vec<WorkerUsage>,
+
+    /// Time spent waiting for a free transfer worker
+    #[serde(default)]
+    pub worker_wait: Duration,
+
+    /// Bytes (de)compressed for transfer
+    #[serde(default)]
+    pub compression_bytes: u64,
+
+    /// CPU time spent compressing and decompressing, summed across threads
+    #[serde(default)]
+    pub compression_duration: Duration,
+
+    /// SSH round trips per remote host
+    #[serde(default)]
+    pub round_trips: BTreeMap<String, RoundTrips>,
+}
+
+/// How much one transfer worker was used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerUsage {
+    pub tasks: u64,
+    pub busy: Duration,
 }
 
 impl PerformanceMetrics {
@@ -91,6 +139,21 @@ impl PerformanceMetrics {
         }
     }
 
+    /// Share of the transfer phase each worker spent running tasks (0.0-1.0)
+    pub fn worker_utilization(&self) -> Vec<f64> {
+        let transfer = self.transfer_duration.as_secs_f64();
+        self.workers
+            .iter()
+            .map(|w| {
+                if transfer > 0.0 {
+                    (w.busy.as_secs_f64() / transfer).min(1.0)
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
     /// Print performance summary to stdout
     pub fn print_summary(&self) {
         use colored::Colorize;
@@ -166,6 +229,76 @@ impl PerformanceMetrics {
                 utilization.to_string().cyan()
             );
         }
+
+        if self.hash_bytes > 0 {
+            let stats = HashStats {
+                bytes: self.hash_bytes,
+                duration: self.hash_duration,
+            };
+            println!(
+                "  Hashing:         {} at {} per thread ({})",
+                Self::format_size(self.hash_bytes).cyan(),
+                Self::format_speed(stats.throughput()).cyan(),
+                integrity::simd_backends().bright_black()
+            );
+        }
+
+        if self.compression_bytes > 0 {
+            println!(
+                "  Compression:     {} in {} CPU",
+                Self::format_size(self.compression_bytes).cyan(),
+                Self::format_duration(self.compression_duration).cyan()
+            );
+        }
+
+        if !self.workers.is_empty() {
+            let utilization = self.worker_utilization();
+            let average = utilization.iter().sum::<f64>() / utilization.len() as f64;
+            println!(
+                "\n  Workers:         {} at {:.1}% average utilization, {} waiting for a free worker",
+                self.workers.len().to_string().cyan(),
+                average * 100.0,
+                Self::format_duration(self.worker_wait).cyan()
+            );
+            for (i, (worker, share)) in self.workers.iter().zip(&utilization).enumerate() {
+                println!(
+                    "    #{:<3}          {:>5.1}% busy, {} tasks",
+                    i + 1,
+                    share * 100.0,
+                    worker.tasks
+                );
+            }
+        }
+
+        for (host, trips) in &self.round_trips {
+            println!(
+                "  SSH {}: {} round trips ({} commands, {} SFTP sessions)",
+                host,
+                trips.total().to_string().cyan(),
+                trips.commands,
+                trips.sftp_sessions
+            );
+        }
+
+        if self.open_noatime {
+            if !fs_util::CAN_OPEN_NOATIME {
+                println!(
+                    "  Source atimes:   {}",
+                    "not protected (O_NOATIME is Linux-only)".yellow()
+                );
+            } else if self.noatime.fallbacks > 0 {
+                println!(
+                    "  Source atimes:   {} opens without updating atime, {} updated it (O_NOATIME needs file ownership)",
+                    self.noatime.opened.to_string().green(),
+                    self.noatime.fallbacks.to_string().yellow()
+                );
+            } else {
+                println!(
+                    "  Source atimes:   {} opens without updating atime",
+                    self.noatime.opened.to_string().green()
+                );
+            }
+        }
     }
 
     /// Format bytes as human-readable size
@@ -193,6 +326,18 @@ pub struct PerformanceMonitor {
     directories_created: Arc<AtomicU64>,
     peak_speed: Arc<AtomicU64>,
     rate_limit: Option<u64>,
+    /// Process-wide checksum counters when monitoring started
+    hash_start: HashStats,
+    /// Process-wide O_NOATIME counters when monitoring started
+    noatime_start: NoatimeStats,
+    /// Process-wide compression counters when monitoring started
+    compression_start: CompressionStats,
+    /// Process-wide SSH round-trip counters when monitoring started
+    round_trips_start: BTreeMap<String, RoundTrips>,
+    workers: Vec<WorkerUsage>,
+    worker_wait: Duration,
+    /// Sources are read with O_NOATIME (--open-noatime)
+    open_noatime: bool,
 }
 
 impl PerformanceMonitor {
@@ -215,9 +360,22 @@ impl PerformanceMonitor {
             directories_created: Arc::new(AtomicU64::new(0)),
             peak_speed: Arc::new(AtomicU64::new(0)),
             rate_limit,
+            hash_start: integrity::hash_stats(),
+            noatime_start: fs_util::noatime_stats(),
+            compression_start: compress::compression_stats(),
+            round_trips_start: ssh::round_trip_stats(),
+            workers: Vec::new(),
+            worker_wait: Duration::ZERO,
+            open_noatime: false,
         }
     }
 
+    /// Report whether sources were read with O_NOATIME (--open-noatime)
+    pub fn with_open_noatime(mut self, enabled: bool) -> Self {
+        self.open_noatime = enabled;
+        self
+    }
+
     /// Start timing the scan phase
     pub fn start_scan(&mut self) {
         self.scan_start = Some(Instant::now());
@@ -260,6 +418,12 @@ impl PerformanceMonitor {
         }
     }
 
+    /// Record per-worker usage and time spent waiting for a free worker
+    pub fn record_workers(&mut self, workers: Vec<WorkerUsage>, wait: Duration) {
+        self.workers = workers;
+        self.worker_wait = wait;
+    }
+
     /// Record bytes transferred
     pub fn add_bytes_transferred(&self, bytes: u64) {
         self.bytes_transferred.fetch_add(bytes, Ordering::Relaxed);
@@ -352,6 +516,21 @@ impl PerformanceMonitor {
             None
         };
 
+        let hashing = integrity::hash_stats().since(&self.hash_start);
+        let compression = compress::compression_stats().since(&self.compression_start);
+        let round_trips = ssh::round_trip_stats()
+            .into_iter()
+            .map(|(host, trips)| {
+                let earlier = self
+                    .round_trips_start
+                    .get(&host)
+                    .copied()
+                    .unwrap_or_default();
+                (host, trips.since(&earlier))
+            })
+            .filter(|(_, trips)| trips.total() > 0)
+            .collect();
+
         PerformanceMetrics {
             total_duration,
             scan_duration,
@@ -368,6 +547,15 @@ impl PerformanceMonitor {
             peak_transfer_speed,
             files_per_second,
             bandwidth_utilization,
+            hash_bytes: hashing.bytes,
+            hash_duration: hashing.duration,
+            open_noatime: self.open_noatime,
+            noatime: fs_util::noatime_stats().since(&self.noatime_start),
+            workers: self.workers.clone(),
+            worker_wait: self.worker_wait,
+            compression_bytes: compression.bytes,
+            compression_duration: compression.duration,
+            round_trips,
         }
     }
 }
@@ -394,6 +582,42 @@ mod tests {
         assert_eq!(metrics.bytes_transferred, 1000);
     }
 
+    #[test]
+    fn test_hash_throughput_tracked() {
+        let monitor = PerformanceMonitor::new(None);
+        crate::integrity::IntegrityVerifier::new(crate::integrity::ChecksumType::Fast, false)
+            .compute_data_checksum(&[1u8; 8192])
+            .unwrap();
+
+        let metrics = monitor.get_metrics();
+        assert!(metrics.hash_bytes >= 8192);
+    }
+
+    #[test]
+    fn test_worker_breakdown() {
+        let mut monitor = PerformanceMonitor::new(None);
+        monitor.start_transfer();
+        thread::sleep(Duration::from_millis(20));
+        monitor.end_transfer();
+        monitor.record_workers(
+            vec![
+                WorkerUsage {
+                    tasks: 3,
+                    busy: Duration::from_secs(3600),
+                },
+                WorkerUsage::default(),
+            ],
+            Duration::from_millis(5),
+        );
+        crate::compress::compress(&[7u8; 4096], crate::compress::Compression::Zstd).unwrap();
+
+        let metrics = monitor.get_metrics();
+        assert_eq!(metrics.workers.len(), 2);
+        assert_eq!(metrics.worker_wait, Duration::from_millis(5));
+        assert_eq!(metrics.worker_utilization(), vec![1.0, 0.0]);
+        assert!(metrics.compression_bytes >= 4096);
+    }
+
     #[test]
     fn test_format_speed() {
         assert_eq!(PerformanceMetrics::format_speed(500.0), "500 B/s");
@@ -0,0 +1,532 @@
+//! Experimental QUIC transport (`sy-remote serve-quic`, `sy --quic`)
+//!
+//! Carries the native protocol of [`crate::daemon`] over QUIC instead of
+//! TCP, for long, fast links where one TCP connection (or SSH on top of it)
+//! can't fill the pipe: each pooled daemon connection becomes a stream of a
+//! single QUIC connection, so a lost packet stalls only its own stream, and
+//! the flow control windows are sized for large bandwidth-delay products.
+//!
+//! The connection is always TLS 1.3. The client either checks the server's
+//! certificate against a PEM file it trusts (`--quic-cert`), or both sides
+//! share a key (`SY_QUIC_PSK`): the server then uses a throwaway self-signed
+//! certificate, and each side proves it knows the key with a keyed BLAKE3
+//! hash of material exported from the TLS session, which a man in the
+//! middle, holding two different sessions, can't forward. Module users and
+//! secrets are checked on top, as over TCP.
+//!
+//! Needs the `quic` cargo feature.
+
+use crate::config::DaemonModule;
+use crate::daemon::Connection;
+use crate::error::{Result, SyncError};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[cfg(feature = "quic")]
+use crate::daemon;
+#[cfg(feature = "quic")]
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+#[cfg(feature = "quic")]
+use rustls::crypto::CryptoProvider;
+#[cfg(feature = "quic")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "quic")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+#[cfg(feature = "quic")]
+use std::path::Path;
+#[cfg(feature = "quic")]
+use std::sync::Arc;
+#[cfg(feature = "quic")]
+use std::time::Duration;
+
+/// UDP port `sy-remote serve-quic` listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 8731;
+
+/// Environment variable both sides read the pre-shared key from
+pub const PSK_ENV: &str = "SY_QUIC_PSK";
+
+/// Label of the TLS exporter the pre-shared key proofs are bound to
+#[cfg_attr(not(feature = "quic"), allow(dead_code))]
+const EXPORTER_LABEL: &[u8] = b"EXPORTER-sy-quic-psk";
+
+/// Flow control window of one stream
+#[cfg(feature = "quic")]
+const STREAM_WINDOW: u32 = 16 * 1024 * 1024;
+
+/// Flow control window of the whole connection, across its streams
+#[cfg(feature = "quic")]
+const CONNECTION_WINDOW: u32 = 64 * 1024 * 1024;
+
+/// Keeps connections open through long scans on either side
+#[cfg(feature = "quic")]
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// Application error code closing a connection whose key didn't match
+#[cfg(feature = "quic")]
+const PSK_MISMATCH: u32 = 1;
+
+/// How long a client has to prove it knows the pre-shared key
+#[cfg(feature = "quic")]
+const PSK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How the client reaches daemon paths over QUIC (`--quic`)
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// PEM certificates to check the server's certificate against
+    pub trusted_cert: Option<PathBuf>,
+    /// Pre-shared key, from `SY_QUIC_PSK`
+    pub psk: Option<String>,
+}
+
+/// How `sy-remote serve-quic` authenticates itself and its clients
+#[derive(Debug, Clone, Default)]
+#[allow(dead_code)] // Served by sy-remote, not the sy binary
+pub struct ServerOptions {
+    /// PEM certificate chain presented to clients
+    pub cert: Option<PathBuf>,
+    /// PEM private key of the certificate
+    pub key: Option<PathBuf>,
+    /// Pre-shared key clients must prove they know, from `SY_QUIC_PSK`
+    pub psk: Option<String>,
+}
+
+/// Proof that `role` ("client" or "server") knows `psk`, for the TLS
+/// session `exporter` was exported from
+#[cfg_attr(not(feature = "quic"), allow(dead_code))]
+fn psk_proof(psk: &str, role: &[u8], exporter: &[u8]) -> blake3::Hash {
+    let key = blake3::hash(psk.as_bytes());
+    let mut hasher = blake3::Hasher::new_keyed(key.as_bytes());
+    hasher.update(role);
+    hasher.update(exporter);
+    hasher.finalize()
+}
+
+/// ALPN protocol, which tells apart servers that expect a pre-shared key
+#[cfg_attr(not(feature = "quic"), allow(dead_code))]
+fn alpn(psk: bool) -> Vec<u8> {
+    let protocol = if psk { "sy-psk" } else { "sy" };
+    format!("{}/{}", protocol, crate::daemon::PROTOCOL_VERSION).into_bytes()
+}
+
+#[cfg(feature = "quic")]
+fn quic_error(e: impl std::fmt::Display) -> SyncError {
+    SyncError::NetworkError {
+        message: format!("QUIC: {}", e),
+    }
+}
+
+#[cfg(feature = "quic")]
+fn tls_config_error(path: &Path, e: impl std::fmt::Display) -> SyncError {
+    SyncError::Config(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(feature = "quic")]
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Windows sized for long, fast links rather than quinn's LAN defaults
+#[cfg(feature = "quic")]
+fn transport_config() -> quinn::TransportConfig {
+    let mut config = quinn::TransportConfig::default();
+    config
+        .stream_receive_window(quinn::VarInt::from_u32(STREAM_WINDOW))
+        .receive_window(quinn::VarInt::from_u32(CONNECTION_WINDOW))
+        .send_window(CONNECTION_WINDOW as u64)
+        .keep_alive_interval(Some(KEEP_ALIVE))
+        // BBR paces by measured bandwidth instead of backing off at every
+        // loss, which is what holds TCP back on long paths
+        .congestion_controller_factory(Arc::new(quinn::congestion::BbrConfig::default()));
+    config
+}
+
+/// Key material of the TLS session for the pre-shared key proofs
+#[cfg(feature = "quic")]
+fn exporter(connection: &quinn::Connection) -> Result<[u8; 32]> {
+    let mut exporter = [0; 32];
+    connection
+        .export_keying_material(&mut exporter, EXPORTER_LABEL, b"")
+        .map_err(|_| quic_error("Failed to export keying material"))?;
+    Ok(exporter)
+}
+
+#[cfg(feature = "quic")]
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|e| tls_config_error(path, e))?;
+    if certs.is_empty() {
+        return Err(tls_config_error(path, "no PEM certificates"));
+    }
+    Ok(certs)
+}
+
+/// Accepts whatever certificate the server presents; only used with a
+/// pre-shared key, whose proofs authenticate the server instead
+#[cfg(feature = "quic")]
+#[derive(Debug)]
+struct PskAuthenticated(Arc<CryptoProvider>);
+
+#[cfg(feature = "quic")]
+impl ServerCertVerifier for PskAuthenticated {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// One QUIC connection to `sy-remote serve-quic`
+pub struct Client {
+    #[cfg(feature = "quic")]
+    connection: quinn::Connection,
+    /// Drives the connection; kept for as long as it's used
+    #[cfg(feature = "quic")]
+    _endpoint: quinn::Endpoint,
+}
+
+impl Client {
+    /// Connect to `host:port`, checking the server's certificate or
+    /// exchanging pre-shared key proofs before anything else is sent
+    #[cfg(feature = "quic")]
+    pub async fn connect(host: &str, port: u16, options: &ClientOptions) -> Result<Self> {
+        let provider = crypto_provider();
+        let builder = rustls::ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(quic_error)?;
+        let mut crypto = match (&options.trusted_cert, &options.psk) {
+            (Some(path), _) => {
+                let mut roots = rustls::RootCertStore::empty();
+                for cert in read_certs(path)? {
+                    roots.add(cert).map_err(|e| tls_config_error(path, e))?;
+                }
+                builder
+                    .with_root_certificates(roots)
+                    .with_no_client_auth()
+            }
+            (None, Some(_)) => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PskAuthenticated(provider)))
+                .with_no_client_auth(),
+            (None, None) => {
+                return Err(SyncError::Config(format!(
+                    "--quic needs --quic-cert to check the server's certificate, or a pre-shared key in {}",
+                    PSK_ENV
+                )))
+            }
+        };
+        crypto.alpn_protocols = vec![alpn(options.psk.is_some())];
+
+        let crypto =
+            quinn::crypto::rustls::QuicClientConfig::try_from(crypto).map_err(quic_error)?;
+        let mut config = quinn::ClientConfig::new(Arc::new(crypto));
+        config.transport_config(Arc::new(transport_config()));
+
+        let address = tokio::net::lookup_host((host, port))
+            .await?
+            .next()
+            .ok_or_else(|| quic_error(format!("{} has no address", host)))?;
+        let bind: SocketAddr = if address.is_ipv6() {
+            (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+        } else {
+            (std::net::Ipv4Addr::UNSPECIFIED, 0).into()
+        };
+        let mut endpoint = quinn::Endpoint::client(bind)?;
+        endpoint.set_default_client_config(config);
+        let connection = endpoint
+            .connect(address, host)
+            .map_err(quic_error)?
+            .await
+            .map_err(|e| SyncError::NetworkError {
+                message: format!("Failed to connect to {} over QUIC: {}", address, e),
+            })?;
+
+        if let Some(psk) = &options.psk {
+            let exporter = exporter(&connection)?;
+            let (mut send, mut recv) = connection.open_bi().await.map_err(quic_error)?;
+            send.write_all(psk_proof(psk, b"client", &exporter).as_bytes())
+                .await
+                .map_err(quic_error)?;
+            send.finish().map_err(quic_error)?;
+            let mut answer = [0; 32];
+            recv.read_exact(&mut answer).await.map_err(|_| {
+                SyncError::Restricted(format!("{} refused the pre-shared key", host))
+            })?;
+            // blake3::Hash compares in constant time
+            if blake3::Hash::from(answer) != psk_proof(psk, b"server", &exporter) {
+                connection.close(PSK_MISMATCH.into(), b"pre-shared key mismatch");
+                return Err(SyncError::Restricted(format!(
+                    "{} doesn't know the pre-shared key",
+                    host
+                )));
+            }
+        }
+        tracing::debug!("QUIC connection to {} established", address);
+
+        Ok(Self {
+            connection,
+            _endpoint: endpoint,
+        })
+    }
+
+    /// Without the `quic` feature there is no QUIC client
+    #[cfg(not(feature = "quic"))]
+    pub async fn connect(_host: &str, _port: u16, _options: &ClientOptions) -> Result<Self> {
+        Err(SyncError::Config(
+            "--quic requires sy built with `--features quic`".to_string(),
+        ))
+    }
+
+    /// Open another stream, carrying one daemon connection
+    #[cfg(feature = "quic")]
+    pub async fn open_stream(&self) -> Result<Box<dyn Connection>> {
+        let (send, recv) = self.connection.open_bi().await.map_err(quic_error)?;
+        Ok(Box::new(tokio::io::join(recv, send)))
+    }
+
+    #[cfg(not(feature = "quic"))]
+    pub async fn open_stream(&self) -> Result<Box<dyn Connection>> {
+        Err(SyncError::Config(
+            "--quic requires sy built with `--features quic`".to_string(),
+        ))
+    }
+}
+
+/// A bound `sy-remote serve-quic` endpoint
+#[allow(dead_code)] // Served by sy-remote, not the sy binary
+pub struct Listener {
+    #[cfg(feature = "quic")]
+    endpoint: quinn::Endpoint,
+    #[cfg(feature = "quic")]
+    psk: Option<String>,
+}
+
+#[allow(dead_code)] // Served by sy-remote, not the sy binary
+impl Listener {
+    /// Bind `address` with the certificate in `options`, or a self-signed
+    /// one when only a pre-shared key authenticates the server
+    ///
+    /// Must be called within a tokio runtime.
+    #[cfg(feature = "quic")]
+    pub fn bind(address: SocketAddr, options: &ServerOptions) -> Result<Self> {
+        let (certs, key) = match (&options.cert, &options.key, &options.psk) {
+            (Some(cert), Some(key), _) => (
+                read_certs(cert)?,
+                PrivateKeyDer::from_pem_file(key).map_err(|e| tls_config_error(key, e))?,
+            ),
+            (None, None, Some(_)) => {
+                let generated = rcgen::generate_simple_self_signed(vec!["sy-remote".to_string()])
+                    .map_err(|e| {
+                    SyncError::Config(format!("Failed to generate certificate: {}", e))
+                })?;
+                (
+                    vec![generated.cert.der().clone()],
+                    PrivatePkcs8KeyDer::from(generated.key_pair.serialize_der()).into(),
+                )
+            }
+            (None, None, None) => {
+                return Err(SyncError::Config(format!(
+                    "serve-quic needs --cert and --key, or a pre-shared key in {}",
+                    PSK_ENV
+                )))
+            }
+            _ => {
+                return Err(SyncError::Config(
+                    "--cert and --key must be given together".to_string(),
+                ))
+            }
+        };
+
+        let mut crypto = rustls::ServerConfig::builder_with_provider(crypto_provider())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(quic_error)?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| SyncError::Config(format!("Invalid certificate or key: {}", e)))?;
+        crypto.alpn_protocols = vec![alpn(options.psk.is_some())];
+
+        let crypto =
+            quinn::crypto::rustls::QuicServerConfig::try_from(crypto).map_err(quic_error)?;
+        let mut config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+        config.transport_config(Arc::new(transport_config()));
+        let endpoint = quinn::Endpoint::server(config, address)?;
+
+        Ok(Self {
+            endpoint,
+            psk: options.psk.clone(),
+        })
+    }
+
+    /// Without the `quic` feature there is nothing to serve with
+    #[cfg(not(feature = "quic"))]
+    pub fn bind(_address: SocketAddr, _options: &ServerOptions) -> Result<Self> {
+        Err(SyncError::Config(
+            "serve-quic requires sy built with `--features quic`".to_string(),
+        ))
+    }
+
+    /// The address actually bound, with the port chosen for port 0
+    #[cfg(feature = "quic")]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.endpoint.local_addr()?)
+    }
+
+    #[cfg(not(feature = "quic"))]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Err(SyncError::Config(
+            "serve-quic requires sy built with `--features quic`".to_string(),
+        ))
+    }
+
+    /// Serve `modules` until the process is stopped
+    ///
+    /// Every module is checked before the first connection is accepted, so
+    /// a typo in the config fails at startup.
+    #[cfg(feature = "quic")]
+    pub async fn serve(self, modules: &HashMap<String, DaemonModule>) -> Result<()> {
+        let modules = Arc::new(daemon::Modules::new(modules)?);
+        let psk = self.psk.map(Arc::new);
+
+        while let Some(incoming) = self.endpoint.accept().await {
+            let peer = incoming.remote_address();
+            let modules = Arc::clone(&modules);
+            let psk = psk.clone();
+            tokio::spawn(async move {
+                match serve_connection(incoming, psk.as_deref().map(String::as_str), modules).await
+                {
+                    Ok(()) => tracing::debug!("{}: disconnected", peer),
+                    Err(e) => tracing::warn!("{}: {}", peer, e),
+                }
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "quic"))]
+    pub async fn serve(self, _modules: &HashMap<String, DaemonModule>) -> Result<()> {
+        Err(SyncError::Config(
+            "serve-quic requires sy built with `--features quic`".to_string(),
+        ))
+    }
+}
+
+/// Check a client's pre-shared key proof and answer with the server's
+#[cfg(feature = "quic")]
+async fn accept_psk(connection: &quinn::Connection, psk: &str) -> Result<()> {
+    let exporter = exporter(connection)?;
+    let (mut send, mut recv) = connection.accept_bi().await.map_err(quic_error)?;
+    let mut given = [0; 32];
+    recv.read_exact(&mut given).await.map_err(quic_error)?;
+    // blake3::Hash compares in constant time
+    if blake3::Hash::from(given) != psk_proof(psk, b"client", &exporter) {
+        connection.close(PSK_MISMATCH.into(), b"pre-shared key mismatch");
+        return Err(SyncError::Restricted(
+            "client doesn't know the pre-shared key".to_string(),
+        ));
+    }
+    send.write_all(psk_proof(psk, b"server", &exporter).as_bytes())
+        .await
+        .map_err(quic_error)?;
+    send.finish().map_err(quic_error)?;
+    Ok(())
+}
+
+/// Serve each stream the client opens as one daemon connection
+#[cfg(feature = "quic")]
+async fn serve_connection(
+    incoming: quinn::Incoming,
+    psk: Option<&str>,
+    modules: Arc<daemon::Modules>,
+) -> Result<()> {
+    let connection = incoming.await.map_err(quic_error)?;
+    if let Some(psk) = psk {
+        // A client that never sends its proof mustn't hold the connection open
+        match tokio::time::timeout(PSK_TIMEOUT, accept_psk(&connection, psk)).await {
+            Ok(accepted) => accepted?,
+            Err(_) => {
+                connection.close(PSK_MISMATCH.into(), b"pre-shared key timed out");
+                return Err(SyncError::Restricted(
+                    "client sent no pre-shared key proof in time".to_string(),
+                ));
+            }
+        }
+    }
+
+    loop {
+        let (send, recv) = match connection.accept_bi().await {
+            Ok(streams) => streams,
+            Err(quinn::ConnectionError::ApplicationClosed(_)) => return Ok(()),
+            Err(e) => return Err(quic_error(e)),
+        };
+        let modules = Arc::clone(&modules);
+        tokio::spawn(async move {
+            match daemon::serve_connection(tokio::io::join(recv, send), &modules).await {
+                Ok(()) => {}
+                // The client closing the connection ends its idle streams
+                Err(SyncError::Io(e)) if e.kind() == std::io::ErrorKind::NotConnected => {}
+                Err(e) => tracing::warn!("QUIC stream: {}", e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_psk_proof() {
+        let exporter = [7; 32];
+        let client = psk_proof("correct horse", b"client", &exporter);
+        assert_eq!(client, psk_proof("correct horse", b"client", &exporter));
+        // Each side's proof differs, so one can't be echoed back as the other
+        assert_ne!(client, psk_proof("correct horse", b"server", &exporter));
+        // Bound to the key and to the TLS session
+        assert_ne!(client, psk_proof("battery staple", b"client", &exporter));
+        assert_ne!(client, psk_proof("correct horse", b"client", &[8; 32]));
+    }
+
+    #[test]
+    fn test_alpn() {
+        assert_ne!(alpn(true), alpn(false));
+        assert!(alpn(false).starts_with(b"sy/"));
+    }
+}
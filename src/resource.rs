@@ -1,4 +1,5 @@
 use crate::error::{Result, SyncError};
+use crate::sync::output::{self, WarningCategory};
 use std::path::Path;
 
 /// Check if there's enough disk space on the destination filesystem
@@ -20,10 +21,14 @@ pub fn check_disk_space(path: &Path, bytes_needed: u64) -> Result<()> {
     // Warn if less than 20% buffer
     let comfortable = bytes_needed + (bytes_needed / 5);
     if available < comfortable {
-        tracing::warn!(
-            "Low disk space: {} available, {} needed (plus buffer)",
-            format_bytes(available),
-            format_bytes(bytes_needed)
+        output::warn(
+            WarningCategory::Resource,
+            Some(path),
+            format!(
+                "Low disk space: {} available, {} needed (plus buffer)",
+                format_bytes(available),
+                format_bytes(bytes_needed)
+            ),
         );
     }
 
@@ -60,11 +65,13 @@ pub fn check_fd_limits(parallel_workers: usize) -> Result<()> {
     let total_needed = estimated_fds + reserved_fds;
 
     if total_needed > soft_limit {
-        tracing::warn!(
-            "May hit file descriptor limit: {} workers need ~{} FDs, but soft limit is {}",
-            parallel_workers,
-            total_needed,
-            soft_limit
+        output::warn(
+            WarningCategory::Resource,
+            None,
+            format!(
+                "May hit file descriptor limit: {} workers need ~{} FDs, but soft limit is {}",
+                parallel_workers, total_needed, soft_limit
+            ),
         );
 
         if total_needed <= hard_limit {
@@ -74,10 +81,14 @@ pub fn check_fd_limits(parallel_workers: usize) -> Result<()> {
                 hard_limit
             );
         } else {
-            tracing::warn!(
-                "Requested workers ({}) may exceed hard limit ({}). Consider reducing with -j flag.",
-                parallel_workers,
-                hard_limit / 10
+            output::warn(
+                WarningCategory::Resource,
+                None,
+                format!(
+                    "Requested workers ({}) may exceed hard limit ({}). Consider reducing with -j flag.",
+                    parallel_workers,
+                    hard_limit / 10
+                ),
             );
         }
     }
@@ -93,20 +104,21 @@ pub fn check_fd_limits(_parallel_workers: usize) -> Result<()> {
     Ok(())
 }
 
+/// Closest existing directory at or above `path`, whose filesystem a
+/// destination that doesn't exist yet will be created on
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|dir| !dir.as_os_str().is_empty() && dir.exists())
+        .unwrap_or(Path::new("."))
+}
+
 /// Get available space on filesystem containing the given path
 #[cfg(unix)]
-fn get_available_space(path: &Path) -> Result<u64> {
+pub fn get_available_space(path: &Path) -> Result<u64> {
     use std::ffi::CString;
     use std::os::unix::ffi::OsStrExt;
 
-    // Create parent directory if it doesn't exist (for statvfs)
-    let check_path = if path.exists() {
-        path
-    } else if let Some(parent) = path.parent() {
-        parent
-    } else {
-        Path::new("/")
-    };
+    let check_path = existing_ancestor(path);
 
     let path_cstr = CString::new(check_path.as_os_str().as_bytes())
         .map_err(|e| SyncError::Io(std::io::Error::other(format!("Invalid path: {}", e))))?;
@@ -128,18 +140,11 @@ fn get_available_space(path: &Path) -> Result<u64> {
 
 /// Windows implementation using GetDiskFreeSpaceEx
 #[cfg(windows)]
-fn get_available_space(path: &Path) -> Result<u64> {
+pub fn get_available_space(path: &Path) -> Result<u64> {
     use std::os::windows::ffi::OsStrExt;
     use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
 
-    // Check parent directory if path doesn't exist (same as Unix behavior)
-    let check_path = if path.exists() {
-        path
-    } else if let Some(parent) = path.parent() {
-        parent
-    } else {
-        path
-    };
+    let check_path = existing_ancestor(path);
 
     let wide_path: Vec<u16> = check_path
         .as_os_str()
@@ -241,6 +246,9 @@ mod tests {
         // Should check parent directory
         let result = get_available_space(&nonexistent);
         assert!(result.is_ok());
+
+        // Or the closest ancestor that exists
+        assert!(get_available_space(&nonexistent.join("a/b")).is_ok());
     }
 
     #[test]
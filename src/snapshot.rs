@@ -0,0 +1,551 @@
+use crate::error::{Result, SyncError};
+use clap::ValueEnum;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Which filesystem snapshot to sync from (`--snapshot-source`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SnapshotKind {
+    /// Whichever provider supports the filesystem holding the source
+    Auto,
+    /// Read-only btrfs subvolume snapshot
+    Btrfs,
+    /// ZFS dataset snapshot, read through `.zfs/snapshot`
+    Zfs,
+    /// LVM snapshot volume, mounted read-only
+    Lvm,
+    /// APFS local snapshot (macOS), mounted read-only
+    Apfs,
+}
+
+/// A filesystem that can snapshot the tree holding a path
+///
+/// Providers shell out to the filesystem's own tools, which usually need
+/// root. New filesystems plug in by implementing this and joining
+/// [`PROVIDERS`].
+trait Provider: Sync {
+    fn kind(&self) -> SnapshotKind;
+
+    fn name(&self) -> &'static str {
+        match self.kind() {
+            SnapshotKind::Auto => "auto",
+            SnapshotKind::Btrfs => "btrfs",
+            SnapshotKind::Zfs => "zfs",
+            SnapshotKind::Lvm => "lvm",
+            SnapshotKind::Apfs => "apfs",
+        }
+    }
+
+    /// Whether the filesystem mounted at `mount` is one this provider snapshots
+    fn supports(&self, mount: &MountInfo) -> bool;
+
+    /// Snapshot the filesystem holding `path`, calling the snapshot `name`
+    fn create(&self, mount: &MountInfo, path: &Path, name: &str) -> CommandResult<Created>;
+
+    /// Delete a snapshot made by `create`, unmounting it first if needed
+    fn remove(&self, created: &Created) -> CommandResult<()>;
+}
+
+static PROVIDERS: &[&dyn Provider] = &[&Btrfs, &Zfs, &Lvm, &Apfs];
+
+/// Error text from a snapshot tool
+type CommandResult<T> = std::result::Result<T, String>;
+
+/// Where a filesystem is mounted from and to
+#[derive(Debug, Clone)]
+struct MountInfo {
+    /// Device, dataset or volume the filesystem comes from
+    source: String,
+    target: PathBuf,
+    fstype: String,
+}
+
+/// A snapshot a provider made
+#[derive(Debug)]
+struct Created {
+    /// Directory the snapshot's copy of `origin` can be read from
+    root: PathBuf,
+    /// Root of the snapshotted tree in the live filesystem
+    origin: PathBuf,
+    /// Provider-specific name to delete the snapshot by
+    id: String,
+    /// Temporary mount point to unmount and remove
+    mount: Option<PathBuf>,
+}
+
+/// Read-only snapshot of the source, deleted when dropped
+///
+/// A sync from the snapshot sees every file as it was at one instant, so a
+/// tree that changes while it is copied still arrives crash-consistent.
+pub struct Snapshot {
+    provider: &'static dyn Provider,
+    created: Created,
+    source: PathBuf,
+}
+
+impl Snapshot {
+    /// Snapshot the filesystem holding the local `source` directory
+    pub fn create(kind: SnapshotKind, source: &Path) -> Result<Self> {
+        let failed = |message: String| SyncError::Snapshot {
+            path: source.to_path_buf(),
+            message,
+        };
+
+        let source = source.canonicalize().map_err(|e| failed(e.to_string()))?;
+        let mount = mount_info(&source)
+            .ok_or_else(|| failed("can't tell which filesystem holds it".to_string()))?;
+
+        let provider = match kind {
+            SnapshotKind::Auto => PROVIDERS.iter().find(|p| p.supports(&mount)),
+            kind => PROVIDERS.iter().find(|p| p.kind() == kind),
+        }
+        .copied()
+        .ok_or_else(|| {
+            failed(format!(
+                "no snapshot support for its {} filesystem (btrfs, zfs, lvm and apfs are supported)",
+                mount.fstype
+            ))
+        })?;
+        if !provider.supports(&mount) {
+            return Err(failed(format!(
+                "it is on {} ({}), not {}",
+                mount.target.display(),
+                mount.fstype,
+                provider.name()
+            )));
+        }
+
+        let name = format!("sy-snapshot-{}", std::process::id());
+        let created = provider.create(&mount, &source, &name).map_err(failed)?;
+        let snapshot_source = match source.strip_prefix(&created.origin) {
+            Ok(relative) => created.root.join(relative),
+            Err(_) => created.root.clone(),
+        };
+        tracing::info!(
+            "Syncing from {} snapshot {} of {}",
+            provider.name(),
+            created.id,
+            source.display()
+        );
+
+        Ok(Self {
+            provider,
+            created,
+            source: snapshot_source,
+        })
+    }
+
+    /// The source directory as the snapshot holds it
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn provider(&self) -> &'static str {
+        self.provider.name()
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        match self.provider.remove(&self.created) {
+            Ok(()) => tracing::debug!("Deleted snapshot {}", self.created.id),
+            Err(e) => tracing::warn!(
+                "Failed to delete {} snapshot {}: {}",
+                self.provider.name(),
+                self.created.id,
+                e
+            ),
+        }
+    }
+}
+
+struct Btrfs;
+
+impl Provider for Btrfs {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Btrfs
+    }
+
+    fn supports(&self, mount: &MountInfo) -> bool {
+        mount.fstype == "btrfs"
+    }
+
+    fn create(&self, mount: &MountInfo, path: &Path, name: &str) -> CommandResult<Created> {
+        let subvolume = btrfs_subvolume(path, &mount.target);
+        let root = subvolume.join(format!(".{}", name));
+        run(
+            "btrfs",
+            [
+                OsStr::new("subvolume"),
+                OsStr::new("snapshot"),
+                OsStr::new("-r"),
+                subvolume.as_os_str(),
+                root.as_os_str(),
+            ],
+        )?;
+        Ok(Created {
+            id: root.display().to_string(),
+            root,
+            origin: subvolume,
+            mount: None,
+        })
+    }
+
+    fn remove(&self, created: &Created) -> CommandResult<()> {
+        run(
+            "btrfs",
+            [
+                OsStr::new("subvolume"),
+                OsStr::new("delete"),
+                created.root.as_os_str(),
+            ],
+        )
+        .map(drop)
+    }
+}
+
+/// Root of the btrfs subvolume holding `path`: the nearest directory with
+/// the inode number every subvolume root has
+#[cfg(unix)]
+fn btrfs_subvolume(path: &Path, mount_target: &Path) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+    const SUBVOLUME_ROOT_INODE: u64 = 256;
+
+    path.ancestors()
+        .take_while(|dir| dir.starts_with(mount_target))
+        .find(|dir| std::fs::metadata(dir).is_ok_and(|m| m.ino() == SUBVOLUME_ROOT_INODE))
+        .unwrap_or(mount_target)
+        .to_path_buf()
+}
+
+#[cfg(not(unix))]
+fn btrfs_subvolume(_path: &Path, mount_target: &Path) -> PathBuf {
+    mount_target.to_path_buf()
+}
+
+struct Zfs;
+
+impl Provider for Zfs {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Zfs
+    }
+
+    fn supports(&self, mount: &MountInfo) -> bool {
+        mount.fstype == "zfs"
+    }
+
+    fn create(&self, mount: &MountInfo, _path: &Path, name: &str) -> CommandResult<Created> {
+        // The mount source is the dataset name
+        let id = format!("{}@{}", mount.source, name);
+        run("zfs", ["snapshot", id.as_str()])?;
+        Ok(Created {
+            root: mount.target.join(".zfs/snapshot").join(name),
+            origin: mount.target.clone(),
+            id,
+            mount: None,
+        })
+    }
+
+    fn remove(&self, created: &Created) -> CommandResult<()> {
+        run("zfs", ["destroy", created.id.as_str()]).map(drop)
+    }
+}
+
+struct Lvm;
+
+/// Copy-on-write space reserved for an LVM snapshot, as `lvcreate --extents`
+///
+/// The snapshot becomes invalid if more of the origin changes during the sync.
+const LVM_SNAPSHOT_EXTENTS: &str = "20%ORIGIN";
+
+impl Provider for Lvm {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Lvm
+    }
+
+    fn supports(&self, mount: &MountInfo) -> bool {
+        cfg!(target_os = "linux") && lvm_volume(&mount.source).is_some()
+    }
+
+    fn create(&self, mount: &MountInfo, _path: &Path, name: &str) -> CommandResult<Created> {
+        let (vg, lv) = lvm_volume(&mount.source)
+            .ok_or_else(|| format!("{} is not an LVM logical volume", mount.source))?;
+        run(
+            "lvcreate",
+            [
+                "--snapshot",
+                "--extents",
+                LVM_SNAPSHOT_EXTENTS,
+                "--name",
+                name,
+                &format!("{}/{}", vg, lv),
+            ],
+        )?;
+        let id = format!("{}/{}", vg, name);
+
+        let mount_point = std::env::temp_dir().join(name);
+        // XFS refuses a second mount of the same filesystem UUID
+        let options = if mount.fstype == "xfs" {
+            "ro,nouuid"
+        } else {
+            "ro"
+        };
+        let mounted = std::fs::create_dir_all(&mount_point)
+            .map_err(|e| e.to_string())
+            .and_then(|()| {
+                run(
+                    "mount",
+                    [
+                        OsStr::new("-o"),
+                        OsStr::new(options),
+                        OsStr::new(&format!("/dev/{}", id)),
+                        mount_point.as_os_str(),
+                    ],
+                )
+            });
+        if let Err(e) = mounted {
+            let _ = std::fs::remove_dir(&mount_point);
+            let _ = run("lvremove", ["--yes", id.as_str()]);
+            return Err(e);
+        }
+
+        Ok(Created {
+            root: mount_point.clone(),
+            origin: mount.target.clone(),
+            id,
+            mount: Some(mount_point),
+        })
+    }
+
+    fn remove(&self, created: &Created) -> CommandResult<()> {
+        unmount(created)?;
+        run("lvremove", ["--yes", created.id.as_str()]).map(drop)
+    }
+}
+
+/// Volume group and logical volume of an LVM device, asked of `lvs`
+fn lvm_volume(device: &str) -> Option<(String, String)> {
+    if !device.starts_with("/dev/") {
+        return None;
+    }
+    let output = run(
+        "lvs",
+        ["--noheadings", "--options", "vg_name,lv_name", device],
+    )
+    .ok()?;
+    let mut fields = output.split_whitespace();
+    Some((fields.next()?.to_string(), fields.next()?.to_string()))
+}
+
+struct Apfs;
+
+impl Provider for Apfs {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Apfs
+    }
+
+    fn supports(&self, mount: &MountInfo) -> bool {
+        mount.fstype == "apfs"
+    }
+
+    fn create(&self, mount: &MountInfo, _path: &Path, name: &str) -> CommandResult<Created> {
+        // tmutil names the snapshot itself, after the time it was taken
+        let output = run("tmutil", ["localsnapshot"])?;
+        let date = parse_tmutil_date(&output)
+            .ok_or_else(|| format!("unexpected tmutil output: {}", output.trim()))?;
+        let snapshot = format!("com.apple.TimeMachine.{}.local", date);
+
+        let mount_point = std::env::temp_dir().join(name);
+        let mounted = std::fs::create_dir_all(&mount_point)
+            .map_err(|e| e.to_string())
+            .and_then(|()| {
+                run(
+                    "mount_apfs",
+                    [
+                        OsStr::new("-o"),
+                        OsStr::new("ro"),
+                        OsStr::new("-s"),
+                        OsStr::new(&snapshot),
+                        OsStr::new(&mount.source),
+                        mount_point.as_os_str(),
+                    ],
+                )
+            });
+        if let Err(e) = mounted {
+            let _ = std::fs::remove_dir(&mount_point);
+            let _ = run("tmutil", ["deletelocalsnapshots", date.as_str()]);
+            return Err(e);
+        }
+
+        Ok(Created {
+            root: mount_point.clone(),
+            origin: mount.target.clone(),
+            id: date,
+            mount: Some(mount_point),
+        })
+    }
+
+    fn remove(&self, created: &Created) -> CommandResult<()> {
+        unmount(created)?;
+        run("tmutil", ["deletelocalsnapshots", created.id.as_str()]).map(drop)
+    }
+}
+
+/// Date `tmutil localsnapshot` names its snapshot after
+///
+/// It prints e.g. "Created local snapshot with date: 2024-05-01-120000".
+fn parse_tmutil_date(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.trim().rsplit_once("date: "))
+        .map(|(_, date)| date.trim().to_string())
+        .filter(|date| !date.is_empty())
+}
+
+/// Unmount a provider's temporary mount point and remove it
+fn unmount(created: &Created) -> CommandResult<()> {
+    if let Some(mount_point) = &created.mount {
+        run("umount", [mount_point.as_os_str()])?;
+        let _ = std::fs::remove_dir(mount_point);
+    }
+    Ok(())
+}
+
+/// Run a snapshot tool, returning its stdout or why it failed
+fn run<I, S>(program: &str, args: I) -> CommandResult<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The filesystem mounted where `path` lives, asked of `findmnt`
+#[cfg(target_os = "linux")]
+fn mount_info(path: &Path) -> Option<MountInfo> {
+    let output = run(
+        "findmnt",
+        [
+            OsStr::new("--noheadings"),
+            OsStr::new("--raw"),
+            OsStr::new("--output"),
+            OsStr::new("SOURCE,TARGET,FSTYPE"),
+            OsStr::new("--target"),
+            path.as_os_str(),
+        ],
+    )
+    .ok()?;
+    let mut fields = output.lines().next()?.split(' ');
+    // btrfs sources carry the mounted subvolume: /dev/sda2[/@home]
+    let source = fields.next()?;
+    let source = source.split_once('[').map_or(source, |(device, _)| device);
+    Some(MountInfo {
+        source: unescape_findmnt(source),
+        target: PathBuf::from(unescape_findmnt(fields.next()?)),
+        fstype: fields.next()?.to_string(),
+    })
+}
+
+/// Undo findmnt's `--raw` escaping of spaces and other bytes (`\x20`)
+#[cfg(target_os = "linux")]
+fn unescape_findmnt(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(pos) = rest.find("\\x") {
+        out.push_str(&rest[..pos]);
+        match rest
+            .get(pos + 2..pos + 4)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[pos + 4..];
+            }
+            None => {
+                out.push_str("\\x");
+                rest = &rest[pos + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The filesystem mounted where `path` lives, from `statfs`
+#[cfg(target_os = "macos")]
+fn mount_info(path: &Path) -> Option<MountInfo> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let field = |chars: &[libc::c_char]| {
+        unsafe { CStr::from_ptr(chars.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    Some(MountInfo {
+        source: field(&stat.f_mntfromname),
+        target: PathBuf::from(field(&stat.f_mntonname)),
+        fstype: field(&stat.f_fstypename),
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn mount_info(_path: &Path) -> Option<MountInfo> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tmutil_date() {
+        assert_eq!(
+            parse_tmutil_date("Created local snapshot with date: 2024-05-01-120000\n"),
+            Some("2024-05-01-120000".to_string())
+        );
+        assert_eq!(parse_tmutil_date("NOTE: nothing\n"), None);
+    }
+
+    #[test]
+    fn test_explicit_provider_must_match_filesystem() {
+        let mount = MountInfo {
+            source: "/dev/sda1".to_string(),
+            target: PathBuf::from("/"),
+            fstype: "ext4".to_string(),
+        };
+        for provider in [&Btrfs as &dyn Provider, &Zfs, &Apfs] {
+            assert!(!provider.supports(&mount), "{}", provider.name());
+        }
+        assert!(Zfs.supports(&MountInfo {
+            source: "tank/home".to_string(),
+            target: PathBuf::from("/home"),
+            fstype: "zfs".to_string(),
+        }));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_unescape_findmnt() {
+        assert_eq!(unescape_findmnt("/mnt/my\\x20disk"), "/mnt/my disk");
+        assert_eq!(unescape_findmnt("/plain"), "/plain");
+        assert_eq!(unescape_findmnt("/odd\\xZZ"), "/odd\\xZZ");
+    }
+}
@@ -0,0 +1,147 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+
+/// Quote one argument for a POSIX shell
+///
+/// Plain words pass through unchanged; anything else is wrapped in single
+/// quotes, with embedded single quotes written as `'\''`.
+pub fn quote(arg: &str) -> Cow<'_, str> {
+    let plain = !arg.is_empty()
+        && arg
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_./=:,+@%".contains(&b));
+    if plain {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
+}
+
+/// Quote a path, keeping a leading `~/` unquoted so the shell still expands it
+fn quote_path(path: &str) -> Cow<'_, str> {
+    match path.strip_prefix("~/") {
+        Some(rest) if !rest.is_empty() => Cow::Owned(format!("~/{}", quote(rest))),
+        _ if path == "~" || path == "~/" => Cow::Borrowed("~/"),
+        _ => quote(path),
+    }
+}
+
+/// Command line for the remote shell, built one argument at a time
+///
+/// SSH runs commands through the remote user's shell, so every argument is
+/// quoted; file names with spaces, quotes or `$` reach the program verbatim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteCommand {
+    line: String,
+}
+
+impl RemoteCommand {
+    /// Start a command line; `program` may be a `~/` path
+    pub fn new(program: &str) -> Self {
+        Self {
+            line: quote_path(program).into_owned(),
+        }
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<str>) -> Self {
+        self.line.push(' ');
+        self.line.push_str(&quote(arg.as_ref()));
+        self
+    }
+
+    /// Append a path, keeping a leading `~/` unquoted so the shell still expands it
+    ///
+    /// Paths are converted lossily, since SSH commands are strings.
+    pub fn path(mut self, path: &Path) -> Self {
+        self.line.push(' ');
+        self.line.push_str(&quote_path(&path.to_string_lossy()));
+        self
+    }
+
+    /// Append `--name value`
+    pub fn option(self, name: &str, value: impl fmt::Display) -> Self {
+        self.arg(name).arg(value.to_string())
+    }
+
+    /// Append `--`, so following paths starting with `-` aren't taken for options
+    pub fn end_of_options(self) -> Self {
+        self.arg("--")
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.line
+    }
+}
+
+impl fmt::Display for RemoteCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.line)
+    }
+}
+
+impl From<RemoteCommand> for String {
+    fn from(command: RemoteCommand) -> Self {
+        command.line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_plain_words() {
+        assert_eq!(quote("sy-remote"), "sy-remote");
+        assert_eq!(quote("/srv/data/file_1.txt"), "/srv/data/file_1.txt");
+        assert_eq!(quote("--max-depth"), "--max-depth");
+    }
+
+    #[test]
+    fn test_quote_special_characters() {
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("my file.txt"), "'my file.txt'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("$(rm -rf /)"), "'$(rm -rf /)'");
+        assert_eq!(quote("a;b|c&d`e`"), "'a;b|c&d`e`'");
+        assert_eq!(quote("line\nbreak"), "'line\nbreak'");
+    }
+
+    #[test]
+    fn test_remote_command() {
+        let command = RemoteCommand::new("sy-remote")
+            .arg("receive-file")
+            .path(Path::new("/dest/it's here.txt"))
+            .option("--mtime", 1_700_000_000);
+        assert_eq!(
+            command.as_str(),
+            r"sy-remote receive-file '/dest/it'\''s here.txt' --mtime 1700000000"
+        );
+
+        let command = RemoteCommand::new("rm")
+            .arg("-f")
+            .end_of_options()
+            .path(Path::new("-rf"));
+        assert_eq!(command.as_str(), "rm -f -- -rf");
+    }
+
+    #[test]
+    fn test_remote_command_home_paths() {
+        let command = RemoteCommand::new("mkdir")
+            .arg("-p")
+            .path(Path::new("~/backup dir"));
+        assert_eq!(command.as_str(), "mkdir -p ~/'backup dir'");
+
+        let command = RemoteCommand::new("test").arg("-e").path(Path::new("~"));
+        assert_eq!(command.as_str(), "test -e ~/");
+
+        let command = RemoteCommand::new("~/bin/sy remote").arg("scan");
+        assert_eq!(command.as_str(), "~/'bin/sy remote' scan");
+
+        // Only a leading `~/` is special
+        let command = RemoteCommand::new("test")
+            .arg("-e")
+            .path(Path::new("/a/~b"));
+        assert_eq!(command.as_str(), "test -e '/a/~b'");
+    }
+}
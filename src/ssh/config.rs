@@ -1,12 +1,26 @@
+use super::system::SshBackend;
 use crate::error::{Result, SyncError};
 use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
 
+/// How the client reaches SSH hosts (`--ssh-option`, `--ssh-backend`,
+/// `--ssh-command`)
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// `Key=Value` options, applied to every host after ssh_config
+    pub options: Vec<(String, String)>,
+    pub backend: SshBackend,
+    /// Command run instead of `ssh` by the system backend, split into words
+    pub program: Option<Vec<String>>,
+}
+
 /// SSH configuration for a specific host
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)] // Will be used in upcoming SSH transport implementation
 pub struct SshConfig {
+    /// Host as given on the command line, which ssh_config `Host` blocks match
+    pub alias: String,
     pub hostname: String,
     pub port: u16,
     pub user: String,
@@ -16,11 +30,20 @@ pub struct SshConfig {
     pub control_path: Option<PathBuf>,
     pub control_persist: Option<Duration>,
     pub compression: bool,
+    /// Options sy doesn't interpret itself (e.g. `Ciphers`), from --ssh-option
+    pub options: Vec<(String, String)>,
+    /// Settings from the command line (`user@host`, --ssh-option), which the
+    /// system ssh is given explicitly; it reads the rest of ssh_config itself
+    pub command_line: Vec<(String, String)>,
+    pub backend: SshBackend,
+    /// Command run instead of `ssh` by the system backend
+    pub program: Option<Vec<String>>,
 }
 
 impl Default for SshConfig {
     fn default() -> Self {
         Self {
+            alias: String::new(),
             hostname: String::new(),
             port: 22,
             user: whoami::username(),
@@ -30,6 +53,10 @@ impl Default for SshConfig {
             control_path: None,
             control_persist: None,
             compression: false,
+            options: Vec::new(),
+            command_line: Vec::new(),
+            backend: SshBackend::default(),
+            program: None,
         }
     }
 }
@@ -38,6 +65,7 @@ impl SshConfig {
     /// Create a new SSH config with defaults
     pub fn new(host: &str) -> Self {
         Self {
+            alias: host.to_string(),
             hostname: host.to_string(),
             port: 22,
             user: whoami::username(),
@@ -47,9 +75,50 @@ impl SshConfig {
             control_path: None,
             control_persist: None,
             compression: false,
+            options: Vec::new(),
+            command_line: Vec::new(),
+            backend: SshBackend::default(),
+            program: None,
+        }
+    }
+
+    /// Apply `Key=Value` options given with --ssh-option
+    ///
+    /// Options for settings sy tracks (port, user, identity files, ...)
+    /// override ssh_config; the rest are kept in `options`.
+    pub fn apply_options(&mut self, options: &[(String, String)]) {
+        for (key, value) in options {
+            self.command_line.push((key.clone(), value.clone()));
+            if !self.set(key, value) {
+                self.options.push((key.clone(), value.clone()));
+            }
         }
     }
 
+    /// Apply one ssh_config directive, returning false for keywords sy
+    /// doesn't interpret itself
+    fn set(&mut self, keyword: &str, value: &str) -> bool {
+        match keyword.to_lowercase().as_str() {
+            "hostname" => self.hostname = value.to_string(),
+            "port" => {
+                if let Ok(port) = value.parse::<u16>() {
+                    self.port = port;
+                }
+            }
+            "user" => self.user = value.to_string(),
+            "identityfile" => self.identity_file.push(Self::expand_path(value)),
+            "proxyjump" => self.proxy_jump = Some(value.to_string()),
+            "controlmaster" => {
+                self.control_master = matches!(value.to_lowercase().as_str(), "yes" | "auto")
+            }
+            "controlpath" => self.control_path = Some(Self::expand_path(value)),
+            "controlpersist" => self.control_persist = parse_duration(value),
+            "compression" => self.compression = value.to_lowercase() == "yes",
+            _ => return false,
+        }
+        true
+    }
+
     /// Expand ~ and environment variables in paths
     fn expand_path(path: &str) -> PathBuf {
         if let Some(home) = dirs::home_dir() {
@@ -116,62 +185,58 @@ pub fn parse_ssh_config_from_str(host: &str, content: &str) -> Result<SshConfig>
                 // Skip directives not in matching host block
                 continue;
             }
-            "hostname" => {
-                if let Some(value) = parts.get(1) {
-                    config.hostname = value.to_string();
-                }
-            }
-            "port" => {
-                if let Some(value) = parts.get(1) {
-                    if let Ok(port) = value.parse::<u16>() {
-                        config.port = port;
-                    }
-                }
-            }
-            "user" => {
-                if let Some(value) = parts.get(1) {
-                    config.user = value.to_string();
-                }
-            }
-            "identityfile" => {
-                if let Some(value) = parts.get(1) {
-                    config.identity_file.push(SshConfig::expand_path(value));
-                }
-            }
-            "proxyjump" => {
-                if let Some(value) = parts.get(1) {
-                    config.proxy_jump = Some(value.to_string());
-                }
-            }
-            "controlmaster" => {
-                if let Some(value) = parts.get(1) {
-                    config.control_master = matches!(value.to_lowercase().as_str(), "yes" | "auto");
-                }
-            }
-            "controlpath" => {
-                if let Some(value) = parts.get(1) {
-                    config.control_path = Some(SshConfig::expand_path(value));
-                }
-            }
-            "controlpersist" => {
-                if let Some(value) = parts.get(1) {
-                    config.control_persist = parse_duration(value);
-                }
-            }
-            "compression" => {
+            _ => {
+                // Unknown directives are ignored
                 if let Some(value) = parts.get(1) {
-                    config.compression = value.to_lowercase() == "yes";
+                    config.set(&keyword, value);
                 }
             }
-            _ => {
-                // Ignore unknown directives
-            }
         }
     }
 
     Ok(config)
 }
 
+/// Configuration for `host` as written on the command line, with `user` if
+/// it was given as `user@host`
+///
+/// ssh_config is only read without a user; `client` applies either way.
+pub fn host_config(host: &str, user: Option<&str>, client: &ClientOptions) -> Result<SshConfig> {
+    let mut config = match user {
+        Some(_) => SshConfig::new(host),
+        None => parse_ssh_config(host)?,
+    };
+    config.apply_options(&client.options);
+    config.backend = client.backend;
+    config.program = client.program.clone();
+    if let Some(user) = user {
+        config.user = user.to_string();
+        config
+            .command_line
+            .push(("User".to_string(), user.to_string()));
+    }
+    Ok(config)
+}
+
+/// Parse an --ssh-option: `Key=Value` or `Key Value`, as with `ssh -o`
+pub fn parse_ssh_option(s: &str) -> std::result::Result<(String, String), String> {
+    let s = s.trim();
+    let invalid = || format!("Invalid SSH option '{}': expected Key=Value", s);
+    let split = s
+        .find(|c: char| c == '=' || c.is_whitespace())
+        .ok_or_else(invalid)?;
+    let key = &s[..split];
+    let rest = s[split..].trim_start();
+    let value = rest.strip_prefix('=').unwrap_or(rest).trim_start();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) || value.is_empty() {
+        return Err(invalid());
+    }
+    if key.eq_ignore_ascii_case("port") && value.parse::<u16>().is_err() {
+        return Err(format!("Invalid SSH port '{}'", value));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
 /// Check if a hostname matches an SSH config pattern
 ///
 /// Supports wildcards (* and ?) and negation (!)
@@ -327,6 +392,55 @@ Host example
         assert!(config.compression);
     }
 
+    #[test]
+    fn test_parse_ssh_option() {
+        let option = |key: &str, value: &str| Ok((key.to_string(), value.to_string()));
+        assert_eq!(parse_ssh_option("Port=2222"), option("Port", "2222"));
+        assert_eq!(parse_ssh_option("Port 2222"), option("Port", "2222"));
+        assert_eq!(
+            parse_ssh_option("Ciphers = aes128-gcm@openssh.com,aes256-ctr"),
+            option("Ciphers", "aes128-gcm@openssh.com,aes256-ctr")
+        );
+        assert_eq!(
+            parse_ssh_option("ProxyCommand=ssh -W %h:%p bastion"),
+            option("ProxyCommand", "ssh -W %h:%p bastion")
+        );
+        assert!(parse_ssh_option("Port").is_err());
+        assert!(parse_ssh_option("Port=").is_err());
+        assert!(parse_ssh_option("=2222").is_err());
+        assert!(parse_ssh_option("Port=ssh").is_err());
+    }
+
+    #[test]
+    fn test_apply_options() {
+        let content = r#"
+Host example
+    HostName example.com
+    Port 2222
+    IdentityFile /keys/id_rsa
+"#;
+
+        let mut config = parse_ssh_config_from_str("example", content).unwrap();
+        config.apply_options(&[
+            ("port".to_string(), "3333".to_string()),
+            ("IdentityFile".to_string(), "/keys/deploy".to_string()),
+            ("ProxyJump".to_string(), "bastion".to_string()),
+            ("Ciphers".to_string(), "aes256-ctr".to_string()),
+        ]);
+        assert_eq!(config.hostname, "example.com");
+        assert_eq!(config.port, 3333);
+        assert_eq!(
+            config.identity_file,
+            [PathBuf::from("/keys/id_rsa"), PathBuf::from("/keys/deploy")]
+        );
+        assert_eq!(config.proxy_jump, Some("bastion".to_string()));
+        assert_eq!(
+            config.options,
+            [("Ciphers".to_string(), "aes256-ctr".to_string())]
+        );
+        assert_eq!(config.command_line.len(), 4);
+    }
+
     #[test]
     fn test_host_matching() {
         assert!(host_matches("example.com", "example.com"));
@@ -1,6 +1,6 @@
 use super::config::SshConfig;
 use crate::error::{Result, SyncError};
-use ssh2::Session;
+use ssh2::{MethodType, Session};
 use std::io::ErrorKind;
 use std::net::TcpStream;
 use std::time::Duration;
@@ -22,6 +22,7 @@ pub async fn connect(config: &SshConfig) -> Result<Session> {
     // Clone config data needed for authentication
     let username = config.user.clone();
     let identity_files = config.identity_file.clone();
+    let options = config.options.clone();
 
     // Wrap all sync operations (session creation, handshake, auth) in spawn_blocking
     let session = tokio::task::spawn_blocking(move || {
@@ -40,6 +41,9 @@ pub async fn connect(config: &SshConfig) -> Result<Session> {
         // Set TCP stream
         session.set_tcp_stream(tcp);
 
+        // Algorithm preferences must be in place before the handshake
+        set_method_prefs(&session, &options)?;
+
         // Perform SSH handshake
         session.handshake().map_err(|e| {
             SyncError::Io(std::io::Error::other(format!(
@@ -115,6 +119,55 @@ pub async fn connect(config: &SshConfig) -> Result<Session> {
     Ok(session)
 }
 
+/// libssh2 methods an ssh_config algorithm option sets, none for other options
+fn option_methods(key: &str) -> &'static [MethodType] {
+    match key.to_lowercase().as_str() {
+        "ciphers" => &[MethodType::CryptCs, MethodType::CryptSc],
+        "macs" => &[MethodType::MacCs, MethodType::MacSc],
+        "kexalgorithms" => &[MethodType::Kex],
+        "hostkeyalgorithms" => &[MethodType::HostKey],
+        _ => &[],
+    }
+}
+
+/// Apply the algorithm options (`Ciphers`, `MACs`, `KexAlgorithms`,
+/// `HostKeyAlgorithms`) among `options`
+fn set_method_prefs(session: &Session, options: &[(String, String)]) -> Result<()> {
+    for (key, value) in options {
+        for method in option_methods(key) {
+            session.method_pref(*method, value).map_err(|e| {
+                SyncError::Io(std::io::Error::other(format!(
+                    "Unsupported SSH option {}={}: {}",
+                    key, value, e
+                )))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Warn about settings in `config` the built-in client can't honour
+///
+/// Called once per host rather than per pooled connection.
+pub fn warn_unsupported(config: &SshConfig) {
+    if let Some(jump) = &config.proxy_jump {
+        tracing::warn!(
+            "Ignoring ProxyJump {} for {}: the built-in SSH client connects directly (use --ssh-backend system to jump hosts)",
+            jump,
+            config.hostname
+        );
+    }
+    for (key, value) in &config.options {
+        if option_methods(key).is_empty() {
+            tracing::warn!(
+                "Ignoring SSH option {}={}: the built-in SSH client doesn't support it (use --ssh-backend system)",
+                key,
+                value
+            );
+        }
+    }
+}
+
 /// Establish TCP connection to SSH server
 async fn connect_tcp(hostname: &str, port: u16) -> Result<TcpStream> {
     let addr = format!("{}:{}", hostname, port);
@@ -144,6 +197,7 @@ mod tests {
     #[test]
     fn test_ssh_config_basic() {
         let config = SshConfig {
+            alias: "localhost".to_string(),
             hostname: "localhost".to_string(),
             port: 22,
             user: "testuser".to_string(),
@@ -153,6 +207,10 @@ mod tests {
             control_path: None,
             control_persist: None,
             compression: false,
+            options: Vec::new(),
+            command_line: Vec::new(),
+            backend: Default::default(),
+            program: None,
         };
 
         assert_eq!(config.hostname, "localhost");
@@ -160,6 +218,13 @@ mod tests {
         assert_eq!(config.user, "testuser");
     }
 
+    #[test]
+    fn test_option_methods() {
+        assert_eq!(option_methods("Ciphers").len(), 2);
+        assert_eq!(option_methods("kexalgorithms").len(), 1);
+        assert!(option_methods("ServerAliveInterval").is_empty());
+    }
+
     // Note: Actual connection tests require a running SSH server
     // These would be integration tests, not unit tests
 }
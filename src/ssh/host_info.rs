@@ -0,0 +1,208 @@
+use crate::error::Result;
+use crate::wire::Capabilities;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// How long recorded facts about a host are trusted before probing again
+///
+/// Long enough for repeated syncs to skip the probes, short enough that an
+/// upgraded (or removed) sy-remote is noticed the next day.
+pub const HOST_INFO_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// Upload chunk size used until a host's bandwidth is known
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Smallest and largest tuned upload chunk sizes
+pub const MIN_CHUNK_SIZE: usize = 64 * 1024;
+pub const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Round trip assumed for hosts whose round trip wasn't measured yet
+const DEFAULT_RTT_MS: u64 = 20;
+
+/// Transfers smaller than this say little about a link's bandwidth
+const MIN_MEASURED_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Where host info is kept (`<state base>/hosts`), and whether cached info
+/// is ignored (`--refresh-host-info`); None keeps nothing
+static CACHE: Mutex<Option<(PathBuf, bool)>> = Mutex::new(None);
+
+/// Cache host info in `dir` from now on; with `refresh`, what was cached
+/// before is ignored and replaced by fresh probes
+///
+/// Set once at startup for syncs; without it SSH transports probe every time.
+pub fn set_cache_dir(dir: PathBuf, refresh: bool) {
+    *CACHE.lock().unwrap() = Some((dir, refresh));
+}
+
+/// Name host info is kept under: `user@host` as written on the command
+/// line, or just `host`
+pub fn host_key(host: &str, user: Option<&str>) -> String {
+    match user {
+        Some(user) => format!("{}@{}", user, host),
+        None => host.to_string(),
+    }
+}
+
+/// What earlier runs learned about an SSH host
+///
+/// Lets later runs skip locating sy-remote and asking its capabilities (a
+/// round trip each), and pick an upload chunk size from the bandwidth the
+/// last sizable sync measured.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostInfo {
+    pub host: String,
+    /// Where sy-remote was found, None if it wasn't looked up yet
+    pub remote_binary: Option<String>,
+    /// No sy-remote on the host, only SFTP
+    #[serde(default)]
+    pub sftp_only: bool,
+    /// sy-remote's answer to `capabilities`
+    pub capabilities: Option<Capabilities>,
+    /// How far the remote clock ran ahead when capabilities were asked
+    pub clock_skew_ms: Option<i64>,
+    /// Bytes per second the last sync moved to or from the host
+    pub bandwidth: Option<u64>,
+    /// Round trip of an SFTP request, in milliseconds
+    pub rtt_ms: Option<u64>,
+    /// Upload chunk size chosen for that bandwidth
+    pub chunk_size: Option<usize>,
+    /// When the info was last probed, in seconds since the Unix epoch
+    pub probed_at: i64,
+}
+
+impl HostInfo {
+    /// Cached info for `key`, unless there is no cache, it's being
+    /// refreshed, or the info is older than `HOST_INFO_TTL_SECS`
+    pub fn load(key: &str) -> Option<Self> {
+        let (dir, refresh) = CACHE.lock().unwrap().clone()?;
+        if refresh {
+            return None;
+        }
+        let info = Self::read(&dir, key)?;
+        let age = chrono::Utc::now().timestamp() - info.probed_at;
+        (0..HOST_INFO_TTL_SECS).contains(&age).then_some(info)
+    }
+
+    /// Change the cached info for `key` with `update`, if there is a cache
+    ///
+    /// With `probed`, the info counts as freshly probed. Failures are only
+    /// logged: the cache saves round trips, it's never needed.
+    pub fn update(key: &str, probed: bool, update: impl FnOnce(&mut HostInfo)) {
+        let Some((dir, _)) = CACHE.lock().unwrap().clone() else {
+            return;
+        };
+        let mut info = Self::read(&dir, key).unwrap_or_else(|| HostInfo {
+            host: key.to_string(),
+            ..Default::default()
+        });
+        update(&mut info);
+        if probed {
+            info.probed_at = chrono::Utc::now().timestamp();
+        }
+        if let Err(e) = info.write(&dir, key) {
+            tracing::debug!("Failed to save host info for {}: {}", key, e);
+        }
+    }
+
+    /// Record the bandwidth of a sync that moved `bytes` in `secs`, and the
+    /// upload chunk size it calls for
+    ///
+    /// Small syncs are ignored, since setup time dominates them.
+    pub fn record_bandwidth(key: &str, bytes: u64, secs: f64) {
+        if bytes < MIN_MEASURED_BYTES || secs <= 0.0 {
+            return;
+        }
+        let bandwidth = (bytes as f64 / secs) as u64;
+        tracing::debug!("Measured {} bytes/s to {}", bandwidth, key);
+        Self::update(key, false, |info| {
+            info.bandwidth = Some(bandwidth);
+            info.chunk_size = Some(chunk_size_for(
+                bandwidth,
+                info.rtt_ms.unwrap_or(DEFAULT_RTT_MS),
+            ));
+        });
+    }
+
+    fn path(dir: &Path, key: &str) -> PathBuf {
+        let hash = blake3::hash(key.as_bytes()).to_hex();
+        dir.join(format!("{}.json", &hash[..16]))
+    }
+
+    fn read(dir: &Path, key: &str) -> Option<Self> {
+        let data = std::fs::read(Self::path(dir, key)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    fn write(&self, dir: &Path, key: &str) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let path = Self::path(dir, key);
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(
+            &temp,
+            serde_json::to_vec_pretty(self).map_err(std::io::Error::from)?,
+        )?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+}
+
+/// Upload chunk size for a link of `bandwidth` bytes per second and a round
+/// trip of `rtt_ms`
+///
+/// Uploads keep one chunk of writes in flight, so a chunk covering the link's
+/// bandwidth-delay product (rounded up to a power of two) keeps the link busy
+/// for a full round trip: links that are both fast and far get large chunks,
+/// while slow or nearby ones keep small chunks for responsive progress and
+/// cancellation.
+pub fn chunk_size_for(bandwidth: u64, rtt_ms: u64) -> usize {
+    let bdp = bandwidth.saturating_mul(rtt_ms) / 1000;
+    usize::try_from(bdp)
+        .unwrap_or(MAX_CHUNK_SIZE)
+        .clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+        .next_power_of_two()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_host_key() {
+        assert_eq!(host_key("nas", Some("backup")), "backup@nas");
+        assert_eq!(host_key("nas", None), "nas");
+    }
+
+    #[test]
+    fn test_read_write() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("hosts");
+        assert_eq!(HostInfo::read(&dir, "nas"), None);
+
+        let info = HostInfo {
+            host: "nas".to_string(),
+            remote_binary: Some("/usr/local/bin/sy-remote".to_string()),
+            capabilities: Some(Capabilities::current()),
+            clock_skew_ms: Some(-120),
+            probed_at: 1_700_000_000,
+            ..Default::default()
+        };
+        info.write(&dir, "nas").unwrap();
+        assert_eq!(HostInfo::read(&dir, "nas"), Some(info));
+        // Keyed per host and user
+        assert_eq!(HostInfo::read(&dir, "backup@nas"), None);
+    }
+
+    #[test]
+    fn test_chunk_size_for() {
+        // Slow link, or a fast one next door
+        assert_eq!(chunk_size_for(200_000, 50), MIN_CHUNK_SIZE);
+        assert_eq!(chunk_size_for(1_000_000_000, 0), MIN_CHUNK_SIZE);
+        // 10 MB/s at 20 ms: 200 KB in flight
+        assert_eq!(chunk_size_for(10_000_000, 20), DEFAULT_CHUNK_SIZE);
+        // 100 MB/s across an ocean
+        assert_eq!(chunk_size_for(100_000_000, 150), MAX_CHUNK_SIZE);
+        assert_eq!(chunk_size_for(u64::MAX, u64::MAX), MAX_CHUNK_SIZE);
+    }
+}
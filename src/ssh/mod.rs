@@ -1,5 +1,10 @@
+pub mod command;
 pub mod config;
 pub mod connect;
+pub mod host_info;
+#[allow(dead_code)] // Used by sy-remote
+pub mod restrict;
+pub mod system;
 
 // Re-export for convenience when SSH transport is implemented
 #[allow(unused_imports)]
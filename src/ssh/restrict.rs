@@ -0,0 +1,568 @@
+//! Restricted sy-remote for `command=` in authorized_keys (rrsync-style)
+//!
+//! With a forced command, sshd runs `sy-remote restrict --root DIR` instead of
+//! whatever the client asked for, and passes the request in
+//! `SSH_ORIGINAL_COMMAND`. The request is parsed here without a shell: only
+//! sy-remote itself and the few shell commands sy's SSH transport sends
+//! (`test -e`, `echo`, `mkdir -p`, `rm`, `ln`, joined by `&&` and `||`) are
+//! accepted, and every path they touch must resolve inside the root.
+
+use crate::error::{Result, SyncError};
+use crate::fs_util::resolve_path;
+use std::path::{Path, PathBuf};
+
+fn refused(reason: impl Into<String>) -> SyncError {
+    SyncError::Restricted(reason.into())
+}
+
+/// How a command is joined to the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `&&`: run if the previous command succeeded
+    And,
+    /// `||`: run if the previous command failed
+    Or,
+}
+
+impl Operator {
+    /// Whether the next command runs, given the status so far
+    pub fn runs_after(self, succeeded: bool) -> bool {
+        match self {
+            Operator::And => succeeded,
+            Operator::Or => !succeeded,
+        }
+    }
+}
+
+/// Shell command sent by sy's SSH transport besides sy-remote itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Builtin {
+    /// `test -e PATH`
+    TestExists(PathBuf),
+    /// `echo WORDS...`
+    Echo(String),
+    /// `mkdir -p PATH`
+    Mkdir(PathBuf),
+    /// `rm -f PATH` or `rm -rf PATH`
+    Remove { path: PathBuf, recursive: bool },
+    /// `ln TARGET LINK` or `ln -s TARGET LINK`
+    Link {
+        target: PathBuf,
+        link: PathBuf,
+        symbolic: bool,
+    },
+}
+
+/// One command of a restricted request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// sy-remote with its arguments (program name first, for clap)
+    SyRemote(Vec<String>),
+    Builtin(Builtin),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Token {
+    Word(String),
+    Operator(Operator),
+}
+
+/// Split a command line into words and operators, like a POSIX shell would
+///
+/// Single quotes, double quotes, backslashes and a leading `~` (expanded to
+/// `home`) are understood. Anything that would need a real shell, such as
+/// `;`, redirections, pipes, `$` or globs, is refused.
+pub(crate) fn tokenize(line: &str, home: &Path) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+
+        if first == '&' || first == '|' {
+            chars.next();
+            if chars.next_if_eq(&first).is_none() {
+                return Err(refused(format!("unsupported shell syntax: {}", first)));
+            }
+            tokens.push(Token::Operator(if first == '&' {
+                Operator::And
+            } else {
+                Operator::Or
+            }));
+            continue;
+        }
+
+        let mut word = String::new();
+        if first == '~' {
+            chars.next();
+            match chars.peek() {
+                None => word.push_str(&home.to_string_lossy()),
+                Some(&c) if c == '/' || c.is_ascii_whitespace() => {
+                    word.push_str(&home.to_string_lossy())
+                }
+                Some(_) => word.push('~'),
+            }
+        }
+
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_whitespace() {
+                break;
+            }
+            chars.next();
+            match c {
+                '\'' => loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(refused("unterminated single quote")),
+                    }
+                },
+                '"' => loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(refused("unterminated double quote")),
+                        },
+                        Some(c @ ('$' | '`')) => {
+                            return Err(refused(format!("unsupported shell syntax: {}", c)))
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err(refused("unterminated double quote")),
+                    }
+                },
+                '\\' => match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => word.push(c),
+                    None => return Err(refused("trailing backslash")),
+                },
+                ';' | '&' | '|' | '<' | '>' | '$' | '`' | '(' | ')' | '*' | '?' | '[' | '#' => {
+                    return Err(refused(format!("unsupported shell syntax: {}", c)))
+                }
+                c => word.push(c),
+            }
+        }
+        tokens.push(Token::Word(word));
+    }
+
+    Ok(tokens)
+}
+
+/// Split leading `-x` flags from operands, dropping a `--` between them
+fn split_flags(args: &[String]) -> (Vec<&str>, Vec<&str>) {
+    let count = args
+        .iter()
+        .take_while(|arg| arg.len() > 1 && arg.starts_with('-') && *arg != "--")
+        .count();
+    let operands = match args.get(count) {
+        Some(arg) if arg == "--" => &args[count + 1..],
+        _ => &args[count..],
+    };
+    (
+        args[..count].iter().map(String::as_str).collect(),
+        operands.iter().map(String::as_str).collect(),
+    )
+}
+
+/// Recognize one simple command
+fn parse_request(words: Vec<String>) -> Result<Request> {
+    let program = &words[0];
+    if Path::new(program).file_name() == Some("sy-remote".as_ref()) {
+        return Ok(Request::SyRemote(words));
+    }
+
+    let (flags, operands) = split_flags(&words[1..]);
+    let builtin = match (program.as_str(), flags.as_slice(), operands.as_slice()) {
+        ("test", ["-e"], [path]) => Builtin::TestExists(path.into()),
+        ("echo", [], words) => Builtin::Echo(words.join(" ")),
+        ("mkdir", ["-p"], [path]) => Builtin::Mkdir(path.into()),
+        ("rm", ["-f"], [path]) => Builtin::Remove {
+            path: path.into(),
+            recursive: false,
+        },
+        ("rm", ["-rf"], [path]) => Builtin::Remove {
+            path: path.into(),
+            recursive: true,
+        },
+        ("ln", [], [target, link]) => Builtin::Link {
+            target: target.into(),
+            link: link.into(),
+            symbolic: false,
+        },
+        ("ln", ["-s"], [target, link]) => Builtin::Link {
+            target: target.into(),
+            link: link.into(),
+            symbolic: true,
+        },
+        _ => return Err(refused(format!("command not allowed: {}", words.join(" ")))),
+    };
+    Ok(Request::Builtin(builtin))
+}
+
+/// Parse `SSH_ORIGINAL_COMMAND` into commands to run in order
+///
+/// The first command is paired with [`Operator::And`], so it runs
+/// unconditionally when the status starts out as success.
+pub fn parse(line: &str, home: &Path) -> Result<Vec<(Operator, Request)>> {
+    let mut requests = Vec::new();
+    let mut operator = Operator::And;
+    let mut words = Vec::new();
+
+    for token in tokenize(line, home)?
+        .into_iter()
+        .chain([Token::Operator(Operator::And)])
+    {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Operator(next) => {
+                if words.is_empty() {
+                    return Err(refused("empty command"));
+                }
+                requests.push((operator, parse_request(std::mem::take(&mut words))?));
+                operator = next;
+            }
+        }
+    }
+
+    Ok(requests)
+}
+
+impl Builtin {
+    /// Run the command, returning its status like the shell command would
+    pub fn run(&self) -> std::io::Result<bool> {
+        match self {
+            Builtin::TestExists(path) => Ok(path.exists()),
+            Builtin::Echo(text) => {
+                println!("{}", text);
+                Ok(true)
+            }
+            Builtin::Mkdir(path) => std::fs::create_dir_all(path).map(|()| true),
+            Builtin::Remove { path, recursive } => {
+                let removed = match std::fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.is_dir() && *recursive => {
+                        std::fs::remove_dir_all(path)
+                    }
+                    Ok(_) => std::fs::remove_file(path),
+                    Err(e) => Err(e),
+                };
+                match removed {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                    _ => Ok(true),
+                }
+            }
+            Builtin::Link {
+                target,
+                link,
+                symbolic: false,
+            } => std::fs::hard_link(target, link).map(|()| true),
+            #[cfg(unix)]
+            Builtin::Link {
+                target,
+                link,
+                symbolic: true,
+            } => std::os::unix::fs::symlink(target, link).map(|()| true),
+            #[cfg(not(unix))]
+            Builtin::Link { symbolic: true, .. } => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "symlinks are not supported on this platform",
+            )),
+        }
+    }
+}
+
+/// Directory tree a restricted key may access (`sy-remote restrict`)
+#[derive(Debug, Clone)]
+pub struct Restriction {
+    root: PathBuf,
+    read_only: bool,
+    /// Relative paths resolve here, as they would in the SSH session's shell
+    home: PathBuf,
+}
+
+impl Restriction {
+    /// Confine requests to `root`, optionally refusing every write
+    pub fn new(root: &Path, read_only: bool) -> Result<Self> {
+        Ok(Self {
+            root: root.canonicalize()?,
+            read_only,
+            home: std::env::current_dir()?,
+        })
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// Refuse `what` if the key is read-only
+    pub fn check_write(&self, what: &str) -> Result<()> {
+        if self.read_only {
+            return Err(refused(format!("{} not allowed: read-only access", what)));
+        }
+        Ok(())
+    }
+
+    /// Refuse `path` unless it resolves inside the root, following symlinks
+    pub fn check(&self, path: &Path) -> Result<()> {
+        self.check_resolved(path, true)
+    }
+
+    /// Like [`check`](Self::check), but a symlink as the last component is
+    /// taken as the entry itself (for removing or creating links)
+    pub fn check_entry(&self, path: &Path) -> Result<()> {
+        self.check_resolved(path, false)
+    }
+
+    fn check_resolved(&self, path: &Path, follow_last: bool) -> Result<()> {
+        let resolved = resolve_path(&self.home.join(path), follow_last);
+        if !resolved.starts_with(&self.root) {
+            return Err(refused(format!(
+                "{} is outside {}",
+                path.display(),
+                self.root.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check a shell command against the restriction before running it
+    pub fn check_builtin(&self, builtin: &Builtin) -> Result<()> {
+        match builtin {
+            Builtin::TestExists(path) => self.check(path),
+            Builtin::Echo(_) => Ok(()),
+            Builtin::Mkdir(path) => {
+                self.check_write("mkdir")?;
+                self.check(path)
+            }
+            Builtin::Remove { path, .. } => {
+                self.check_write("rm")?;
+                self.check_entry(path)
+            }
+            Builtin::Link {
+                target,
+                link,
+                symbolic,
+            } => {
+                self.check_write("ln")?;
+                // A symlink's target is only text; following it later is checked then
+                if !symbolic {
+                    self.check(target)?;
+                }
+                self.check_entry(link)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::command::RemoteCommand;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn words(line: &str) -> Vec<String> {
+        tokenize(line, Path::new("/home/u"))
+            .unwrap()
+            .into_iter()
+            .map(|token| match token {
+                Token::Word(word) => word,
+                Token::Operator(op) => format!("{:?}", op),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_quoting() {
+        assert_eq!(
+            words(r#"sy-remote scan '/srv/it'\''s here' "a \"b\"" c\ d"#),
+            vec!["sy-remote", "scan", "/srv/it's here", r#"a "b""#, "c d"]
+        );
+        assert_eq!(
+            words("ls ~/'my dir' ~ a~b"),
+            vec!["ls", "/home/u/my dir", "/home/u", "a~b"]
+        );
+        assert_eq!(
+            words("test -e x && echo 'exists' || echo 'not found'"),
+            vec![
+                "test",
+                "-e",
+                "x",
+                "And",
+                "echo",
+                "exists",
+                "Or",
+                "echo",
+                "not found"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_refuses_shell_syntax() {
+        for line in [
+            "sy-remote scan /srv; rm -rf /",
+            "cat /etc/passwd > out",
+            "sy-remote scan $(whoami)",
+            "sy-remote scan \"$HOME\"",
+            "sy-remote scan /srv/*",
+            "sy-remote scan 'unterminated",
+            "a | b",
+        ] {
+            assert!(
+                matches!(
+                    tokenize(line, Path::new("/")),
+                    Err(SyncError::Restricted(_))
+                ),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_matches_transport_commands() {
+        let home = Path::new("/home/u");
+        let line = RemoteCommand::new("/opt/sy/sy-remote")
+            .arg("scan")
+            .path(Path::new("/srv/my files"));
+        assert_eq!(
+            parse(line.as_str(), home).unwrap(),
+            vec![(
+                Operator::And,
+                Request::SyRemote(vec![
+                    "/opt/sy/sy-remote".into(),
+                    "scan".into(),
+                    "/srv/my files".into()
+                ])
+            )]
+        );
+
+        let line = format!(
+            "{} && echo 'exists' || echo 'not found'",
+            RemoteCommand::new("test").arg("-e").path(Path::new("~/x"))
+        );
+        assert_eq!(
+            parse(&line, home).unwrap(),
+            vec![
+                (
+                    Operator::And,
+                    Request::Builtin(Builtin::TestExists("/home/u/x".into()))
+                ),
+                (
+                    Operator::And,
+                    Request::Builtin(Builtin::Echo("exists".into()))
+                ),
+                (
+                    Operator::Or,
+                    Request::Builtin(Builtin::Echo("not found".into()))
+                ),
+            ]
+        );
+
+        let line = RemoteCommand::new("ln")
+            .arg("-s")
+            .end_of_options()
+            .path(Path::new("-target"))
+            .path(Path::new("/srv/link"));
+        assert_eq!(
+            parse(line.as_str(), home).unwrap(),
+            vec![(
+                Operator::And,
+                Request::Builtin(Builtin::Link {
+                    target: "-target".into(),
+                    link: "/srv/link".into(),
+                    symbolic: true,
+                })
+            )]
+        );
+
+        assert!(parse("sh -c 'command -v sy-remote'", home).is_err());
+        assert!(parse("rm -rf --no-preserve-root /", home).is_err());
+        assert!(parse("&& echo hi", home).is_err());
+    }
+
+    #[test]
+    fn test_check_confines_paths() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir(temp.path().join("outside")).unwrap();
+        let restriction = Restriction::new(&root, false).unwrap();
+
+        assert!(restriction.check(&root).is_ok());
+        assert!(restriction.check(&root.join("sub/new/file")).is_ok());
+        assert!(restriction.check(&root.join("sub/../sub")).is_ok());
+        assert!(restriction.check(&root.join("../outside")).is_err());
+        assert!(restriction.check(temp.path()).is_err());
+        assert!(restriction.check(Path::new("/etc/passwd")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_follows_symlinks() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir(temp.path().join("outside")).unwrap();
+        std::os::unix::fs::symlink(temp.path().join("outside"), root.join("escape")).unwrap();
+        let restriction = Restriction::new(&root, false).unwrap();
+
+        assert!(restriction.check(&root.join("escape")).is_err());
+        assert!(restriction.check(&root.join("escape/file")).is_err());
+        // The link itself may still be removed
+        assert!(restriction.check_entry(&root.join("escape")).is_ok());
+    }
+
+    #[test]
+    fn test_read_only() {
+        let temp = TempDir::new().unwrap();
+        let restriction = Restriction::new(temp.path(), true).unwrap();
+        let inside = temp.path().join("file");
+
+        assert!(restriction
+            .check_builtin(&Builtin::TestExists(inside.clone()))
+            .is_ok());
+        assert!(matches!(
+            restriction.check_builtin(&Builtin::Mkdir(inside.clone())),
+            Err(SyncError::Restricted(_))
+        ));
+        assert!(restriction
+            .check_builtin(&Builtin::Remove {
+                path: inside,
+                recursive: false
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_builtins_run() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("a/b");
+
+        assert!(!Builtin::TestExists(dir.clone()).run().unwrap());
+        assert!(Builtin::Mkdir(dir.clone()).run().unwrap());
+        assert!(Builtin::TestExists(dir.clone()).run().unwrap());
+        assert!(Builtin::Remove {
+            path: temp.path().join("a"),
+            recursive: true
+        }
+        .run()
+        .unwrap());
+        assert!(!temp.path().join("a").exists());
+        // rm -f of a missing file succeeds
+        assert!(Builtin::Remove {
+            path: temp.path().join("missing"),
+            recursive: false
+        }
+        .run()
+        .unwrap());
+    }
+}
@@ -0,0 +1,263 @@
+use super::config::SshConfig;
+use super::restrict::{tokenize, Operator, Token};
+use crate::error::{Result, SyncError};
+use clap::ValueEnum;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+/// How long the shared master connection outlives its last command
+const CONTROL_PERSIST: Duration = Duration::from_secs(60);
+
+/// Split an --ssh-command (e.g. "ssh -F ~/.ssh/work") into words
+///
+/// Quotes, backslashes and a leading `~` work as in a shell. There is no
+/// shell, so pipes, `&&`, `$` and the like are refused rather than passed on.
+pub fn parse_command(command: &str) -> std::result::Result<Vec<String>, String> {
+    let invalid = |reason: String| format!("Invalid SSH command '{}': {}", command, reason);
+    let home = dirs::home_dir().unwrap_or_default();
+    let tokens = tokenize(command, &home).map_err(|e| match e {
+        SyncError::Restricted(reason) => invalid(reason),
+        e => invalid(e.to_string()),
+    })?;
+
+    let mut words = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Operator(operator) => {
+                let syntax = match operator {
+                    Operator::And => "&&",
+                    Operator::Or => "||",
+                };
+                return Err(invalid(format!("unsupported shell syntax: {}", syntax)));
+            }
+        }
+    }
+    if words.is_empty() {
+        return Err(invalid("no program given".to_string()));
+    }
+    Ok(words)
+}
+
+/// SSH implementation used for remote paths
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SshBackend {
+    /// Built-in libssh2 sessions, with SFTP (default)
+    #[default]
+    Libssh2,
+
+    /// The system `ssh` binary, running sy-remote over one shared connection
+    System,
+}
+
+/// Runs remote commands with the system `ssh` binary (`--ssh-backend system`)
+///
+/// OpenSSH streams faster than libssh2, whose blocking sessions cap a single
+/// transfer, and it honours everything in the user's ssh_config (ProxyCommand,
+/// GSSAPI, FIDO keys, ...). Every command rides one ControlMaster connection,
+/// so each costs a round trip rather than a handshake. There is no SFTP: the
+/// host needs sy-remote. `--ssh-command` swaps in another client or wrapper.
+#[derive(Debug, Clone)]
+pub struct SystemSsh {
+    /// The ssh binary and any arguments from --ssh-command
+    program: Vec<String>,
+    options: Vec<String>,
+    host: String,
+}
+
+impl SystemSsh {
+    /// Connect to the host in `config`, starting the shared master connection
+    ///
+    /// Authentication happens here, prompting on the terminal if it must, so
+    /// later commands never stop to ask.
+    pub async fn connect(config: &SshConfig) -> Result<Self> {
+        let ssh = Self::new(config);
+        let check = ssh.clone();
+        let output = tokio::task::spawn_blocking(move || {
+            check
+                .command("true")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .output()
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+        .map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to run ssh: {}", e),
+            ))
+        })?;
+
+        if !output.status.success() {
+            return Err(SyncError::Io(std::io::Error::other(format!(
+                "SSH connection to {} failed: {}",
+                ssh.host,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))));
+        }
+        Ok(ssh)
+    }
+
+    fn new(config: &SshConfig) -> Self {
+        // ssh resolves the alias through ssh_config itself, so only what the
+        // command line overrides is spelled out
+        let mut options = vec!["-T".to_string()];
+        for (key, value) in &config.command_line {
+            let flag = match key.to_lowercase().as_str() {
+                "port" => "-p",
+                "user" => "-l",
+                "identityfile" => "-i",
+                "proxyjump" => "-J",
+                _ => {
+                    options.push("-o".to_string());
+                    options.push(format!("{}={}", key, value));
+                    continue;
+                }
+            };
+            options.push(flag.to_string());
+            options.push(value.clone());
+        }
+
+        // %C hashes host, port and user, keeping the socket path short
+        let control_path = config
+            .control_path
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("sy-ssh-%C"));
+        let persist = config.control_persist.unwrap_or(CONTROL_PERSIST);
+        options.extend([
+            "-o".to_string(),
+            "ControlMaster=auto".to_string(),
+            "-o".to_string(),
+            format!("ControlPath={}", control_path.display()),
+            "-o".to_string(),
+            format!("ControlPersist={}", persist.as_secs()),
+        ]);
+
+        let program = config
+            .program
+            .clone()
+            .filter(|program| !program.is_empty())
+            .unwrap_or_else(|| vec!["ssh".to_string()]);
+
+        Self {
+            program,
+            options,
+            host: config.alias.clone(),
+        }
+    }
+
+    fn command(&self, remote_command: &str) -> Command {
+        let mut command = Command::new(&self.program[0]);
+        command
+            .args(&self.program[1..])
+            .args(&self.options)
+            .arg("--")
+            .arg(&self.host)
+            .arg(remote_command);
+        command
+    }
+
+    /// Start `remote_command` on the host with piped stdin, stdout and stderr
+    pub fn spawn(&self, remote_command: &str) -> std::io::Result<Child> {
+        self.command(remote_command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_command_line() {
+        // As resolved from ssh_config, which ssh reads again itself
+        let mut config = SshConfig::new("nas");
+        config.hostname = "nas.local".to_string();
+        config.port = 2200;
+        config.identity_file = vec![PathBuf::from("/keys/id_rsa")];
+        config.control_path = Some(PathBuf::from("/run/sy-%C"));
+        config.apply_options(&[
+            ("Port".to_string(), "2222".to_string()),
+            ("User".to_string(), "backup".to_string()),
+            ("IdentityFile".to_string(), "/keys/id_ed25519".to_string()),
+            ("ProxyJump".to_string(), "bastion".to_string()),
+            ("Ciphers".to_string(), "aes128-gcm@openssh.com".to_string()),
+        ]);
+
+        let command = SystemSsh::new(&config).command("sy-remote capabilities");
+        let args: Vec<_> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+        assert_eq!(command.get_program(), "ssh");
+        assert_eq!(
+            args,
+            [
+                "-T",
+                "-p",
+                "2222",
+                "-l",
+                "backup",
+                "-i",
+                "/keys/id_ed25519",
+                "-J",
+                "bastion",
+                "-o",
+                "Ciphers=aes128-gcm@openssh.com",
+                "-o",
+                "ControlMaster=auto",
+                "-o",
+                "ControlPath=/run/sy-%C",
+                "-o",
+                "ControlPersist=60",
+                "--",
+                "nas",
+                "sy-remote capabilities",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_program() {
+        let mut config = SshConfig::new("nas.local");
+        config.program = Some(vec![
+            "ssh".to_string(),
+            "-F".to_string(),
+            "/etc/sy/ssh_config".to_string(),
+        ]);
+
+        let command = SystemSsh::new(&config).command("true");
+        let args: Vec<_> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+        assert_eq!(command.get_program(), "ssh");
+        assert_eq!(args[..3], ["-F", "/etc/sy/ssh_config", "-T"]);
+        assert_eq!(args[args.len() - 2..], ["nas.local", "true"]);
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("tsh ssh").unwrap(), ["tsh", "ssh"]);
+        assert_eq!(
+            parse_command(r#"ssh -F "/etc/sy/ssh config" -o 'ProxyCommand=nc %h %p'"#).unwrap(),
+            [
+                "ssh",
+                "-F",
+                "/etc/sy/ssh config",
+                "-o",
+                "ProxyCommand=nc %h %p"
+            ]
+        );
+        assert!(parse_command("ssh && rm -rf /").is_err());
+        assert!(parse_command("ssh $HOME").is_err());
+        assert!(parse_command("ssh 'unterminated").is_err());
+        assert!(parse_command("  ").is_err());
+    }
+}
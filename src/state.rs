@@ -0,0 +1,103 @@
+use crate::error::{Result, SyncError};
+use crate::path::SyncPath;
+use std::path::{Path, PathBuf};
+
+/// Base directory for per-sync state (resume state, directory cache, checksum DB)
+///
+/// Uses `override_dir` (--state-dir) if given, otherwise `$XDG_STATE_HOME/sy`,
+/// falling back to `~/.local/state/sy`.
+pub fn base_dir(override_dir: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = override_dir {
+        return Ok(dir.to_path_buf());
+    }
+
+    let state_home = if let Some(xdg_state) = std::env::var_os("XDG_STATE_HOME") {
+        PathBuf::from(xdg_state)
+    } else if let Some(home) = dirs::home_dir() {
+        home.join(".local").join("state")
+    } else {
+        return Err(SyncError::Config(
+            "Cannot determine state directory (HOME not set). Use --state-dir to set one."
+                .to_string(),
+        ));
+    };
+
+    Ok(state_home.join("sy"))
+}
+
+/// Stable identifier for one side of a sync, used to key its state directory
+///
+/// Local paths are made absolute so the same pair maps to the same state
+/// regardless of the working directory; remote and S3 paths keep their host.
+pub fn path_key(path: &SyncPath) -> String {
+    match path {
+        SyncPath::Local(p) if p.is_relative() => std::env::current_dir()
+            .map(|cwd| cwd.join(p).display().to_string())
+            .unwrap_or_else(|_| p.display().to_string()),
+        other => other.to_string(),
+    }
+}
+
+/// State directory for a source/destination pair
+///
+/// Returns `<base>/<hash>`, where the hash is derived from both sides, so
+/// different pairs never share resume state, caches or checksum databases.
+/// The directory is not created here; writers create it on first save.
+pub fn sync_state_dir(
+    override_dir: Option<&Path>,
+    source: &SyncPath,
+    destination: &SyncPath,
+) -> Result<PathBuf> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(path_key(source).as_bytes());
+    hasher.update(b"\0");
+    hasher.update(path_key(destination).as_bytes());
+    let hash = hasher.finalize().to_hex();
+
+    Ok(base_dir(override_dir)?.join(&hash[..16]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_state_dir_override() {
+        let temp = TempDir::new().unwrap();
+        let source = SyncPath::Local(PathBuf::from("/src"));
+        let dest = SyncPath::Local(PathBuf::from("/dest"));
+
+        let dir = sync_state_dir(Some(temp.path()), &source, &dest).unwrap();
+        assert_eq!(dir.parent(), Some(temp.path()));
+
+        // Not created until something is saved
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_state_dir_unique_per_pair() {
+        let base = Path::new("/state");
+        let a = SyncPath::Local(PathBuf::from("/a"));
+        let b = SyncPath::Local(PathBuf::from("/b"));
+        let remote = SyncPath::Remote {
+            host: "server".to_string(),
+            user: None,
+            path: PathBuf::from("/b"),
+        };
+
+        let ab = sync_state_dir(Some(base), &a, &b).unwrap();
+        assert_eq!(ab, sync_state_dir(Some(base), &a, &b).unwrap());
+        assert_ne!(ab, sync_state_dir(Some(base), &b, &a).unwrap());
+        assert_ne!(ab, sync_state_dir(Some(base), &a, &remote).unwrap());
+    }
+
+    #[test]
+    fn test_relative_paths_keyed_absolute() {
+        let cwd = std::env::current_dir().unwrap();
+        let relative = SyncPath::Local(PathBuf::from("data"));
+        let absolute = SyncPath::Local(cwd.join("data"));
+
+        assert_eq!(path_key(&relative), path_key(&absolute));
+    }
+}
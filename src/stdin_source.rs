@@ -0,0 +1,103 @@
+use crate::error::Result;
+use crate::resource::check_disk_space;
+use std::io::{Read, Write};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Source argument that reads the file's data from stdin (`sy - dest/file`)
+pub const STDIN_SOURCE: &str = "-";
+
+/// Stdin spooled to a temporary file, deleted when dropped
+///
+/// Comparison, delta sync, compression and verification all read the source
+/// more than once, which a pipe can't do. The stream is written to disk first
+/// and then synced like any single file.
+pub struct StdinSpool {
+    file: NamedTempFile,
+    size: u64,
+}
+
+impl StdinSpool {
+    /// Copy `input` to a temp file in `dir`, calling `on_progress` with the
+    /// bytes read so far
+    ///
+    /// With `size_hint` (the expected stream size) the spool is refused up
+    /// front when `dir` can't hold it, rather than after a long dump fills
+    /// the disk.
+    pub fn read(
+        mut input: impl Read,
+        dir: &Path,
+        size_hint: Option<u64>,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<Self> {
+        if let Some(hint) = size_hint {
+            check_disk_space(dir, hint)?;
+        }
+
+        let mut file = tempfile::Builder::new()
+            .prefix(".sy-stdin-")
+            .tempfile_in(dir)?;
+        let mut buf = vec![0u8; 256 * 1024];
+        let mut size = 0u64;
+        loop {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            file.write_all(&buf[..n])?;
+            size += n as u64;
+            on_progress(size);
+        }
+        file.flush()?;
+
+        // Temp files are created 0600; use the usual file mode so -p doesn't
+        // leave the destination private
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o644))?;
+        }
+
+        Ok(Self { file, size })
+    }
+
+    /// Spooled file to sync from
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Bytes read from stdin
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_spools_stream() {
+        let temp = TempDir::new().unwrap();
+        let data = vec![7u8; 600 * 1024];
+        let mut reported = Vec::new();
+
+        let spool = StdinSpool::read(&data[..], temp.path(), None, |n| reported.push(n)).unwrap();
+        assert_eq!(spool.size(), data.len() as u64);
+        assert_eq!(std::fs::read(spool.path()).unwrap(), data);
+        assert_eq!(reported.last(), Some(&(data.len() as u64)));
+
+        let path = spool.path().to_path_buf();
+        drop(spool);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_size_hint_checks_free_space() {
+        let temp = TempDir::new().unwrap();
+        assert!(StdinSpool::read(&b""[..], temp.path(), Some(u64::MAX / 2), |_| {}).is_err());
+    }
+}
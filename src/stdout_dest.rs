@@ -0,0 +1,136 @@
+use crate::error::{Result, SyncError};
+use crate::integrity::IntegrityVerifier;
+use crate::transport::{PathStat, Transport};
+use std::io::Write;
+use std::path::Path;
+
+/// Destination argument that writes the file's data to stdout (`sy host:/file -`)
+pub const STDOUT_DEST: &str = "-";
+
+/// Pull the file at `source` through `transport` and write it to `output`
+///
+/// The file lands in a temp directory in `spool_dir` first, so it is checked
+/// before any of it reaches the pipeline: its size must match the source's,
+/// the source must not have changed while it was read, and with a
+/// `verifier` (local sources) the checksums must match too. A pipeline
+/// never sees a truncated or half-updated file. Returns the bytes written.
+pub async fn pull<T: Transport + ?Sized>(
+    transport: &T,
+    source: &Path,
+    spool_dir: &Path,
+    verifier: Option<&IntegrityVerifier>,
+    mut output: impl Write,
+) -> Result<u64> {
+    let before = stat(transport, source).await?;
+
+    let spool = tempfile::Builder::new()
+        .prefix(".sy-stdout-")
+        .tempdir_in(spool_dir)?;
+    let pulled = spool.path().join("file");
+    transport.download_file(source, &pulled).await?;
+
+    if stat(transport, source).await? != before {
+        return Err(SyncError::Io(std::io::Error::other(format!(
+            "{} changed while it was read; try again",
+            source.display()
+        ))));
+    }
+    let size = std::fs::metadata(&pulled)?.len();
+    if size != before.size {
+        return Err(SyncError::Io(std::io::Error::other(format!(
+            "Pulled {} of {} bytes of {}",
+            size,
+            before.size,
+            source.display()
+        ))));
+    }
+    if let Some(verifier) = verifier {
+        if !verifier.verify_transfer(source, &pulled)? {
+            return Err(SyncError::Io(std::io::Error::other(format!(
+                "Verification failed for {}: checksums do not match",
+                source.display()
+            ))));
+        }
+    }
+
+    let mut file = std::fs::File::open(&pulled)?;
+    let written = std::io::copy(&mut file, &mut output)?;
+    output.flush()?;
+    Ok(written)
+}
+
+/// The source's size and mtime, refusing anything but an existing file
+async fn stat<T: Transport + ?Sized>(transport: &T, source: &Path) -> Result<PathStat> {
+    match transport
+        .stat_batch(&[source.to_path_buf()])
+        .await?
+        .pop()
+        .flatten()
+    {
+        Some(stat) if !stat.is_dir => Ok(stat),
+        Some(_) => Err(SyncError::Io(std::io::Error::other(format!(
+            "{} is a directory; only a single file can be written to stdout (-)",
+            source.display()
+        )))),
+        None => Err(SyncError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} not found", source.display()),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrity::ChecksumType;
+    use crate::transport::local::LocalTransport;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_pull_to_output() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("db.sql");
+        let data = vec![42u8; 300 * 1024];
+        std::fs::write(&source, &data).unwrap();
+        let verifier = IntegrityVerifier::new(ChecksumType::Fast, false);
+
+        let mut output = Vec::new();
+        let written = pull(
+            &LocalTransport::new(),
+            &source,
+            temp.path(),
+            Some(&verifier),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        assert_eq!(written, data.len() as u64);
+        assert_eq!(output, data);
+        // The spool is gone again
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pull_refuses_directories() {
+        let temp = TempDir::new().unwrap();
+        let spool = TempDir::new().unwrap();
+        let mut output = Vec::new();
+
+        let transport = LocalTransport::new();
+        assert!(
+            pull(&transport, temp.path(), spool.path(), None, &mut output)
+                .await
+                .is_err()
+        );
+        assert!(pull(
+            &transport,
+            &temp.path().join("missing"),
+            spool.path(),
+            None,
+            &mut output
+        )
+        .await
+        .is_err());
+        assert!(output.is_empty());
+    }
+}
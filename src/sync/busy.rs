@@ -0,0 +1,140 @@
+use crate::error::{Result, SyncError};
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Longest pause between retries of a busy file
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Windows ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+#[cfg(windows)]
+const SHARING_VIOLATION_CODES: [i32; 2] = [32, 33];
+
+/// Whether an I/O error means the file is held open by another process
+///
+/// Covers EBUSY/ETXTBSY on Unix and sharing/lock violations on Windows.
+pub fn is_busy_io(error: &std::io::Error) -> bool {
+    if matches!(
+        error.kind(),
+        ErrorKind::ResourceBusy | ErrorKind::ExecutableFileBusy
+    ) {
+        return true;
+    }
+
+    #[cfg(windows)]
+    if let Some(code) = error.raw_os_error() {
+        return SHARING_VIOLATION_CODES.contains(&code);
+    }
+
+    false
+}
+
+/// Whether a sync error was caused by a busy file (checks the whole source chain)
+pub fn is_busy(error: &SyncError) -> bool {
+    let mut current: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(err) = current {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if is_busy_io(io) {
+                return true;
+            }
+        }
+        current = err.source();
+    }
+    false
+}
+
+/// Run `op`, retrying with backoff while it fails on a busy file
+///
+/// Gives up after `wait` (no retries if `None`) and returns the last error.
+pub async fn retry_busy<T, F, Fut>(wait: Option<Duration>, path: &Path, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let deadline = wait.map(|wait| Instant::now() + wait);
+    let mut delay = Duration::from_millis(100);
+
+    loop {
+        match op().await {
+            Err(e) if is_busy(&e) => {
+                let now = Instant::now();
+                match deadline {
+                    Some(deadline) if now < deadline => {
+                        tracing::debug!("{} is busy, retrying in {:?}", path.display(), delay);
+                        tokio::time::sleep(delay.min(deadline - now)).await;
+                        delay = (delay * 2).min(MAX_RETRY_DELAY);
+                    }
+                    _ => return Err(e),
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn busy_error() -> SyncError {
+        SyncError::Io(std::io::Error::from(ErrorKind::ResourceBusy))
+    }
+
+    #[test]
+    fn test_is_busy() {
+        assert!(is_busy(&busy_error()));
+        assert!(is_busy(&SyncError::CopyError {
+            path: "file".into(),
+            source: std::io::Error::from(ErrorKind::ExecutableFileBusy),
+        }));
+        assert!(!is_busy(&SyncError::Io(std::io::Error::from(
+            ErrorKind::PermissionDenied
+        ))));
+    }
+
+    #[tokio::test]
+    async fn test_retry_busy_until_free() {
+        let counter = AtomicUsize::new(0);
+        let attempts = &counter;
+        let result = retry_busy(
+            Some(Duration::from_secs(5)),
+            Path::new("file"),
+            || async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(busy_error())
+                } else {
+                    Ok(42)
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_busy_gives_up() {
+        let counter = AtomicUsize::new(0);
+        let attempts = &counter;
+
+        // Without a wait, busy errors are returned immediately
+        let result: Result<()> = retry_busy(None, Path::new("file"), || async move {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(busy_error())
+        })
+        .await;
+        assert!(result.is_err_and(|e| is_busy(&e)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // Other errors are never retried
+        let result: Result<()> =
+            retry_busy(Some(Duration::from_secs(5)), Path::new("file"), || async {
+                Err(SyncError::Io(std::io::Error::from(ErrorKind::NotFound)))
+            })
+            .await;
+        assert!(result.is_err_and(|e| !is_busy(&e)));
+    }
+}
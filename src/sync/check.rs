@@ -0,0 +1,167 @@
+use super::strategy::{self, StrategyPlanner, SyncAction};
+use crate::error::Result;
+use crate::filter::FilterEngine;
+use crate::transport::Transport;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Drift between source and destination found by `sy check`
+///
+/// Paths are relative to the sync roots.
+#[derive(Debug, Default, Serialize)]
+pub struct CheckReport {
+    pub files_checked: usize,
+    /// In source, missing from destination
+    pub missing: Vec<PathBuf>,
+    /// In both, but differing by size/mtime (or checksum)
+    pub changed: Vec<PathBuf>,
+    /// In destination only (not counted with `--ignore-extra`)
+    pub extra: Vec<PathBuf>,
+    pub duration_secs: f64,
+}
+
+impl CheckReport {
+    pub fn in_sync(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// Compare source and destination the way a sync would, without changing anything
+///
+/// Files are compared by size and mtime (or checksum with `checksum`), using
+/// the same planner as a real sync, so a clean report means a sync would be
+/// a no-op. Destination-only entries are reported unless `ignore_extra`.
+pub async fn check<T: Transport>(
+    transport: &T,
+    source: &Path,
+    destination: &Path,
+    filter: &FilterEngine,
+    checksum: bool,
+    ignore_extra: bool,
+) -> Result<CheckReport> {
+    let start = Instant::now();
+
+    let source_files: Vec<_> = transport
+        .scan(source)
+        .await?
+        .into_iter()
+        .filter(|f| !filter.should_exclude(&f.relative_path, f.is_dir))
+        .collect();
+    let mut report = CheckReport {
+        files_checked: source_files.iter().filter(|f| !f.is_dir).count(),
+        ..Default::default()
+    };
+
+    let mut planner = StrategyPlanner::with_comparison_flags(false, false, checksum);
+    // Allow for a destination clock running ahead or behind, like a sync does
+    if let Some(skew_ms) = transport
+        .clock_skew_ms()
+        .await
+        .filter(|skew| skew.unsigned_abs() > strategy::MTIME_TOLERANCE_SECS * 1000)
+    {
+        planner = planner.with_clock_skew(skew_ms);
+    }
+    let pending: Vec<_> = source_files.iter().collect();
+    for batch in pending.chunks(strategy::PLAN_BATCH_SIZE) {
+        for task in planner
+            .plan_batch_async(batch, destination, transport, None)
+            .await?
+        {
+            let Some(source) = task.source else { continue };
+            match task.action {
+                SyncAction::Create => report.missing.push(source.relative_path),
+                SyncAction::Update => report.changed.push(source.relative_path),
+                SyncAction::Skip | SyncAction::Delete => {}
+            }
+        }
+    }
+
+    // A missing destination has no extras (everything is already "missing")
+    if !ignore_extra && transport.exists(destination).await? {
+        let source_paths: HashSet<_> = source_files.iter().map(|f| &f.relative_path).collect();
+        report.extra = transport
+            .scan_destination(destination)
+            .await?
+            .into_iter()
+            .filter(|f| !filter.should_exclude(&f.relative_path, f.is_dir))
+            .filter(|f| !source_paths.contains(&f.relative_path))
+            .map(|f| f.relative_path)
+            .collect();
+    }
+
+    report.duration_secs = start.elapsed().as_secs_f64();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::local::LocalTransport;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_check_reports_drift() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        fs::write(source.path().join("same.txt"), "same").unwrap();
+        fs::write(source.path().join("new.txt"), "new").unwrap();
+        fs::write(source.path().join("edited.txt"), "longer content").unwrap();
+        fs::write(dest.path().join("edited.txt"), "short").unwrap();
+        fs::write(dest.path().join("stale.txt"), "stale").unwrap();
+        fs::copy(source.path().join("same.txt"), dest.path().join("same.txt")).unwrap();
+        let mtime = filetime::FileTime::from_last_modification_time(
+            &fs::metadata(source.path().join("same.txt")).unwrap(),
+        );
+        filetime::set_file_mtime(dest.path().join("same.txt"), mtime).unwrap();
+
+        let transport = LocalTransport::new();
+        let filter = FilterEngine::new();
+        let report = check(
+            &transport,
+            source.path(),
+            dest.path(),
+            &filter,
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert!(!report.in_sync());
+        assert_eq!(report.files_checked, 3);
+        assert_eq!(report.missing, vec![PathBuf::from("new.txt")]);
+        assert_eq!(report.changed, vec![PathBuf::from("edited.txt")]);
+        assert_eq!(report.extra, vec![PathBuf::from("stale.txt")]);
+
+        let report = check(&transport, source.path(), dest.path(), &filter, false, true)
+            .await
+            .unwrap();
+        assert!(report.extra.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_in_sync() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        fs::write(source.path().join("a.txt"), "content").unwrap();
+        fs::write(dest.path().join("a.txt"), "content").unwrap();
+
+        let transport = LocalTransport::new();
+        let report = check(
+            &transport,
+            source.path(),
+            dest.path(),
+            &FilterEngine::new(),
+            true,
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert!(report.in_sync());
+        assert_eq!(report.files_checked, 1);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::error::Result;
-use crate::integrity::Checksum;
-use rusqlite::{params, Connection};
+use crate::integrity::{Checksum, ChecksumType};
+use crate::sync::output::{self, WarningCategory};
+use rusqlite::{params, Connection, OptionalExtension};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -8,25 +9,67 @@ use std::time::{SystemTime, UNIX_EPOCH};
 /// Persistent checksum database for fast re-verification
 ///
 /// Stores file checksums with metadata to avoid recomputing on every sync.
-/// Uses SQLite for reliability and efficient querying.
+/// Both source and destination files are recorded (keyed by absolute path),
+/// so a repeat `--checksum` run whose files are unchanged only needs to stat
+/// them. Uses SQLite in WAL mode so lookups and upserts stay on disk: memory use is
+/// independent of the number of entries, even at tens of millions of files.
 #[allow(dead_code)] // Integration with SyncEngine pending
 pub struct ChecksumDatabase {
     conn: Connection,
+    generation: u64, // Sync run that rows written now are stamped with
 }
 
 #[allow(dead_code)] // Integration with SyncEngine pending
 impl ChecksumDatabase {
-    /// Database file name in destination directory
-    const DB_FILE: &'static str = ".sy-checksums.db";
+    /// Database file name in the sync's state directory
+    pub const DB_FILE: &'static str = ".sy-checksums.db";
 
     /// Database schema version
-    const SCHEMA_VERSION: i32 = 1;
+    const SCHEMA_VERSION: i32 = 2;
 
-    /// Open or create checksum database in destination directory
-    pub fn open(dest_path: &Path) -> Result<Self> {
-        let db_path = dest_path.join(Self::DB_FILE);
+    /// Rows per transaction for bulk upserts (bounds WAL growth and lock time)
+    const BATCH_SIZE: usize = 10_000;
+
+    /// Path of the database file within a state directory
+    pub fn db_path(state_dir: &Path) -> PathBuf {
+        state_dir.join(Self::DB_FILE)
+    }
+
+    /// Open or create checksum database in the sync's state directory
+    pub fn open(state_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(state_dir)?;
+        let db_path = Self::db_path(state_dir);
         let conn = Connection::open(&db_path)?;
 
+        // WAL: readers don't block the writer, and commits only append to the log
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+
+        // Store schema version in metadata table
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS metadata (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Entries are only a cache, so an old schema is dropped rather than migrated
+        let stored_version: Option<i32> = conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'schema_version'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if stored_version.is_some_and(|v| v != Self::SCHEMA_VERSION) {
+            tracing::info!(
+                "Checksum database schema changed (v{} -> v{}), discarding entries",
+                stored_version.unwrap_or(0),
+                Self::SCHEMA_VERSION
+            );
+            conn.execute("DROP TABLE IF EXISTS checksums", [])?;
+        }
+
         // Create schema if not exists
         conn.execute(
             "CREATE TABLE IF NOT EXISTS checksums (
@@ -34,8 +77,10 @@ impl ChecksumDatabase {
                 mtime_secs INTEGER NOT NULL,
                 mtime_nanos INTEGER NOT NULL,
                 size INTEGER NOT NULL,
+                inode INTEGER,
                 checksum_type TEXT NOT NULL,
                 checksum BLOB NOT NULL,
+                generation INTEGER NOT NULL,
                 updated_at INTEGER NOT NULL
             )",
             [],
@@ -47,46 +92,76 @@ impl ChecksumDatabase {
             [],
         )?;
 
-        // Store schema version in metadata table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS metadata (
-                key TEXT PRIMARY KEY,
-                value INTEGER NOT NULL
-            )",
-            [],
-        )?;
-
         conn.execute(
             "INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', ?1)",
             params![Self::SCHEMA_VERSION],
         )?;
 
-        Ok(Self { conn })
+        let generation: Option<i64> = conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'generation'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(Self {
+            conn,
+            generation: generation.unwrap_or(0) as u64,
+        })
+    }
+
+    /// Start a new sync generation
+    ///
+    /// Checksums stored afterwards are stamped with the new generation, so
+    /// entries not re-verified by recent syncs can be told apart.
+    pub fn begin_generation(&mut self) -> Result<u64> {
+        self.generation += 1;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('generation', ?1)",
+            params![self.generation as i64],
+        )?;
+        Ok(self.generation)
+    }
+
+    /// Current sync generation (0 if no sync has stored checksums yet)
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
-    /// Get cached checksum if file unchanged (mtime + size match)
+    /// Get cached checksum if file unchanged (mtime + size + inode match)
     ///
     /// Returns None if:
     /// - No entry found
-    /// - File metadata changed (stale cache)
+    /// - File metadata changed (stale cache), including being replaced by
+    ///   a different file with the same size and mtime (inode changed)
     /// - Checksum type doesn't match
     pub fn get_checksum(
         &self,
         path: &Path,
         mtime: SystemTime,
         size: u64,
+        inode: Option<u64>,
         checksum_type: &str,
     ) -> Result<Option<Checksum>> {
         let path_str = path.to_string_lossy();
         let (mtime_secs, mtime_nanos) = system_time_to_parts(mtime);
 
-        let mut stmt = self.conn.prepare(
+        // IS compares NULLs as equal (platforms without inode numbers)
+        let mut stmt = self.conn.prepare_cached(
             "SELECT checksum_type, checksum FROM checksums
-             WHERE path = ?1 AND mtime_secs = ?2 AND mtime_nanos = ?3 AND size = ?4",
+             WHERE path = ?1 AND mtime_secs = ?2 AND mtime_nanos = ?3 AND size = ?4
+               AND inode IS ?5",
         )?;
 
         let result = stmt.query_row(
-            params![path_str.as_ref(), mtime_secs, mtime_nanos, size as i64],
+            params![
+                path_str.as_ref(),
+                mtime_secs,
+                mtime_nanos,
+                size as i64,
+                inode.map(|i| i as i64)
+            ],
             |row| {
                 let stored_type: String = row.get(0)?;
                 let checksum_blob: Vec<u8> = row.get(1)?;
@@ -112,7 +187,11 @@ impl ChecksumDatabase {
                     "fast" => Checksum::Fast(checksum_blob),
                     "cryptographic" => Checksum::Cryptographic(checksum_blob),
                     _ => {
-                        tracing::warn!("Unknown checksum type in database: {}", stored_type);
+                        output::warn(
+                            WarningCategory::Cache,
+                            None,
+                            format!("Unknown checksum type in database: {}", stored_type),
+                        );
                         return Ok(None);
                     }
                 };
@@ -134,38 +213,50 @@ impl ChecksumDatabase {
         path: &Path,
         mtime: SystemTime,
         size: u64,
+        inode: Option<u64>,
         checksum: &Checksum,
     ) -> Result<()> {
-        let path_str = path.to_string_lossy();
-        let (mtime_secs, mtime_nanos) = system_time_to_parts(mtime);
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
+        if upsert_checksum(
+            &self.conn,
+            path,
+            mtime,
+            size,
+            inode,
+            checksum,
+            self.generation,
+        )? {
+            tracing::debug!("Stored checksum for {}", path.display());
+        }
+        Ok(())
+    }
 
-        let (checksum_type, checksum_blob) = match checksum {
-            Checksum::None => return Ok(()), // Don't store None checksums
-            Checksum::Fast(bytes) => ("fast", bytes.clone()),
-            Checksum::Cryptographic(bytes) => ("cryptographic", bytes.clone()),
-        };
+    /// Store many checksums, committing every `BATCH_SIZE` rows
+    ///
+    /// Entries are consumed lazily, so callers can stream checksums as they
+    /// are computed without collecting them first. Returns the number stored.
+    pub fn store_checksums<'a>(
+        &self,
+        entries: impl IntoIterator<Item = (&'a Path, SystemTime, u64, Option<u64>, Checksum)>,
+    ) -> Result<usize> {
+        let mut stored = 0;
+        let mut pending = 0;
+        let mut tx = self.conn.unchecked_transaction()?;
+
+        for (path, mtime, size, inode, checksum) in entries {
+            if upsert_checksum(&tx, path, mtime, size, inode, &checksum, self.generation)? {
+                stored += 1;
+                pending += 1;
+            }
 
-        self.conn.execute(
-            "INSERT OR REPLACE INTO checksums
-             (path, mtime_secs, mtime_nanos, size, checksum_type, checksum, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
-                path_str.as_ref(),
-                mtime_secs,
-                mtime_nanos,
-                size as i64,
-                checksum_type,
-                checksum_blob,
-                now
-            ],
-        )?;
+            if pending >= Self::BATCH_SIZE {
+                tx.commit()?;
+                tx = self.conn.unchecked_transaction()?;
+                pending = 0;
+            }
+        }
 
-        tracing::debug!("Stored checksum for {}", path.display());
-        Ok(())
+        tx.commit()?;
+        Ok(stored)
     }
 
     /// Clear all cached checksums
@@ -180,27 +271,28 @@ impl ChecksumDatabase {
     /// Takes a set of existing file paths and removes database entries
     /// for paths not in the set.
     pub fn prune(&self, existing_files: &HashSet<PathBuf>) -> Result<usize> {
-        // Get all paths in database
-        let mut stmt = self.conn.prepare("SELECT path FROM checksums")?;
-        let db_paths: Vec<String> = stmt
-            .query_map([], |row| row.get(0))?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
-
-        // Find paths to delete (in database but not in existing_files)
-        let mut to_delete = Vec::new();
-        for db_path in &db_paths {
-            let path = PathBuf::from(db_path);
-            if !existing_files.contains(&path) {
-                to_delete.push(db_path.clone());
+        // Load the existing paths into a temp table and let SQLite do the
+        // set difference, instead of reading every database path into memory
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "CREATE TEMP TABLE IF NOT EXISTS existing_paths (path TEXT PRIMARY KEY)",
+            [],
+        )?;
+        tx.execute("DELETE FROM temp.existing_paths", [])?;
+        {
+            let mut stmt =
+                tx.prepare("INSERT OR IGNORE INTO temp.existing_paths (path) VALUES (?1)")?;
+            for path in existing_files {
+                stmt.execute(params![path.to_string_lossy().as_ref()])?;
             }
         }
 
-        // Delete stale entries
-        let deleted_count = to_delete.len();
-        for path in to_delete {
-            self.conn
-                .execute("DELETE FROM checksums WHERE path = ?1", params![path])?;
-        }
+        let deleted_count = tx.execute(
+            "DELETE FROM checksums WHERE path NOT IN (SELECT path FROM temp.existing_paths)",
+            [],
+        )?;
+        tx.execute("DROP TABLE temp.existing_paths", [])?;
+        tx.commit()?;
 
         if deleted_count > 0 {
             tracing::info!(
@@ -230,12 +322,103 @@ impl ChecksumDatabase {
             |row| row.get(0),
         )?;
 
+        let (size_bytes, free_bytes) = self.page_usage()?;
+
         Ok(ChecksumDbStats {
             total_entries: total_entries as usize,
             fast_checksums: fast_count as usize,
             cryptographic_checksums: crypto_count as usize,
+            generation: self.generation,
+            size_bytes,
+            free_bytes,
         })
     }
+
+    /// Rebuild the database file, reclaiming space left by deleted entries
+    ///
+    /// Checkpoints the WAL first so the log is folded into the main file.
+    /// Returns (size before, size after) in bytes.
+    pub fn vacuum(&self) -> Result<(u64, u64)> {
+        let (before, _) = self.page_usage()?;
+        self.conn
+            .execute_batch("PRAGMA wal_checkpoint(TRUNCATE); VACUUM;")?;
+        let (after, _) = self.page_usage()?;
+
+        tracing::info!("Vacuumed checksum database: {} -> {} bytes", before, after);
+        Ok((before, after))
+    }
+
+    /// Database size and unused (free list) space, in bytes
+    fn page_usage(&self) -> Result<(u64, u64)> {
+        let page_size: i64 = self
+            .conn
+            .query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let page_count: i64 = self
+            .conn
+            .query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let free_pages: i64 = self
+            .conn
+            .query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+
+        Ok((
+            (page_size * page_count) as u64,
+            (page_size * free_pages) as u64,
+        ))
+    }
+}
+
+/// Insert or update one checksum row
+///
+/// Returns false if the checksum was `None` and nothing was stored.
+fn upsert_checksum(
+    conn: &Connection,
+    path: &Path,
+    mtime: SystemTime,
+    size: u64,
+    inode: Option<u64>,
+    checksum: &Checksum,
+    generation: u64,
+) -> Result<bool> {
+    let (checksum_type, checksum_blob) = match checksum {
+        Checksum::None => return Ok(false), // Don't store None checksums
+        Checksum::Fast(bytes) => ("fast", bytes),
+        Checksum::Cryptographic(bytes) => ("cryptographic", bytes),
+    };
+
+    let path_str = path.to_string_lossy();
+    let (mtime_secs, mtime_nanos) = system_time_to_parts(mtime);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO checksums
+         (path, mtime_secs, mtime_nanos, size, inode, checksum_type, checksum, generation, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(path) DO UPDATE SET
+             mtime_secs = excluded.mtime_secs,
+             mtime_nanos = excluded.mtime_nanos,
+             size = excluded.size,
+             inode = excluded.inode,
+             checksum_type = excluded.checksum_type,
+             checksum = excluded.checksum,
+             generation = excluded.generation,
+             updated_at = excluded.updated_at",
+    )?;
+    stmt.execute(params![
+        path_str.as_ref(),
+        mtime_secs,
+        mtime_nanos,
+        size as i64,
+        inode.map(|i| i as i64),
+        checksum_type,
+        checksum_blob,
+        generation as i64,
+        now
+    ])?;
+
+    Ok(true)
 }
 
 /// Database statistics
@@ -245,6 +428,18 @@ pub struct ChecksumDbStats {
     pub total_entries: usize,
     pub fast_checksums: usize,
     pub cryptographic_checksums: usize,
+    pub generation: u64, // Sync runs that have stored checksums
+    pub size_bytes: u64, // Database file size (excluding WAL)
+    pub free_bytes: u64, // Unused space reclaimable by vacuum
+}
+
+/// Name a checksum type is stored under (the `checksum_type` column)
+pub fn checksum_type_name(checksum_type: ChecksumType) -> &'static str {
+    match checksum_type {
+        ChecksumType::None => "none",
+        ChecksumType::Fast => "fast",
+        ChecksumType::Cryptographic => "cryptographic",
+    }
 }
 
 /// Convert SystemTime to (seconds, nanoseconds) tuple
@@ -285,10 +480,11 @@ mod tests {
         let checksum = Checksum::Fast(vec![1, 2, 3, 4, 5, 6, 7, 8]);
 
         // Store checksum
-        db.store_checksum(&path, mtime, size, &checksum).unwrap();
+        db.store_checksum(&path, mtime, size, None, &checksum)
+            .unwrap();
 
         // Retrieve checksum
-        let retrieved = db.get_checksum(&path, mtime, size, "fast").unwrap();
+        let retrieved = db.get_checksum(&path, mtime, size, None, "fast").unwrap();
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap(), checksum);
 
@@ -310,10 +506,11 @@ mod tests {
         let checksum = Checksum::Fast(vec![1, 2, 3, 4]);
 
         // Store with mtime1
-        db.store_checksum(&path, mtime1, size, &checksum).unwrap();
+        db.store_checksum(&path, mtime1, size, None, &checksum)
+            .unwrap();
 
         // Try to retrieve with mtime2 (should miss)
-        let retrieved = db.get_checksum(&path, mtime2, size, "fast").unwrap();
+        let retrieved = db.get_checksum(&path, mtime2, size, None, "fast").unwrap();
         assert!(retrieved.is_none());
     }
 
@@ -329,10 +526,11 @@ mod tests {
         let checksum = Checksum::Fast(vec![1, 2, 3, 4]);
 
         // Store with size1
-        db.store_checksum(&path, mtime, size1, &checksum).unwrap();
+        db.store_checksum(&path, mtime, size1, None, &checksum)
+            .unwrap();
 
         // Try to retrieve with size2 (should miss)
-        let retrieved = db.get_checksum(&path, mtime, size2, "fast").unwrap();
+        let retrieved = db.get_checksum(&path, mtime, size2, None, "fast").unwrap();
         assert!(retrieved.is_none());
     }
 
@@ -347,7 +545,8 @@ mod tests {
         let checksum = Checksum::Fast(vec![1, 2, 3, 4]);
 
         // Store checksum
-        db.store_checksum(&path, mtime, size, &checksum).unwrap();
+        db.store_checksum(&path, mtime, size, None, &checksum)
+            .unwrap();
         assert_eq!(db.stats().unwrap().total_entries, 1);
 
         // Clear database
@@ -365,11 +564,11 @@ mod tests {
         let checksum = Checksum::Fast(vec![1, 2, 3, 4]);
 
         // Store checksums for 3 files
-        db.store_checksum(&PathBuf::from("file1.txt"), mtime, size, &checksum)
+        db.store_checksum(&PathBuf::from("file1.txt"), mtime, size, None, &checksum)
             .unwrap();
-        db.store_checksum(&PathBuf::from("file2.txt"), mtime, size, &checksum)
+        db.store_checksum(&PathBuf::from("file2.txt"), mtime, size, None, &checksum)
             .unwrap();
-        db.store_checksum(&PathBuf::from("file3.txt"), mtime, size, &checksum)
+        db.store_checksum(&PathBuf::from("file3.txt"), mtime, size, None, &checksum)
             .unwrap();
 
         assert_eq!(db.stats().unwrap().total_entries, 3);
@@ -395,17 +594,18 @@ mod tests {
         let checksum = Checksum::Cryptographic(vec![0xde, 0xad, 0xbe, 0xef]);
 
         // Store cryptographic checksum
-        db.store_checksum(&path, mtime, size, &checksum).unwrap();
+        db.store_checksum(&path, mtime, size, None, &checksum)
+            .unwrap();
 
         // Retrieve with correct type
         let retrieved = db
-            .get_checksum(&path, mtime, size, "cryptographic")
+            .get_checksum(&path, mtime, size, None, "cryptographic")
             .unwrap();
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap(), checksum);
 
         // Try to retrieve with wrong type (should miss)
-        let retrieved_wrong = db.get_checksum(&path, mtime, size, "fast").unwrap();
+        let retrieved_wrong = db.get_checksum(&path, mtime, size, None, "fast").unwrap();
         assert!(retrieved_wrong.is_none());
 
         // Verify stats
@@ -426,17 +626,184 @@ mod tests {
         let checksum2 = Checksum::Fast(vec![5, 6, 7, 8]);
 
         // Store initial checksum
-        db.store_checksum(&path, mtime, size, &checksum1).unwrap();
+        db.store_checksum(&path, mtime, size, None, &checksum1)
+            .unwrap();
         assert_eq!(db.stats().unwrap().total_entries, 1);
 
         // Update with new checksum (same path, mtime, size)
-        db.store_checksum(&path, mtime, size, &checksum2).unwrap();
+        db.store_checksum(&path, mtime, size, None, &checksum2)
+            .unwrap();
 
         // Should still have only 1 entry (replaced, not added)
         assert_eq!(db.stats().unwrap().total_entries, 1);
 
         // Should retrieve the new checksum
-        let retrieved = db.get_checksum(&path, mtime, size, "fast").unwrap();
+        let retrieved = db.get_checksum(&path, mtime, size, None, "fast").unwrap();
         assert_eq!(retrieved.unwrap(), checksum2);
     }
+
+    #[test]
+    fn test_wal_mode_enabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = ChecksumDatabase::open(temp_dir.path()).unwrap();
+
+        let mode: String = db
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode.to_lowercase(), "wal");
+    }
+
+    #[test]
+    fn test_store_checksums_batch() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = ChecksumDatabase::open(temp_dir.path()).unwrap();
+
+        let mtime = SystemTime::now();
+        let paths: Vec<PathBuf> = (0..ChecksumDatabase::BATCH_SIZE + 5)
+            .map(|i| PathBuf::from(format!("file{}.txt", i)))
+            .collect();
+
+        // Spans more than one transaction; None checksums are skipped
+        let entries = paths.iter().enumerate().map(|(i, p)| {
+            let checksum = if i == 0 {
+                Checksum::None
+            } else {
+                Checksum::Fast(vec![i as u8])
+            };
+            (p.as_path(), mtime, 1024, None, checksum)
+        });
+        let stored = db.store_checksums(entries).unwrap();
+
+        assert_eq!(stored, ChecksumDatabase::BATCH_SIZE + 4);
+        assert_eq!(db.stats().unwrap().total_entries, stored);
+        assert_eq!(
+            db.get_checksum(&paths[3], mtime, 1024, None, "fast")
+                .unwrap(),
+            Some(Checksum::Fast(vec![3]))
+        );
+    }
+
+    #[test]
+    fn test_vacuum_reclaims_space() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = ChecksumDatabase::open(temp_dir.path()).unwrap();
+
+        let mtime = SystemTime::now();
+        let paths: Vec<PathBuf> = (0..2000)
+            .map(|i| PathBuf::from(format!("dir/file{}.txt", i)))
+            .collect();
+        db.store_checksums(
+            paths
+                .iter()
+                .map(|p| (p.as_path(), mtime, 1024, None, Checksum::Fast(vec![0; 32]))),
+        )
+        .unwrap();
+        db.clear().unwrap();
+
+        let (before, after) = db.vacuum().unwrap();
+        assert!(after < before);
+        assert_eq!(db.stats().unwrap().free_bytes, 0);
+    }
+
+    #[test]
+    fn test_cache_miss_on_inode_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = ChecksumDatabase::open(temp_dir.path()).unwrap();
+
+        let path = PathBuf::from("test/file.txt");
+        let mtime = SystemTime::now();
+        let checksum = Checksum::Fast(vec![1, 2, 3, 4]);
+
+        db.store_checksum(&path, mtime, 1024, Some(42), &checksum)
+            .unwrap();
+
+        // Same size and mtime, but the file was replaced
+        assert_eq!(
+            db.get_checksum(&path, mtime, 1024, Some(42), "fast")
+                .unwrap(),
+            Some(checksum)
+        );
+        assert!(db
+            .get_checksum(&path, mtime, 1024, Some(43), "fast")
+            .unwrap()
+            .is_none());
+        assert!(db
+            .get_checksum(&path, mtime, 1024, None, "fast")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_generation_persists() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let mut db = ChecksumDatabase::open(temp_dir.path()).unwrap();
+            assert_eq!(db.generation(), 0);
+            assert_eq!(db.begin_generation().unwrap(), 1);
+            db.store_checksum(
+                Path::new("file.txt"),
+                SystemTime::now(),
+                1,
+                None,
+                &Checksum::Fast(vec![1]),
+            )
+            .unwrap();
+        }
+
+        // Opening alone (e.g. for stats) doesn't start a new generation
+        let mut db = ChecksumDatabase::open(temp_dir.path()).unwrap();
+        assert_eq!(db.stats().unwrap().generation, 1);
+        assert_eq!(db.begin_generation().unwrap(), 2);
+
+        let generation: i64 = db
+            .conn
+            .query_row(
+                "SELECT generation FROM checksums WHERE path = 'file.txt'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(generation, 1);
+    }
+
+    #[test]
+    fn test_old_schema_discarded() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Version 1 layout (no inode/generation columns)
+        {
+            let conn = Connection::open(ChecksumDatabase::db_path(temp_dir.path())).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE checksums (
+                    path TEXT PRIMARY KEY,
+                    mtime_secs INTEGER NOT NULL,
+                    mtime_nanos INTEGER NOT NULL,
+                    size INTEGER NOT NULL,
+                    checksum_type TEXT NOT NULL,
+                    checksum BLOB NOT NULL,
+                    updated_at INTEGER NOT NULL
+                );
+                INSERT INTO checksums VALUES ('old.txt', 0, 0, 1, 'fast', x'01', 0);
+                CREATE TABLE metadata (key TEXT PRIMARY KEY, value INTEGER NOT NULL);
+                INSERT INTO metadata VALUES ('schema_version', 1);",
+            )
+            .unwrap();
+        }
+
+        let db = ChecksumDatabase::open(temp_dir.path()).unwrap();
+        assert_eq!(db.stats().unwrap().total_entries, 0);
+
+        // New columns are usable
+        db.store_checksum(
+            Path::new("new.txt"),
+            SystemTime::now(),
+            1,
+            Some(7),
+            &Checksum::Fast(vec![1]),
+        )
+        .unwrap();
+        assert_eq!(db.stats().unwrap().total_entries, 1);
+    }
 }
@@ -0,0 +1,294 @@
+use super::checksumdb::ChecksumDatabase;
+use super::dircache::DirectoryCache;
+use super::lock::{self, LockInfo};
+use super::resume::STATE_FILE_NAME;
+use crate::error::{Result, SyncError};
+use crate::fs_util::resolve_path;
+use crate::temp_file::TEMP_SUFFIX;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// What a leftover found by `sy clean` was for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LeftoverKind {
+    /// `NAME.sy-tmp` a transfer was writing when it stopped
+    TempFile,
+    /// `.sy-lock` whose owning process is gone
+    StaleLock,
+    /// Resume state of an interrupted sync into the destination
+    ResumeState,
+    /// Control socket of a sync that is no longer running
+    ControlSocket,
+    /// Resume state, directory cache or checksum DB that versions before the
+    /// state directory kept in the destination, no longer read
+    LegacyState,
+}
+
+impl fmt::Display for LeftoverKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LeftoverKind::TempFile => "temp file",
+            LeftoverKind::StaleLock => "stale lock",
+            LeftoverKind::ResumeState => "resume state",
+            LeftoverKind::ControlSocket => "control socket",
+            LeftoverKind::LegacyState => "legacy state",
+        })
+    }
+}
+
+/// File left behind by a crashed or interrupted sync
+#[derive(Debug, Clone, Serialize)]
+pub struct Leftover {
+    pub path: PathBuf,
+    pub kind: LeftoverKind,
+    pub size: u64,
+}
+
+/// The part of a resume state file that names its destination
+#[derive(Deserialize)]
+struct ResumeDestination {
+    destination: PathBuf,
+}
+
+/// Find what crashed or interrupted syncs into `destination` left behind
+///
+/// Looks for temp files anywhere in the destination tree, a lock whose owner
+/// is gone, files older versions kept in the destination root, resume state
+/// in `state_base` recorded for this destination, and a control socket
+/// nothing serves anymore. Refuses a destination a running sync may hold,
+/// since its temp files are still being written.
+pub fn find(destination: &Path, state_base: &Path, control_socket: &Path) -> Result<Vec<Leftover>> {
+    let mut leftovers = Vec::new();
+
+    let lock_file = lock::lock_path(destination);
+    if lock_file.exists() {
+        let holder: Option<LockInfo> = std::fs::read(&lock_file)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok());
+        match holder {
+            Some(holder) if holder.is_stale() => {
+                push(&mut leftovers, lock_file, LeftoverKind::StaleLock)
+            }
+            holder => {
+                return Err(SyncError::DestinationLocked {
+                    path: destination.to_path_buf(),
+                    holder: holder
+                        .map(|h| h.describe())
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    lock_file,
+                });
+            }
+        }
+    }
+
+    for entry in walkdir::WalkDir::new(destination)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        if entry.file_type().is_file() && entry.file_name().to_string_lossy().ends_with(TEMP_SUFFIX)
+        {
+            push(&mut leftovers, entry.into_path(), LeftoverKind::TempFile);
+        }
+    }
+
+    for name in [
+        STATE_FILE_NAME.to_string(),
+        format!("{}.tmp", STATE_FILE_NAME),
+        DirectoryCache::CACHE_FILENAME.to_string(),
+        ChecksumDatabase::DB_FILE.to_string(),
+        format!("{}-wal", ChecksumDatabase::DB_FILE),
+        format!("{}-shm", ChecksumDatabase::DB_FILE),
+    ] {
+        let path = destination.join(name);
+        if path.is_file() {
+            push(&mut leftovers, path, LeftoverKind::LegacyState);
+        }
+    }
+
+    if let Ok(entries) = std::fs::read_dir(state_base) {
+        let target = absolute(destination);
+        let mut state_dirs: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        state_dirs.sort();
+        for dir in state_dirs {
+            let state_file = dir.join(STATE_FILE_NAME);
+            let recorded = std::fs::read(&state_file)
+                .ok()
+                .and_then(|data| serde_json::from_slice::<ResumeDestination>(&data).ok());
+            if recorded.is_some_and(|r| absolute(&r.destination) == target) {
+                push(&mut leftovers, state_file, LeftoverKind::ResumeState);
+                let temp = dir.join(format!("{}.tmp", STATE_FILE_NAME));
+                if temp.is_file() {
+                    push(&mut leftovers, temp, LeftoverKind::ResumeState);
+                }
+            }
+        }
+    }
+
+    if control_socket.exists() && !is_served(control_socket) {
+        push(
+            &mut leftovers,
+            control_socket.to_path_buf(),
+            LeftoverKind::ControlSocket,
+        );
+    }
+
+    Ok(leftovers)
+}
+
+/// Remove leftovers, returning the ones that couldn't be removed
+pub fn remove(leftovers: &[Leftover]) -> Vec<(PathBuf, std::io::Error)> {
+    leftovers
+        .iter()
+        .filter_map(|leftover| match std::fs::remove_file(&leftover.path) {
+            Ok(()) => None,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => Some((leftover.path.clone(), e)),
+        })
+        .collect()
+}
+
+fn push(leftovers: &mut Vec<Leftover>, path: PathBuf, kind: LeftoverKind) {
+    let size = std::fs::symlink_metadata(&path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+    leftovers.push(Leftover { path, kind, size });
+}
+
+/// `path` made absolute and resolved, to compare destinations recorded from
+/// different working directories
+fn absolute(path: &Path) -> PathBuf {
+    let path = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    };
+    resolve_path(&path, true)
+}
+
+/// Whether a running sync still answers on `socket`
+#[cfg(unix)]
+fn is_served(socket: &Path) -> bool {
+    std::os::unix::net::UnixStream::connect(socket).is_ok()
+}
+
+#[cfg(not(unix))]
+fn is_served(_socket: &Path) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn kinds(leftovers: &[Leftover]) -> Vec<(PathBuf, LeftoverKind)> {
+        leftovers.iter().map(|l| (l.path.clone(), l.kind)).collect()
+    }
+
+    #[test]
+    fn test_finds_temp_files_and_legacy_state() {
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(dest.join("a/b")).unwrap();
+        fs::write(dest.join("keep.txt"), "data").unwrap();
+        fs::write(dest.join("a/b/big.iso.sy-tmp"), "partial").unwrap();
+        fs::write(dest.join(".sy-dir-cache.json"), "{}").unwrap();
+        // Only the destination root held legacy state
+        fs::write(dest.join("a/.sy-dir-cache.json"), "{}").unwrap();
+
+        let leftovers = find(
+            &dest,
+            &temp.path().join("state"),
+            &temp.path().join("x.sock"),
+        )
+        .unwrap();
+        let mut found = kinds(&leftovers);
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            found,
+            vec![
+                (dest.join(".sy-dir-cache.json"), LeftoverKind::LegacyState),
+                (dest.join("a/b/big.iso.sy-tmp"), LeftoverKind::TempFile),
+            ]
+        );
+        assert_eq!(
+            leftovers
+                .iter()
+                .find(|l| l.kind == LeftoverKind::TempFile)
+                .unwrap()
+                .size,
+            7
+        );
+
+        assert!(remove(&leftovers).is_empty());
+        assert!(!dest.join("a/b/big.iso.sy-tmp").exists());
+        assert!(dest.join("keep.txt").exists());
+        assert!(dest.join("a/.sy-dir-cache.json").exists());
+    }
+
+    #[test]
+    fn test_resume_state_matched_by_destination() {
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("dest");
+        let other = temp.path().join("other");
+        fs::create_dir_all(&dest).unwrap();
+        let state = temp.path().join("state");
+        for (pair, destination) in [("pair1", &dest), ("pair2", &other)] {
+            fs::create_dir_all(state.join(pair)).unwrap();
+            fs::write(
+                state.join(pair).join(STATE_FILE_NAME),
+                serde_json::json!({ "destination": destination }).to_string(),
+            )
+            .unwrap();
+        }
+        fs::write(
+            state.join("pair1").join(DirectoryCache::CACHE_FILENAME),
+            "{}",
+        )
+        .unwrap();
+
+        let leftovers = find(&dest, &state, &temp.path().join("x.sock")).unwrap();
+        assert_eq!(
+            kinds(&leftovers),
+            vec![(
+                state.join("pair1").join(STATE_FILE_NAME),
+                LeftoverKind::ResumeState
+            )]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_locks() {
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(&dest).unwrap();
+        let state = temp.path().join("state");
+        let socket = temp.path().join("x.sock");
+
+        // A running sync (this process) holds the destination
+        let owner = LockInfo::current();
+        assert!(lock::try_lock(&dest, &owner).unwrap().is_none());
+        assert!(matches!(
+            find(&dest, &state, &socket),
+            Err(SyncError::DestinationLocked { .. })
+        ));
+        assert!(lock::unlock(&dest, &owner).unwrap());
+
+        // Its owner has exited
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        let crashed = LockInfo {
+            pid: dead_pid,
+            ..LockInfo::current()
+        };
+        assert!(lock::try_lock(&dest, &crashed).unwrap().is_none());
+        assert_eq!(
+            kinds(&find(&dest, &state, &socket).unwrap()),
+            vec![(lock::lock_path(&dest), LeftoverKind::StaleLock)]
+        );
+    }
+}
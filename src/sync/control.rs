@@ -0,0 +1,320 @@
+use crate::error::{Result, SyncError};
+use crate::path::SyncPath;
+use crate::state;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use tokio::sync::Notify;
+
+/// Command sent to a running sync over its control socket (`sy ctl`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlCommand {
+    /// Stop starting new transfers (running ones finish)
+    Pause,
+
+    /// Continue a paused sync
+    Resume,
+
+    /// Show whether the sync is paused and how far it got
+    Status,
+}
+
+/// State of a running sync, returned for every control command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlStatus {
+    pub pid: u32,
+    pub paused: bool,
+    pub files_processed: usize,
+    pub files_total: usize,
+    pub bytes_transferred: u64,
+}
+
+/// Pause flag and progress counters shared between a sync and its control socket
+#[derive(Debug, Default)]
+pub struct SyncControl {
+    paused: AtomicBool,
+    resumed: Notify,
+    files_processed: AtomicUsize,
+    files_total: AtomicUsize,
+    bytes_transferred: AtomicU64,
+}
+
+impl SyncControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.resumed.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the sync is no longer paused
+    pub async fn wait_while_paused(&self) {
+        loop {
+            // Register before checking so a resume in between isn't missed
+            let resumed = self.resumed.notified();
+            if !self.is_paused() {
+                return;
+            }
+            resumed.await;
+        }
+    }
+
+    /// Reset progress counters for a new sync pass of `total` tasks
+    pub fn begin(&self, total: usize) {
+        self.files_total.store(total, Ordering::SeqCst);
+        self.files_processed.store(0, Ordering::SeqCst);
+        self.bytes_transferred.store(0, Ordering::SeqCst);
+    }
+
+    /// Record a finished task
+    pub fn record_processed(&self, bytes: u64) {
+        self.files_processed.fetch_add(1, Ordering::SeqCst);
+        self.bytes_transferred.fetch_add(bytes, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> ControlStatus {
+        ControlStatus {
+            pid: std::process::id(),
+            paused: self.is_paused(),
+            files_processed: self.files_processed.load(Ordering::SeqCst),
+            files_total: self.files_total.load(Ordering::SeqCst),
+            bytes_transferred: self.bytes_transferred.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Apply a control command and return the resulting status
+    pub fn apply(&self, command: ControlCommand) -> ControlStatus {
+        match command {
+            ControlCommand::Pause => self.pause(),
+            ControlCommand::Resume => self.resume(),
+            ControlCommand::Status => {}
+        }
+        self.status()
+    }
+}
+
+/// Control socket path for syncs into `destination`
+///
+/// Keyed on the destination alone so `sy ctl` only needs the destination;
+/// the socket lives in `<state base>/control/`.
+pub fn socket_path(state_dir_override: Option<&Path>, destination: &SyncPath) -> Result<PathBuf> {
+    let hash = blake3::hash(state::path_key(destination).as_bytes()).to_hex();
+    Ok(state::base_dir(state_dir_override)?
+        .join("control")
+        .join(format!("{}.sock", &hash[..16])))
+}
+
+/// Control socket served for the duration of a sync, removed when dropped
+#[cfg(unix)]
+pub struct ControlServer {
+    path: PathBuf,
+    task: tokio::task::JoinHandle<()>,
+}
+
+#[cfg(unix)]
+impl ControlServer {
+    /// Listen for control commands on `path`
+    ///
+    /// Fails if another running sync already serves the same socket.
+    pub fn start(control: std::sync::Arc<SyncControl>, path: PathBuf) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err(SyncError::Io(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    format!(
+                        "Another sync to this destination is serving {}",
+                        path.display()
+                    ),
+                )));
+            }
+            // Left behind by a sync that crashed
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("Control socket accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let control = std::sync::Arc::clone(&control);
+                tokio::spawn(async move {
+                    if let Err(e) = handle_client(stream, &control).await {
+                        tracing::debug!("Control client error: {}", e);
+                    }
+                });
+            }
+        });
+
+        tracing::debug!("Serving control socket at {}", path.display());
+        Ok(Self { path, task })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Read one JSON command line and reply with the status as a JSON line
+#[cfg(unix)]
+async fn handle_client(stream: tokio::net::UnixStream, control: &SyncControl) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+
+    let command: ControlCommand = serde_json::from_str(line.trim()).map_err(|e| {
+        SyncError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid control command: {}", e),
+        ))
+    })?;
+    tracing::info!("Control command: {:?}", command);
+
+    let mut reply = serde_json::to_vec(&control.apply(command))
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?;
+    reply.push(b'\n');
+    writer.write_all(&reply).await?;
+    Ok(())
+}
+
+/// Send a command to the sync serving `path` and return its status
+#[cfg(unix)]
+pub fn send(path: &Path, command: ControlCommand) -> Result<ControlStatus> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused => {
+            SyncError::Config(format!(
+                "No running sync found for this destination (no control socket at {})",
+                path.display()
+            ))
+        }
+        _ => SyncError::Io(e),
+    })?;
+
+    let mut request = serde_json::to_vec(&command)
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?;
+    request.push(b'\n');
+    stream.write_all(&request)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    serde_json::from_str(line.trim()).map_err(|e| {
+        SyncError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid control reply: {}", e),
+        ))
+    })
+}
+
+#[cfg(not(unix))]
+pub fn send(_path: &Path, _command: ControlCommand) -> Result<ControlStatus> {
+    Err(SyncError::Config(
+        "sy ctl is only supported on Unix (requires Unix domain sockets)".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_wait_while_paused() {
+        let control = Arc::new(SyncControl::new());
+        control.wait_while_paused().await;
+
+        control.pause();
+        let waiter = tokio::spawn({
+            let control = Arc::clone(&control);
+            async move { control.wait_while_paused().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        control.resume();
+        tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_socket_path_keyed_on_destination() {
+        let base = Path::new("/state");
+        let a = SyncPath::Local(PathBuf::from("/a"));
+        let b = SyncPath::Local(PathBuf::from("/b"));
+
+        let path = socket_path(Some(base), &a).unwrap();
+        assert!(path.starts_with("/state/control"));
+        assert_eq!(path, socket_path(Some(base), &a).unwrap());
+        assert_ne!(path, socket_path(Some(base), &b).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_control_socket_roundtrip() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("control").join("test.sock");
+        let control = Arc::new(SyncControl::new());
+        control.begin(10);
+        control.record_processed(100);
+
+        let server = ControlServer::start(Arc::clone(&control), path.clone()).unwrap();
+        assert!(ControlServer::start(Arc::clone(&control), path.clone()).is_err());
+
+        let send_path = path.clone();
+        let status = tokio::task::spawn_blocking(move || send(&send_path, ControlCommand::Pause))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(status.paused);
+        assert_eq!(status.files_processed, 1);
+        assert_eq!(status.files_total, 10);
+        assert_eq!(status.bytes_transferred, 100);
+        assert!(control.is_paused());
+
+        let send_path = path.clone();
+        let status = tokio::task::spawn_blocking(move || send(&send_path, ControlCommand::Resume))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!status.paused);
+
+        // Socket is removed with the server
+        drop(server);
+        assert!(!path.exists());
+        assert!(matches!(
+            send(&path, ControlCommand::Status),
+            Err(SyncError::Config(_))
+        ));
+    }
+}
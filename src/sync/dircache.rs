@@ -1,10 +1,83 @@
 use crate::error::{Result, SyncError};
-use crate::sync::scanner::FileEntry;
+use crate::sync::output::{self, WarningCategory};
+use crate::sync::scanner::{FileEntry, Scanner};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// Get the status change time (ctime) of a file
+///
+/// ctime is updated by any metadata change (chmod, chown, xattrs, rename) and
+/// can't be set from userspace, so it catches changes that preserve mtime.
+#[cfg(unix)]
+fn change_time(metadata: &std::fs::Metadata) -> Option<SystemTime> {
+    let secs = u64::try_from(metadata.ctime()).ok()?;
+    let nanos = u32::try_from(metadata.ctime_nsec()).ok()?;
+    Some(SystemTime::UNIX_EPOCH + std::time::Duration::new(secs, nanos))
+}
+
+/// Non-Unix platforms have no ctime equivalent
+#[cfg(not(unix))]
+fn change_time(_metadata: &std::fs::Metadata) -> Option<SystemTime> {
+    None
+}
+
+/// Inode number of a file, used to detect files replaced in place
+#[cfg(unix)]
+pub(super) fn inode(metadata: &std::fs::Metadata) -> Option<u64> {
+    Some(metadata.ino())
+}
+
+/// Non-Unix platforms don't expose inode numbers
+#[cfg(not(unix))]
+pub(super) fn inode(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Identity of a directory at the time it was scanned
+///
+/// mtime changes when entries are added, removed or renamed; ctime also
+/// catches metadata-only changes; the inode catches a directory that was
+/// replaced by another one with the same name and mtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirStamp {
+    pub mtime: SystemTime,
+    #[serde(default)]
+    pub ctime: Option<SystemTime>,
+    #[serde(default)]
+    pub inode: Option<u64>,
+}
+
+impl DirStamp {
+    /// Stamp with only an mtime (no ctime/inode checks)
+    pub fn new(mtime: SystemTime) -> Self {
+        Self {
+            mtime,
+            ctime: None,
+            inode: None,
+        }
+    }
+
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Option<Self> {
+        Some(Self {
+            mtime: metadata.modified().ok()?,
+            ctime: change_time(metadata),
+            inode: inode(metadata),
+        })
+    }
+
+    /// Read the current stamp of a directory, or None if it can't be read
+    pub fn read(path: &Path) -> Option<Self> {
+        std::fs::metadata(path)
+            .ok()
+            .and_then(|m| Self::from_metadata(&m))
+    }
+}
+
 /// Cached file metadata for incremental scanning
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedFile {
@@ -16,19 +89,72 @@ pub struct CachedFile {
     pub modified: SystemTime,
     /// Whether this is a directory
     pub is_dir: bool,
+    /// Status change time (Unix only), used for per-entry validation
+    #[serde(default)]
+    pub ctime: Option<SystemTime>,
+    /// Inode number (Unix only), used for per-entry validation and hardlinks
+    #[serde(default)]
+    pub inode: Option<u64>,
+    /// Number of hard links to this file
+    #[serde(default = "default_nlink")]
+    pub nlink: u64,
+    /// Whether this is a symlink
+    #[serde(default)]
+    pub is_symlink: bool,
+    /// Symlink target (if symlink)
+    #[serde(default)]
+    pub symlink_target: Option<PathBuf>,
+}
+
+fn default_nlink() -> u64 {
+    1
 }
 
 impl CachedFile {
     /// Create from FileEntry
+    ///
+    /// The ctime isn't part of FileEntry, so it is read from the file itself.
     pub fn from_file_entry(file: &FileEntry) -> Self {
+        let ctime = std::fs::symlink_metadata(&file.path)
+            .ok()
+            .and_then(|m| change_time(&m));
+
         Self {
             path: file.relative_path.clone(),
             size: file.size,
             modified: file.modified,
             is_dir: file.is_dir,
+            ctime,
+            inode: file.inode,
+            nlink: file.nlink,
+            is_symlink: file.is_symlink,
+            symlink_target: file.symlink_target.clone(),
         }
     }
 
+    /// Check that the entry on disk still matches what was cached
+    ///
+    /// Compares type, size, mtime, ctime and inode, so replaced files and
+    /// metadata-only changes invalidate the entry even when mtime is preserved.
+    pub fn is_current(&self, source_root: &Path) -> bool {
+        let metadata = match std::fs::symlink_metadata(source_root.join(&self.path)) {
+            Ok(m) => m,
+            Err(_) => return false,
+        };
+
+        if metadata.is_dir() != self.is_dir || metadata.is_symlink() != self.is_symlink {
+            return false;
+        }
+        if !self.is_dir && metadata.len() != self.size {
+            return false;
+        }
+        if metadata.modified().ok() != Some(self.modified) {
+            return false;
+        }
+
+        change_time(&metadata) == self.ctime && inode(&metadata) == self.inode
+    }
+
     /// Convert back to FileEntry
     /// Note: Some fields have default values for simplicity
     pub fn to_file_entry(&self, source_root: &Path) -> FileEntry {
@@ -38,24 +164,28 @@ impl CachedFile {
             size: self.size,
             modified: self.modified,
             is_dir: self.is_dir,
-            is_symlink: false, // Not cached
-            symlink_target: None,
+            is_symlink: self.is_symlink,
+            symlink_target: self.symlink_target.clone(),
             is_sparse: false,
             allocated_size: self.size,
-            xattrs: None,
-            inode: None,
-            nlink: 1,
-            acls: None,
+            xattrs: None, // Not cached
+            inode: self.inode,
+            nlink: self.nlink,
+            acls: None, // Not cached
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None, // Not cached
         }
     }
 }
 
-/// Directory modification time cache for incremental scanning
+/// Directory cache for incremental scanning
 ///
-/// Stores the last known mtime of directories and cached file metadata to enable
-/// incremental scanning. When a directory's mtime hasn't changed, we can use the
-/// cached file list instead of re-scanning, dramatically speeding up re-syncs.
+/// Stores the last known stamp (mtime, ctime, inode) of directories and cached file
+/// metadata to enable incremental scanning. When no directory stamp has changed and
+/// every cached entry still validates, we can use the cached file list instead of
+/// re-scanning, dramatically speeding up re-syncs.
 ///
 /// # Performance Impact
 /// - Initial sync: No overhead (cache is empty)
@@ -63,25 +193,43 @@ impl CachedFile {
 /// - Re-sync with changes: Only scans changed directories
 ///
 /// # Cache File Format
-/// - Location: `<dest>/.sy-dir-cache.json`
+/// - Location: `<state dir>/.sy-dir-cache.json` (see `crate::state`)
 /// - Format: JSON (human-readable, debuggable)
 /// - Size: ~200 bytes per file (includes full metadata)
 ///
 /// # Invalidation
-/// - Directory mtime changed → re-scan that directory
-/// - Parent directory changed → re-scan subtree
+/// - Directory mtime, ctime or inode changed → re-scan
+/// - Any cached entry changed (size, mtime, ctime, inode, type) → re-scan
 /// - Cache file corrupted → full re-scan (safe fallback)
+///
+/// # Scan journal (`--scan-journal`)
+/// `journal_entries` re-reads only the directories whose stamp changed, that
+/// watch mode marked dirty, or that hold a file that changed. A file edited in
+/// place doesn't change its directory's stamp, so the cached files of other
+/// directories are still stat'ed, just not listed again. Each directory's
+/// entries carry a hash, so a cache file that doesn't match what was recorded
+/// means a full scan instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirectoryCache {
-    /// Map of directory path (relative to sync root) to last known mtime
+    /// Map of directory path (relative to sync root) to last known stamp
     #[serde(rename = "directories")]
-    dir_entries: HashMap<PathBuf, SystemTime>,
+    dir_entries: HashMap<PathBuf, DirStamp>,
 
     /// Cached file metadata keyed by directory path
     /// Maps directory -> list of files in that directory
     #[serde(rename = "files", default)]
     file_entries: HashMap<PathBuf, Vec<CachedFile>>,
 
+    /// Hash of each directory's cached entries, checked before the scan
+    /// journal trusts them
+    #[serde(rename = "hashes", default)]
+    entry_hashes: HashMap<PathBuf, u64>,
+
+    /// Directories watch mode saw change since the cache was written; the
+    /// scan journal re-reads them even if their stamp is the same
+    #[serde(default)]
+    dirty: BTreeSet<PathBuf>,
+
     /// Version number for cache format changes
     #[serde(default = "default_version")]
     version: u32,
@@ -96,34 +244,40 @@ fn default_version() -> u32 {
 }
 
 impl DirectoryCache {
-    const CURRENT_VERSION: u32 = 2; // Bumped for new cache format
-    const CACHE_FILENAME: &'static str = ".sy-dir-cache.json";
+    const CURRENT_VERSION: u32 = 3; // Bumped for ctime/inode directory stamps
+    pub const CACHE_FILENAME: &'static str = ".sy-dir-cache.json";
 
     /// Create a new empty cache
     pub fn new() -> Self {
         Self {
             dir_entries: HashMap::new(),
             file_entries: HashMap::new(),
+            entry_hashes: HashMap::new(),
+            dirty: BTreeSet::new(),
             version: Self::CURRENT_VERSION,
             last_updated: SystemTime::now(),
         }
     }
 
-    /// Load cache from destination directory
+    /// Load cache from the sync's state directory
     ///
     /// Returns empty cache if file doesn't exist or is corrupted.
-    pub fn load(dest_root: &Path) -> Self {
-        let cache_path = dest_root.join(Self::CACHE_FILENAME);
+    pub fn load(state_dir: &Path) -> Self {
+        let cache_path = state_dir.join(Self::CACHE_FILENAME);
 
         match std::fs::read_to_string(&cache_path) {
             Ok(content) => match serde_json::from_str::<Self>(&content) {
                 Ok(mut cache) => {
                     // Check version compatibility
                     if cache.version != Self::CURRENT_VERSION {
-                        tracing::warn!(
-                            "Directory cache version mismatch (found {}, expected {}). Using empty cache.",
-                            cache.version,
-                            Self::CURRENT_VERSION
+                        output::warn(
+                            WarningCategory::Cache,
+                            Some(cache_path.as_path()),
+                            format!(
+                                "Directory cache version mismatch (found {}, expected {}). Using empty cache.",
+                                cache.version,
+                                Self::CURRENT_VERSION
+                            ),
                         );
                         return Self::new();
                     }
@@ -137,7 +291,11 @@ impl DirectoryCache {
                     cache
                 }
                 Err(e) => {
-                    tracing::warn!("Failed to parse directory cache: {}. Using empty cache.", e);
+                    output::warn(
+                        WarningCategory::Cache,
+                        Some(cache_path.as_path()),
+                        format!("Failed to parse directory cache: {}. Using empty cache.", e),
+                    );
                     Self::new()
                 }
             },
@@ -146,15 +304,20 @@ impl DirectoryCache {
                 Self::new()
             }
             Err(e) => {
-                tracing::warn!("Failed to read directory cache: {}. Using empty cache.", e);
+                output::warn(
+                    WarningCategory::Cache,
+                    Some(cache_path.as_path()),
+                    format!("Failed to read directory cache: {}. Using empty cache.", e),
+                );
                 Self::new()
             }
         }
     }
 
-    /// Save cache to destination directory
-    pub fn save(&self, dest_root: &Path) -> Result<()> {
-        let cache_path = dest_root.join(Self::CACHE_FILENAME);
+    /// Save cache to the sync's state directory
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        let cache_path = state_dir.join(Self::CACHE_FILENAME);
+        std::fs::create_dir_all(state_dir)?;
 
         let content = serde_json::to_string_pretty(self).map_err(|e| {
             SyncError::Io(std::io::Error::other(format!(
@@ -181,9 +344,9 @@ impl DirectoryCache {
         Ok(())
     }
 
-    /// Delete cache file from destination directory
-    pub fn delete(dest_root: &Path) -> Result<()> {
-        let cache_path = dest_root.join(Self::CACHE_FILENAME);
+    /// Delete cache file from the sync's state directory
+    pub fn delete(state_dir: &Path) -> Result<()> {
+        let cache_path = state_dir.join(Self::CACHE_FILENAME);
 
         if cache_path.exists() {
             std::fs::remove_file(&cache_path).map_err(|e| {
@@ -203,17 +366,30 @@ impl DirectoryCache {
     /// Returns true if:
     /// - Directory not in cache (first scan)
     /// - Directory mtime has changed (was modified)
-    /// - Parent directory was modified (might affect this directory)
+    /// - Directory ctime has changed (metadata-only change, e.g. chmod/chown)
+    /// - Directory inode has changed (replaced by another directory)
     ///
-    /// Returns false if directory mtime matches cache (can skip scan)
-    pub fn needs_rescan(&self, dir_path: &Path, current_mtime: SystemTime) -> bool {
+    /// Returns false if the directory stamp matches cache (can skip scan)
+    pub fn needs_rescan(&self, dir_path: &Path, current: &DirStamp) -> bool {
         match self.dir_entries.get(dir_path) {
-            Some(&cached_mtime) => {
+            Some(cached) => {
                 // Compare mtimes (with 1-second tolerance for filesystem granularity)
-                match current_mtime.duration_since(cached_mtime) {
+                let mtime_changed = match current.mtime.duration_since(cached.mtime) {
                     Ok(duration) => duration.as_secs() > 1,
                     Err(e) => e.duration().as_secs() > 1,
-                }
+                };
+
+                // ctime and inode are only compared when both sides recorded them
+                let ctime_changed = matches!(
+                    (cached.ctime, current.ctime),
+                    (Some(a), Some(b)) if a != b
+                );
+                let inode_changed = matches!(
+                    (cached.inode, current.inode),
+                    (Some(a), Some(b)) if a != b
+                );
+
+                mtime_changed || ctime_changed || inode_changed
             }
             None => {
                 // Not in cache - need to scan
@@ -223,34 +399,254 @@ impl DirectoryCache {
     }
 
     /// Update cache entry for a directory
-    pub fn update(&mut self, dir_path: PathBuf, mtime: SystemTime) {
-        self.dir_entries.insert(dir_path, mtime);
+    pub fn update(&mut self, dir_path: PathBuf, stamp: DirStamp) {
+        self.dir_entries.insert(dir_path, stamp);
+    }
+
+    /// Get all cached entries for a source tree, if the cache is still valid
+    ///
+    /// Returns None (full re-scan needed) unless the root and every cached
+    /// directory have unchanged stamps and every cached entry validates
+    /// against the filesystem.
+    pub fn cached_entries(&self, source_root: &Path) -> Option<Vec<FileEntry>> {
+        let root_stamp = DirStamp::read(source_root)?;
+        if self.needs_rescan(Path::new("."), &root_stamp) {
+            return None;
+        }
+
+        for dir_path in self.dir_entries.keys() {
+            let full_path = if dir_path == Path::new(".") {
+                source_root.to_path_buf()
+            } else {
+                source_root.join(dir_path)
+            };
+            let stamp = DirStamp::read(&full_path)?;
+            if self.needs_rescan(dir_path, &stamp) {
+                tracing::debug!("Directory changed since cached: {}", dir_path.display());
+                return None;
+            }
+        }
+
+        let mut entries = Vec::new();
+        for file in self.file_entries.values().flatten() {
+            // A directory without a stamp could have gained entries unnoticed
+            if file.is_dir && !self.dir_entries.contains_key(&file.path) {
+                return None;
+            }
+            if !file.is_current(source_root) {
+                tracing::debug!("Entry changed since cached: {}", file.path.display());
+                return None;
+            }
+            entries.push(file.to_file_entry(source_root));
+        }
+
+        Some(entries)
     }
 
     /// Get cached files for a directory
     /// Returns None if directory not in cache or needs rescan
+    #[allow(dead_code)] // Public API for per-directory lookups
     pub fn get_cached_files(&self, dir_path: &Path) -> Option<&Vec<CachedFile>> {
         self.file_entries.get(dir_path)
     }
 
     /// Store cached files for a directory
     pub fn cache_files(&mut self, dir_path: PathBuf, files: Vec<CachedFile>) {
+        self.entry_hashes
+            .insert(dir_path.clone(), entries_hash(&files));
         self.file_entries.insert(dir_path, files);
     }
 
+    /// Add scanned entries: directories get their stamp read and an entry
+    /// list of their own (starting with themselves), files go in their
+    /// parent's list
+    pub fn record_scan(&mut self, files: &[FileEntry]) {
+        let mut files_by_dir: HashMap<PathBuf, Vec<CachedFile>> = HashMap::new();
+        for file in files {
+            let dir_path = if file.is_dir {
+                if let Some(stamp) = DirStamp::read(&file.path) {
+                    self.update(file.relative_path.clone(), stamp);
+                }
+                file.relative_path.clone()
+            } else {
+                parent_dir(&file.relative_path)
+            };
+            files_by_dir
+                .entry(dir_path)
+                .or_default()
+                .push(CachedFile::from_file_entry(file));
+        }
+
+        for (dir_path, mut files) in files_by_dir {
+            if let Some(cached) = self.file_entries.remove(&dir_path) {
+                files.splice(0..0, cached);
+            }
+            self.cache_files(dir_path, files);
+        }
+    }
+
+    /// Mark directories (relative to the source root) as changed, for the
+    /// next `journal_entries` to re-read
+    pub fn mark_dirty(&mut self, dirs: impl IntoIterator<Item = PathBuf>) {
+        self.dirty.extend(dirs);
+    }
+
+    /// Get all entries of a source tree, re-reading only the directories
+    /// that changed since they were cached (the scan journal)
+    ///
+    /// Changed directories are those whose stamp moved, those marked dirty and
+    /// those with a cached file that isn't current, since writing a file in
+    /// place leaves its directory's stamp alone. Each is re-read one level
+    /// deep: its files are refreshed, new subdirectories scanned in full and
+    /// removed ones forgotten. Entries of unchanged directories are returned
+    /// as cached.
+    ///
+    /// Returns None (full re-scan needed) for an empty cache, entries that
+    /// don't match their recorded hash, or a directory that can't be read.
+    pub fn journal_entries(&mut self, source_root: &Path) -> Option<Vec<FileEntry>> {
+        if self.dir_entries.is_empty() {
+            return None;
+        }
+        for (dir_path, files) in &self.file_entries {
+            if self.entry_hashes.get(dir_path) != Some(&entries_hash(files)) {
+                tracing::debug!("Scan journal out of step at {}", dir_path.display());
+                return None;
+            }
+        }
+        DirStamp::read(source_root)?;
+
+        let mut changed: Vec<PathBuf> = self
+            .dir_entries
+            .keys()
+            .filter(|dir_path| {
+                self.dirty.contains(*dir_path)
+                    || DirStamp::read(&full_path(source_root, dir_path))
+                        .is_none_or(|stamp| self.needs_rescan(dir_path, &stamp))
+                    || self
+                        .file_entries
+                        .get(*dir_path)
+                        .into_iter()
+                        .flatten()
+                        .any(|file| !file.is_dir && !file.is_current(source_root))
+            })
+            .cloned()
+            .collect();
+        // Parents first, so a removed directory is forgotten before its turn
+        changed.sort_by_key(|dir_path| depth(dir_path));
+
+        for dir_path in &changed {
+            if self.dir_entries.contains_key(dir_path) {
+                self.refresh_dir(source_root, dir_path)?;
+            }
+        }
+        tracing::debug!(
+            "Scan journal re-read {} of {} directories",
+            changed.len(),
+            self.dir_entries.len()
+        );
+        self.dirty.clear();
+
+        let mut entries: Vec<FileEntry> = self
+            .file_entries
+            .values()
+            .flatten()
+            .map(|file| file.to_file_entry(source_root))
+            .collect();
+        // Directories before their contents, like a scan
+        entries.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        Some(entries)
+    }
+
+    /// Re-read one directory of the journal, one level deep
+    fn refresh_dir(&mut self, source_root: &Path, dir_path: &Path) -> Option<()> {
+        let dir = full_path(source_root, dir_path);
+        let Some(stamp) = DirStamp::read(&dir) else {
+            self.forget(dir_path);
+            return Some(());
+        };
+        let children = Scanner::new(&dir).max_depth(Some(1)).scan().ok()?;
+
+        // The directory's own entry comes first in its list
+        let mut files: Vec<CachedFile> = self
+            .file_entries
+            .get(dir_path)
+            .into_iter()
+            .flatten()
+            .filter(|file| file.is_dir && file.path == dir_path)
+            .map(|own| CachedFile {
+                modified: stamp.mtime,
+                ctime: stamp.ctime,
+                inode: stamp.inode,
+                ..own.clone()
+            })
+            .collect();
+        let mut new_dirs = Vec::new();
+        let mut subdirs = BTreeSet::new();
+        for mut child in children {
+            child.relative_path = full_path(dir_path, &child.relative_path);
+            if !child.is_dir {
+                files.push(CachedFile::from_file_entry(&child));
+            } else if self.dir_entries.contains_key(&child.relative_path) {
+                subdirs.insert(child.relative_path);
+            } else {
+                new_dirs.push(child);
+            }
+        }
+
+        let gone: Vec<PathBuf> = self
+            .dir_entries
+            .keys()
+            .filter(|path| {
+                path.as_path() != dir_path
+                    && parent_dir(path) == dir_path
+                    && !subdirs.contains(*path)
+            })
+            .cloned()
+            .collect();
+        for path in gone {
+            self.forget(&path);
+        }
+
+        for new_dir in new_dirs {
+            let mut tree = Scanner::new(&new_dir.path).scan().ok()?;
+            for entry in &mut tree {
+                entry.relative_path = new_dir.relative_path.join(&entry.relative_path);
+            }
+            tree.insert(0, new_dir);
+            self.record_scan(&tree);
+        }
+
+        self.update(dir_path.to_path_buf(), stamp);
+        self.cache_files(dir_path.to_path_buf(), files);
+        Some(())
+    }
+
+    /// Drop a directory and everything below it
+    fn forget(&mut self, dir_path: &Path) {
+        self.dir_entries
+            .retain(|path, _| !path.starts_with(dir_path));
+        self.file_entries
+            .retain(|path, _| !path.starts_with(dir_path));
+        self.entry_hashes
+            .retain(|path, _| !path.starts_with(dir_path));
+        self.dirty.retain(|path| !path.starts_with(dir_path));
+    }
+
     /// Remove a directory from cache (e.g., after deletion)
     #[allow(dead_code)] // Used in tests
     pub fn remove(&mut self, dir_path: &Path) -> bool {
         let dir_removed = self.dir_entries.remove(dir_path).is_some();
         let files_removed = self.file_entries.remove(dir_path).is_some();
+        self.entry_hashes.remove(dir_path);
         dir_removed || files_removed
     }
 
     /// Clear all cache entries
-    #[allow(dead_code)] // Used in tests
     pub fn clear(&mut self) {
         self.dir_entries.clear();
         self.file_entries.clear();
+        self.entry_hashes.clear();
+        self.dirty.clear();
         self.last_updated = SystemTime::now();
     }
 
@@ -265,10 +661,10 @@ impl DirectoryCache {
         self.dir_entries.is_empty() && self.file_entries.is_empty()
     }
 
-    /// Get cache file path for a destination
+    /// Get cache file path within a state directory
     #[allow(dead_code)] // Will be used for incremental scanning
-    pub fn cache_path(dest_root: &Path) -> PathBuf {
-        dest_root.join(Self::CACHE_FILENAME)
+    pub fn cache_path(state_dir: &Path) -> PathBuf {
+        state_dir.join(Self::CACHE_FILENAME)
     }
 }
 
@@ -278,6 +674,51 @@ impl Default for DirectoryCache {
     }
 }
 
+/// Directory whose entry list holds a file: its parent, "." at the top
+fn parent_dir(relative: &Path) -> PathBuf {
+    match relative.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// `relative` below `root`, where "." is `root` itself
+fn full_path(root: &Path, relative: &Path) -> PathBuf {
+    if relative == Path::new(".") {
+        root.to_path_buf()
+    } else if root == Path::new(".") {
+        relative.to_path_buf()
+    } else {
+        root.join(relative)
+    }
+}
+
+/// How deep a relative directory is, with "." at 0
+fn depth(relative: &Path) -> usize {
+    if relative == Path::new(".") {
+        0
+    } else {
+        relative.components().count()
+    }
+}
+
+/// Hash of a directory's cached entries, as recorded
+fn entries_hash(files: &[CachedFile]) -> u64 {
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    for file in files {
+        let modified = file
+            .modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        hasher.update(file.path.to_string_lossy().as_bytes());
+        hasher.update(&[0, file.is_dir as u8, file.is_symlink as u8]);
+        hasher.update(&file.size.to_le_bytes());
+        hasher.update(&modified.to_le_bytes());
+        hasher.update(&file.inode.unwrap_or(0).to_le_bytes());
+    }
+    hasher.digest()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -298,20 +739,20 @@ mod tests {
         let mtime = SystemTime::now();
 
         // First check - should need rescan (not in cache)
-        assert!(cache.needs_rescan(&dir, mtime));
+        assert!(cache.needs_rescan(&dir, &DirStamp::new(mtime)));
 
         // Update cache
-        cache.update(dir.clone(), mtime);
+        cache.update(dir.clone(), DirStamp::new(mtime));
         assert_eq!(cache.len(), 1);
 
         // Second check - should not need rescan (mtime matches)
-        assert!(!cache.needs_rescan(&dir, mtime));
+        assert!(!cache.needs_rescan(&dir, &DirStamp::new(mtime)));
 
         // Check with mtime 2 seconds in the future (beyond 1-second tolerance)
         let new_mtime = mtime + Duration::from_secs(2);
 
         // Should need rescan (mtime changed beyond tolerance)
-        assert!(cache.needs_rescan(&dir, new_mtime));
+        assert!(cache.needs_rescan(&dir, &DirStamp::new(new_mtime)));
     }
 
     #[test]
@@ -320,9 +761,12 @@ mod tests {
         let mut cache = DirectoryCache::new();
 
         // Add some entries
-        cache.update(PathBuf::from("dir1"), SystemTime::now());
-        cache.update(PathBuf::from("dir2"), SystemTime::now());
-        cache.update(PathBuf::from("dir3/subdir"), SystemTime::now());
+        cache.update(PathBuf::from("dir1"), DirStamp::new(SystemTime::now()));
+        cache.update(PathBuf::from("dir2"), DirStamp::new(SystemTime::now()));
+        cache.update(
+            PathBuf::from("dir3/subdir"),
+            DirStamp::new(SystemTime::now()),
+        );
 
         // Save
         cache.save(temp.path()).unwrap();
@@ -371,7 +815,7 @@ mod tests {
         let temp = TempDir::new().unwrap();
         let mut cache = DirectoryCache::new();
 
-        cache.update(PathBuf::from("dir1"), SystemTime::now());
+        cache.update(PathBuf::from("dir1"), DirStamp::new(SystemTime::now()));
         cache.save(temp.path()).unwrap();
 
         let cache_path = DirectoryCache::cache_path(temp.path());
@@ -390,7 +834,7 @@ mod tests {
         let mut cache = DirectoryCache::new();
         let dir = PathBuf::from("test/dir");
 
-        cache.update(dir.clone(), SystemTime::now());
+        cache.update(dir.clone(), DirStamp::new(SystemTime::now()));
         assert_eq!(cache.len(), 1);
 
         // Remove existing entry
@@ -405,8 +849,8 @@ mod tests {
     fn test_clear() {
         let mut cache = DirectoryCache::new();
 
-        cache.update(PathBuf::from("dir1"), SystemTime::now());
-        cache.update(PathBuf::from("dir2"), SystemTime::now());
+        cache.update(PathBuf::from("dir1"), DirStamp::new(SystemTime::now()));
+        cache.update(PathBuf::from("dir2"), DirStamp::new(SystemTime::now()));
         assert_eq!(cache.len(), 2);
 
         cache.clear();
@@ -419,18 +863,197 @@ mod tests {
         let dir = PathBuf::from("test/dir");
         let mtime = SystemTime::now();
 
-        cache.update(dir.clone(), mtime);
+        cache.update(dir.clone(), DirStamp::new(mtime));
 
         // Check with same mtime - should not need rescan
-        assert!(!cache.needs_rescan(&dir, mtime));
+        assert!(!cache.needs_rescan(&dir, &DirStamp::new(mtime)));
 
         // Check with mtime 500ms later - should not need rescan (within tolerance)
         let mtime_close = mtime + Duration::from_millis(500);
-        assert!(!cache.needs_rescan(&dir, mtime_close));
+        assert!(!cache.needs_rescan(&dir, &DirStamp::new(mtime_close)));
 
         // Check with mtime 2 seconds later - should need rescan (outside tolerance)
         let mtime_far = mtime + Duration::from_secs(2);
-        assert!(cache.needs_rescan(&dir, mtime_far));
+        assert!(cache.needs_rescan(&dir, &DirStamp::new(mtime_far)));
+    }
+
+    fn scan_into_cache(root: &Path) -> DirectoryCache {
+        let mut cache = DirectoryCache::new();
+        cache.update(PathBuf::from("."), DirStamp::read(root).unwrap());
+        let entries = crate::sync::scanner::Scanner::new(root).scan().unwrap();
+        for entry in &entries {
+            if entry.is_dir {
+                cache.update(
+                    entry.relative_path.clone(),
+                    DirStamp::read(&entry.path).unwrap(),
+                );
+            }
+        }
+        cache.cache_files(
+            PathBuf::from("."),
+            entries.iter().map(CachedFile::from_file_entry).collect(),
+        );
+        cache
+    }
+
+    #[test]
+    fn test_cached_entries_valid_when_unchanged() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("sub")).unwrap();
+        std::fs::write(temp.path().join("sub/file.txt"), "content").unwrap();
+
+        let cache = scan_into_cache(temp.path());
+        let entries = cache.cached_entries(temp.path()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .any(|e| e.relative_path == Path::new("sub/file.txt") && e.size == 7));
+    }
+
+    #[test]
+    fn test_cached_entries_invalid_after_replace_with_same_mtime() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        std::fs::write(&file, "content").unwrap();
+        let mtime = std::fs::metadata(&file).unwrap().modified().unwrap();
+        let dir_mtime = std::fs::metadata(temp.path()).unwrap().modified().unwrap();
+
+        let cache = scan_into_cache(temp.path());
+
+        // Replace the file with same-size content and restore both mtimes
+        let replacement = temp.path().join("replacement.txt");
+        std::fs::write(&replacement, "CONTENT").unwrap();
+        std::fs::rename(&replacement, &file).unwrap();
+        filetime::set_file_mtime(&file, filetime::FileTime::from_system_time(mtime)).unwrap();
+        filetime::set_file_mtime(temp.path(), filetime::FileTime::from_system_time(dir_mtime))
+            .unwrap();
+
+        assert!(cache.cached_entries(temp.path()).is_none());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_cached_entries_invalid_after_chmod() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        std::fs::write(&file, "content").unwrap();
+
+        let cache = scan_into_cache(temp.path());
+
+        // Metadata-only change: mtime is untouched, ctime is not
+        std::thread::sleep(Duration::from_millis(10));
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        assert!(cache.cached_entries(temp.path()).is_none());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_journal_entries_rereads_changed_dirs() {
+        use std::fs;
+
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        for dir in ["a", "b", "d"] {
+            fs::create_dir(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("a/one.txt"), "1").unwrap();
+        fs::write(root.join("b/two.txt"), "2").unwrap();
+        fs::write(root.join("d/log.txt"), "x").unwrap();
+
+        let mut cache = DirectoryCache::new();
+        cache.update(PathBuf::from("."), DirStamp::read(root).unwrap());
+        cache.record_scan(&Scanner::new(root).scan().unwrap());
+
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(root.join("a/new.txt"), "new").unwrap();
+        fs::remove_dir_all(root.join("b")).unwrap();
+        fs::create_dir_all(root.join("c/deep")).unwrap();
+        fs::write(root.join("c/deep/three.txt"), "3").unwrap();
+        // Edited in place, which leaves d's stamp alone
+        let d_mtime = fs::metadata(root.join("d")).unwrap().modified().unwrap();
+        fs::write(root.join("d/log.txt"), "xyz").unwrap();
+        assert_eq!(
+            fs::metadata(root.join("d")).unwrap().modified().unwrap(),
+            d_mtime
+        );
+
+        let entries = cache.journal_entries(root).unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.relative_path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                "a",
+                "a/new.txt",
+                "a/one.txt",
+                "c",
+                "c/deep",
+                "c/deep/three.txt",
+                "d",
+                "d/log.txt"
+            ]
+            .map(PathBuf::from)
+        );
+        let size = |entries: &[FileEntry], path: &str| {
+            entries
+                .iter()
+                .find(|e| e.relative_path == Path::new(path))
+                .unwrap()
+                .size
+        };
+        // The edit is found by statting d's files
+        assert_eq!(size(&entries, "d/log.txt"), 3);
+
+        // Watch mode reporting it as well changes nothing
+        cache.mark_dirty([PathBuf::from("d")]);
+        let entries = cache.journal_entries(root).unwrap();
+        assert_eq!(size(&entries, "d/log.txt"), 3);
+    }
+
+    #[test]
+    fn test_journal_entries_checks_hashes() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("file.txt"), "content").unwrap();
+
+        let mut cache = DirectoryCache::new();
+        cache.update(PathBuf::from("."), DirStamp::read(temp.path()).unwrap());
+        cache.record_scan(&Scanner::new(temp.path()).scan().unwrap());
+        assert_eq!(cache.journal_entries(temp.path()).unwrap().len(), 1);
+
+        // Entries that aren't what was recorded mean a full scan
+        cache.file_entries.get_mut(Path::new(".")).unwrap().clear();
+        assert!(cache.journal_entries(temp.path()).is_none());
+    }
+
+    #[test]
+    fn test_needs_rescan_on_inode_change() {
+        let mut cache = DirectoryCache::new();
+        let dir = PathBuf::from("dir");
+        let mtime = SystemTime::now();
+        let stamp = DirStamp {
+            mtime,
+            ctime: Some(mtime),
+            inode: Some(1),
+        };
+        cache.update(dir.clone(), stamp);
+
+        assert!(!cache.needs_rescan(&dir, &stamp));
+        assert!(cache.needs_rescan(
+            &dir,
+            &DirStamp {
+                inode: Some(2),
+                ..stamp
+            }
+        ));
+        assert!(cache.needs_rescan(
+            &dir,
+            &DirStamp {
+                ctime: Some(mtime + Duration::from_millis(1)),
+                ..stamp
+            }
+        ));
     }
 
     #[test]
@@ -0,0 +1,173 @@
+use crate::error::{Result, SyncError};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Environment variable that has to be set to 1 before `--fault-inject` is
+/// accepted, so the option can't slip into a production command line
+pub const ENABLE_VAR: &str = "SY_FAULT_INJECTION";
+
+/// Faults to inject, from `--fault-inject transfer=PCT,disconnect=PCT[,seed=N]`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultSpec {
+    /// Percent of transfers that fail on their own
+    pub transfer: u8,
+    /// Percent chance per transfer that the connection drops, failing it and
+    /// every transfer after it for the rest of the run
+    pub disconnect: u8,
+    /// Seed for reproducible runs; random if not given
+    pub seed: Option<u64>,
+}
+
+impl FaultSpec {
+    pub fn parse(spec: &str) -> std::result::Result<Self, String> {
+        let mut parsed = FaultSpec::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Expected KEY=VALUE, got '{}'", part))?;
+            match key {
+                "transfer" => parsed.transfer = parse_percent(value)?,
+                "disconnect" => parsed.disconnect = parse_percent(value)?,
+                "seed" => {
+                    parsed.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid seed '{}'", value))?,
+                    )
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown fault '{}'. Use transfer, disconnect, or seed",
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_percent(value: &str) -> std::result::Result<u8, String> {
+    match value.trim_end_matches('%').parse::<u8>() {
+        Ok(percent) if percent <= 100 => Ok(percent),
+        _ => Err(format!(
+            "Expected a percentage from 0 to 100, got '{}'",
+            value
+        )),
+    }
+}
+
+/// Fails transfers at random to exercise `--retry-failed` and resume
+///
+/// Injected failures look like real ones to the rest of the engine: they are
+/// recorded per file, retried, and leave resume state behind.
+pub struct FaultInjector {
+    spec: FaultSpec,
+    /// splitmix64 state, advanced once per roll
+    state: AtomicU64,
+    disconnected: AtomicBool,
+}
+
+impl FaultInjector {
+    pub fn new(spec: FaultSpec) -> Self {
+        let seed = spec.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        Self {
+            spec,
+            state: AtomicU64::new(seed),
+            disconnected: AtomicBool::new(false),
+        }
+    }
+
+    /// Called before transferring `path`; an error fails the transfer
+    pub fn check(&self, path: &Path) -> Result<()> {
+        if self.disconnected.load(Ordering::SeqCst) || self.roll(self.spec.disconnect) {
+            self.disconnected.store(true, Ordering::SeqCst);
+            return Err(SyncError::NetworkError {
+                message: format!("connection dropped (injected fault) at {}", path.display()),
+            });
+        }
+        if self.roll(self.spec.transfer) {
+            return Err(SyncError::Io(std::io::Error::other(format!(
+                "injected transfer failure: {}",
+                path.display()
+            ))));
+        }
+        Ok(())
+    }
+
+    fn roll(&self, percent: u8) -> bool {
+        if percent == 0 {
+            return false;
+        }
+        let mut z = self
+            .state
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        z % 100 < percent as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            FaultSpec::parse("transfer=10,disconnect=2%,seed=7").unwrap(),
+            FaultSpec {
+                transfer: 10,
+                disconnect: 2,
+                seed: Some(7),
+            }
+        );
+        assert_eq!(FaultSpec::parse("transfer=25").unwrap().disconnect, 0);
+
+        assert!(FaultSpec::parse("transfer=101").is_err());
+        assert!(FaultSpec::parse("transfer").is_err());
+        assert!(FaultSpec::parse("latency=5").is_err());
+    }
+
+    #[test]
+    fn test_fault_rate_and_seed() {
+        let spec = FaultSpec {
+            transfer: 30,
+            seed: Some(42),
+            ..FaultSpec::default()
+        };
+        let outcomes = |injector: &FaultInjector| -> Vec<bool> {
+            (0..1000)
+                .map(|_| injector.check(Path::new("f")).is_err())
+                .collect()
+        };
+
+        let first = outcomes(&FaultInjector::new(spec));
+        let failed = first.iter().filter(|&&f| f).count();
+        assert!((200..400).contains(&failed), "{} of 1000 failed", failed);
+        // The same seed fails the same transfers
+        assert_eq!(outcomes(&FaultInjector::new(spec)), first);
+    }
+
+    #[test]
+    fn test_disconnect_fails_rest_of_run() {
+        let injector = FaultInjector::new(FaultSpec {
+            disconnect: 100,
+            ..FaultSpec::default()
+        });
+        assert!(matches!(
+            injector.check(Path::new("a")),
+            Err(SyncError::NetworkError { .. })
+        ));
+
+        let injector = FaultInjector::new(FaultSpec::default());
+        injector.disconnected.store(true, Ordering::SeqCst);
+        assert!(injector.check(Path::new("b")).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use crate::error::{Result, SyncError};
+use crate::sync::output::{self, WarningCategory};
+use crate::transport::Transport;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lock file name, created in the destination root while a sync is running
+pub const LOCK_FILE_NAME: &str = ".sy-lock";
+
+/// Owner of a destination lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub hostname: String,
+    pub started_at: u64, // Seconds since UNIX epoch
+}
+
+impl LockInfo {
+    /// Lock owner for the current process
+    pub fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            hostname: whoami::fallible::hostname().unwrap_or_default(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    /// Whether the owner is known to be gone
+    ///
+    /// Only locks taken on this host can be checked (the owning process no
+    /// longer exists); locks from other hosts are never considered stale.
+    pub fn is_stale(&self) -> bool {
+        let hostname = whoami::fallible::hostname().unwrap_or_default();
+        !hostname.is_empty() && self.hostname == hostname && !process_alive(self.pid)
+    }
+
+    /// Human-readable owner description for error messages
+    pub fn describe(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        format!(
+            "pid {} on {}, started {}s ago",
+            self.pid,
+            if self.hostname.is_empty() {
+                "unknown host"
+            } else {
+                &self.hostname
+            },
+            now.saturating_sub(self.started_at)
+        )
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // Signal 0 only checks for existence; EPERM means it exists but isn't ours
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a portable liveness check, assume the owner is still running
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Path of the lock file for a destination
+pub fn lock_path(dest: &Path) -> PathBuf {
+    dest.join(LOCK_FILE_NAME)
+}
+
+/// Try to create the lock file in a local destination
+///
+/// Returns `None` if the lock was acquired for `owner`, or the current
+/// holder if another sync already holds it.
+pub fn try_lock(dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+    std::fs::create_dir_all(dest)?;
+    let path = lock_path(dest);
+
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    {
+        Ok(mut file) => {
+            let json = serde_json::to_vec(owner).map_err(|e| {
+                SyncError::Io(std::io::Error::other(format!(
+                    "Failed to serialize lock info: {}",
+                    e
+                )))
+            })?;
+            file.write_all(&json)?;
+            file.sync_all()?;
+            Ok(None)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            let holder = std::fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice(&data).ok())
+                // Unreadable or half-written lock: report an unknown owner
+                .unwrap_or(LockInfo {
+                    pid: 0,
+                    hostname: String::new(),
+                    started_at: 0,
+                });
+            Ok(Some(holder))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Remove the lock file from a local destination if `owner` still holds it
+///
+/// Returns false if the lock is missing or held by someone else.
+pub fn unlock(dest: &Path, owner: &LockInfo) -> Result<bool> {
+    let path = lock_path(dest);
+    let holder: Option<LockInfo> = match std::fs::read(&path) {
+        Ok(data) => serde_json::from_slice(&data).ok(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+
+    if holder.as_ref() != Some(owner) {
+        return Ok(false);
+    }
+
+    std::fs::remove_file(&path)?;
+    Ok(true)
+}
+
+/// Advisory lock held on a destination for the duration of a sync
+///
+/// Must be released with [`DestinationLock::release`]; a lock left behind by
+/// a crash is detected as stale by the next run on the same host.
+pub struct DestinationLock {
+    dest: PathBuf,
+    owner: LockInfo,
+}
+
+impl DestinationLock {
+    /// Acquire the lock, replacing it once if the holder is stale
+    pub async fn acquire<T: Transport>(transport: &T, dest: &Path) -> Result<Self> {
+        let owner = LockInfo::current();
+
+        for _ in 0..2 {
+            match transport.try_lock(dest, &owner).await? {
+                None => {
+                    tracing::debug!("Acquired lock on {}", dest.display());
+                    return Ok(Self {
+                        dest: dest.to_path_buf(),
+                        owner,
+                    });
+                }
+                Some(holder) if holder.is_stale() => {
+                    output::warn(
+                        WarningCategory::Lock,
+                        Some(dest),
+                        format!(
+                            "Removing stale lock on {} ({})",
+                            dest.display(),
+                            holder.describe()
+                        ),
+                    );
+                    transport.unlock(dest, &holder).await?;
+                }
+                Some(holder) => {
+                    return Err(SyncError::DestinationLocked {
+                        path: dest.to_path_buf(),
+                        holder: holder.describe(),
+                        lock_file: lock_path(dest),
+                    });
+                }
+            }
+        }
+
+        // Another run replaced the stale lock first
+        Err(SyncError::DestinationLocked {
+            path: dest.to_path_buf(),
+            holder: "another sync".to_string(),
+            lock_file: lock_path(dest),
+        })
+    }
+
+    /// Release the lock
+    pub async fn release<T: Transport>(self, transport: &T) {
+        match transport.unlock(&self.dest, &self.owner).await {
+            Ok(true) => tracing::debug!("Released lock on {}", self.dest.display()),
+            Ok(false) => output::warn(
+                WarningCategory::Lock,
+                Some(self.dest.as_path()),
+                format!(
+                    "Lock on {} was removed or taken over during the sync",
+                    self.dest.display()
+                ),
+            ),
+            Err(e) => output::warn(
+                WarningCategory::Lock,
+                Some(self.dest.as_path()),
+                format!("Failed to release lock on {}: {}", self.dest.display(), e),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::local::LocalTransport;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_try_lock_and_unlock() {
+        let temp = TempDir::new().unwrap();
+        let owner = LockInfo::current();
+
+        assert_eq!(try_lock(temp.path(), &owner).unwrap(), None);
+        assert!(lock_path(temp.path()).exists());
+
+        // Second attempt reports the holder
+        let other = LockInfo {
+            pid: owner.pid + 1,
+            ..owner.clone()
+        };
+        assert_eq!(try_lock(temp.path(), &other).unwrap(), Some(owner.clone()));
+
+        // Only the owner can unlock
+        assert!(!unlock(temp.path(), &other).unwrap());
+        assert!(unlock(temp.path(), &owner).unwrap());
+        assert!(!lock_path(temp.path()).exists());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_fails_while_held() {
+        let temp = TempDir::new().unwrap();
+        let transport = LocalTransport::new();
+
+        let lock = DestinationLock::acquire(&transport, temp.path())
+            .await
+            .unwrap();
+
+        // Held by a live process (this one)
+        let err = DestinationLock::acquire(&transport, temp.path())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, SyncError::DestinationLocked { .. }));
+
+        lock.release(&transport).await;
+        assert!(!lock_path(temp.path()).exists());
+
+        DestinationLock::acquire(&transport, temp.path())
+            .await
+            .unwrap()
+            .release(&transport)
+            .await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_acquire_replaces_stale_lock() {
+        let temp = TempDir::new().unwrap();
+        let transport = LocalTransport::new();
+
+        // A process that has already exited
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+
+        let stale = LockInfo {
+            pid: dead_pid,
+            ..LockInfo::current()
+        };
+        assert!(stale.is_stale());
+        assert_eq!(try_lock(temp.path(), &stale).unwrap(), None);
+
+        let lock = DestinationLock::acquire(&transport, temp.path())
+            .await
+            .unwrap();
+        assert_eq!(lock.owner.pid, std::process::id());
+        lock.release(&transport).await;
+    }
+}
@@ -1,33 +1,61 @@
+mod busy;
+pub mod check;
 pub mod checksumdb;
+pub mod clean;
+pub mod control;
 pub mod dircache;
+pub mod fault;
+pub mod lock;
+pub mod munge;
+pub mod names;
+pub mod observer;
 pub mod output;
+pub mod preflight;
+pub mod priority;
+mod progress;
+pub mod quota;
 mod ratelimit;
 pub mod resume;
+pub mod sandbox;
 pub mod scale;
 pub mod scanner;
+mod scheduler;
+pub mod staging;
 pub mod strategy;
 pub mod transfer;
+pub mod trash;
 pub mod watch;
 
-use crate::cli::SymlinkMode;
+use crate::acl::AclMapPolicy;
+use crate::cli::{ScanErrorMode, SpecialsMode, SymlinkMode};
 use crate::error::Result;
 use crate::filter::FilterEngine;
 use crate::integrity::{ChecksumType, IntegrityVerifier};
+use crate::owner::PreservePerms;
 use crate::perf::{PerformanceMetrics, PerformanceMonitor};
 use crate::resource;
 use crate::transport::Transport;
 use dircache::DirectoryCache;
+use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use output::SyncEvent;
-use ratelimit::RateLimiter;
-use resume::{ResumeState, SyncFlags};
-use scanner::FileEntry;
+use observer::{Observers, SyncObserver};
+use output::{SyncEvent, WarningCategory};
+use progress::TransferProgress;
+use ratelimit::BandwidthLimiter;
+use resume::{CompletedFile, ResumeState, SyncFlags};
+use sandbox::DestConfinement;
+use scanner::{FileEntry, ScanError};
+use scheduler::SizeScheduler;
+use staging::Staging;
+use std::collections::{BTreeSet, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use strategy::{StrategyPlanner, SyncAction};
-use tokio::sync::Semaphore;
-use transfer::Transferrer;
+use std::time::{Duration, SystemTime};
+use strategy::{PlanReason, StrategyPlanner, SyncAction};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use transfer::{ImmutableMode, Transferrer};
+use trash::{Trash, TrashTarget};
 
 #[derive(Debug, Clone)]
 pub struct SyncError {
@@ -57,6 +85,13 @@ pub struct SyncStats {
     pub bytes_would_delete: u64,
     // Error tracking
     pub errors: Vec<SyncError>,
+    pub skipped_busy: Vec<PathBuf>, // In use by another process (--skip-busy)
+    pub deferred_hot: Vec<PathBuf>, // Modified too recently to copy (--skip-hot)
+    pub skipped_specials: Vec<PathBuf>, // Sockets, FIFOs and device nodes (--specials)
+    pub names: names::NameReport,   // Renamed or left out for Windows (--windows-names)
+    pub files_retried: usize,       // Failed files retried at the end of the run (--retry-failed)
+    pub changed_during_transfer: Vec<PathBuf>, // Sources modified while being read
+    pub metadata_not_preserved: output::MetadataReport, // Xattrs, ACLs, owners, ... that couldn't be applied
 }
 
 #[derive(Debug)]
@@ -69,14 +104,75 @@ pub struct VerificationResult {
     pub duration: Duration,
 }
 
+/// Settings of [`SyncEngine::new`] that most callers leave alone
+///
+/// `Default` turns every one of them off, the destination lock included.
+#[derive(Default)]
+pub struct SyncOptions {
+    /// Source tree inside the destination, never deleted (--allow-nested)
+    pub nested_source: Option<PathBuf>,
+    /// Stage files and move them into place together (--delay-updates)
+    pub delay_updates: bool,
+    /// Hard-link unchanged files from here, relative to the destination (--link-dest)
+    pub link_dest: Option<PathBuf>,
+    /// Limit for each file on its own (--bwlimit-per-file)
+    pub bwlimit_per_file: Option<u64>,
+    /// Reconcile resume state saved with other flags
+    pub resume_force: bool,
+    pub preserve_atimes: bool,
+    /// Ignored where the destination can't set creation times
+    pub preserve_crtimes: bool,
+    pub preserve_perms: PreservePerms,
+    /// Re-read only changed directories of the cache (--scan-journal)
+    pub scan_journal: bool,
+    pub scan_error_mode: ScanErrorMode,
+    pub specials_mode: SpecialsMode,
+    pub max_depth: Option<usize>,
+    /// Hold the destination lock while syncing (off with --no-lock)
+    pub lock: bool,
+    pub skip_busy: bool,
+    /// Retry busy files for this long (--wait-busy)
+    pub wait_busy: Option<Duration>,
+    /// Defer files modified this recently (--skip-hot)
+    pub skip_hot: Option<Duration>,
+    /// Handling of ACLs from a different ACL model (--acl-map)
+    pub acl_map: AclMapPolicy,
+    /// Size limit for the destination tree (--dest-quota)
+    pub dest_quota: Option<quota::DestQuota>,
+    /// Refuse writes leaving the destination root
+    pub dest_confinement: DestConfinement,
+    /// Destination entries with immutable flags (--force-change)
+    pub immutable_mode: ImmutableMode,
+    /// Windows naming rules for destination paths
+    pub windows_names: Option<names::NameMode>,
+    /// Extra passes over failed files at the end of the run (--retry-failed)
+    pub retry_passes: usize,
+    /// Transfer order tiers (--priority-pattern)
+    pub priority_rules: Vec<priority::PriorityRule>,
+    /// Fail files whose (local) source changed while being read
+    pub check_source_changes: bool,
+    /// Copy updated files whole instead of through delta sync (--whole-file)
+    pub whole_file: bool,
+    /// Read local sources with O_NOATIME where the platform allows (--open-noatime)
+    pub open_noatime: bool,
+    /// Pause flag and progress for sy ctl
+    pub control: Option<Arc<control::SyncControl>>,
+    /// Random failures (--fault-inject)
+    pub fault_injector: Option<Arc<fault::FaultInjector>>,
+    /// Stops the sync early, keeping resume state
+    pub cancel: Option<CancellationToken>,
+}
+
 pub struct SyncEngine<T: Transport> {
     transport: Arc<T>,
     dry_run: bool,
     diff_mode: bool,
     delete: bool,
     delete_threshold: u8,
-    #[allow(dead_code)] // Planned feature: trash/recycle bin support
-    trash: bool,
+    trash: Option<TrashTarget>, // Where deletions are moved instead (--trash)
+    nested_source: Option<PathBuf>, // Source tree inside the destination, never deleted (--allow-nested)
+    delay_updates: bool, // Stage files and move them into place together (--delay-updates)
+    link_dest: Option<PathBuf>, // Hard-link unchanged files from here, relative to the destination (--link-dest)
     force_delete: bool,
     quiet: bool,
     max_concurrent: usize,
@@ -85,7 +181,9 @@ pub struct SyncEngine<T: Transport> {
     max_size: Option<u64>,
     filter_engine: FilterEngine,
     bwlimit: Option<u64>,
+    bwlimit_per_file: Option<u64>,
     resume: bool,
+    resume_force: bool, // Reconcile resume state saved with other flags
     checkpoint_files: usize,
     checkpoint_bytes: u64,
     json: bool,
@@ -96,6 +194,9 @@ pub struct SyncEngine<T: Transport> {
     preserve_hardlinks: bool,
     preserve_acls: bool,
     preserve_flags: bool, // macOS only, no-op on other platforms
+    preserve_atimes: bool,
+    preserve_crtimes: bool, // Ignored where the destination can't set creation times
+    preserve_perms: PreservePerms,
     ignore_times: bool,
     size_only: bool,
     checksum: bool,
@@ -103,9 +204,33 @@ pub struct SyncEngine<T: Transport> {
     verify_only: bool,
     use_cache: bool,
     clear_cache: bool,
+    scan_journal: bool, // Re-read only changed directories of the cache (--scan-journal)
+    changed_dirs: Mutex<HashSet<PathBuf>>, // Seen changing by watch mode, for the scan journal
     checksum_db: bool,
     clear_checksum_db: bool,
     prune_checksum_db: bool,
+    scan_error_mode: ScanErrorMode,
+    specials_mode: SpecialsMode,
+    max_depth: Option<usize>,
+    state_dir: PathBuf, // Per-pair state directory, derived by the caller from the real paths
+    lock: bool,         // Hold the destination lock while syncing (off with --no-lock)
+    skip_busy: bool,
+    wait_busy: Option<Duration>, // Retry busy files for this long (--wait-busy)
+    skip_hot: Option<Duration>,  // Defer files modified this recently (--skip-hot)
+    acl_map: AclMapPolicy,       // Handling of ACLs from a different ACL model (--acl-map)
+    dest_quota: Option<quota::DestQuota>, // Size limit for the destination tree (--dest-quota)
+    dest_confinement: DestConfinement, // Refuse writes leaving the destination root
+    immutable_mode: ImmutableMode, // Destination entries with immutable flags (--force-change)
+    windows_names: Option<names::NameMode>, // Windows naming rules for destination paths
+    retry_passes: usize, // Extra passes over failed files at the end of the run (--retry-failed)
+    priority_rules: Vec<priority::PriorityRule>, // Transfer order tiers (--priority-pattern)
+    check_source_changes: bool, // Fail files whose (local) source changed while being read
+    whole_file: bool,    // Copy updated files whole instead of through delta sync
+    open_noatime: bool,  // Read local sources with O_NOATIME (--open-noatime)
+    control: Option<Arc<control::SyncControl>>, // Pause flag and progress for sy ctl
+    fault_injector: Option<Arc<fault::FaultInjector>>, // Random failures (--fault-inject)
+    cancel: Option<CancellationToken>, // Stops the sync early, keeping resume state
+    observers: Vec<Arc<dyn SyncObserver>>, // Added with with_observer, for library users
     perf_monitor: Option<Arc<Mutex<PerformanceMonitor>>>,
 }
 
@@ -117,7 +242,7 @@ impl<T: Transport + 'static> SyncEngine<T> {
         diff_mode: bool,
         delete: bool,
         delete_threshold: u8,
-        trash: bool,
+        trash: Option<TrashTarget>,
         force_delete: bool,
         quiet: bool,
         max_concurrent: usize,
@@ -146,10 +271,46 @@ impl<T: Transport + 'static> SyncEngine<T> {
         checksum_db: bool,
         clear_checksum_db: bool,
         prune_checksum_db: bool,
+        state_dir: PathBuf,
         perf: bool,
+        options: SyncOptions,
     ) -> Self {
+        let SyncOptions {
+            nested_source,
+            delay_updates,
+            link_dest,
+            bwlimit_per_file,
+            resume_force,
+            preserve_atimes,
+            preserve_crtimes,
+            preserve_perms,
+            scan_journal,
+            scan_error_mode,
+            specials_mode,
+            max_depth,
+            lock,
+            skip_busy,
+            wait_busy,
+            skip_hot,
+            acl_map,
+            dest_quota,
+            dest_confinement,
+            immutable_mode,
+            windows_names,
+            retry_passes,
+            priority_rules,
+            check_source_changes,
+            whole_file,
+            open_noatime,
+            control,
+            fault_injector,
+            cancel,
+        } = options;
+
         let perf_monitor = if perf {
-            Some(Arc::new(Mutex::new(PerformanceMonitor::new(bwlimit))))
+            Some(Arc::new(Mutex::new(
+                PerformanceMonitor::new(bwlimit).with_open_noatime(open_noatime),
+            )))
         } else {
             None
         };
@@ -161,6 +322,9 @@ impl<T: Transport + 'static> SyncEngine<T> {
             delete,
             delete_threshold,
             trash,
+            nested_source,
+            delay_updates,
+            link_dest,
             force_delete,
             quiet,
             max_concurrent,
@@ -169,7 +333,9 @@ impl<T: Transport + 'static> SyncEngine<T> {
             max_size,
             filter_engine,
             bwlimit,
+            bwlimit_per_file,
             resume,
+            resume_force,
             checkpoint_files,
             checkpoint_bytes,
             json,
@@ -180,19 +346,108 @@ impl<T: Transport + 'static> SyncEngine<T> {
             preserve_hardlinks,
             preserve_acls,
             preserve_flags,
+            preserve_atimes,
+            preserve_crtimes,
+            preserve_perms,
             ignore_times,
             size_only,
             checksum,
             verify_only,
             use_cache,
             clear_cache,
+            scan_journal,
+            changed_dirs: Mutex::new(HashSet::new()),
             checksum_db,
             clear_checksum_db,
             prune_checksum_db,
+            scan_error_mode,
+            specials_mode,
+            max_depth,
+            state_dir,
+            lock,
+            skip_busy,
+            wait_busy,
+            skip_hot,
+            acl_map,
+            dest_quota,
+            dest_confinement,
+            immutable_mode,
+            windows_names,
+            retry_passes,
+            priority_rules,
+            check_source_changes,
+            whole_file,
+            open_noatime,
+            control,
+            fault_injector,
+            cancel,
+            observers: Vec::new(),
             perf_monitor,
         }
     }
 
+    /// Record source paths that changed (from watch mode's events), so the
+    /// next sync's scan journal re-reads their directories
+    ///
+    /// Needed for files edited in place, which leave their directory's stamp
+    /// alone. Does nothing without --scan-journal.
+    pub fn note_changes<'a>(&self, source: &Path, paths: impl IntoIterator<Item = &'a PathBuf>) {
+        if !self.scan_journal {
+            return;
+        }
+        let mut changed_dirs = self.changed_dirs.lock().unwrap();
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(source) else {
+                continue;
+            };
+            changed_dirs.insert(match relative.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            });
+        }
+    }
+
+    /// Report scan and per-file progress of every sync to `observer`, as well
+    /// as to the progress bar and JSON output
+    #[allow(dead_code)] // For library users; the CLI adds no observers of its own
+    pub fn with_observer(mut self, observer: Arc<dyn SyncObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Drop completed entries of a reconciled resume state whose destination
+    /// no longer shows them done: a copied file gone or of another size, or
+    /// a deleted one back. Returns how many were dropped.
+    async fn verify_completed(&self, state: &mut ResumeState, destination: &Path) -> Result<usize> {
+        let paths: Vec<PathBuf> = state
+            .completed_files()
+            .iter()
+            .map(|file| destination.join(&file.relative_path))
+            .collect();
+        let mut stats = self.transport.stat_batch(&paths).await?.into_iter();
+        Ok(state.retain_completed(|file| {
+            let stat = stats.next().flatten();
+            match file.action.as_str() {
+                "delete" => stat.is_none(),
+                _ => stat.is_some_and(|stat| stat.is_dir || stat.size == file.size),
+            }
+        }))
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Stop between phases of a sync once it was cancelled
+    fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(crate::error::SyncError::Cancelled);
+        }
+        Ok(())
+    }
+
     fn should_filter_by_size(&self, file_size: u64) -> bool {
         if let Some(min) = self.min_size {
             if file_size < min {
@@ -211,7 +466,120 @@ impl<T: Transport + 'static> SyncEngine<T> {
         self.filter_engine.should_exclude(relative_path, is_dir)
     }
 
+    /// Check if a path lies deeper than --max-depth allows
+    fn exceeds_max_depth(&self, relative_path: &Path) -> bool {
+        self.max_depth
+            .is_some_and(|max| relative_path.components().count() > max)
+    }
+
+    /// Scan the source tree, handling unreadable entries per `scan_error_mode`
+    /// and special files per `specials_mode`
+    ///
+    /// Returns the scanned entries and the per-entry scan errors, special
+    /// files included. In strict mode the first unreadable entry (symlink
+    /// loops included) fails the scan instead.
+    async fn scan_source(&self, source: &Path) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        let (entries, errors) = self.transport.scan_with_errors(source).await?;
+        for err in &errors {
+            if err.special {
+                match self.specials_mode {
+                    SpecialsMode::Fail => {
+                        return Err(crate::error::SyncError::SpecialFile {
+                            path: err.path.clone(),
+                            kind: err.error.clone(),
+                        });
+                    }
+                    SpecialsMode::Skip => {
+                        tracing::debug!(
+                            "Skipping special file ({}): {}",
+                            err.error,
+                            err.path.display()
+                        );
+                    }
+                    SpecialsMode::Warn => {
+                        output::warn(
+                            WarningCategory::Special,
+                            Some(err.path.as_path()),
+                            format!(
+                                "Skipping special file ({}): {}",
+                                err.error,
+                                err.path.display()
+                            ),
+                        );
+                    }
+                }
+                continue;
+            }
+
+            match self.scan_error_mode {
+                ScanErrorMode::Strict => {
+                    return Err(crate::error::SyncError::ReadDirError {
+                        path: err.path.clone(),
+                        source: std::io::Error::other(err.error.clone()),
+                    });
+                }
+                ScanErrorMode::Ignore => {
+                    tracing::debug!(
+                        "Skipping unreadable entry {}: {}",
+                        err.path.display(),
+                        err.error
+                    );
+                }
+                ScanErrorMode::Report => {
+                    output::warn(
+                        WarningCategory::Scan,
+                        Some(err.path.as_path()),
+                        format!(
+                            "Skipping unreadable entry {}: {}",
+                            err.path.display(),
+                            err.error
+                        ),
+                    );
+                }
+            }
+        }
+
+        Ok((entries, errors))
+    }
+
     pub async fn sync(&self, source: &Path, destination: &Path) -> Result<SyncStats> {
+        let start = std::time::Instant::now();
+        let span = tracing::info_span!(
+            "sync",
+            source = %source.display(),
+            destination = %destination.display(),
+            dry_run = self.dry_run
+        );
+        let result = self.sync_locked(source, destination).instrument(span).await;
+        crate::telemetry::record_run(
+            destination,
+            result.as_ref().ok(),
+            start.elapsed(),
+            self.dry_run,
+        );
+        result
+    }
+
+    async fn sync_locked(&self, source: &Path, destination: &Path) -> Result<SyncStats> {
+        // One clear error up front instead of a permission error for every file
+        // (and before the lock file, which would fail the same way)
+        if !self.dry_run {
+            self.transport.check_writable(destination).await?;
+        }
+
+        // Concurrent runs into one destination would race on temp files and state.
+        // Dry runs don't write, so they don't need (or block on) the lock.
+        if !self.lock || self.dry_run {
+            return self.sync_unlocked(source, destination).await;
+        }
+
+        let lock = lock::DestinationLock::acquire(&self.transport, destination).await?;
+        let result = self.sync_unlocked(source, destination).await;
+        lock.release(&self.transport).await;
+        result
+    }
+
+    async fn sync_unlocked(&self, source: &Path, destination: &Path) -> Result<SyncStats> {
         let start_time = std::time::Instant::now();
 
         tracing::info!(
@@ -220,10 +588,17 @@ impl<T: Transport + 'static> SyncEngine<T> {
             destination.display()
         );
 
+        // Resume state, directory cache and checksum DB live outside the destination tree
+        let state_dir = self.state_dir.clone();
+
         // Handle directory cache
         if self.clear_cache && !self.dry_run {
-            if let Err(e) = DirectoryCache::delete(destination) {
-                tracing::warn!("Failed to clear directory cache: {}", e);
+            if let Err(e) = DirectoryCache::delete(&state_dir) {
+                output::warn(
+                    WarningCategory::Cache,
+                    None,
+                    format!("Failed to clear directory cache: {}", e),
+                );
             } else {
                 tracing::debug!("Cleared directory cache");
             }
@@ -231,7 +606,7 @@ impl<T: Transport + 'static> SyncEngine<T> {
 
         // Load directory cache (if enabled)
         let mut dir_cache = if self.use_cache {
-            let cache = DirectoryCache::load(destination);
+            let cache = DirectoryCache::load(&state_dir);
             tracing::debug!("Loaded directory cache with {} entries", cache.len());
             Some(cache)
         } else {
@@ -241,23 +616,45 @@ impl<T: Transport + 'static> SyncEngine<T> {
         // Handle checksum database
         let checksum_db = if self.checksum && self.checksum_db {
             // Open checksum database
-            match checksumdb::ChecksumDatabase::open(destination) {
-                Ok(db) => {
+            match checksumdb::ChecksumDatabase::open(&state_dir) {
+                Ok(mut db) => {
                     tracing::debug!("Opened checksum database");
 
                     // Clear if requested
                     if self.clear_checksum_db && !self.dry_run {
                         if let Err(e) = db.clear() {
-                            tracing::warn!("Failed to clear checksum database: {}", e);
+                            output::warn(
+                                WarningCategory::Cache,
+                                None,
+                                format!("Failed to clear checksum database: {}", e),
+                            );
                         } else {
                             tracing::info!("Cleared checksum database");
                         }
                     }
 
+                    // Checksums stored by this run belong to a new generation
+                    if !self.dry_run {
+                        match db.begin_generation() {
+                            Ok(generation) => {
+                                tracing::debug!("Checksum database generation {}", generation)
+                            }
+                            Err(e) => output::warn(
+                                WarningCategory::Cache,
+                                None,
+                                format!("Failed to start checksum generation: {}", e),
+                            ),
+                        }
+                    }
+
                     Some(db)
                 }
                 Err(e) => {
-                    tracing::warn!("Failed to open checksum database: {}", e);
+                    output::warn(
+                        WarningCategory::Cache,
+                        None,
+                        format!("Failed to open checksum database: {}", e),
+                    );
                     None
                 }
             }
@@ -265,58 +662,71 @@ impl<T: Transport + 'static> SyncEngine<T> {
             None
         };
 
-        // Check if we can use cached scan results (incremental scanning)
-        let can_use_cache = if let Some(ref cache) = dir_cache {
-            // Check source directory mtime
-            if let Ok(source_meta) = std::fs::metadata(source) {
-                if let Ok(source_mtime) = source_meta.modified() {
-                    let source_path = PathBuf::from(".");
-                    !cache.needs_rescan(&source_path, source_mtime)
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        } else {
-            false
-        };
-
         // Start scan timing
         if let Some(ref monitor) = self.perf_monitor {
             monitor.lock().unwrap().start_scan();
         }
 
-        // Scan source directory (or use cache)
-        let all_files = if can_use_cache {
-            // Use cached files for incremental scan
-            if let Some(ref cache) = dir_cache {
-                if let Some(cached_files) = cache.get_cached_files(&PathBuf::from(".")) {
-                    let file_count = cached_files.len();
-                    tracing::info!(
-                        "Using cached scan results ({} files) - source unchanged",
-                        file_count
-                    );
+        // Progress goes to library observers, --json output and (once the
+        // plan is known) the progress bar
+        let mut observers = Observers::new(self.observers.clone());
+        if self.json {
+            observers.push(Arc::new(output::JsonObserver));
+        }
+        observers.on_scan_progress(source, 0, false);
 
-                    // Convert cached files back to FileEntry
-                    cached_files
-                        .iter()
-                        .map(|cf| cf.to_file_entry(source))
-                        .collect()
-                } else {
-                    // Cache exists but no files cached for root directory
-                    tracing::debug!("No cached files found, performing full scan");
-                    self.transport.scan(source).await?
-                }
-            } else {
-                // This shouldn't happen, but fall back to full scan
-                self.transport.scan(source).await?
-            }
+        // Changes watch mode saw since the last sync; forgotten once the
+        // cache that includes them is saved
+        let noted_changes: HashSet<PathBuf> = self.changed_dirs.lock().unwrap().clone();
+
+        // Check if we can use cached scan results (incremental scanning)
+        // xattrs, ACLs, access/creation times and permissions aren't cached,
+        // so syncs preserving them always re-scan
+        let cached_entries = if self.preserve_xattrs
+            || self.preserve_acls
+            || self.preserve_atimes
+            || self.preserve_crtimes
+            || self.preserve_perms.any()
+        {
+            None
+        } else if self.scan_journal {
+            dir_cache.as_mut().and_then(|cache| {
+                cache.mark_dirty(noted_changes.iter().cloned());
+                cache.journal_entries(source)
+            })
+        } else {
+            dir_cache
+                .as_ref()
+                .and_then(|cache| cache.cached_entries(source))
+        };
+        let can_use_cache = cached_entries.is_some();
+
+        // Scan source directory (or use cache)
+        let (all_files, scan_errors) = if let Some(entries) = cached_entries {
+            tracing::info!(
+                "Using cached scan results ({} files) - source unchanged",
+                entries.len()
+            );
+            (entries, Vec::new())
         } else {
             tracing::debug!("Scanning source directory (cache miss or disabled)...");
-            self.transport.scan(source).await?
+            self.scan_source(source)
+                .instrument(tracing::debug_span!("scan", path = %source.display()))
+                .await?
         };
 
+        // Source paths (relative) that couldn't be read; an empty path means
+        // the location is unknown and covers the whole tree
+        let unreadable_paths: Vec<PathBuf> = scan_errors
+            .iter()
+            .map(|e| {
+                e.path
+                    .strip_prefix(source)
+                    .map(|p| p.to_path_buf())
+                    .unwrap_or_default()
+            })
+            .collect();
+
         let total_scanned = all_files.len();
         if can_use_cache {
             tracing::info!("Retrieved {} items from cache", total_scanned);
@@ -325,45 +735,28 @@ impl<T: Transport + 'static> SyncEngine<T> {
         }
 
         // Update cache with scanned directory mtimes and file entries (for future incremental scans)
-        if let Some(ref mut cache) = dir_cache {
-            use crate::sync::dircache::CachedFile;
-            use std::collections::HashMap;
-
-            // Group files by their parent directory
-            let mut files_by_dir: HashMap<PathBuf, Vec<CachedFile>> = HashMap::new();
-
-            for file in &all_files {
-                // Update directory mtimes
-                if file.is_dir {
-                    cache.update(file.relative_path.clone(), file.modified);
-                }
-
-                // Group files by directory for caching
-                let dir_path = if file.is_dir {
-                    file.relative_path.clone()
-                } else {
-                    file.relative_path
-                        .parent()
-                        .map(|p| p.to_path_buf())
-                        .unwrap_or_else(|| PathBuf::from("."))
-                };
-
-                files_by_dir
-                    .entry(dir_path)
-                    .or_default()
-                    .push(CachedFile::from_file_entry(file));
+        // An incomplete scan (unreadable entries or --max-depth) is never cached,
+        // or the missing entries would stay hidden from later syncs
+        let scan_complete = scan_errors.is_empty() && self.max_depth.is_none();
+        if let Some(cache) = dir_cache
+            .as_mut()
+            .filter(|_| scan_complete && !can_use_cache)
+        {
+            use crate::sync::dircache::DirStamp;
+
+            // Rebuild from scratch so directories that no longer exist are dropped
+            cache.clear();
+            if let Some(stamp) = DirStamp::read(source) {
+                cache.update(PathBuf::from("."), stamp);
             }
 
-            // Cache files for each directory
-            let total_files: usize = files_by_dir.values().map(|v| v.len()).sum();
-            for (dir_path, files) in files_by_dir {
-                cache.cache_files(dir_path, files);
-            }
+            // Directory stamps (mtime, ctime, inode), and files grouped by directory
+            cache.record_scan(&all_files);
 
             tracing::debug!(
                 "Updated directory cache with {} directories, {} files",
                 cache.len(),
-                total_files
+                all_files.len()
             );
         }
 
@@ -374,6 +767,11 @@ impl<T: Transport + 'static> SyncEngine<T> {
         let source_files: Vec<_> = all_files
             .into_iter()
             .filter(|file| {
+                // Transports without depth-limited scans (S3, cache) may return deeper entries
+                if self.exceeds_max_depth(&file.relative_path) {
+                    return false;
+                }
+
                 // Check if this file is inside an excluded directory
                 for excluded_dir in &excluded_dirs {
                     if file.relative_path.starts_with(excluded_dir) {
@@ -415,6 +813,33 @@ impl<T: Transport + 'static> SyncEngine<T> {
             tracing::info!("Filtered out {} files", filtered_count);
         }
 
+        // Rename or leave out names a Windows destination would reject
+        let (source_files, name_report) = match self.windows_names {
+            Some(mode) => names::apply(source_files, destination, mode)?,
+            None => (source_files, names::NameReport::default()),
+        };
+        for (from, to) in &name_report.renamed {
+            output::warn(
+                WarningCategory::Name,
+                Some(from.as_path()),
+                format!(
+                    "Renaming for Windows: {} -> {}",
+                    from.display(),
+                    to.display()
+                ),
+            );
+        }
+        for (path, problem) in &name_report.skipped {
+            output::warn(
+                WarningCategory::Name,
+                Some(path.as_path()),
+                format!("Skipping {} ({})", path.display(), problem),
+            );
+        }
+
+        observers.on_scan_progress(source, source_files.len(), true);
+        self.check_cancelled()?;
+
         // End scan timing
         if let Some(ref monitor) = self.perf_monitor {
             monitor.lock().unwrap().end_scan();
@@ -444,8 +869,10 @@ impl<T: Transport + 'static> SyncEngine<T> {
             max_size: self.max_size,
         };
 
+        // Large transfers cut short keep their temp file to continue from
+        let partial_dir = (self.resume && !self.dry_run).then(|| resume::partial_dir(&state_dir));
         let resume_state = if self.resume {
-            match ResumeState::load(destination)? {
+            match ResumeState::load(&state_dir)? {
                 Some(state) => {
                     if state.is_compatible_with(&current_flags) {
                         let (completed, total) = state.progress();
@@ -461,12 +888,34 @@ impl<T: Transport + 'static> SyncEngine<T> {
                             );
                         }
                         Some(state)
+                    } else if self.resume_force {
+                        let mut state = state;
+                        let dropped = state.reconcile(current_flags, &source_files)
+                            + self.verify_completed(&mut state, destination).await?;
+                        let (completed, total) = state.progress();
+                        tracing::info!(
+                            "Reconciled resume state with changed flags: {} of {} files still completed, {} to redo",
+                            completed,
+                            total,
+                            dropped
+                        );
+                        if !self.quiet {
+                            println!(
+                                "📋 Resuming previous sync with changed flags ({}/{} files still completed, {} to redo)",
+                                completed, total, dropped
+                            );
+                        }
+                        Some(state)
                     } else {
-                        tracing::warn!("Resume state incompatible (flags changed), starting fresh");
+                        output::warn(
+                            WarningCategory::Resume,
+                            None,
+                            "Resume state incompatible (flags changed), starting fresh (--resume-force reconciles it instead)",
+                        );
                         if !self.quiet {
                             println!("⚠️  Resume state incompatible, starting fresh sync");
                         }
-                        ResumeState::delete(destination)?;
+                        ResumeState::delete(&state_dir)?;
                         Some(ResumeState::new(
                             source.to_path_buf(),
                             destination.to_path_buf(),
@@ -501,36 +950,161 @@ impl<T: Transport + 'static> SyncEngine<T> {
         }
 
         // Plan sync operations
-        let planner = StrategyPlanner::with_comparison_flags(
+        let link_dest = self.link_dest.as_ref().map(|dir| destination.join(dir));
+        let mut planner = StrategyPlanner::with_comparison_flags(
             self.ignore_times,
             self.size_only,
             self.checksum,
-        );
+        )
+        .with_open_noatime(self.open_noatime)
+        .with_link_dest(link_dest.clone())
+        .with_protect_rules(self.filter_engine.clone());
+        // Only mtime comparisons care how far apart the clocks are
+        let compares_mtimes = !(self.checksum || self.size_only || self.ignore_times);
+        if let Some(skew_ms) = self
+            .transport
+            .clock_skew_ms()
+            .await
+            .filter(|_| compares_mtimes)
+        {
+            if skew_ms.unsigned_abs() > strategy::MTIME_TOLERANCE_SECS * 1000 {
+                output::warn(
+                    WarningCategory::Clock,
+                    None,
+                    format!(
+                        "Destination clock is {:.1}s {} the source's; allowing for it when comparing modification times (--checksum compares contents instead)",
+                        skew_ms.unsigned_abs() as f64 / 1000.0,
+                        if skew_ms > 0 { "ahead of" } else { "behind" }
+                    ),
+                );
+                planner = planner.with_clock_skew(skew_ms);
+            }
+        }
         let mut tasks = Vec::with_capacity(source_files.len());
 
-        for file in &source_files {
-            // Skip files that are already completed (if resuming)
-            if !completed_paths.is_empty() && completed_paths.contains(&file.relative_path) {
-                tracing::debug!("Skipping completed file: {}", file.relative_path.display());
-                continue;
+        // Plan files in batches: each batch stats its destination paths in one
+        // call (one round-trip for remote destinations), with several batches
+        // in flight. `buffered` keeps source order, so directories are still
+        // planned before their contents.
+        let pending: Vec<&FileEntry> = source_files
+            .iter()
+            .filter(|file| {
+                // Skip files that are already completed (if resuming)
+                let completed =
+                    !completed_paths.is_empty() && completed_paths.contains(&file.relative_path);
+                if completed {
+                    tracing::debug!("Skipping completed file: {}", file.relative_path.display());
+                }
+                !completed
+            })
+            .collect();
+        let mut planned =
+            futures::stream::iter(pending.chunks(strategy::PLAN_BATCH_SIZE).map(|batch| {
+                planner
+                    .plan_batch_async(batch, destination, &self.transport, checksum_db.as_ref())
+                    .instrument(tracing::trace_span!("plan_batch", files = batch.len()))
+            }))
+            .buffered(strategy::PLAN_CONCURRENCY);
+
+        while let Some(batch) = planned.next().await {
+            tasks.extend(batch?);
+            if self.json {
+                SyncEvent::PlanProgress {
+                    files_planned: tasks.len(),
+                    total_files: pending.len(),
+                }
+                .emit();
             }
-
-            let task = planner
-                .plan_file_async(file, destination, &self.transport, checksum_db.as_ref())
-                .await?;
-            tasks.push(task);
+        }
+        // Release the stream's borrows of the scan results and checksum DB
+        drop(planned);
+
+        // Files modified within --skip-hot are probably still being written
+        // (logs, databases); leave them for a later run instead of copying a
+        // torn file. They stay in the source list, so --delete keeps their
+        // existing destination copies.
+        let mut deferred_hot = Vec::new();
+        if let Some(window) = self.skip_hot {
+            let now = SystemTime::now();
+            tasks.retain(|task| {
+                let hot = matches!(task.action, SyncAction::Create | SyncAction::Update)
+                    && task.source.as_ref().is_some_and(|file| {
+                        !file.is_dir
+                            && now
+                                .duration_since(file.modified)
+                                .is_ok_and(|age| age < window)
+                    });
+                if hot {
+                    tracing::debug!("Deferring hot file: {}", task.dest_path.display());
+                    deferred_hot.push(task.dest_path.clone());
+                }
+                !hot
+            });
         }
 
         // Plan deletions if requested
         if self.delete {
-            let deletions = planner.plan_deletions(&source_files, destination);
+            // A remote destination is listed by the remote side in one go,
+            // rather than looked for on the local filesystem
+            let remote_dest_files = if !self.transport.is_remote_destination() {
+                None
+            } else if self.transport.exists(destination).await? {
+                Some(self.transport.scan_destination(destination).await?)
+            } else {
+                Some(Vec::new())
+            };
+            let dest_file_count = remote_dest_files.as_ref().map(Vec::len);
+            let mut deletions =
+                tracing::debug_span!("plan_deletions").in_scope(|| match remote_dest_files {
+                    Some(dest_files) => planner.plan_deletions_from(&source_files, dest_files),
+                    None => planner.plan_deletions(&source_files, destination),
+                });
+
+            // Entries under unreadable source paths are missing from the scan,
+            // not from the source, so they must never be deleted
+            if !unreadable_paths.is_empty() {
+                deletions.retain(|task| {
+                    let relative = task
+                        .dest_path
+                        .strip_prefix(destination)
+                        .unwrap_or(&task.dest_path);
+                    !unreadable_paths.iter().any(|p| relative.starts_with(p))
+                });
+            }
+
+            // Only entries within --max-depth were compared against the source
+            if self.max_depth.is_some() {
+                deletions.retain(|task| {
+                    let relative = task
+                        .dest_path
+                        .strip_prefix(destination)
+                        .unwrap_or(&task.dest_path);
+                    !self.exceeds_max_depth(relative)
+                });
+            }
+
+            // The trash is kept across syncs, never deleted as an extra
+            deletions
+                .retain(|task| !trash::is_trash(destination, self.trash.as_ref(), &task.dest_path));
+
+            // Nor is a source tree nested in the destination
+            if let Some(nested) = &self.nested_source {
+                let nested = destination.join(nested);
+                deletions.retain(|task| !task.dest_path.starts_with(&nested));
+            }
+
+            // Nor the staging directory of an interrupted --delay-updates sync,
+            // which the next one clears itself
+            deletions.retain(|task| !staging::is_staging(destination, &task.dest_path));
 
             // Apply deletion safety checks
             if !deletions.is_empty() && !self.force_delete {
-                let dest_file_count = scanner::Scanner::new(destination)
-                    .scan()
-                    .map(|files| files.len())
-                    .unwrap_or(0);
+                let dest_file_count = dest_file_count.unwrap_or_else(|| {
+                    scanner::Scanner::new(destination)
+                        .scan()
+                        .map(|files| files.len())
+                        .unwrap_or(0)
+                });
 
                 // Check threshold: prevent mass deletion
                 if dest_file_count > 0 {
@@ -582,13 +1156,105 @@ impl<T: Transport + 'static> SyncEngine<T> {
                 }
             }
 
+            if self.json {
+                for task in &deletions {
+                    SyncEvent::DeletePlanned {
+                        path: task.dest_path.clone(),
+                        size: task.dest_size,
+                    }
+                    .emit();
+                }
+            }
+
+            // A trashed directory takes its entries along, so only the
+            // outermost deleted entries are moved
+            if self.trash.is_some() {
+                // Planned deletions have no size for directories, which
+                // works for remote destinations too
+                let dirs: HashSet<PathBuf> = deletions
+                    .iter()
+                    .filter(|task| task.dest_size.is_none())
+                    .map(|task| task.dest_path.clone())
+                    .collect();
+                deletions.retain(|task| {
+                    !task
+                        .dest_path
+                        .ancestors()
+                        .skip(1)
+                        .any(|parent| dirs.contains(parent))
+                });
+            }
+
             tasks.extend(deletions);
         }
 
+        // Keep the destination within --dest-quota
+        if let Some(dest_quota) = self.dest_quota {
+            let current: u64 = if self.transport.exists(destination).await? {
+                self.transport
+                    .scan_destination(destination)
+                    .await?
+                    .iter()
+                    .filter(|f| !f.is_dir)
+                    .map(|f| f.size)
+                    .sum()
+            } else {
+                0
+            };
+
+            let report = dest_quota.enforce(&mut tasks, current, destination)?;
+            tracing::info!(
+                "Destination usage: {} now, {} after sync (quota {})",
+                crate::error::format_bytes(report.current),
+                crate::error::format_bytes(report.projected),
+                crate::error::format_bytes(dest_quota.limit)
+            );
+
+            if !report.skipped.is_empty() {
+                for path in &report.skipped {
+                    tracing::debug!("Skipping (quota): {}", path.display());
+                }
+                output::warn(
+                    WarningCategory::Quota,
+                    Some(destination),
+                    format!(
+                        "Skipping {} files ({}) to stay within --dest-quota",
+                        report.skipped.len(),
+                        crate::error::format_bytes(report.skipped_bytes)
+                    ),
+                );
+                if !self.quiet {
+                    eprintln!(
+                        "⚠️  Skipping {} largest files ({}) to stay within the destination quota",
+                        report.skipped.len(),
+                        crate::error::format_bytes(report.skipped_bytes)
+                    );
+                }
+            }
+        }
+
+        // Tell tooling why each entry would be copied
+        if self.dry_run && self.json {
+            for task in &tasks {
+                if let (SyncAction::Create | SyncAction::Update, Some(source)) =
+                    (&task.action, &task.source)
+                {
+                    SyncEvent::CopyPlanned {
+                        path: task.dest_path.clone(),
+                        action: task.action.clone(),
+                        reason: task.reason,
+                        size: if source.is_dir { 0 } else { source.size },
+                    }
+                    .emit();
+                }
+            }
+        }
+
         // End plan timing
         if let Some(ref monitor) = self.perf_monitor {
             monitor.lock().unwrap().end_plan();
         }
+        self.check_cancelled()?;
 
         // Emit start event if JSON mode
         if self.json {
@@ -624,7 +1290,30 @@ impl<T: Transport + 'static> SyncEngine<T> {
             bytes_would_add: 0,
             bytes_would_change: 0,
             bytes_would_delete: 0,
-            errors: Vec::new(),
+            errors: if self.scan_error_mode == ScanErrorMode::Report {
+                scan_errors
+                    .iter()
+                    .filter(|e| !e.special)
+                    .map(|e| SyncError {
+                        path: e.path.clone(),
+                        error: e.error.clone(),
+                        action: "scan".to_string(),
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            skipped_busy: Vec::new(),
+            deferred_hot,
+            skipped_specials: scan_errors
+                .iter()
+                .filter(|e| e.special)
+                .map(|e| e.path.clone())
+                .collect(),
+            names: name_report,
+            files_retried: 0,
+            changed_during_transfer: Vec::new(),
+            metadata_not_preserved: output::MetadataReport::default(),
         }));
 
         // Calculate total bytes to transfer (for accurate progress/ETA)
@@ -639,534 +1328,940 @@ impl<T: Transport + 'static> SyncEngine<T> {
             })
             .sum();
 
+        // Planned and network bytes per transfer kind, for a delta-aware ETA
+        let transfer_progress = Arc::new(TransferProgress::new());
+        for (kind, bytes) in tasks.iter().filter_map(progress::planned) {
+            transfer_progress.plan(kind, bytes);
+        }
+
         // Create progress bar (only if not quiet)
         let pb = if self.quiet {
             ProgressBar::hidden()
         } else {
             let pb = ProgressBar::new(total_bytes);
+            // The ETA follows network throughput rather than the bar's rate
+            let eta_progress = Arc::clone(&transfer_progress);
+            let eta = move |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+                eta_progress.write_eta(state, w)
+            };
             pb.set_style(
                 ProgressStyle::default_bar()
                     .template(
                         "{msg}\n{spinner:.green} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})"
                     )
                     .unwrap()
+                    .with_key("eta", eta)
                     .progress_chars("#>-"),
             );
             pb.enable_steady_tick(std::time::Duration::from_millis(100));
             pb
         };
+        observers.push(Arc::new(progress::ProgressObserver::new(pb.clone())));
+        let observers = Arc::new(observers);
 
-        // Create rate limiter if bandwidth limit is set
-        let rate_limiter = self
-            .bwlimit
-            .map(|limit| Arc::new(Mutex::new(RateLimiter::new(limit))));
+        // Create rate limiter if a bandwidth limit is set
+        let rate_limiter = (self.bwlimit.is_some() || self.bwlimit_per_file.is_some())
+            .then(|| Arc::new(BandwidthLimiter::new(self.bwlimit, self.bwlimit_per_file)));
 
         // Create hardlink map for tracking inodes (shared across all parallel transfers)
         let hardlink_map = Arc::new(Mutex::new(std::collections::HashMap::new()));
 
+        // Writes are checked against the destination root, strictly with --sandbox-dest
+        let sandbox = match self.dest_confinement {
+            DestConfinement::Off => None,
+            confinement => Some(Arc::new(sandbox::DestSandbox::new(
+                destination,
+                confinement == DestConfinement::Strict,
+            ))),
+        };
+
+        // Deleted entries of this sync go to one timestamped trash folder
+        let trash = self
+            .trash
+            .as_ref()
+            .map(|target| Arc::new(Trash::new(destination, target)));
+
+        // With --delay-updates, files are written to a staging directory and
+        // only moved into place once all of them are; deletions wait for that
+        let mut staging = None;
+        let mut staged_moves = Vec::new();
+        let mut deferred_deletes = Vec::new();
+        if self.delay_updates && !self.dry_run {
+            // Fails before anything is written where commits aren't supported
+            self.transport.commit_staged(&[]).await?;
+
+            let area = Staging::new(destination);
+            // Left by a sync that stopped before committing
+            if self.transport.exists(area.dir()).await? {
+                self.transport.remove(area.dir(), true).await?;
+            }
+
+            let (deletes, rest) = tasks
+                .into_iter()
+                .partition(|task| task.action == SyncAction::Delete);
+            tasks = rest;
+            deferred_deletes = deletes;
+
+            staged_moves = tasks
+                .iter()
+                .filter(|task| matches!(task.action, SyncAction::Create | SyncAction::Update))
+                .filter(|task| task.source.as_ref().is_some_and(|source| !source.is_dir))
+                .map(|task| area.staged_move(&task.dest_path, task.action == SyncAction::Update))
+                .collect::<Vec<_>>();
+            self.transport
+                .create_dirs(&staged_parents(&staged_moves))
+                .await?;
+            staging = Some(Arc::new(area));
+        }
+        let mut not_committed = None;
+
+        // Create every planned directory up front, parents first, so no file
+        // transfer races its parent's task under high parallelism. Each
+        // directory's own task still runs (for xattrs and ACLs) and reports
+        // any failure, so one here only costs the batching.
+        if !self.dry_run {
+            let dirs = planned_dirs(&tasks, sandbox.as_deref());
+            if let Err(e) = self.transport.create_dirs(&dirs).await {
+                tracing::debug!("Creating {} directories up front failed: {}", dirs.len(), e);
+            }
+        }
+
+        // Privileged xattrs that couldn't be written, reported once after the sync
+        let privileged_xattr_skips: transfer::PrivilegedXattrSkips =
+            Arc::new(Mutex::new(Vec::new()));
+        // Other metadata that couldn't be applied, summarized after the sync
+        let metadata_report: transfer::SharedMetadataReport =
+            Arc::new(Mutex::new(output::MetadataReport::default()));
+
         // Start transfer timing
         if let Some(ref monitor) = self.perf_monitor {
             monitor.lock().unwrap().start_transfer();
         }
 
-        // Parallel execution with semaphore for concurrency control
-        let semaphore = Arc::new(Semaphore::new(self.max_concurrent));
-        let mut handles = Vec::with_capacity(tasks.len());
-
-        for task in tasks {
-            let transport = Arc::clone(&self.transport);
-            let dry_run = self.dry_run;
-            let diff_mode = self.diff_mode;
-            let json = self.json;
-            let stats = Arc::clone(&stats);
-            let pb = pb.clone();
-            let permit = semaphore.clone().acquire_owned().await.unwrap();
-            let rate_limiter = rate_limiter.clone();
-            let _resume_state = Arc::clone(&resume_state);
-            let _dest_path_for_checkpoint = destination.to_path_buf();
-            let verification_mode = self.verification_mode;
-            let verify_on_write = self.verify_on_write;
-            let symlink_mode = self.symlink_mode;
-            let preserve_xattrs = self.preserve_xattrs;
-            let preserve_hardlinks = self.preserve_hardlinks;
-            let preserve_acls = self.preserve_acls;
-            let preserve_flags = self.preserve_flags;
-            let hardlink_map = Arc::clone(&hardlink_map);
-            let perf_monitor = self.perf_monitor.clone();
-
-            let handle = tokio::spawn(async move {
-                let transferrer = Transferrer::new(
-                    transport.as_ref(),
-                    dry_run,
-                    diff_mode,
-                    symlink_mode,
-                    preserve_xattrs,
-                    preserve_hardlinks,
-                    preserve_acls,
-                    preserve_flags,
-                    hardlink_map,
-                );
-                let verifier = IntegrityVerifier::new(verification_mode, verify_on_write);
-
-                // Update progress message (show filename only for cleaner display)
-                let filename = task
-                    .dest_path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or_else(|| task.dest_path.to_str().unwrap_or(""));
-
-                let msg = match &task.action {
-                    SyncAction::Create => format!("Creating: {}", filename),
-                    SyncAction::Update => format!("Updating: {}", filename),
-                    SyncAction::Skip => format!("Skipping: {}", filename),
-                    SyncAction::Delete => format!("Deleting: {}", filename),
-                };
+        // Files that fail are retried in up to `retry_passes` more passes at
+        // the end of the run, getting past transient contention (locked or
+        // briefly unavailable files) without re-running the whole job
+        let mut pending = tasks;
+        let mut pass = 0;
+        loop {
+            // Parallel execution with size-aware scheduling: small and large files
+            // run in separate lanes and idle workers steal from the other lane,
+            // after every task of a higher --priority-pattern tier has started
+            let mut scheduler = SizeScheduler::new(
+                std::mem::take(&mut pending),
+                self.max_concurrent,
+                |task| match task.action {
+                    SyncAction::Create | SyncAction::Update => {
+                        task.source.as_ref().filter(|f| !f.is_dir).map(|f| f.size)
+                    }
+                    SyncAction::Skip | SyncAction::Delete => None,
+                },
+                |task| {
+                    task.source
+                        .as_ref()
+                        .map_or(priority::Priority::Normal, |f| {
+                            priority::priority_of(&self.priority_rules, &f.relative_path, f.is_dir)
+                        })
+                },
+            );
+            let mut handles = Vec::with_capacity(scheduler.remaining());
 
-                if !matches!(task.action, SyncAction::Skip) {
-                    pb.set_message(msg);
-                }
+            if let Some(ref control) = self.control {
+                control.begin(scheduler.remaining());
+            }
 
-                // Execute task
-                let result = match task.action {
-                    SyncAction::Create => {
-                        if let Some(source) = &task.source {
-                            match transferrer.create(source, &task.dest_path).await {
-                                Ok(transfer_result) => {
-                                    let bytes_written = if let Some(ref result) = transfer_result {
-                                        result.bytes_written
-                                    } else {
-                                        0
-                                    };
+            loop {
+                // Stop handing out work while paused (sy ctl pause); running transfers finish
+                if let Some(ref control) = self.control {
+                    if control.is_paused() {
+                        tracing::info!("Sync paused");
+                        pb.set_message("Paused (sy ctl resume to continue)");
+                        // Checkpoint so a sync stopped while paused resumes where it left off
+                        if !self.dry_run {
+                            save_resume_state(&resume_state, &state_dir);
+                        }
+                        tokio::select! {
+                            _ = control.wait_while_paused() => tracing::info!("Sync resumed"),
+                            _ = cancelled(self.cancel.as_ref()) => {}
+                        }
+                    }
+                }
 
-                                    {
-                                        let mut stats = stats.lock().unwrap();
-                                        stats.bytes_transferred += bytes_written;
-                                        stats.files_created += 1;
-
-                                        // Track in performance monitor
-                                        if let Some(monitor) = &perf_monitor {
-                                            monitor.lock().unwrap().add_file_created();
-                                            monitor
-                                                .lock()
-                                                .unwrap()
-                                                .add_bytes_transferred(bytes_written);
-                                            if !source.is_dir {
-                                                monitor.lock().unwrap().add_bytes_read(source.size);
-                                            }
+                // No new tasks once cancelled, even while waiting for a free worker
+                let next = tokio::select! {
+                    biased;
+                    _ = cancelled(self.cancel.as_ref()) => None,
+                    next = scheduler.next() => next,
+                };
+                let Some((task, permit)) = next else {
+                    break;
+                };
+                let transport = Arc::clone(&self.transport);
+                let dry_run = self.dry_run;
+                let diff_mode = self.diff_mode;
+                let stats = Arc::clone(&stats);
+                let observers = Arc::clone(&observers);
+                let transfer_progress = Arc::clone(&transfer_progress);
+                let rate_limiter = rate_limiter.clone();
+                let resume_state = Arc::clone(&resume_state);
+                let _dest_path_for_checkpoint = destination.to_path_buf();
+                let verification_mode = self.verification_mode;
+                let verify_on_write = self.verify_on_write;
+                let symlink_mode = self.symlink_mode;
+                let preserve_xattrs = self.preserve_xattrs;
+                let preserve_hardlinks = self.preserve_hardlinks;
+                let preserve_acls = self.preserve_acls;
+                let preserve_flags = self.preserve_flags;
+                let preserve_atimes = self.preserve_atimes;
+                let preserve_crtimes = self.preserve_crtimes;
+                let preserve_perms = self.preserve_perms;
+                let hardlink_map = Arc::clone(&hardlink_map);
+                let privileged_xattr_skips = Arc::clone(&privileged_xattr_skips);
+                let metadata_report = Arc::clone(&metadata_report);
+                let sandbox = sandbox.clone();
+                let trash = trash.clone();
+                let staging = staging.clone();
+                let link_from = link_dest
+                    .as_ref()
+                    .zip(task.source.as_ref())
+                    .filter(|_| task.reason == PlanReason::LinkDest)
+                    .map(|(dir, source)| dir.join(&source.relative_path));
+                let immutable_mode = self.immutable_mode;
+                let check_source_changes = self.check_source_changes;
+                let whole_file = self.whole_file;
+                let partial_dir = partial_dir.clone();
+                let open_noatime = self.open_noatime;
+                let perf_monitor = self.perf_monitor.clone();
+                let skip_busy = self.skip_busy;
+                let wait_busy = self.wait_busy;
+                let acl_map = self.acl_map;
+                let control = self.control.clone();
+                let fault_injector = self.fault_injector.clone();
+                let cancel = self.cancel.clone();
+
+                let span = tracing::trace_span!(
+                    "transfer",
+                    path = %task.dest_path.display(),
+                    action = ?task.action
+                );
+                let transfer = async move {
+                    // Hold a share of the bandwidth limit while sending
+                    let lane = match task.action {
+                        SyncAction::Create | SyncAction::Update => rate_limiter
+                            .as_ref()
+                            .map(|limiter| Arc::new(limiter.lane())),
+                        SyncAction::Skip | SyncAction::Delete => None,
+                    };
+                    let progress = (!dry_run).then(|| {
+                        let progress = observers.file_progress(task.dest_path.clone());
+                        match &lane {
+                            Some(lane) => lane.pace(progress),
+                            None => progress,
+                        }
+                    });
+                    let transferrer = Transferrer::new(
+                        transport.as_ref(),
+                        dry_run,
+                        diff_mode,
+                        symlink_mode,
+                        preserve_xattrs,
+                        preserve_hardlinks,
+                        preserve_acls,
+                        preserve_flags,
+                        hardlink_map,
+                    )
+                    .with_privileged_xattr_skips(privileged_xattr_skips)
+                    .with_metadata_report(metadata_report)
+                    .with_acl_map(acl_map)
+                    .with_preserve_times(preserve_atimes, preserve_crtimes)
+                    .with_preserve_perms(preserve_perms)
+                    .with_sandbox(sandbox)
+                    .with_trash(trash)
+                    .with_immutable_mode(immutable_mode)
+                    .with_source_check(check_source_changes)
+                    .with_whole_file(whole_file)
+                    .with_partial_dir(partial_dir)
+                    .with_why(output::why().then(|| task.why()))
+                    .with_fault_injector(fault_injector)
+                    .with_progress(progress);
+                    let verifier = IntegrityVerifier::new(verification_mode, verify_on_write)
+                        .open_noatime(open_noatime);
+
+                    // Staged files are written beside the destination tree until the commit
+                    let write_path = match (&staging, &task.source) {
+                        (Some(staging), Some(source)) if !source.is_dir => {
+                            staging.staged_path(&task.dest_path)
+                        }
+                        _ => task.dest_path.clone(),
+                    };
+
+                    observers.on_file_start(&task);
+
+                    // Execute task
+                    let mut wire_bytes = None;
+                    let result = match task.action {
+                        SyncAction::Create => {
+                            if let Some(source) = &task.source {
+                                match busy::retry_busy(wait_busy, &task.dest_path, || async {
+                                    match &link_from {
+                                        Some(link_from) => {
+                                            transferrer.link(source, link_from, &write_path).await
                                         }
+                                        None => transferrer.create(source, &write_path).await,
+                                    }
+                                })
+                                .await
+                                {
+                                    Ok(transfer_result) => {
+                                        let bytes_written =
+                                            if let Some(ref result) = transfer_result {
+                                                result.bytes_written
+                                            } else {
+                                                0
+                                            };
+                                        wire_bytes = Some(
+                                            transfer_result
+                                                .as_ref()
+                                                .map_or(0, |result| result.wire_bytes()),
+                                        );
+
+                                        {
+                                            let mut stats = stats.lock().unwrap();
+                                            stats.bytes_transferred += bytes_written;
+                                            stats.files_created += 1;
+
+                                            // Track in performance monitor
+                                            if let Some(monitor) = &perf_monitor {
+                                                monitor.lock().unwrap().add_file_created();
+                                                monitor
+                                                    .lock()
+                                                    .unwrap()
+                                                    .add_bytes_transferred(bytes_written);
+                                                if !source.is_dir {
+                                                    monitor
+                                                        .lock()
+                                                        .unwrap()
+                                                        .add_bytes_read(source.size);
+                                                }
+                                            }
 
-                                        // In dry-run mode, track bytes that would be added
-                                        if dry_run && !source.is_dir {
-                                            stats.bytes_would_add += source.size;
-                                        }
+                                            // In dry-run mode, track bytes that would be added
+                                            if dry_run && !source.is_dir {
+                                                stats.bytes_would_add += source.size;
+                                            }
 
-                                        // Track compression usage and savings
-                                        if let Some(ref result) = transfer_result {
-                                            if result.compression_used {
-                                                stats.files_compressed += 1;
-
-                                                // Calculate bytes saved (uncompressed - compressed)
-                                                if let Some(transferred) = result.transferred_bytes
-                                                {
-                                                    let bytes_saved = result
-                                                        .bytes_written
-                                                        .saturating_sub(transferred);
-                                                    stats.compression_bytes_saved += bytes_saved;
+                                            // Track compression usage and savings
+                                            if let Some(ref result) = transfer_result {
+                                                if result.compression_used {
+                                                    stats.files_compressed += 1;
+
+                                                    // Calculate bytes saved (uncompressed - compressed)
+                                                    if let Some(transferred) =
+                                                        result.transferred_bytes
+                                                    {
+                                                        let bytes_saved = result
+                                                            .bytes_written
+                                                            .saturating_sub(transferred);
+                                                        stats.compression_bytes_saved +=
+                                                            bytes_saved;
+                                                    }
                                                 }
                                             }
                                         }
-                                    }
 
-                                    // Apply rate limiting if enabled (outside stats lock)
-                                    if let Some(ref limiter) = rate_limiter {
-                                        if bytes_written > 0 {
-                                            let sleep_duration =
-                                                limiter.lock().unwrap().consume(bytes_written);
+                                        // Apply rate limiting if enabled (outside stats lock)
+                                        if let Some(ref lane) = lane {
+                                            let sleep_duration = lane.delay(bytes_written);
                                             if sleep_duration > Duration::ZERO {
                                                 tokio::time::sleep(sleep_duration).await;
                                             }
                                         }
-                                    }
 
-                                    // Verify transfer if verification is enabled (skip directories)
-                                    if verification_mode != ChecksumType::None
-                                        && !dry_run
-                                        && !source.is_dir
-                                    {
-                                        let source_path = &source.path;
-                                        let dest_path = &task.dest_path;
-
-                                        match verifier.verify_transfer(source_path, dest_path) {
-                                            Ok(verified) => {
-                                                let mut stats = stats.lock().unwrap();
-                                                if verified {
-                                                    stats.files_verified += 1;
-                                                } else {
-                                                    stats.verification_failures += 1;
-                                                    tracing::warn!(
-                                                        "Verification failed for {}: checksums do not match",
-                                                        dest_path.display()
+                                        // Verify transfer if verification is enabled (skip directories)
+                                        if verification_mode != ChecksumType::None
+                                            && !dry_run
+                                            && !source.is_dir
+                                        {
+                                            let source_path = &source.path;
+                                            let dest_path = &task.dest_path;
+
+                                            let verified = {
+                                                let verifier = verifier.clone();
+                                                let (src, dst) =
+                                                    (source_path.clone(), dest_path.clone());
+                                                crate::cpu::run(move || verifier.verify_transfer(&src, &dst))
+                                                    .instrument(tracing::trace_span!("verify", path = %dest_path.display()))
+                                                    .await
+                                            };
+                                            match verified {
+                                                Ok(verified) => {
+                                                    let mut stats = stats.lock().unwrap();
+                                                    if verified {
+                                                        stats.files_verified += 1;
+                                                    } else {
+                                                        stats.verification_failures += 1;
+                                                        output::warn(
+                                                            WarningCategory::Verification,
+                                                            Some(dest_path.as_path()),
+                                                            format!(
+                                                                "Verification failed for {}: checksums do not match",
+                                                                dest_path.display()
+                                                            ),
+                                                        );
+                                                    }
+                                                }
+                                                Err(e) => {
+                                                    output::warn(
+                                                        WarningCategory::Verification,
+                                                        Some(dest_path.as_path()),
+                                                        format!(
+                                                            "Verification error for {}: {}",
+                                                            dest_path.display(),
+                                                            e
+                                                        ),
                                                     );
+                                                    let mut stats = stats.lock().unwrap();
+                                                    stats.verification_failures += 1;
                                                 }
                                             }
-                                            Err(e) => {
-                                                tracing::warn!(
-                                                    "Verification error for {}: {}",
-                                                    dest_path.display(),
-                                                    e
-                                                );
-                                                let mut stats = stats.lock().unwrap();
-                                                stats.verification_failures += 1;
-                                            }
                                         }
-                                    }
 
-                                    // Emit JSON event if enabled
-                                    if json {
-                                        SyncEvent::Create {
-                                            path: task.dest_path.clone(),
-                                            size: source.size,
-                                            bytes_transferred: bytes_written,
-                                        }
-                                        .emit();
-                                    }
+                                        observers.on_file_done(&task, transfer_result.as_ref());
 
-                                    Ok(())
-                                }
-                                Err(e) => {
-                                    // Record error
-                                    {
-                                        let mut stats = stats.lock().unwrap();
-                                        stats.errors.push(SyncError {
-                                            path: task.dest_path.clone(),
-                                            error: e.to_string(),
-                                            action: "create".to_string(),
-                                        });
+                                        Ok(())
+                                    }
+                                    Err(e) if skip_busy && busy::is_busy(&e) => {
+                                        output::warn(
+                                            WarningCategory::Busy,
+                                            Some(task.dest_path.as_path()),
+                                            format!(
+                                                "Skipping busy file {}: {}",
+                                                task.dest_path.display(),
+                                                e
+                                            ),
+                                        );
+                                        stats
+                                            .lock()
+                                            .unwrap()
+                                            .skipped_busy
+                                            .push(task.dest_path.clone());
+                                        Ok(())
+                                    }
+                                    Err(e) => {
+                                        observers.on_error(&task, &e);
+                                        // Record error
+                                        {
+                                            let mut stats = stats.lock().unwrap();
+                                            stats.errors.push(SyncError {
+                                                path: task.dest_path.clone(),
+                                                error: e.to_string(),
+                                                action: "create".to_string(),
+                                            });
+                                            if matches!(
+                                                e,
+                                                crate::error::SyncError::SourceChanged { .. }
+                                            ) && !stats
+                                                .changed_during_transfer
+                                                .contains(&source.path)
+                                            {
+                                                stats
+                                                    .changed_during_transfer
+                                                    .push(source.path.clone());
+                                            }
+                                        }
+                                        Err(e)
                                     }
-                                    Err(e)
                                 }
+                            } else {
+                                Ok(())
                             }
-                        } else {
-                            Ok(())
                         }
-                    }
-                    SyncAction::Update => {
-                        if let Some(source) = &task.source {
-                            match transferrer.update(source, &task.dest_path).await {
-                                Ok(transfer_result) => {
-                                    let bytes_written = if let Some(ref result) = transfer_result {
-                                        result.bytes_written
-                                    } else {
-                                        0
-                                    };
-
-                                    {
-                                        let mut stats = stats.lock().unwrap();
-                                        if let Some(ref result) = transfer_result {
-                                            stats.bytes_transferred += result.bytes_written;
-
-                                            // Track delta sync usage and savings
-                                            if result.used_delta() {
-                                                stats.files_delta_synced += 1;
-
-                                                // Calculate bytes saved (full file size - literal bytes)
-                                                if let Some(literal_bytes) = result.literal_bytes {
-                                                    let bytes_saved = result
-                                                        .bytes_written
-                                                        .saturating_sub(literal_bytes);
-                                                    stats.delta_bytes_saved += bytes_saved;
+                        SyncAction::Update => {
+                            if let Some(source) = &task.source {
+                                match busy::retry_busy(wait_busy, &task.dest_path, || {
+                                    transferrer.update(source, &write_path)
+                                })
+                                .await
+                                {
+                                    Ok(transfer_result) => {
+                                        let bytes_written =
+                                            if let Some(ref result) = transfer_result {
+                                                result.bytes_written
+                                            } else {
+                                                0
+                                            };
+                                        wire_bytes = Some(
+                                            transfer_result
+                                                .as_ref()
+                                                .map_or(0, |result| result.wire_bytes()),
+                                        );
+
+                                        {
+                                            let mut stats = stats.lock().unwrap();
+                                            if let Some(ref result) = transfer_result {
+                                                stats.bytes_transferred += result.bytes_written;
+
+                                                // Track delta sync usage and savings
+                                                if result.used_delta() {
+                                                    stats.files_delta_synced += 1;
+
+                                                    // Calculate bytes saved (full file size - literal bytes)
+                                                    if let Some(literal_bytes) =
+                                                        result.literal_bytes
+                                                    {
+                                                        let bytes_saved = result
+                                                            .bytes_written
+                                                            .saturating_sub(literal_bytes);
+                                                        stats.delta_bytes_saved += bytes_saved;
+                                                    }
                                                 }
 
-                                                if let Some(ratio) = result.compression_ratio() {
-                                                    pb.set_message(format!(
-                                                        "Updating: {} (delta: {:.1}% literal)",
-                                                        filename, ratio
-                                                    ));
+                                                // Track compression usage and savings
+                                                if result.compression_used {
+                                                    stats.files_compressed += 1;
+
+                                                    // Calculate bytes saved (uncompressed - compressed)
+                                                    if let Some(transferred) =
+                                                        result.transferred_bytes
+                                                    {
+                                                        let bytes_saved = result
+                                                            .bytes_written
+                                                            .saturating_sub(transferred);
+                                                        stats.compression_bytes_saved +=
+                                                            bytes_saved;
+                                                    }
                                                 }
                                             }
-
-                                            // Track compression usage and savings
-                                            if result.compression_used {
-                                                stats.files_compressed += 1;
-
-                                                // Calculate bytes saved (uncompressed - compressed)
-                                                if let Some(transferred) = result.transferred_bytes
-                                                {
-                                                    let bytes_saved = result
-                                                        .bytes_written
-                                                        .saturating_sub(transferred);
-                                                    stats.compression_bytes_saved += bytes_saved;
+                                            stats.files_updated += 1;
+
+                                            // Track in performance monitor
+                                            if let Some(monitor) = &perf_monitor {
+                                                monitor.lock().unwrap().add_file_updated();
+                                                monitor
+                                                    .lock()
+                                                    .unwrap()
+                                                    .add_bytes_transferred(bytes_written);
+                                                if !source.is_dir {
+                                                    monitor
+                                                        .lock()
+                                                        .unwrap()
+                                                        .add_bytes_read(source.size);
                                                 }
                                             }
-                                        }
-                                        stats.files_updated += 1;
-
-                                        // Track in performance monitor
-                                        if let Some(monitor) = &perf_monitor {
-                                            monitor.lock().unwrap().add_file_updated();
-                                            monitor
-                                                .lock()
-                                                .unwrap()
-                                                .add_bytes_transferred(bytes_written);
-                                            if !source.is_dir {
-                                                monitor.lock().unwrap().add_bytes_read(source.size);
-                                            }
-                                        }
 
-                                        // In dry-run mode, track bytes that would be changed
-                                        if dry_run && !source.is_dir {
-                                            stats.bytes_would_change += source.size;
+                                            // In dry-run mode, track bytes that would be changed
+                                            if dry_run && !source.is_dir {
+                                                stats.bytes_would_change += source.size;
+                                            }
                                         }
-                                    }
 
-                                    // Apply rate limiting if enabled (outside stats lock)
-                                    if let Some(ref limiter) = rate_limiter {
-                                        if bytes_written > 0 {
-                                            let sleep_duration =
-                                                limiter.lock().unwrap().consume(bytes_written);
+                                        // Apply rate limiting if enabled (outside stats lock)
+                                        if let Some(ref lane) = lane {
+                                            let sleep_duration = lane.delay(bytes_written);
                                             if sleep_duration > Duration::ZERO {
                                                 tokio::time::sleep(sleep_duration).await;
                                             }
                                         }
-                                    }
 
-                                    // Verify transfer if verification is enabled (skip directories)
-                                    if verification_mode != ChecksumType::None
-                                        && !dry_run
-                                        && !source.is_dir
-                                    {
-                                        let source_path = &source.path;
-                                        let dest_path = &task.dest_path;
-
-                                        match verifier.verify_transfer(source_path, dest_path) {
-                                            Ok(verified) => {
-                                                let mut stats = stats.lock().unwrap();
-                                                if verified {
-                                                    stats.files_verified += 1;
-                                                } else {
-                                                    stats.verification_failures += 1;
-                                                    tracing::warn!(
-                                                        "Verification failed for {}: checksums do not match",
-                                                        dest_path.display()
+                                        // Verify transfer if verification is enabled (skip directories)
+                                        if verification_mode != ChecksumType::None
+                                            && !dry_run
+                                            && !source.is_dir
+                                        {
+                                            let source_path = &source.path;
+                                            let dest_path = &task.dest_path;
+
+                                            let verified = {
+                                                let verifier = verifier.clone();
+                                                let (src, dst) =
+                                                    (source_path.clone(), dest_path.clone());
+                                                crate::cpu::run(move || verifier.verify_transfer(&src, &dst))
+                                                    .instrument(tracing::trace_span!("verify", path = %dest_path.display()))
+                                                    .await
+                                            };
+                                            match verified {
+                                                Ok(verified) => {
+                                                    let mut stats = stats.lock().unwrap();
+                                                    if verified {
+                                                        stats.files_verified += 1;
+                                                    } else {
+                                                        stats.verification_failures += 1;
+                                                        output::warn(
+                                                            WarningCategory::Verification,
+                                                            Some(dest_path.as_path()),
+                                                            format!(
+                                                                "Verification failed for {}: checksums do not match",
+                                                                dest_path.display()
+                                                            ),
+                                                        );
+                                                    }
+                                                }
+                                                Err(e) => {
+                                                    output::warn(
+                                                        WarningCategory::Verification,
+                                                        Some(dest_path.as_path()),
+                                                        format!(
+                                                            "Verification error for {}: {}",
+                                                            dest_path.display(),
+                                                            e
+                                                        ),
                                                     );
+                                                    let mut stats = stats.lock().unwrap();
+                                                    stats.verification_failures += 1;
                                                 }
                                             }
-                                            Err(e) => {
-                                                tracing::warn!(
-                                                    "Verification error for {}: {}",
-                                                    dest_path.display(),
-                                                    e
-                                                );
-                                                let mut stats = stats.lock().unwrap();
-                                                stats.verification_failures += 1;
+                                        }
+
+                                        observers.on_file_done(&task, transfer_result.as_ref());
+
+                                        Ok(())
+                                    }
+                                    Err(e) if skip_busy && busy::is_busy(&e) => {
+                                        output::warn(
+                                            WarningCategory::Busy,
+                                            Some(task.dest_path.as_path()),
+                                            format!(
+                                                "Skipping busy file {}: {}",
+                                                task.dest_path.display(),
+                                                e
+                                            ),
+                                        );
+                                        stats
+                                            .lock()
+                                            .unwrap()
+                                            .skipped_busy
+                                            .push(task.dest_path.clone());
+                                        Ok(())
+                                    }
+                                    Err(e) => {
+                                        observers.on_error(&task, &e);
+                                        // Record error
+                                        {
+                                            let mut stats = stats.lock().unwrap();
+                                            stats.errors.push(SyncError {
+                                                path: task.dest_path.clone(),
+                                                error: e.to_string(),
+                                                action: "update".to_string(),
+                                            });
+                                            if matches!(
+                                                e,
+                                                crate::error::SyncError::SourceChanged { .. }
+                                            ) && !stats
+                                                .changed_during_transfer
+                                                .contains(&source.path)
+                                            {
+                                                stats
+                                                    .changed_during_transfer
+                                                    .push(source.path.clone());
                                             }
                                         }
+                                        Err(e)
                                     }
+                                }
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        SyncAction::Skip => {
+                            {
+                                let mut stats = stats.lock().unwrap();
+                                stats.files_skipped += 1;
+                            }
+                            if output::why() {
+                                tracing::debug!(
+                                    "Skipped: {} ({})",
+                                    task.dest_path.display(),
+                                    task.why()
+                                );
+                            }
+                            observers.on_file_done(&task, None);
 
-                                    // Emit JSON event if enabled
-                                    if json {
-                                        let delta_used = transfer_result
-                                            .as_ref()
-                                            .map(|r| r.used_delta())
-                                            .unwrap_or(false);
-                                        SyncEvent::Update {
-                                            path: task.dest_path.clone(),
-                                            size: source.size,
-                                            bytes_transferred: bytes_written,
-                                            delta_used,
-                                        }
-                                        .emit();
+                            Ok(())
+                        }
+                        SyncAction::Delete => {
+                            let is_dir = task.dest_path.is_dir();
+
+                            // In dry-run mode, track bytes that would be deleted
+                            if dry_run && !is_dir {
+                                if let Ok(metadata) = std::fs::metadata(&task.dest_path) {
+                                    let mut stats = stats.lock().unwrap();
+                                    stats.bytes_would_delete += metadata.len();
+                                }
+                            }
+
+                            match transferrer.delete(&task.dest_path, is_dir).await {
+                                Ok(_) => {
+                                    {
+                                        let mut stats = stats.lock().unwrap();
+                                        stats.files_deleted += 1;
+                                    }
+
+                                    // Track in performance monitor
+                                    if let Some(monitor) = &perf_monitor {
+                                        monitor.lock().unwrap().add_file_deleted();
                                     }
+                                    observers.on_file_done(&task, None);
 
                                     Ok(())
                                 }
                                 Err(e) => {
+                                    observers.on_error(&task, &e);
                                     // Record error
                                     {
                                         let mut stats = stats.lock().unwrap();
                                         stats.errors.push(SyncError {
                                             path: task.dest_path.clone(),
                                             error: e.to_string(),
-                                            action: "update".to_string(),
+                                            action: "delete".to_string(),
                                         });
                                     }
                                     Err(e)
                                 }
                             }
-                        } else {
-                            Ok(())
-                        }
-                    }
-                    SyncAction::Skip => {
-                        {
-                            let mut stats = stats.lock().unwrap();
-                            stats.files_skipped += 1;
                         }
+                    };
 
-                        // Emit JSON event if enabled
-                        if json {
-                            SyncEvent::Skip {
-                                path: task.dest_path.clone(),
-                                reason: "up_to_date".to_string(),
-                            }
-                            .emit();
+                    // Source bytes this task accounts for (the progress bar's unit)
+                    let bytes_for_progress = match &task.action {
+                        SyncAction::Create | SyncAction::Update => {
+                            task.source.as_ref().map(|f| f.size).unwrap_or(0)
                         }
-
-                        Ok(())
+                        _ => 0,
+                    };
+                    if let Some((kind, planned)) = progress::planned(&task) {
+                        transfer_progress.finish(kind, planned, wire_bytes);
                     }
-                    SyncAction::Delete => {
-                        let is_dir = task.dest_path.is_dir();
-
-                        // In dry-run mode, track bytes that would be deleted
-                        if dry_run && !is_dir {
-                            if let Ok(metadata) = std::fs::metadata(&task.dest_path) {
-                                let mut stats = stats.lock().unwrap();
-                                stats.bytes_would_delete += metadata.len();
-                            }
-                        }
-
-                        match transferrer.delete(&task.dest_path, is_dir).await {
-                            Ok(_) => {
-                                {
-                                    let mut stats = stats.lock().unwrap();
-                                    stats.files_deleted += 1;
-                                }
-
-                                // Track in performance monitor
-                                if let Some(monitor) = &perf_monitor {
-                                    monitor.lock().unwrap().add_file_deleted();
-                                }
-
-                                // Emit JSON event if enabled
-                                if json {
-                                    SyncEvent::Delete {
-                                        path: task.dest_path.clone(),
-                                    }
-                                    .emit();
-                                }
 
-                                Ok(())
-                            }
-                            Err(e) => {
-                                // Record error
-                                {
-                                    let mut stats = stats.lock().unwrap();
-                                    stats.errors.push(SyncError {
-                                        path: task.dest_path.clone(),
-                                        error: e.to_string(),
-                                        action: "delete".to_string(),
-                                    });
-                                }
-                                Err(e)
+                    // Record completed transfers for resume checkpoints and sy ctl status
+                    let completed_action = match (&result, &task.action) {
+                        (Ok(()), SyncAction::Create) => Some("create"),
+                        (Ok(()), SyncAction::Update) => Some("update"),
+                        _ => None,
+                    };
+                    if let (Some(action), Some(source)) = (completed_action, &task.source) {
+                        let busy_skipped = skip_busy
+                            && stats.lock().unwrap().skipped_busy.contains(&task.dest_path);
+                        if !dry_run && !busy_skipped {
+                            if let Some(state) = resume_state.lock().unwrap().as_mut() {
+                                state.add_completed_file(
+                                    CompletedFile::new(
+                                        source.relative_path.clone(),
+                                        action,
+                                        source.size,
+                                    ),
+                                    bytes_for_progress,
+                                );
                             }
                         }
                     }
+                    if let Some(control) = &control {
+                        control.record_processed(bytes_for_progress);
+                    }
+
+                    drop(permit);
+                    (task, result)
                 };
 
-                // Increment progress by bytes written (for byte-based progress bar)
-                let bytes_for_progress = match &task.action {
-                    SyncAction::Create | SyncAction::Update => {
-                        task.source.as_ref().map(|f| f.size).unwrap_or(0)
+                // A cancelled sync drops running transfers at their next await
+                // point (between chunks of a streaming copy); files are written
+                // to a temp file and renamed, so none is left half-written
+                let transfer = async move {
+                    tokio::select! {
+                        biased;
+                        _ = cancelled(cancel.as_ref()) => None,
+                        done = transfer => Some(done),
                     }
-                    _ => 0,
                 };
-                pb.inc(bytes_for_progress);
-                drop(permit);
-                result
-            });
+                handles.push(tokio::spawn(transfer.instrument(span)));
+            }
 
-            handles.push(handle);
-        }
+            // Collect all results
+            let results = futures::future::join_all(handles).await;
 
-        // Collect all results
-        let results = futures::future::join_all(handles).await;
+            if let Some(ref monitor) = self.perf_monitor {
+                monitor
+                    .lock()
+                    .unwrap()
+                    .record_workers(scheduler.worker_usage(), scheduler.blocked());
+            }
 
-        // End transfer timing
-        if let Some(ref monitor) = self.perf_monitor {
-            monitor.lock().unwrap().end_transfer();
-        }
+            // Check for errors and count them
+            let mut error_count = 0;
+            let mut first_error = None;
+            let mut all_errors = Vec::new();
+            let mut failed = Vec::new();
+
+            for result in results {
+                match result {
+                    Ok(None) => {}              // Cancelled
+                    Ok(Some((_, Ok(())))) => {} // Success
+                    Ok(Some((task, Err(e)))) => {
+                        failed.push(task);
+                        error_count += 1;
+                        if first_error.is_none() {
+                            first_error = Some(e.to_string());
+                        }
+                        all_errors.push(format!("{}", e));
 
-        // Check for errors and count them
-        let mut error_count = 0;
-        let mut first_error = None;
-        let mut all_errors = Vec::new();
+                        tracing::error!("Sync error: {}", e);
 
-        for result in results {
-            match result {
-                Ok(Ok(())) => {} // Success
-                Ok(Err(e)) => {
-                    error_count += 1;
-                    if first_error.is_none() {
-                        first_error = Some(e.to_string());
-                    }
-                    all_errors.push(format!("{}", e));
+                        // Check if we've exceeded the error threshold
+                        if self.max_errors > 0 && error_count >= self.max_errors {
+                            tracing::error!(
+                                "Error threshold exceeded: {} errors (max: {})",
+                                error_count,
+                                self.max_errors
+                            );
 
-                    tracing::error!("Sync error: {}", e);
+                            if !self.quiet {
+                                eprintln!(
+                                    "⚠️  ERROR: {} errors occurred (threshold: {}). Aborting sync.",
+                                    error_count, self.max_errors
+                                );
+                            }
 
-                    // Check if we've exceeded the error threshold
-                    if self.max_errors > 0 && error_count >= self.max_errors {
-                        tracing::error!(
-                            "Error threshold exceeded: {} errors (max: {})",
-                            error_count,
-                            self.max_errors
-                        );
+                            pb.finish_with_message("Sync aborted due to errors");
 
-                        if !self.quiet {
-                            eprintln!(
-                                "⚠️  ERROR: {} errors occurred (threshold: {}). Aborting sync.",
-                                error_count, self.max_errors
+                            let message = format!(
+                                "Error threshold exceeded: {} errors (max: {}). First error: {}",
+                                error_count,
+                                self.max_errors,
+                                first_error.unwrap_or_else(|| "Unknown".to_string())
                             );
+                            return Err(crate::error::SyncError::Io(std::io::Error::other(
+                                message,
+                            )));
+                        }
+                    }
+                    Err(e) => {
+                        error_count += 1;
+                        let error_msg = format!("Task panicked: {}", e);
+                        if first_error.is_none() {
+                            first_error = Some(error_msg.clone());
                         }
+                        all_errors.push(error_msg.clone());
 
-                        pb.finish_with_message("Sync aborted due to errors");
+                        tracing::error!("{}", error_msg);
 
-                        return Err(crate::error::SyncError::Io(std::io::Error::other(format!(
-                            "Error threshold exceeded: {} errors (max: {}). First error: {}",
-                            error_count,
-                            self.max_errors,
-                            first_error.unwrap_or_else(|| "Unknown".to_string())
-                        ))));
-                    }
-                }
-                Err(e) => {
-                    error_count += 1;
-                    let error_msg = format!("Task panicked: {}", e);
-                    if first_error.is_none() {
-                        first_error = Some(error_msg.clone());
-                    }
-                    all_errors.push(error_msg.clone());
+                        // Check if we've exceeded the error threshold
+                        if self.max_errors > 0 && error_count >= self.max_errors {
+                            tracing::error!(
+                                "Error threshold exceeded: {} errors (max: {})",
+                                error_count,
+                                self.max_errors
+                            );
 
-                    tracing::error!("{}", error_msg);
+                            if !self.quiet {
+                                eprintln!(
+                                    "⚠️  ERROR: {} errors occurred (threshold: {}). Aborting sync.",
+                                    error_count, self.max_errors
+                                );
+                            }
 
-                    // Check if we've exceeded the error threshold
-                    if self.max_errors > 0 && error_count >= self.max_errors {
-                        tracing::error!(
-                            "Error threshold exceeded: {} errors (max: {})",
-                            error_count,
-                            self.max_errors
-                        );
+                            pb.finish_with_message("Sync aborted due to errors");
 
-                        if !self.quiet {
-                            eprintln!(
-                                "⚠️  ERROR: {} errors occurred (threshold: {}). Aborting sync.",
-                                error_count, self.max_errors
+                            let message = format!(
+                                "Error threshold exceeded: {} errors (max: {}). First error: {}",
+                                error_count,
+                                self.max_errors,
+                                first_error.unwrap_or_else(|| "Unknown".to_string())
                             );
+                            return Err(crate::error::SyncError::Io(std::io::Error::other(
+                                message,
+                            )));
                         }
+                    }
+                }
+            }
 
-                        pb.finish_with_message("Sync aborted due to errors");
-
-                        return Err(crate::error::SyncError::Io(std::io::Error::other(format!(
-                            "Error threshold exceeded: {} errors (max: {}). First error: {}",
-                            error_count,
-                            self.max_errors,
-                            first_error.unwrap_or_else(|| "Unknown".to_string())
-                        ))));
+            if failed.is_empty() || pass == self.retry_passes || self.is_cancelled() {
+                if let Some(staging) = staging.take() {
+                    let commit = failed.is_empty() && !self.is_cancelled();
+                    let committed = if commit {
+                        pb.set_message("Committing staged files");
+                        self.transport.commit_staged(&staged_moves).await
+                    } else {
+                        Ok(0)
+                    };
+                    if let Err(e) = self.transport.remove(staging.dir(), true).await {
+                        tracing::warn!("Failed to remove {}: {}", staging.dir().display(), e);
+                    }
+                    match committed {
+                        Ok(moved) if commit => {
+                            tracing::info!("Committed {} staged files", moved);
+                            if !deferred_deletes.is_empty() {
+                                pending = std::mem::take(&mut deferred_deletes);
+                                pass = 0;
+                                continue;
+                            }
+                        }
+                        Ok(_) if !self.is_cancelled() => not_committed = Some(failed.len()),
+                        Ok(_) => {}
+                        Err(e) => {
+                            pb.finish_with_message("Sync not committed");
+                            return Err(e);
+                        }
                     }
                 }
+                break;
+            }
+            pass += 1;
+            tracing::info!(
+                "Retrying {} failed files (pass {} of {})",
+                failed.len(),
+                pass,
+                self.retry_passes
+            );
+            if !self.quiet {
+                pb.set_message(format!("Retrying {} failed files", failed.len()));
+            }
+
+            // Errors are recorded again by the retry if it fails too
+            {
+                let mut stats = stats.lock().unwrap();
+                let retried: HashSet<&Path> =
+                    failed.iter().map(|task| task.dest_path.as_path()).collect();
+                stats
+                    .errors
+                    .retain(|e| e.action == "scan" || !retried.contains(e.path.as_path()));
+                if pass == 1 {
+                    stats.files_retried = failed.len();
+                }
+            }
+            pb.inc_length(
+                failed
+                    .iter()
+                    .filter(|task| matches!(task.action, SyncAction::Create | SyncAction::Update))
+                    .filter_map(|task| task.source.as_ref())
+                    .map(|f| f.size)
+                    .sum(),
+            );
+            for (kind, bytes) in failed.iter().filter_map(progress::planned) {
+                transfer_progress.plan(kind, bytes);
+            }
+            pending = failed;
+        }
+
+        // End transfer timing
+        if let Some(ref monitor) = self.perf_monitor {
+            monitor.lock().unwrap().end_transfer();
+        }
+
+        // Keep what was done so --resume picks up from here
+        if self.is_cancelled() {
+            tracing::info!("Sync cancelled");
+            pb.finish_with_message("Sync cancelled");
+            if !self.dry_run {
+                save_resume_state(&resume_state, &state_dir);
             }
+            return Err(crate::error::SyncError::Cancelled);
         }
 
-        pb.finish_with_message("Sync complete");
+        pb.finish_with_message(if not_committed.is_some() {
+            "Sync not committed"
+        } else {
+            "Sync complete"
+        });
 
         // Extract final stats before reporting errors
         let mut final_stats = Arc::try_unwrap(stats).unwrap().into_inner().unwrap();
@@ -1202,6 +2297,36 @@ impl<T: Transport + 'static> SyncEngine<T> {
             }
         }
 
+        // Metadata that couldn't be applied: one warning per kind and reason
+        // rather than one per file
+        final_stats.metadata_not_preserved = std::mem::take(&mut *metadata_report.lock().unwrap());
+        final_stats.metadata_not_preserved.warn();
+
+        if let Some(failed) = not_committed {
+            return Err(crate::error::SyncError::NotCommitted { failed });
+        }
+
+        // One warning for all privileged xattrs (e.g. file capabilities) that were dropped
+        let privileged_xattr_skips = std::mem::take(&mut *privileged_xattr_skips.lock().unwrap());
+        if !privileged_xattr_skips.is_empty() {
+            let mut names: Vec<&str> = privileged_xattr_skips
+                .iter()
+                .flat_map(|(_, names)| names.iter().map(String::as_str))
+                .collect();
+            names.sort_unstable();
+            names.dedup();
+
+            output::warn(
+                WarningCategory::Xattr,
+                None,
+                format!(
+                    "Could not preserve privileged xattrs ({}) on {} files: requires root or CAP_SETFCAP",
+                    names.join(", "),
+                    privileged_xattr_skips.len()
+                ),
+            );
+        }
+
         // Add duration after extracting stats
         final_stats.duration = start_time.elapsed();
 
@@ -1225,6 +2350,10 @@ impl<T: Transport + 'static> SyncEngine<T> {
                 duration_secs: final_stats.duration.as_secs_f64(),
                 files_verified: final_stats.files_verified,
                 verification_failures: final_stats.verification_failures,
+                files_skipped_busy: final_stats.skipped_busy.len(),
+                files_deferred_hot: final_stats.deferred_hot.len(),
+                files_skipped_special: final_stats.skipped_specials.len(),
+                files_metadata_not_preserved: final_stats.metadata_not_preserved.file_count(),
             }
             .emit();
 
@@ -1256,29 +2385,46 @@ impl<T: Transport + 'static> SyncEngine<T> {
             if state_guard.is_some() {
                 // Only clean up if this was an actual resume operation
                 // (Don't clean up if we just created a new state that was never saved)
-                if ResumeState::load(destination)?.is_some() {
+                if ResumeState::load(&state_dir)?.is_some() {
                     tracing::debug!("Cleaning up resume state file");
-                    if let Err(e) = ResumeState::delete(destination) {
-                        tracing::warn!("Failed to delete resume state: {}", e);
+                    if let Err(e) = ResumeState::delete(&state_dir) {
+                        output::warn(
+                            WarningCategory::Resume,
+                            None,
+                            format!("Failed to delete resume state: {}", e),
+                        );
                     }
                 }
             }
             // Drop the state
             *state_guard = None;
         }
+        // A transfer that failed keeps its checkpoint for the next run
+        if self.resume && final_stats.errors.is_empty() {
+            if let Err(e) = resume::clear_partials(&state_dir) {
+                output::warn(
+                    WarningCategory::Resume,
+                    None,
+                    format!("Failed to delete partial transfer checkpoints: {}", e),
+                );
+            }
+        }
 
         // Save directory cache if enabled
         if self.use_cache && !self.dry_run {
             if let Some(ref cache) = dir_cache {
-                // Ensure destination directory exists before saving cache
-                if destination.exists() {
-                    if let Err(e) = cache.save(destination) {
-                        tracing::warn!("Failed to save directory cache: {}", e);
-                    } else {
-                        tracing::debug!("Saved directory cache with {} entries", cache.len());
-                    }
+                if let Err(e) = cache.save(&state_dir) {
+                    output::warn(
+                        WarningCategory::Cache,
+                        None,
+                        format!("Failed to save directory cache: {}", e),
+                    );
                 } else {
-                    tracing::debug!("Skipping cache save - destination directory doesn't exist");
+                    tracing::debug!("Saved directory cache with {} entries", cache.len());
+                    self.changed_dirs
+                        .lock()
+                        .unwrap()
+                        .retain(|dir| !noted_changes.contains(dir));
                 }
             }
         }
@@ -1286,7 +2432,6 @@ impl<T: Transport + 'static> SyncEngine<T> {
         // Store checksums in database if enabled
         if let Some(ref db) = checksum_db {
             if !self.dry_run {
-                let mut stored_count = 0;
                 let verifier = IntegrityVerifier::new(
                     if self.checksum {
                         ChecksumType::Fast
@@ -1294,39 +2439,98 @@ impl<T: Transport + 'static> SyncEngine<T> {
                         ChecksumType::None
                     },
                     false,
-                );
+                )
+                .open_noatime(self.open_noatime);
+
+                let checksum_type = checksumdb::checksum_type_name(verifier.checksum_type());
+
+                // Files that failed to sync have no verified destination copy
+                let failed_paths: HashSet<&Path> = final_stats
+                    .errors
+                    .iter()
+                    .map(|e| e.path.as_path())
+                    .chain(final_stats.skipped_busy.iter().map(PathBuf::as_path))
+                    .chain(final_stats.deferred_hot.iter().map(PathBuf::as_path))
+                    .collect();
+
+                // Source checksums come from the database when the file is
+                // unchanged (planning just verified it), otherwise they're
+                // computed. The destination now holds the same content, so its
+                // row reuses the source checksum with the destination's own
+                // metadata instead of hashing it again.
+                let dest_paths: Vec<PathBuf> = source_files
+                    .iter()
+                    .map(|file| destination.join(&file.relative_path))
+                    .collect();
+                let checksums = source_files
+                    .iter()
+                    .zip(&dest_paths)
+                    .filter(|(file, dest_path)| {
+                        !file.is_dir // Skip directories
+                            && !failed_paths.contains(file.path.as_path())
+                            && !failed_paths.contains(dest_path.as_path())
+                    })
+                    .filter_map(|(file, dest_path)| {
+                        let checksum = db
+                            .get_checksum(
+                                &file.path,
+                                file.modified,
+                                file.size,
+                                file.inode,
+                                checksum_type,
+                            )
+                            .ok()
+                            .flatten()
+                            .or_else(|| verifier.compute_file_checksum(&file.path).ok())?;
+                        Some((file, dest_path, checksum))
+                    })
+                    .flat_map(|(file, dest_path, checksum)| {
+                        // Only local destinations that match the source size were verified
+                        let dest_entry = std::fs::metadata(dest_path)
+                            .ok()
+                            .filter(|meta| meta.is_file() && meta.len() == file.size)
+                            .and_then(|meta| {
+                                let mtime = meta.modified().ok()?;
+                                Some((
+                                    dest_path.as_path(),
+                                    mtime,
+                                    meta.len(),
+                                    dircache::inode(&meta),
+                                    checksum.clone(),
+                                ))
+                            });
+
+                        std::iter::once((
+                            file.path.as_path(),
+                            file.modified,
+                            file.size,
+                            file.inode,
+                            checksum,
+                        ))
+                        .chain(dest_entry)
+                    });
 
-                for file in &source_files {
-                    if file.is_dir {
-                        continue; // Skip directories
+                match db.store_checksums(checksums) {
+                    Ok(stored_count) if stored_count > 0 => {
+                        tracing::info!("Stored {} checksums in database", stored_count);
                     }
-
-                    // Compute checksum for source file
-                    if let Ok(checksum) = verifier.compute_file_checksum(&file.path) {
-                        // Store in database
-                        if let Err(e) =
-                            db.store_checksum(&file.path, file.modified, file.size, &checksum)
-                        {
-                            tracing::warn!(
-                                "Failed to store checksum for {}: {}",
-                                file.path.display(),
-                                e
-                            );
-                        } else {
-                            stored_count += 1;
-                        }
+                    Ok(_) => {}
+                    Err(e) => {
+                        output::warn(
+                            WarningCategory::Cache,
+                            None,
+                            format!("Failed to store checksums in database: {}", e),
+                        );
                     }
                 }
 
-                if stored_count > 0 {
-                    tracing::info!("Stored {} checksums in database", stored_count);
-                }
-
                 // Handle prune flag
                 if self.prune_checksum_db {
-                    use std::collections::HashSet;
-                    let existing_paths: HashSet<_> =
-                        source_files.iter().map(|f| f.path.clone()).collect();
+                    let existing_paths: HashSet<_> = source_files
+                        .iter()
+                        .map(|f| f.path.clone())
+                        .chain(dest_paths)
+                        .collect();
 
                     match db.prune(&existing_paths) {
                         Ok(pruned) => {
@@ -1338,7 +2542,11 @@ impl<T: Transport + 'static> SyncEngine<T> {
                             }
                         }
                         Err(e) => {
-                            tracing::warn!("Failed to prune checksum database: {}", e);
+                            output::warn(
+                                WarningCategory::Cache,
+                                None,
+                                format!("Failed to prune checksum database: {}", e),
+                            );
                         }
                     }
                 }
@@ -1400,7 +2608,7 @@ impl<T: Transport + 'static> SyncEngine<T> {
         } else {
             self.verification_mode // Use user-specified mode
         };
-        let verifier = IntegrityVerifier::new(checksum_type, false);
+        let verifier = IntegrityVerifier::new(checksum_type, false).open_noatime(self.open_noatime);
 
         // Results tracking
         let mut files_matched = 0;
@@ -1521,6 +2729,7 @@ impl<T: Transport + 'static> SyncEngine<T> {
             source.display(),
             destination.display()
         );
+        self.check_cancelled()?;
 
         let mut stats = SyncStats {
             files_scanned: 1,
@@ -1540,6 +2749,13 @@ impl<T: Transport + 'static> SyncEngine<T> {
             bytes_would_change: 0,
             bytes_would_delete: 0,
             errors: Vec::new(),
+            skipped_busy: Vec::new(),
+            deferred_hot: Vec::new(),
+            skipped_specials: Vec::new(),
+            names: names::NameReport::default(),
+            files_retried: 0,
+            changed_during_transfer: Vec::new(),
+            metadata_not_preserved: output::MetadataReport::default(),
         };
 
         // Check if destination exists
@@ -1558,7 +2774,16 @@ impl<T: Transport + 'static> SyncEngine<T> {
             self.preserve_acls,
             self.preserve_flags,
             hardlink_map,
-        );
+        )
+        .with_acl_map(self.acl_map)
+        .with_preserve_times(self.preserve_atimes, self.preserve_crtimes)
+        .with_preserve_perms(self.preserve_perms)
+        .with_source_check(self.check_source_changes)
+        .with_whole_file(self.whole_file)
+        .with_fault_injector(self.fault_injector.clone());
+
+        let lane = (self.bwlimit.is_some() || self.bwlimit_per_file.is_some())
+            .then(|| Arc::new(BandwidthLimiter::new(self.bwlimit, self.bwlimit_per_file)).lane());
 
         if !dest_exists {
             // Create new file
@@ -1590,6 +2815,9 @@ impl<T: Transport + 'static> SyncEngine<T> {
                         nlink: 1,
                         acls: None,
                         bsd_flags: None,
+                        atime: None,
+                        crtime: None,
+                        perms: None,
                     },
                     destination,
                 )
@@ -1610,21 +2838,34 @@ impl<T: Transport + 'static> SyncEngine<T> {
 
             // Verify transfer if verification is enabled
             if self.verification_mode != ChecksumType::None && !self.dry_run {
-                let verifier = IntegrityVerifier::new(self.verification_mode, self.verify_on_write);
-                match verifier.verify_transfer(source, destination) {
+                let verifier = IntegrityVerifier::new(self.verification_mode, self.verify_on_write)
+                    .open_noatime(self.open_noatime);
+                let (src, dst) = (source.to_path_buf(), destination.to_path_buf());
+                let verified = crate::cpu::run(move || verifier.verify_transfer(&src, &dst))
+                    .instrument(tracing::trace_span!("verify", path = %destination.display()))
+                    .await;
+                match verified {
                     Ok(verified) => {
                         if verified {
                             stats.files_verified = 1;
                         } else {
                             stats.verification_failures = 1;
-                            tracing::warn!(
-                                "Verification failed for {}: checksums do not match",
-                                destination.display()
+                            output::warn(
+                                WarningCategory::Verification,
+                                Some(destination),
+                                format!(
+                                    "Verification failed for {}: checksums do not match",
+                                    destination.display()
+                                ),
                             );
                         }
                     }
                     Err(e) => {
-                        tracing::warn!("Verification error for {}: {}", destination.display(), e);
+                        output::warn(
+                            WarningCategory::Verification,
+                            Some(destination),
+                            format!("Verification error for {}: {}", destination.display(), e),
+                        );
                         stats.verification_failures = 1;
                     }
                 }
@@ -1659,6 +2900,9 @@ impl<T: Transport + 'static> SyncEngine<T> {
                         nlink: 1,
                         acls: None,
                         bsd_flags: None,
+                        atime: None,
+                        crtime: None,
+                        perms: None,
                     },
                     destination,
                 )
@@ -1688,27 +2932,47 @@ impl<T: Transport + 'static> SyncEngine<T> {
 
             // Verify transfer if verification is enabled
             if self.verification_mode != ChecksumType::None && !self.dry_run {
-                let verifier = IntegrityVerifier::new(self.verification_mode, self.verify_on_write);
-                match verifier.verify_transfer(source, destination) {
+                let verifier = IntegrityVerifier::new(self.verification_mode, self.verify_on_write)
+                    .open_noatime(self.open_noatime);
+                let (src, dst) = (source.to_path_buf(), destination.to_path_buf());
+                let verified = crate::cpu::run(move || verifier.verify_transfer(&src, &dst))
+                    .instrument(tracing::trace_span!("verify", path = %destination.display()))
+                    .await;
+                match verified {
                     Ok(verified) => {
                         if verified {
                             stats.files_verified = 1;
                         } else {
                             stats.verification_failures = 1;
-                            tracing::warn!(
-                                "Verification failed for {}: checksums do not match",
-                                destination.display()
+                            output::warn(
+                                WarningCategory::Verification,
+                                Some(destination),
+                                format!(
+                                    "Verification failed for {}: checksums do not match",
+                                    destination.display()
+                                ),
                             );
                         }
                     }
                     Err(e) => {
-                        tracing::warn!("Verification error for {}: {}", destination.display(), e);
+                        output::warn(
+                            WarningCategory::Verification,
+                            Some(destination),
+                            format!("Verification error for {}: {}", destination.display(), e),
+                        );
                         stats.verification_failures = 1;
                     }
                 }
             }
         }
 
+        if let Some(lane) = lane {
+            let sleep_duration = lane.delay(stats.bytes_transferred);
+            if sleep_duration > Duration::ZERO {
+                tokio::time::sleep(sleep_duration).await;
+            }
+        }
+
         stats.duration = start_time.elapsed();
         Ok(stats)
     }
@@ -1721,23 +2985,90 @@ impl<T: Transport + 'static> SyncEngine<T> {
     }
 }
 
+/// Destination directories the plan creates, parents before children
+///
+/// Symlinked directories are left to their tasks, as are paths the sandbox
+/// refuses.
+fn planned_dirs(
+    tasks: &[strategy::SyncTask],
+    sandbox: Option<&sandbox::DestSandbox>,
+) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = tasks
+        .iter()
+        .filter(|task| task.action == SyncAction::Create)
+        .filter(|task| {
+            task.source
+                .as_ref()
+                .is_some_and(|source| source.is_dir && !source.is_symlink)
+        })
+        .filter(|task| match sandbox {
+            Some(sandbox) => sandbox.check(&task.dest_path).is_ok(),
+            None => true,
+        })
+        .map(|task| task.dest_path.clone())
+        .collect();
+    dirs.sort_by_key(|dir| dir.components().count());
+    dirs
+}
+
+/// Directories the staged files are written into, each once, parents first
+fn staged_parents(moves: &[staging::StagedMove]) -> Vec<PathBuf> {
+    let mut parents: Vec<PathBuf> = moves
+        .iter()
+        .filter_map(|m| m.staged.parent().map(Path::to_path_buf))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    parents.sort_by_key(|dir| dir.components().count());
+    parents
+}
+
+/// Completes once `cancel` is cancelled, never without a token
+async fn cancelled(cancel: Option<&CancellationToken>) {
+    match cancel {
+        Some(cancel) => cancel.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Checkpoint resume state, if the sync keeps any
+fn save_resume_state(resume_state: &Mutex<Option<ResumeState>>, state_dir: &Path) {
+    if let Some(state) = resume_state.lock().unwrap().as_ref() {
+        if let Err(e) = state.save(state_dir) {
+            output::warn(
+                WarningCategory::Resume,
+                None,
+                format!("Failed to save resume state: {}", e),
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::transport::local::LocalTransport;
+    use crate::transport::memory::MemoryTransport;
+    use std::collections::BTreeMap;
     use std::fs;
     use tempfile::TempDir;
 
     // Helper to create a basic sync engine for testing
-    fn create_test_engine() -> SyncEngine<LocalTransport> {
-        let transport = LocalTransport::new();
+    fn create_test_engine(state_dir: &Path) -> SyncEngine<LocalTransport> {
+        create_test_engine_with(LocalTransport::new(), state_dir)
+    }
+
+    fn create_test_engine_with<T: Transport + 'static>(
+        transport: T,
+        state_dir: &Path,
+    ) -> SyncEngine<T> {
         SyncEngine::new(
             transport,
             false,               // dry_run
             false,               // diff_mode
             false,               // delete
             50,                  // delete_threshold
-            false,               // trash
+            None,                // trash
             false,               // force_delete
             true,                // quiet
             4,                   // max_concurrent
@@ -1753,20 +3084,22 @@ mod tests {
             ChecksumType::Fast,
             false, // verify_on_write
             SymlinkMode::Preserve,
-            false, // preserve_xattrs
-            false, // preserve_hardlinks
-            false, // preserve_acls
-            false, // preserve_flags
-            false, // ignore_times
-            false, // size_only
-            false, // checksum
-            false, // verify_only
-            false, // use_cache (disabled in tests to avoid side effects)
-            false, // clear_cache
-            false, // checksum_db
-            false, // clear_checksum_db
-            false, // prune_checksum_db
-            false, // perf
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache (disabled in tests to avoid side effects)
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.to_path_buf(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
         )
     }
 
@@ -1774,12 +3107,13 @@ mod tests {
     async fn test_basic_sync_success() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create test files in source
         fs::write(source_dir.path().join("file1.txt"), "content1").unwrap();
         fs::write(source_dir.path().join("file2.txt"), "content2").unwrap();
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -1798,12 +3132,13 @@ mod tests {
     async fn test_sync_with_subdirectories() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create nested structure
         fs::create_dir(source_dir.path().join("subdir")).unwrap();
         fs::write(source_dir.path().join("subdir/file.txt"), "nested").unwrap();
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -1821,8 +3156,9 @@ mod tests {
     async fn test_sync_empty_source() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -1836,6 +3172,7 @@ mod tests {
     async fn test_sync_dry_run_no_changes() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         fs::write(source_dir.path().join("file.txt"), "content").unwrap();
 
@@ -1846,7 +3183,7 @@ mod tests {
             false,               // diff_mode
             false,               // delete
             50,                  // delete_threshold
-            false,               // trash
+            None,                // trash
             false,               // force_delete
             true,                // quiet
             4,                   // max_concurrent
@@ -1862,20 +3199,22 @@ mod tests {
             ChecksumType::Fast,
             false, // verify_on_write
             SymlinkMode::Preserve,
-            false, // preserve_xattrs
-            false, // preserve_hardlinks
-            false, // preserve_acls
-            false, // preserve_flags
-            false, // ignore_times
-            false, // size_only
-            false, // checksum
-            false, // verify_only
-            false, // use_cache
-            false, // clear_cache
-            false, // checksum_db
-            false, // clear_checksum_db
-            false, // prune_checksum_db
-            false, // perf
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
         );
 
         let stats = engine
@@ -1894,6 +3233,7 @@ mod tests {
     async fn test_toctou_file_deleted_after_scan() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create file
         let file_path = source_dir.path().join("file.txt");
@@ -1908,7 +3248,7 @@ mod tests {
         fs::remove_file(&file_path).unwrap();
 
         // Try to sync - should handle gracefully
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let result = engine.sync(source_dir.path(), dest_dir.path()).await;
 
         // Should either succeed with 0 files or handle the error gracefully
@@ -1927,13 +3267,14 @@ mod tests {
     async fn test_toctou_file_modified_after_scan() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create file with initial content
         let file_path = source_dir.path().join("file.txt");
         fs::write(&file_path, "initial content").unwrap();
 
         // Start sync in background
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let source = source_dir.path().to_path_buf();
         let dest = dest_dir.path().to_path_buf();
 
@@ -1952,6 +3293,7 @@ mod tests {
     async fn test_toctou_file_size_changed() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create small file
         let file_path = source_dir.path().join("file.txt");
@@ -1965,7 +3307,7 @@ mod tests {
         fs::write(&file_path, "a".repeat(10000)).unwrap();
 
         // Sync should handle size change
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let result = engine.sync(source_dir.path(), dest_dir.path()).await;
 
         // Should either succeed or fail gracefully
@@ -1985,6 +3327,7 @@ mod tests {
     async fn test_toctou_directory_deleted_after_scan() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create directory with file
         let subdir = source_dir.path().join("subdir");
@@ -2000,7 +3343,7 @@ mod tests {
         fs::remove_dir_all(&subdir).unwrap();
 
         // Sync should handle gracefully
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let result = engine.sync(source_dir.path(), dest_dir.path()).await;
 
         match result {
@@ -2018,6 +3361,7 @@ mod tests {
     async fn test_toctou_new_file_created_during_sync() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create initial file
         fs::write(source_dir.path().join("file1.txt"), "content1").unwrap();
@@ -2026,7 +3370,7 @@ mod tests {
         fs::write(source_dir.path().join("file2.txt"), "content2").unwrap();
 
         // Sync - should get file1 (file2 created after scan won't be included)
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -2042,6 +3386,7 @@ mod tests {
     async fn test_sync_many_small_files() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create 100 small files
         for i in 0..100 {
@@ -2052,7 +3397,7 @@ mod tests {
             .unwrap();
         }
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -2070,6 +3415,7 @@ mod tests {
     async fn test_sync_very_deep_nesting() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create 100-level deep nesting
         let mut path = source_dir.path().to_path_buf();
@@ -2079,7 +3425,7 @@ mod tests {
         fs::create_dir_all(&path).unwrap();
         fs::write(path.join("deep.txt"), "very deep content").unwrap();
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -2101,12 +3447,13 @@ mod tests {
     async fn test_sync_large_file() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create 10MB file
         let large_content = "x".repeat(10 * 1024 * 1024);
         fs::write(source_dir.path().join("large.bin"), &large_content).unwrap();
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -2124,6 +3471,7 @@ mod tests {
     async fn test_sync_mixed_sizes() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Mix of file sizes
         fs::write(source_dir.path().join("tiny.txt"), "x").unwrap();
@@ -2131,7 +3479,7 @@ mod tests {
         fs::write(source_dir.path().join("medium.txt"), "x".repeat(100 * 1024)).unwrap();
         fs::write(source_dir.path().join("large.txt"), "x".repeat(1024 * 1024)).unwrap();
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
         let stats = engine
             .sync(source_dir.path(), dest_dir.path())
             .await
@@ -2148,10 +3496,11 @@ mod tests {
     async fn test_sync_idempotent() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         fs::write(source_dir.path().join("file.txt"), "content").unwrap();
 
-        let engine = create_test_engine();
+        let engine = create_test_engine(state_dir.path());
 
         // First sync
         let stats1 = engine
@@ -2169,10 +3518,463 @@ mod tests {
         assert_eq!(stats2.files_created, 0);
     }
 
+    #[tokio::test]
+    async fn test_max_depth_limits_sync_and_deletions() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+
+        fs::write(source_dir.path().join("top.txt"), "top").unwrap();
+        fs::create_dir(source_dir.path().join("sub")).unwrap();
+        fs::write(source_dir.path().join("sub/deep.txt"), "deep").unwrap();
+
+        // Destination has an extra file below the depth limit
+        fs::create_dir(dest_dir.path().join("sub")).unwrap();
+        fs::write(dest_dir.path().join("sub/old.txt"), "old").unwrap();
+
+        let transport = LocalTransport::new();
+        let engine = SyncEngine::new(
+            transport,
+            false,               // dry_run
+            false,               // diff_mode
+            true,                // delete
+            100,                 // delete_threshold
+            None,                // trash
+            true,                // force_delete
+            true,                // quiet
+            4,                   // max_concurrent
+            100,                 // max_errors
+            None,                // min_size
+            None,                // max_size
+            FilterEngine::new(), // filter_engine
+            None,                // bwlimit
+            false,               // resume
+            0,                   // checkpoint_files
+            0,                   // checkpoint_bytes
+            false,               // json
+            ChecksumType::Fast,
+            false, // verify_on_write
+            SymlinkMode::Preserve,
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions {
+                max_depth: Some(1),
+                ..Default::default()
+            },
+        );
+
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        assert!(dest_dir.path().join("top.txt").exists());
+        assert!(dest_dir.path().join("sub").is_dir());
+        assert!(!dest_dir.path().join("sub/deep.txt").exists());
+
+        // Entries beyond the depth limit were never compared, so they are kept
+        assert!(dest_dir.path().join("sub/old.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_nested_source_never_deleted() {
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        let source = dest_dir.path().join("src");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("file.txt"), "content").unwrap();
+        fs::write(dest_dir.path().join("old.txt"), "old").unwrap();
+
+        let transport = LocalTransport::new();
+        let engine = SyncEngine::new(
+            transport,
+            false,               // dry_run
+            false,               // diff_mode
+            true,                // delete
+            100,                 // delete_threshold
+            None,                // trash
+            true,                // force_delete
+            true,                // quiet
+            4,                   // max_concurrent
+            100,                 // max_errors
+            None,                // min_size
+            None,                // max_size
+            FilterEngine::new(), // filter_engine
+            None,                // bwlimit
+            false,               // resume
+            0,                   // checkpoint_files
+            0,                   // checkpoint_bytes
+            false,               // json
+            ChecksumType::Fast,
+            false, // verify_on_write
+            SymlinkMode::Preserve,
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions {
+                nested_source: Some(PathBuf::from("src")),
+                ..Default::default()
+            },
+        );
+
+        engine.sync(&source, dest_dir.path()).await.unwrap();
+
+        assert!(dest_dir.path().join("file.txt").exists());
+        assert!(!dest_dir.path().join("old.txt").exists());
+        // The source is an extra at the destination, but kept
+        assert!(source.join("file.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_delay_updates() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        fs::create_dir(source_dir.path().join("assets")).unwrap();
+        fs::write(source_dir.path().join("assets/app.js"), "new app").unwrap();
+        fs::write(source_dir.path().join("index.html"), "new index").unwrap();
+        fs::write(dest_dir.path().join("index.html"), "old").unwrap();
+        fs::write(dest_dir.path().join("stale.html"), "stale").unwrap();
+
+        // A failed file leaves the destination as it was
+        let mut engine = create_test_engine(state_dir.path());
+        engine.delay_updates = true;
+        engine.delete = true;
+        engine.force_delete = true;
+        engine.fault_injector = Some(Arc::new(fault::FaultInjector::new(fault::FaultSpec {
+            disconnect: 100,
+            ..Default::default()
+        })));
+        let result = engine.sync(source_dir.path(), dest_dir.path()).await;
+        assert!(matches!(
+            result,
+            Err(crate::error::SyncError::NotCommitted { .. })
+        ));
+        assert_eq!(
+            fs::read_to_string(dest_dir.path().join("index.html")).unwrap(),
+            "old"
+        );
+        assert!(!dest_dir.path().join("assets/app.js").exists());
+        assert!(dest_dir.path().join("stale.html").exists());
+        assert!(!dest_dir.path().join(staging::STAGING_DIR).exists());
+
+        // Otherwise everything moves into place, then deletions run
+        engine.fault_injector = None;
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dest_dir.path().join("index.html")).unwrap(),
+            "new index"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.path().join("assets/app.js")).unwrap(),
+            "new app"
+        );
+        assert!(!dest_dir.path().join("stale.html").exists());
+        assert!(!dest_dir.path().join(staging::STAGING_DIR).exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_scan_journal() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        fs::create_dir(source_dir.path().join("docs")).unwrap();
+        fs::create_dir(source_dir.path().join("notes")).unwrap();
+        fs::write(source_dir.path().join("docs/a.txt"), "a").unwrap();
+        fs::write(source_dir.path().join("notes/todo.txt"), "todo").unwrap();
+
+        let mut engine = create_test_engine(state_dir.path());
+        engine.use_cache = true;
+        engine.scan_journal = true;
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        // A new file changes its directory's stamp; an edit in place is
+        // reported by watch mode
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(source_dir.path().join("docs/b.txt"), "b").unwrap();
+        let edited = source_dir.path().join("notes/todo.txt");
+        fs::write(&edited, "todo: more").unwrap();
+        engine.note_changes(source_dir.path(), [&edited]);
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        let read = |path: &str| fs::read_to_string(dest_dir.path().join(path)).unwrap();
+        assert_eq!(read("docs/b.txt"), "b");
+        assert_eq!(read("notes/todo.txt"), "todo: more");
+        assert!(engine.changed_dirs.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_scan_journal_edit_in_place() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        let notes = source_dir.path().join("notes");
+        fs::create_dir(&notes).unwrap();
+        fs::write(notes.join("todo.txt"), "todo").unwrap();
+
+        let mut engine = create_test_engine(state_dir.path());
+        engine.use_cache = true;
+        engine.scan_journal = true;
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        // Rewritten in place without watch mode: the directory's stamp stays
+        let dir_mtime = || fs::metadata(&notes).unwrap().modified().unwrap();
+        let before = dir_mtime();
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(notes.join("todo.txt"), "todo: more").unwrap();
+        assert_eq!(dir_mtime(), before);
+
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dest_dir.path().join("notes/todo.txt")).unwrap(),
+            "todo: more"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_link_dest() {
+        let state_dir = TempDir::new().unwrap();
+        use std::os::unix::fs::MetadataExt;
+
+        let source_dir = TempDir::new().unwrap();
+        let backups = TempDir::new().unwrap();
+        let previous = backups.path().join("2024-06-01");
+        let today = backups.path().join("2024-06-02");
+        fs::create_dir(&previous).unwrap();
+        fs::create_dir(&today).unwrap();
+        fs::write(source_dir.path().join("same.txt"), "same").unwrap();
+        fs::write(source_dir.path().join("changed.txt"), "new").unwrap();
+
+        let mut engine = create_test_engine(state_dir.path());
+        engine.sync(source_dir.path(), &previous).await.unwrap();
+        fs::write(source_dir.path().join("changed.txt"), "changed").unwrap();
+
+        engine.link_dest = Some(PathBuf::from("../2024-06-01"));
+        engine.sync(source_dir.path(), &today).await.unwrap();
+
+        let ino = |path: PathBuf| fs::metadata(path).unwrap().ino();
+        assert_eq!(ino(today.join("same.txt")), ino(previous.join("same.txt")));
+        assert_ne!(
+            ino(today.join("changed.txt")),
+            ino(previous.join("changed.txt"))
+        );
+        assert_eq!(
+            fs::read_to_string(today.join("changed.txt")).unwrap(),
+            "changed"
+        );
+        assert_eq!(
+            fs::read_to_string(previous.join("changed.txt")).unwrap(),
+            "new"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_checksum_db_records_destination_checksums() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+
+        fs::write(source_dir.path().join("file.txt"), "content").unwrap();
+
+        let engine = SyncEngine::new(
+            LocalTransport::new(),
+            false,               // dry_run
+            false,               // diff_mode
+            false,               // delete
+            50,                  // delete_threshold
+            None,                // trash
+            false,               // force_delete
+            true,                // quiet
+            4,                   // max_concurrent
+            100,                 // max_errors
+            None,                // min_size
+            None,                // max_size
+            FilterEngine::new(), // filter_engine
+            None,                // bwlimit
+            false,               // resume
+            0,                   // checkpoint_files
+            0,                   // checkpoint_bytes
+            false,               // json
+            ChecksumType::None,
+            false, // verify_on_write
+            SymlinkMode::Preserve,
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            true,                    // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            true,                    // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
+        );
+
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        let db = checksumdb::ChecksumDatabase::open(state_dir.path()).unwrap();
+        assert_eq!(db.generation(), 2);
+
+        // Both ends are recorded, so a repeat run only needs to stat them
+        let lookup = |path: &Path| {
+            let meta = fs::metadata(path).unwrap();
+            db.get_checksum(
+                path,
+                meta.modified().unwrap(),
+                meta.len(),
+                dircache::inode(&meta),
+                "fast",
+            )
+            .unwrap()
+        };
+        let source_checksum = lookup(&source_dir.path().join("file.txt"));
+        let dest_checksum = lookup(&dest_dir.path().join("file.txt"));
+        assert!(source_checksum.is_some());
+        assert_eq!(source_checksum, dest_checksum);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unreadable_source_directory_reported_not_deleted() {
+        let state_dir = TempDir::new().unwrap();
+        use std::os::unix::fs::PermissionsExt;
+
+        // Root ignores permission bits
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+
+        fs::write(source_dir.path().join("visible.txt"), "visible").unwrap();
+        let locked = source_dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("secret.txt"), "secret").unwrap();
+
+        // Destination already has a copy of the locked directory's contents
+        fs::create_dir(dest_dir.path().join("locked")).unwrap();
+        fs::write(dest_dir.path().join("locked/secret.txt"), "secret").unwrap();
+
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let transport = LocalTransport::new();
+        let engine = SyncEngine::new(
+            transport,
+            false,               // dry_run
+            false,               // diff_mode
+            true,                // delete
+            100,                 // delete_threshold
+            None,                // trash
+            true,                // force_delete
+            true,                // quiet
+            4,                   // max_concurrent
+            100,                 // max_errors
+            None,                // min_size
+            None,                // max_size
+            FilterEngine::new(), // filter_engine
+            None,                // bwlimit
+            false,               // resume
+            0,                   // checkpoint_files
+            0,                   // checkpoint_bytes
+            false,               // json
+            ChecksumType::Fast,
+            false, // verify_on_write
+            SymlinkMode::Preserve,
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
+        );
+
+        let result = engine.sync(source_dir.path(), dest_dir.path()).await;
+
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // The rest of the tree is synced and the unreadable directory is reported
+        let stats = result.unwrap();
+        assert!(dest_dir.path().join("visible.txt").exists());
+        assert_eq!(stats.errors.len(), 1);
+        assert_eq!(stats.errors[0].path, locked);
+        assert_eq!(stats.errors[0].action, "scan");
+
+        // Files under the unreadable directory must not be deleted
+        assert!(dest_dir.path().join("locked/secret.txt").exists());
+    }
+
     // === Error Collection and max_errors Threshold Tests ===
 
     #[tokio::test]
     async fn test_error_threshold_zero_collects_all_errors() {
+        let state_dir = TempDir::new().unwrap();
         use std::os::unix::fs::PermissionsExt;
 
         let source_dir = TempDir::new().unwrap();
@@ -2195,7 +3997,7 @@ mod tests {
             false,               // diff_mode
             false,               // delete
             50,                  // delete_threshold
-            false,               // trash
+            None,                // trash
             false,               // force_delete
             true,                // quiet
             1,                   // max_concurrent (serial to make errors predictable)
@@ -2211,20 +4013,22 @@ mod tests {
             ChecksumType::Fast,
             false, // verify_on_write
             SymlinkMode::Preserve,
-            false, // preserve_xattrs
-            false, // preserve_hardlinks
-            false, // preserve_acls
-            false, // preserve_flags
-            false, // ignore_times
-            false, // size_only
-            false, // checksum
-            false, // verify_only
-            false, // use_cache
-            false, // clear_cache
-            false, // checksum_db
-            false, // clear_checksum_db
-            false, // prune_checksum_db
-            false, // perf
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
         );
 
         let result = engine.sync(source_dir.path(), dest_dir.path()).await;
@@ -2249,6 +4053,7 @@ mod tests {
     async fn test_error_threshold_aborts_when_exceeded() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create multiple files
         for i in 1..=10 {
@@ -2273,7 +4078,7 @@ mod tests {
             false,               // diff_mode
             false,               // delete
             50,                  // delete_threshold
-            false,               // trash
+            None,                // trash
             false,               // force_delete
             true,                // quiet
             1,                   // max_concurrent (serial)
@@ -2289,20 +4094,22 @@ mod tests {
             ChecksumType::Fast,
             false, // verify_on_write
             SymlinkMode::Preserve,
-            false, // preserve_xattrs
-            false, // preserve_hardlinks
-            false, // preserve_acls
-            false, // preserve_flags
-            false, // ignore_times
-            false, // size_only
-            false, // checksum
-            false, // verify_only
-            false, // use_cache
-            false, // clear_cache
-            false, // checksum_db
-            false, // clear_checksum_db
-            false, // prune_checksum_db
-            false, // perf
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
         );
 
         let result = engine.sync(source_dir.path(), dest_dir.path()).await;
@@ -2330,6 +4137,7 @@ mod tests {
     async fn test_error_collection_below_threshold_continues() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create files
         for i in 1..=10 {
@@ -2353,7 +4161,7 @@ mod tests {
             false,               // diff_mode
             false,               // delete
             50,                  // delete_threshold
-            false,               // trash
+            None,                // trash
             false,               // force_delete
             true,                // quiet
             1,                   // max_concurrent
@@ -2369,20 +4177,22 @@ mod tests {
             ChecksumType::Fast,
             false, // verify_on_write
             SymlinkMode::Preserve,
-            false, // preserve_xattrs
-            false, // preserve_hardlinks
-            false, // preserve_acls
-            false, // preserve_flags
-            false, // ignore_times
-            false, // size_only
-            false, // checksum
-            false, // verify_only
-            false, // use_cache
-            false, // clear_cache
-            false, // checksum_db
-            false, // clear_checksum_db
-            false, // prune_checksum_db
-            false, // perf
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
         );
 
         let result = engine.sync(source_dir.path(), dest_dir.path()).await;
@@ -2407,6 +4217,7 @@ mod tests {
     async fn test_error_message_includes_count_and_first_error() {
         let source_dir = TempDir::new().unwrap();
         let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
 
         // Create files
         for i in 1..=5 {
@@ -2430,7 +4241,7 @@ mod tests {
             false, // diff_mode
             false, // delete
             50,    // delete_threshold
-            false, // trash
+            None,  // trash
             false, // force_delete
             true,  // quiet
             1,     // max_concurrent
@@ -2446,20 +4257,22 @@ mod tests {
             ChecksumType::Fast,
             false, // verify_on_write
             SymlinkMode::Preserve,
-            false, // preserve_xattrs
-            false, // preserve_hardlinks
-            false, // preserve_acls
-            false, // preserve_flags
-            false, // ignore_times
-            false, // size_only
-            false, // checksum
-            false, // verify_only
-            false, // use_cache
-            false, // clear_cache
-            false, // checksum_db
-            false, // clear_checksum_db
-            false, // prune_checksum_db
-            false, // perf
+            false,                   // preserve_xattrs
+            false,                   // preserve_hardlinks
+            false,                   // preserve_acls
+            false,                   // preserve_flags
+            false,                   // ignore_times
+            false,                   // size_only
+            false,                   // checksum
+            false,                   // verify_only
+            false,                   // use_cache
+            false,                   // clear_cache
+            false,                   // checksum_db
+            false,                   // clear_checksum_db
+            false,                   // prune_checksum_db
+            state_dir.path().into(), // state_dir
+            false,                   // perf
+            SyncOptions::default(),
         );
 
         let result = engine.sync(source_dir.path(), dest_dir.path()).await;
@@ -2475,4 +4288,436 @@ mod tests {
             );
         }
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dest_confinement_refuses_symlink_escape() {
+        let state_dir = TempDir::new().unwrap();
+        for confinement in [DestConfinement::Links, DestConfinement::Strict] {
+            let source_dir = TempDir::new().unwrap();
+            let dest_dir = TempDir::new().unwrap();
+            let outside = TempDir::new().unwrap();
+
+            fs::create_dir(source_dir.path().join("escape")).unwrap();
+            fs::write(source_dir.path().join("escape/file.txt"), "data").unwrap();
+            fs::write(source_dir.path().join("safe.txt"), "data").unwrap();
+            // A destination symlink leading out, e.g. left behind by an earlier sync
+            std::os::unix::fs::symlink(outside.path(), dest_dir.path().join("escape")).unwrap();
+
+            let mut engine = create_test_engine(state_dir.path());
+            engine.dest_confinement = confinement;
+            let stats = engine
+                .sync(source_dir.path(), dest_dir.path())
+                .await
+                .unwrap();
+
+            assert!(!outside.path().join("file.txt").exists());
+            assert!(dest_dir.path().join("safe.txt").exists());
+            assert!(stats
+                .errors
+                .iter()
+                .any(|e| e.path == dest_dir.path().join("escape/file.txt")));
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_files_retried_once_per_pass() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+
+        fs::create_dir(source_dir.path().join("escape")).unwrap();
+        fs::write(source_dir.path().join("escape/file.txt"), "data").unwrap();
+        fs::write(source_dir.path().join("safe.txt"), "data").unwrap();
+        // Refused on every pass, so it stays failed
+        std::os::unix::fs::symlink(outside.path(), dest_dir.path().join("escape")).unwrap();
+
+        let mut engine = create_test_engine(state_dir.path());
+        engine.dest_confinement = DestConfinement::Links;
+        engine.retry_passes = 2;
+        let stats = engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        // Each retry replaces a file's error instead of adding another
+        let file = dest_dir.path().join("escape/file.txt");
+        assert_eq!(stats.errors.iter().filter(|e| e.path == file).count(), 1);
+        assert!(stats.files_retried >= 1);
+        assert_eq!(stats.files_retried, stats.errors.len());
+        assert!(dest_dir.path().join("safe.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_injected_faults() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        for i in 0..20 {
+            fs::write(source_dir.path().join(format!("file{}.txt", i)), "data").unwrap();
+        }
+        let injector = |spec| Some(Arc::new(fault::FaultInjector::new(spec)));
+
+        // Retry passes get past transfers that fail on their own
+        let mut engine = create_test_engine(state_dir.path());
+        engine.retry_passes = 10;
+        engine.fault_injector = injector(fault::FaultSpec {
+            transfer: 30,
+            seed: Some(1),
+            ..Default::default()
+        });
+        let stats = engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        assert!(stats.files_retried > 0);
+        assert!(stats.errors.is_empty());
+        for i in 0..20 {
+            assert!(dest_dir.path().join(format!("file{}.txt", i)).exists());
+        }
+
+        // A dropped connection fails the rest of the run, retries included
+        let dest_dir = TempDir::new().unwrap();
+        engine.fault_injector = injector(fault::FaultSpec {
+            disconnect: 100,
+            ..Default::default()
+        });
+        let stats = engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        assert_eq!(stats.errors.len(), 20);
+        assert!(stats.errors[0].error.contains("connection dropped"));
+    }
+
+    #[tokio::test]
+    async fn test_observer_receives_progress() {
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl SyncObserver for Recorder {
+            fn on_scan_progress(&self, _source: &Path, files_found: usize, complete: bool) {
+                let event = format!("scan {} {}", files_found, complete);
+                self.0.lock().unwrap().push(event);
+            }
+
+            fn on_file_start(&self, task: &strategy::SyncTask) {
+                let event = format!("start {:?}", task.action);
+                self.0.lock().unwrap().push(event);
+            }
+
+            fn on_file_done(
+                &self,
+                task: &strategy::SyncTask,
+                transfer: Option<&crate::transport::TransferResult>,
+            ) {
+                let bytes = transfer.map_or(0, |result| result.bytes_written);
+                let event = format!("done {:?} {}", task.action, bytes);
+                self.0.lock().unwrap().push(event);
+            }
+
+            fn on_error(&self, task: &strategy::SyncTask, _error: &crate::error::SyncError) {
+                let event = format!("error {:?}", task.action);
+                self.0.lock().unwrap().push(event);
+            }
+        }
+
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        fs::write(source_dir.path().join("file.txt"), "content").unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        let engine = create_test_engine(state_dir.path()).with_observer(recorder.clone());
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "scan 0 false",
+                "scan 1 true",
+                "start Create",
+                "done Create 7"
+            ]
+        );
+
+        // Up-to-date files are reported as skips, failed ones as errors
+        recorder.0.lock().unwrap().clear();
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        assert!(recorder
+            .0
+            .lock()
+            .unwrap()
+            .ends_with(&["start Skip".to_string(), "done Skip 0".to_string()]));
+
+        recorder.0.lock().unwrap().clear();
+        let mut engine = engine;
+        engine.fault_injector = Some(Arc::new(fault::FaultInjector::new(fault::FaultSpec {
+            disconnect: 100,
+            ..Default::default()
+        })));
+        let dest_dir = TempDir::new().unwrap();
+        engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        assert!(recorder
+            .0
+            .lock()
+            .unwrap()
+            .ends_with(&["start Create".to_string(), "error Create".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_keeps_resume_state() {
+        /// Cancels the sync once the first file is done
+        struct CancelAfterFirst(CancellationToken);
+
+        impl SyncObserver for CancelAfterFirst {
+            fn on_file_done(
+                &self,
+                _task: &strategy::SyncTask,
+                _transfer: Option<&crate::transport::TransferResult>,
+            ) {
+                self.0.cancel();
+            }
+        }
+
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        for i in 0..20 {
+            fs::write(source_dir.path().join(format!("file{}.txt", i)), "data").unwrap();
+        }
+
+        // Cancelled before it starts
+        let mut engine = create_test_engine(state_dir.path());
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        engine.cancel = Some(cancel);
+        let result = engine.sync(source_dir.path(), dest_dir.path()).await;
+        assert!(matches!(result, Err(crate::error::SyncError::Cancelled)));
+        assert_eq!(fs::read_dir(dest_dir.path()).unwrap().count(), 0);
+
+        // Cancelled part way: what was done is saved for --resume
+        let cancel = CancellationToken::new();
+        let mut engine = create_test_engine(state_dir.path())
+            .with_observer(Arc::new(CancelAfterFirst(cancel.clone())));
+        engine.cancel = Some(cancel);
+        engine.resume = true;
+        let result = engine.sync(source_dir.path(), dest_dir.path()).await;
+        assert!(matches!(result, Err(crate::error::SyncError::Cancelled)));
+        let state = ResumeState::load(state_dir.path()).unwrap().unwrap();
+        let (completed, total) = state.progress();
+        assert!(
+            completed >= 1 && completed < 20,
+            "{} of {}",
+            completed,
+            total
+        );
+
+        // The resumed sync finishes the rest
+        engine.cancel = None;
+        let stats = engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+        // Files copied after the last checkpoint are found up to date
+        assert!(stats.files_created <= 20 - completed);
+        for i in 0..20 {
+            assert!(dest_dir.path().join(format!("file{}.txt", i)).exists());
+        }
+        assert!(ResumeState::load(state_dir.path()).unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_special_files_counted_not_copied() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        fs::write(source_dir.path().join("file.txt"), "data").unwrap();
+        let socket = source_dir.path().join("app.sock");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+        let mut engine = create_test_engine(state_dir.path());
+        engine.specials_mode = SpecialsMode::Skip;
+        let stats = engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        assert!(dest_dir.path().join("file.txt").exists());
+        assert!(!dest_dir.path().join("app.sock").exists());
+        assert_eq!(stats.skipped_specials, vec![socket]);
+        assert!(stats.errors.is_empty());
+
+        engine.specials_mode = SpecialsMode::Fail;
+        let result = engine.sync(source_dir.path(), dest_dir.path()).await;
+        assert!(matches!(
+            result,
+            Err(crate::error::SyncError::SpecialFile { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hot_files_deferred() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+
+        let cold = source_dir.path().join("cold.txt");
+        fs::write(&cold, "cold").unwrap();
+        let hour_ago =
+            filetime::FileTime::from_system_time(SystemTime::now() - Duration::from_secs(3600));
+        filetime::set_file_mtime(&cold, hour_ago).unwrap();
+        fs::write(source_dir.path().join("app.log"), "still writing").unwrap();
+        fs::write(dest_dir.path().join("app.log"), "old").unwrap();
+
+        let mut engine = create_test_engine(state_dir.path());
+        engine.delete = true;
+        engine.force_delete = true;
+        engine.skip_hot = Some(Duration::from_secs(60));
+        let stats = engine
+            .sync(source_dir.path(), dest_dir.path())
+            .await
+            .unwrap();
+
+        assert!(dest_dir.path().join("cold.txt").exists());
+        assert_eq!(stats.deferred_hot, vec![dest_dir.path().join("app.log")]);
+        // Neither updated nor deleted, the old copy waits for a later run
+        assert_eq!(
+            fs::read_to_string(dest_dir.path().join("app.log")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn test_planned_dirs_parents_first() {
+        let dest = Path::new("/nonexistent/sy-dest");
+        let entry = |path: &str, is_dir: bool, is_symlink: bool| FileEntry {
+            path: Path::new("/src").join(path),
+            relative_path: PathBuf::from(path),
+            size: 0,
+            modified: SystemTime::UNIX_EPOCH,
+            is_dir,
+            is_symlink,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 0,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        let planner = StrategyPlanner::new();
+        let tasks: Vec<_> = [
+            entry("a/b/c", true, false),
+            entry("a/file.txt", false, false),
+            entry("link", true, true),
+            entry("a/b", true, false),
+            entry("a", true, false),
+            entry("d", true, false),
+        ]
+        .iter()
+        .map(|source| planner.plan_file(source, dest))
+        .collect();
+
+        assert_eq!(
+            planned_dirs(&tasks, None),
+            ["a", "d", "a/b", "a/b/c"].map(|dir| dest.join(dir))
+        );
+    }
+
+    #[test]
+    fn test_staged_parents_unique() {
+        let dest = Path::new("/nonexistent/sy-dest");
+        let area = Staging::new(dest);
+        // Files of one directory that aren't next to each other in the plan
+        let moves: Vec<_> = ["a/one", "b/two", "a/three", "top", "a/b/four", "b/five"]
+            .iter()
+            .map(|path| area.staged_move(&dest.join(path), false))
+            .collect();
+
+        let new = area.dir().join("new");
+        assert_eq!(
+            staged_parents(&moves),
+            [new.clone(), new.join("a"), new.join("b"), new.join("a/b")]
+        );
+    }
+
+    /// Trees of files (contents, mtime in seconds) below single-letter
+    /// directories, so no path is both a file and a directory
+    fn memory_tree(
+        mtimes: std::ops::Range<u64>,
+    ) -> impl proptest::strategy::Strategy<Value = BTreeMap<String, (Vec<u8>, u64)>> {
+        proptest::collection::btree_map(
+            "([a-c]/){0,2}[a-c]\\.txt",
+            (
+                proptest::collection::vec(proptest::prelude::any::<u8>(), 0..64),
+                mtimes,
+            ),
+            0..16,
+        )
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_memory_sync_mirrors_source(
+            source in memory_tree(1_000..1_000_000),
+            stale in memory_tree(0..1),
+        ) {
+            let at = |secs: u64| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+            let transport = MemoryTransport::new();
+            transport.mkdir("/src");
+            transport.mkdir("/dst");
+            for (path, (data, secs)) in &source {
+                transport.write(Path::new("/src").join(path), data.clone(), at(*secs));
+            }
+            // Older copies in the destination, some of them of source files
+            for (path, (data, secs)) in &stale {
+                transport.write(Path::new("/dst").join(path), data.clone(), at(*secs));
+            }
+            let mut expected = transport.entries("/dst");
+            expected.extend(transport.entries("/src"));
+
+            let state_dir = TempDir::new().unwrap();
+            let mut engine = create_test_engine_with(transport, state_dir.path());
+            // Verification reads from the local filesystem
+            engine.verification_mode = ChecksumType::None;
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            let (src, dst) = (Path::new("/src"), Path::new("/dst"));
+
+            let stats = runtime.block_on(engine.sync(src, dst)).unwrap();
+            proptest::prop_assert!(stats.errors.is_empty(), "{:?}", stats.errors);
+            // Every source entry arrived; destination extras are left alone
+            proptest::prop_assert_eq!(engine.transport.entries(dst), expected);
+            for (path, (_, secs)) in &source {
+                proptest::prop_assert_eq!(
+                    engine.transport.modified(dst.join(path)),
+                    Some(at(*secs))
+                );
+            }
+
+            // A second run has nothing left to do
+            let stats = runtime.block_on(engine.sync(src, dst)).unwrap();
+            proptest::prop_assert_eq!(stats.files_created + stats.files_updated, 0);
+        }
+    }
 }
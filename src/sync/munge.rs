@@ -0,0 +1,68 @@
+#[cfg(unix)]
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Placeholder files start with this, followed by the link target
+///
+/// The marker Cygwin uses for symlinks on filesystems that can't hold them.
+pub const MAGIC: &[u8] = b"!<symlink>";
+
+/// Larger files are never placeholders, so they aren't read to check
+pub const MAX_PLACEHOLDER_SIZE: u64 = MAGIC.len() as u64 + 4096;
+
+/// Contents of the placeholder file standing in for a symlink to `target`
+/// (`--munge-links`)
+pub fn encode(target: &Path) -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    #[cfg(unix)]
+    data.extend_from_slice(target.as_os_str().as_bytes());
+    #[cfg(not(unix))]
+    data.extend_from_slice(target.to_string_lossy().as_bytes());
+    data
+}
+
+/// Link target stored in a placeholder file, or `None` for any other file
+/// (`--unmunge-links`)
+pub fn decode(data: &[u8]) -> Option<PathBuf> {
+    let target = data.strip_prefix(MAGIC)?;
+    if target.is_empty() || target.contains(&0) {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        Some(PathBuf::from(OsStr::from_bytes(target)))
+    }
+    #[cfg(not(unix))]
+    {
+        std::str::from_utf8(target).ok().map(PathBuf::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for target in [
+            "file.txt",
+            "../shared/lib.so",
+            "/etc/hosts",
+            "dir with spaces/x",
+        ] {
+            let data = encode(Path::new(target));
+            assert!(data.starts_with(MAGIC));
+            assert_eq!(decode(&data), Some(PathBuf::from(target)));
+        }
+    }
+
+    #[test]
+    fn test_regular_files_not_placeholders() {
+        assert_eq!(decode(b""), None);
+        assert_eq!(decode(b"hello world"), None);
+        assert_eq!(decode(MAGIC), None);
+        assert_eq!(decode(b"!<symlink>bad\0target"), None);
+    }
+}
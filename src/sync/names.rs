@@ -0,0 +1,415 @@
+use super::scanner::FileEntry;
+use crate::error::{Result, SyncError};
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// What to do with names a Windows or SMB destination would reject
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum NameMode {
+    /// Rename them to the closest valid name (`aux.txt` -> `aux_.txt`)
+    Sanitize,
+
+    /// Leave them out and list them in the summary
+    Skip,
+
+    /// Refuse to start the sync
+    Fail,
+}
+
+/// Longest path Windows accepts without long path support (`MAX_PATH` less the NUL)
+pub const MAX_PATH_LEN: usize = 259;
+
+/// Longest name NTFS and SMB accept, in UTF-16 code units
+pub const MAX_NAME_LEN: usize = 255;
+
+/// Why a name can't be created on Windows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameProblem {
+    Reserved,
+    TrailingDotOrSpace,
+    InvalidChar,
+    NameTooLong,
+    PathTooLong,
+    /// The sanitized name is already taken (Windows names ignore case)
+    Collision,
+}
+
+impl fmt::Display for NameProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameProblem::Reserved => f.write_str("reserved device name"),
+            NameProblem::TrailingDotOrSpace => f.write_str("ends with a dot or space"),
+            NameProblem::InvalidChar => f.write_str("contains a character Windows doesn't allow"),
+            NameProblem::NameTooLong => write!(f, "name longer than {} characters", MAX_NAME_LEN),
+            NameProblem::PathTooLong => write!(f, "path longer than {} characters", MAX_PATH_LEN),
+            NameProblem::Collision => f.write_str("sanitized name is taken by another file"),
+        }
+    }
+}
+
+/// Entries renamed or left out by [`apply`]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct NameReport {
+    /// Relative paths before and after sanitizing
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    pub skipped: Vec<(PathBuf, NameProblem)>,
+}
+
+fn is_invalid_char(c: char) -> bool {
+    c < ' ' || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+}
+
+/// Device names Windows reserves in every directory, with or without an extension
+fn is_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or("").trim_end_matches(' ');
+    let upper = stem.to_ascii_uppercase();
+    match upper.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            let Some(port) = upper
+                .strip_prefix("COM")
+                .or_else(|| upper.strip_prefix("LPT"))
+            else {
+                return false;
+            };
+            let mut chars = port.chars();
+            matches!(
+                (chars.next(), chars.next()),
+                (Some('0'..='9' | '¹' | '²' | '³'), None)
+            )
+        }
+    }
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.encode_utf16().count()
+}
+
+/// First rule a single path component breaks, if any
+pub fn name_problem(name: &str) -> Option<NameProblem> {
+    if name.chars().any(is_invalid_char) {
+        Some(NameProblem::InvalidChar)
+    } else if is_reserved(name) {
+        Some(NameProblem::Reserved)
+    } else if name.ends_with(['.', ' ']) {
+        Some(NameProblem::TrailingDotOrSpace)
+    } else if utf16_len(name) > MAX_NAME_LEN {
+        Some(NameProblem::NameTooLong)
+    } else {
+        None
+    }
+}
+
+/// Closest name Windows accepts: invalid characters and trailing dots or
+/// spaces become `_`, reserved names get a `_` after the device name, and
+/// long names are shortened keeping their extension
+pub fn sanitize_name(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|c| if is_invalid_char(c) { '_' } else { c })
+        .collect();
+
+    if utf16_len(&name) > MAX_NAME_LEN {
+        let (stem, extension) = match name.rfind('.') {
+            Some(dot) if dot > 0 && utf16_len(&name[dot..]) <= 16 => name.split_at(dot),
+            _ => (name.as_str(), ""),
+        };
+        let mut budget = MAX_NAME_LEN - utf16_len(extension);
+        let shortened: String = stem
+            .chars()
+            .take_while(|c| {
+                let fits = c.len_utf16() <= budget;
+                if fits {
+                    budget -= c.len_utf16();
+                }
+                fits
+            })
+            .chain(extension.chars())
+            .collect();
+        name = shortened;
+    }
+
+    let trimmed = name.trim_end_matches(['.', ' ']).len();
+    let trailing = name.len() - trimmed;
+    name.truncate(trimmed);
+    name.push_str(&"_".repeat(trailing));
+
+    if is_reserved(&name) {
+        let stem_len = name.find('.').unwrap_or(name.len());
+        name.insert(stem_len, '_');
+    }
+    name
+}
+
+/// Windows compares names case-insensitively
+fn fold(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// Apply Windows naming rules to the entries about to be synced to `destination`
+///
+/// Every component of an entry's relative path is checked, along with the
+/// length of its full destination path. Entries under a directory that was
+/// renamed or left out follow it without being reported again. Sanitized
+/// entries keep their source path and only change where they're written.
+pub fn apply(
+    entries: Vec<FileEntry>,
+    destination: &Path,
+    mode: NameMode,
+) -> Result<(Vec<FileEntry>, NameReport)> {
+    let mut report = NameReport::default();
+    let path_too_long =
+        |relative: &Path| utf16_len(&destination.join(relative).to_string_lossy()) > MAX_PATH_LEN;
+
+    // Destination paths of entries that keep their names
+    let mut taken: HashSet<String> = entries
+        .iter()
+        .filter(|entry| {
+            entry
+                .relative_path
+                .components()
+                .all(|c| name_problem(&c.as_os_str().to_string_lossy()).is_none())
+        })
+        .map(|entry| fold(&entry.relative_path))
+        .collect();
+    let mut skipped_dirs: Vec<PathBuf> = Vec::new();
+
+    let mut kept = Vec::with_capacity(entries.len());
+    for mut entry in entries {
+        if skipped_dirs
+            .iter()
+            .any(|dir| entry.relative_path.starts_with(dir))
+        {
+            continue;
+        }
+
+        let names: Vec<String> = entry
+            .relative_path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let problems: Vec<Option<NameProblem>> = names.iter().map(|n| name_problem(n)).collect();
+        let own_problem = problems.last().copied().flatten();
+        let parent_problem = problems.iter().rev().skip(1).find_map(|problem| *problem);
+
+        let problem = match mode {
+            NameMode::Fail => own_problem.or(parent_problem).or_else(|| {
+                path_too_long(&entry.relative_path).then_some(NameProblem::PathTooLong)
+            }),
+            NameMode::Skip => {
+                if parent_problem.is_some() {
+                    // Left out with its parent directory
+                    continue;
+                }
+                own_problem.or_else(|| {
+                    path_too_long(&entry.relative_path).then_some(NameProblem::PathTooLong)
+                })
+            }
+            NameMode::Sanitize => {
+                if own_problem.is_none() && parent_problem.is_none() {
+                    path_too_long(&entry.relative_path).then_some(NameProblem::PathTooLong)
+                } else {
+                    let renamed: PathBuf = names
+                        .iter()
+                        .zip(&problems)
+                        .map(|(name, problem)| match problem {
+                            Some(_) => sanitize_name(name),
+                            None => name.clone(),
+                        })
+                        .collect();
+                    if path_too_long(&renamed) {
+                        Some(NameProblem::PathTooLong)
+                    } else if !taken.insert(fold(&renamed)) {
+                        Some(NameProblem::Collision)
+                    } else {
+                        if own_problem.is_some() {
+                            report
+                                .renamed
+                                .push((entry.relative_path.clone(), renamed.clone()));
+                        }
+                        entry.relative_path = renamed;
+                        None
+                    }
+                }
+            }
+        };
+
+        match problem {
+            Some(problem) if mode == NameMode::Fail => {
+                return Err(SyncError::WindowsName {
+                    path: entry.relative_path,
+                    problem: problem.to_string(),
+                });
+            }
+            Some(problem) => {
+                if entry.is_dir {
+                    skipped_dirs.push(entry.relative_path.clone());
+                }
+                report.skipped.push((entry.relative_path, problem));
+            }
+            None => kept.push(entry),
+        }
+    }
+
+    Ok((kept, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn entry(path: &str, is_dir: bool) -> FileEntry {
+        FileEntry {
+            path: Path::new("/src").join(path),
+            relative_path: PathBuf::from(path),
+            size: 0,
+            modified: SystemTime::UNIX_EPOCH,
+            is_dir,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 0,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        }
+    }
+
+    fn paths(entries: &[FileEntry]) -> Vec<&Path> {
+        entries.iter().map(|e| e.relative_path.as_path()).collect()
+    }
+
+    #[test]
+    fn test_name_problems() {
+        for name in [
+            "file.txt",
+            "CONSOLE",
+            "com10",
+            "aux-1.txt",
+            ".hidden",
+            "naïve",
+        ] {
+            assert_eq!(name_problem(name), None, "{}", name);
+        }
+        for (name, problem) in [
+            ("CON", NameProblem::Reserved),
+            ("aux.txt", NameProblem::Reserved),
+            ("nul.tar.gz", NameProblem::Reserved),
+            ("Com1", NameProblem::Reserved),
+            ("lpt²", NameProblem::Reserved),
+            ("notes.", NameProblem::TrailingDotOrSpace),
+            ("notes ", NameProblem::TrailingDotOrSpace),
+            ("a:b.txt", NameProblem::InvalidChar),
+            ("what?", NameProblem::InvalidChar),
+            ("tab\there", NameProblem::InvalidChar),
+        ] {
+            assert_eq!(name_problem(name), Some(problem), "{}", name);
+        }
+        assert_eq!(
+            name_problem(&"x".repeat(256)),
+            Some(NameProblem::NameTooLong)
+        );
+        assert_eq!(name_problem(&"x".repeat(255)), None);
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("aux.txt"), "aux_.txt");
+        assert_eq!(sanitize_name("CON"), "CON_");
+        assert_eq!(sanitize_name("notes. "), "notes__");
+        assert_eq!(sanitize_name("a:b|c?.txt"), "a_b_c_.txt");
+
+        let long = format!("{}.txt", "x".repeat(300));
+        let sanitized = sanitize_name(&long);
+        assert!(sanitized.ends_with(".txt"));
+        assert_eq!(utf16_len(&sanitized), MAX_NAME_LEN);
+        assert_eq!(name_problem(&sanitized), None);
+    }
+
+    #[test]
+    fn test_apply_skip() {
+        let entries = vec![
+            entry("ok.txt", false),
+            entry("aux", true),
+            entry("aux/file.txt", false),
+            entry("a:b.txt", false),
+        ];
+        let (kept, report) = apply(entries, Path::new("/dest"), NameMode::Skip).unwrap();
+
+        assert_eq!(paths(&kept), vec![Path::new("ok.txt")]);
+        assert_eq!(
+            report.skipped,
+            vec![
+                (PathBuf::from("aux"), NameProblem::Reserved),
+                (PathBuf::from("a:b.txt"), NameProblem::InvalidChar),
+            ]
+        );
+        assert!(report.renamed.is_empty());
+    }
+
+    #[test]
+    fn test_apply_sanitize() {
+        let entries = vec![
+            entry("aux", true),
+            entry("aux/file.txt", false),
+            entry("a_b.txt", false),
+            entry("a:b.txt", false),
+            entry("notes.", false),
+        ];
+        let (kept, report) = apply(entries, Path::new("/dest"), NameMode::Sanitize).unwrap();
+
+        assert_eq!(
+            paths(&kept),
+            vec![
+                Path::new("aux_"),
+                Path::new("aux_/file.txt"),
+                Path::new("a_b.txt"),
+                Path::new("notes_"),
+            ]
+        );
+        // Source paths are untouched
+        assert_eq!(kept[1].path, Path::new("/src/aux/file.txt"));
+        assert_eq!(
+            report.renamed,
+            vec![
+                (PathBuf::from("aux"), PathBuf::from("aux_")),
+                (PathBuf::from("notes."), PathBuf::from("notes_")),
+            ]
+        );
+        assert_eq!(
+            report.skipped,
+            vec![(PathBuf::from("a:b.txt"), NameProblem::Collision)]
+        );
+    }
+
+    #[test]
+    fn test_apply_long_paths_and_fail() {
+        let deep = format!("{}/file.txt", "d".repeat(250));
+        let (kept, report) = apply(
+            vec![entry(&deep, false)],
+            Path::new("/dest"),
+            NameMode::Sanitize,
+        )
+        .unwrap();
+        assert!(kept.is_empty());
+        assert_eq!(report.skipped[0].1, NameProblem::PathTooLong);
+
+        let result = apply(
+            vec![entry("ok.txt", false), entry("PRN.log", false)],
+            Path::new("/dest"),
+            NameMode::Fail,
+        );
+        assert!(matches!(result, Err(SyncError::WindowsName { .. })));
+    }
+}
@@ -0,0 +1,186 @@
+use super::strategy::SyncTask;
+use crate::error::SyncError;
+use crate::transport::{ProgressCallback, TransferResult};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Shortest time between progress reports for one file
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How far a file copy has got
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileProgress {
+    pub bytes_done: u64,
+    pub total: u64,
+    /// Time since the copy started
+    pub elapsed: Duration,
+}
+
+impl FileProgress {
+    /// Average speed of the copy so far
+    pub fn bytes_per_sec(&self) -> u64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            (self.bytes_done as f64 / secs) as u64
+        } else {
+            0
+        }
+    }
+
+    /// Time left at the average speed so far, None before anything moved
+    pub fn eta(&self) -> Option<Duration> {
+        let speed = self.bytes_per_sec();
+        (speed > 0).then(|| {
+            Duration::from_secs_f64(
+                self.total.saturating_sub(self.bytes_done) as f64 / speed as f64,
+            )
+        })
+    }
+}
+
+/// Receives progress from a running sync
+///
+/// The CLI's progress bar and `--json` output are observers too; library
+/// users add their own with [`SyncEngine::with_observer`](super::SyncEngine::with_observer)
+/// to get structured progress without parsing stdout. Transfers run in
+/// parallel, so the file callbacks are called from several tasks at once and
+/// should return quickly. Every callback has an empty default.
+pub trait SyncObserver: Send + Sync {
+    /// The source scan started (`complete: false`) or finished
+    fn on_scan_progress(&self, _source: &Path, _files_found: usize, _complete: bool) {}
+
+    /// An entry is about to be created, updated, skipped or deleted
+    fn on_file_start(&self, _task: &SyncTask) {}
+
+    /// A file copy to `path` moved along; called at most twice a second per
+    /// file, and once it's complete
+    ///
+    /// Only copies streamed in chunks (SFTP uploads) report progress.
+    fn on_file_progress(&self, _path: &Path, _progress: &FileProgress) {}
+
+    /// An entry was processed; `transfer` is what copying it took (None for
+    /// directories, skips, deletions and dry runs)
+    fn on_file_done(&self, _task: &SyncTask, _transfer: Option<&TransferResult>) {}
+
+    /// An entry failed; with `--retry-failed` it may start again later in
+    /// the run
+    fn on_error(&self, _task: &SyncTask, _error: &SyncError) {}
+}
+
+/// Passes every callback on to each observer of a sync, in order
+pub(crate) struct Observers(Vec<Arc<dyn SyncObserver>>);
+
+impl Observers {
+    pub fn new(observers: Vec<Arc<dyn SyncObserver>>) -> Self {
+        Self(observers)
+    }
+
+    pub fn push(&mut self, observer: Arc<dyn SyncObserver>) {
+        self.0.push(observer);
+    }
+
+    /// Callback for a copy to `path` that passes its progress on to
+    /// `on_file_progress`, at most every `PROGRESS_INTERVAL`
+    pub fn file_progress(self: &Arc<Self>, path: PathBuf) -> ProgressCallback {
+        let observers = Arc::clone(self);
+        let started = Instant::now();
+        let last_report: Mutex<Option<Instant>> = Mutex::new(None);
+        Arc::new(move |bytes_done, total| {
+            let now = Instant::now();
+            {
+                let mut last_report = last_report.lock().unwrap();
+                let recent = last_report.is_some_and(|last| now - last < PROGRESS_INTERVAL);
+                if recent && bytes_done < total {
+                    return;
+                }
+                *last_report = Some(now);
+            }
+            observers.on_file_progress(
+                &path,
+                &FileProgress {
+                    bytes_done,
+                    total,
+                    elapsed: now - started,
+                },
+            );
+        })
+    }
+}
+
+impl SyncObserver for Observers {
+    fn on_scan_progress(&self, source: &Path, files_found: usize, complete: bool) {
+        for observer in &self.0 {
+            observer.on_scan_progress(source, files_found, complete);
+        }
+    }
+
+    fn on_file_start(&self, task: &SyncTask) {
+        for observer in &self.0 {
+            observer.on_file_start(task);
+        }
+    }
+
+    fn on_file_progress(&self, path: &Path, progress: &FileProgress) {
+        for observer in &self.0 {
+            observer.on_file_progress(path, progress);
+        }
+    }
+
+    fn on_file_done(&self, task: &SyncTask, transfer: Option<&TransferResult>) {
+        for observer in &self.0 {
+            observer.on_file_done(task, transfer);
+        }
+    }
+
+    fn on_error(&self, task: &SyncTask, error: &SyncError) {
+        for observer in &self.0 {
+            observer.on_error(task, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_progress_eta() {
+        let progress = FileProgress {
+            bytes_done: 0,
+            total: 1000,
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(progress.bytes_per_sec(), 0);
+        assert_eq!(progress.eta(), None);
+
+        let progress = FileProgress {
+            bytes_done: 250,
+            total: 1000,
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(progress.bytes_per_sec(), 125);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn test_file_progress_throttled() {
+        struct Recorder(Mutex<Vec<u64>>);
+
+        impl SyncObserver for Recorder {
+            fn on_file_progress(&self, _path: &Path, progress: &FileProgress) {
+                self.0.lock().unwrap().push(progress.bytes_done);
+            }
+        }
+
+        let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+        let observers = Arc::new(Observers::new(vec![recorder.clone()]));
+        let progress = observers.file_progress(PathBuf::from("big.bin"));
+        for done in [100, 200, 300, 400] {
+            progress(done, 400);
+        }
+
+        // The first report goes out, then only the last one within the interval
+        assert_eq!(*recorder.0.lock().unwrap(), vec![100, 400]);
+    }
+}
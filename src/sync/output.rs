@@ -1,5 +1,17 @@
-use serde::Serialize;
-use std::path::PathBuf;
+use super::observer::{FileProgress, SyncObserver};
+use super::strategy::{PlanReason, SyncAction, SyncTask};
+use crate::compress::CompressionDecision;
+use crate::transport::TransferResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether warnings are also emitted as `Warning` events (set once from `--json`)
+static JSON_WARNINGS: AtomicBool = AtomicBool::new(false);
+
+/// Whether verbose per-file lines say why each entry is copied or skipped (set once from `--why`)
+static WHY: AtomicBool = AtomicBool::new(false);
 
 /// JSON output mode for machine-readable sync events
 /// Uses NDJSON format (newline-delimited JSON)
@@ -11,16 +23,51 @@ pub enum SyncEvent {
         destination: PathBuf,
         total_files: usize,
     },
+    /// Source scan started (`complete: false`) or finished, before planning
+    ScanProgress {
+        path: PathBuf,
+        files_found: usize,
+        complete: bool,
+    },
+    /// Emitted after each planning batch (destination lookups)
+    PlanProgress {
+        files_planned: usize,
+        total_files: usize,
+    },
+    /// A destination entry the plan will delete (emitted in dry runs too)
+    DeletePlanned {
+        path: PathBuf,
+        size: Option<u64>,
+    },
+    /// An entry a dry run would create or update, and the comparison that
+    /// decided it (`--dry-run --json`)
+    CopyPlanned {
+        path: PathBuf,
+        action: SyncAction,
+        reason: PlanReason,
+        size: u64,
+    },
+    /// Non-fatal problem that was previously only logged
+    Warning {
+        category: WarningCategory,
+        path: Option<PathBuf>,
+        message: String,
+    },
     Create {
         path: PathBuf,
         size: u64,
         bytes_transferred: u64,
+        /// Codec chosen for a network transfer and why
+        #[serde(skip_serializing_if = "Option::is_none")]
+        compression: Option<CompressionDecision>,
     },
     Update {
         path: PathBuf,
         size: u64,
         bytes_transferred: u64,
         delta_used: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        compression: Option<CompressionDecision>,
     },
     Skip {
         path: PathBuf,
@@ -29,7 +76,27 @@ pub enum SyncEvent {
     Delete {
         path: PathBuf,
     },
-    #[allow(dead_code)] // Event for error reporting
+    /// How far a file copy has got, emitted while it runs (speed in bytes
+    /// per second, ETA None until anything moved)
+    Progress {
+        path: PathBuf,
+        bytes_done: u64,
+        total: u64,
+        bytes_per_sec: u64,
+        eta_secs: Option<f64>,
+    },
+    /// A file both sides of a bidirectional sync changed, and how it was
+    /// resolved (modification times in seconds since the Unix epoch)
+    Conflict {
+        path: PathBuf,
+        resolution: &'static str,
+        action: String,
+        source_size: Option<u64>,
+        source_modified: Option<u64>,
+        dest_size: Option<u64>,
+        dest_modified: Option<u64>,
+    },
+    /// A file that failed; it may be retried later in the run
     Error {
         path: PathBuf,
         error: String,
@@ -43,6 +110,10 @@ pub enum SyncEvent {
         duration_secs: f64,
         files_verified: usize,
         verification_failures: usize,
+        files_skipped_busy: usize,
+        files_deferred_hot: usize,
+        files_skipped_special: usize,
+        files_metadata_not_preserved: usize,
     },
     #[allow(dead_code)] // Event for verify-only mode (Phase 5c)
     VerificationResult {
@@ -73,6 +144,203 @@ pub enum SyncEvent {
     },
 }
 
+/// Machine-readable kind of a `Warning` event
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCategory {
+    /// Extended attributes that couldn't be read or written
+    Xattr,
+    /// ACLs that couldn't be parsed, applied or fully translated
+    Acl,
+    /// BSD file flags that couldn't be set
+    Flags,
+    /// Permission bits, owners or groups that couldn't be set (`-p`, `-o`, `-g`)
+    Permissions,
+    /// Dangling, looping or unsupported symlinks that were skipped
+    Symlink,
+    /// Source entries that couldn't be read during the scan
+    Scan,
+    /// Sockets, FIFOs and device nodes that weren't copied (`--specials`)
+    Special,
+    /// Names renamed or left out for Windows destinations (`--windows-names`)
+    Name,
+    /// Directory cache or checksum database problems
+    Cache,
+    /// Resume state that couldn't be loaded or saved
+    Resume,
+    /// Post-write verification failures
+    Verification,
+    /// Files skipped because they were being written (`--skip-busy`)
+    Busy,
+    /// Files left out to stay within `--dest-quota`
+    Quota,
+    /// Low disk space or file descriptor limits
+    Resource,
+    /// Destination lock problems
+    Lock,
+    /// Source and destination clocks that disagree by more than the mtime tolerance
+    Clock,
+}
+
+impl WarningCategory {
+    /// What the summary calls this kind of metadata; None for warnings that
+    /// aren't about metadata
+    fn metadata_label(self) -> Option<&'static str> {
+        match self {
+            Self::Xattr => Some("xattrs"),
+            Self::Acl => Some("ACLs"),
+            Self::Permissions => Some("owners/permissions"),
+            Self::Flags => Some("file flags"),
+            _ => None,
+        }
+    }
+}
+
+/// Why metadata couldn't be applied to a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataFailure {
+    /// The destination filesystem has no place for it (ENOTSUP, FAT, SMB, ...)
+    Unsupported,
+    /// Only root (or the file's owner) may set it (EPERM, EACCES)
+    NotPermitted,
+    /// Anything else, e.g. ACL text that couldn't be parsed
+    Other,
+}
+
+impl MetadataFailure {
+    /// Tell why from the error the write failed with
+    pub fn from_io_error(error: &std::io::Error) -> Self {
+        #[cfg(unix)]
+        match error.raw_os_error() {
+            Some(code) if code == libc::ENOTSUP || code == libc::EOPNOTSUPP => {
+                return Self::Unsupported;
+            }
+            Some(code) if code == libc::EPERM || code == libc::EACCES => {
+                return Self::NotPermitted;
+            }
+            _ => {}
+        }
+        match error.kind() {
+            std::io::ErrorKind::Unsupported => Self::Unsupported,
+            std::io::ErrorKind::PermissionDenied => Self::NotPermitted,
+            _ => Self::Other,
+        }
+    }
+
+    /// Tell why from a transport error, e.g. of setting permissions
+    pub fn from_error(error: &crate::error::SyncError) -> Self {
+        match error {
+            crate::error::SyncError::Io(e) => Self::from_io_error(e),
+            crate::error::SyncError::PermissionDenied { .. } => Self::NotPermitted,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Unsupported => "not supported by the destination filesystem",
+            Self::NotPermitted => "not permitted (run as root or as the files' owner)",
+            Self::Other => "other errors",
+        }
+    }
+}
+
+/// Metadata that couldn't be applied to a file, classified where the write failed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataError {
+    pub category: WarningCategory,
+    pub reason: MetadataFailure,
+    pub message: String,
+}
+
+impl MetadataError {
+    pub fn new(category: WarningCategory, reason: MetadataFailure, message: String) -> Self {
+        Self {
+            category,
+            reason,
+            message,
+        }
+    }
+}
+
+/// Files whose xattrs, ACLs, owners, permissions or flags couldn't be
+/// applied in a sync, by kind and reason
+///
+/// Filled in by the transfers of a sync, so the end of the run can say "ACLs
+/// not preserved on 1200 files: not supported by the destination filesystem"
+/// rather than leaving 1200 warnings in the log. Only counts and one example
+/// path are kept, however many files there are.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MetadataReport {
+    /// Files affected and the first of them, per kind and reason
+    failures: BTreeMap<(WarningCategory, MetadataFailure), (usize, PathBuf)>,
+    /// Files with at least one kind of metadata missing
+    files: usize,
+}
+
+impl MetadataReport {
+    pub fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Files with at least one kind of metadata missing
+    pub fn file_count(&self) -> usize {
+        self.files
+    }
+
+    /// Count one file's missing metadata; each kind and reason is counted once
+    pub fn add(
+        &mut self,
+        path: &Path,
+        failures: impl IntoIterator<Item = (WarningCategory, MetadataFailure)>,
+    ) {
+        let mut counted = false;
+        let mut seen = Vec::new();
+        for key in failures {
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            self.failures
+                .entry(key)
+                .or_insert_with(|| (0, path.to_path_buf()))
+                .0 += 1;
+            counted = true;
+        }
+        if counted {
+            self.files += 1;
+        }
+    }
+
+    /// One line per kind and reason, e.g. "xattrs: 12 files, not supported
+    /// by the destination filesystem (e.g. /dst/a.txt)"
+    pub fn lines(&self) -> Vec<(WarningCategory, String)> {
+        self.failures
+            .iter()
+            .map(|((category, reason), (count, example))| {
+                let line = format!(
+                    "{}: {} {}, {} (e.g. {})",
+                    category.metadata_label().unwrap_or("metadata"),
+                    count,
+                    if *count == 1 { "file" } else { "files" },
+                    reason.description(),
+                    example.display()
+                );
+                (*category, line)
+            })
+            .collect()
+    }
+
+    /// Log the report as one warning per kind and reason (`Warning` events in
+    /// JSON mode)
+    pub fn warn(&self) {
+        for (category, line) in self.lines() {
+            warn(category, None, format!("Metadata not preserved: {}", line));
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct VerificationError {
     pub path: PathBuf,
@@ -89,6 +357,110 @@ impl SyncEvent {
     }
 }
 
+/// Emits scan progress and per-file events as JSON (`--json`)
+pub(crate) struct JsonObserver;
+
+impl SyncObserver for JsonObserver {
+    fn on_scan_progress(&self, source: &Path, files_found: usize, complete: bool) {
+        SyncEvent::ScanProgress {
+            path: source.to_path_buf(),
+            files_found,
+            complete,
+        }
+        .emit();
+    }
+
+    fn on_file_progress(&self, path: &Path, progress: &FileProgress) {
+        SyncEvent::Progress {
+            path: path.to_path_buf(),
+            bytes_done: progress.bytes_done,
+            total: progress.total,
+            bytes_per_sec: progress.bytes_per_sec(),
+            eta_secs: progress.eta().map(|eta| eta.as_secs_f64()),
+        }
+        .emit();
+    }
+
+    fn on_file_done(&self, task: &SyncTask, transfer: Option<&TransferResult>) {
+        let path = task.dest_path.clone();
+        let size = task.source.as_ref().map_or(0, |source| source.size);
+        let bytes_transferred = transfer.map_or(0, |result| result.bytes_written);
+        let compression = transfer.and_then(|result| result.compression_decision);
+        match task.action {
+            SyncAction::Create => SyncEvent::Create {
+                path,
+                size,
+                bytes_transferred,
+                compression,
+            },
+            SyncAction::Update => SyncEvent::Update {
+                path,
+                size,
+                bytes_transferred,
+                delta_used: transfer.is_some_and(TransferResult::used_delta),
+                compression,
+            },
+            SyncAction::Skip => SyncEvent::Skip {
+                path,
+                reason: "up_to_date".to_string(),
+            },
+            SyncAction::Delete => SyncEvent::Delete { path },
+        }
+        .emit();
+    }
+
+    fn on_error(&self, task: &SyncTask, error: &crate::error::SyncError) {
+        SyncEvent::Error {
+            path: task.dest_path.clone(),
+            error: error.to_string(),
+        }
+        .emit();
+    }
+}
+
+/// Emit warnings as `Warning` events as well as logging them
+pub fn set_json_warnings(enabled: bool) {
+    JSON_WARNINGS.store(enabled, Ordering::Relaxed);
+}
+
+/// Append the planner's reason to verbose per-file lines
+pub fn set_why(enabled: bool) {
+    WHY.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--why` is on
+pub fn why() -> bool {
+    WHY.load(Ordering::Relaxed)
+}
+
+/// Log a warning and, in JSON mode, emit it as a `Warning` event
+pub fn warn(category: WarningCategory, path: Option<&Path>, message: impl Into<String>) {
+    let message = message.into();
+    tracing::warn!("{}", message);
+    emit_warning(category, path, message);
+}
+
+/// Log a warning that a summary at the end of the sync stands in for
+///
+/// Logged at debug level only (`-v`); in JSON mode it's still emitted as a
+/// `Warning` event.
+pub fn note(category: WarningCategory, path: Option<&Path>, message: impl Into<String>) {
+    let message = message.into();
+    tracing::debug!("{}", message);
+    emit_warning(category, path, message);
+}
+
+fn emit_warning(category: WarningCategory, path: Option<&Path>, message: String) {
+    if JSON_WARNINGS.load(Ordering::Relaxed) {
+        SyncEvent::Warning {
+            category,
+            path: path.map(Path::to_path_buf),
+            message,
+        }
+        .emit();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,17 +478,88 @@ mod tests {
         assert!(json.contains(r#""total_files":100"#));
     }
 
+    #[test]
+    fn test_serialize_pre_transfer_events() {
+        let json = serde_json::to_string(&SyncEvent::ScanProgress {
+            path: PathBuf::from("/src"),
+            files_found: 42,
+            complete: true,
+        })
+        .unwrap();
+        assert!(json.contains(r#""type":"scan_progress"#));
+        assert!(json.contains(r#""files_found":42"#));
+
+        let json = serde_json::to_string(&SyncEvent::PlanProgress {
+            files_planned: 500,
+            total_files: 1200,
+        })
+        .unwrap();
+        assert!(json.contains(r#""type":"plan_progress"#));
+        assert!(json.contains(r#""files_planned":500"#));
+
+        let json = serde_json::to_string(&SyncEvent::DeletePlanned {
+            path: PathBuf::from("/dst/old.txt"),
+            size: Some(10),
+        })
+        .unwrap();
+        assert!(json.contains(r#""type":"delete_planned"#));
+        assert!(json.contains(r#""size":10"#));
+
+        let json = serde_json::to_string(&SyncEvent::CopyPlanned {
+            path: PathBuf::from("/dst/app.db"),
+            action: SyncAction::Update,
+            reason: PlanReason::MtimeNewer,
+            size: 4096,
+        })
+        .unwrap();
+        assert!(json.contains(r#""type":"copy_planned"#));
+        assert!(json.contains(r#""action":"update"#));
+        assert!(json.contains(r#""reason":"mtime_newer"#));
+    }
+
+    #[test]
+    fn test_serialize_warning_event() {
+        let event = SyncEvent::Warning {
+            category: WarningCategory::Xattr,
+            path: Some(PathBuf::from("/dst/file.txt")),
+            message: "Skipped privileged xattrs security.capability".to_string(),
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#""type":"warning"#));
+        assert!(json.contains(r#""category":"xattr"#));
+        assert!(json.contains(r#""path":"/dst/file.txt"#));
+    }
+
     #[test]
     fn test_serialize_create_event() {
         let event = SyncEvent::Create {
             path: PathBuf::from("file.txt"),
             size: 1234,
             bytes_transferred: 1234,
+            compression: None,
         };
 
         let json = serde_json::to_string(&event).unwrap();
         assert!(json.contains(r#""type":"create"#));
         assert!(json.contains(r#""size":1234"#));
+        assert!(!json.contains("compression"));
+    }
+
+    #[test]
+    fn test_serialize_progress_event() {
+        let event = SyncEvent::Progress {
+            path: PathBuf::from("big.iso"),
+            bytes_done: 1024,
+            total: 4096,
+            bytes_per_sec: 512,
+            eta_secs: Some(6.0),
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#""type":"progress"#));
+        assert!(json.contains(r#""bytes_done":1024"#));
+        assert!(json.contains(r#""eta_secs":6.0"#));
     }
 
     #[test]
@@ -125,12 +568,20 @@ mod tests {
             path: PathBuf::from("file.txt"),
             size: 5678,
             bytes_transferred: 234,
-            delta_used: true,
+            delta_used: false,
+            compression: Some(CompressionDecision {
+                compression: crate::compress::Compression::None,
+                reason: crate::compress::CompressionReason::Incompressible,
+                sample_ratio: Some(0.97),
+            }),
         };
 
         let json = serde_json::to_string(&event).unwrap();
         assert!(json.contains(r#""type":"update"#));
-        assert!(json.contains(r#""delta_used":true"#));
+        assert!(json.contains(r#""delta_used":false"#));
+        assert!(json.contains(
+            r#""compression":{"compression":"none","reason":"incompressible","sample_ratio":0.97}"#
+        ));
     }
 
     #[test]
@@ -144,6 +595,10 @@ mod tests {
             duration_secs: 12.5,
             files_verified: 15,
             verification_failures: 0,
+            files_skipped_busy: 0,
+            files_deferred_hot: 0,
+            files_skipped_special: 0,
+            files_metadata_not_preserved: 3,
         };
 
         let json = serde_json::to_string(&event).unwrap();
@@ -152,6 +607,81 @@ mod tests {
         assert!(json.contains(r#""duration_secs":12.5"#));
         assert!(json.contains(r#""files_verified":15"#));
         assert!(json.contains(r#""verification_failures":0"#));
+        assert!(json.contains(r#""files_metadata_not_preserved":3"#));
+    }
+
+    #[test]
+    fn test_metadata_failure_from_io_error() {
+        use std::io::{Error, ErrorKind};
+
+        #[cfg(unix)]
+        {
+            let os_error = |code| MetadataFailure::from_io_error(&Error::from_raw_os_error(code));
+            assert_eq!(os_error(libc::ENOTSUP), MetadataFailure::Unsupported);
+            assert_eq!(os_error(libc::EOPNOTSUPP), MetadataFailure::Unsupported);
+            assert_eq!(os_error(libc::EPERM), MetadataFailure::NotPermitted);
+            assert_eq!(os_error(libc::EACCES), MetadataFailure::NotPermitted);
+            assert_eq!(os_error(libc::EINVAL), MetadataFailure::Other);
+        }
+        assert_eq!(
+            MetadataFailure::from_io_error(&Error::from(ErrorKind::Unsupported)),
+            MetadataFailure::Unsupported
+        );
+        assert_eq!(
+            MetadataFailure::from_io_error(&Error::from(ErrorKind::PermissionDenied)),
+            MetadataFailure::NotPermitted
+        );
+        // The message doesn't matter, only the error
+        assert_eq!(
+            MetadataFailure::from_io_error(&Error::other("Operation not supported")),
+            MetadataFailure::Other
+        );
+        assert_eq!(
+            MetadataFailure::from_error(&crate::error::SyncError::PermissionDenied {
+                path: PathBuf::from("/srv/a.txt")
+            }),
+            MetadataFailure::NotPermitted
+        );
+    }
+
+    #[test]
+    fn test_metadata_report() {
+        let mut report = MetadataReport::default();
+        assert!(report.is_empty());
+
+        let unsupported = (WarningCategory::Xattr, MetadataFailure::Unsupported);
+        // Several xattrs failing on one file count it once
+        report.add(
+            Path::new("/dst/a.txt"),
+            [
+                unsupported,
+                unsupported,
+                (WarningCategory::Acl, MetadataFailure::NotPermitted),
+            ],
+        );
+        report.add(Path::new("/dst/b.txt"), [unsupported]);
+        report.add(Path::new("/dst/c.txt"), []);
+
+        assert_eq!(report.file_count(), 2);
+        assert_eq!(
+            report.failures[&unsupported],
+            (2, PathBuf::from("/dst/a.txt"))
+        );
+        assert_eq!(
+            report.lines(),
+            vec![
+                (
+                    WarningCategory::Xattr,
+                    "xattrs: 2 files, not supported by the destination filesystem (e.g. /dst/a.txt)"
+                        .to_string()
+                ),
+                (
+                    WarningCategory::Acl,
+                    "ACLs: 1 file, not permitted (run as root or as the files' owner) (e.g. /dst/a.txt)"
+                        .to_string()
+                ),
+            ]
+        );
     }
 
     #[test]
@@ -0,0 +1,447 @@
+use crate::error::format_bytes;
+use crate::filter::{self, FilterEngine};
+use crate::transport::Transport;
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Clock differences below this are filesystem timestamp granularity (FAT
+/// rounds to 2 seconds), not skew
+const CLOCK_SKEW_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Name of the directory created to read the destination's clock
+const CLOCK_PROBE_NAME: &str = ".sy-preflight-clock";
+
+/// Outcome of one preflight check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    /// The sync can run, but something deserves a look first
+    Warn,
+    /// The sync would fail
+    Fail,
+    /// Not applicable to this pair, or blocked by an earlier failure
+    Skip,
+}
+
+impl Status {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Pass => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+            Status::Skip => "skip",
+        }
+    }
+}
+
+/// One line of the preflight report
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Go/no-go report printed by `sy preflight`
+#[derive(Debug, Default, Serialize)]
+pub struct PreflightReport {
+    pub checks: Vec<Check>,
+}
+
+impl PreflightReport {
+    /// Whether no check failed
+    pub fn go(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Fail)
+    }
+
+    pub fn push(&mut self, check: Check) {
+        self.checks.push(check);
+    }
+}
+
+/// Parse filter rules and pattern lists the way a sync would
+///
+/// Unlike a sync, which stops at the first bad pattern, every problem is
+/// collected into the check's detail.
+pub fn load_filters(
+    rules: &[String],
+    includes: &[String],
+    excludes: &[String],
+    include_from: Option<&str>,
+    exclude_from: Option<&str>,
+) -> (FilterEngine, Check) {
+    let mut engine = FilterEngine::new();
+    let mut problems = Vec::new();
+
+    for rule in rules {
+        if let Err(e) = engine.add_rule(rule) {
+            problems.push(format!("filter rule '{}': {}", rule, e));
+        }
+    }
+    for pattern in includes {
+        if let Err(e) = engine.add_include(pattern) {
+            problems.push(format!("include pattern '{}': {}", pattern, e));
+        }
+    }
+    for pattern in excludes {
+        if let Err(e) = engine.add_exclude(pattern) {
+            problems.push(format!("exclude pattern '{}': {}", pattern, e));
+        }
+    }
+    for (list, include) in [(include_from, true), (exclude_from, false)] {
+        let Some(list) = list else {
+            continue;
+        };
+        let kind = if include { "include" } else { "exclude" };
+        let text = match filter::read_pattern_list(list) {
+            Ok(text) => text,
+            Err(e) => {
+                problems.push(format!("{} list {}: {}", kind, list, e));
+                continue;
+            }
+        };
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let added = if include {
+                engine.add_include(line)
+            } else {
+                engine.add_exclude(line)
+            };
+            if let Err(e) = added {
+                problems.push(format!(
+                    "{} pattern at line {} in {}: {}",
+                    kind,
+                    line_num + 1,
+                    list,
+                    e
+                ));
+            }
+        }
+    }
+
+    let check = if problems.is_empty() {
+        Check::new(
+            "filters",
+            Status::Pass,
+            format!("{} rules", engine.rule_count()),
+        )
+    } else {
+        Check::new("filters", Status::Fail, problems.join("; "))
+    };
+    (engine, check)
+}
+
+/// Run the checks that need a connected transport
+///
+/// Covers the sy-remote version, the source, destination permissions,
+/// free space (local destinations) and the destination's clock. Nothing is
+/// changed, except a probe file and directory created and removed again.
+pub async fn check_pair<T: Transport>(
+    transport: &T,
+    source: &Path,
+    destination: &Path,
+    filter: &FilterEngine,
+    local_destination: bool,
+    report: &mut PreflightReport,
+) {
+    report.push(match transport.remote_version().await {
+        Ok(None) => Check::new("sy-remote", Status::Skip, "no remote side"),
+        Ok(Some(version)) if version == env!("CARGO_PKG_VERSION") => {
+            Check::new("sy-remote", Status::Pass, format!("version {}", version))
+        }
+        Ok(Some(version)) => Check::new(
+            "sy-remote",
+            Status::Warn,
+            format!(
+                "version {} differs from sy {}; newer features fall back or are skipped",
+                version,
+                env!("CARGO_PKG_VERSION")
+            ),
+        ),
+        Err(e) => Check::new("sy-remote", Status::Fail, first_line(&e.to_string())),
+    });
+
+    let source_bytes = match check_source(transport, source, filter).await {
+        Ok((check, bytes)) => {
+            report.push(check);
+            Some(bytes)
+        }
+        Err(check) => {
+            report.push(check);
+            None
+        }
+    };
+
+    let writable = transport.check_writable(destination).await;
+    report.push(match &writable {
+        Ok(()) => Check::new("permissions", Status::Pass, "destination is writable"),
+        Err(e) => Check::new(
+            "permissions",
+            Status::Fail,
+            e.to_string().replace('\n', " "),
+        ),
+    });
+
+    report.push(match source_bytes {
+        _ if !local_destination => Check::new(
+            "disk space",
+            Status::Skip,
+            "not measured for remote destinations",
+        ),
+        None => Check::new("disk space", Status::Skip, "source size unknown"),
+        Some(needed) => check_disk_space(destination, needed),
+    });
+
+    report.push(if writable.is_ok() {
+        check_clock(transport, destination).await
+    } else {
+        Check::new("clock", Status::Skip, "destination not writable")
+    });
+}
+
+/// Scan the source, returning the check and the bytes a full copy would send
+async fn check_source<T: Transport>(
+    transport: &T,
+    source: &Path,
+    filter: &FilterEngine,
+) -> std::result::Result<(Check, u64), Check> {
+    match transport.exists(source).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(Check::new(
+                "source",
+                Status::Fail,
+                format!("{} does not exist", source.display()),
+            ))
+        }
+        Err(e) => {
+            return Err(Check::new(
+                "source",
+                Status::Fail,
+                first_line(&e.to_string()),
+            ))
+        }
+    }
+
+    let (entries, errors) = transport
+        .scan_with_errors(source)
+        .await
+        .map_err(|e| Check::new("source", Status::Fail, first_line(&e.to_string())))?;
+    let (mut files, mut bytes, mut excluded) = (0usize, 0u64, 0usize);
+    for entry in entries.iter().filter(|e| !e.is_dir) {
+        if filter.should_exclude(&entry.relative_path, false) {
+            excluded += 1;
+        } else {
+            files += 1;
+            bytes += entry.size;
+        }
+    }
+
+    let mut detail = format!("{} files, {}", files, format_bytes(bytes));
+    if excluded > 0 {
+        detail.push_str(&format!(" ({} excluded by filters)", excluded));
+    }
+    let status = if errors.is_empty() {
+        Status::Pass
+    } else {
+        detail.push_str(&format!("; {} entries unreadable", errors.len()));
+        Status::Warn
+    };
+    Ok((Check::new("source", status, detail), bytes))
+}
+
+/// Compare free space at a local destination with the source size
+///
+/// An initial sync needs the whole source plus the 10% buffer a sync checks
+/// for; later syncs need less, so a shortfall is reported against that.
+fn check_disk_space(destination: &Path, needed: u64) -> Check {
+    let mut probe = destination;
+    while !probe.exists() {
+        match probe.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => probe = parent,
+            _ => break,
+        }
+    }
+
+    match crate::resource::get_available_space(probe) {
+        Ok(available) if available >= needed + needed / 10 => Check::new(
+            "disk space",
+            Status::Pass,
+            format!(
+                "{} free, an initial sync needs {}",
+                format_bytes(available),
+                format_bytes(needed)
+            ),
+        ),
+        Ok(available) => Check::new(
+            "disk space",
+            Status::Warn,
+            format!(
+                "{} free, but an initial sync needs {} plus a 10% buffer",
+                format_bytes(available),
+                format_bytes(needed)
+            ),
+        ),
+        Err(e) => Check::new("disk space", Status::Skip, first_line(&e.to_string())),
+    }
+}
+
+/// Read the destination's clock from the mtime of a directory created there
+///
+/// Directory mtimes come from the host (or file server) writing them, so
+/// this also catches skew on network mounts of a local destination.
+async fn check_clock<T: Transport>(transport: &T, destination: &Path) -> Check {
+    // Never create the destination itself just to probe it
+    let parent = match transport.exists(destination).await {
+        Ok(true) => destination,
+        _ => match destination.parent() {
+            Some(parent) if transport.exists(parent).await.unwrap_or(false) => parent,
+            _ => return Check::new("clock", Status::Skip, "destination doesn't exist yet"),
+        },
+    };
+    let probe = parent.join(CLOCK_PROBE_NAME);
+
+    let before = SystemTime::now();
+    if let Err(e) = transport.create_dir_all(&probe).await {
+        return Check::new("clock", Status::Skip, first_line(&e.to_string()));
+    }
+    let after = SystemTime::now();
+    let mtime = transport.get_mtime(&probe).await;
+    let _ = transport.remove(&probe, true).await;
+
+    let mtime = match mtime {
+        Ok(mtime) => mtime,
+        Err(e) => return Check::new("clock", Status::Skip, first_line(&e.to_string())),
+    };
+    let (skew, direction) = match (before.duration_since(mtime), mtime.duration_since(after)) {
+        (Ok(behind), _) => (behind, "behind"),
+        (_, Ok(ahead)) => (ahead, "ahead"),
+        _ => (Duration::ZERO, "ahead"),
+    };
+
+    if skew <= CLOCK_SKEW_TOLERANCE {
+        Check::new("clock", Status::Pass, "in sync with the destination")
+    } else {
+        Check::new(
+            "clock",
+            Status::Warn,
+            format!(
+                "destination clock is {}s {}; timestamps it sets itself won't compare with local ones",
+                skew.as_secs(),
+                direction
+            ),
+        )
+    }
+}
+
+/// Error messages carry hints on later lines; the report has one line per check
+fn first_line(message: &str) -> String {
+    message.lines().next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::local::LocalTransport;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_filters_collect_every_problem() {
+        let (_, check) = load_filters(
+            &["+ *.rs".to_string()],
+            &[],
+            &["*.tmp".to_string()],
+            None,
+            None,
+        );
+        assert_eq!(check.status, Status::Pass);
+
+        let (_, check) = load_filters(
+            &["+".to_string()],
+            &["[".to_string()],
+            &[],
+            Some("/nonexistent/include-list"),
+            None,
+        );
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("filter rule '+'"), "{}", check.detail);
+        assert!(
+            check.detail.contains("include pattern '['"),
+            "{}",
+            check.detail
+        );
+        assert!(check.detail.contains("include list"), "{}", check.detail);
+    }
+
+    #[tokio::test]
+    async fn test_local_pair() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("src");
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(source.join("a.txt"), "hello").unwrap();
+        fs::write(source.join("b.tmp"), "skipped").unwrap();
+
+        let (filter, _) = load_filters(&[], &[], &["*.tmp".to_string()], None, None);
+        let mut report = PreflightReport::default();
+        check_pair(
+            &LocalTransport::new(),
+            &source,
+            &dest,
+            &filter,
+            true,
+            &mut report,
+        )
+        .await;
+
+        let status = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|c| c.name == name)
+                .unwrap()
+                .status
+        };
+        assert_eq!(status("sy-remote"), Status::Skip);
+        assert_eq!(status("source"), Status::Pass);
+        assert_eq!(status("permissions"), Status::Pass);
+        assert_eq!(status("disk space"), Status::Pass);
+        assert_eq!(status("clock"), Status::Pass);
+        assert!(report.go());
+        assert!(report.checks[1]
+            .detail
+            .starts_with("1 files, 5 B (1 excluded"));
+        // The clock probe is removed again
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 0);
+
+        let mut report = PreflightReport::default();
+        let missing = temp.path().join("missing");
+        check_pair(
+            &LocalTransport::new(),
+            &missing,
+            &dest,
+            &filter,
+            true,
+            &mut report,
+        )
+        .await;
+        assert!(!report.go());
+    }
+}
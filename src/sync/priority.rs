@@ -0,0 +1,110 @@
+use crate::filter::{FilterAction, FilterRule};
+use std::path::Path;
+
+/// Transfer tier set by `--priority-pattern`; every task of a higher tier is
+/// started before any task of a lower one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+/// `PATTERN:LEVEL` rule from `--priority-pattern`
+#[derive(Debug, Clone)]
+pub struct PriorityRule {
+    rule: FilterRule,
+    pub priority: Priority,
+}
+
+impl PriorityRule {
+    /// Parse `PATTERN:LEVEL` (e.g. `*.db:high`)
+    ///
+    /// Patterns match like filter patterns: against the basename unless they
+    /// contain a '/', and a trailing '/' covers a directory and its contents.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (pattern, level) = spec
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Expected PATTERN:LEVEL (e.g. '*.db:high'), got '{}'", spec))?;
+        let priority = match level.trim().to_ascii_lowercase().as_str() {
+            "high" => Priority::High,
+            "normal" => Priority::Normal,
+            "low" => Priority::Low,
+            _ => {
+                return Err(format!(
+                    "Unknown priority '{}'. Use high, normal, or low",
+                    level
+                ))
+            }
+        };
+        if pattern.is_empty() {
+            return Err(format!("Missing pattern before ':' in '{}'", spec));
+        }
+        let rule =
+            FilterRule::new(FilterAction::Include, pattern).map_err(|e| format!("{:#}", e))?;
+
+        Ok(Self { rule, priority })
+    }
+}
+
+/// Priority of a source entry (path relative to the source root): the first
+/// matching rule's, or normal
+pub fn priority_of(rules: &[PriorityRule], path: &Path, is_dir: bool) -> Priority {
+    rules
+        .iter()
+        .find(|r| r.rule.matches(path, is_dir))
+        .map_or(Priority::Normal, |r| r.priority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            PriorityRule::parse("*.db:high").unwrap().priority,
+            Priority::High
+        );
+        assert_eq!(
+            PriorityRule::parse("logs/:LOW").unwrap().priority,
+            Priority::Low
+        );
+        // The level follows the last ':'
+        assert_eq!(
+            PriorityRule::parse("a:b:normal").unwrap().priority,
+            Priority::Normal
+        );
+
+        assert!(PriorityRule::parse("*.db").is_err());
+        assert!(PriorityRule::parse("*.db:urgent").is_err());
+        assert!(PriorityRule::parse(":high").is_err());
+        assert!(PriorityRule::parse("[abc:high").is_err());
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let rules: Vec<PriorityRule> = ["config/:high", "*.db:low", "*.db:high"]
+            .iter()
+            .map(|spec| PriorityRule::parse(spec).unwrap())
+            .collect();
+
+        assert_eq!(
+            priority_of(&rules, Path::new("config/app.db"), false),
+            Priority::High
+        );
+        assert_eq!(
+            priority_of(&rules, Path::new("data/app.db"), false),
+            Priority::Low
+        );
+        assert_eq!(
+            priority_of(&rules, Path::new("data/app.log"), false),
+            Priority::Normal
+        );
+        assert_eq!(
+            priority_of(&[], Path::new("app.db"), false),
+            Priority::Normal
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use super::observer::SyncObserver;
+use super::strategy::{SyncAction, SyncTask};
+use crate::error::SyncError;
+use crate::transport::TransferResult;
+use indicatif::{HumanDuration, ProgressBar, ProgressState};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Kind of file transfer, tracked separately because updates can go through
+/// delta sync and send a small fraction of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransferKind {
+    Create,
+    Update,
+}
+
+/// Kind and planned bytes of a task that transfers file data
+pub(crate) fn planned(task: &SyncTask) -> Option<(TransferKind, u64)> {
+    let kind = match task.action {
+        SyncAction::Create => TransferKind::Create,
+        SyncAction::Update => TransferKind::Update,
+        SyncAction::Skip | SyncAction::Delete => return None,
+    };
+    let source = task.source.as_ref()?;
+    Some((kind, if source.is_dir { 0 } else { source.size }))
+}
+
+/// Planned and network bytes of one transfer kind
+#[derive(Debug, Default, Clone, Copy)]
+struct KindBytes {
+    /// File bytes planned, including retries
+    planned: u64,
+    /// Planned bytes of transfers that finished or failed
+    done: u64,
+    /// Planned bytes of transfers that finished
+    measured: u64,
+    /// Bytes those transfers sent over the network
+    wire: u64,
+}
+
+impl KindBytes {
+    /// Network bytes the remaining transfers are expected to send, scaled by
+    /// the wire/planned ratio measured so far (1 until the first transfer)
+    fn remaining_wire(&self) -> f64 {
+        let remaining = self.planned.saturating_sub(self.done) as f64;
+        if self.measured == 0 {
+            remaining
+        } else {
+            remaining * self.wire as f64 / self.measured as f64
+        }
+    }
+}
+
+/// Progress accounting behind the progress bar's ETA
+///
+/// The bar advances by file size so it shows how much of the tree is done,
+/// but delta and compressed transfers send only part of each file. Timing the
+/// bar's own rate would make the ETA swing with the mix of full and delta
+/// transfers, so the ETA comes from the network bytes still expected (planned
+/// bytes scaled by each kind's measured ratio) over the network throughput
+/// measured so far.
+pub(crate) struct TransferProgress {
+    started: Instant,
+    kinds: Mutex<[KindBytes; 2]>,
+}
+
+impl TransferProgress {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            kinds: Mutex::new([KindBytes::default(); 2]),
+        }
+    }
+
+    /// Add a transfer of `bytes` to the plan
+    pub fn plan(&self, kind: TransferKind, bytes: u64) {
+        self.kinds.lock().unwrap()[kind as usize].planned += bytes;
+    }
+
+    /// Record a finished transfer of `bytes` planned that sent `wire` bytes
+    /// over the network, or a failed one (`wire` None)
+    pub fn finish(&self, kind: TransferKind, bytes: u64, wire: Option<u64>) {
+        let mut kinds = self.kinds.lock().unwrap();
+        let entry = &mut kinds[kind as usize];
+        entry.done += bytes;
+        if let Some(wire) = wire {
+            entry.measured += bytes;
+            entry.wire += wire;
+        }
+    }
+
+    /// Estimated time left, None until any bytes were sent
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta_after(self.started.elapsed())
+    }
+
+    fn eta_after(&self, elapsed: Duration) -> Option<Duration> {
+        let kinds = *self.kinds.lock().unwrap();
+        let sent: u64 = kinds.iter().map(|k| k.wire).sum();
+        if sent == 0 || elapsed.is_zero() {
+            return None;
+        }
+        let throughput = sent as f64 / elapsed.as_secs_f64();
+        let remaining: f64 = kinds.iter().map(KindBytes::remaining_wire).sum();
+        Some(Duration::from_secs_f64(remaining / throughput))
+    }
+
+    /// `{eta}` for the progress bar template, falling back to the bar's own
+    /// estimate until network bytes were measured
+    pub fn write_eta(&self, state: &ProgressState, w: &mut dyn fmt::Write) {
+        let eta = self.eta().unwrap_or_else(|| state.eta());
+        let _ = write!(w, "{:#}", HumanDuration(eta));
+    }
+}
+
+/// Drives the CLI progress bar: the message names the entry in flight and
+/// the bar advances by file size as entries finish, failed or not
+pub(crate) struct ProgressObserver {
+    bar: ProgressBar,
+}
+
+impl ProgressObserver {
+    pub fn new(bar: ProgressBar) -> Self {
+        Self { bar }
+    }
+
+    fn advance(&self, task: &SyncTask) {
+        if let Some(source) = task
+            .source
+            .as_ref()
+            .filter(|_| matches!(task.action, SyncAction::Create | SyncAction::Update))
+        {
+            self.bar.inc(source.size);
+        }
+    }
+}
+
+/// File name of `task`'s destination, for a short progress message
+fn display_name(task: &SyncTask) -> &str {
+    task.dest_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_else(|| task.dest_path.to_str().unwrap_or(""))
+}
+
+impl SyncObserver for ProgressObserver {
+    fn on_file_start(&self, task: &SyncTask) {
+        let filename = display_name(task);
+        let msg = match task.action {
+            SyncAction::Create => format!("Creating: {}", filename),
+            SyncAction::Update => format!("Updating: {}", filename),
+            SyncAction::Delete => format!("Deleting: {}", filename),
+            SyncAction::Skip => return,
+        };
+        self.bar.set_message(msg);
+    }
+
+    fn on_file_done(&self, task: &SyncTask, transfer: Option<&TransferResult>) {
+        if let Some(ratio) = transfer
+            .filter(|result| result.used_delta())
+            .and_then(TransferResult::compression_ratio)
+        {
+            self.bar.set_message(format!(
+                "Updating: {} (delta: {:.1}% literal)",
+                display_name(task),
+                ratio
+            ));
+        }
+        self.advance(task);
+    }
+
+    fn on_error(&self, task: &SyncTask, _error: &SyncError) {
+        self.advance(task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_eta_uses_measured_ratio_per_kind() {
+        let progress = TransferProgress::new();
+        progress.plan(TransferKind::Create, 100 * MB);
+        progress.plan(TransferKind::Update, 1000 * MB);
+        assert_eq!(progress.eta_after(Duration::from_secs(1)), None);
+
+        // A 100 MB update went through delta sync, sending 1 MB
+        progress.finish(TransferKind::Update, 100 * MB, Some(MB));
+        // 900 MB of updates left at 1% is 9 MB; the creates count in full
+        // until measured: 109 MB at 1 MB/s
+        assert_eq!(
+            progress.eta_after(Duration::from_secs(1)),
+            Some(Duration::from_secs(109))
+        );
+
+        // A failed create is done but says nothing about the ratio
+        progress.finish(TransferKind::Create, 50 * MB, None);
+        assert_eq!(
+            progress.eta_after(Duration::from_secs(1)),
+            Some(Duration::from_secs(59))
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use super::strategy::{SyncAction, SyncTask};
+use crate::error::{Result, SyncError};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// What to do when a sync would take the destination past `--dest-quota`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum QuotaMode {
+    /// Refuse to start the sync
+    #[default]
+    Stop,
+
+    /// Leave out the largest new or growing files until the rest fits
+    SkipLargest,
+}
+
+/// Size limit for the destination tree (`--dest-quota`, `--quota-mode`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DestQuota {
+    pub limit: u64,
+    pub mode: QuotaMode,
+}
+
+/// Destination usage before and after the planned sync
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct QuotaReport {
+    pub current: u64,
+    pub projected: u64,
+    /// Files left out to stay within the quota (skip-largest mode)
+    pub skipped: Vec<PathBuf>,
+    pub skipped_bytes: u64,
+}
+
+/// Bytes a task adds to (positive) or frees from (negative) the destination
+fn growth(task: &SyncTask) -> i64 {
+    let source_size = task
+        .source
+        .as_ref()
+        .filter(|f| !f.is_dir)
+        .map_or(0, |f| f.size as i64);
+    let dest_size = task.dest_size.unwrap_or(0) as i64;
+
+    match task.action {
+        SyncAction::Create | SyncAction::Update => source_size - dest_size,
+        SyncAction::Delete => -dest_size,
+        SyncAction::Skip => 0,
+    }
+}
+
+impl DestQuota {
+    /// Check planned tasks against the quota, given the destination's current usage
+    ///
+    /// In stop mode an over-quota plan is an error. In skip-largest mode the
+    /// transfers that grow the destination most are turned into skips until
+    /// the projected usage fits. Deletions are counted as freeing their space.
+    pub fn enforce(
+        &self,
+        tasks: &mut [SyncTask],
+        current: u64,
+        destination: &Path,
+    ) -> Result<QuotaReport> {
+        let projected = current as i64 + tasks.iter().map(growth).sum::<i64>();
+        let mut report = QuotaReport {
+            current,
+            projected: projected.max(0) as u64,
+            ..Default::default()
+        };
+
+        if report.projected <= self.limit {
+            return Ok(report);
+        }
+
+        if self.mode == QuotaMode::Stop {
+            return Err(SyncError::QuotaExceeded {
+                path: destination.to_path_buf(),
+                projected: report.projected,
+                quota: self.limit,
+            });
+        }
+
+        let mut growing: Vec<(usize, i64)> = tasks
+            .iter()
+            .enumerate()
+            .map(|(i, task)| (i, growth(task)))
+            .filter(|&(_, growth)| growth > 0)
+            .collect();
+        growing.sort_by_key(|&(_, growth)| std::cmp::Reverse(growth));
+
+        for (i, growth) in growing {
+            if report.projected <= self.limit {
+                break;
+            }
+            let task = &mut tasks[i];
+            task.action = SyncAction::Skip;
+            report.projected -= growth as u64;
+            report.skipped_bytes += growth as u64;
+            report.skipped.push(task.dest_path.clone());
+        }
+
+        // Only possible if the destination is already over quota
+        if report.projected > self.limit {
+            return Err(SyncError::QuotaExceeded {
+                path: destination.to_path_buf(),
+                projected: report.projected,
+                quota: self.limit,
+            });
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::scanner::FileEntry;
+    use crate::sync::strategy::PlanReason;
+    use std::time::SystemTime;
+
+    fn task(name: &str, action: SyncAction, size: u64, dest_size: Option<u64>) -> SyncTask {
+        SyncTask {
+            source: (action != SyncAction::Delete).then(|| FileEntry {
+                path: PathBuf::from("/source").join(name),
+                relative_path: PathBuf::from(name),
+                size,
+                modified: SystemTime::now(),
+                is_dir: false,
+                is_symlink: false,
+                symlink_target: None,
+                is_sparse: false,
+                allocated_size: size,
+                xattrs: None,
+                inode: None,
+                nlink: 1,
+                acls: None,
+                bsd_flags: None,
+                atime: None,
+                crtime: None,
+                perms: None,
+            }),
+            dest_path: PathBuf::from("/dest").join(name),
+            action,
+            reason: PlanReason::Missing,
+            dest_size,
+            dest_modified: None,
+            source_checksum: None,
+            dest_checksum: None,
+        }
+    }
+
+    fn plan() -> Vec<SyncTask> {
+        vec![
+            task("small", SyncAction::Create, 100, None),
+            task("large", SyncAction::Create, 1_000, None),
+            task("grown", SyncAction::Update, 600, Some(100)),
+            task("old", SyncAction::Delete, 0, Some(200)),
+            task("same", SyncAction::Skip, 5_000, Some(5_000)),
+        ]
+    }
+
+    #[test]
+    fn test_projected_usage() {
+        let quota = DestQuota {
+            limit: 10_000,
+            mode: QuotaMode::Stop,
+        };
+        let report = quota
+            .enforce(&mut plan(), 5_000, Path::new("/dest"))
+            .unwrap();
+
+        // 5000 + 100 + 1000 + 500 - 200
+        assert_eq!(report.projected, 6_400);
+        assert!(report.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_stop_mode_refuses() {
+        let quota = DestQuota {
+            limit: 6_000,
+            mode: QuotaMode::Stop,
+        };
+        assert!(matches!(
+            quota.enforce(&mut plan(), 5_000, Path::new("/dest")),
+            Err(SyncError::QuotaExceeded {
+                projected: 6_400,
+                quota: 6_000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_skip_largest() {
+        let quota = DestQuota {
+            limit: 5_500,
+            mode: QuotaMode::SkipLargest,
+        };
+        let mut tasks = plan();
+        let report = quota
+            .enforce(&mut tasks, 5_000, Path::new("/dest"))
+            .unwrap();
+
+        // Skipping the 1000-byte file alone fits: 6400 - 1000
+        assert_eq!(report.projected, 5_400);
+        assert_eq!(report.skipped, vec![PathBuf::from("/dest/large")]);
+        assert_eq!(report.skipped_bytes, 1_000);
+        assert_eq!(tasks[1].action, SyncAction::Skip);
+        assert_eq!(tasks[0].action, SyncAction::Create);
+
+        // Already over quota: nothing to skip makes it fit
+        assert!(quota
+            .enforce(&mut plan(), 9_000, Path::new("/dest"))
+            .is_err());
+    }
+}
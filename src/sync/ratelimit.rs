@@ -1,94 +1,200 @@
+use crate::transport::ProgressCallback;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-/// Simple token bucket rate limiter
-pub struct RateLimiter {
-    bytes_per_second: u64,
-    last_refill: Instant,
-    available_tokens: f64,
-    max_tokens: f64,
+/// Bandwidth limits for a sync (`--bwlimit` and `--bwlimit-per-file`)
+///
+/// Transfers run at full speed and are paced afterwards by holding their
+/// worker until the file's bytes fit the limit. The global limit is split
+/// evenly between the transfers in flight, so each worker only waits for its
+/// own bytes: a large file paces itself at its share and never leaves a debt
+/// that small files queued behind it have to pay off.
+pub struct BandwidthLimiter {
+    /// Global limit in bytes per second
+    global: Option<u64>,
+    /// Limit for any single file in bytes per second
+    per_file: Option<u64>,
+    /// Transfers holding a lane
+    active: AtomicUsize,
 }
 
-impl RateLimiter {
-    pub fn new(bytes_per_second: u64) -> Self {
-        // Allow burst up to 1 second worth of tokens
-        let max_tokens = bytes_per_second as f64;
+impl BandwidthLimiter {
+    pub fn new(global: Option<u64>, per_file: Option<u64>) -> Self {
         Self {
-            bytes_per_second,
-            last_refill: Instant::now(),
-            available_tokens: max_tokens,
-            max_tokens,
+            global,
+            per_file,
+            active: AtomicUsize::new(0),
         }
     }
 
-    /// Consume tokens for the given number of bytes
-    /// Returns the duration to sleep to maintain rate limit
-    pub fn consume(&mut self, bytes: u64) -> Duration {
-        // Refill tokens based on elapsed time
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_refill);
-        let tokens_to_add =
-            (elapsed.as_secs_f64() * self.bytes_per_second as f64).min(self.max_tokens);
-        self.available_tokens = (self.available_tokens + tokens_to_add).min(self.max_tokens);
-        self.last_refill = now;
-
-        let bytes_f64 = bytes as f64;
-
-        if self.available_tokens >= bytes_f64 {
-            // We have enough tokens, consume them
-            self.available_tokens -= bytes_f64;
-            Duration::ZERO
-        } else {
-            // Not enough tokens, calculate sleep time
-            let deficit = bytes_f64 - self.available_tokens;
-            let sleep_secs = deficit / self.bytes_per_second as f64;
-            self.available_tokens = 0.0;
-            Duration::from_secs_f64(sleep_secs)
+    /// Start pacing one transfer; its share of the global limit is held
+    /// until the lane is dropped
+    pub fn lane(self: &Arc<Self>) -> Lane {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        Lane {
+            limiter: Arc::clone(self),
+            started: Instant::now(),
+        }
+    }
+
+    /// Rate one of `active` transfers may use, None if unlimited
+    fn rate(&self, active: usize) -> Option<f64> {
+        let share = self.global.map(|limit| limit as f64 / active.max(1) as f64);
+        match (share, self.per_file) {
+            (Some(share), Some(per_file)) => Some(share.min(per_file as f64)),
+            (share, per_file) => share.or(per_file.map(|limit| limit as f64)),
+        }
+    }
+
+    /// How long a transfer of `bytes` that took `elapsed` must still wait
+    /// while `active` transfers share the limit
+    fn delay_after(&self, bytes: u64, elapsed: Duration, active: usize) -> Duration {
+        match self.rate(active) {
+            Some(rate) if rate > 0.0 && bytes > 0 => {
+                Duration::from_secs_f64(bytes as f64 / rate).saturating_sub(elapsed)
+            }
+            _ => Duration::ZERO,
         }
     }
 }
 
+/// One transfer's slot in a [`BandwidthLimiter`]
+pub struct Lane {
+    limiter: Arc<BandwidthLimiter>,
+    started: Instant,
+}
+
+impl Lane {
+    /// How long to wait after sending `bytes` so the transfer keeps to its
+    /// share of the limits
+    pub fn delay(&self, bytes: u64) -> Duration {
+        let active = self.limiter.active.load(Ordering::SeqCst);
+        self.limiter
+            .delay_after(bytes, self.started.elapsed(), active)
+    }
+
+    /// Wrap `progress` so the transfer is paced as its chunks go, rather
+    /// than held once it is done
+    ///
+    /// Transports report progress from blocking threads, so the wait after
+    /// each chunk sleeps the thread. The first report is the starting point,
+    /// so bytes a resumed transfer already had don't count.
+    pub fn pace(self: &Arc<Self>, progress: ProgressCallback) -> ProgressCallback {
+        let lane = Arc::clone(self);
+        let start = AtomicU64::new(u64::MAX);
+        Arc::new(move |bytes_done, total| {
+            progress(bytes_done, total);
+            let start = match start.compare_exchange(
+                u64::MAX,
+                bytes_done,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => bytes_done,
+                Err(start) => start,
+            };
+            let wait = lane.delay(bytes_done.saturating_sub(start));
+            if wait > Duration::ZERO {
+                std::thread::sleep(wait);
+            }
+        })
+    }
+}
+
+impl Drop for Lane {
+    fn drop(&mut self) {
+        self.limiter.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
 
-    #[test]
-    fn test_rate_limiter_burst() {
-        let mut limiter = RateLimiter::new(1024 * 1024); // 1 MB/s
+    const MB: u64 = 1024 * 1024;
 
-        // First consume should be instant (burst allowed)
-        let sleep = limiter.consume(1024 * 1024);
-        assert_eq!(sleep, Duration::ZERO);
+    #[test]
+    fn test_single_transfer_gets_full_rate() {
+        let limiter = BandwidthLimiter::new(Some(MB), None);
+        let delay = limiter.delay_after(2 * MB, Duration::ZERO, 1);
+        assert_eq!(delay, Duration::from_secs(2));
 
-        // Second immediate consume should require sleep
-        let sleep = limiter.consume(1024 * 1024);
-        assert!(sleep > Duration::ZERO);
-        assert!(sleep.as_secs_f64() > 0.9 && sleep.as_secs_f64() < 1.1);
+        // Time spent transferring counts towards the wait
+        let delay = limiter.delay_after(2 * MB, Duration::from_millis(500), 1);
+        assert_eq!(delay, Duration::from_millis(1500));
+        assert_eq!(
+            limiter.delay_after(MB, Duration::from_secs(3), 1),
+            Duration::ZERO
+        );
     }
 
     #[test]
-    fn test_rate_limiter_refill() {
-        let mut limiter = RateLimiter::new(1024); // 1 KB/s
+    fn test_transfers_share_global_rate() {
+        let limiter = BandwidthLimiter::new(Some(4 * MB), None);
+
+        // Four transfers get 1 MB/s each: a small file waits for its own
+        // bytes only, however large the file next to it
+        assert_eq!(
+            limiter.delay_after(MB / 4, Duration::ZERO, 4),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            limiter.delay_after(100 * MB, Duration::ZERO, 4),
+            Duration::from_secs(100)
+        );
+    }
 
-        // Consume all tokens
-        limiter.consume(1024);
+    #[test]
+    fn test_per_file_limit() {
+        let limiter = BandwidthLimiter::new(None, Some(MB));
+        assert_eq!(
+            limiter.delay_after(3 * MB, Duration::ZERO, 8),
+            Duration::from_secs(3)
+        );
 
-        // Wait for refill
-        thread::sleep(Duration::from_millis(500));
+        // The lower of the per-file limit and the global share applies
+        let limiter = BandwidthLimiter::new(Some(10 * MB), Some(4 * MB));
+        assert_eq!(
+            limiter.delay_after(4 * MB, Duration::ZERO, 1),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            limiter.delay_after(4 * MB, Duration::ZERO, 5),
+            Duration::from_secs(2)
+        );
+    }
 
-        // Should have ~512 bytes available
-        let sleep = limiter.consume(512);
-        assert_eq!(sleep, Duration::ZERO);
+    #[test]
+    fn test_lanes_count_active_transfers() {
+        let limiter = Arc::new(BandwidthLimiter::new(Some(2 * MB), None));
+        let first = limiter.lane();
+        {
+            let _second = limiter.lane();
+            assert!(first.delay(2 * MB) > Duration::from_millis(1900));
+        }
+        let delay = first.delay(2 * MB);
+        assert!(delay > Duration::from_millis(900) && delay <= Duration::from_secs(1));
     }
 
     #[test]
-    fn test_rate_limiter_small_transfers() {
-        let mut limiter = RateLimiter::new(1024 * 1024); // 1 MB/s
+    fn test_pace_waits_per_chunk() {
+        let limiter = Arc::new(BandwidthLimiter::new(Some(10 * MB), None));
+        let lane = Arc::new(limiter.lane());
+        let reports = Arc::new(AtomicU64::new(0));
+        let counted = Arc::clone(&reports);
+        let progress = lane.pace(Arc::new(move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        }));
 
-        // Small transfers should be instant
-        for _ in 0..10 {
-            let sleep = limiter.consume(1024);
-            assert_eq!(sleep, Duration::ZERO);
-        }
+        // A resumed transfer starting at 50 MB doesn't wait for those bytes
+        let started = Instant::now();
+        progress(50 * MB, 60 * MB);
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        // 1 MB more at 10 MB/s waits about 100ms
+        progress(51 * MB, 60 * MB);
+        assert!(started.elapsed() >= Duration::from_millis(90));
+        assert_eq!(reports.load(Ordering::SeqCst), 2);
     }
 }
@@ -1,13 +1,25 @@
 use crate::error::{Result, SyncError};
+use crate::sync::output::{self, WarningCategory};
+use crate::sync::scanner::FileEntry;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-const STATE_FILE_NAME: &str = ".sy-state.json";
+pub const STATE_FILE_NAME: &str = ".sy-state.json";
 const STATE_VERSION: u32 = 1;
 
+/// Files at least this large are checkpointed as they are copied, so an
+/// interrupted transfer continues from its last checkpoint
+pub const PARTIAL_MIN_SIZE: u64 = 64 * 1024 * 1024;
+/// Bytes copied between two checkpoints of a partial transfer
+pub const CHECKPOINT_INTERVAL: u64 = 16 * 1024 * 1024;
+/// Most bytes before a checkpoint compared with the temp file on resume
+const CHECKPOINT_TAIL: usize = 64 * 1024;
+/// Subdirectory of the state directory holding partial transfer checkpoints
+const PARTIAL_DIR: &str = "partial";
+
 /// Resume state for interrupted sync operations
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ResumeState {
@@ -41,6 +53,19 @@ pub struct CompletedFile {
     pub completed_at: String,
 }
 
+impl CompletedFile {
+    /// Record a file completed now (checksum is not computed for checkpoints)
+    pub fn new(relative_path: PathBuf, action: &str, size: u64) -> Self {
+        Self {
+            relative_path,
+            action: action.to_string(),
+            size,
+            checksum: String::new(),
+            completed_at: format_timestamp(SystemTime::now()),
+        }
+    }
+}
+
 impl ResumeState {
     /// Create a new resume state
     pub fn new(
@@ -63,9 +88,9 @@ impl ResumeState {
         }
     }
 
-    /// Load resume state from destination directory
-    pub fn load(destination: &Path) -> Result<Option<Self>> {
-        let state_path = destination.join(STATE_FILE_NAME);
+    /// Load resume state from the sync's state directory
+    pub fn load(state_dir: &Path) -> Result<Option<Self>> {
+        let state_path = state_dir.join(STATE_FILE_NAME);
 
         if !state_path.exists() {
             return Ok(None);
@@ -84,18 +109,26 @@ impl ResumeState {
         let state: Self = match serde_json::from_reader(reader) {
             Ok(state) => state,
             Err(e) => {
-                tracing::warn!("Failed to parse resume state (corrupted JSON): {}", e);
+                output::warn(
+                    WarningCategory::Resume,
+                    None,
+                    format!("Failed to parse resume state (corrupted JSON): {}", e),
+                );
                 tracing::info!("Deleting corrupted state file and starting fresh");
-                Self::delete(destination)?;
+                Self::delete(state_dir)?;
                 return Ok(None);
             }
         };
 
         // Verify state integrity
         if let Err(e) = state.verify_integrity() {
-            tracing::warn!("Resume state failed integrity check: {}", e);
+            output::warn(
+                WarningCategory::Resume,
+                None,
+                format!("Resume state failed integrity check: {}", e),
+            );
             tracing::info!("Deleting invalid state file and starting fresh");
-            Self::delete(destination)?;
+            Self::delete(state_dir)?;
             return Ok(None);
         }
 
@@ -210,14 +243,16 @@ impl ResumeState {
         Ok(())
     }
 
-    /// Save resume state to destination directory (atomic)
+    /// Save resume state to the sync's state directory (atomic)
     #[allow(dead_code)] // Public API for manual state saving
-    pub fn save(&self, destination: &Path) -> Result<()> {
-        let state_path = destination.join(STATE_FILE_NAME);
-        let temp_path = destination.join(format!("{}.tmp", STATE_FILE_NAME));
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        let state_path = state_dir.join(STATE_FILE_NAME);
+        let temp_path = state_dir.join(format!("{}.tmp", STATE_FILE_NAME));
 
         tracing::trace!("Saving resume state to {}", state_path.display());
 
+        std::fs::create_dir_all(state_dir)?;
+
         // Write to temporary file
         let file = File::create(&temp_path).map_err(|e| {
             SyncError::Io(std::io::Error::new(
@@ -246,8 +281,8 @@ impl ResumeState {
     }
 
     /// Delete resume state file
-    pub fn delete(destination: &Path) -> Result<()> {
-        let state_path = destination.join(STATE_FILE_NAME);
+    pub fn delete(state_dir: &Path) -> Result<()> {
+        let state_path = state_dir.join(STATE_FILE_NAME);
 
         if state_path.exists() {
             tracing::debug!("Deleting resume state file");
@@ -268,8 +303,56 @@ impl ResumeState {
         self.flags == *current_flags
     }
 
+    /// Adopt `flags` for a state saved under different ones (`--resume-force`)
+    ///
+    /// Instead of starting over, every completed entry is checked against
+    /// this run's source: a copied file stays completed only if the source
+    /// still has it at the recorded size (so one the new filters exclude no
+    /// longer counts), and a deletion only if the new flags still delete.
+    /// Returns how many entries were dropped; those files are planned again.
+    pub fn reconcile(&mut self, flags: SyncFlags, source_files: &[FileEntry]) -> usize {
+        let sizes: std::collections::HashMap<&Path, u64> = source_files
+            .iter()
+            .map(|f| (f.relative_path.as_path(), f.size))
+            .collect();
+        let deletes = flags.delete;
+        let dropped = self.retain_completed(|file| match file.action.as_str() {
+            "delete" => deletes && !sizes.contains_key(file.relative_path.as_path()),
+            _ => sizes.get(file.relative_path.as_path()) == Some(&file.size),
+        });
+
+        self.flags = flags;
+        self.total_files = source_files.len().max(self.completed_files.len());
+        self.checkpoint_at = format_timestamp(SystemTime::now());
+        dropped
+    }
+
+    /// Keep only the completed entries `keep` accepts, in order, returning
+    /// how many were dropped
+    pub fn retain_completed(&mut self, mut keep: impl FnMut(&CompletedFile) -> bool) -> usize {
+        let before = self.completed_files.len();
+        let mut dropped_bytes = 0;
+        self.completed_files.retain(|file| {
+            let kept = keep(file);
+            if !kept {
+                tracing::debug!(
+                    "No longer counted as completed: {}",
+                    file.relative_path.display()
+                );
+                dropped_bytes += file.size;
+            }
+            kept
+        });
+        self.total_bytes_transferred = self.total_bytes_transferred.saturating_sub(dropped_bytes);
+        before - self.completed_files.len()
+    }
+
+    /// Transfers recorded as completed, in the order they finished
+    pub fn completed_files(&self) -> &[CompletedFile] {
+        &self.completed_files
+    }
+
     /// Add a completed file to the state
-    #[allow(dead_code)] // Public API for state management
     pub fn add_completed_file(&mut self, file: CompletedFile, bytes_transferred: u64) {
         self.completed_files.push(file);
         self.total_bytes_transferred += bytes_transferred;
@@ -290,6 +373,193 @@ impl ResumeState {
     }
 }
 
+/// Where a sync with `--resume` keeps the checkpoints of its large transfers
+///
+/// The engine hands it to transports with each copy (see
+/// `Transport::copy_file_resumable`).
+pub fn partial_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join(PARTIAL_DIR)
+}
+
+/// Remove every partial transfer checkpoint in `state_dir`
+///
+/// Called once a sync completed, so no transfer is left to continue.
+pub fn clear_partials(state_dir: &Path) -> Result<()> {
+    let dir = state_dir.join(PARTIAL_DIR);
+    match std::fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SyncError::Io(e)),
+        _ => Ok(()),
+    }
+}
+
+/// Checkpoint of a large file transfer, taken as it is written
+///
+/// The bytes copied so far stay in the destination's `.sy-tmp` file; the
+/// checkpoint says how many of them are good and which version of the source
+/// they came from, so the next run can continue the transfer instead of
+/// starting it over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartialTransfer {
+    pub source_size: u64,
+    pub source_mtime: u64, // Seconds since UNIX epoch
+    /// Bytes of the temp file written and flushed
+    pub offset: u64,
+    /// Length and xxHash3 of the bytes just before `offset`
+    pub tail_len: usize,
+    pub tail_checksum: u64,
+}
+
+impl PartialTransfer {
+    /// Checkpoint file for a transfer to `dest`, named after its path
+    fn path(dir: &Path, dest: &Path) -> PathBuf {
+        let hash = xxhash_rust::xxh3::xxh3_64(dest.to_string_lossy().as_bytes());
+        dir.join(format!("{:016x}.json", hash))
+    }
+
+    fn load(dir: &Path, dest: &Path) -> Option<Self> {
+        let data = std::fs::read(Self::path(dir, dest)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Save atomically, so a crash mid-write never leaves a torn checkpoint
+    fn save(&self, dir: &Path, dest: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let path = Self::path(dir, dest);
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(
+            &temp,
+            serde_json::to_vec(self).map_err(std::io::Error::from)?,
+        )?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+}
+
+/// Takes the checkpoints of one large transfer as it is written
+///
+/// Transports write the temp file themselves (locally or over SFTP): they
+/// ask [`resume_offset`](Self::resume_offset) where to start, call
+/// [`checkpoint`](Self::checkpoint) whenever [`due`](Self::due) after
+/// flushing, and [`finish`](Self::finish) once the file is in place. A
+/// transfer that fails after a checkpoint keeps its temp file.
+pub struct Checkpointer {
+    dir: PathBuf,
+    dest: PathBuf,
+    source_size: u64,
+    source_mtime: u64,
+    /// Offset of the last checkpoint (0 before the first)
+    last: u64,
+}
+
+impl Checkpointer {
+    /// Checkpointer for copying a `source_size` byte source to `dest`, with
+    /// its checkpoints in `dir` (see [`partial_dir`]), or None for a file
+    /// below `PARTIAL_MIN_SIZE`
+    pub fn new(dir: &Path, dest: &Path, source_size: u64, source_mtime: u64) -> Option<Self> {
+        if source_size < PARTIAL_MIN_SIZE {
+            return None;
+        }
+        Some(Self::in_dir(
+            dir.to_path_buf(),
+            dest,
+            source_size,
+            source_mtime,
+        ))
+    }
+
+    fn in_dir(dir: PathBuf, dest: &Path, source_size: u64, source_mtime: u64) -> Self {
+        Self {
+            dir,
+            dest: dest.to_path_buf(),
+            source_size,
+            source_mtime,
+            last: 0,
+        }
+    }
+
+    /// Offset to continue the transfer from, 0 to start over
+    ///
+    /// Only a checkpoint taken while copying the same source size and mtime
+    /// counts, and only if `read_tail(start, len)` (the temp file's bytes at
+    /// `start`, None if it's gone or shorter) still holds what was written.
+    pub fn resume_offset(&mut self, read_tail: impl FnOnce(u64, usize) -> Option<Vec<u8>>) -> u64 {
+        let Some(partial) = PartialTransfer::load(&self.dir, &self.dest) else {
+            return 0;
+        };
+        if partial.source_size != self.source_size
+            || partial.source_mtime != self.source_mtime
+            || partial.offset > self.source_size
+            || partial.tail_len as u64 > partial.offset
+        {
+            tracing::debug!(
+                "Source of {} changed since its checkpoint, starting over",
+                self.dest.display()
+            );
+            return 0;
+        }
+
+        let start = partial.offset - partial.tail_len as u64;
+        let intact = read_tail(start, partial.tail_len)
+            .is_some_and(|tail| xxhash_rust::xxh3::xxh3_64(&tail) == partial.tail_checksum);
+        if !intact {
+            tracing::debug!(
+                "Temp file of {} doesn't match its checkpoint, starting over",
+                self.dest.display()
+            );
+            return 0;
+        }
+
+        tracing::info!(
+            "Resuming transfer of {} at byte {} of {}",
+            self.dest.display(),
+            partial.offset,
+            self.source_size
+        );
+        self.last = partial.offset;
+        partial.offset
+    }
+
+    /// Whether `offset` bytes written call for a checkpoint
+    pub fn due(&self, offset: u64) -> bool {
+        offset - self.last >= CHECKPOINT_INTERVAL && offset < self.source_size
+    }
+
+    /// Record that the first `offset` bytes are written and flushed, `last`
+    /// being the most recently written of them
+    ///
+    /// A checkpoint that can't be saved is only logged: the transfer goes
+    /// on, it just can't resume from here.
+    pub fn checkpoint(&mut self, offset: u64, last: &[u8]) {
+        let tail = &last[last.len().saturating_sub(CHECKPOINT_TAIL)..];
+        let partial = PartialTransfer {
+            source_size: self.source_size,
+            source_mtime: self.source_mtime,
+            offset,
+            tail_len: tail.len(),
+            tail_checksum: xxhash_rust::xxh3::xxh3_64(tail),
+        };
+        match partial.save(&self.dir, &self.dest) {
+            Ok(()) => self.last = offset,
+            Err(e) => tracing::debug!(
+                "Failed to checkpoint transfer of {}: {}",
+                self.dest.display(),
+                e
+            ),
+        }
+    }
+
+    /// Whether a checkpoint exists to resume from, so the temp file is
+    /// worth keeping when the transfer fails
+    pub fn has_checkpoint(&self) -> bool {
+        self.last > 0
+    }
+
+    /// The transfer completed: drop its checkpoint
+    pub fn finish(self) {
+        let _ = std::fs::remove_file(PartialTransfer::path(&self.dir, &self.dest));
+    }
+}
+
 /// Format a timestamp for serialization (ISO 8601)
 fn format_timestamp(time: SystemTime) -> String {
     let datetime: chrono::DateTime<chrono::Utc> = time.into();
@@ -377,6 +647,65 @@ mod tests {
         assert!(!state.is_compatible_with(&flags2));
     }
 
+    #[test]
+    fn test_reconcile_with_changed_flags() {
+        let entry = |path: &str, size: u64| FileEntry {
+            path: PathBuf::from("/src").join(path),
+            relative_path: PathBuf::from(path),
+            size,
+            modified: SystemTime::now(),
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: size,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        let flags = |delete: bool, min_size: Option<u64>| SyncFlags {
+            delete,
+            exclude: vec![],
+            min_size,
+            max_size: None,
+        };
+
+        let mut state = ResumeState::new(
+            PathBuf::from("/src"),
+            PathBuf::from("/dst"),
+            flags(true, None),
+            5,
+        );
+        for (path, action, size) in [
+            ("kept.txt", "create", 2048),
+            ("grown.txt", "update", 2048),
+            ("small.txt", "create", 10),
+            ("old.txt", "delete", 0),
+        ] {
+            state.add_completed_file(CompletedFile::new(PathBuf::from(path), action, size), size);
+        }
+
+        // The new run skips small files and no longer deletes; grown.txt changed
+        let source_files = vec![entry("kept.txt", 2048), entry("grown.txt", 4096)];
+        let new_flags = flags(false, Some(1024));
+        assert!(!state.is_compatible_with(&new_flags));
+
+        assert_eq!(state.reconcile(new_flags.clone(), &source_files), 3);
+        assert!(state.is_compatible_with(&new_flags));
+        assert_eq!(
+            state.completed_paths(),
+            [PathBuf::from("kept.txt")].into_iter().collect()
+        );
+        assert_eq!(state.progress(), (1, 2));
+        assert_eq!(state.total_bytes_transferred, 2048);
+        assert!(state.verify_integrity().is_ok());
+    }
+
     #[test]
     fn test_resume_state_delete() {
         let temp_dir = tempdir().unwrap();
@@ -1006,4 +1335,57 @@ mod tests {
         // Should succeed (idempotent)
         assert!(result.is_ok(), "Deleting nonexistent state should succeed");
     }
+
+    #[test]
+    fn test_checkpointer_resume_offset() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path().join(PARTIAL_DIR);
+        let dest = Path::new("/backup/disk.img");
+        let written: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let read_tail = |start: u64, len: usize| {
+            let start = start as usize;
+            written.get(start..start + len).map(<[u8]>::to_vec)
+        };
+
+        // No checkpoint yet: start over
+        let mut checkpointer = Checkpointer::in_dir(dir.clone(), dest, 1_000_000, 1_700_000_000);
+        assert_eq!(checkpointer.resume_offset(read_tail), 0);
+        assert!(!checkpointer.has_checkpoint());
+
+        checkpointer.checkpoint(200_000, &written);
+        assert!(checkpointer.has_checkpoint());
+
+        // Same source and intact temp file: continue from the checkpoint
+        let mut resumed = Checkpointer::in_dir(dir.clone(), dest, 1_000_000, 1_700_000_000);
+        assert_eq!(resumed.resume_offset(read_tail), 200_000);
+
+        // Changed source, or a temp file that no longer matches: start over
+        let mut changed = Checkpointer::in_dir(dir.clone(), dest, 1_000_000, 1_700_000_001);
+        assert_eq!(changed.resume_offset(read_tail), 0);
+        let mut corrupted = Checkpointer::in_dir(dir.clone(), dest, 1_000_000, 1_700_000_000);
+        assert_eq!(corrupted.resume_offset(|_, len| Some(vec![0; len])), 0);
+        let mut truncated = Checkpointer::in_dir(dir.clone(), dest, 1_000_000, 1_700_000_000);
+        assert_eq!(truncated.resume_offset(|_, _| None), 0);
+
+        // A finished transfer leaves nothing to resume
+        resumed.finish();
+        let mut after = Checkpointer::in_dir(dir.clone(), dest, 1_000_000, 1_700_000_000);
+        assert_eq!(after.resume_offset(read_tail), 0);
+
+        clear_partials(temp_dir.path()).unwrap();
+        assert!(!dir.exists());
+        clear_partials(temp_dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_checkpointer_due() {
+        let mut checkpointer =
+            Checkpointer::in_dir(PathBuf::from("/nonexistent"), Path::new("/d"), 100 << 20, 0);
+        assert!(!checkpointer.due(CHECKPOINT_INTERVAL - 1));
+        assert!(checkpointer.due(CHECKPOINT_INTERVAL));
+        checkpointer.last = CHECKPOINT_INTERVAL;
+        assert!(!checkpointer.due(CHECKPOINT_INTERVAL + 1));
+        // Never at the very end, where the file is about to be renamed into place
+        assert!(!checkpointer.due(100 << 20));
+    }
 }
@@ -0,0 +1,221 @@
+use crate::error::{Result, SyncError};
+use crate::fs_util::resolve_path;
+use std::path::{Component, Path, PathBuf};
+
+/// How far writes are kept inside the destination root
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DestConfinement {
+    /// No checks (remote destinations, `--keep-dirlinks`)
+    #[default]
+    Off,
+    /// Refuse writing through destination symlinks that lead out of the tree
+    Links,
+    /// Also refuse any `..` or path outside the root (`--sandbox-dest`)
+    Strict,
+}
+
+/// Destination root that writes must stay inside
+///
+/// Guards against writing "through" a symlink in the destination that leads
+/// somewhere else, clobbering files outside the tree. Strict sandboxes
+/// (`--sandbox-dest`) also guard against source trees, malicious or
+/// corrupted, whose paths climb out with `..`.
+#[derive(Debug, Clone)]
+pub struct DestSandbox {
+    root: PathBuf,
+    /// `root` with symlinks resolved, to compare resolved paths against
+    resolved_root: PathBuf,
+    strict: bool,
+}
+
+impl DestSandbox {
+    /// Sandbox for `root`, which doesn't need to exist yet
+    pub fn new(root: &Path, strict: bool) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            resolved_root: resolve_path(root, true),
+            strict,
+        }
+    }
+
+    /// Refuse writing to `path`, following a symlink at the end like writes do
+    pub fn check(&self, path: &Path) -> Result<()> {
+        self.check_resolved(path, true)
+    }
+
+    /// Refuse removing or replacing the entry at `path` itself
+    pub fn check_entry(&self, path: &Path) -> Result<()> {
+        self.check_resolved(path, false)
+    }
+
+    fn check_resolved(&self, path: &Path, follow_last: bool) -> Result<()> {
+        let escapes = match path.strip_prefix(&self.root) {
+            // Any `..` is refused, even one that stays inside: after a symlink
+            // the kernel resolves it from the link's target, not lexically
+            Ok(relative) if self.strict => {
+                relative
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+                    || !resolve_path(path, follow_last).starts_with(&self.resolved_root)
+            }
+            Err(_) if self.strict => true,
+            _ => !resolve_path(path, follow_last).starts_with(&self.resolved_root),
+        };
+
+        if escapes {
+            return Err(SyncError::SandboxEscape {
+                path: path.to_path_buf(),
+                root: self.root.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Whether a symlink at `relative_path` in a tree points outside of it
+///
+/// Judged lexically, like rsync's `--safe-links`: absolute and empty targets
+/// are unsafe, as are targets whose `..` climb above the tree root.
+pub fn is_unsafe_symlink(relative_path: &Path, target: &Path) -> bool {
+    if target.as_os_str().is_empty() {
+        return true;
+    }
+    // Directories between the root and the link
+    let mut depth = relative_path.components().count().saturating_sub(1);
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(parent) => depth = parent,
+                None => return true,
+            },
+            Component::RootDir | Component::Prefix(_) => return true,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_paths_inside_root() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("dest");
+        let sandbox = DestSandbox::new(&root, true);
+
+        // The root doesn't exist before the first sync
+        assert!(sandbox.check(&root).is_ok());
+        assert!(sandbox.check(&root.join("a/b/file.txt")).is_ok());
+        assert!(sandbox.check_entry(&root.join("a")).is_ok());
+    }
+
+    #[test]
+    fn test_traversal_refused() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("dest");
+        fs::create_dir_all(root.join("a")).unwrap();
+        let sandbox = DestSandbox::new(&root, true);
+
+        for path in [
+            root.join("../outside.txt"),
+            root.join("a/../../outside.txt"),
+            root.join("a/../b.txt"),
+            root.join("/etc/passwd"),
+            temp.path().join("outside.txt"),
+        ] {
+            assert!(
+                matches!(sandbox.check(&path), Err(SyncError::SandboxEscape { .. })),
+                "{}",
+                path.display()
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_escape_refused() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("dest");
+        let outside = temp.path().join("outside");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("file.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+        std::os::unix::fs::symlink(outside.join("file.txt"), root.join("file.txt")).unwrap();
+        let sandbox = DestSandbox::new(&root, true);
+
+        assert!(sandbox.check(&root.join("escape/new.txt")).is_err());
+        assert!(sandbox.check(&root.join("file.txt")).is_err());
+        // The links themselves can still be removed or replaced
+        assert!(sandbox.check_entry(&root.join("escape")).is_ok());
+        assert!(sandbox.check_entry(&root.join("file.txt")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_links_confinement() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("dest");
+        let outside = temp.path().join("outside");
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("inside")).unwrap();
+        let sandbox = DestSandbox::new(&root, false);
+
+        assert!(sandbox.check(&root.join("escape/new.txt")).is_err());
+        assert!(sandbox.check_entry(&root.join("escape")).is_ok());
+        // Symlinks within the tree and `..` that stay inside are fine
+        assert!(sandbox.check(&root.join("inside/new.txt")).is_ok());
+        assert!(sandbox.check(&root.join("a/../b.txt")).is_ok());
+    }
+
+    #[test]
+    fn test_unsafe_symlinks() {
+        for (link, target) in [
+            ("link", "file.txt"),
+            ("link", "./a/b"),
+            ("a/link", "../file.txt"),
+            ("a/b/link", "../../c/../file.txt"),
+        ] {
+            assert!(
+                !is_unsafe_symlink(Path::new(link), Path::new(target)),
+                "{} -> {}",
+                link,
+                target
+            );
+        }
+        for (link, target) in [
+            ("link", "/etc/passwd"),
+            ("link", ""),
+            ("link", "../file.txt"),
+            ("a/link", "../../file.txt"),
+            ("a/link", "b/../../../file.txt"),
+        ] {
+            assert!(
+                is_unsafe_symlink(Path::new(link), Path::new(target)),
+                "{} -> {}",
+                link,
+                target
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_root() {
+        let temp = TempDir::new().unwrap();
+        let real = temp.path().join("real");
+        fs::create_dir(&real).unwrap();
+        let root = temp.path().join("dest");
+        std::os::unix::fs::symlink(&real, &root).unwrap();
+        let sandbox = DestSandbox::new(&root, true);
+
+        assert!(sandbox.check(&root.join("file.txt")).is_ok());
+    }
+}
@@ -1,7 +1,11 @@
+use crate::cli::DanglingMode;
 use crate::error::{Result, SyncError};
+use crate::owner::FilePerms;
+use crate::sync::output::{self, WarningCategory};
 use ignore::WalkBuilder;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 #[cfg(unix)]
@@ -28,6 +32,9 @@ pub struct FileEntry {
     pub nlink: u64,                               // Number of hard links to this file
     pub acls: Option<Vec<u8>>,                    // Serialized ACLs (if enabled)
     pub bsd_flags: Option<u32>, // BSD file flags (hidden, immutable, etc.) - macOS only, None on other platforms
+    pub atime: Option<SystemTime>, // Last access time (restored with --atimes)
+    pub crtime: Option<SystemTime>, // Creation time, where the filesystem records it (--crtimes)
+    pub perms: Option<FilePerms>, // Mode, owner and group (None on Windows)
 }
 
 /// Detect if a file is sparse and get its allocated size
@@ -154,10 +161,124 @@ fn read_bsd_flags(_metadata: &std::fs::Metadata) -> Option<u32> {
     None
 }
 
+/// Directory identity used for loop detection: (device, inode)
+type DirId = (u64, u64);
+
+#[cfg(unix)]
+fn dir_id(metadata: &std::fs::Metadata) -> Option<DirId> {
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Non-Unix platforms rely on the walker's own loop detection
+#[cfg(not(unix))]
+fn dir_id(_metadata: &std::fs::Metadata) -> Option<DirId> {
+    None
+}
+
+/// Extract the looping path from a walker error, if it is a filesystem loop
+fn loop_error_path(err: &ignore::Error) -> Option<PathBuf> {
+    match err {
+        ignore::Error::Loop { child, .. } => Some(child.clone()),
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => loop_error_path(err),
+        _ => None,
+    }
+}
+
+/// Target of the symlink at `path` if it doesn't exist
+fn dangling_target(path: &Path) -> Option<PathBuf> {
+    let target = std::fs::read_link(path).ok()?;
+    path.metadata().is_err().then_some(target)
+}
+
+/// Split a walker error into the path it refers to and the underlying cause
+fn error_path(err: &ignore::Error) -> Option<(PathBuf, String)> {
+    match err {
+        ignore::Error::WithPath { path, err } => Some((path.clone(), err.to_string())),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        _ => None,
+    }
+}
+
+/// Kind of a socket, FIFO or device node, which can't be copied like a file
+#[cfg(unix)]
+fn special_kind(metadata: &std::fs::Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    if file_type.is_fifo() {
+        Some("FIFO")
+    } else if file_type.is_socket() {
+        Some("socket")
+    } else if file_type.is_block_device() {
+        Some("block device")
+    } else if file_type.is_char_device() {
+        Some("character device")
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_metadata: &std::fs::Metadata) -> Option<&'static str> {
+    None
+}
+
+/// A per-entry error encountered while scanning (e.g. an unreadable directory)
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub error: String,
+    /// A socket, FIFO or device node, which is never copied (`error` is its kind)
+    pub special: bool,
+}
+
+impl From<SyncError> for ScanError {
+    fn from(err: SyncError) -> Self {
+        match err {
+            SyncError::ReadDirError { path, source } => ScanError {
+                path,
+                error: source.to_string(),
+                special: false,
+            },
+            SyncError::SymlinkLoop { path } => ScanError {
+                path,
+                error: "Symlink loop detected, directory skipped".to_string(),
+                special: false,
+            },
+            SyncError::DanglingSymlink { path, target } => ScanError {
+                path,
+                error: format!("Dangling symlink to {}", target.display()),
+                special: false,
+            },
+            SyncError::InvalidPath { path } => ScanError {
+                path,
+                error: "Invalid path".to_string(),
+                special: false,
+            },
+            SyncError::SpecialFile { path, kind } => ScanError {
+                path,
+                error: kind,
+                special: true,
+            },
+            other => ScanError {
+                path: PathBuf::new(),
+                error: other.to_string(),
+                special: false,
+            },
+        }
+    }
+}
+
 pub struct Scanner {
     root: PathBuf,
     threads: usize,
     follow_links: bool,
+    dangling_links: DanglingMode,
+    max_depth: Option<usize>,
 }
 
 impl Scanner {
@@ -167,6 +288,8 @@ impl Scanner {
             root: root.into(),
             threads: num_cpus::get(),
             follow_links: false,
+            dangling_links: DanglingMode::default(),
+            max_depth: None,
         }
     }
 
@@ -180,14 +303,17 @@ impl Scanner {
             root: root.into(),
             threads,
             follow_links: false,
+            dangling_links: DanglingMode::default(),
+            max_depth: None,
         }
     }
 
     /// Enable following symbolic links during directory traversal
     ///
     /// When enabled, symbolic links to directories will be followed and their
-    /// contents will be scanned. Loop detection is automatic (via walkdir crate)
-    /// and will report an error if a symlink loop is detected.
+    /// contents will be scanned. Directories that link back to one of their
+    /// ancestors (same device and inode) are not descended into; they are
+    /// reported as `SyncError::SymlinkLoop` per-entry errors instead.
     ///
     /// Default: false (symlinks are recorded but not followed)
     #[allow(dead_code)] // Public API for symlink following control
@@ -196,12 +322,92 @@ impl Scanner {
         self
     }
 
+    /// Set how symlinks whose target doesn't exist are handled
+    ///
+    /// Preserved links are returned like any other symlink, skipped ones are
+    /// left out with a warning, and with `Fail` they are errors that abort
+    /// `scan` and `scan_with_errors`. Followed links have nothing to return,
+    /// so unless skipped they are `SyncError::DanglingSymlink` per-entry
+    /// errors.
+    ///
+    /// Default: Preserve
+    pub fn dangling_links(mut self, mode: DanglingMode) -> Self {
+        self.dangling_links = mode;
+        self
+    }
+
+    /// Limit how deep the scan descends below the root
+    ///
+    /// Depth 1 yields only the root's direct children, depth 2 also their
+    /// children, and so on. Directories at the limit are still returned,
+    /// just not descended into.
+    ///
+    /// Default: None (unlimited)
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
     /// Scan and return all entries at once (legacy API, kept for compatibility)
     ///
+    /// Symlink loops are logged and special files (sockets, FIFOs, devices)
+    /// skipped; other errors abort the scan.
     /// For large directories (>100k files), consider using `scan_streaming()` instead
     pub fn scan(&self) -> Result<Vec<FileEntry>> {
-        // For backward compatibility, collect streaming results into Vec
-        self.scan_streaming()?.collect()
+        let mut entries = Vec::new();
+        for result in self.scan_streaming()? {
+            match result {
+                Ok(entry) => entries.push(entry),
+                Err(SyncError::SymlinkLoop { path }) => {
+                    output::warn(
+                        WarningCategory::Symlink,
+                        Some(path.as_path()),
+                        format!("Skipping symlink loop: {}", path.display()),
+                    );
+                }
+                Err(SyncError::SpecialFile { path, kind }) => {
+                    tracing::debug!("Skipping special file ({}): {}", kind, path.display());
+                }
+                Err(SyncError::DanglingSymlink { path, target })
+                    if self.dangling_links != DanglingMode::Fail =>
+                {
+                    output::warn(
+                        WarningCategory::Symlink,
+                        Some(path.as_path()),
+                        format!(
+                            "Skipping dangling symlink: {} -> {}",
+                            path.display(),
+                            target.display()
+                        ),
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Scan the whole tree, collecting per-entry errors instead of aborting
+    ///
+    /// Unreadable directories, entries whose metadata can't be read, symlink
+    /// loops and special files are returned alongside the entries that were
+    /// scanned successfully, so one bad path doesn't prevent syncing the rest.
+    /// Dangling symlinks abort the scan when set to fail.
+    pub fn scan_with_errors(&self) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        for result in self.scan_streaming()? {
+            match result {
+                Ok(entry) => entries.push(entry),
+                Err(e @ SyncError::DanglingSymlink { .. })
+                    if self.dangling_links == DanglingMode::Fail =>
+                {
+                    return Err(e)
+                }
+                Err(e) => errors.push(ScanError::from(e)),
+            }
+        }
+        Ok((entries, errors))
     }
 
     /// Streaming scan that yields FileEntry one at a time
@@ -229,15 +435,54 @@ impl Scanner {
             .git_global(true) // Respect global gitignore
             .git_exclude(true) // Respect .git/info/exclude
             .threads(self.threads) // Parallel walking if threads > 1
-            .follow_links(self.follow_links) // Follow symlinks with automatic loop detection
-            .filter_entry(|entry| {
+            .follow_links(self.follow_links)
+            .max_depth(self.max_depth);
+
+        // Directory identities of the current entry's ancestors, indexed by depth
+        // (root at depth 0). Entries arrive in depth-first pre-order, so truncating
+        // to an entry's depth leaves exactly its ancestors on the stack.
+        let root_id = std::fs::metadata(&self.root).ok().and_then(|m| dir_id(&m));
+        let ancestors: Arc<Mutex<Vec<Option<DirId>>>> = Arc::new(Mutex::new(vec![root_id]));
+        let loops: Arc<Mutex<Vec<PathBuf>>> = Arc::new(Mutex::new(Vec::new()));
+
+        walker.filter_entry({
+            let loops = Arc::clone(&loops);
+            move |entry| {
                 // Skip .git directories
-                entry.file_name() != ".git"
-            });
+                if entry.file_name() == ".git" {
+                    return false;
+                }
+
+                // Skip the destination lock of a running (or crashed) sync
+                if entry.depth() == 1 && entry.file_name() == crate::sync::lock::LOCK_FILE_NAME {
+                    return false;
+                }
+
+                if !entry.file_type().is_some_and(|t| t.is_dir()) {
+                    return true;
+                }
+
+                // Metadata follows symlinks when follow_links is enabled
+                let id = entry.metadata().ok().and_then(|m| dir_id(&m));
+                let mut stack = ancestors.lock().unwrap();
+                stack.truncate(entry.depth());
+
+                if id.is_some() && stack.contains(&id) {
+                    // Don't descend: record the loop and report it per-entry
+                    loops.lock().unwrap().push(entry.path().to_path_buf());
+                    return false;
+                }
+
+                stack.push(id);
+                true
+            }
+        });
 
         Ok(StreamingScanner {
             root: self.root.clone(),
             walker: walker.build(),
+            loops,
+            dangling_links: self.dangling_links,
         })
     }
 }
@@ -249,6 +494,35 @@ impl Scanner {
 pub struct StreamingScanner {
     root: PathBuf,
     walker: ignore::Walk,
+    loops: Arc<Mutex<Vec<PathBuf>>>, // Loops found by the entry filter, not yet reported
+    dangling_links: DanglingMode,
+}
+
+impl StreamingScanner {
+    fn take_loop_error(&self) -> Option<SyncError> {
+        self.loops
+            .lock()
+            .unwrap()
+            .pop()
+            .map(|path| SyncError::SymlinkLoop { path })
+    }
+
+    /// Error for the dangling symlink at `path`, None if it's skipped
+    fn dangling_error(&self, path: PathBuf, target: PathBuf) -> Option<SyncError> {
+        if self.dangling_links == DanglingMode::Skip {
+            output::warn(
+                WarningCategory::Symlink,
+                Some(path.as_path()),
+                format!(
+                    "Skipping dangling symlink: {} -> {}",
+                    path.display(),
+                    target.display()
+                ),
+            );
+            return None;
+        }
+        Some(SyncError::DanglingSymlink { path, target })
+    }
 }
 
 impl Iterator for StreamingScanner {
@@ -256,11 +530,38 @@ impl Iterator for StreamingScanner {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let result = self.walker.next()?;
+            if let Some(err) = self.take_loop_error() {
+                return Some(Err(err));
+            }
+
+            let result = match self.walker.next() {
+                Some(result) => result,
+                None => return self.take_loop_error().map(Err),
+            };
 
             let entry = match result {
                 Ok(entry) => entry,
-                Err(e) => return Some(Err(SyncError::Io(std::io::Error::other(e.to_string())))),
+                Err(e) => {
+                    // Loops detected by the walker itself are per-entry errors too
+                    if let Some(path) = loop_error_path(&e) {
+                        return Some(Err(SyncError::SymlinkLoop { path }));
+                    }
+                    // Attach the offending path (e.g. permission denied on a directory)
+                    if let Some((path, cause)) = error_path(&e) {
+                        // Following a link whose target is missing
+                        if let Some(target) = dangling_target(&path) {
+                            match self.dangling_error(path, target) {
+                                Some(err) => return Some(Err(err)),
+                                None => continue,
+                            }
+                        }
+                        return Some(Err(SyncError::ReadDirError {
+                            path,
+                            source: std::io::Error::other(cause),
+                        }));
+                    }
+                    return Some(Err(SyncError::Io(std::io::Error::other(e.to_string()))));
+                }
             };
 
             let path = entry.path().to_path_buf();
@@ -273,13 +574,26 @@ impl Iterator for StreamingScanner {
             let metadata = match entry.metadata() {
                 Ok(m) => m,
                 Err(e) => {
+                    if let Some(target) = dangling_target(&path) {
+                        match self.dangling_error(path, target) {
+                            Some(err) => return Some(Err(err)),
+                            None => continue,
+                        }
+                    }
                     return Some(Err(SyncError::ReadDirError {
                         path: path.clone(),
                         source: std::io::Error::other(e.to_string()),
-                    }))
+                    }));
                 }
             };
 
+            if let Some(kind) = special_kind(&metadata) {
+                return Some(Err(SyncError::SpecialFile {
+                    path,
+                    kind: kind.to_string(),
+                }));
+            }
+
             let relative_path = match path.strip_prefix(&self.root) {
                 Ok(p) => p.to_path_buf(),
                 Err(_) => return Some(Err(SyncError::InvalidPath { path: path.clone() })),
@@ -292,6 +606,14 @@ impl Iterator for StreamingScanner {
             } else {
                 None
             };
+            if is_symlink && self.dangling_links != DanglingMode::Preserve {
+                if let Some(target) = dangling_target(&path) {
+                    match self.dangling_error(path.clone(), target) {
+                        Some(err) => return Some(Err(err)),
+                        None => continue,
+                    }
+                }
+            }
 
             // Detect sparse files (only for regular files, not directories or symlinks)
             let (is_sparse, allocated_size) = if !metadata.is_dir() && !is_symlink {
@@ -337,6 +659,9 @@ impl Iterator for StreamingScanner {
                 nlink,
                 acls,
                 bsd_flags,
+                atime: metadata.accessed().ok(),
+                crtime: metadata.created().ok(),
+                perms: FilePerms::from_metadata(&metadata),
             }));
         }
     }
@@ -471,23 +796,23 @@ mod tests {
         let symlink_entry = entries.iter().find(|e| e.is_symlink).unwrap();
         assert_eq!(symlink_entry.relative_path, PathBuf::from("link"));
 
-        // With follow_links enabled, walkdir detects the loop and returns an error
+        // With follow_links enabled, the loop is skipped and the rest is still scanned
         let scanner = Scanner::new(&dir_a).follow_links(true);
-        let result = scanner.scan();
-
-        // The scan should either:
-        // 1. Return Ok but skip the looping directory
-        // 2. Return an error about the loop
-        // walkdir's behavior is to skip the loop with a warning in the iterator
-        match result {
-            Ok(entries) => {
-                // Loop was skipped, we should still have file.txt
-                assert!(entries.iter().any(|e| e.path.ends_with("file.txt")));
-            }
-            Err(_) => {
-                // Loop caused an error - also acceptable
-            }
-        }
+        let entries = scanner.scan().unwrap();
+        assert!(entries.iter().any(|e| e.path.ends_with("file.txt")));
+
+        // The streaming scanner reports the loop as a per-entry error
+        let results: Vec<_> = Scanner::new(&dir_a)
+            .follow_links(true)
+            .scan_streaming()
+            .unwrap()
+            .collect();
+        assert!(results
+            .iter()
+            .any(|r| matches!(r, Err(SyncError::SymlinkLoop { path }) if path.ends_with("link"))));
+        assert!(results
+            .iter()
+            .any(|r| matches!(r, Ok(e) if e.path.ends_with("file.txt"))));
     }
 
     #[test]
@@ -522,21 +847,111 @@ mod tests {
         assert!(entries.len() >= 4);
         assert_eq!(entries.iter().filter(|e| e.is_symlink).count(), 2);
 
-        // With follow_links, walkdir should detect the cycle
+        // With follow_links, the cycle is detected and skipped without aborting
         let scanner = Scanner::new(root).follow_links(true);
-        let result = scanner.scan();
-
-        // Should handle gracefully (either skip loop or return error)
-        match result {
-            Ok(entries) => {
-                // Should still have both regular files
-                assert!(entries.iter().any(|e| e.path.ends_with("file_a.txt")));
-                assert!(entries.iter().any(|e| e.path.ends_with("file_b.txt")));
-            }
-            Err(_) => {
-                // Loop detection error is acceptable
-            }
-        }
+        let entries = scanner.scan().unwrap();
+
+        // Should still have both regular files
+        assert!(entries.iter().any(|e| e.path.ends_with("file_a.txt")));
+        assert!(entries.iter().any(|e| e.path.ends_with("file_b.txt")));
+
+        // Each cycle entry point is reported as a loop
+        let loops = Scanner::new(root)
+            .follow_links(true)
+            .scan_streaming()
+            .unwrap()
+            .filter(|r| matches!(r, Err(SyncError::SymlinkLoop { .. })))
+            .count();
+        assert!(loops >= 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_scanner_special_files() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::write(root.join("file.txt"), "data").unwrap();
+        let _listener = std::os::unix::net::UnixListener::bind(root.join("app.sock")).unwrap();
+
+        let (entries, errors) = Scanner::new(root).scan_with_errors().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].special);
+        assert_eq!(errors[0].error, "socket");
+        assert!(errors[0].path.ends_with("app.sock"));
+
+        // The legacy API skips them
+        assert_eq!(Scanner::new(root).scan().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_scanner_dangling_symlinks() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::write(root.join("file.txt"), "data").unwrap();
+        std::os::unix::fs::symlink("missing.txt", root.join("broken")).unwrap();
+
+        // Preserved like any other symlink
+        let (entries, errors) = Scanner::new(root).scan_with_errors().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(errors.is_empty());
+        let link = entries.iter().find(|e| e.is_symlink).unwrap();
+        assert_eq!(link.symlink_target, Some(PathBuf::from("missing.txt")));
+
+        let (entries, errors) = Scanner::new(root)
+            .dangling_links(DanglingMode::Skip)
+            .scan_with_errors()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(errors.is_empty());
+
+        let result = Scanner::new(root)
+            .dangling_links(DanglingMode::Fail)
+            .scan_with_errors();
+        assert!(matches!(
+            result,
+            Err(SyncError::DanglingSymlink { path, target })
+                if path.ends_with("broken") && target == Path::new("missing.txt")
+        ));
+
+        // Followed, they are per-entry errors and the rest is still scanned
+        let (entries, errors) = Scanner::new(root)
+            .follow_links(true)
+            .scan_with_errors()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].path.ends_with("broken"));
+        assert_eq!(errors[0].error, "Dangling symlink to missing.txt");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_scanner_symlink_to_sibling_not_loop() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+
+        // shared/ is reachable both directly and via a/shared_link - not a cycle
+        fs::create_dir_all(root.join("shared")).unwrap();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("shared/data.txt"), "data").unwrap();
+        std::os::unix::fs::symlink(root.join("shared"), root.join("a/shared_link")).unwrap();
+
+        let results: Vec<_> = Scanner::new(root)
+            .follow_links(true)
+            .scan_streaming()
+            .unwrap()
+            .collect();
+
+        assert!(results.iter().all(|r| r.is_ok()));
+        let entries: Vec<_> = results.into_iter().map(|r| r.unwrap()).collect();
+        assert!(entries
+            .iter()
+            .any(|e| e.relative_path == PathBuf::from("a/shared_link/data.txt")));
+        assert!(entries
+            .iter()
+            .any(|e| e.relative_path == PathBuf::from("shared/data.txt")));
     }
 
     #[test]
@@ -755,6 +1170,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scanner_skips_root_lock_file() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join(crate::sync::lock::LOCK_FILE_NAME), "{}").unwrap();
+        fs::write(
+            root.join("sub").join(crate::sync::lock::LOCK_FILE_NAME),
+            "{}",
+        )
+        .unwrap();
+
+        let mut paths: Vec<_> = Scanner::new(root)
+            .scan()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.relative_path)
+            .collect();
+        paths.sort();
+
+        // Only the destination root's lock is sync metadata
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("sub"),
+                PathBuf::from("sub").join(crate::sync::lock::LOCK_FILE_NAME),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scanner_max_depth() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::write(root.join("top.txt"), "top").unwrap();
+        fs::write(root.join("a/mid.txt"), "mid").unwrap();
+        fs::write(root.join("a/b/c/deep.txt"), "deep").unwrap();
+
+        let scanner = Scanner::new(root).max_depth(Some(2));
+        let mut paths: Vec<_> = scanner
+            .scan()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.relative_path)
+            .collect();
+        paths.sort();
+
+        // Directories at the limit are listed but not descended into
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("a"),
+                PathBuf::from("a/b"),
+                PathBuf::from("a/mid.txt"),
+                PathBuf::from("top.txt"),
+            ]
+        );
+
+        // Unlimited by default
+        assert_eq!(Scanner::new(root).scan().unwrap().len(), 6);
+    }
+
     #[test]
     fn test_scanner_very_long_filename() {
         let temp = TempDir::new().unwrap();
@@ -886,26 +1366,32 @@ mod tests {
         perms.set_mode(0o000);
         fs::set_permissions(&protected_dir, perms.clone()).unwrap();
 
+        fs::write(root.join("visible.txt"), "visible").unwrap();
+
         let scanner = Scanner::new(root);
-        let result = scanner.scan();
+        let result = scanner.scan_with_errors();
 
         // Restore permissions for cleanup
         perms.set_mode(0o755);
         fs::set_permissions(&protected_dir, perms).unwrap();
 
-        // Scanner should either error or skip the unreadable directory
-        // Both behaviors are acceptable
-        match result {
-            Ok(entries) => {
-                // If it succeeds, it should have skipped the protected directory
-                assert!(
-                    !entries.iter().any(|e| e.path.starts_with(&protected_dir)),
-                    "Should not include files from unreadable directory"
-                );
-            }
-            Err(_) => {
-                // Error is also acceptable
-            }
+        // The unreadable directory must not abort the scan
+        let (entries, errors) = result.unwrap();
+        assert!(entries
+            .iter()
+            .any(|e| e.relative_path == Path::new("visible.txt")));
+        assert!(entries
+            .iter()
+            .any(|e| e.relative_path == Path::new("protected")));
+
+        // Running as root bypasses the permission check entirely
+        let readable = entries
+            .iter()
+            .any(|e| e.relative_path == Path::new("protected/secret.txt"));
+        if !readable {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].path, protected_dir);
+            assert!(!errors[0].error.is_empty());
         }
     }
 
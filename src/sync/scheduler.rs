@@ -0,0 +1,288 @@
+use super::priority::Priority;
+use crate::perf::WorkerUsage;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Files at least this large are scheduled in the large-file lane
+pub const LARGE_FILE_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+/// Size-aware task scheduler with separate small-file and large-file lanes
+///
+/// Up to a quarter of the workers (at least one) run large files while small
+/// files are waiting, so thousands of small files never queue behind a few
+/// big ones and big files still make progress during a flood of small ones.
+/// A worker whose lane is empty steals from the other lane, keeping every
+/// worker busy. Large files are started biggest-first so the longest transfer
+/// doesn't end up last.
+///
+/// Priorities (`--priority-pattern`) come before all of that: a task is only
+/// handed out once every higher-priority task has started, in either lane.
+pub(crate) struct SizeScheduler<T> {
+    small: VecDeque<(Priority, T)>,
+    large: VecDeque<(Priority, T)>,
+    workers: Arc<Semaphore>,
+    large_running: Arc<AtomicUsize>,
+    large_workers: usize,
+    slots: Arc<WorkerSlots>,
+    /// Time spent waiting for a free worker
+    blocked: Duration,
+}
+
+/// Worker slot numbers and how much each slot has been used
+struct WorkerSlots {
+    free: Mutex<Vec<usize>>,
+    tasks: Vec<AtomicU64>,
+    busy_nanos: Vec<AtomicU64>,
+}
+
+/// Worker slot held by a running task, released on drop
+pub(crate) struct WorkerPermit {
+    _worker: OwnedSemaphorePermit,
+    large_running: Option<Arc<AtomicUsize>>,
+    slot: usize,
+    slots: Arc<WorkerSlots>,
+    started: Instant,
+}
+
+impl Drop for WorkerPermit {
+    fn drop(&mut self) {
+        if let Some(large_running) = &self.large_running {
+            large_running.fetch_sub(1, Ordering::SeqCst);
+        }
+        self.slots.tasks[self.slot].fetch_add(1, Ordering::Relaxed);
+        self.slots.busy_nanos[self.slot]
+            .fetch_add(self.started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        self.slots.free.lock().unwrap().push(self.slot);
+    }
+}
+
+impl<T> SizeScheduler<T> {
+    /// Split `items` into lanes by `size` (None for work without file data,
+    /// e.g. directories and deletions, which always go to the small lane),
+    /// ordered by `priority` within each lane
+    pub fn new<I, F, P>(items: I, workers: usize, size: F, priority: P) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        F: Fn(&T) -> Option<u64>,
+        P: Fn(&T) -> Priority,
+    {
+        let workers = workers.max(1);
+        let (mut large, mut small): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .map(|item| (priority(&item), item))
+            .partition(|(_, item)| size(item).is_some_and(|s| s >= LARGE_FILE_THRESHOLD));
+        // Stable, so small tasks of one priority keep their order
+        small.sort_by_key(|(priority, _)| *priority);
+        large.sort_by_key(|(priority, item)| (*priority, Reverse(size(item))));
+
+        Self {
+            small: small.into(),
+            large: large.into(),
+            workers: Arc::new(Semaphore::new(workers)),
+            large_running: Arc::new(AtomicUsize::new(0)),
+            large_workers: (workers / 4).max(1),
+            slots: Arc::new(WorkerSlots {
+                free: Mutex::new((0..workers).rev().collect()),
+                tasks: (0..workers).map(|_| AtomicU64::new(0)).collect(),
+                busy_nanos: (0..workers).map(|_| AtomicU64::new(0)).collect(),
+            }),
+            blocked: Duration::ZERO,
+        }
+    }
+
+    /// Number of tasks not yet handed out
+    pub fn remaining(&self) -> usize {
+        self.small.len() + self.large.len()
+    }
+
+    /// Wait for a free worker and hand out the next task
+    ///
+    /// The returned permit must be held until the task finishes. Returns
+    /// None once both lanes are empty.
+    pub async fn next(&mut self) -> Option<(T, WorkerPermit)> {
+        if self.remaining() == 0 {
+            return None;
+        }
+
+        let waiting = Instant::now();
+        let worker = Arc::clone(&self.workers)
+            .acquire_owned()
+            .await
+            .expect("scheduler semaphore is never closed");
+        self.blocked += waiting.elapsed();
+
+        // Only the lanes holding the highest remaining priority are eligible
+        let top = self
+            .small
+            .front()
+            .into_iter()
+            .chain(self.large.front())
+            .map(|(priority, _)| *priority)
+            .min()?;
+        let small_ready = self.small.front().is_some_and(|(p, _)| *p == top);
+        let large_ready = self.large.front().is_some_and(|(p, _)| *p == top);
+        let take_large = large_ready
+            && (!small_ready || self.large_running.load(Ordering::SeqCst) < self.large_workers);
+
+        let (item, large_running) = if take_large {
+            let (_, item) = self.large.pop_front()?;
+            self.large_running.fetch_add(1, Ordering::SeqCst);
+            (item, Some(Arc::clone(&self.large_running)))
+        } else {
+            (self.small.pop_front()?.1, None)
+        };
+
+        // Holding a semaphore permit guarantees a free slot
+        let slot = self
+            .slots
+            .free
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("a worker permit always has a free slot");
+        Some((
+            item,
+            WorkerPermit {
+                _worker: worker,
+                large_running,
+                slot,
+                slots: Arc::clone(&self.slots),
+                started: Instant::now(),
+            },
+        ))
+    }
+
+    /// Tasks run and busy time per worker slot so far
+    pub fn worker_usage(&self) -> Vec<WorkerUsage> {
+        self.slots
+            .tasks
+            .iter()
+            .zip(&self.slots.busy_nanos)
+            .map(|(tasks, busy)| WorkerUsage {
+                tasks: tasks.load(Ordering::Relaxed),
+                busy: Duration::from_nanos(busy.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+
+    /// Time spent waiting for a free worker so far
+    pub fn blocked(&self) -> Duration {
+        self.blocked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn scheduler(sizes: &[u64], workers: usize) -> SizeScheduler<u64> {
+        SizeScheduler::new(
+            sizes.iter().copied(),
+            workers,
+            |size| Some(*size),
+            |_| Priority::Normal,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_large_lane_is_capped_while_small_files_wait() {
+        let mut scheduler = scheduler(&[100 * MB, 1, 200 * MB, 2, 3, 300 * MB], 4);
+
+        // One of four workers runs the largest file, the rest take small ones
+        let mut permits = Vec::new();
+        let mut started = Vec::new();
+        for _ in 0..4 {
+            let (size, permit) = scheduler.next().await.unwrap();
+            started.push(size);
+            permits.push(permit);
+        }
+        assert_eq!(started, vec![300 * MB, 1, 2, 3]);
+        assert_eq!(scheduler.remaining(), 2);
+
+        // Small lane is empty: freed workers steal the remaining large files
+        permits.truncate(2);
+        assert_eq!(scheduler.next().await.unwrap().0, 200 * MB);
+        assert_eq!(scheduler.next().await.unwrap().0, 100 * MB);
+        assert!(scheduler.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_small_files_steal_idle_large_workers() {
+        let mut scheduler = scheduler(&[1, 2, 3, 4, 5], 4);
+
+        let mut permits = Vec::new();
+        for expected in 1..=4 {
+            let (size, permit) = scheduler.next().await.unwrap();
+            assert_eq!(size, expected);
+            permits.push(permit);
+        }
+
+        // All workers busy until one finishes
+        let next = tokio::time::timeout(std::time::Duration::from_millis(50), scheduler.next());
+        assert!(next.await.is_err());
+
+        permits.pop();
+        assert_eq!(scheduler.next().await.unwrap().0, 5);
+    }
+
+    #[tokio::test]
+    async fn test_worker_usage() {
+        let mut scheduler = scheduler(&[1, 2, 3], 2);
+
+        let (_, first) = scheduler.next().await.unwrap();
+        let (_, second) = scheduler.next().await.unwrap();
+        assert_ne!(first.slot, second.slot);
+        drop(first);
+        drop(second);
+        let (_, third) = scheduler.next().await.unwrap();
+        drop(third);
+
+        let usage = scheduler.worker_usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage.iter().map(|w| w.tasks).sum::<u64>(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_unsized_tasks_use_small_lane() {
+        let mut scheduler = SizeScheduler::new(
+            vec![Some(100 * MB), None],
+            1,
+            |size| *size,
+            |_| Priority::Normal,
+        );
+
+        // A single worker prefers the large lane, then drains the small one
+        let (first, permit) = scheduler.next().await.unwrap();
+        assert_eq!(first, Some(100 * MB));
+        drop(permit);
+        assert_eq!(scheduler.next().await.unwrap().0, None);
+    }
+
+    #[tokio::test]
+    async fn test_priority_tiers_start_first() {
+        // (size, priority) pairs
+        let items = vec![
+            (1, Priority::Normal),
+            (100 * MB, Priority::Low),
+            (2, Priority::High),
+            (200 * MB, Priority::Normal),
+            (300 * MB, Priority::High),
+            (3, Priority::Low),
+        ];
+        let mut scheduler = SizeScheduler::new(items, 4, |(size, _)| Some(*size), |(_, p)| *p);
+
+        // Each priority is drained before the next one starts
+        let mut order = Vec::new();
+        while let Some(((size, _), permit)) = scheduler.next().await {
+            order.push(size);
+            drop(permit);
+        }
+        assert_eq!(order, vec![300 * MB, 2, 200 * MB, 1, 100 * MB, 3]);
+    }
+}
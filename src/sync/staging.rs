@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Staging directory in the destination root for --delay-updates
+pub const STAGING_DIR: &str = ".sy-staging";
+
+/// Whether `path` is in the staging directory of `dest_root`
+pub fn is_staging(dest_root: &Path, path: &Path) -> bool {
+    path.starts_with(dest_root.join(STAGING_DIR))
+}
+
+/// One file to move into place when a staged sync commits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StagedMove {
+    /// Where the new version was written
+    pub staged: PathBuf,
+    /// Where it goes
+    pub dest: PathBuf,
+    /// Where the version it replaces is kept until the commit is done, so a
+    /// failed commit can put it back; None for new files
+    pub backup: Option<PathBuf>,
+}
+
+/// Where one sync writes its files with --delay-updates (`--atomic`)
+///
+/// New and updated files are written to `.sy-staging/new/` in the
+/// destination, so the destination itself doesn't change while they
+/// transfer. Once all of them are written, `commit_moves` renames them into
+/// place one after another; the versions they replace are hard-linked into
+/// `.sy-staging/old/` first, to put back if the commit fails halfway.
+pub struct Staging {
+    dest_root: PathBuf,
+    dir: PathBuf,
+}
+
+impl Staging {
+    pub fn new(dest_root: &Path) -> Self {
+        Self {
+            dest_root: dest_root.to_path_buf(),
+            dir: dest_root.join(STAGING_DIR),
+        }
+    }
+
+    /// The staging directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the new version of `dest` (in the destination) is written
+    pub fn staged_path(&self, dest: &Path) -> PathBuf {
+        self.dir.join("new").join(self.relative(dest))
+    }
+
+    /// The move that puts the file staged for `dest` into place, keeping the
+    /// version there if it `replaces` one
+    pub fn staged_move(&self, dest: &Path, replaces: bool) -> StagedMove {
+        StagedMove {
+            staged: self.staged_path(dest),
+            dest: dest.to_path_buf(),
+            backup: replaces.then(|| self.dir.join("old").join(self.relative(dest))),
+        }
+    }
+
+    fn relative<'a>(&self, dest: &'a Path) -> &'a Path {
+        dest.strip_prefix(&self.dest_root).unwrap_or(dest)
+    }
+}
+
+/// Move staged files into place in quick succession, on the local filesystem
+///
+/// The files being replaced are hard-linked to their backup paths before
+/// anything moves. If a move fails, the files already moved are put back
+/// (or removed, if they were new) before the error is returned. Moves whose
+/// staged file doesn't exist (transfers that were skipped) are left out.
+/// Returns how many files were moved.
+pub fn commit_moves(moves: &[StagedMove]) -> std::io::Result<usize> {
+    let moves: Vec<&StagedMove> = moves
+        .iter()
+        .filter(|m| m.staged.symlink_metadata().is_ok())
+        .collect();
+
+    // Keep the old versions first, before the destination changes at all
+    for m in &moves {
+        let Some(backup) = &m.backup else {
+            continue;
+        };
+        if let Some(parent) = backup.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match std::fs::hard_link(&m.dest, backup) {
+            Ok(()) => {}
+            // Gone since the sync was planned: nothing to put back
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to keep {} for rollback: {}", m.dest.display(), e),
+                ))
+            }
+        }
+    }
+
+    for (i, m) in moves.iter().enumerate() {
+        if let Err(e) = std::fs::rename(&m.staged, &m.dest) {
+            roll_back(&moves[..i]);
+            return Err(std::io::Error::new(
+                e.kind(),
+                format!(
+                    "Failed to move {} into place, undid the moves before it: {}",
+                    m.dest.display(),
+                    e
+                ),
+            ));
+        }
+    }
+    Ok(moves.len())
+}
+
+/// Undo `moved`, newest first
+fn roll_back(moved: &[&StagedMove]) {
+    for m in moved.iter().rev() {
+        let result = match &m.backup {
+            Some(backup) if backup.symlink_metadata().is_ok() => std::fs::rename(backup, &m.dest),
+            _ => std::fs::remove_file(&m.dest),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to roll back {}: {}", m.dest.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_staged_paths() {
+        let staging = Staging::new(Path::new("/dest"));
+        assert_eq!(
+            staging.staged_path(Path::new("/dest/a/b.txt")),
+            Path::new("/dest/.sy-staging/new/a/b.txt")
+        );
+        assert_eq!(
+            staging.staged_move(Path::new("/dest/a/b.txt"), true).backup,
+            Some(PathBuf::from("/dest/.sy-staging/old/a/b.txt"))
+        );
+        assert_eq!(
+            staging.staged_move(Path::new("/dest/c.txt"), false).backup,
+            None
+        );
+        assert!(is_staging(
+            Path::new("/dest"),
+            Path::new("/dest/.sy-staging/old")
+        ));
+        assert!(!is_staging(Path::new("/dest"), Path::new("/dest/a")));
+    }
+
+    #[test]
+    fn test_commit_moves() {
+        let temp = TempDir::new().unwrap();
+        let staging = Staging::new(temp.path());
+        let updated = temp.path().join("index.html");
+        let created = temp.path().join("app.js");
+        let skipped = temp.path().join("skipped.css");
+        fs::write(&updated, "old").unwrap();
+
+        let moves = vec![
+            staging.staged_move(&updated, true),
+            staging.staged_move(&created, false),
+            staging.staged_move(&skipped, false),
+        ];
+        fs::create_dir_all(staging.dir().join("new")).unwrap();
+        fs::write(&moves[0].staged, "new").unwrap();
+        fs::write(&moves[1].staged, "app").unwrap();
+
+        assert_eq!(commit_moves(&moves).unwrap(), 2);
+        assert_eq!(fs::read_to_string(&updated).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&created).unwrap(), "app");
+        assert!(!skipped.exists());
+        // The replaced version is kept until the staging directory goes
+        assert_eq!(
+            fs::read_to_string(moves[0].backup.as_ref().unwrap()).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn test_commit_moves_rolls_back() {
+        let temp = TempDir::new().unwrap();
+        let staging = Staging::new(temp.path());
+        let updated = temp.path().join("index.html");
+        let created = temp.path().join("app.js");
+        let blocked = temp.path().join("assets");
+        fs::write(&updated, "old").unwrap();
+        // Appeared since planning, and a file can't replace a directory
+        fs::create_dir_all(blocked.join("logo.png")).unwrap();
+
+        let moves = vec![
+            staging.staged_move(&updated, true),
+            staging.staged_move(&created, false),
+            staging.staged_move(&blocked, false),
+        ];
+        fs::create_dir_all(staging.dir().join("new")).unwrap();
+        for m in &moves {
+            fs::write(&m.staged, "new").unwrap();
+        }
+
+        assert!(commit_moves(&moves).is_err());
+        assert_eq!(fs::read_to_string(&updated).unwrap(), "old");
+        assert!(!created.exists());
+        assert!(blocked.join("logo.png").is_dir());
+    }
+}
@@ -1,12 +1,28 @@
-use super::checksumdb::ChecksumDatabase;
+use super::checksumdb::{checksum_type_name, ChecksumDatabase};
+use super::dircache::inode;
 use super::scanner::FileEntry;
-use crate::error::Result;
+use crate::error::{format_bytes, Result};
+use crate::filter::FilterEngine;
 use crate::integrity::{Checksum, ChecksumType, IntegrityVerifier};
 use crate::transport::{FileInfo, Transport};
-use std::path::Path;
-use std::time::SystemTime;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// mtime differences up to this many seconds count as equal (filesystem granularity)
+pub const MTIME_TOLERANCE_SECS: u64 = 1;
+
+/// Files planned per destination `stat_batch` call
+pub const PLAN_BATCH_SIZE: usize = 500;
+
+/// Planning batches kept in flight at once
+///
+/// Each batch costs a round-trip against a remote destination, so batches
+/// overlap instead of running one at a time.
+pub const PLAN_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SyncAction {
     /// Skip - file unchanged
     Skip,
@@ -18,11 +34,45 @@ pub enum SyncAction {
     Delete,
 }
 
+/// Why the planner chose a task's action (reported by `--dry-run --json`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanReason {
+    /// Not in the destination
+    Missing,
+    /// Source and destination sizes differ
+    SizeDiffers,
+    /// Source modified after the destination
+    MtimeNewer,
+    /// Source modified before the destination
+    MtimeOlder,
+    /// Source and destination checksums differ
+    ChecksumMismatch,
+    /// --checksum without checksums at planning time; contents are compared
+    /// during the transfer
+    ChecksumRequested,
+    /// --ignore-times with equal sizes
+    IgnoreTimes,
+    /// Size and mtime, or checksums, match
+    Unchanged,
+    /// In the destination but not in the source
+    NotInSource,
+    /// Not in the destination, but unchanged in the --link-dest directory,
+    /// so it is hard-linked from there
+    LinkDest,
+}
+
 #[derive(Debug)]
 pub struct SyncTask {
     pub source: Option<FileEntry>,
     pub dest_path: std::path::PathBuf,
     pub action: SyncAction,
+    /// Comparison that decided `action`
+    pub reason: PlanReason,
+    /// Size of the existing destination file (None if missing or a directory)
+    pub dest_size: Option<u64>,
+    /// Modification time of the existing destination file
+    pub dest_modified: Option<SystemTime>,
     /// Pre-computed source checksum (for --checksum mode)
     #[allow(dead_code)] // Will be used for checksum database storage (Phase 5b)
     pub source_checksum: Option<Checksum>,
@@ -31,9 +81,72 @@ pub struct SyncTask {
     pub dest_checksum: Option<Checksum>,
 }
 
+impl SyncTask {
+    /// Why the planner chose this task's action, in words (`--why`), e.g.
+    /// "size differs 1.20 MB→1.30 MB" or "mtime newer by 42s"
+    pub fn why(&self) -> String {
+        match self.reason {
+            PlanReason::Missing => "missing".to_string(),
+            PlanReason::SizeDiffers => match (self.dest_size, &self.source) {
+                (Some(dest), Some(source)) => format!(
+                    "size differs {}→{}",
+                    format_bytes(dest),
+                    format_bytes(source.size)
+                ),
+                _ => "size differs".to_string(),
+            },
+            PlanReason::MtimeNewer | PlanReason::MtimeOlder => {
+                let direction = if self.reason == PlanReason::MtimeNewer {
+                    "newer"
+                } else {
+                    "older"
+                };
+                match (&self.source, self.dest_modified) {
+                    (Some(source), Some(dest)) => {
+                        let difference = source
+                            .modified
+                            .duration_since(dest)
+                            .unwrap_or_else(|e| e.duration());
+                        format!("mtime {} by {}", direction, format_age(difference))
+                    }
+                    _ => format!("mtime {}", direction),
+                }
+            }
+            PlanReason::ChecksumMismatch => "checksum mismatch".to_string(),
+            PlanReason::ChecksumRequested => "--checksum, compared during transfer".to_string(),
+            PlanReason::IgnoreTimes => "--ignore-times".to_string(),
+            PlanReason::Unchanged => "up to date".to_string(),
+            PlanReason::NotInSource => "not in source".to_string(),
+            PlanReason::LinkDest => "unchanged in --link-dest".to_string(),
+        }
+    }
+}
+
+/// Short time difference, rounded down: "42s", "5m", "3h", "2d"
+fn format_age(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=119 => format!("{}s", secs),
+        120..=7199 => format!("{}m", secs / 60),
+        7200..=172_799 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// Whether a destination entry is a `NAME.sy-tmp` file a transfer writes
+fn is_temp_file(file: &FileEntry) -> bool {
+    !file.is_dir
+        && file
+            .path
+            .to_string_lossy()
+            .ends_with(crate::temp_file::TEMP_SUFFIX)
+}
+
 pub struct StrategyPlanner {
     /// mtime tolerance in seconds (to handle filesystem granularity)
     mtime_tolerance: u64,
+    /// How far the destination clock runs ahead of the source's, in milliseconds
+    clock_skew_ms: i64,
     /// Ignore modification times, always compare checksums
     ignore_times: bool,
     /// Only compare file size, skip mtime checks
@@ -42,16 +155,23 @@ pub struct StrategyPlanner {
     checksum: bool,
     /// Integrity verifier for checksum computation
     verifier: Option<IntegrityVerifier>,
+    /// Earlier copy of the destination to hard-link unchanged files from
+    link_dest: Option<PathBuf>,
+    /// Protect/risk rules keeping destination entries from deletion
+    protect: FilterEngine,
 }
 
 impl StrategyPlanner {
     pub fn new() -> Self {
         Self {
-            mtime_tolerance: 1, // 1 second tolerance for mtime comparison
+            mtime_tolerance: MTIME_TOLERANCE_SECS,
+            clock_skew_ms: 0,
             ignore_times: false,
             size_only: false,
             checksum: false,
             verifier: None,
+            link_dest: None,
+            protect: FilterEngine::new(),
         }
     }
 
@@ -66,15 +186,55 @@ impl StrategyPlanner {
         };
 
         Self {
-            mtime_tolerance: 1,
+            mtime_tolerance: MTIME_TOLERANCE_SECS,
+            clock_skew_ms: 0,
             ignore_times,
             size_only,
             checksum,
             verifier,
+            link_dest: None,
+            protect: FilterEngine::new(),
         }
     }
 
+    /// Read sources for checksum comparisons with O_NOATIME (--open-noatime)
+    pub fn with_open_noatime(mut self, enabled: bool) -> Self {
+        self.verifier = self.verifier.map(|verifier| verifier.open_noatime(enabled));
+        self
+    }
+
+    /// Allow for destination mtimes stamped by a clock `skew_ms` ahead of the source's
+    ///
+    /// Files sy copied carry the source mtime and still match exactly; files
+    /// written at the destination by other means match once the skew is
+    /// taken off.
+    pub fn with_clock_skew(mut self, skew_ms: i64) -> Self {
+        self.clock_skew_ms = skew_ms;
+        self
+    }
+
+    /// Hard-link files missing from the destination from `dir` where they're unchanged
+    ///
+    /// `dir` is an earlier copy of the destination, such as the previous
+    /// dated backup (--link-dest). A file counts as unchanged there by the
+    /// same comparison that would skip it at the destination.
+    pub fn with_link_dest(mut self, dir: Option<PathBuf>) -> Self {
+        self.link_dest = dir;
+        self
+    }
+
+    /// Keep destination entries that `filter`'s protect rules cover out of deletions
+    ///
+    /// Only its protect and risk rules count here (`P`/`R` in --filter).
+    /// Directories holding a protected entry aren't deleted either, since
+    /// deleting a directory removes everything in it.
+    pub fn with_protect_rules(mut self, filter: FilterEngine) -> Self {
+        self.protect = filter;
+        self
+    }
+
     /// Determine sync action for a source file (async version using transport)
+    #[allow(dead_code)] // The engine plans in batches (plan_batch_async)
     pub async fn plan_file_async<T: Transport>(
         &self,
         source: &FileEntry,
@@ -84,19 +244,133 @@ impl StrategyPlanner {
     ) -> Result<SyncTask> {
         let dest_path = dest_root.join(&source.relative_path);
 
-        let (action, source_checksum, dest_checksum) = if source.is_dir {
+        let (dest_exists, dest_info) = if source.is_dir {
             // For directories, just check existence (no metadata needed)
-            let exists = transport.exists(&dest_path).await.unwrap_or(false);
-            let action = if exists {
-                SyncAction::Skip
-            } else {
-                SyncAction::Create
-            };
-            (action, None, None)
+            (transport.exists(&dest_path).await.unwrap_or(false), None)
         } else {
             // For files, check existence and file info
             match transport.file_info(&dest_path).await {
-                Ok(dest_info) => {
+                Ok(info) => (true, Some(info)),
+                Err(_) => (false, None),
+            }
+        };
+
+        self.plan_with_dest(source, dest_path, dest_exists, dest_info, checksum_db)
+    }
+
+    /// Determine sync actions for a batch of source files
+    ///
+    /// All destination paths are looked up with a single `stat_batch` call,
+    /// which remote transports answer in one round-trip (`sy-remote
+    /// stat-batch`) instead of one per file. Tasks are returned in input order.
+    pub async fn plan_batch_async<T: Transport>(
+        &self,
+        sources: &[&FileEntry],
+        dest_root: &Path,
+        transport: &T,
+        checksum_db: Option<&ChecksumDatabase>,
+    ) -> Result<Vec<SyncTask>> {
+        let dest_paths: Vec<PathBuf> = sources
+            .iter()
+            .map(|source| dest_root.join(&source.relative_path))
+            .collect();
+        let dest_stats = transport.stat_batch(&dest_paths).await?;
+
+        let mut tasks = sources
+            .iter()
+            .zip(dest_paths)
+            .zip(dest_stats)
+            .map(|((source, dest_path), stat)| {
+                let dest_info = stat.map(|stat| stat.file_info());
+                self.plan_with_dest(source, dest_path, stat.is_some(), dest_info, checksum_db)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(link_dest) = &self.link_dest {
+            self.plan_link_dest(&mut tasks, link_dest, transport, checksum_db)
+                .await?;
+        }
+        Ok(tasks)
+    }
+
+    /// Mark new files that are unchanged in `link_dest` to be hard-linked from there
+    ///
+    /// Their copies in `link_dest` are looked up with one `stat_batch` call.
+    async fn plan_link_dest<T: Transport>(
+        &self,
+        tasks: &mut [SyncTask],
+        link_dest: &Path,
+        transport: &T,
+        checksum_db: Option<&ChecksumDatabase>,
+    ) -> Result<()> {
+        let mut candidates: Vec<(&mut SyncTask, PathBuf)> = tasks
+            .iter_mut()
+            .filter(|task| task.action == SyncAction::Create)
+            .filter_map(|task| {
+                let source = task.source.as_ref()?;
+                let path = (!source.is_dir && !source.is_symlink)
+                    .then(|| link_dest.join(&source.relative_path))?;
+                Some((task, path))
+            })
+            .collect();
+        if candidates.is_empty() {
+            return Ok(());
+        }
+
+        let paths: Vec<PathBuf> = candidates.iter().map(|(_, path)| path.clone()).collect();
+        let stats = transport.stat_batch(&paths).await?;
+        for ((task, path), stat) in candidates.iter_mut().zip(stats) {
+            let (Some(stat), Some(source)) = (stat, &task.source) else {
+                continue;
+            };
+            if stat.is_dir {
+                continue;
+            }
+            let (source_checksum, link_checksum) = match &self.verifier {
+                Some(verifier) => {
+                    self.compute_checksums_local(source, path, verifier, checksum_db)?
+                }
+                None => (None, None),
+            };
+            let (action, _) =
+                self.compare(source, &stat.file_info(), &source_checksum, &link_checksum);
+            if action == SyncAction::Skip {
+                task.reason = PlanReason::LinkDest;
+            }
+        }
+        Ok(())
+    }
+
+    /// Determine the sync action once the destination state is known
+    ///
+    /// Directories only need `dest_exists`; files are compared against
+    /// `dest_info` (None if the destination file is missing).
+    fn plan_with_dest(
+        &self,
+        source: &FileEntry,
+        dest_path: PathBuf,
+        dest_exists: bool,
+        dest_info: Option<FileInfo>,
+        checksum_db: Option<&ChecksumDatabase>,
+    ) -> Result<SyncTask> {
+        let _span = tracing::trace_span!("plan", path = %source.relative_path.display()).entered();
+        let dest_size = dest_info
+            .as_ref()
+            .filter(|_| !source.is_dir)
+            .map(|info| info.size);
+        let dest_modified = dest_info
+            .as_ref()
+            .filter(|_| !source.is_dir)
+            .map(|info| info.modified);
+        let (action, reason, source_checksum, dest_checksum) = if source.is_dir {
+            if dest_exists {
+                (SyncAction::Skip, PlanReason::Unchanged, None, None)
+            } else {
+                (SyncAction::Create, PlanReason::Missing, None, None)
+            }
+        } else {
+            match dest_info {
+                Some(dest_info) => {
                     // Compute checksums if verifier is present and files are local
                     let (source_cksum, dest_cksum) = if let Some(ref verifier) = self.verifier {
                         self.compute_checksums_local(source, &dest_path, verifier, checksum_db)?
@@ -104,36 +378,11 @@ impl StrategyPlanner {
                         (None, None)
                     };
 
-                    // If checksums are available and match, skip transfer
-                    let action = if let (Some(ref src_cksum), Some(ref dst_cksum)) =
-                        (&source_cksum, &dest_cksum)
-                    {
-                        if src_cksum == dst_cksum {
-                            tracing::debug!(
-                                "Checksums match for {}, skipping transfer",
-                                source.relative_path.display()
-                            );
-                            SyncAction::Skip
-                        } else {
-                            tracing::debug!(
-                                "Checksums differ for {}, will transfer",
-                                source.relative_path.display()
-                            );
-                            SyncAction::Update
-                        }
-                    } else {
-                        // No checksums available, use normal comparison
-                        let needs_update = self.needs_update(source, &dest_info);
-                        if needs_update {
-                            SyncAction::Update
-                        } else {
-                            SyncAction::Skip
-                        }
-                    };
-
-                    (action, source_cksum, dest_cksum)
+                    let (action, reason) =
+                        self.compare(source, &dest_info, &source_cksum, &dest_cksum);
+                    (action, reason, source_cksum, dest_cksum)
                 }
-                Err(_) => (SyncAction::Create, None, None),
+                None => (SyncAction::Create, PlanReason::Missing, None, None),
             }
         };
 
@@ -141,6 +390,9 @@ impl StrategyPlanner {
             source: Some(source.clone()),
             dest_path,
             action,
+            reason,
+            dest_size,
+            dest_modified,
             source_checksum,
             dest_checksum,
         })
@@ -156,53 +408,15 @@ impl StrategyPlanner {
         verifier: &IntegrityVerifier,
         checksum_db: Option<&ChecksumDatabase>,
     ) -> Result<(Option<Checksum>, Option<Checksum>)> {
-        let checksum_type = match verifier.checksum_type() {
-            ChecksumType::None => "none",
-            ChecksumType::Fast => "fast",
-            ChecksumType::Cryptographic => "cryptographic",
-        };
-
         // Try to get source checksum (check database first, then compute)
         let source_checksum = if source.path.exists() {
-            // Try database first
-            if let Some(db) = checksum_db {
-                if let Ok(Some(cached)) =
-                    db.get_checksum(&source.path, source.modified, source.size, checksum_type)
-                {
-                    tracing::debug!("Database hit for source: {}", source.path.display());
-                    Some(cached)
-                } else {
-                    // Cache miss, compute
-                    tracing::debug!(
-                        "Database miss for source: {}, computing",
-                        source.path.display()
-                    );
-                    match verifier.compute_file_checksum(&source.path) {
-                        Ok(cksum) => Some(cksum),
-                        Err(e) => {
-                            tracing::warn!(
-                                "Failed to compute source checksum for {}: {}",
-                                source.path.display(),
-                                e
-                            );
-                            None
-                        }
-                    }
-                }
-            } else {
-                // No database, compute directly
-                match verifier.compute_file_checksum(&source.path) {
-                    Ok(cksum) => Some(cksum),
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to compute source checksum for {}: {}",
-                            source.path.display(),
-                            e
-                        );
-                        None
-                    }
-                }
-            }
+            Self::cached_or_computed(
+                &source.path,
+                Some((source.modified, source.size, source.inode)),
+                verifier,
+                checksum_db,
+                "source",
+            )
         } else {
             None
         };
@@ -210,47 +424,13 @@ impl StrategyPlanner {
         // Try to get dest checksum (check database first, then compute)
         let dest_checksum = if dest_path.exists() {
             // Get dest metadata for database query
-            let dest_metadata = std::fs::metadata(dest_path).ok();
-            let (dest_mtime, dest_size) = if let Some(ref meta) = dest_metadata {
-                (meta.modified().ok(), Some(meta.len()))
-            } else {
-                (None, None)
-            };
+            let dest_stamp = std::fs::metadata(dest_path).ok().and_then(|meta| {
+                meta.modified()
+                    .ok()
+                    .map(|mtime| (mtime, meta.len(), inode(&meta)))
+            });
 
-            // Try database first
-            if let (Some(db), Some(mtime), Some(size)) = (checksum_db, dest_mtime, dest_size) {
-                if let Ok(Some(cached)) = db.get_checksum(dest_path, mtime, size, checksum_type) {
-                    tracing::debug!("Database hit for dest: {}", dest_path.display());
-                    Some(cached)
-                } else {
-                    // Cache miss, compute
-                    tracing::debug!("Database miss for dest: {}, computing", dest_path.display());
-                    match verifier.compute_file_checksum(dest_path) {
-                        Ok(cksum) => Some(cksum),
-                        Err(e) => {
-                            tracing::warn!(
-                                "Failed to compute dest checksum for {}: {}",
-                                dest_path.display(),
-                                e
-                            );
-                            None
-                        }
-                    }
-                }
-            } else {
-                // No database or metadata, compute directly
-                match verifier.compute_file_checksum(dest_path) {
-                    Ok(cksum) => Some(cksum),
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to compute dest checksum for {}: {}",
-                            dest_path.display(),
-                            e
-                        );
-                        None
-                    }
-                }
-            }
+            Self::cached_or_computed(dest_path, dest_stamp, verifier, checksum_db, "dest")
         } else {
             None
         };
@@ -258,23 +438,57 @@ impl StrategyPlanner {
         Ok((source_checksum, dest_checksum))
     }
 
+    /// Look up a checksum in the database by (mtime, size, inode), computing it on a miss
+    fn cached_or_computed(
+        path: &Path,
+        stamp: Option<(SystemTime, u64, Option<u64>)>,
+        verifier: &IntegrityVerifier,
+        checksum_db: Option<&ChecksumDatabase>,
+        side: &str,
+    ) -> Option<Checksum> {
+        if let (Some(db), Some((mtime, size, inode))) = (checksum_db, stamp) {
+            let checksum_type = checksum_type_name(verifier.checksum_type());
+            if let Ok(Some(cached)) = db.get_checksum(path, mtime, size, inode, checksum_type) {
+                tracing::debug!("Database hit for {}: {}", side, path.display());
+                return Some(cached);
+            }
+            tracing::debug!("Database miss for {}: {}, computing", side, path.display());
+        }
+
+        match verifier.compute_file_checksum(path) {
+            Ok(cksum) => Some(cksum),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to compute {} checksum for {}: {}",
+                    side,
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
     /// Determine sync action for a source file (sync version for local-only)
     #[allow(dead_code)]
     pub fn plan_file(&self, source: &FileEntry, dest_root: &Path) -> SyncTask {
         let dest_path = dest_root.join(&source.relative_path);
+        let mut dest_size = None;
+        let mut dest_modified = None;
 
-        let (action, source_checksum, dest_checksum) = if source.is_dir {
+        let (action, reason, source_checksum, dest_checksum) = if source.is_dir {
             // For directories, just check existence (no metadata needed)
-            let action = if dest_path.exists() {
-                SyncAction::Skip
+            if dest_path.exists() {
+                (SyncAction::Skip, PlanReason::Unchanged, None, None)
             } else {
-                SyncAction::Create
-            };
-            (action, None, None)
+                (SyncAction::Create, PlanReason::Missing, None, None)
+            }
         } else {
             // For files, check existence and metadata
             match std::fs::metadata(&dest_path) {
                 Ok(dest_meta) => {
+                    dest_size = Some(dest_meta.len());
+
                     // Compute checksums if verifier is present
                     let (source_cksum, dest_cksum) = if let Some(ref verifier) = self.verifier {
                         self.compute_checksums_local(source, &dest_path, verifier, None)
@@ -283,40 +497,16 @@ impl StrategyPlanner {
                         (None, None)
                     };
 
-                    // If checksums are available and match, skip transfer
-                    let action = if let (Some(ref src_cksum), Some(ref dst_cksum)) =
-                        (&source_cksum, &dest_cksum)
-                    {
-                        if src_cksum == dst_cksum {
-                            tracing::debug!(
-                                "Checksums match for {}, skipping transfer",
-                                source.relative_path.display()
-                            );
-                            SyncAction::Skip
-                        } else {
-                            tracing::debug!(
-                                "Checksums differ for {}, will transfer",
-                                source.relative_path.display()
-                            );
-                            SyncAction::Update
-                        }
-                    } else {
-                        // No checksums available, use normal comparison
-                        let dest_info = FileInfo {
-                            size: dest_meta.len(),
-                            modified: dest_meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
-                        };
-                        let needs_update = self.needs_update(source, &dest_info);
-                        if needs_update {
-                            SyncAction::Update
-                        } else {
-                            SyncAction::Skip
-                        }
+                    let dest_info = FileInfo {
+                        size: dest_meta.len(),
+                        modified: dest_meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
                     };
-
-                    (action, source_cksum, dest_cksum)
+                    dest_modified = Some(dest_info.modified);
+                    let (action, reason) =
+                        self.compare(source, &dest_info, &source_cksum, &dest_cksum);
+                    (action, reason, source_cksum, dest_cksum)
                 }
-                Err(_) => (SyncAction::Create, None, None),
+                Err(_) => (SyncAction::Create, PlanReason::Missing, None, None),
             }
         };
 
@@ -324,56 +514,108 @@ impl StrategyPlanner {
             source: Some(source.clone()),
             dest_path,
             action,
+            reason,
+            dest_size,
+            dest_modified,
             source_checksum,
             dest_checksum,
         }
     }
 
-    /// Check if file needs update based on size and mtime
-    fn needs_update(&self, source: &FileEntry, dest_info: &FileInfo) -> bool {
+    /// Decide between update and skip for a file present at the destination
+    ///
+    /// Checksums, when both are available, decide on their own; otherwise
+    /// size and mtime are compared.
+    fn compare(
+        &self,
+        source: &FileEntry,
+        dest_info: &FileInfo,
+        source_checksum: &Option<Checksum>,
+        dest_checksum: &Option<Checksum>,
+    ) -> (SyncAction, PlanReason) {
+        if let (Some(src_cksum), Some(dst_cksum)) = (source_checksum, dest_checksum) {
+            return if src_cksum == dst_cksum {
+                tracing::debug!(
+                    "Checksums match for {}, skipping transfer",
+                    source.relative_path.display()
+                );
+                (SyncAction::Skip, PlanReason::Unchanged)
+            } else {
+                tracing::debug!(
+                    "Checksums differ for {}, will transfer",
+                    source.relative_path.display()
+                );
+                (SyncAction::Update, PlanReason::ChecksumMismatch)
+            };
+        }
+
+        // No checksums available, use normal comparison
+        match self.needs_update(source, dest_info) {
+            Some(reason) => (SyncAction::Update, reason),
+            None => (SyncAction::Skip, PlanReason::Unchanged),
+        }
+    }
+
+    /// Check if file needs update based on size and mtime, and why
+    fn needs_update(&self, source: &FileEntry, dest_info: &FileInfo) -> Option<PlanReason> {
         // Handle comparison flags
 
-        // --checksum: Always return true to force checksum comparison
+        // --checksum: Always update to force checksum comparison
         // (actual checksum verification happens during transfer)
         if self.checksum {
-            return true;
+            return Some(PlanReason::ChecksumRequested);
         }
 
         // --ignore-times: Skip mtime checks, only compare size
         // (if sizes match, still force transfer to compare checksums)
         if self.ignore_times {
             if source.size != dest_info.size {
-                return true; // Different size = definitely needs update
+                return Some(PlanReason::SizeDiffers);
             }
-            return true; // Same size but ignore mtime = force checksum comparison
+            return Some(PlanReason::IgnoreTimes);
         }
 
         // --size-only: Only compare file size, skip mtime checks
         if self.size_only {
-            return source.size != dest_info.size;
+            return (source.size != dest_info.size).then_some(PlanReason::SizeDiffers);
         }
 
         // Default behavior: compare size + mtime
 
         // Different size = needs update
         if source.size != dest_info.size {
-            return true;
+            return Some(PlanReason::SizeDiffers);
         }
 
         // Check mtime with tolerance
         if !self.mtime_matches(&source.modified, &dest_info.modified) {
-            return true;
+            return Some(if source.modified > dest_info.modified {
+                PlanReason::MtimeNewer
+            } else {
+                PlanReason::MtimeOlder
+            });
         }
 
-        false
+        None
     }
 
-    /// Check if mtimes match within tolerance
+    /// Check if mtimes match within tolerance, with or without the clock skew
     fn mtime_matches(&self, source_mtime: &SystemTime, dest_mtime: &SystemTime) -> bool {
-        match source_mtime.duration_since(*dest_mtime) {
+        let within = |dest_mtime: SystemTime| match source_mtime.duration_since(dest_mtime) {
             Ok(duration) => duration.as_secs() <= self.mtime_tolerance,
             Err(e) => e.duration().as_secs() <= self.mtime_tolerance,
+        };
+        if within(*dest_mtime) {
+            return true;
         }
+
+        let skew = Duration::from_millis(self.clock_skew_ms.unsigned_abs());
+        let corrected = if self.clock_skew_ms > 0 {
+            dest_mtime.checked_sub(skew)
+        } else {
+            dest_mtime.checked_add(skew)
+        };
+        self.clock_skew_ms != 0 && corrected.is_some_and(within)
     }
 
     /// Find files to delete (in destination but not in source)
@@ -382,9 +624,43 @@ impl StrategyPlanner {
     /// providing 100x memory reduction vs HashMap while maintaining correctness.
     ///
     /// For small file sets (<10k), uses HashMap for simplicity.
+    ///
+    /// Temp files are left alone: an interrupted large transfer continues
+    /// from its temp file (see `resume::Checkpointer`), and `sy clean` removes
+    /// stale ones.
     pub fn plan_deletions(&self, source_files: &[FileEntry], dest_root: &Path) -> Vec<SyncTask> {
+        // Scan destination (use streaming to avoid loading all into memory)
+        match crate::sync::scanner::Scanner::new(dest_root).scan_streaming() {
+            Ok(dest_scanner) => self.plan_deletions_from(source_files, dest_scanner.flatten()),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Find files to delete among `dest_files`, a listing of the destination
+    ///
+    /// For destinations the local scanner can't read, such as remote hosts,
+    /// whose listing comes from `Transport::scan_destination`.
+    pub fn plan_deletions_from(
+        &self,
+        source_files: &[FileEntry],
+        dest_files: impl IntoIterator<Item = FileEntry>,
+    ) -> Vec<SyncTask> {
         let mut deletions = Vec::new();
 
+        // Directories that hold protected entries, and so must stay too
+        let mut protected = std::collections::HashSet::new();
+        let dest_files = dest_files
+            .into_iter()
+            .filter(|f| !is_temp_file(f))
+            .filter(|f| {
+                if !self.protect.is_protected(&f.relative_path, f.is_dir) {
+                    return true;
+                }
+                tracing::debug!("Protected from deletion: {}", f.relative_path.display());
+                protected.extend(f.path.ancestors().skip(1).map(Path::to_path_buf));
+                false
+            });
+
         // Choose strategy based on file count
         const BLOOM_THRESHOLD: usize = 10_000;
 
@@ -406,31 +682,34 @@ impl StrategyPlanner {
                 .map(|f| f.relative_path.clone())
                 .collect();
 
-            // Stream destination files and check against Bloom filter
-            if let Ok(dest_scanner) = crate::sync::scanner::Scanner::new(dest_root).scan_streaming()
-            {
-                for dest_file in dest_scanner.flatten() {
-                    // Check Bloom filter first (O(1), no false negatives)
-                    if !source_bloom.contains(&dest_file.relative_path) {
-                        // Definitely not in source - safe to delete
+            // Check destination files against the Bloom filter
+            for dest_file in dest_files {
+                // Check Bloom filter first (O(1), no false negatives)
+                if !source_bloom.contains(&dest_file.relative_path) {
+                    // Definitely not in source - safe to delete
+                    deletions.push(SyncTask {
+                        source: None,
+                        dest_path: dest_file.path,
+                        action: SyncAction::Delete,
+                        reason: PlanReason::NotInSource,
+                        dest_size: (!dest_file.is_dir).then_some(dest_file.size),
+                        dest_modified: None,
+                        source_checksum: None,
+                        dest_checksum: None,
+                    });
+                } else {
+                    // Bloom says "might exist" - verify with HashMap to handle false positives
+                    if !source_paths.contains(&dest_file.relative_path) {
                         deletions.push(SyncTask {
                             source: None,
                             dest_path: dest_file.path,
                             action: SyncAction::Delete,
+                            reason: PlanReason::NotInSource,
+                            dest_size: (!dest_file.is_dir).then_some(dest_file.size),
+                            dest_modified: None,
                             source_checksum: None,
                             dest_checksum: None,
                         });
-                    } else {
-                        // Bloom says "might exist" - verify with HashMap to handle false positives
-                        if !source_paths.contains(&dest_file.relative_path) {
-                            deletions.push(SyncTask {
-                                source: None,
-                                dest_path: dest_file.path,
-                                action: SyncAction::Delete,
-                                source_checksum: None,
-                                dest_checksum: None,
-                            });
-                        }
                     }
                 }
             }
@@ -441,23 +720,23 @@ impl StrategyPlanner {
                 .map(|f| f.relative_path.clone())
                 .collect();
 
-            // Scan destination (use streaming to avoid loading all into memory)
-            if let Ok(dest_scanner) = crate::sync::scanner::Scanner::new(dest_root).scan_streaming()
-            {
-                for dest_file in dest_scanner.flatten() {
-                    if !source_paths.contains(&dest_file.relative_path) {
-                        deletions.push(SyncTask {
-                            source: None,
-                            dest_path: dest_file.path,
-                            action: SyncAction::Delete,
-                            source_checksum: None,
-                            dest_checksum: None,
-                        });
-                    }
+            for dest_file in dest_files {
+                if !source_paths.contains(&dest_file.relative_path) {
+                    deletions.push(SyncTask {
+                        source: None,
+                        dest_path: dest_file.path,
+                        action: SyncAction::Delete,
+                        reason: PlanReason::NotInSource,
+                        dest_size: (!dest_file.is_dir).then_some(dest_file.size),
+                        dest_modified: None,
+                        source_checksum: None,
+                        dest_checksum: None,
+                    });
                 }
             }
         }
 
+        deletions.retain(|task| !protected.contains(&task.dest_path));
         deletions
     }
 }
@@ -495,12 +774,16 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let planner = StrategyPlanner::new();
         let task = planner.plan_file(&source_file, dest_root);
 
         assert_eq!(task.action, SyncAction::Create);
+        assert_eq!(task.reason, PlanReason::Missing);
     }
 
     #[test]
@@ -526,12 +809,16 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let planner = StrategyPlanner::new();
         let task = planner.plan_file(&source_file, dest_root);
 
         assert_eq!(task.action, SyncAction::Skip);
+        assert_eq!(task.reason, PlanReason::Unchanged);
     }
 
     #[test]
@@ -557,12 +844,64 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let planner = StrategyPlanner::new();
         let task = planner.plan_file(&source_file, dest_root);
 
         assert_eq!(task.action, SyncAction::Update);
+        assert_eq!(task.reason, PlanReason::SizeDiffers);
+        assert_eq!(task.why(), "size differs 3 B→100 B");
+    }
+
+    #[test]
+    fn test_plan_reason_mtime() {
+        let temp = TempDir::new().unwrap();
+        let dest_root = temp.path();
+        fs::write(dest_root.join("file.txt"), "content").unwrap();
+
+        let mut source_file = FileEntry {
+            path: PathBuf::from("/source/file.txt"),
+            relative_path: PathBuf::from("file.txt"),
+            size: 7,
+            modified: SystemTime::now() + Duration::from_secs(3600),
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 7,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+
+        let planner = StrategyPlanner::new();
+        let task = planner.plan_file(&source_file, dest_root);
+        assert_eq!(task.action, SyncAction::Update);
+        assert_eq!(task.reason, PlanReason::MtimeNewer);
+
+        source_file.modified = SystemTime::now() - Duration::from_secs(3600);
+        let task = planner.plan_file(&source_file, dest_root);
+        assert_eq!(task.reason, PlanReason::MtimeOlder);
+        assert!(task.why().starts_with("mtime older by "));
+
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(300)), "5m");
+        assert_eq!(format_age(Duration::from_secs(3 * 3600 + 59)), "3h");
+        assert_eq!(format_age(Duration::from_secs(2 * 86_400)), "2d");
+
+        // --size-only ignores the mtime
+        let planner = StrategyPlanner::with_comparison_flags(false, true, false);
+        let task = planner.plan_file(&source_file, dest_root);
+        assert_eq!(task.action, SyncAction::Skip);
     }
 
     #[test]
@@ -574,6 +913,8 @@ mod tests {
         fs::write(dest_root.join("keep.txt"), "keep").unwrap();
         fs::write(dest_root.join("delete1.txt"), "delete").unwrap();
         fs::write(dest_root.join("delete2.txt"), "delete").unwrap();
+        // An interrupted transfer's temp file is kept to resume from
+        fs::write(dest_root.join("big.iso.sy-tmp"), "partial").unwrap();
 
         // Source only has keep.txt
         let source_files = vec![FileEntry {
@@ -591,6 +932,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         }];
 
         let planner = StrategyPlanner::new();
@@ -599,6 +943,9 @@ mod tests {
         // Should plan to delete 2 files (delete1.txt, delete2.txt)
         assert_eq!(deletions.len(), 2);
         assert!(deletions.iter().all(|t| t.action == SyncAction::Delete));
+        assert!(deletions
+            .iter()
+            .all(|t| t.reason == PlanReason::NotInSource));
 
         let deletion_names: Vec<_> = deletions
             .iter()
@@ -641,6 +988,9 @@ mod tests {
                 nlink: 1,
                 acls: None,
                 bsd_flags: None,
+                atime: None,
+                crtime: None,
+                perms: None,
             });
         }
 
@@ -659,6 +1009,107 @@ mod tests {
         assert!(deletion_names.contains(&"delete2.txt"));
     }
 
+    #[test]
+    fn test_plan_deletions_from_listing() {
+        let entry = |path: &str, is_dir: bool| FileEntry {
+            path: PathBuf::from("/srv/www").join(path),
+            relative_path: PathBuf::from(path),
+            size: if is_dir { 0 } else { 10 },
+            modified: SystemTime::now(),
+            is_dir,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 0,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        // A remote destination: nothing at these paths on this machine
+        let dest_files = vec![
+            entry("index.html", false),
+            entry("old", true),
+            entry("old/page.html", false),
+            entry("video.mp4.sy-tmp", false),
+        ];
+        let source_files = vec![entry("index.html", false)];
+
+        let planner = StrategyPlanner::new();
+        let deletions = planner.plan_deletions_from(&source_files, dest_files);
+
+        let deleted: Vec<_> = deletions
+            .iter()
+            .map(|t| (t.dest_path.clone(), t.dest_size))
+            .collect();
+        assert_eq!(
+            deleted,
+            vec![
+                (PathBuf::from("/srv/www/old"), None),
+                (PathBuf::from("/srv/www/old/page.html"), Some(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_deletions_protected() {
+        let entry = |path: &str, is_dir: bool| FileEntry {
+            path: PathBuf::from("/srv/www").join(path),
+            relative_path: PathBuf::from(path),
+            size: if is_dir { 0 } else { 10 },
+            modified: SystemTime::now(),
+            is_dir,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 0,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        let dest_files = vec![
+            entry("index.html", false),
+            entry(".env", false),
+            // Not protected itself, but deleting it would take .env with it
+            entry("config", true),
+            entry("config/.env", false),
+            entry("config/old.toml", false),
+            entry("uploads", true),
+            entry("uploads/avatar.png", false),
+            entry("uploads/tmp", true),
+            entry("uploads/tmp/part.bin", false),
+            entry("stale.html", false),
+        ];
+        let source_files = vec![entry("index.html", false)];
+
+        let mut protect = FilterEngine::new();
+        protect.add_rule("R uploads/tmp/").unwrap();
+        protect.add_rule("P .env").unwrap();
+        protect.add_rule("P uploads/").unwrap();
+        let planner = StrategyPlanner::new().with_protect_rules(protect);
+        let deletions = planner.plan_deletions_from(&source_files, dest_files);
+
+        let deleted: Vec<_> = deletions.iter().map(|t| t.dest_path.clone()).collect();
+        assert_eq!(
+            deleted,
+            vec![
+                PathBuf::from("/srv/www/config/old.toml"),
+                PathBuf::from("/srv/www/uploads/tmp"),
+                PathBuf::from("/srv/www/uploads/tmp/part.bin"),
+                PathBuf::from("/srv/www/stale.html"),
+            ]
+        );
+    }
+
     #[test]
     fn test_plan_deletions_empty_source() {
         let temp_dest = TempDir::new().unwrap();
@@ -703,6 +1154,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         // Create planner with checksum mode enabled
@@ -745,6 +1199,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         // Create planner with checksum mode enabled
@@ -786,6 +1243,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         // Create planner with checksum mode enabled
@@ -825,6 +1285,9 @@ mod tests {
                 nlink: 1,
                 acls: None,
                 bsd_flags: None,
+                atime: None,
+                crtime: None,
+                perms: None,
             },
             FileEntry {
                 path: PathBuf::from("/source/file2.txt"),
@@ -841,6 +1304,9 @@ mod tests {
                 nlink: 1,
                 acls: None,
                 bsd_flags: None,
+                atime: None,
+                crtime: None,
+                perms: None,
             },
         ];
 
@@ -850,4 +1316,150 @@ mod tests {
         // No deletions needed
         assert_eq!(deletions.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_plan_batch_async_matches_per_file_plans() {
+        let temp = TempDir::new().unwrap();
+        let dest_root = temp.path();
+        fs::create_dir(dest_root.join("existing_dir")).unwrap();
+        fs::write(dest_root.join("same.txt"), "content").unwrap();
+        fs::write(dest_root.join("changed.txt"), "old").unwrap();
+
+        let entry = |name: &str, size: u64, is_dir: bool| FileEntry {
+            path: PathBuf::from("/source").join(name),
+            relative_path: PathBuf::from(name),
+            size,
+            modified: fs::metadata(dest_root.join(name))
+                .and_then(|m| m.modified())
+                .unwrap_or_else(|_| SystemTime::now()),
+            is_dir,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: size,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        let sources = [
+            entry("existing_dir", 0, true),
+            entry("new_dir", 0, true),
+            entry("same.txt", 7, false),
+            entry("changed.txt", 7, false),
+            entry("new.txt", 7, false),
+        ];
+        let batch: Vec<&FileEntry> = sources.iter().collect();
+
+        let planner = StrategyPlanner::new();
+        let transport = crate::transport::local::LocalTransport::new();
+        let tasks = planner
+            .plan_batch_async(&batch, dest_root, &transport, None)
+            .await
+            .unwrap();
+
+        let actions: Vec<_> = tasks.iter().map(|t| t.action.clone()).collect();
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Skip,
+                SyncAction::Create,
+                SyncAction::Skip,
+                SyncAction::Update,
+                SyncAction::Create,
+            ]
+        );
+        for (source, task) in sources.iter().zip(&tasks) {
+            let single = planner
+                .plan_file_async(source, dest_root, &transport, None)
+                .await
+                .unwrap();
+            assert_eq!(task.action, single.action);
+            assert_eq!(task.dest_path, dest_root.join(&source.relative_path));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_link_dest() {
+        let temp = TempDir::new().unwrap();
+        let previous = temp.path().join("2024-01-01");
+        let dest_root = temp.path().join("2024-01-02");
+        fs::create_dir_all(&previous).unwrap();
+        fs::create_dir_all(&dest_root).unwrap();
+        fs::write(previous.join("same.txt"), "content").unwrap();
+        fs::write(previous.join("changed.txt"), "old").unwrap();
+        let mtime = fs::metadata(previous.join("same.txt"))
+            .unwrap()
+            .modified()
+            .unwrap();
+
+        let entry = |name: &str, size: u64| FileEntry {
+            path: PathBuf::from("/source").join(name),
+            relative_path: PathBuf::from(name),
+            size,
+            modified: mtime,
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: size,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        let sources = [
+            entry("same.txt", 7),
+            entry("changed.txt", 7),
+            entry("new.txt", 7),
+        ];
+        let batch: Vec<&FileEntry> = sources.iter().collect();
+
+        let planner = StrategyPlanner::new().with_link_dest(Some(previous));
+        let transport = crate::transport::local::LocalTransport::new();
+        let tasks = planner
+            .plan_batch_async(&batch, &dest_root, &transport, None)
+            .await
+            .unwrap();
+
+        assert!(tasks.iter().all(|t| t.action == SyncAction::Create));
+        let reasons: Vec<_> = tasks.iter().map(|t| t.reason).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                PlanReason::LinkDest,
+                PlanReason::Missing,
+                PlanReason::Missing
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clock_skew_correction() {
+        let source = SystemTime::now();
+        let minute = Duration::from_secs(60);
+
+        let planner = StrategyPlanner::new();
+        assert!(planner.mtime_matches(&source, &source));
+        assert!(!planner.mtime_matches(&source, &(source + minute)));
+
+        // Destination clock a minute ahead: files it stamped itself match,
+        // and files sy copied still do
+        let planner = StrategyPlanner::new().with_clock_skew(60_000);
+        assert!(planner.mtime_matches(&source, &(source + minute)));
+        assert!(planner.mtime_matches(&source, &source));
+        assert!(!planner.mtime_matches(&source, &(source - minute)));
+        assert!(!planner.mtime_matches(&source, &(source + minute * 2)));
+
+        let planner = StrategyPlanner::new().with_clock_skew(-60_000);
+        assert!(planner.mtime_matches(&source, &(source - minute)));
+    }
 }
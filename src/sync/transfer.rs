@@ -1,10 +1,19 @@
+use crate::acl::AclMapPolicy;
 use crate::cli::SymlinkMode;
 use crate::error::{Result, SyncError};
+use crate::fs_util;
+use crate::owner::PreservePerms;
+use crate::sync::fault::FaultInjector;
+use crate::sync::munge;
+use crate::sync::output::{self, MetadataError, MetadataFailure, MetadataReport, WarningCategory};
+use crate::sync::sandbox::{is_unsafe_symlink, DestSandbox};
 use crate::sync::scanner::FileEntry;
-use crate::transport::{TransferResult, Transport};
+use crate::sync::trash::Trash;
+use crate::transport::{ProgressCallback, TransferResult, Transport};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use tokio::sync::Notify;
 
 /// State of an inode during hardlink processing
@@ -16,6 +25,24 @@ pub(crate) enum InodeState {
     Completed(PathBuf),
 }
 
+/// Destination files and the privileged xattrs that couldn't be written to them
+pub(crate) type PrivilegedXattrSkips = Arc<Mutex<Vec<(PathBuf, Vec<String>)>>>;
+
+/// Metadata that couldn't be applied in a sync, filled in by its transfers
+pub(crate) type SharedMetadataReport = Arc<Mutex<MetadataReport>>;
+
+/// What to do about destination entries with immutable or append-only flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImmutableMode {
+    /// Don't look for them (remote destinations)
+    #[default]
+    Ignore,
+    /// Refuse to change them, with an error saying why
+    Report,
+    /// Clear the flags for the change and restore them after (`--force-change`)
+    ForceChange,
+}
+
 pub struct Transferrer<'a, T: Transport> {
     transport: &'a T,
     dry_run: bool,
@@ -24,9 +51,23 @@ pub struct Transferrer<'a, T: Transport> {
     preserve_xattrs: bool,
     preserve_hardlinks: bool,
     preserve_acls: bool,
-    #[allow(dead_code)] // macOS only, no-op on other platforms - TODO: implement
-    preserve_flags: bool,
+    preserve_flags: bool, // macOS only, no-op on other platforms
+    preserve_atimes: bool,
+    preserve_crtimes: bool,
+    preserve_perms: PreservePerms,
     hardlink_map: Arc<Mutex<HashMap<u64, InodeState>>>, // inode -> state
+    privileged_xattr_skips: Option<PrivilegedXattrSkips>,
+    metadata_report: Option<SharedMetadataReport>,
+    acl_map: AclMapPolicy,
+    sandbox: Option<Arc<DestSandbox>>,
+    trash: Option<Arc<Trash>>, // Deletions are moved here instead (--trash)
+    immutable_mode: ImmutableMode,
+    check_source: bool,
+    whole_file: bool, // Copy updates whole instead of through delta sync (--whole-file)
+    why: Option<String>, // Planner's reason, appended to verbose lines (--why)
+    fault_injector: Option<Arc<FaultInjector>>, // Random failures (--fault-inject)
+    progress: Option<ProgressCallback>, // Bytes copied so far, for observers
+    partial_dir: Option<PathBuf>, // Checkpoints of large copies (--resume)
 }
 
 impl<'a, T: Transport> Transferrer<'a, T> {
@@ -51,10 +92,158 @@ impl<'a, T: Transport> Transferrer<'a, T> {
             preserve_hardlinks,
             preserve_acls,
             preserve_flags,
+            preserve_atimes: false,
+            preserve_crtimes: false,
+            preserve_perms: PreservePerms::default(),
             hardlink_map,
+            privileged_xattr_skips: None,
+            metadata_report: None,
+            acl_map: AclMapPolicy::default(),
+            sandbox: None,
+            trash: None,
+            immutable_mode: ImmutableMode::default(),
+            check_source: false,
+            whole_file: false,
+            why: None,
+            fault_injector: None,
+            progress: None,
+            partial_dir: None,
+        }
+    }
+
+    /// Collect privileged xattrs that couldn't be written instead of warning per file
+    ///
+    /// Lets the caller report them once for the whole sync.
+    pub(crate) fn with_privileged_xattr_skips(mut self, skips: PrivilegedXattrSkips) -> Self {
+        self.privileged_xattr_skips = Some(skips);
+        self
+    }
+
+    /// Count metadata that couldn't be applied in `report` instead of warning per file
+    pub(crate) fn with_metadata_report(mut self, report: SharedMetadataReport) -> Self {
+        self.metadata_report = Some(report);
+        self
+    }
+
+    /// Set how ACLs from a different ACL model than the destination's are handled
+    pub(crate) fn with_acl_map(mut self, policy: AclMapPolicy) -> Self {
+        self.acl_map = policy;
+        self
+    }
+
+    /// Refuse writes that would leave the destination root
+    pub(crate) fn with_sandbox(mut self, sandbox: Option<Arc<DestSandbox>>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Move deleted entries to the trash instead of removing them
+    pub(crate) fn with_trash(mut self, trash: Option<Arc<Trash>>) -> Self {
+        self.trash = trash;
+        self
+    }
+
+    /// Set how destination entries with immutable flags are handled
+    pub(crate) fn with_immutable_mode(mut self, mode: ImmutableMode) -> Self {
+        self.immutable_mode = mode;
+        self
+    }
+
+    /// Fail files whose local source changed while it was being read
+    ///
+    /// The source is stat'ed right before and after the copy, so files changed
+    /// between the scan and the copy still count as a clean copy.
+    pub(crate) fn with_source_check(mut self, enabled: bool) -> Self {
+        self.check_source = enabled;
+        self
+    }
+
+    /// Copy updated files whole instead of through delta sync (`--whole-file`)
+    pub(crate) fn with_whole_file(mut self, enabled: bool) -> Self {
+        self.whole_file = enabled;
+        self
+    }
+
+    /// Append the planner's reason for the entry to verbose lines (`--why`)
+    pub(crate) fn with_why(mut self, why: Option<String>) -> Self {
+        self.why = why;
+        self
+    }
+
+    /// Fail file transfers at random (`--fault-inject`)
+    pub(crate) fn with_fault_injector(mut self, injector: Option<Arc<FaultInjector>>) -> Self {
+        self.fault_injector = injector;
+        self
+    }
+
+    /// Report how far file copies have got to `progress` while they run
+    ///
+    /// Only transports that stream files in chunks (SFTP uploads) report
+    /// before a copy is done.
+    pub(crate) fn with_progress(mut self, progress: Option<ProgressCallback>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Checkpoint large copies into `dir`, so one cut short continues from
+    /// its temp file on the next run (`--resume`)
+    pub(crate) fn with_partial_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.partial_dir = dir;
+        self
+    }
+
+    /// Injected failure for a file transfer, if one is due
+    fn inject_fault(&self, source: &FileEntry, dest_path: &Path) -> Result<()> {
+        match &self.fault_injector {
+            Some(injector) if !source.is_dir => injector.check(dest_path),
+            _ => Ok(()),
         }
     }
 
+    /// ` (reason)` for verbose lines, empty without `--why`
+    fn why_suffix(&self) -> String {
+        self.why
+            .as_ref()
+            .map(|why| format!(" ({})", why))
+            .unwrap_or_default()
+    }
+
+    /// Check `dest_path` against the sandbox; symlinks are placed, not written through
+    fn check_sandbox(&self, source: &FileEntry, dest_path: &Path) -> Result<()> {
+        match &self.sandbox {
+            Some(sandbox) if self.places_symlink(source) => sandbox.check_entry(dest_path),
+            Some(sandbox) => sandbox.check(dest_path),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `source` ends up as a symlink (or nothing) rather than a copy of its target
+    fn places_symlink(&self, source: &FileEntry) -> bool {
+        if !source.is_symlink {
+            return false;
+        }
+        match (self.symlink_mode, &source.symlink_target) {
+            (SymlinkMode::Follow | SymlinkMode::Munge, _) => false,
+            (SymlinkMode::CopyUnsafe, Some(target)) => {
+                !is_unsafe_symlink(&source.relative_path, target)
+            }
+            _ => true,
+        }
+    }
+
+    /// Restore source access times (`--atimes`) and/or creation times (`--crtimes`)
+    pub(crate) fn with_preserve_times(mut self, atimes: bool, crtimes: bool) -> Self {
+        self.preserve_atimes = atimes;
+        self.preserve_crtimes = crtimes;
+        self
+    }
+
+    /// Copy the source's permission bits, owner and/or group (`-p`, `-o`, `-g`)
+    pub(crate) fn with_preserve_perms(mut self, preserve: PreservePerms) -> Self {
+        self.preserve_perms = preserve;
+        self
+    }
+
     /// Create a new file or directory
     /// Returns Some(TransferResult) for files, None for directories
     pub async fn create(
@@ -62,26 +251,38 @@ impl<'a, T: Transport> Transferrer<'a, T> {
         source: &FileEntry,
         dest_path: &Path,
     ) -> Result<Option<TransferResult>> {
+        self.check_sandbox(source, dest_path)?;
+
         if self.dry_run {
             if self.diff_mode && !source.is_dir {
                 tracing::info!(
-                    "Would create: {} ({})",
+                    "Would create: {} ({}){}",
                     dest_path.display(),
-                    Self::format_size(source.size)
+                    Self::format_size(source.size),
+                    self.why_suffix()
                 );
             } else {
-                tracing::info!("Would create: {}", dest_path.display());
+                tracing::info!("Would create: {}{}", dest_path.display(), self.why_suffix());
             }
             return Ok(None);
         }
+        self.inject_fault(source, dest_path)?;
 
         // Handle symlinks based on mode
         if source.is_symlink {
             return self.handle_symlink(source, dest_path).await;
         }
+        if self.restore_placeholder(source, dest_path, false).await? {
+            return Ok(None);
+        }
 
         if source.is_dir {
             self.create_directory(dest_path).await?;
+
+            // Directories carry xattrs, ACLs and permissions too (e.g. default
+            // ACLs); times are left alone since creating the children changes
+            // them anyway
+            self.write_metadata(source, dest_path).await?;
             Ok(None)
         } else {
             // Check if this is a hardlink we should preserve
@@ -110,6 +311,7 @@ impl<'a, T: Transport> Transferrer<'a, T> {
                                 return Ok(Some(TransferResult {
                                     bytes_written: 0,
                                     compression_used: false,
+                                    compression_decision: None,
                                     transferred_bytes: Some(0),
                                     delta_operations: None,
                                     literal_bytes: None,
@@ -146,16 +348,11 @@ impl<'a, T: Transport> Transferrer<'a, T> {
                                 );
 
                                 // Copy the file
+                                let before = self.source_snapshot(source);
                                 let result = self.copy_file(&source.path, dest_path).await?;
-
-                                // Write extended attributes if present
-                                self.write_xattrs(source, dest_path).await?;
-
-                                // Write ACLs if present
-                                self.write_acls(source, dest_path).await?;
-
-                                // Write BSD flags if present (macOS only)
-                                self.write_bsd_flags(source, dest_path).await?;
+                                self.check_source_unchanged(source, before)?;
+                                self.write_metadata(source, dest_path).await?;
+                                self.log_created(source, dest_path);
 
                                 // Mark as completed and notify waiters
                                 {
@@ -175,18 +372,68 @@ impl<'a, T: Transport> Transferrer<'a, T> {
             }
 
             // Not a hardlink or not preserving hardlinks - normal copy
+            let before = self.source_snapshot(source);
             let result = self.copy_file(&source.path, dest_path).await?;
+            self.check_source_unchanged(source, before)?;
+            self.write_metadata(source, dest_path).await?;
+            self.log_created(source, dest_path);
 
-            // Write extended attributes if present
-            self.write_xattrs(source, dest_path).await?;
+            Ok(Some(result))
+        }
+    }
 
-            // Write ACLs if present
-            self.write_acls(source, dest_path).await?;
+    /// New files are only listed with `--why`, to say why each was copied
+    fn log_created(&self, source: &FileEntry, dest_path: &Path) {
+        if self.why.is_some() {
+            tracing::info!(
+                "Created: {} -> {}{}",
+                source.path.display(),
+                dest_path.display(),
+                self.why_suffix()
+            );
+        }
+    }
 
-            // Write BSD flags if present (macOS only)
-            self.write_bsd_flags(source, dest_path).await?;
+    /// Create a new file as a hard link to its unchanged copy `link_from` (--link-dest)
+    ///
+    /// Falls back to copying when the link can't be made, e.g. across
+    /// filesystems. A linked file shares its metadata with `link_from`.
+    pub async fn link(
+        &self,
+        source: &FileEntry,
+        link_from: &Path,
+        dest_path: &Path,
+    ) -> Result<Option<TransferResult>> {
+        self.check_sandbox(source, dest_path)?;
 
-            Ok(Some(result))
+        if self.dry_run {
+            tracing::info!(
+                "Would hard-link: {} => {}{}",
+                dest_path.display(),
+                link_from.display(),
+                self.why_suffix()
+            );
+            return Ok(None);
+        }
+        self.inject_fault(source, dest_path)?;
+
+        match self.transport.create_hardlink(link_from, dest_path).await {
+            Ok(()) => Ok(Some(TransferResult {
+                bytes_written: 0,
+                compression_used: false,
+                compression_decision: None,
+                transferred_bytes: Some(0),
+                delta_operations: None,
+                literal_bytes: None,
+            })),
+            Err(e) => {
+                tracing::debug!(
+                    "Hard-linking {} failed ({}), copying instead",
+                    dest_path.display(),
+                    e
+                );
+                self.create(source, dest_path).await
+            }
         }
     }
 
@@ -197,58 +444,173 @@ impl<'a, T: Transport> Transferrer<'a, T> {
         source: &FileEntry,
         dest_path: &Path,
     ) -> Result<Option<TransferResult>> {
+        self.check_sandbox(source, dest_path)?;
+
         if self.dry_run {
             if self.diff_mode && !source.is_dir {
                 tracing::info!(
-                    "Would update: {} ({}, using delta sync)",
+                    "Would update: {} ({}, using delta sync){}",
                     dest_path.display(),
-                    Self::format_size(source.size)
+                    Self::format_size(source.size),
+                    self.why_suffix()
                 );
             } else {
-                tracing::info!("Would update: {}", dest_path.display());
+                tracing::info!("Would update: {}{}", dest_path.display(), self.why_suffix());
             }
             return Ok(None);
         }
+        self.inject_fault(source, dest_path)?;
 
-        if !source.is_dir {
-            // Use delta sync for updates
-            let result = self
-                .transport
-                .sync_file_with_delta(&source.path, dest_path)
-                .await?;
-
-            // Write extended attributes if present
-            self.write_xattrs(source, dest_path).await?;
-
-            // Write ACLs if present
-            self.write_acls(source, dest_path).await?;
-
-            // Write BSD flags if present (macOS only)
-            self.write_bsd_flags(source, dest_path).await?;
+        if source.is_symlink && self.symlink_mode == SymlinkMode::Munge {
+            return self.handle_symlink(source, dest_path).await;
+        }
+        if self.restore_placeholder(source, dest_path, true).await? {
+            return Ok(None);
+        }
 
-            tracing::info!(
-                "Updated: {} -> {}",
-                source.path.display(),
-                dest_path.display()
-            );
-            Ok(Some(result))
+        if !source.is_dir {
+            let cleared = self.clear_immutable(dest_path)?;
+            let result = self.update_file(source, dest_path).await;
+            // With -F on macOS the source's flags were just written instead
+            if result.is_err() || !(self.preserve_flags && cfg!(target_os = "macos")) {
+                self.restore_immutable(dest_path, cleared);
+            }
+            Ok(Some(result?))
         } else {
             Ok(None)
         }
     }
 
+    async fn update_file(&self, source: &FileEntry, dest_path: &Path) -> Result<TransferResult> {
+        // Use delta sync for updates, unless --whole-file
+        let before = self.source_snapshot(source);
+        let result = if self.whole_file {
+            self.copy_file(&source.path, dest_path).await?
+        } else {
+            self.transport
+                .sync_file_with_delta(&source.path, dest_path)
+                .await?
+        };
+        self.check_source_unchanged(source, before)?;
+        self.write_metadata(source, dest_path).await?;
+
+        tracing::info!(
+            "Updated: {} -> {}{}",
+            source.path.display(),
+            dest_path.display(),
+            self.why_suffix()
+        );
+        Ok(result)
+    }
+
     /// Delete a file or directory
     pub async fn delete(&self, dest_path: &Path, is_dir: bool) -> Result<()> {
+        if let Some(sandbox) = &self.sandbox {
+            sandbox.check_entry(dest_path)?;
+        }
+
         if self.dry_run {
-            tracing::info!("Would delete: {}", dest_path.display());
+            let action = if self.trash.is_some() {
+                "move to trash"
+            } else {
+                "delete"
+            };
+            tracing::info!(
+                "Would {}: {}{}",
+                action,
+                dest_path.display(),
+                self.why_suffix()
+            );
             return Ok(());
         }
 
-        self.transport.remove(dest_path, is_dir).await?;
-        tracing::info!("Deleted: {}", dest_path.display());
+        let cleared = self.clear_immutable(dest_path)?;
+        let trashed = match &self.trash {
+            Some(trash) => trash.put(self.transport, dest_path).await.map(Some),
+            None => self
+                .transport
+                .remove(dest_path, is_dir)
+                .await
+                .map(|()| None),
+        };
+        match trashed {
+            Ok(Some(trashed)) => tracing::info!(
+                "Moved to trash: {} -> {}{}",
+                dest_path.display(),
+                trashed.display(),
+                self.why_suffix()
+            ),
+            Ok(None) => tracing::info!("Deleted: {}{}", dest_path.display(), self.why_suffix()),
+            Err(e) => {
+                self.restore_immutable(dest_path, cleared);
+                return Err(e);
+            }
+        }
         Ok(())
     }
 
+    /// Clear immutable and append-only flags on `dest_path` before changing it
+    ///
+    /// Returns the cleared flags, for `restore_immutable`. Without
+    /// `--force-change`, or the privileges it needs, flagged entries are
+    /// refused with an error saying why.
+    fn clear_immutable(&self, dest_path: &Path) -> Result<u32> {
+        if self.immutable_mode == ImmutableMode::Ignore {
+            return Ok(0);
+        }
+        let flags = fs_util::immutable_flags(dest_path);
+        if flags == 0 {
+            return Ok(0);
+        }
+
+        let hint = match self.immutable_mode {
+            ImmutableMode::ForceChange if fs_util::may_clear_immutable() => {
+                match fs_util::set_immutable_flags(dest_path, 0) {
+                    Ok(()) => {
+                        tracing::debug!(
+                            "Cleared immutable flags 0x{:x} on {}",
+                            flags,
+                            dest_path.display()
+                        );
+                        return Ok(flags);
+                    }
+                    Err(e) => format!("Clearing the flag failed: {}", e),
+                }
+            }
+            ImmutableMode::ForceChange => {
+                "Clearing the flag (--force-change) needs root or CAP_LINUX_IMMUTABLE.".to_string()
+            }
+            _ => "Clear the flag (chattr -i, chflags nouchg), or use --force-change to clear it \
+                  for the change and restore it after."
+                .to_string(),
+        };
+        Err(SyncError::Immutable {
+            path: dest_path.to_path_buf(),
+            hint,
+        })
+    }
+
+    /// Put back flags taken off by `clear_immutable`
+    fn restore_immutable(&self, dest_path: &Path, flags: u32) {
+        if flags == 0 {
+            return;
+        }
+        if let Err(e) = fs_util::set_immutable_flags(dest_path, flags) {
+            self.report_metadata(
+                dest_path,
+                vec![MetadataError::new(
+                    WarningCategory::Flags,
+                    MetadataFailure::from_io_error(&e),
+                    format!(
+                        "Failed to restore immutable flags on {}: {}",
+                        dest_path.display(),
+                        e
+                    ),
+                )],
+            );
+        }
+    }
+
     async fn create_directory(&self, path: &Path) -> Result<()> {
         self.transport.create_dir_all(path).await?;
         tracing::debug!("Created directory: {}", path.display());
@@ -262,157 +624,233 @@ impl<'a, T: Transport> Transferrer<'a, T> {
         }
 
         // Copy file using transport
-        let result = self.transport.copy_file(source, dest).await?;
+        let result = match (&self.partial_dir, &self.progress) {
+            (Some(dir), progress) => {
+                self.transport
+                    .copy_file_resumable(source, dest, progress.clone(), dir)
+                    .await?
+            }
+            (None, Some(progress)) => {
+                self.transport
+                    .copy_file_with_progress(source, dest, Arc::clone(progress))
+                    .await?
+            }
+            (None, None) => self.transport.copy_file(source, dest).await?,
+        };
 
         tracing::debug!("Copied: {} -> {}", source.display(), dest.display());
         Ok(result)
     }
 
-    /// Write extended attributes to a file
-    async fn write_xattrs(&self, file_entry: &FileEntry, dest_path: &Path) -> Result<()> {
-        if !self.preserve_xattrs {
+    /// Size and modification time of a local source, for `check_source_unchanged`
+    fn source_snapshot(&self, source: &FileEntry) -> Option<(u64, SystemTime)> {
+        if !self.check_source {
+            return None;
+        }
+        let meta = std::fs::metadata(&source.path).ok()?;
+        Some((meta.len(), meta.modified().ok()?))
+    }
+
+    /// Refuse a copy whose source changed size or mtime while it was read
+    ///
+    /// Like rsync, the torn copy isn't trusted: the file counts as failed, so
+    /// it is retried at the end of the run (`--retry-failed`). The copy keeps
+    /// the mtime from before the change, so the next sync updates it anyway.
+    fn check_source_unchanged(
+        &self,
+        source: &FileEntry,
+        before: Option<(u64, SystemTime)>,
+    ) -> Result<()> {
+        let Some(before) = before else {
+            return Ok(());
+        };
+        // A source that vanished meanwhile was still read whole; deletions are
+        // picked up by the next sync
+        let Ok(meta) = std::fs::metadata(&source.path) else {
             return Ok(());
+        };
+        if meta.len() != before.0 || meta.modified().ok() != Some(before.1) {
+            return Err(SyncError::SourceChanged {
+                path: source.path.clone(),
+            });
         }
+        Ok(())
+    }
 
-        #[cfg(unix)]
-        {
-            if let Some(ref xattrs) = file_entry.xattrs {
-                if xattrs.is_empty() {
-                    return Ok(());
-                }
+    /// Write the source's xattrs, ACLs and permissions, and for files its
+    /// times and BSD flags, to what was just created or updated
+    ///
+    /// Metadata that couldn't be applied doesn't fail the file, since the
+    /// contents are in place; it goes to the sync's report instead.
+    async fn write_metadata(&self, file_entry: &FileEntry, dest_path: &Path) -> Result<()> {
+        let mut failures = self.write_xattrs(file_entry, dest_path).await?;
+        failures.extend(self.write_acls(file_entry, dest_path).await?);
 
-                let dest_path = dest_path.to_path_buf();
-                let xattrs_clone = xattrs.clone();
+        // Permission bits, owner and group, after the ACLs
+        failures.extend(self.write_perms(file_entry, dest_path).await);
 
-                tokio::task::spawn_blocking(move || {
-                    for (name, value) in xattrs_clone {
-                        if let Err(e) = xattr::set(&dest_path, &name, &value) {
-                            tracing::warn!(
-                                "Failed to set xattr {} on {}: {}",
-                                name,
-                                dest_path.display(),
-                                e
-                            );
-                        } else {
-                            tracing::debug!("Set xattr {} on {}", name, dest_path.display());
-                        }
-                    }
-                })
-                .await
-                .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?;
-            }
-        }
+        if !file_entry.is_dir {
+            // Restore access/creation times (before flags, which can make the file immutable)
+            self.write_times(file_entry, dest_path).await?;
 
-        #[cfg(not(unix))]
-        {
-            // xattrs not supported on non-Unix platforms
-            let _ = (file_entry, dest_path);
+            // Write BSD flags if present (macOS only)
+            failures.extend(self.write_bsd_flags(file_entry, dest_path).await?);
         }
 
+        self.report_metadata(dest_path, failures);
         Ok(())
     }
 
-    async fn write_acls(&self, file_entry: &FileEntry, dest_path: &Path) -> Result<()> {
-        if !self.preserve_acls {
-            return Ok(());
+    /// Count metadata that couldn't be applied to `dest_path` in the sync's report
+    ///
+    /// With a report, which is summarized at the end of the sync, each failure
+    /// is only logged with `-v` (and emitted in JSON mode); without one, e.g.
+    /// for a single file, each is a warning.
+    fn report_metadata(&self, dest_path: &Path, failures: Vec<MetadataError>) {
+        if failures.is_empty() {
+            return;
         }
-
-        #[cfg(unix)]
-        {
-            if let Some(ref acls_bytes) = file_entry.acls {
-                if acls_bytes.is_empty() {
-                    return Ok(());
+        match self.metadata_report {
+            Some(ref report) => {
+                report.lock().unwrap().add(
+                    dest_path,
+                    failures
+                        .iter()
+                        .map(|failure| (failure.category, failure.reason)),
+                );
+                for failure in failures {
+                    output::note(failure.category, Some(dest_path), failure.message);
                 }
+            }
+            None => {
+                for failure in failures {
+                    output::warn(failure.category, Some(dest_path), failure.message);
+                }
+            }
+        }
+    }
 
-                let dest_path = dest_path.to_path_buf();
-                let acls_bytes = acls_bytes.clone();
+    /// Write extended attributes to a file
+    ///
+    /// Routed through the transport so xattrs land on the destination side,
+    /// whether that is the local filesystem or a remote host. Returns the
+    /// attributes that couldn't be written.
+    async fn write_xattrs(
+        &self,
+        file_entry: &FileEntry,
+        dest_path: &Path,
+    ) -> Result<Vec<MetadataError>> {
+        if !self.preserve_xattrs {
+            return Ok(Vec::new());
+        }
 
-                tokio::task::spawn_blocking(move || {
-                    use exacl::{setfacl, AclEntry};
-                    use std::str::FromStr;
+        let Some(ref xattrs) = file_entry.xattrs else {
+            return Ok(Vec::new());
+        };
+        if xattrs.is_empty() {
+            return Ok(Vec::new());
+        }
 
-                    // Parse ACL text back to string
-                    let acls_text = match String::from_utf8(acls_bytes) {
-                        Ok(text) => text,
-                        Err(e) => {
-                            tracing::warn!(
-                                "Failed to parse ACL text for {}: {}",
-                                dest_path.display(),
-                                e
-                            );
-                            return;
-                        }
-                    };
+        let mut result = self.transport.set_xattrs(dest_path, xattrs).await?;
+        let skipped = result.skipped_privileged;
+        if !skipped.is_empty() {
+            result.failed.push(MetadataError::new(
+                WarningCategory::Xattr,
+                MetadataFailure::NotPermitted,
+                format!(
+                    "Skipped privileged xattrs {} on {} (requires root or CAP_SETFCAP)",
+                    skipped.join(", "),
+                    dest_path.display()
+                ),
+            ));
+            if let Some(ref skips) = self.privileged_xattr_skips {
+                skips
+                    .lock()
+                    .unwrap()
+                    .push((dest_path.to_path_buf(), skipped));
+            }
+        }
 
-                    // Parse each line as an ACL entry
-                    let mut acl_entries = Vec::new();
-                    for line in acls_text.lines() {
-                        let line = line.trim();
-                        if line.is_empty() {
-                            continue;
-                        }
+        Ok(result.failed)
+    }
 
-                        // Parse ACL entry from standard text format
-                        match AclEntry::from_str(line) {
-                            Ok(entry) => acl_entries.push(entry),
-                            Err(e) => {
-                                tracing::warn!(
-                                    "Failed to parse ACL entry '{}' for {}: {}",
-                                    line,
-                                    dest_path.display(),
-                                    e
-                                );
-                                continue;
-                            }
-                        }
-                    }
+    /// Write ACLs to a file; returns the entries that couldn't be written
+    async fn write_acls(
+        &self,
+        file_entry: &FileEntry,
+        dest_path: &Path,
+    ) -> Result<Vec<MetadataError>> {
+        if !self.preserve_acls {
+            return Ok(Vec::new());
+        }
 
-                    if acl_entries.is_empty() {
-                        tracing::debug!(
-                            "No valid ACL entries to write for {}",
-                            dest_path.display()
-                        );
-                        return;
-                    }
+        let Some(ref acls_bytes) = file_entry.acls else {
+            return Ok(Vec::new());
+        };
+        if acls_bytes.is_empty() {
+            return Ok(Vec::new());
+        }
 
-                    // Apply ACLs to destination file
-                    match setfacl(&[&dest_path], &acl_entries, None) {
-                        Ok(_) => {
-                            tracing::debug!(
-                                "Successfully applied {} ACL entries to {}",
-                                acl_entries.len(),
-                                dest_path.display()
-                            );
-                        }
-                        Err(e) => {
-                            tracing::warn!(
-                                "Failed to apply ACLs to {}: {}",
-                                dest_path.display(),
-                                e
-                            );
-                        }
-                    }
-                })
-                .await
-                .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?;
-            }
+        let result = self
+            .transport
+            .set_acls(dest_path, acls_bytes, self.acl_map)
+            .await?;
+        Ok(result.failed)
+    }
+
+    /// Set the source's permission bits, owner and/or group on the destination
+    ///
+    /// The mode's group bits are an ACL's mask, so this follows the ACLs.
+    /// Directories keep owner rwx so their contents can still be written.
+    /// Failures are returned rather than failing the file, since the contents
+    /// are in place.
+    async fn write_perms(&self, file_entry: &FileEntry, dest_path: &Path) -> Option<MetadataError> {
+        let mut update = file_entry
+            .perms
+            .and_then(|perms| self.preserve_perms.update(&perms))?;
+        if file_entry.is_dir {
+            update.mode = update.mode.map(|mode| mode | 0o700);
         }
 
-        #[cfg(not(unix))]
-        {
-            // ACLs not supported on non-Unix platforms
-            let _ = (file_entry, dest_path);
+        match self.transport.set_permissions(dest_path, update).await {
+            Ok(()) => None,
+            Err(e) => Some(MetadataError::new(
+                WarningCategory::Permissions,
+                MetadataFailure::from_error(&e),
+                format!(
+                    "Failed to set permissions on {}: {}",
+                    dest_path.display(),
+                    e
+                ),
+            )),
         }
+    }
 
-        Ok(())
+    /// Restore access and creation times on the destination
+    ///
+    /// Done after the content and other metadata are written, since those
+    /// writes would otherwise move the access time again.
+    async fn write_times(&self, file_entry: &FileEntry, dest_path: &Path) -> Result<()> {
+        let atime = file_entry.atime.filter(|_| self.preserve_atimes);
+        let crtime = file_entry.crtime.filter(|_| self.preserve_crtimes);
+        if atime.is_none() && crtime.is_none() {
+            return Ok(());
+        }
+
+        self.transport.set_times(dest_path, atime, crtime).await
     }
 
-    async fn write_bsd_flags(&self, file_entry: &FileEntry, dest_path: &Path) -> Result<()> {
+    /// Set BSD file flags; returns why they couldn't be set, if they couldn't
+    async fn write_bsd_flags(
+        &self,
+        file_entry: &FileEntry,
+        dest_path: &Path,
+    ) -> Result<Option<MetadataError>> {
         #[cfg(not(target_os = "macos"))]
         {
             // BSD flags only supported on macOS
             let _ = (file_entry, dest_path);
-            return Ok(());
+            return Ok(None);
         }
 
         #[cfg(target_os = "macos")]
@@ -433,12 +871,15 @@ impl<'a, T: Transport> Transferrer<'a, T> {
                 let c_path = match CString::new(dest_path.to_str().unwrap_or("")) {
                     Ok(p) => p,
                     Err(e) => {
-                        tracing::warn!(
-                            "Failed to create C string for {}: {}",
-                            dest_path.display(),
-                            e
-                        );
-                        return;
+                        return Some(MetadataError::new(
+                            WarningCategory::Flags,
+                            MetadataFailure::Other,
+                            format!(
+                                "Failed to create C string for {}: {}",
+                                dest_path.display(),
+                                e
+                            ),
+                        ));
                     }
                 };
 
@@ -446,23 +887,26 @@ impl<'a, T: Transport> Transferrer<'a, T> {
 
                 if result != 0 {
                     let err = std::io::Error::last_os_error();
-                    tracing::warn!(
-                        "Failed to set BSD flags on {}: {}",
-                        dest_path.display(),
-                        err
-                    );
+                    Some(MetadataError::new(
+                        WarningCategory::Flags,
+                        MetadataFailure::from_io_error(&err),
+                        format!(
+                            "Failed to set BSD flags on {}: {}",
+                            dest_path.display(),
+                            err
+                        ),
+                    ))
                 } else {
                     tracing::debug!(
                         "Set BSD flags 0x{:x} on {}",
                         flags_to_set,
                         dest_path.display()
                     );
+                    None
                 }
             })
             .await
-            .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?;
-
-            Ok(())
+            .map_err(|e| crate::error::SyncError::Io(std::io::Error::other(e.to_string())))
         }
     }
 
@@ -479,38 +923,71 @@ impl<'a, T: Transport> Transferrer<'a, T> {
             SymlinkMode::Follow => {
                 // Follow the symlink and copy the target
                 if let Some(ref target) = source.symlink_target {
-                    // Check if target exists
-                    if !target.exists() {
-                        tracing::warn!(
-                            "Symlink target does not exist: {} -> {}",
-                            source.path.display(),
-                            target.display()
-                        );
-                        return Ok(None);
-                    }
+                    self.copy_symlink_target(source, target, dest_path).await
+                } else {
+                    output::warn(
+                        WarningCategory::Symlink,
+                        Some(source.path.as_path()),
+                        format!("Symlink has no target: {}", source.path.display()),
+                    );
+                    Ok(None)
+                }
+            }
+            SymlinkMode::Safe | SymlinkMode::CopyUnsafe => {
+                let Some(ref target) = source.symlink_target else {
+                    output::warn(
+                        WarningCategory::Symlink,
+                        Some(source.path.as_path()),
+                        format!("Symlink has no target: {}", source.path.display()),
+                    );
+                    return Ok(None);
+                };
 
-                    // Copy the target file/directory
-                    if target.is_dir() {
-                        tracing::warn!(
-                            "Skipping symlink to directory (not supported in follow mode): {}",
-                            source.path.display()
-                        );
-                        Ok(None)
-                    } else {
-                        let result = self.copy_file(target, dest_path).await?;
-                        tracing::debug!(
-                            "Followed symlink and copied target: {} -> {}",
-                            target.display(),
-                            dest_path.display()
-                        );
-                        Ok(Some(result))
-                    }
+                if !is_unsafe_symlink(&source.relative_path, target) {
+                    self.transport.create_symlink(target, dest_path).await?;
+                    tracing::debug!(
+                        "Created symlink: {} -> {}",
+                        dest_path.display(),
+                        target.display()
+                    );
+                    Ok(None)
+                } else if self.symlink_mode == SymlinkMode::CopyUnsafe {
+                    // Relative targets are relative to the link's directory
+                    let target = match source.path.parent() {
+                        Some(dir) => dir.join(target),
+                        None => target.clone(),
+                    };
+                    self.copy_symlink_target(source, &target, dest_path).await
                 } else {
-                    tracing::warn!("Symlink has no target: {}", source.path.display());
+                    tracing::debug!(
+                        "Skipping unsafe symlink: {} -> {}",
+                        source.path.display(),
+                        target.display()
+                    );
                     Ok(None)
                 }
             }
-            SymlinkMode::Preserve => {
+            SymlinkMode::Munge => {
+                let Some(ref target) = source.symlink_target else {
+                    output::warn(
+                        WarningCategory::Symlink,
+                        Some(source.path.as_path()),
+                        format!("Symlink has no target: {}", source.path.display()),
+                    );
+                    return Ok(None);
+                };
+
+                self.transport
+                    .write_file(dest_path, &munge::encode(target), source.modified)
+                    .await?;
+                tracing::debug!(
+                    "Stored symlink as placeholder file: {} -> {}",
+                    dest_path.display(),
+                    target.display()
+                );
+                Ok(None)
+            }
+            SymlinkMode::Preserve | SymlinkMode::Unmunge => {
                 // Preserve the symlink as a symlink
                 if let Some(ref target) = source.symlink_target {
                     // Create symlink using transport (works for both local and SSH)
@@ -523,13 +1000,94 @@ impl<'a, T: Transport> Transferrer<'a, T> {
 
                     Ok(None)
                 } else {
-                    tracing::warn!("Symlink has no target: {}", source.path.display());
+                    output::warn(
+                        WarningCategory::Symlink,
+                        Some(source.path.as_path()),
+                        format!("Symlink has no target: {}", source.path.display()),
+                    );
                     Ok(None)
                 }
             }
         }
     }
 
+    /// Recreate the symlink a `--munge-links` placeholder stands for (`--unmunge-links`)
+    ///
+    /// Returns false if `source` isn't a placeholder. `replace` removes what's
+    /// at `dest_path` first.
+    async fn restore_placeholder(
+        &self,
+        source: &FileEntry,
+        dest_path: &Path,
+        replace: bool,
+    ) -> Result<bool> {
+        if self.symlink_mode != SymlinkMode::Unmunge
+            || source.is_dir
+            || source.is_symlink
+            || source.size < munge::MAGIC.len() as u64
+            || source.size > munge::MAX_PLACEHOLDER_SIZE
+        {
+            return Ok(false);
+        }
+        let Some(target) = munge::decode(&self.transport.read_file(&source.path).await?) else {
+            return Ok(false);
+        };
+
+        if replace {
+            self.transport.remove(dest_path, false).await?;
+        }
+        self.transport.create_symlink(&target, dest_path).await?;
+        tracing::debug!(
+            "Restored symlink from placeholder file: {} -> {}",
+            dest_path.display(),
+            target.display()
+        );
+        Ok(true)
+    }
+
+    /// Copy the file a symlink points to in place of the symlink
+    async fn copy_symlink_target(
+        &self,
+        source: &FileEntry,
+        target: &Path,
+        dest_path: &Path,
+    ) -> Result<Option<TransferResult>> {
+        // Check if target exists
+        if !target.exists() {
+            output::warn(
+                WarningCategory::Symlink,
+                Some(source.path.as_path()),
+                format!(
+                    "Symlink target does not exist: {} -> {}",
+                    source.path.display(),
+                    target.display()
+                ),
+            );
+            return Ok(None);
+        }
+
+        // Copy the target file/directory
+        if target.is_dir() {
+            output::warn(
+                WarningCategory::Symlink,
+                Some(source.path.as_path()),
+                format!(
+                    "Skipping symlink to directory (not supported in follow mode): {}",
+                    source.path.display()
+                ),
+            );
+            Ok(None)
+        } else {
+            let result = self.copy_file(target, dest_path).await?;
+            tracing::debug!(
+                "Followed symlink and copied target: {} -> {}",
+                target.display(),
+                dest_path.display()
+            );
+            Ok(Some(result))
+        }
+    }
+
     /// Format file size in human-readable format
     fn format_size(bytes: u64) -> String {
         const KB: u64 = 1024;
@@ -583,6 +1141,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -628,6 +1189,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -669,6 +1233,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -723,6 +1290,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -779,6 +1349,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -835,6 +1408,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -857,6 +1433,142 @@ mod tests {
         assert!(!dest_path.exists());
     }
 
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_symlink_safe_and_copy_unsafe() {
+        let source_dir = TempDir::new().unwrap();
+        let outside_dir = TempDir::new().unwrap();
+
+        fs::write(source_dir.path().join("inside.txt"), "inside").unwrap();
+        let outside_file = outside_dir.path().join("outside.txt");
+        fs::write(&outside_file, "outside").unwrap();
+        std::os::unix::fs::symlink("inside.txt", source_dir.path().join("safe")).unwrap();
+        std::os::unix::fs::symlink(&outside_file, source_dir.path().join("unsafe")).unwrap();
+
+        let link_entry = |name: &str, target: &Path| FileEntry {
+            path: source_dir.path().join(name),
+            relative_path: PathBuf::from(name),
+            size: 0,
+            modified: SystemTime::now(),
+            is_dir: false,
+            is_symlink: true,
+            symlink_target: Some(target.to_path_buf()),
+            is_sparse: false,
+            allocated_size: 0,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        let safe = link_entry("safe", Path::new("inside.txt"));
+        let unsafe_link = link_entry("unsafe", &outside_file);
+
+        let transport = LocalTransport::new();
+        for mode in [SymlinkMode::Safe, SymlinkMode::CopyUnsafe] {
+            let dest_dir = TempDir::new().unwrap();
+            let hardlink_map = Arc::new(Mutex::new(std::collections::HashMap::new()));
+            let transferrer = Transferrer::new(
+                &transport,
+                false,
+                false,
+                mode,
+                false,
+                false,
+                false,
+                false,
+                hardlink_map,
+            );
+            transferrer
+                .create(&safe, &dest_dir.path().join("safe"))
+                .await
+                .unwrap();
+            transferrer
+                .create(&unsafe_link, &dest_dir.path().join("unsafe"))
+                .await
+                .unwrap();
+
+            // Links within the tree are kept either way
+            assert_eq!(
+                fs::read_link(dest_dir.path().join("safe")).unwrap(),
+                PathBuf::from("inside.txt")
+            );
+            let unsafe_dest = dest_dir.path().join("unsafe");
+            if mode == SymlinkMode::Safe {
+                assert!(fs::symlink_metadata(&unsafe_dest).is_err());
+            } else {
+                assert!(!fs::symlink_metadata(&unsafe_dest).unwrap().is_symlink());
+                assert_eq!(fs::read_to_string(&unsafe_dest).unwrap(), "outside");
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_munge_and_unmunge_links() {
+        let source_dir = TempDir::new().unwrap();
+        let fat_dir = TempDir::new().unwrap();
+        let restored_dir = TempDir::new().unwrap();
+        std::os::unix::fs::symlink("../target.txt", source_dir.path().join("link")).unwrap();
+
+        let entry = |path: PathBuf, is_symlink: bool| FileEntry {
+            size: fs::symlink_metadata(&path).unwrap().len(),
+            relative_path: PathBuf::from("link"),
+            modified: SystemTime::now(),
+            is_dir: false,
+            is_symlink,
+            symlink_target: is_symlink.then(|| fs::read_link(&path).unwrap()),
+            is_sparse: false,
+            allocated_size: 0,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+            path,
+        };
+        let transport = LocalTransport::new();
+        let transferrer = |mode| {
+            Transferrer::new(
+                &transport,
+                false,
+                false,
+                mode,
+                false,
+                false,
+                false,
+                false,
+                Arc::new(Mutex::new(std::collections::HashMap::new())),
+            )
+        };
+
+        // Out: the symlink becomes a plain file holding its target
+        let placeholder = fat_dir.path().join("link");
+        transferrer(SymlinkMode::Munge)
+            .create(&entry(source_dir.path().join("link"), true), &placeholder)
+            .await
+            .unwrap();
+        assert!(fs::symlink_metadata(&placeholder).unwrap().is_file());
+        assert_eq!(fs::read(&placeholder).unwrap(), b"!<symlink>../target.txt");
+
+        // Back: the placeholder becomes the symlink again
+        let restored = restored_dir.path().join("link");
+        transferrer(SymlinkMode::Unmunge)
+            .create(&entry(placeholder, false), &restored)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_link(&restored).unwrap(),
+            PathBuf::from("../target.txt")
+        );
+    }
+
     #[tokio::test]
     #[cfg(unix)] // xattrs work differently on different platforms
     async fn test_xattr_preservation() {
@@ -894,6 +1606,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -923,6 +1638,60 @@ mod tests {
         assert_eq!(xattr2, b"value2");
     }
 
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_directory_xattr_preservation() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+
+        let source_subdir = source_dir.path().join("subdir");
+        fs::create_dir(&source_subdir).unwrap();
+
+        let dir_entry = FileEntry {
+            path: source_subdir.clone(),
+            relative_path: PathBuf::from("subdir"),
+            size: 0,
+            modified: SystemTime::now(),
+            is_dir: true,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 0,
+            xattrs: Some(
+                [("user.project".to_string(), b"sy".to_vec())]
+                    .into_iter()
+                    .collect(),
+            ),
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+
+        let transport = LocalTransport::new();
+        let hardlink_map = Arc::new(Mutex::new(std::collections::HashMap::new()));
+        let transferrer = Transferrer::new(
+            &transport,
+            false,
+            false,
+            SymlinkMode::Preserve,
+            true,
+            false,
+            false,
+            false,
+            hardlink_map,
+        ); // preserve_xattrs = true
+        let dest_path = dest_dir.path().join("subdir");
+        transferrer.create(&dir_entry, &dest_path).await.unwrap();
+
+        assert!(dest_path.is_dir());
+        let value = xattr::get(&dest_path, "user.project").unwrap().unwrap();
+        assert_eq!(value, b"sy");
+    }
+
     #[tokio::test]
     #[cfg(unix)]
     async fn test_xattr_not_preserved_without_flag() {
@@ -954,6 +1723,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1018,6 +1790,9 @@ mod tests {
             nlink: 2,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let link_entry = FileEntry {
@@ -1035,6 +1810,9 @@ mod tests {
             nlink: 2,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         // Transfer with preserve_hardlinks = true
@@ -1126,6 +1904,9 @@ mod tests {
             nlink: 2,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let link_entry = FileEntry {
@@ -1143,6 +1924,9 @@ mod tests {
             nlink: 2,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         // Transfer with preserve_hardlinks = false
@@ -1226,6 +2010,9 @@ mod tests {
             nlink: 3,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let entry2 = FileEntry {
@@ -1243,6 +2030,9 @@ mod tests {
             nlink: 3,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let entry3 = FileEntry {
@@ -1260,6 +2050,9 @@ mod tests {
             nlink: 3,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         // Transfer with preserve_hardlinks = true
@@ -1328,6 +2121,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1384,6 +2180,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1467,6 +2266,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1518,6 +2320,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1565,6 +2370,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1612,6 +2420,9 @@ mod tests {
             nlink: 1,
             acls: Some(acls_text.into_bytes()),
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1659,6 +2470,9 @@ mod tests {
             nlink: 1,
             acls: Some(acls_text.into_bytes()),
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1705,6 +2519,9 @@ mod tests {
             nlink: 1,
             acls: Some(Vec::new()), // Empty ACLs
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1766,6 +2583,9 @@ mod tests {
             nlink: 1,
             acls: Some(acls_bytes),
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         // Transfer with preserve_acls = true
@@ -1824,6 +2644,9 @@ mod tests {
             nlink: 1,
             acls: Some(acls_text.into_bytes()),
             bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1888,6 +2711,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: Some(flags),
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1953,6 +2779,9 @@ mod tests {
             nlink: 1,
             acls: None,
             bsd_flags: Some(flags),
+            atime: None,
+            crtime: None,
+            perms: None,
         };
 
         let transport = LocalTransport::new();
@@ -1982,4 +2811,268 @@ mod tests {
             "Hidden flag should not be preserved when preserve_flags=false"
         );
     }
+
+    #[tokio::test]
+    #[cfg(target_os = "linux")]
+    async fn test_immutable_destination() {
+        const FS_IMMUTABLE_FL: u32 = 0x10;
+
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+        let source_file = source_dir.path().join("test.txt");
+        let dest_path = dest_dir.path().join("test.txt");
+        fs::write(&source_file, "new content").unwrap();
+        fs::write(&dest_path, "old").unwrap();
+
+        // Needs root or CAP_LINUX_IMMUTABLE, and a filesystem with inode flags
+        if !fs_util::may_clear_immutable()
+            || fs_util::set_immutable_flags(&dest_path, FS_IMMUTABLE_FL).is_err()
+            || fs_util::immutable_flags(&dest_path) == 0
+        {
+            return;
+        }
+
+        let file_entry = FileEntry {
+            path: source_file,
+            relative_path: PathBuf::from("test.txt"),
+            size: 11,
+            modified: SystemTime::now(),
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 11,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+        let transport = LocalTransport::new();
+        let transferrer = |mode| {
+            Transferrer::new(
+                &transport,
+                false,
+                false,
+                SymlinkMode::Preserve,
+                false,
+                false,
+                false,
+                false,
+                Arc::new(Mutex::new(std::collections::HashMap::new())),
+            )
+            .with_immutable_mode(mode)
+        };
+
+        // Refused with a clear error by default
+        let result = transferrer(ImmutableMode::Report)
+            .update(&file_entry, &dest_path)
+            .await;
+        assert!(matches!(result, Err(SyncError::Immutable { .. })));
+        assert_eq!(fs::read_to_string(&dest_path).unwrap(), "old");
+
+        // Cleared for the update and restored after with --force-change
+        transferrer(ImmutableMode::ForceChange)
+            .update(&file_entry, &dest_path)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&dest_path).unwrap(), "new content");
+        assert_eq!(fs_util::immutable_flags(&dest_path), FS_IMMUTABLE_FL);
+
+        transferrer(ImmutableMode::ForceChange)
+            .delete(&dest_path, false)
+            .await
+            .unwrap();
+        assert!(!dest_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_atime_preservation() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+
+        let source_file = source_dir.path().join("test.txt");
+        fs::write(&source_file, "test content").unwrap();
+        let atime = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+
+        let file_entry = FileEntry {
+            path: source_file.clone(),
+            relative_path: PathBuf::from("test.txt"),
+            size: 12,
+            modified: SystemTime::now(),
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 12,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: Some(atime),
+            crtime: None,
+            perms: None,
+        };
+
+        let transport = LocalTransport::new();
+        let dest_path = dest_dir.path().join("test.txt");
+        for preserve_atimes in [false, true] {
+            let hardlink_map = Arc::new(Mutex::new(std::collections::HashMap::new()));
+            let transferrer = Transferrer::new(
+                &transport,
+                false,
+                false,
+                SymlinkMode::Preserve,
+                false,
+                false,
+                false,
+                false,
+                hardlink_map,
+            )
+            .with_preserve_times(preserve_atimes, false);
+            transferrer.create(&file_entry, &dest_path).await.unwrap();
+
+            let accessed = fs::metadata(&dest_path).unwrap().accessed().unwrap();
+            assert_eq!(accessed == atime, preserve_atimes);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_perms_preservation() {
+        use crate::owner::FilePerms;
+        use std::os::unix::fs::MetadataExt;
+
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+
+        let source_file = source_dir.path().join("test.txt");
+        fs::write(&source_file, "test content").unwrap();
+        let metadata = fs::metadata(&source_file).unwrap();
+        let (uid, gid) = (metadata.uid(), metadata.gid());
+
+        let entry = |path: PathBuf, is_dir: bool, mode: u32| FileEntry {
+            relative_path: PathBuf::from(path.file_name().unwrap()),
+            path,
+            size: 12,
+            modified: SystemTime::now(),
+            is_dir,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 12,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: Some(FilePerms { mode, uid, gid }),
+        };
+
+        let transport = LocalTransport::new();
+        let hardlink_map = Arc::new(Mutex::new(std::collections::HashMap::new()));
+        let transferrer = Transferrer::new(
+            &transport,
+            false,
+            false,
+            SymlinkMode::Preserve,
+            false,
+            false,
+            false,
+            false,
+            hardlink_map,
+        )
+        .with_preserve_perms(PreservePerms {
+            mode: true,
+            owner: true,
+            group: true,
+        });
+
+        let dest_file = dest_dir.path().join("test.txt");
+        transferrer
+            .create(&entry(source_file, false, 0o604), &dest_file)
+            .await
+            .unwrap();
+        let metadata = fs::metadata(&dest_file).unwrap();
+        assert_eq!(metadata.mode() & 0o7777, 0o604);
+        assert_eq!(metadata.gid(), gid);
+
+        // Read-only directories stay writable by their owner for their contents
+        let dest_subdir = dest_dir.path().join("sub");
+        transferrer
+            .create(
+                &entry(source_dir.path().join("sub"), true, 0o555),
+                &dest_subdir,
+            )
+            .await
+            .unwrap();
+        assert_eq!(fs::metadata(&dest_subdir).unwrap().mode() & 0o7777, 0o755);
+    }
+
+    #[tokio::test]
+    async fn test_source_changed_during_transfer() {
+        let source_dir = TempDir::new().unwrap();
+        let dest_dir = TempDir::new().unwrap();
+
+        let source_file = source_dir.path().join("test.txt");
+        fs::write(&source_file, "test content").unwrap();
+
+        let file_entry = FileEntry {
+            path: source_file.clone(),
+            relative_path: PathBuf::from("test.txt"),
+            size: 12,
+            modified: SystemTime::now(),
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 12,
+            xattrs: None,
+            inode: None,
+            nlink: 1,
+            acls: None,
+            bsd_flags: None,
+            atime: None,
+            crtime: None,
+            perms: None,
+        };
+
+        let transport = LocalTransport::new();
+        let hardlink_map = Arc::new(Mutex::new(std::collections::HashMap::new()));
+        let transferrer = Transferrer::new(
+            &transport,
+            false,
+            false,
+            SymlinkMode::Preserve,
+            false,
+            false,
+            false,
+            false,
+            hardlink_map,
+        )
+        .with_source_check(true);
+
+        // Unchanged sources copy normally
+        let dest_path = dest_dir.path().join("test.txt");
+        transferrer.create(&file_entry, &dest_path).await.unwrap();
+
+        // Simulate a write landing while the file was read
+        let before = transferrer.source_snapshot(&file_entry);
+        assert!(before.is_some());
+        fs::write(&source_file, "test content, appended").unwrap();
+        assert!(matches!(
+            transferrer.check_source_unchanged(&file_entry, before),
+            Err(SyncError::SourceChanged { .. })
+        ));
+
+        // Without the check nothing is stat'ed
+        let transferrer = transferrer.with_source_check(false);
+        assert!(transferrer.source_snapshot(&file_entry).is_none());
+    }
 }
@@ -0,0 +1,387 @@
+use crate::error::{Result, SyncError};
+use crate::transport::Transport;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Trash directory in the destination root, unless --trash-dir says otherwise
+pub const TRASH_DIR: &str = ".sy-trash";
+
+/// Where `--trash` moves deleted entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrashTarget {
+    /// The user's trash (XDG trash, or ~/.Trash on macOS), for local
+    /// destinations without --trash-dir
+    Os,
+    /// A directory with a timestamped subfolder per sync; relative paths are
+    /// taken from the destination root
+    Dir(PathBuf),
+}
+
+/// Trash directory for `dest_root`: `dir` if given (relative to the root), or
+/// `.sy-trash` in the root
+pub fn trash_dir(dest_root: &Path, dir: Option<&Path>) -> PathBuf {
+    dest_root.join(dir.unwrap_or(Path::new(TRASH_DIR)))
+}
+
+/// Whether `path` is in the trash of `dest_root`: the configured trash
+/// directory, or `.sy-trash` left by earlier syncs
+pub fn is_trash(dest_root: &Path, target: Option<&TrashTarget>, path: &Path) -> bool {
+    let configured = match target {
+        Some(TrashTarget::Dir(dir)) => Some(dir.as_path()),
+        _ => None,
+    };
+    path.starts_with(trash_dir(dest_root, None))
+        || path.starts_with(trash_dir(dest_root, configured))
+}
+
+/// Moves the entries one sync deletes into the trash
+///
+/// Entries that can't go to the user's trash (no trash, or it's on another
+/// filesystem) go to `.sy-trash` in the destination instead, so deleting
+/// never turns into copying.
+pub struct Trash {
+    dest_root: PathBuf,
+    os: bool,
+    /// This sync's subfolder of the trash directory
+    run_dir: PathBuf,
+}
+
+impl Trash {
+    pub fn new(dest_root: &Path, target: &TrashTarget) -> Self {
+        let dir = match target {
+            TrashTarget::Os => trash_dir(dest_root, None),
+            TrashTarget::Dir(dir) => trash_dir(dest_root, Some(dir)),
+        };
+        let stamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        Self {
+            dest_root: dest_root.to_path_buf(),
+            os: *target == TrashTarget::Os,
+            run_dir: dir.join(stamp),
+        }
+    }
+
+    /// Move `path` (in the destination) to the trash, returning where it went
+    pub async fn put<T: Transport + ?Sized>(&self, transport: &T, path: &Path) -> Result<PathBuf> {
+        if self.os {
+            match move_to_os_trash(path) {
+                Ok(trashed) => return Ok(trashed),
+                Err(e) => tracing::debug!(
+                    "Can't move {} to the trash ({}), using {}",
+                    path.display(),
+                    e,
+                    self.run_dir.display()
+                ),
+            }
+        }
+
+        let relative = path.strip_prefix(&self.dest_root).unwrap_or(path);
+        let trashed = self.run_dir.join(relative);
+        transport.rename(path, &trashed).await?;
+        Ok(trashed)
+    }
+}
+
+/// Move `path` to the user's trash, returning where it went
+///
+/// Fails on other platforms, when there is no trash, and when the trash is on
+/// another filesystem than `path`.
+pub fn move_to_os_trash(path: &Path) -> std::io::Result<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        let trash = dirs::home_dir()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "No home directory"))?
+            .join(".Trash");
+        let name = path
+            .file_name()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "No file name"))?;
+        let trashed = (1..)
+            .map(|n| trash.join(numbered(name, n)))
+            .find(|candidate| std::fs::symlink_metadata(candidate).is_err())
+            .expect("unbounded range");
+        std::fs::rename(path, &trashed)?;
+        Ok(trashed)
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let trash = dirs::data_dir()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "No data directory"))?
+            .join("Trash");
+        move_to_xdg_trash(&trash, path)
+    }
+    #[cfg(not(unix))]
+    {
+        Err(std::io::Error::new(
+            ErrorKind::Unsupported,
+            format!("No trash support for {}", path.display()),
+        ))
+    }
+}
+
+/// Move `path` into the XDG trash at `trash`, per the FreeDesktop.org spec
+///
+/// The `.trashinfo` file is created first (exclusively), which claims the
+/// name; file managers read it to restore the entry.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn move_to_xdg_trash(trash: &Path, path: &Path) -> std::io::Result<PathBuf> {
+    use std::io::Write;
+
+    let path = std::path::absolute(path)?;
+    let name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "No file name"))?;
+    let files = trash.join("files");
+    let info = trash.join("info");
+    std::fs::create_dir_all(&files)?;
+    std::fs::create_dir_all(&info)?;
+
+    for n in 1.. {
+        let name = numbered(name, n);
+        let info_path = info.join(format!("{}.trashinfo", name.to_string_lossy()));
+        let mut info_file = match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        let trashed = files.join(&name);
+        if std::fs::symlink_metadata(&trashed).is_ok() {
+            // Left behind without its info file; keep both
+            drop(info_file);
+            std::fs::remove_file(&info_path)?;
+            continue;
+        }
+
+        let written = write!(
+            info_file,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(&path),
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+        )
+        .and_then(|()| std::fs::rename(&path, &trashed));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&info_path);
+            return Err(e);
+        }
+        return Ok(trashed);
+    }
+    unreachable!("unbounded range")
+}
+
+/// `name`, then `name 2`, `name 3`, ... for later tries at a free name
+#[cfg(unix)]
+fn numbered(name: &std::ffi::OsStr, n: usize) -> std::ffi::OsString {
+    let mut numbered = name.to_os_string();
+    if n > 1 {
+        numbered.push(format!(" {}", n));
+    }
+    numbered
+}
+
+/// `path` with bytes outside the URI unreserved set (and `/`) percent-encoded,
+/// as `.trashinfo` files store it
+#[cfg(all(unix, not(target_os = "macos")))]
+fn percent_encode(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// One sync's worth of trashed entries (a subfolder of the trash directory)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrashRun {
+    /// Subfolder name, the time the sync started
+    pub name: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Runs in the trash directory `dir`, oldest first
+pub async fn list<T: Transport + ?Sized>(transport: &T, dir: &Path) -> Result<Vec<TrashRun>> {
+    if !transport.exists(dir).await? {
+        return Ok(Vec::new());
+    }
+
+    let mut runs: Vec<TrashRun> = Vec::new();
+    for entry in transport.scan_destination(dir).await? {
+        let mut components = entry.relative_path.components();
+        let Some(name) = components.next() else {
+            continue;
+        };
+        let name = name.as_os_str().to_string_lossy();
+        let index = match runs.iter().position(|run| run.name == name) {
+            Some(index) => index,
+            None => {
+                runs.push(TrashRun {
+                    name: name.into_owned(),
+                    files: 0,
+                    bytes: 0,
+                });
+                runs.len() - 1
+            }
+        };
+        if !entry.is_dir && components.next().is_some() {
+            runs[index].files += 1;
+            runs[index].bytes += entry.size;
+        }
+    }
+    runs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(runs)
+}
+
+/// What `restore` moved back, and what it left in the trash
+#[derive(Debug, Default, Serialize)]
+pub struct RestoreReport {
+    pub run: String,
+    pub restored: Vec<PathBuf>,
+    /// Entries left in the trash because the destination has them again
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Move the entries of trash run `run` (the newest if None) in `dir` back into
+/// `dest_root`
+///
+/// Entries the destination has again are left in the trash; the run's
+/// subfolder is removed once nothing is left in it. With `dry_run`, nothing
+/// is moved.
+pub async fn restore<T: Transport + ?Sized>(
+    transport: &T,
+    dest_root: &Path,
+    dir: &Path,
+    run: Option<&str>,
+    dry_run: bool,
+) -> Result<RestoreReport> {
+    let runs = list(transport, dir).await?;
+    let run = match run {
+        Some(name) => runs.iter().find(|r| r.name == name),
+        None => runs.last(),
+    }
+    .ok_or_else(|| {
+        SyncError::Io(std::io::Error::new(
+            ErrorKind::NotFound,
+            match run {
+                Some(name) => format!("No trash run {} in {}", name, dir.display()),
+                None => format!("Nothing in the trash at {}", dir.display()),
+            },
+        ))
+    })?;
+    let run_dir = dir.join(&run.name);
+
+    let mut entries = transport.scan_destination(&run_dir).await?;
+    entries.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    let mut report = RestoreReport {
+        run: run.name.clone(),
+        ..Default::default()
+    };
+    // Directories moved back whole, whose entries went with them
+    let mut moved: Vec<PathBuf> = Vec::new();
+    for entry in entries {
+        let relative = entry.relative_path;
+        if moved.iter().any(|dir| relative.starts_with(dir)) {
+            continue;
+        }
+        let target = dest_root.join(&relative);
+        if transport.exists(&target).await? {
+            // Directories that exist again get their trashed entries merged back
+            if !entry.is_dir {
+                report.skipped.push(relative);
+            }
+            continue;
+        }
+        if !dry_run {
+            transport.rename(&run_dir.join(&relative), &target).await?;
+        }
+        if entry.is_dir {
+            moved.push(relative.clone());
+        }
+        report.restored.push(relative);
+    }
+
+    if !dry_run && report.skipped.is_empty() {
+        transport.remove(&run_dir, true).await?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::local::LocalTransport;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_put_and_restore() {
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path();
+        fs::create_dir_all(dest.join("docs/old")).unwrap();
+        fs::write(dest.join("docs/old/a.txt"), "a").unwrap();
+        fs::write(dest.join("docs/b.txt"), "b").unwrap();
+        fs::write(dest.join("c.txt"), "c").unwrap();
+
+        let transport = LocalTransport::new();
+        let trash = Trash::new(dest, &TrashTarget::Dir(PathBuf::from(TRASH_DIR)));
+        for path in ["docs/old", "docs/b.txt", "c.txt"] {
+            let trashed = trash.put(&transport, &dest.join(path)).await.unwrap();
+            assert!(trashed.starts_with(dest.join(TRASH_DIR)));
+            assert!(!dest.join(path).exists());
+        }
+
+        let dir = trash_dir(dest, None);
+        let runs = list(&transport, &dir).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!((runs[0].files, runs[0].bytes), (3, 3));
+
+        // c.txt came back in the meantime, so it stays in the trash
+        fs::write(dest.join("c.txt"), "new").unwrap();
+        let report = restore(&transport, dest, &dir, None, false).await.unwrap();
+        assert_eq!(
+            report.restored,
+            vec![PathBuf::from("docs/b.txt"), PathBuf::from("docs/old")]
+        );
+        assert_eq!(report.skipped, vec![PathBuf::from("c.txt")]);
+        assert_eq!(
+            fs::read_to_string(dest.join("docs/old/a.txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(fs::read_to_string(dest.join("c.txt")).unwrap(), "new");
+        assert!(dir.join(&report.run).join("c.txt").exists());
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn test_move_to_xdg_trash() {
+        let temp = TempDir::new().unwrap();
+        let trash = temp.path().join("Trash");
+        for content in ["first", "second"] {
+            let path = temp.path().join("my file.txt");
+            fs::write(&path, content).unwrap();
+            move_to_xdg_trash(&trash, &path).unwrap();
+            assert!(!path.exists());
+        }
+
+        assert_eq!(
+            fs::read_to_string(trash.join("files/my file.txt")).unwrap(),
+            "first"
+        );
+        assert_eq!(
+            fs::read_to_string(trash.join("files/my file.txt 2")).unwrap(),
+            "second"
+        );
+        let info = fs::read_to_string(trash.join("info/my file.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("my%20file.txt\nDeletionDate="));
+    }
+}
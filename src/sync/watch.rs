@@ -86,6 +86,9 @@ impl<T: Transport + 'static> WatchMode<T> {
                         tracing::info!("Detected {} changes, syncing...", pending_changes.len());
                         println!("📝 Changes detected, syncing...");
 
+                        let changed = pending_changes.iter().flat_map(|event| &event.paths);
+                        self.engine.note_changes(&self.source, changed);
+
                         match self.engine.sync(&self.source, &self.destination).await {
                             Ok(_) => {
                                 println!("✓ Sync complete\n");
@@ -123,6 +126,7 @@ impl<T: Transport + 'static> WatchMode<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sync::SyncOptions;
     use crate::transport::local::LocalTransport;
     use std::fs;
     use tempfile::TempDir;
@@ -132,6 +136,7 @@ mod tests {
         let temp = TempDir::new().unwrap();
         let source = temp.path().join("src");
         let destination = temp.path().join("dst");
+        let state_dir = temp.path().join("state");
         fs::create_dir_all(&source).unwrap();
         fs::create_dir_all(&destination).unwrap();
 
@@ -142,7 +147,7 @@ mod tests {
             false,                              // diff_mode
             false,                              // delete
             50,                                 // delete_threshold
-            false,                              // trash
+            None,                               // trash
             false,                              // force_delete
             true,                               // quiet
             10,                                 // parallel
@@ -171,7 +176,9 @@ mod tests {
             false,                              // checksum_db
             false,                              // clear_checksum_db
             false,                              // prune_checksum_db
+            state_dir,                          // state_dir
             false,                              // perf
+            SyncOptions::default(),
         );
 
         let watch_mode = WatchMode::new(
@@ -193,6 +200,7 @@ mod tests {
         let temp = TempDir::new().unwrap();
         let source = temp.path().join("src");
         let destination = temp.path().join("dst");
+        let state_dir = temp.path().join("state");
         fs::create_dir_all(&source).unwrap();
         fs::create_dir_all(&destination).unwrap();
 
@@ -203,7 +211,7 @@ mod tests {
             false, // diff_mode
             false, // delete
             50,    // delete_threshold
-            false, // trash
+            None,  // trash
             false, // force_delete
             true,
             10,
@@ -222,17 +230,19 @@ mod tests {
             false,
             false,
             false,
-            false, // preserve_flags
-            false, // ignore_times
-            false, // size_only
-            false, // checksum
-            false, // verify_only
-            false, // use_cache
-            false, // clear_cache
-            false, // checksum_db
-            false, // clear_checksum_db
-            false, // prune_checksum_db
-            false, // perf
+            false,     // preserve_flags
+            false,     // ignore_times
+            false,     // size_only
+            false,     // checksum
+            false,     // verify_only
+            false,     // use_cache
+            false,     // clear_cache
+            false,     // checksum_db
+            false,     // clear_checksum_db
+            false,     // prune_checksum_db
+            state_dir, // state_dir
+            false,     // perf
+            SyncOptions::default(),
         );
 
         let watch_mode = WatchMode::new(engine, source, destination, Duration::from_millis(500));
@@ -0,0 +1,219 @@
+//! OpenTelemetry (OTLP) export for `--otlp-endpoint`
+//!
+//! Spans (one `sync` root per run with scan, plan, transfer and verify
+//! children) are exported through a tracing layer, and every finished run is
+//! counted by [`record_run`]. Export needs the `otel` cargo feature; without
+//! it, or without an endpoint, [`record_run`] is a no-op.
+
+use crate::error::{Result, SyncError};
+use crate::sync::SyncStats;
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "otel")]
+use opentelemetry::metrics::{Counter, Histogram, MeterProvider as _};
+#[cfg(feature = "otel")]
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otel")]
+use opentelemetry::KeyValue;
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::SdkTracerProvider, Resource};
+#[cfg(feature = "otel")]
+use std::sync::OnceLock;
+
+/// Instruments for per-run metrics, set once by [`init`]
+#[cfg(feature = "otel")]
+static RUN_METRICS: OnceLock<RunMetrics> = OnceLock::new();
+
+#[cfg(feature = "otel")]
+struct RunMetrics {
+    runs: Counter<u64>,
+    failures: Counter<u64>,
+    files: Counter<u64>,
+    bytes: Counter<u64>,
+    errors: Counter<u64>,
+    duration: Histogram<f64>,
+}
+
+/// OTLP exporters for one process; keep it alive until exit (dropping it flushes)
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    tracer_provider: SdkTracerProvider,
+    #[cfg(feature = "otel")]
+    meter_provider: SdkMeterProvider,
+}
+
+/// Signal URL under an OTLP/HTTP base endpoint (`http://collector:4318`)
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+fn signal_url(endpoint: &str, signal: &str) -> String {
+    format!("{}/v1/{}", endpoint.trim_end_matches('/'), signal)
+}
+
+#[cfg(feature = "otel")]
+fn export_error(e: impl std::fmt::Display) -> SyncError {
+    SyncError::Config(format!("OTLP export: {}", e))
+}
+
+/// Set up span and metric export to an OTLP/HTTP collector at `endpoint`
+#[cfg(feature = "otel")]
+pub fn init(endpoint: &str) -> Result<Telemetry> {
+    let resource = Resource::builder()
+        .with_service_name("sy")
+        .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+        .build();
+
+    let span_exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(signal_url(endpoint, "traces"))
+        .build()
+        .map_err(export_error)?;
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_resource(resource.clone())
+        .with_batch_exporter(span_exporter)
+        .build();
+
+    let metric_exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(signal_url(endpoint, "metrics"))
+        .build()
+        .map_err(export_error)?;
+    let meter_provider = SdkMeterProvider::builder()
+        .with_resource(resource)
+        .with_periodic_exporter(metric_exporter)
+        .build();
+
+    let meter = meter_provider.meter("sy");
+    let _ = RUN_METRICS.set(RunMetrics {
+        runs: meter
+            .u64_counter("sy.sync.runs")
+            .with_description("Completed sync runs")
+            .build(),
+        failures: meter
+            .u64_counter("sy.sync.failures")
+            .with_description("Sync runs that failed or finished with file errors")
+            .build(),
+        files: meter
+            .u64_counter("sy.sync.files")
+            .with_description("Files created, updated or deleted, by action")
+            .build(),
+        bytes: meter
+            .u64_counter("sy.sync.bytes")
+            .with_description("File bytes written at the destination")
+            .with_unit("By")
+            .build(),
+        errors: meter
+            .u64_counter("sy.sync.errors")
+            .with_description("Per-file errors")
+            .build(),
+        duration: meter
+            .f64_histogram("sy.sync.duration")
+            .with_description("Wall-clock time of a sync run")
+            .with_unit("s")
+            .build(),
+    });
+
+    Ok(Telemetry {
+        tracer_provider,
+        meter_provider,
+    })
+}
+
+/// Without the `otel` feature there is nothing to export to
+#[cfg(not(feature = "otel"))]
+pub fn init(_endpoint: &str) -> Result<Telemetry> {
+    Err(SyncError::Config(
+        "--otlp-endpoint requires sy built with `--features otel`".to_string(),
+    ))
+}
+
+impl Telemetry {
+    /// Tracing layer exporting sy's spans
+    #[cfg(feature = "otel")]
+    pub fn layer<S>(&self) -> impl tracing_subscriber::Layer<S>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        use tracing_subscriber::{filter::Targets, Layer as _};
+
+        tracing_opentelemetry::layer()
+            .with_tracer(self.tracer_provider.tracer("sy"))
+            .with_filter(Targets::new().with_target("sy", tracing::Level::TRACE))
+    }
+}
+
+/// Flushes buffered spans and the last metrics
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        {
+            if let Err(e) = self.tracer_provider.shutdown() {
+                tracing::warn!("Failed to flush OTLP spans: {}", e);
+            }
+            if let Err(e) = self.meter_provider.shutdown() {
+                tracing::warn!("Failed to flush OTLP metrics: {}", e);
+            }
+        }
+    }
+}
+
+/// Count one finished sync run (or a failed one, with `stats` of `None`)
+pub fn record_run(
+    destination: &Path,
+    stats: Option<&SyncStats>,
+    duration: Duration,
+    dry_run: bool,
+) {
+    #[cfg(feature = "otel")]
+    {
+        let Some(metrics) = RUN_METRICS.get() else {
+            return;
+        };
+        let attributes = [
+            KeyValue::new("destination", destination.display().to_string()),
+            KeyValue::new("dry_run", dry_run),
+        ];
+
+        metrics.runs.add(1, &attributes);
+        metrics.duration.record(duration.as_secs_f64(), &attributes);
+        let Some(stats) = stats else {
+            metrics.failures.add(1, &attributes);
+            return;
+        };
+        if !stats.errors.is_empty() {
+            metrics.failures.add(1, &attributes);
+        }
+
+        for (action, count) in [
+            ("created", stats.files_created),
+            ("updated", stats.files_updated),
+            ("deleted", stats.files_deleted),
+        ] {
+            let mut attributes = attributes.to_vec();
+            attributes.push(KeyValue::new("action", action));
+            metrics.files.add(count as u64, &attributes);
+        }
+        metrics.bytes.add(stats.bytes_transferred, &attributes);
+        metrics.errors.add(stats.errors.len() as u64, &attributes);
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = (destination, stats, duration, dry_run);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_url() {
+        assert_eq!(
+            signal_url("http://collector:4318", "traces"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            signal_url("http://collector:4318/", "metrics"),
+            "http://collector:4318/v1/metrics"
+        );
+    }
+}
@@ -1,5 +1,40 @@
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 
+/// Appended to a destination path to name the file it is written to first
+pub const TEMP_SUFFIX: &str = ".sy-tmp";
+
+/// Temp file a new version of `dest` is written to, next to it
+///
+/// Being in the same directory keeps the final rename on one filesystem.
+pub fn temp_path(dest: &Path) -> PathBuf {
+    let mut name = OsString::from(dest.as_os_str());
+    name.push(TEMP_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Write `dest` through `write`, so a failed write never leaves a partial file
+///
+/// `write` gets the path to write to: a temp file that is renamed over `dest`
+/// once it succeeds, or `dest` itself when `inplace` (`--inplace`). The temp
+/// file is removed if `write` fails.
+pub fn write_atomically<T, E: From<std::io::Error>>(
+    dest: &Path,
+    inplace: bool,
+    write: impl FnOnce(&Path) -> Result<T, E>,
+) -> Result<T, E> {
+    if inplace {
+        return write(dest);
+    }
+
+    let temp = temp_path(dest);
+    let guard = TempFileGuard::new(&temp);
+    let result = write(&temp)?;
+    std::fs::rename(&temp, dest)?;
+    guard.defuse();
+    Ok(result)
+}
+
 /// RAII guard for temporary files that automatically cleans up on drop.
 ///
 /// This ensures temp files are deleted even if:
@@ -136,4 +171,46 @@ mod tests {
         guard.defuse();
         // Path is cleared after defuse
     }
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(
+            temp_path(Path::new("/data/report.txt")),
+            PathBuf::from("/data/report.txt.sy-tmp")
+        );
+        // Names differing only in extension get different temp files
+        assert_ne!(temp_path(Path::new("a.txt")), temp_path(Path::new("a.md")));
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest = temp_dir.path().join("file.txt");
+        fs::write(&dest, b"old").unwrap();
+
+        // A failed write leaves the old file and no temp file
+        let result = write_atomically(&dest, false, |path| {
+            fs::write(path, b"partial")?;
+            Err::<(), _>(std::io::Error::other("interrupted"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read(&dest).unwrap(), b"old");
+        assert!(!temp_path(&dest).exists());
+
+        write_atomically(&dest, false, |path| {
+            assert_ne!(path, dest);
+            fs::write(path, b"new")
+        })
+        .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"new");
+        assert!(!temp_path(&dest).exists());
+
+        // In place, the destination itself is written
+        write_atomically(&dest, true, |path| {
+            assert_eq!(path, dest);
+            fs::write(path, b"newer")
+        })
+        .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"newer");
+    }
 }
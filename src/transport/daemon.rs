@@ -0,0 +1,777 @@
+use super::{FileInfo, PathStat, TransferResult, Transport, TransportOptions};
+use crate::acl::AclMapPolicy;
+use crate::daemon::{self, Connection, Request, Response, PROTOCOL_VERSION};
+use crate::error::{Result, SyncError};
+use crate::fs_util::{AclsApplied, XattrsApplied};
+use crate::owner::{NamedPerms, PermsUpdate};
+use crate::quic;
+use crate::sync::lock::LockInfo;
+use crate::sync::scanner::{FileEntry, ScanError};
+use crate::temp_file;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+type Stream = Box<dyn Connection>;
+
+/// How new connections to the daemon are made
+enum Dialer {
+    /// A TCP connection to `host:port`
+    Tcp(String),
+    /// Another stream on one QUIC connection (`--quic`)
+    Quic(quic::Client),
+}
+
+/// Transport for a module served by `sy --daemon` (`host::module/path`)
+///
+/// Requests go over plain TCP, one at a time per connection; parallel
+/// transfers each take a connection from a pool that grows on demand.
+/// The native protocol has no delta sync, compression, xattrs or ACLs, so
+/// changed files are sent whole. With `--quic`, every pooled connection is
+/// a stream of a single QUIC connection to `sy-remote serve-quic` instead.
+pub struct DaemonTransport {
+    dialer: Dialer,
+    module: String,
+    user: String,
+    secret: Option<String>,
+    max_depth: Option<usize>,
+    options: TransportOptions,
+    /// Authenticated connections waiting for the next request
+    idle: Mutex<Vec<Stream>>,
+    version: String,
+    clock_skew_ms: Option<i64>,
+}
+
+fn unexpected(response: Response) -> SyncError {
+    match response {
+        Response::Error { message } => SyncError::Io(std::io::Error::other(message)),
+        response => SyncError::NetworkError {
+            message: format!("Unexpected response from sy daemon: {:?}", response),
+        },
+    }
+}
+
+impl DaemonTransport {
+    /// Connect to `module` on the daemon at `host:port`
+    ///
+    /// The first connection is made (and authenticated as `user` with
+    /// `secret`) right away, so a wrong module or secret fails before any
+    /// planning. `user` defaults to the local user name.
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        user: Option<&str>,
+        module: &str,
+        secret: Option<String>,
+    ) -> Result<Self> {
+        let dialer = Dialer::Tcp(format!("{}:{}", host, port));
+        Self::connect_with(dialer, user, module, secret).await
+    }
+
+    /// Connect to `module` served by `sy-remote serve-quic` at `host:port`
+    ///
+    /// Like [`connect`](Self::connect), after the QUIC handshake and the
+    /// check of the server's certificate or pre-shared key.
+    pub async fn connect_quic(
+        host: &str,
+        port: u16,
+        user: Option<&str>,
+        module: &str,
+        secret: Option<String>,
+        options: &quic::ClientOptions,
+    ) -> Result<Self> {
+        let client = quic::Client::connect(host, port, options).await?;
+        Self::connect_with(Dialer::Quic(client), user, module, secret).await
+    }
+
+    async fn connect_with(
+        dialer: Dialer,
+        user: Option<&str>,
+        module: &str,
+        secret: Option<String>,
+    ) -> Result<Self> {
+        let mut transport = Self {
+            dialer,
+            module: module.to_string(),
+            user: user.map_or_else(whoami::username, str::to_string),
+            secret,
+            max_depth: None,
+            options: TransportOptions::default(),
+            idle: Mutex::new(Vec::new()),
+            version: String::new(),
+            clock_skew_ms: None,
+        };
+
+        let sent_ms = chrono::Utc::now().timestamp_millis();
+        let (stream, version, clock_ms) = transport.open().await?;
+        let received_ms = chrono::Utc::now().timestamp_millis();
+        let skew_ms = clock_ms - (sent_ms + received_ms) / 2;
+        tracing::debug!("sy daemon {} clock is {} ms ahead", version, skew_ms);
+
+        transport.version = version;
+        transport.clock_skew_ms = Some(skew_ms);
+        transport.release(stream);
+        Ok(transport)
+    }
+
+    /// Limit how deep scans recurse (--max-depth)
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// How files are written (--inplace)
+    pub fn options(mut self, options: TransportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Open and authenticate a new connection
+    ///
+    /// Returns it with the daemon's version and clock.
+    async fn open(&self) -> Result<(Stream, String, i64)> {
+        let mut stream: Stream = match &self.dialer {
+            Dialer::Tcp(address) => {
+                let stream =
+                    TcpStream::connect(address)
+                        .await
+                        .map_err(|e| SyncError::NetworkError {
+                            message: format!(
+                                "Failed to connect to sy daemon at {}: {}",
+                                address, e
+                            ),
+                        })?;
+                stream.set_nodelay(true)?;
+                Box::new(stream)
+            }
+            Dialer::Quic(client) => client.open_stream().await?,
+        };
+
+        let hello = Request::Hello {
+            protocol: PROTOCOL_VERSION,
+            module: self.module.clone(),
+            user: self.user.clone(),
+        };
+        daemon::send(&mut stream, &hello).await?;
+        let (version, nonce) = match daemon::receive(&mut stream).await? {
+            Response::Challenge { version, nonce } => (version, nonce),
+            response => return Err(unexpected(response)),
+        };
+
+        let proof = match (&nonce, &self.secret) {
+            (Some(nonce), Some(secret)) => Some(daemon::proof(secret, nonce).to_hex().to_string()),
+            (Some(_), None) => {
+                return Err(SyncError::Restricted(format!(
+                    "module '{}' needs a secret; set {}",
+                    self.module,
+                    daemon::SECRET_ENV
+                )))
+            }
+            (None, _) => None,
+        };
+        daemon::send(&mut stream, &Request::Auth { proof }).await?;
+        match daemon::receive(&mut stream).await? {
+            Response::Welcome {
+                read_only,
+                clock_ms,
+            } => {
+                if read_only {
+                    tracing::debug!("sy daemon module '{}' is read-only", self.module);
+                }
+                Ok((stream, version, clock_ms))
+            }
+            Response::Error { message } => Err(SyncError::Restricted(message)),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// An idle connection, or a new one if all are busy
+    async fn connection(&self) -> Result<Stream> {
+        let idle = self.idle.lock().unwrap().pop();
+        match idle {
+            Some(stream) => Ok(stream),
+            None => Ok(self.open().await?.0),
+        }
+    }
+
+    /// Return a connection whose last exchange completed
+    fn release(&self, stream: Stream) {
+        self.idle.lock().unwrap().push(stream);
+    }
+
+    /// Send `request` and wait for the daemon's response
+    ///
+    /// A connection that fails mid-exchange is dropped rather than reused.
+    async fn call(&self, request: &Request) -> Result<Response> {
+        let mut stream = self.connection().await?;
+        daemon::send(&mut stream, request).await?;
+        let response = daemon::receive(&mut stream).await?;
+        self.release(stream);
+        match response {
+            Response::Error { .. } => Err(unexpected(response)),
+            response => Ok(response),
+        }
+    }
+
+    async fn call_done(&self, request: &Request) -> Result<()> {
+        match self.call(request).await? {
+            Response::Done => Ok(()),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Upload `size` bytes from `contents` to `dest`
+    async fn put<R: tokio::io::AsyncRead + Unpin>(
+        &self,
+        contents: &mut R,
+        dest: &Path,
+        size: u64,
+        mtime: SystemTime,
+    ) -> Result<()> {
+        let mut stream = self.connection().await?;
+        let request = Request::Put {
+            path: dest.to_path_buf(),
+            size,
+            mtime,
+            inplace: self.options.inplace,
+        };
+        daemon::send(&mut stream, &request).await?;
+        daemon::copy_contents(contents, &mut stream, size).await?;
+        let response = daemon::receive(&mut stream).await?;
+        self.release(stream);
+        match response {
+            Response::Done => Ok(()),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Ask for `source`; returns the connection its contents follow on,
+    /// with their size and the file's mtime
+    async fn get(&self, source: &Path) -> Result<(Stream, u64, SystemTime)> {
+        let mut stream = self.connection().await?;
+        let request = Request::Get {
+            path: source.to_path_buf(),
+        };
+        daemon::send(&mut stream, &request).await?;
+        match daemon::receive(&mut stream).await? {
+            Response::File { size, mtime } => Ok((stream, size, mtime)),
+            response => {
+                self.release(stream);
+                Err(unexpected(response))
+            }
+        }
+    }
+
+    async fn stat(&self, path: &Path) -> Result<PathStat> {
+        self.stat_batch(&[path.to_path_buf()])
+            .await?
+            .pop()
+            .flatten()
+            .ok_or_else(|| {
+                SyncError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{} not found on sy daemon", path.display()),
+                ))
+            })
+    }
+}
+
+#[async_trait]
+impl Transport for DaemonTransport {
+    async fn scan(&self, path: &Path) -> Result<Vec<FileEntry>> {
+        Ok(self.scan_with_errors(path).await?.0)
+    }
+
+    async fn scan_with_errors(&self, path: &Path) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        let request = Request::Scan {
+            path: path.to_path_buf(),
+            max_depth: self.max_depth,
+        };
+        let output = match self.call(&request).await? {
+            Response::Scan(output) => output,
+            response => return Err(unexpected(response)),
+        };
+
+        let ids = output.id_map(self.options.numeric_ids);
+        let entries = output
+            .entries
+            .into_iter()
+            .map(|e| e.into_entry(path, &ids))
+            .collect();
+        let errors = output
+            .errors
+            .into_iter()
+            .map(|e| ScanError {
+                path: PathBuf::from(e.path),
+                error: e.error,
+                special: e.special,
+            })
+            .collect();
+        Ok((entries, errors))
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        let request = Request::Exists {
+            path: path.to_path_buf(),
+        };
+        match self.call(&request).await? {
+            Response::Exists(exists) => Ok(exists),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn metadata(&self, _path: &Path) -> Result<std::fs::Metadata> {
+        Err(SyncError::Io(std::io::Error::other(
+            "sy daemon transport can't return local Metadata; use file_info()",
+        )))
+    }
+
+    async fn file_info(&self, path: &Path) -> Result<FileInfo> {
+        Ok(self.stat(path).await?.file_info())
+    }
+
+    async fn stat_batch(&self, paths: &[PathBuf]) -> Result<Vec<Option<PathStat>>> {
+        let request = Request::StatBatch {
+            paths: paths.to_vec(),
+        };
+        match self.call(&request).await? {
+            Response::Stats(stats) => Ok(stats),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.create_dirs(&[path.to_path_buf()]).await
+    }
+
+    async fn create_dirs(&self, paths: &[PathBuf]) -> Result<()> {
+        self.call_done(&Request::Mkdirs {
+            paths: paths.to_vec(),
+        })
+        .await
+    }
+
+    async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        let mut file = tokio::fs::File::open(source).await.map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to open {}: {}", source.display(), e),
+            ))
+        })?;
+        let metadata = file.metadata().await?;
+        let size = metadata.len();
+        self.put(&mut file, dest, size, metadata.modified()?)
+            .await?;
+        Ok(TransferResult::new(size))
+    }
+
+    async fn remove(&self, path: &Path, is_dir: bool) -> Result<()> {
+        self.call_done(&Request::Remove {
+            path: path.to_path_buf(),
+            is_dir,
+        })
+        .await
+    }
+
+    async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
+        self.call_done(&Request::Hardlink {
+            source: source.to_path_buf(),
+            path: dest.to_path_buf(),
+        })
+        .await
+    }
+
+    async fn create_symlink(&self, target: &Path, dest: &Path) -> Result<()> {
+        self.call_done(&Request::Symlink {
+            target: target.to_path_buf(),
+            path: dest.to_path_buf(),
+        })
+        .await
+    }
+
+    async fn set_xattrs(
+        &self,
+        path: &Path,
+        _xattrs: &HashMap<String, Vec<u8>>,
+    ) -> Result<XattrsApplied> {
+        tracing::debug!(
+            "sy daemon protocol doesn't carry xattrs, skipping {}",
+            path.display()
+        );
+        Ok(XattrsApplied::default())
+    }
+
+    async fn set_acls(
+        &self,
+        path: &Path,
+        _acls: &[u8],
+        _policy: AclMapPolicy,
+    ) -> Result<AclsApplied> {
+        tracing::debug!(
+            "sy daemon protocol doesn't carry ACLs, skipping {}",
+            path.display()
+        );
+        Ok(AclsApplied::default())
+    }
+
+    async fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<SystemTime>,
+        crtime: Option<SystemTime>,
+    ) -> Result<()> {
+        self.call_done(&Request::SetTimes {
+            path: path.to_path_buf(),
+            atime,
+            crtime,
+        })
+        .await
+    }
+
+    async fn set_permissions(&self, path: &Path, update: PermsUpdate) -> Result<()> {
+        self.call_done(&Request::SetPermissions {
+            path: path.to_path_buf(),
+            perms: NamedPerms::new(&update, self.options.numeric_ids),
+        })
+        .await
+    }
+
+    async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        let (mut stream, size, _) = self.get(path).await?;
+        let mut data = Vec::with_capacity(size as usize);
+        daemon::copy_contents(&mut stream, &mut data, size).await?;
+        self.release(stream);
+        Ok(data)
+    }
+
+    async fn write_file(&self, path: &Path, data: &[u8], mtime: SystemTime) -> Result<()> {
+        let mut contents = data;
+        self.put(&mut contents, path, data.len() as u64, mtime)
+            .await
+    }
+
+    async fn get_mtime(&self, path: &Path) -> Result<SystemTime> {
+        Ok(self.stat(path).await?.modified)
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    async fn download_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        let (mut stream, size, mtime) = self.get(source).await?;
+
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write to a temp file renamed into place, unless --inplace
+        let temp = (!self.options.inplace).then(|| temp_file::temp_path(dest));
+        let guard = temp.as_ref().map(temp_file::TempFileGuard::new);
+        let write_path = temp.as_deref().unwrap_or(dest);
+
+        let mut file = tokio::fs::File::create(write_path).await?;
+        daemon::copy_contents(&mut stream, &mut file, size).await?;
+        self.release(stream);
+        file.flush().await?;
+        drop(file);
+
+        filetime::set_file_mtime(write_path, filetime::FileTime::from_system_time(mtime))?;
+        if let Some(temp) = &temp {
+            tokio::fs::rename(temp, dest).await?;
+        }
+        if let Some(guard) = guard {
+            guard.defuse();
+        }
+        Ok(TransferResult::new(size))
+    }
+
+    async fn try_lock(&self, dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+        let request = Request::Lock {
+            path: dest.to_path_buf(),
+            owner: owner.clone(),
+        };
+        match self.call(&request).await? {
+            Response::Locked(holder) => Ok(holder),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn unlock(&self, dest: &Path, owner: &LockInfo) -> Result<bool> {
+        let request = Request::Unlock {
+            path: dest.to_path_buf(),
+            owner: owner.clone(),
+        };
+        match self.call(&request).await? {
+            Response::Unlocked(released) => Ok(released),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn check_writable(&self, dest: &Path) -> Result<()> {
+        let request = Request::CheckWritable {
+            path: dest.to_path_buf(),
+        };
+        match self.call(&request).await? {
+            Response::Done => Ok(()),
+            Response::Unwritable { path, reason } => {
+                Err(SyncError::DestinationNotWritable { path, reason })
+            }
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn remote_version(&self) -> Result<Option<String>> {
+        Ok(Some(self.version.clone()))
+    }
+
+    async fn clock_skew_ms(&self) -> Option<i64> {
+        self.clock_skew_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DaemonModule;
+    use std::fs;
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+
+    /// Serve `root` as module "data" on a free local port
+    async fn start_daemon(root: &Path, read_only: bool, users: &[(&str, &str)]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modules = HashMap::from([(
+            "data".to_string(),
+            DaemonModule {
+                path: root.to_path_buf(),
+                read_only,
+                users: users
+                    .iter()
+                    .map(|(user, secret)| (user.to_string(), secret.to_string()))
+                    .collect(),
+            },
+        )]);
+        tokio::spawn(async move { daemon::serve(listener, &modules).await });
+        port
+    }
+
+    /// Serve `root` as module "data" over QUIC on a free local port
+    #[cfg(feature = "quic")]
+    fn start_quic(root: &Path, psk: &str) -> u16 {
+        let options = quic::ServerOptions {
+            psk: Some(psk.to_string()),
+            ..Default::default()
+        };
+        let listener = quic::Listener::bind("127.0.0.1:0".parse().unwrap(), &options).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modules = HashMap::from([(
+            "data".to_string(),
+            DaemonModule {
+                path: root.to_path_buf(),
+                read_only: false,
+                users: HashMap::new(),
+            },
+        )]);
+        tokio::spawn(async move { listener.serve(&modules).await });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_upload_scan_and_download() {
+        let module = TempDir::new().unwrap();
+        let local = TempDir::new().unwrap();
+        let port = start_daemon(module.path(), false, &[]).await;
+        let transport = DaemonTransport::connect("127.0.0.1", port, None, "data", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            transport.remote_version().await.unwrap().as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+
+        let source = local.path().join("hello.txt");
+        fs::write(&source, b"hello daemon").unwrap();
+        transport
+            .create_dirs(&[PathBuf::from("backup/docs")])
+            .await
+            .unwrap();
+        let result = transport
+            .copy_file(&source, Path::new("backup/docs/hello.txt"))
+            .await
+            .unwrap();
+        assert_eq!(result.bytes_written, 12);
+        assert_eq!(
+            fs::read(module.path().join("backup/docs/hello.txt")).unwrap(),
+            b"hello daemon"
+        );
+
+        let entries = transport.scan(Path::new("backup")).await.unwrap();
+        let file = entries.iter().find(|e| !e.is_dir).unwrap();
+        assert_eq!(file.path, PathBuf::from("backup/docs/hello.txt"));
+        assert_eq!(file.relative_path, PathBuf::from("docs/hello.txt"));
+        assert_eq!(file.size, 12);
+
+        let stats = transport
+            .stat_batch(&[
+                PathBuf::from("backup/docs/hello.txt"),
+                PathBuf::from("missing"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(stats[0].map(|s| s.size), Some(12));
+        assert_eq!(stats[1], None);
+
+        let copy = local.path().join("copy.txt");
+        transport
+            .download_file(Path::new("backup/docs/hello.txt"), &copy)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&copy).unwrap(), b"hello daemon");
+        assert_eq!(
+            fs::metadata(&copy).unwrap().modified().unwrap(),
+            fs::metadata(&source).unwrap().modified().unwrap()
+        );
+
+        transport.remove(Path::new("backup"), true).await.unwrap();
+        assert!(!module.path().join("backup").exists());
+    }
+
+    #[tokio::test]
+    async fn test_paths_stay_inside_the_module() {
+        let parent = TempDir::new().unwrap();
+        let root = parent.path().join("module");
+        fs::create_dir(&root).unwrap();
+        fs::write(parent.path().join("secret.txt"), b"outside").unwrap();
+        let port = start_daemon(&root, false, &[]).await;
+        let transport = DaemonTransport::connect("127.0.0.1", port, None, "data", None)
+            .await
+            .unwrap();
+
+        assert!(transport
+            .read_file(Path::new("../secret.txt"))
+            .await
+            .is_err());
+        assert!(transport
+            .write_file(Path::new("../evil.txt"), b"x", SystemTime::now())
+            .await
+            .is_err());
+        assert!(!parent.path().join("evil.txt").exists());
+        assert!(transport.remove(Path::new(""), true).await.is_err());
+
+        // The connection is still usable after refusals
+        transport
+            .write_file(Path::new("/ok.txt"), b"ok", SystemTime::now())
+            .await
+            .unwrap();
+        assert_eq!(fs::read(root.join("ok.txt")).unwrap(), b"ok");
+    }
+
+    #[tokio::test]
+    async fn test_read_only_module() {
+        let module = TempDir::new().unwrap();
+        fs::write(module.path().join("a.txt"), b"a").unwrap();
+        let port = start_daemon(module.path(), true, &[]).await;
+        let transport = DaemonTransport::connect("127.0.0.1", port, None, "data", None)
+            .await
+            .unwrap();
+
+        assert_eq!(transport.read_file(Path::new("a.txt")).await.unwrap(), b"a");
+        assert!(transport.create_dir_all(Path::new("new")).await.is_err());
+        assert!(transport
+            .write_file(Path::new("a.txt"), b"changed", SystemTime::now())
+            .await
+            .is_err());
+        assert_eq!(fs::read(module.path().join("a.txt")).unwrap(), b"a");
+    }
+
+    #[tokio::test]
+    async fn test_authentication() {
+        let module = TempDir::new().unwrap();
+        let port = start_daemon(module.path(), false, &[("alice", "hunter2")]).await;
+        let connect = |user: &'static str, secret: Option<&str>| {
+            let secret = secret.map(str::to_string);
+            async move { DaemonTransport::connect("127.0.0.1", port, Some(user), "data", secret).await }
+        };
+
+        assert!(connect("alice", Some("hunter2")).await.is_ok());
+        assert!(matches!(
+            connect("alice", Some("wrong")).await,
+            Err(SyncError::Restricted(_))
+        ));
+        assert!(matches!(
+            connect("alice", None).await,
+            Err(SyncError::Restricted(_))
+        ));
+        assert!(matches!(
+            connect("mallory", Some("hunter2")).await,
+            Err(SyncError::Restricted(_))
+        ));
+        assert!(
+            DaemonTransport::connect("127.0.0.1", port, None, "nope", None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_hello_rejected() {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+
+        let module = TempDir::new().unwrap();
+        let port = start_daemon(module.path(), false, &[]).await;
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+        // The daemon hangs up instead of waiting for a megabyte of Hello
+        stream.write_u32(1024 * 1024).await.unwrap();
+        let reply = tokio::time::timeout(Duration::from_secs(5), stream.read_u8())
+            .await
+            .unwrap();
+        assert!(reply.is_err());
+    }
+
+    #[cfg(feature = "quic")]
+    #[tokio::test]
+    async fn test_quic_with_psk() {
+        let module = TempDir::new().unwrap();
+        let port = start_quic(module.path(), "shared");
+        let connect = |psk: Option<&str>| {
+            let options = quic::ClientOptions {
+                trusted_cert: None,
+                psk: psk.map(str::to_string),
+            };
+            async move {
+                DaemonTransport::connect_quic("127.0.0.1", port, None, "data", None, &options).await
+            }
+        };
+
+        let transport = connect(Some("shared")).await.unwrap();
+        transport
+            .write_file(Path::new("docs/a.txt"), b"over quic", SystemTime::now())
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read(module.path().join("docs/a.txt")).unwrap(),
+            b"over quic"
+        );
+        // Concurrent requests each get a stream of the one connection
+        let (first, second) = tokio::join!(
+            transport.read_file(Path::new("docs/a.txt")),
+            transport.read_file(Path::new("docs/a.txt"))
+        );
+        assert_eq!(first.unwrap(), b"over quic");
+        assert_eq!(second.unwrap(), b"over quic");
+
+        assert!(matches!(
+            connect(Some("wrong")).await,
+            Err(SyncError::Restricted(_))
+        ));
+        // Without a key or a certificate to check, the server isn't trusted
+        assert!(matches!(connect(None).await, Err(SyncError::Config(_))));
+    }
+}
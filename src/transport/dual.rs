@@ -1,8 +1,14 @@
-use super::{TransferResult, Transport};
+use super::{PathStat, ProgressCallback, TransferResult, Transport};
+use crate::acl::AclMapPolicy;
 use crate::error::Result;
-use crate::sync::scanner::FileEntry;
+use crate::fs_util::{AclsApplied, XattrsApplied};
+use crate::owner::PermsUpdate;
+use crate::sync::lock::LockInfo;
+use crate::sync::scanner::{FileEntry, ScanError};
+use crate::sync::staging::StagedMove;
 use async_trait::async_trait;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// DualTransport handles operations that span two different transports
 ///
@@ -11,7 +17,10 @@ use std::path::Path;
 ///
 /// Operations are routed based on the context:
 /// - scan() operates on source
-/// - exists(), create_dir_all(), copy_file(), remove() operate on destination
+/// - exists(), create_dir_all(), remove() and metadata writes (symlinks, hardlinks,
+///   xattrs, ACLs) operate on destination
+/// - read_file() reads from source, write_file() writes to destination
+/// - copy_file() is performed by whichever side is remote
 pub struct DualTransport {
     source: Box<dyn Transport>,
     dest: Box<dyn Transport>,
@@ -21,6 +30,11 @@ impl DualTransport {
     pub fn new(source: Box<dyn Transport>, dest: Box<dyn Transport>) -> Self {
         Self { source, dest }
     }
+
+    /// The transport on the source side
+    pub fn source(&self) -> &dyn Transport {
+        self.source.as_ref()
+    }
 }
 
 #[async_trait]
@@ -30,6 +44,14 @@ impl Transport for DualTransport {
         self.source.scan(path).await
     }
 
+    async fn scan_with_errors(&self, path: &Path) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        self.source.scan_with_errors(path).await
+    }
+
+    async fn scan_destination(&self, path: &Path) -> Result<Vec<FileEntry>> {
+        self.dest.scan(path).await
+    }
+
     async fn exists(&self, path: &Path) -> Result<bool> {
         // Check existence on destination
         self.dest.exists(path).await
@@ -45,11 +67,20 @@ impl Transport for DualTransport {
         self.dest.file_info(path).await
     }
 
+    async fn stat_batch(&self, paths: &[PathBuf]) -> Result<Vec<Option<PathStat>>> {
+        // Stat on destination (one round-trip for remote destinations)
+        self.dest.stat_batch(paths).await
+    }
+
     async fn create_dir_all(&self, path: &Path) -> Result<()> {
         // Create on destination
         self.dest.create_dir_all(path).await
     }
 
+    async fn create_dirs(&self, paths: &[PathBuf]) -> Result<()> {
+        self.dest.create_dirs(paths).await
+    }
+
     async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
         // Cross-transport copy: delegate to destination transport
         // The destination transport (e.g., SshTransport) knows how to copy
@@ -61,13 +92,57 @@ impl Transport for DualTransport {
             dest.display()
         );
 
-        // Delegate to destination transport which handles the cross-transport copy
+        // For remote→local: the source transport owns the path, so it pulls the
+        // file down (SshTransport compresses compressible files on the wire)
+        if self.source.is_remote() {
+            return self.source.download_file(source, dest).await;
+        }
+
         // For local→remote: dest is SshTransport which reads from local source and writes remote
-        // For remote→local: dest is LocalTransport but source should be readable
         self.dest.copy_file(source, dest).await
     }
 
+    async fn copy_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        if self.source.is_remote() {
+            return self
+                .source
+                .download_file_with_progress(source, dest, progress)
+                .await;
+        }
+        self.dest
+            .copy_file_with_progress(source, dest, progress)
+            .await
+    }
+
+    async fn copy_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        if self.source.is_remote() {
+            return self
+                .source
+                .download_file_resumable(source, dest, progress, partial_dir)
+                .await;
+        }
+        self.dest
+            .copy_file_resumable(source, dest, progress, partial_dir)
+            .await
+    }
+
     async fn sync_file_with_delta(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        // Remote→local delta would need a reverse protocol; use full (compressed) download
+        if self.source.is_remote() {
+            return self.copy_file(source, dest).await;
+        }
+
         // Check if destination exists - delta sync requires existing dest
         if !self.dest.exists(dest).await? {
             tracing::debug!("Destination doesn't exist, using full copy");
@@ -118,6 +193,16 @@ impl Transport for DualTransport {
         self.dest.remove(path, is_dir).await
     }
 
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        // Move on destination
+        self.dest.rename(from, to).await
+    }
+
+    async fn commit_staged(&self, moves: &[StagedMove]) -> Result<usize> {
+        // Staged on destination
+        self.dest.commit_staged(moves).await
+    }
+
     async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
         // Create hardlink on destination
         self.dest.create_hardlink(source, dest).await
@@ -127,4 +212,323 @@ impl Transport for DualTransport {
         // Create symlink on destination
         self.dest.create_symlink(target, dest).await
     }
+
+    async fn set_xattrs(
+        &self,
+        path: &Path,
+        xattrs: &HashMap<String, Vec<u8>>,
+    ) -> Result<XattrsApplied> {
+        // Apply xattrs on destination
+        self.dest.set_xattrs(path, xattrs).await
+    }
+
+    async fn set_acls(
+        &self,
+        path: &Path,
+        acls: &[u8],
+        policy: AclMapPolicy,
+    ) -> Result<AclsApplied> {
+        // Apply ACLs on destination
+        self.dest.set_acls(path, acls, policy).await
+    }
+
+    async fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<std::time::SystemTime>,
+        crtime: Option<std::time::SystemTime>,
+    ) -> Result<()> {
+        // Apply times on destination
+        self.dest.set_times(path, atime, crtime).await
+    }
+
+    async fn set_permissions(&self, path: &Path, update: PermsUpdate) -> Result<()> {
+        self.dest.set_permissions(path, update).await
+    }
+
+    async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        self.source.read_file(path).await
+    }
+
+    async fn write_file(
+        &self,
+        path: &Path,
+        data: &[u8],
+        mtime: std::time::SystemTime,
+    ) -> Result<()> {
+        self.dest.write_file(path, data, mtime).await
+    }
+
+    fn is_remote(&self) -> bool {
+        self.source.is_remote() || self.dest.is_remote()
+    }
+
+    fn is_remote_destination(&self) -> bool {
+        self.dest.is_remote()
+    }
+
+    async fn download_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        self.source.download_file(source, dest).await
+    }
+
+    async fn download_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        self.source
+            .download_file_with_progress(source, dest, progress)
+            .await
+    }
+
+    async fn download_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        self.source
+            .download_file_resumable(source, dest, progress, partial_dir)
+            .await
+    }
+
+    async fn try_lock(&self, dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+        self.dest.try_lock(dest, owner).await
+    }
+
+    async fn unlock(&self, dest: &Path, owner: &LockInfo) -> Result<bool> {
+        self.dest.unlock(dest, owner).await
+    }
+
+    async fn check_writable(&self, dest: &Path) -> Result<()> {
+        self.dest.check_writable(dest).await
+    }
+
+    async fn remote_version(&self) -> Result<Option<String>> {
+        match self.dest.remote_version().await? {
+            Some(version) => Ok(Some(version)),
+            None => self.source.remote_version().await,
+        }
+    }
+
+    async fn clock_skew_ms(&self) -> Option<i64> {
+        if self.dest.is_remote() {
+            self.dest.clock_skew_ms().await
+        } else {
+            // The remote source runs ahead of the local destination
+            self.source.clock_skew_ms().await.map(|skew| -skew)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SymlinkMode;
+    use crate::sync::transfer::Transferrer;
+    use crate::transport::local::LocalTransport;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// Local transport that reports itself as remote, standing in for SshTransport
+    struct RemoteLike(LocalTransport);
+
+    #[async_trait]
+    impl Transport for RemoteLike {
+        async fn scan(&self, path: &Path) -> Result<Vec<FileEntry>> {
+            self.0.scan(path).await
+        }
+
+        async fn exists(&self, path: &Path) -> Result<bool> {
+            self.0.exists(path).await
+        }
+
+        async fn metadata(&self, path: &Path) -> Result<std::fs::Metadata> {
+            self.0.metadata(path).await
+        }
+
+        async fn create_dir_all(&self, path: &Path) -> Result<()> {
+            self.0.create_dir_all(path).await
+        }
+
+        async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+            self.0.copy_file(source, dest).await
+        }
+
+        async fn remove(&self, path: &Path, is_dir: bool) -> Result<()> {
+            self.0.remove(path, is_dir).await
+        }
+
+        async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
+            self.0.create_hardlink(source, dest).await
+        }
+
+        async fn create_symlink(&self, target: &Path, dest: &Path) -> Result<()> {
+            self.0.create_symlink(target, dest).await
+        }
+
+        fn is_remote(&self) -> bool {
+            true
+        }
+    }
+
+    /// Remote→local and local→remote dual transports
+    fn both_directions() -> Vec<(&'static str, DualTransport)> {
+        vec![
+            (
+                "remote→local",
+                DualTransport::new(
+                    Box::new(RemoteLike(LocalTransport::new())),
+                    Box::new(LocalTransport::new()),
+                ),
+            ),
+            (
+                "local→remote",
+                DualTransport::new(
+                    Box::new(LocalTransport::new()),
+                    Box::new(RemoteLike(LocalTransport::new())),
+                ),
+            ),
+        ]
+    }
+
+    /// Scan source via the dual transport and recreate every entry under dest
+    async fn sync_all(transport: &DualTransport, source: &Path, dest: &Path) {
+        let entries = transport.scan(source).await.unwrap();
+        let hardlink_map = Arc::new(Mutex::new(std::collections::HashMap::new()));
+        let transferrer = Transferrer::new(
+            transport,
+            false,
+            false,
+            SymlinkMode::Preserve,
+            true, // preserve_xattrs
+            true, // preserve_hardlinks
+            true, // preserve_acls
+            false,
+            hardlink_map,
+        );
+
+        for entry in &entries {
+            if entry.relative_path.as_os_str().is_empty() {
+                continue;
+            }
+            let dest_path = dest.join(&entry.relative_path);
+            transferrer.create(entry, &dest_path).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_symlinks_recreated_both_directions() {
+        for (direction, transport) in both_directions() {
+            let source = TempDir::new().unwrap();
+            let dest = TempDir::new().unwrap();
+            fs::write(source.path().join("target.txt"), b"content").unwrap();
+            std::os::unix::fs::symlink("target.txt", source.path().join("link")).unwrap();
+
+            sync_all(&transport, source.path(), dest.path()).await;
+
+            let link = dest.path().join("link");
+            assert!(
+                fs::symlink_metadata(&link)
+                    .unwrap()
+                    .file_type()
+                    .is_symlink(),
+                "{}: link should be a symlink",
+                direction
+            );
+            assert_eq!(
+                fs::read_link(&link).unwrap(),
+                Path::new("target.txt"),
+                "{}: symlink target should be preserved",
+                direction
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_xattrs_recreated_both_directions() {
+        for (direction, transport) in both_directions() {
+            let source = TempDir::new().unwrap();
+            let dest = TempDir::new().unwrap();
+            let file = source.path().join("file.txt");
+            fs::write(&file, b"content").unwrap();
+
+            // Skip if the filesystem doesn't support user xattrs
+            if xattr::set(&file, "user.sy_test", b"value").is_err() {
+                return;
+            }
+
+            sync_all(&transport, source.path(), dest.path()).await;
+
+            let value = xattr::get(dest.path().join("file.txt"), "user.sy_test")
+                .unwrap()
+                .unwrap_or_default();
+            assert_eq!(value, b"value", "{}: xattr should be preserved", direction);
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_acls_recreated_both_directions() {
+        use exacl::getfacl;
+
+        for (direction, transport) in both_directions() {
+            let source = TempDir::new().unwrap();
+            let dest = TempDir::new().unwrap();
+            let file = source.path().join("file.txt");
+            fs::write(&file, b"content").unwrap();
+
+            let entries = transport.scan(source.path()).await.unwrap();
+            let scanned = entries
+                .iter()
+                .find(|e| e.relative_path == Path::new("file.txt"))
+                .unwrap();
+            if scanned.acls.is_none() {
+                // Filesystem doesn't expose ACLs, nothing to preserve
+                return;
+            }
+
+            sync_all(&transport, source.path(), dest.path()).await;
+
+            let dest_acls = getfacl(dest.path().join("file.txt"), None).unwrap();
+            let dest_text: Vec<String> = dest_acls.iter().map(|e| format!("{}", e)).collect();
+            let source_text = String::from_utf8(scanned.acls.clone().unwrap()).unwrap();
+            assert_eq!(
+                dest_text.join("\n"),
+                source_text,
+                "{}: ACLs should be preserved",
+                direction
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_hardlinks_recreated_both_directions() {
+        use std::os::unix::fs::MetadataExt;
+
+        for (direction, transport) in both_directions() {
+            let source = TempDir::new().unwrap();
+            let dest = TempDir::new().unwrap();
+            fs::write(source.path().join("a.txt"), b"shared").unwrap();
+            fs::hard_link(source.path().join("a.txt"), source.path().join("b.txt")).unwrap();
+
+            sync_all(&transport, source.path(), dest.path()).await;
+
+            let a = fs::metadata(dest.path().join("a.txt")).unwrap();
+            let b = fs::metadata(dest.path().join("b.txt")).unwrap();
+            assert_eq!(
+                a.ino(),
+                b.ino(),
+                "{}: hardlink group should be preserved",
+                direction
+            );
+            assert_eq!(a.nlink(), 2, "{}: expected 2 links", direction);
+        }
+    }
 }
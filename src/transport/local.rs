@@ -1,12 +1,18 @@
-use super::{TransferResult, Transport};
+use super::{TransferResult, Transport, TransportOptions};
+use crate::cli::DanglingMode;
 use crate::error::{format_bytes, Result, SyncError};
-use crate::fs_util::{has_hard_links, same_filesystem, supports_cow_reflinks};
+use crate::fs_util::{
+    copy_source, has_hard_links, is_network_filesystem, open_source, same_file, same_filesystem,
+    supports_cow_reflinks,
+};
 use crate::integrity::{ChecksumType, IntegrityVerifier};
-use crate::sync::scanner::{FileEntry, Scanner};
-use crate::temp_file::TempFileGuard;
+use crate::sync::scanner::{FileEntry, ScanError, Scanner};
+use crate::sync::staging::{self, StagedMove};
+use crate::temp_file::{self, write_atomically, TempFileGuard};
 use async_trait::async_trait;
 use std::fs::{self, File};
 use std::path::Path;
+use std::time::SystemTime;
 
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
@@ -28,18 +34,122 @@ fn is_file_sparse(_metadata: &std::fs::Metadata) -> bool {
     false // Non-Unix platforms don't support sparse detection
 }
 
+/// Bring `dest` up to date with `source` by rewriting only the blocks that differ
+///
+/// The destination is changed in place: an interrupted update leaves a mix of
+/// old and new blocks, which the next sync finishes. Returns the bytes
+/// compared, the bytes written and the number of blocks written.
+fn update_changed_blocks(
+    source: &Path,
+    dest: &Path,
+    block_size: usize,
+    verifier: &IntegrityVerifier,
+    noatime: bool,
+) -> Result<(u64, u64, usize)> {
+    use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+
+    let copy_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source: std::io::Error| SyncError::CopyError { path, source }
+    };
+
+    let mut source_file = BufReader::with_capacity(
+        256 * 1024,
+        open_source(source, noatime).map_err(copy_error(source))?,
+    );
+    let mut dest_file = File::options()
+        .read(true)
+        .write(true)
+        .open(dest)
+        .map_err(copy_error(dest))?;
+
+    let mut source_buf = vec![0u8; block_size];
+    let mut dest_buf = vec![0u8; block_size];
+    let mut offset = 0u64;
+    let mut literal_bytes = 0u64;
+    let mut changed_blocks = 0usize;
+
+    loop {
+        let src_read = read_block(&mut source_file, &mut source_buf).map_err(copy_error(source))?;
+        if src_read == 0 {
+            break;
+        }
+        dest_file
+            .seek(SeekFrom::Start(offset))
+            .map_err(copy_error(dest))?;
+        let dst_read = read_block(&mut dest_file, &mut dest_buf).map_err(copy_error(dest))?;
+
+        if source_buf[..src_read] != dest_buf[..dst_read] {
+            dest_file
+                .seek(SeekFrom::Start(offset))
+                .and_then(|_| dest_file.write_all(&source_buf[..src_read]))
+                .map_err(copy_error(dest))?;
+
+            if verifier.verify_on_write() {
+                let mut verify_buf = vec![0u8; src_read];
+                dest_file
+                    .seek(SeekFrom::Start(offset))
+                    .and_then(|_| dest_file.read_exact(&mut verify_buf))
+                    .map_err(copy_error(dest))?;
+                if !verifier.verify_block(&source_buf[..src_read], &verify_buf)? {
+                    return Err(SyncError::BlockCorruption {
+                        path: dest.to_path_buf(),
+                        block_number: (offset / block_size as u64) as usize,
+                        expected_checksum: verifier
+                            .compute_data_checksum(&source_buf[..src_read])?
+                            .to_hex(),
+                        actual_checksum: verifier.compute_data_checksum(&verify_buf)?.to_hex(),
+                    });
+                }
+            }
+
+            literal_bytes += src_read as u64;
+            changed_blocks += 1;
+        }
+        offset += src_read as u64;
+    }
+
+    // Drop the old tail if the file got shorter
+    dest_file.set_len(offset).map_err(copy_error(dest))?;
+    dest_file.flush().map_err(copy_error(dest))?;
+    Ok((offset, literal_bytes, changed_blocks))
+}
+
+/// Fill `buf` as far as the file allows, returning how much was read
+fn read_block(reader: &mut impl std::io::Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Refuse to write `dest` when it is `source` itself, which would truncate it
+fn check_not_same_file(source: &Path, dest: &Path) -> Result<()> {
+    if same_file(source, dest) {
+        return Err(SyncError::SameFile {
+            path: source.to_path_buf(),
+            dest: dest.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
 /// Copy a sparse file while preserving holes
 ///
 /// Tries to use SEEK_HOLE/SEEK_DATA for efficiency, falls back to block-based
 /// zero detection if not supported.
 #[cfg(unix)]
-fn copy_sparse_file(source: &Path, dest: &Path) -> std::io::Result<u64> {
+fn copy_sparse_file(source: &Path, dest: &Path, noatime: bool) -> std::io::Result<u64> {
     // Try SEEK_HOLE/SEEK_DATA first (most efficient)
-    match copy_sparse_file_seek(source, dest) {
+    match copy_sparse_file_seek(source, dest, noatime) {
         Ok(size) => Ok(size),
         Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
             // SEEK_DATA not supported, fall back to block-based approach
-            copy_sparse_file_blocks(source, dest)
+            copy_sparse_file_blocks(source, dest, noatime)
         }
         Err(e) => Err(e),
     }
@@ -47,14 +157,14 @@ fn copy_sparse_file(source: &Path, dest: &Path) -> std::io::Result<u64> {
 
 /// Copy sparse file using SEEK_HOLE/SEEK_DATA (fast path)
 #[cfg(unix)]
-fn copy_sparse_file_seek(source: &Path, dest: &Path) -> std::io::Result<u64> {
+fn copy_sparse_file_seek(source: &Path, dest: &Path, noatime: bool) -> std::io::Result<u64> {
     use std::io::{Read, Seek, SeekFrom, Write};
     use std::os::unix::io::AsRawFd;
 
     const SEEK_DATA: i32 = 3; // Find next data region
     const SEEK_HOLE: i32 = 4; // Find next hole
 
-    let mut src_file = File::open(source)?;
+    let mut src_file = open_source(source, noatime)?;
     let src_meta = src_file.metadata()?;
     let file_size = src_meta.len();
 
@@ -126,10 +236,10 @@ fn copy_sparse_file_seek(source: &Path, dest: &Path) -> std::io::Result<u64> {
 
 /// Copy sparse file by reading blocks and detecting zeros (slow path, portable)
 #[cfg(unix)]
-fn copy_sparse_file_blocks(source: &Path, dest: &Path) -> std::io::Result<u64> {
+fn copy_sparse_file_blocks(source: &Path, dest: &Path, noatime: bool) -> std::io::Result<u64> {
     use std::io::{Read, Seek, SeekFrom, Write};
 
-    let mut src_file = File::open(source)?;
+    let mut src_file = open_source(source, noatime)?;
     let src_meta = src_file.metadata()?;
     let file_size = src_meta.len();
 
@@ -170,9 +280,9 @@ fn copy_sparse_file_blocks(source: &Path, dest: &Path) -> std::io::Result<u64> {
 }
 
 #[cfg(not(unix))]
-fn copy_sparse_file(source: &Path, dest: &Path) -> std::io::Result<u64> {
+fn copy_sparse_file(source: &Path, dest: &Path, noatime: bool) -> std::io::Result<u64> {
     // On non-Unix platforms, fall back to regular copy
-    fs::copy(source, dest)
+    copy_source(source, dest, noatime)
 }
 
 /// Local filesystem transport
@@ -181,6 +291,10 @@ fn copy_sparse_file(source: &Path, dest: &Path) -> std::io::Result<u64> {
 /// This wraps the existing Phase 1 implementation in the async Transport interface.
 pub struct LocalTransport {
     verifier: IntegrityVerifier,
+    follow_links: bool,
+    dangling_links: DanglingMode,
+    max_depth: Option<usize>,
+    options: TransportOptions,
 }
 
 impl LocalTransport {
@@ -188,11 +302,47 @@ impl LocalTransport {
         // Default: no verification
         Self {
             verifier: IntegrityVerifier::new(ChecksumType::None, false),
+            follow_links: false,
+            dangling_links: DanglingMode::default(),
+            max_depth: None,
+            options: TransportOptions::default(),
         }
     }
 
     pub fn with_verifier(verifier: IntegrityVerifier) -> Self {
-        Self { verifier }
+        Self {
+            verifier,
+            follow_links: false,
+            dangling_links: DanglingMode::default(),
+            max_depth: None,
+            options: TransportOptions::default(),
+        }
+    }
+
+    /// Follow symlinks to directories when scanning (--links follow)
+    ///
+    /// Symlink loops are detected by the scanner and skipped.
+    pub fn follow_links(mut self, follow: bool) -> Self {
+        self.follow_links = follow;
+        self
+    }
+
+    /// Handle symlinks whose target doesn't exist when scanning (--dangling-links)
+    pub fn dangling_links(mut self, mode: DanglingMode) -> Self {
+        self.dangling_links = mode;
+        self
+    }
+
+    /// Limit scan recursion depth (--max-depth)
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// How files are read and written (--inplace, --no-whole-file, --open-noatime)
+    pub fn options(mut self, options: TransportOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
@@ -207,14 +357,36 @@ impl Transport for LocalTransport {
     async fn scan(&self, path: &Path) -> Result<Vec<FileEntry>> {
         // Use existing scanner (runs synchronously, wrapped in async)
         let path = path.to_path_buf();
+        let follow_links = self.follow_links;
+        let dangling_links = self.dangling_links;
+        let max_depth = self.max_depth;
         tokio::task::spawn_blocking(move || {
-            let scanner = Scanner::new(&path);
+            let scanner = Scanner::new(&path)
+                .follow_links(follow_links)
+                .dangling_links(dangling_links)
+                .max_depth(max_depth);
             scanner.scan()
         })
         .await
         .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
     }
 
+    async fn scan_with_errors(&self, path: &Path) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        let path = path.to_path_buf();
+        let follow_links = self.follow_links;
+        let dangling_links = self.dangling_links;
+        let max_depth = self.max_depth;
+        tokio::task::spawn_blocking(move || {
+            let scanner = Scanner::new(&path)
+                .follow_links(follow_links)
+                .dangling_links(dangling_links)
+                .max_depth(max_depth);
+            scanner.scan_with_errors()
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+    }
+
     async fn exists(&self, path: &Path) -> Result<bool> {
         Ok(tokio::fs::try_exists(path).await.unwrap_or(false))
     }
@@ -241,6 +413,8 @@ impl Transport for LocalTransport {
         // Copy file with checksum verification using spawn_blocking
         let source = source.to_path_buf();
         let dest = dest.to_path_buf();
+        let inplace = self.options.inplace;
+        let noatime = self.options.open_noatime;
 
         tokio::task::spawn_blocking(move || {
             // Check if source is sparse
@@ -248,6 +422,7 @@ impl Transport for LocalTransport {
                 path: source.clone(),
                 source: e,
             })?;
+            check_not_same_file(&source, &dest)?;
 
             let is_sparse = is_file_sparse(&source_meta);
 
@@ -257,10 +432,12 @@ impl Transport for LocalTransport {
                     "Sparse file detected ({}), using sparse-aware copy",
                     source.display()
                 );
-                let bytes_written = fs::copy(&source, &dest).map_err(|e| SyncError::CopyError {
-                    path: source.clone(),
-                    source: e,
-                })?;
+                let bytes_written =
+                    write_atomically(&dest, inplace, |temp| copy_source(&source, temp, noatime))
+                        .map_err(|e| SyncError::CopyError {
+                            path: source.clone(),
+                            source: e,
+                        })?;
 
                 // Strip xattrs (fs::copy may preserve them on some platforms)
                 #[cfg(unix)]
@@ -289,15 +466,18 @@ impl Transport for LocalTransport {
                 return Ok(bytes_written);
             }
 
-            // Use fs::copy() which is optimized per-platform:
+            // Use fs::copy() (via copy_source, unless --open-noatime is set)
+            // which is optimized per-platform:
             // - macOS: clonefile() for COW reflinks on APFS (100x+ faster)
             // - Linux: copy_file_range() for zero-copy (kernel-side)
             // - Fallback: sendfile() or read/write
             // This is MUCH faster than manual read/write loop
-            let bytes_written = fs::copy(&source, &dest).map_err(|e| SyncError::CopyError {
-                path: source.clone(),
-                source: e,
-            })?;
+            let bytes_written =
+                write_atomically(&dest, inplace, |temp| copy_source(&source, temp, noatime))
+                    .map_err(|e| SyncError::CopyError {
+                        path: source.clone(),
+                        source: e,
+                    })?;
 
             // fs::copy() may preserve xattrs on some platforms (e.g., macOS).
             // Strip all xattrs so that Transferrer can selectively re-add them
@@ -337,6 +517,7 @@ impl Transport for LocalTransport {
             tracing::debug!("Destination doesn't exist, using full copy");
             return self.copy_file(source, dest).await;
         }
+        check_not_same_file(source, dest)?;
 
         // Get file sizes
         let source_meta = self.metadata(source).await?;
@@ -351,7 +532,11 @@ impl Transport for LocalTransport {
         // for files as small as 10MB when changes are localized (e.g., 1MB change in 100MB).
         const DELTA_THRESHOLD: u64 = 10 * 1024 * 1024; // 10MB
 
-        if dest_size < DELTA_THRESHOLD {
+        // --no-whole-file: delta for every update, whatever its size or change ratio
+        let forced = self.options.always_delta;
+        let network_dest = is_network_filesystem(dest);
+
+        if dest_size < DELTA_THRESHOLD && !forced {
             tracing::debug!(
                 "File size ({:.1} MB) below delta threshold ({} MB), using full copy",
                 dest_size as f64 / 1024.0 / 1024.0,
@@ -375,6 +560,8 @@ impl Transport for LocalTransport {
         let source = source.to_path_buf();
         let dest = dest.to_path_buf();
         let verifier = self.verifier.clone();
+        let inplace = self.options.inplace;
+        let noatime = self.options.open_noatime;
 
         tokio::task::spawn_blocking(move || {
             use crate::delta::estimate_change_ratio;
@@ -397,7 +584,7 @@ impl Transport for LocalTransport {
                 );
 
                 // Use SEEK_HOLE/SEEK_DATA to preserve sparseness
-                let bytes_written = copy_sparse_file(&source, &dest).map_err(|e| SyncError::CopyError {
+                let bytes_written = write_atomically(&dest, inplace, |temp| copy_sparse_file(&source, temp, noatime)).map_err(|e| SyncError::CopyError {
                     path: source.clone(),
                     source: e,
                 })?;
@@ -418,6 +605,7 @@ impl Transport for LocalTransport {
                 block_size,
                 Some(20), // Sample 20 blocks
                 Some(0.75), // 75% threshold
+                noatime,
             );
 
             match change_ratio_result {
@@ -429,7 +617,7 @@ impl Transport for LocalTransport {
                         ratio.blocks_sampled
                     );
 
-                    if !ratio.use_delta {
+                    if !ratio.use_delta && !forced {
                         tracing::info!(
                             "Change ratio {} exceeds threshold {:.1}%, using full copy instead of delta sync",
                             ratio.change_ratio_percent(),
@@ -437,7 +625,7 @@ impl Transport for LocalTransport {
                         );
 
                         // Fallback to full copy (not sparse, so fs::copy is fine)
-                        let bytes_written = fs::copy(&source, &dest).map_err(|e| SyncError::CopyError {
+                        let bytes_written = write_atomically(&dest, inplace, |temp| copy_source(&source, temp, noatime)).map_err(|e| SyncError::CopyError {
                             path: source.clone(),
                             source: e,
                         })?;
@@ -463,6 +651,31 @@ impl Transport for LocalTransport {
             let same_fs = same_filesystem(&source, &dest);
             let has_hardlinks = has_hard_links(&dest);
 
+            // Strategy 0: rewrite only the changed blocks of the destination file
+            // itself, when it is writing a whole new copy that's slow (network
+            // filesystems). A temp copy would be written in full, so this works in
+            // place; other hard links to the file would see the change, so they
+            // keep the full rebuild unless --inplace says that's wanted.
+            if network_dest && (!has_hardlinks || inplace) {
+                tracing::info!(
+                    "Delta sync strategy: changed blocks only - destination is on a network filesystem"
+                );
+                let (bytes_written, literal_bytes, changed_blocks) =
+                    update_changed_blocks(&source, &dest, block_size, &verifier, noatime)?;
+                tracing::info!(
+                    "Local delta sync: {} of {} rewritten in {} blocks ({:?})",
+                    format_bytes(literal_bytes),
+                    format_bytes(bytes_written),
+                    changed_blocks,
+                    total_start.elapsed()
+                );
+                return Ok(TransferResult::with_delta(
+                    bytes_written,
+                    changed_blocks,
+                    literal_bytes,
+                ));
+            }
+
             let use_cow_strategy = supports_cow && same_fs && !has_hardlinks;
 
             // Log strategy selection for debugging
@@ -487,7 +700,7 @@ impl Transport for LocalTransport {
 
             // Strategy 1: COW clone + selective writes (fast on APFS/BTRFS/XFS)
             // Strategy 2: In-place delta (for ext4, hard links, cross-filesystem)
-            let temp_dest = dest.with_extension("sy.tmp");
+            let temp_dest = temp_file::temp_path(&dest);
             let temp_guard = TempFileGuard::new(&temp_dest);
 
             let (bytes_written, literal_bytes, changed_blocks) = if use_cow_strategy {
@@ -516,7 +729,7 @@ impl Transport for LocalTransport {
                 // Open source and original dest for reading (sequential)
                 let mut source_file = BufReader::with_capacity(
                     256 * 1024,
-                    File::open(&source).map_err(|e| SyncError::CopyError {
+                    open_source(&source, noatime).map_err(|e| SyncError::CopyError {
                         path: source.clone(),
                         source: e,
                     })?,
@@ -657,7 +870,7 @@ impl Transport for LocalTransport {
                 // Open source and dest for reading
                 let mut source_file = BufReader::with_capacity(
                     256 * 1024,
-                    File::open(&source).map_err(|e| SyncError::CopyError {
+                    open_source(&source, noatime).map_err(|e| SyncError::CopyError {
                         path: source.clone(),
                         source: e,
                     })?,
@@ -809,6 +1022,10 @@ impl Transport for LocalTransport {
         .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
     }
 
+    async fn write_file(&self, path: &Path, data: &[u8], mtime: SystemTime) -> Result<()> {
+        super::write_local_file(path, data, mtime, self.options.inplace).await
+    }
+
     async fn remove(&self, path: &Path, is_dir: bool) -> Result<()> {
         if is_dir {
             tokio::fs::remove_dir_all(path)
@@ -821,6 +1038,24 @@ impl Transport for LocalTransport {
         Ok(())
     }
 
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        if let Some(parent) = to.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(SyncError::Io)?;
+        }
+        tokio::fs::rename(from, to).await.map_err(SyncError::Io)?;
+        Ok(())
+    }
+
+    async fn commit_staged(&self, moves: &[StagedMove]) -> Result<usize> {
+        let moves = moves.to_vec();
+        tokio::task::spawn_blocking(move || staging::commit_moves(&moves))
+            .await
+            .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+            .map_err(SyncError::Io)
+    }
+
     async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
         // Ensure parent directory exists
         if let Some(parent) = dest.parent() {
@@ -942,6 +1177,51 @@ mod tests {
         assert_eq!(fs::read_to_string(&dest_file).unwrap(), "test content");
     }
 
+    #[tokio::test]
+    async fn test_copy_file_refuses_same_file() {
+        let temp = TempDir::new().unwrap();
+        let source_file = temp.path().join("test.txt");
+        fs::write(&source_file, "test content").unwrap();
+        let link = temp.path().join("link.txt");
+        fs::hard_link(&source_file, &link).unwrap();
+
+        let transport = LocalTransport::new();
+        for dest in [&source_file, &link] {
+            let err = transport.copy_file(&source_file, dest).await.unwrap_err();
+            assert!(matches!(err, SyncError::SameFile { .. }));
+            let err = transport
+                .sync_file_with_delta(&source_file, dest)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, SyncError::SameFile { .. }));
+        }
+        assert_eq!(fs::read_to_string(&source_file).unwrap(), "test content");
+    }
+
+    #[test]
+    fn test_update_changed_blocks() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("disk.img");
+        let dest = temp.path().join("disk.img.old");
+
+        let old: Vec<u8> = (0..10 * 1024).map(|i| (i % 251) as u8).collect();
+        let mut new = old.clone();
+        new[5000] ^= 0xFF; // Second 4 KiB block
+        new.truncate(9 * 1024); // Shorter, with a changed last block
+        new.extend_from_slice(b"tail");
+        fs::write(&dest, &old).unwrap();
+        fs::write(&source, &new).unwrap();
+
+        let verifier = IntegrityVerifier::new(ChecksumType::Fast, true);
+        let (compared, written, blocks) =
+            update_changed_blocks(&source, &dest, 4096, &verifier, false).unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), new);
+        assert_eq!(compared, new.len() as u64);
+        assert_eq!(blocks, 2);
+        assert_eq!(written, 4096 + (new.len() - 8192) as u64);
+    }
+
     #[tokio::test]
     async fn test_local_transport_create_dir_all() {
         let temp = TempDir::new().unwrap();
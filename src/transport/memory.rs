@@ -0,0 +1,459 @@
+use super::{FileInfo, PathStat, TransferResult, Transport};
+use crate::acl::AclMapPolicy;
+use crate::error::{Result, SyncError};
+use crate::fs_util::{AclsApplied, XattrsApplied};
+use crate::owner::PermsUpdate;
+use crate::sync::lock::LockInfo;
+use crate::sync::scanner::FileEntry;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// An entry of a [`MemoryTransport`] tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryEntry {
+    File(Vec<u8>),
+    Dir,
+    Symlink(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    entry: MemoryEntry,
+    modified: SystemTime,
+}
+
+/// Transport over an in-memory tree, for tests
+///
+/// Source and destination share one tree, like two directories of a local
+/// filesystem, so the planner and transferrer run without touching disk or
+/// SSH and every run is deterministic. Extended attributes, ACLs and
+/// access/creation times aren't modelled; writing them is a no-op. Hard links
+/// are created as copies.
+///
+/// The sync engine still reads a few things from the local filesystem around
+/// the transport (`--delete` planning, post-transfer verification), so syncs
+/// over this transport run without them.
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+    locks: Mutex<HashMap<PathBuf, LockInfo>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write a file with the given contents and mtime, creating its parents
+    pub fn write(&self, path: impl AsRef<Path>, data: impl Into<Vec<u8>>, modified: SystemTime) {
+        let path = path.as_ref();
+        let mut nodes = self.nodes.lock().unwrap();
+        if let Some(parent) = path.parent() {
+            mkdir_all(&mut nodes, parent).expect("parent of a file is not a directory");
+        }
+        nodes.insert(
+            path.to_path_buf(),
+            Node {
+                entry: MemoryEntry::File(data.into()),
+                modified,
+            },
+        );
+    }
+
+    /// Create a directory and its parents
+    pub fn mkdir(&self, path: impl AsRef<Path>) {
+        mkdir_all(&mut self.nodes.lock().unwrap(), path.as_ref())
+            .expect("a file is in the way of the directory");
+    }
+
+    /// Contents of a file, None if it isn't one
+    pub fn read(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        match self.nodes.lock().unwrap().get(path.as_ref()) {
+            Some(Node {
+                entry: MemoryEntry::File(data),
+                ..
+            }) => Some(data.clone()),
+            _ => None,
+        }
+    }
+
+    /// Modification time of any entry
+    pub fn modified(&self, path: impl AsRef<Path>) -> Option<SystemTime> {
+        self.nodes
+            .lock()
+            .unwrap()
+            .get(path.as_ref())
+            .map(|node| node.modified)
+    }
+
+    /// Every entry below `root`, keyed by its path relative to `root`
+    pub fn entries(&self, root: impl AsRef<Path>) -> BTreeMap<PathBuf, MemoryEntry> {
+        let root = root.as_ref();
+        let nodes = self.nodes.lock().unwrap();
+        descendants(&nodes, root)
+            .map(|(path, node)| (relative(path, root), node.entry.clone()))
+            .collect()
+    }
+
+    fn node(&self, path: &Path) -> Result<Node> {
+        self.nodes
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+/// Entries strictly below `root`; paths order by component, so they follow
+/// `root` in one run
+fn descendants<'a>(
+    nodes: &'a BTreeMap<PathBuf, Node>,
+    root: &'a Path,
+) -> impl Iterator<Item = (&'a PathBuf, &'a Node)> + 'a {
+    nodes
+        .range::<Path, _>((Bound::Excluded(root), Bound::Unbounded))
+        .take_while(move |(path, _)| path.starts_with(root))
+}
+
+fn relative(path: &Path, root: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+fn mkdir_all(nodes: &mut BTreeMap<PathBuf, Node>, path: &Path) -> Result<()> {
+    for dir in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+        if dir.as_os_str().is_empty() || dir.parent().is_none() {
+            continue; // "" or the root
+        }
+        match nodes.get(dir) {
+            Some(Node {
+                entry: MemoryEntry::Dir,
+                ..
+            }) => {}
+            Some(_) => {
+                return Err(io_error(
+                    ErrorKind::AlreadyExists,
+                    format!("Not a directory: {}", dir.display()),
+                ))
+            }
+            None => {
+                nodes.insert(
+                    dir.to_path_buf(),
+                    Node {
+                        entry: MemoryEntry::Dir,
+                        modified: SystemTime::now(),
+                    },
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn io_error(kind: ErrorKind, message: String) -> SyncError {
+    SyncError::Io(std::io::Error::new(kind, message))
+}
+
+fn not_found(path: &Path) -> SyncError {
+    io_error(
+        ErrorKind::NotFound,
+        format!("No such file or directory: {}", path.display()),
+    )
+}
+
+fn size(entry: &MemoryEntry) -> u64 {
+    match entry {
+        MemoryEntry::File(data) => data.len() as u64,
+        MemoryEntry::Dir => 0,
+        MemoryEntry::Symlink(target) => target.as_os_str().len() as u64,
+    }
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn scan(&self, path: &Path) -> Result<Vec<FileEntry>> {
+        if !matches!(self.node(path)?.entry, MemoryEntry::Dir) {
+            return Err(io_error(
+                ErrorKind::Other,
+                format!("Not a directory: {}", path.display()),
+            ));
+        }
+        let nodes = self.nodes.lock().unwrap();
+        Ok(descendants(&nodes, path)
+            .map(|(full, node)| {
+                let size = size(&node.entry);
+                FileEntry {
+                    path: full.clone(),
+                    relative_path: relative(full, path),
+                    size,
+                    modified: node.modified,
+                    is_dir: node.entry == MemoryEntry::Dir,
+                    is_symlink: matches!(node.entry, MemoryEntry::Symlink(_)),
+                    symlink_target: match &node.entry {
+                        MemoryEntry::Symlink(target) => Some(target.clone()),
+                        _ => None,
+                    },
+                    is_sparse: false,
+                    allocated_size: size,
+                    xattrs: None,
+                    inode: None,
+                    nlink: 1,
+                    acls: None,
+                    bsd_flags: None,
+                    atime: None,
+                    crtime: None,
+                    perms: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        Ok(self.nodes.lock().unwrap().contains_key(path))
+    }
+
+    async fn metadata(&self, _path: &Path) -> Result<std::fs::Metadata> {
+        // std::fs::Metadata can't be built for an in-memory entry
+        Err(SyncError::Io(std::io::Error::other(
+            "metadata() not supported for memory transport, use file_info() instead",
+        )))
+    }
+
+    async fn file_info(&self, path: &Path) -> Result<FileInfo> {
+        let node = self.node(path)?;
+        Ok(FileInfo {
+            size: size(&node.entry),
+            modified: node.modified,
+        })
+    }
+
+    async fn stat_batch(&self, paths: &[PathBuf]) -> Result<Vec<Option<PathStat>>> {
+        let nodes = self.nodes.lock().unwrap();
+        Ok(paths
+            .iter()
+            .map(|path| {
+                nodes.get(path).map(|node| PathStat {
+                    size: size(&node.entry),
+                    modified: node.modified,
+                    is_dir: node.entry == MemoryEntry::Dir,
+                })
+            })
+            .collect())
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> Result<()> {
+        mkdir_all(&mut self.nodes.lock().unwrap(), path)
+    }
+
+    async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        let node = self.node(source)?;
+        let MemoryEntry::File(ref data) = node.entry else {
+            return Err(io_error(
+                ErrorKind::Other,
+                format!("Not a file: {}", source.display()),
+            ));
+        };
+        let len = data.len() as u64;
+        self.write_file(dest, data, node.modified).await?;
+        Ok(TransferResult::new(len))
+    }
+
+    async fn remove(&self, path: &Path, is_dir: bool) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = nodes.get(path).ok_or_else(|| not_found(path))?;
+        if (node.entry == MemoryEntry::Dir) != is_dir {
+            return Err(io_error(
+                ErrorKind::Other,
+                format!("Wrong entry type to remove: {}", path.display()),
+            ));
+        }
+        let below: Vec<PathBuf> = descendants(&nodes, path)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for child in below {
+            nodes.remove(&child);
+        }
+        nodes.remove(path);
+        Ok(())
+    }
+
+    async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
+        self.copy_file(source, dest).await.map(|_| ())
+    }
+
+    async fn create_symlink(&self, target: &Path, dest: &Path) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if let Some(parent) = dest.parent() {
+            mkdir_all(&mut nodes, parent)?;
+        }
+        if nodes.contains_key(dest) {
+            return Err(io_error(
+                ErrorKind::AlreadyExists,
+                format!("File exists: {}", dest.display()),
+            ));
+        }
+        nodes.insert(
+            dest.to_path_buf(),
+            Node {
+                entry: MemoryEntry::Symlink(target.to_path_buf()),
+                modified: SystemTime::now(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn set_xattrs(
+        &self,
+        _path: &Path,
+        _xattrs: &HashMap<String, Vec<u8>>,
+    ) -> Result<XattrsApplied> {
+        Ok(XattrsApplied::default())
+    }
+
+    async fn set_acls(
+        &self,
+        _path: &Path,
+        _acls: &[u8],
+        _policy: AclMapPolicy,
+    ) -> Result<AclsApplied> {
+        Ok(AclsApplied::default())
+    }
+
+    async fn set_times(
+        &self,
+        _path: &Path,
+        _atime: Option<SystemTime>,
+        _crtime: Option<SystemTime>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn set_permissions(&self, _path: &Path, _update: PermsUpdate) -> Result<()> {
+        Ok(())
+    }
+
+    async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        self.read(path).ok_or_else(|| not_found(path))
+    }
+
+    async fn write_file(&self, path: &Path, data: &[u8], mtime: SystemTime) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if let Some(parent) = path.parent() {
+            mkdir_all(&mut nodes, parent)?;
+        }
+        if nodes
+            .get(path)
+            .is_some_and(|node| node.entry == MemoryEntry::Dir)
+        {
+            return Err(io_error(
+                ErrorKind::Other,
+                format!("Is a directory: {}", path.display()),
+            ));
+        }
+        nodes.insert(
+            path.to_path_buf(),
+            Node {
+                entry: MemoryEntry::File(data.to_vec()),
+                modified: mtime,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_mtime(&self, path: &Path) -> Result<SystemTime> {
+        Ok(self.node(path)?.modified)
+    }
+
+    async fn try_lock(&self, dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+        let mut locks = self.locks.lock().unwrap();
+        match locks.get(dest) {
+            Some(holder) => Ok(Some(holder.clone())),
+            None => {
+                locks.insert(dest.to_path_buf(), owner.clone());
+                Ok(None)
+            }
+        }
+    }
+
+    async fn unlock(&self, dest: &Path, owner: &LockInfo) -> Result<bool> {
+        let mut locks = self.locks.lock().unwrap();
+        if locks.get(dest) != Some(owner) {
+            return Ok(false);
+        }
+        locks.remove(dest);
+        Ok(true)
+    }
+
+    async fn check_writable(&self, _dest: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[tokio::test]
+    async fn test_scan_and_copy() {
+        let transport = MemoryTransport::new();
+        transport.write("/src/a.txt", "alpha", at(100));
+        transport.write("/src/dir/b.txt", "beta", at(200));
+        transport.mkdir("/src/empty");
+        transport.mkdir("/src2");
+
+        let entries = transport.scan(Path::new("/src")).await.unwrap();
+        let relative: Vec<_> = entries.iter().map(|e| e.relative_path.clone()).collect();
+        // Parents come before their contents, and /src2 isn't under /src
+        assert_eq!(
+            relative,
+            ["a.txt", "dir", "dir/b.txt", "empty"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+        assert!(entries[1].is_dir);
+        assert_eq!(entries[2].size, 4);
+
+        let result = transport
+            .copy_file(Path::new("/src/dir/b.txt"), Path::new("/dst/x/b.txt"))
+            .await
+            .unwrap();
+        assert_eq!(result.bytes_written, 4);
+        assert_eq!(transport.read("/dst/x/b.txt").unwrap(), b"beta");
+        assert_eq!(transport.modified("/dst/x/b.txt"), Some(at(200)));
+        assert_eq!(transport.entries("/dst").len(), 2);
+
+        transport.remove(Path::new("/src/dir"), true).await.unwrap();
+        assert!(!transport.exists(Path::new("/src/dir/b.txt")).await.unwrap());
+        assert!(transport.scan(Path::new("/missing")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lock() {
+        let transport = MemoryTransport::new();
+        let owner = LockInfo::current();
+        let other = LockInfo {
+            pid: owner.pid + 1,
+            ..owner.clone()
+        };
+        let dest = Path::new("/dst");
+
+        assert!(transport.try_lock(dest, &owner).await.unwrap().is_none());
+        assert_eq!(
+            transport.try_lock(dest, &other).await.unwrap(),
+            Some(owner.clone())
+        );
+        assert!(!transport.unlock(dest, &other).await.unwrap());
+        assert!(transport.unlock(dest, &owner).await.unwrap());
+    }
+}
@@ -1,13 +1,25 @@
+pub mod daemon;
 pub mod dual;
 pub mod local;
+#[allow(dead_code)] // For tests and library users; the sy binary doesn't use it
+pub mod memory;
 pub mod router;
 pub mod s3;
+mod sftp;
 pub mod ssh;
 
+use crate::acl::AclMapPolicy;
+use crate::compress::CompressionDecision;
 use crate::error::Result;
-use crate::sync::scanner::FileEntry;
+use crate::fs_util::{AclsApplied, XattrsApplied};
+use crate::owner::PermsUpdate;
+use crate::sync::lock::LockInfo;
+use crate::sync::scanner::{FileEntry, ScanError};
+use crate::sync::staging::StagedMove;
 use async_trait::async_trait;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 /// Transport-agnostic file information
@@ -19,6 +31,23 @@ pub struct FileInfo {
     pub modified: SystemTime,
 }
 
+/// Size, modification time and type of an existing path (see [`Transport::stat_batch`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathStat {
+    pub size: u64,
+    pub modified: SystemTime,
+    pub is_dir: bool,
+}
+
+impl PathStat {
+    pub fn file_info(&self) -> FileInfo {
+        FileInfo {
+            size: self.size,
+            modified: self.modified,
+        }
+    }
+}
+
 /// Result of a file transfer operation
 #[derive(Debug, Clone, Copy)]
 pub struct TransferResult {
@@ -32,6 +61,8 @@ pub struct TransferResult {
     pub transferred_bytes: Option<u64>,
     /// Whether compression was used
     pub compression_used: bool,
+    /// Codec chosen for the file and why (None if no detection ran, e.g. local or delta)
+    pub compression_decision: Option<CompressionDecision>,
 }
 
 impl TransferResult {
@@ -42,6 +73,7 @@ impl TransferResult {
             literal_bytes: None,
             transferred_bytes: None,
             compression_used: false,
+            compression_decision: None,
         }
     }
 
@@ -52,6 +84,7 @@ impl TransferResult {
             literal_bytes: Some(literal_bytes),
             transferred_bytes: None,
             compression_used: false,
+            compression_decision: None,
         }
     }
 
@@ -62,14 +95,29 @@ impl TransferResult {
             literal_bytes: None,
             transferred_bytes: Some(transferred_bytes),
             compression_used: true,
+            compression_decision: None,
         }
     }
 
+    /// Attach the compression decision made for this transfer
+    pub fn with_decision(mut self, decision: CompressionDecision) -> Self {
+        self.compression_decision = Some(decision);
+        self
+    }
+
     /// Returns true if this transfer used delta sync
     pub fn used_delta(&self) -> bool {
         self.delta_operations.is_some()
     }
 
+    /// Bytes sent over the network: the literal data of a delta transfer,
+    /// the compressed size, or everything written for a plain copy
+    pub fn wire_bytes(&self) -> u64 {
+        self.literal_bytes
+            .or(self.transferred_bytes)
+            .unwrap_or(self.bytes_written)
+    }
+
     /// Calculate compression ratio (percentage of file that was literal data)
     /// Returns None if full file copy
     pub fn compression_ratio(&self) -> Option<f64> {
@@ -81,6 +129,81 @@ impl TransferResult {
     }
 }
 
+/// How a sync's transports read and write files, set per transport rather
+/// than per call
+#[derive(Debug, Clone, Copy)]
+pub struct TransportOptions {
+    /// Write destination files at their final path instead of through a temp
+    /// file renamed into place (`--inplace`)
+    pub inplace: bool,
+    /// Send deltas for every update, whatever its size or change ratio
+    /// (`--no-whole-file`); otherwise local copies only use them for large
+    /// files that mostly stayed the same
+    pub always_delta: bool,
+    /// Read local source files without updating their access time, where
+    /// the platform allows (`--open-noatime`)
+    pub open_noatime: bool,
+    /// Keep owners and groups by number between machines instead of
+    /// matching them by name (`--numeric-ids`)
+    pub numeric_ids: bool,
+    /// Bytes read by each content probe when deciding whether to compress a
+    /// file over SSH (`--compression-probe-size`)
+    pub compression_probe_size: usize,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        Self {
+            inplace: false,
+            always_delta: false,
+            open_noatime: false,
+            numeric_ids: false,
+            compression_probe_size: crate::compress::DEFAULT_PROBE_SIZE,
+        }
+    }
+}
+
+/// Write `data` to the local file `path` with modification time `mtime`
+///
+/// The file is written to a temp file renamed into place, unless `inplace`.
+pub(crate) async fn write_local_file(
+    path: &Path,
+    data: &[u8],
+    mtime: SystemTime,
+    inplace: bool,
+) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    // Create parent directories
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let temp = (!inplace).then(|| crate::temp_file::temp_path(path));
+    let guard = temp.as_ref().map(crate::temp_file::TempFileGuard::new);
+    let write_path = temp.as_deref().unwrap_or(path);
+
+    // Write file
+    let mut file = tokio::fs::File::create(write_path).await?;
+    file.write_all(data).await?;
+    file.flush().await?;
+    drop(file);
+
+    // Set mtime
+    filetime::set_file_mtime(write_path, filetime::FileTime::from_system_time(mtime))?;
+
+    if let Some(temp) = &temp {
+        tokio::fs::rename(temp, path).await?;
+    }
+    if let Some(guard) = guard {
+        guard.defuse();
+    }
+    Ok(())
+}
+
+/// Called with (bytes copied so far, total bytes) while a file is copied
+pub type ProgressCallback = std::sync::Arc<dyn Fn(u64, u64) + Send + Sync>;
+
 /// Transport abstraction for local and remote file operations
 ///
 /// This trait provides a unified interface for file operations that works
@@ -94,6 +217,23 @@ pub trait Transport: Send + Sync {
     /// and excluding .git directories.
     async fn scan(&self, path: &Path) -> Result<Vec<FileEntry>>;
 
+    /// Scan a directory, collecting per-entry errors instead of aborting
+    ///
+    /// Unreadable entries (e.g. permission-denied directories) are returned
+    /// alongside the successfully scanned entries. The default falls back to
+    /// `scan()`, which fails on the first error.
+    async fn scan_with_errors(&self, path: &Path) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        Ok((self.scan(path).await?, Vec::new()))
+    }
+
+    /// Scan a directory on the destination side
+    ///
+    /// Same as `scan()` except for transports whose source and destination
+    /// live on different hosts, where `scan()` reads the source.
+    async fn scan_destination(&self, path: &Path) -> Result<Vec<FileEntry>> {
+        self.scan(path).await
+    }
+
     /// Check if a path exists
     async fn exists(&self, path: &Path) -> Result<bool>;
 
@@ -119,15 +259,82 @@ pub trait Transport: Send + Sync {
         })
     }
 
+    /// Stat many paths at once (None for paths that don't exist or can't be read)
+    ///
+    /// Remote transports answer the whole batch in one round-trip.
+    /// Default implementation stats each path in turn.
+    async fn stat_batch(&self, paths: &[PathBuf]) -> Result<Vec<Option<PathStat>>> {
+        let mut stats = Vec::with_capacity(paths.len());
+        for path in paths {
+            let stat = match self.metadata(path).await {
+                Ok(meta) => meta.modified().ok().map(|modified| PathStat {
+                    size: meta.len(),
+                    modified,
+                    is_dir: meta.is_dir(),
+                }),
+                // Transports without local metadata (e.g. S3) only hold files
+                Err(_) => self.file_info(path).await.ok().map(|info| PathStat {
+                    size: info.size,
+                    modified: info.modified,
+                    is_dir: false,
+                }),
+            };
+            stats.push(stat);
+        }
+        Ok(stats)
+    }
+
     /// Create all parent directories for a path
     async fn create_dir_all(&self, path: &Path) -> Result<()>;
 
+    /// Create many directories and their parents, in the order given
+    ///
+    /// Remote transports create the whole batch in one round-trip.
+    /// Default implementation creates each directory in turn.
+    async fn create_dirs(&self, paths: &[PathBuf]) -> Result<()> {
+        for path in paths {
+            self.create_dir_all(path).await?;
+        }
+        Ok(())
+    }
+
     /// Copy a file from source to destination
     ///
     /// This preserves modification time and handles parent directory creation.
     /// Returns the number of bytes actually written.
     async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult>;
 
+    /// Copy a file like `copy_file`, calling `progress` as the copy goes
+    ///
+    /// Default implementation copies without reporting progress; transports
+    /// that stream a file in chunks override it.
+    async fn copy_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        _progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        self.copy_file(source, dest).await
+    }
+
+    /// Copy a file like `copy_file_with_progress`, checkpointing large
+    /// transfers into `partial_dir` so one cut short continues from its temp
+    /// file on the next run (`--resume`, see `resume::partial_dir`)
+    ///
+    /// Default implementation takes no checkpoints.
+    async fn copy_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        _partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        match progress {
+            Some(progress) => self.copy_file_with_progress(source, dest, progress).await,
+            None => self.copy_file(source, dest).await,
+        }
+    }
+
     /// Sync a file using delta sync if destination exists
     ///
     /// This uses the rsync algorithm to transfer only changed blocks when
@@ -142,6 +349,28 @@ pub trait Transport: Send + Sync {
     /// Remove a file or directory
     async fn remove(&self, path: &Path, is_dir: bool) -> Result<()>;
 
+    /// Move a file or directory to `to` on the same side, creating its parent
+    ///
+    /// Used by --trash. Default implementation: not supported.
+    async fn rename(&self, from: &Path, _to: &Path) -> Result<()> {
+        Err(crate::error::SyncError::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("Can't move {} on this transport", from.display()),
+        )))
+    }
+
+    /// Move files staged by --delay-updates into place together, undoing the
+    /// moves if one fails (see `staging::commit_moves`)
+    ///
+    /// Returns how many files were moved. An empty `moves` only checks that
+    /// commits are supported. Default implementation: not supported.
+    async fn commit_staged(&self, _moves: &[StagedMove]) -> Result<usize> {
+        Err(crate::error::SyncError::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "--delay-updates isn't supported on this transport",
+        )))
+    }
+
     /// Create a hard link
     ///
     /// Creates a hard link at `dest` pointing to `source`.
@@ -153,6 +382,74 @@ pub trait Transport: Send + Sync {
     /// Creates a symbolic link at `dest` pointing to `target`.
     async fn create_symlink(&self, target: &Path, dest: &Path) -> Result<()>;
 
+    /// Apply extended attributes to a file
+    ///
+    /// Attributes that fail to apply are skipped and returned in `failed`, and
+    /// privileged attributes (e.g. `security.capability`) the destination side
+    /// lacks the privileges for in `skipped_privileged`.
+    /// Default implementation writes to the local filesystem.
+    async fn set_xattrs(
+        &self,
+        path: &Path,
+        xattrs: &HashMap<String, Vec<u8>>,
+    ) -> Result<XattrsApplied> {
+        let path = path.to_path_buf();
+        let xattrs = xattrs.clone();
+        tokio::task::spawn_blocking(move || crate::fs_util::apply_xattrs(&path, &xattrs))
+            .await
+            .map_err(|e| crate::error::SyncError::Io(std::io::Error::other(e.to_string())))
+    }
+
+    /// Apply ACLs to a file (text format, one entry per line)
+    ///
+    /// Entries that fail to parse or apply are skipped and returned in
+    /// `failed`. ACLs from a different ACL model than the destination's are
+    /// handled per `policy`.
+    /// Default implementation writes to the local filesystem.
+    async fn set_acls(
+        &self,
+        path: &Path,
+        acls: &[u8],
+        policy: AclMapPolicy,
+    ) -> Result<AclsApplied> {
+        let path = path.to_path_buf();
+        let acls = acls.to_vec();
+        tokio::task::spawn_blocking(move || crate::fs_util::apply_acls(&path, &acls, policy))
+            .await
+            .map_err(|e| crate::error::SyncError::Io(std::io::Error::other(e.to_string())))?
+    }
+
+    /// Restore access and/or creation time of a file (`--atimes`, `--crtimes`)
+    ///
+    /// The modification time is not changed. Creation times are ignored where
+    /// the destination platform can't set them.
+    /// Default implementation writes to the local filesystem.
+    async fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<std::time::SystemTime>,
+        crtime: Option<std::time::SystemTime>,
+    ) -> Result<()> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || crate::fs_util::apply_times(&path, atime, crtime))
+            .await
+            .map_err(|e| crate::error::SyncError::Io(std::io::Error::other(e.to_string())))??;
+        Ok(())
+    }
+
+    /// Set the permission bits, owner and/or group of an entry (`-p`, `-o`, `-g`)
+    ///
+    /// Ids are this machine's; remote transports send the owner and group
+    /// names along so the other side can set its own ids for them. Symlinks
+    /// aren't followed. Default implementation writes to the local filesystem.
+    async fn set_permissions(&self, path: &Path, update: PermsUpdate) -> Result<()> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || crate::owner::apply(&path, &update))
+            .await
+            .map_err(|e| crate::error::SyncError::Io(std::io::Error::other(e.to_string())))??;
+        Ok(())
+    }
+
     /// Read file contents into a vector
     ///
     /// This is used for cross-transport operations (e.g., remote→local).
@@ -176,23 +473,7 @@ pub trait Transport: Send + Sync {
         data: &[u8],
         mtime: std::time::SystemTime,
     ) -> Result<()> {
-        use tokio::io::AsyncWriteExt;
-
-        // Create parent directories
-        if let Some(parent) = path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
-        }
-
-        // Write file
-        let mut file = tokio::fs::File::create(path).await?;
-        file.write_all(data).await?;
-        file.flush().await?;
-        drop(file);
-
-        // Set mtime
-        filetime::set_file_mtime(path, filetime::FileTime::from_system_time(mtime))?;
-
-        Ok(())
+        write_local_file(path, data, mtime, false).await
     }
 
     /// Get modification time for a file
@@ -218,7 +499,7 @@ pub trait Transport: Send + Sync {
         &self,
         source: &Path,
         dest: &Path,
-        progress_callback: Option<std::sync::Arc<dyn Fn(u64, u64) + Send + Sync>>,
+        progress_callback: Option<ProgressCallback>,
     ) -> Result<TransferResult> {
         // Default implementation: fall back to read_file/write_file for simplicity
         // Implementations can override for true streaming
@@ -236,6 +517,111 @@ pub trait Transport: Send + Sync {
 
         Ok(TransferResult::new(total_size))
     }
+
+    /// Whether this transport operates on a remote host
+    ///
+    /// DualTransport uses this to decide which side performs a cross-transport copy.
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    /// Whether destination paths are on a remote host
+    ///
+    /// The engine lists such destinations with `scan_destination` instead of
+    /// reading them from the local filesystem, so deletions (and what a dry
+    /// run says it would delete) match what is actually there.
+    fn is_remote_destination(&self) -> bool {
+        self.is_remote()
+    }
+
+    /// Download a file from this transport to the local filesystem
+    ///
+    /// Used for remote→local transfers, where the source transport owns the path
+    /// being read. Remote transports can override this to compress on the wire.
+    /// Default implementation uses copy_file_streaming().
+    async fn download_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        self.copy_file_streaming(source, dest, None).await
+    }
+
+    /// Download a file like `download_file`, calling `progress` as it arrives
+    ///
+    /// Default implementation downloads without reporting progress;
+    /// transports that stream a download in chunks override it.
+    async fn download_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        _progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        self.download_file(source, dest).await
+    }
+
+    /// Download a file like `download_file_with_progress`, checkpointing
+    /// large downloads into `partial_dir` (`--resume`)
+    ///
+    /// Default implementation takes no checkpoints.
+    async fn download_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        _partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        match progress {
+            Some(progress) => {
+                self.download_file_with_progress(source, dest, progress)
+                    .await
+            }
+            None => self.download_file(source, dest).await,
+        }
+    }
+
+    /// Try to take the advisory sync lock on a destination directory
+    ///
+    /// Returns `None` if acquired, or the current holder if already locked.
+    /// Default implementation creates the lock file on the local filesystem.
+    async fn try_lock(&self, dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+        crate::sync::lock::try_lock(dest, owner)
+    }
+
+    /// Release the advisory sync lock if `owner` still holds it
+    ///
+    /// Default implementation removes the local lock file.
+    async fn unlock(&self, dest: &Path, owner: &LockInfo) -> Result<bool> {
+        crate::sync::lock::unlock(dest, owner)
+    }
+
+    /// Fail if files can't be written into the destination `dest`
+    ///
+    /// Checked before a sync plans anything, so a read-only destination gives
+    /// one actionable error instead of a permission error per file.
+    /// Default implementation probes the local filesystem.
+    async fn check_writable(&self, dest: &Path) -> Result<()> {
+        match crate::fs_util::unwritable_reason(dest) {
+            Some((path, reason)) => {
+                Err(crate::error::SyncError::DestinationNotWritable { path, reason })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Version of the sy-remote serving this transport
+    ///
+    /// `None` for transports without a remote helper. Fails if the helper
+    /// can't be run, which every remote operation would then hit too.
+    async fn remote_version(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// How far the destination's clock runs ahead of the source's, in milliseconds
+    ///
+    /// Remote transports report how far the remote host is ahead of this one,
+    /// measured during the sy-remote handshake; DualTransport turns that into
+    /// destination minus source. `None` when both sides share a clock or the
+    /// remote side can't tell.
+    async fn clock_skew_ms(&self) -> Option<i64> {
+        None
+    }
 }
 
 // Implement Transport for Arc<T> where T: Transport
@@ -246,6 +632,14 @@ impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
         (**self).scan(path).await
     }
 
+    async fn scan_with_errors(&self, path: &Path) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        (**self).scan_with_errors(path).await
+    }
+
+    async fn scan_destination(&self, path: &Path) -> Result<Vec<FileEntry>> {
+        (**self).scan_destination(path).await
+    }
+
     async fn exists(&self, path: &Path) -> Result<bool> {
         (**self).exists(path).await
     }
@@ -258,14 +652,45 @@ impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
         (**self).file_info(path).await
     }
 
+    async fn stat_batch(&self, paths: &[PathBuf]) -> Result<Vec<Option<PathStat>>> {
+        (**self).stat_batch(paths).await
+    }
+
     async fn create_dir_all(&self, path: &Path) -> Result<()> {
         (**self).create_dir_all(path).await
     }
 
+    async fn create_dirs(&self, paths: &[PathBuf]) -> Result<()> {
+        (**self).create_dirs(paths).await
+    }
+
     async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
         (**self).copy_file(source, dest).await
     }
 
+    async fn copy_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        (**self)
+            .copy_file_with_progress(source, dest, progress)
+            .await
+    }
+
+    async fn copy_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        (**self)
+            .copy_file_resumable(source, dest, progress, partial_dir)
+            .await
+    }
+
     async fn sync_file_with_delta(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
         (**self).sync_file_with_delta(source, dest).await
     }
@@ -274,6 +699,14 @@ impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
         (**self).remove(path, is_dir).await
     }
 
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        (**self).rename(from, to).await
+    }
+
+    async fn commit_staged(&self, moves: &[StagedMove]) -> Result<usize> {
+        (**self).commit_staged(moves).await
+    }
+
     async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
         (**self).create_hardlink(source, dest).await
     }
@@ -282,6 +715,36 @@ impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
         (**self).create_symlink(target, dest).await
     }
 
+    async fn set_xattrs(
+        &self,
+        path: &Path,
+        xattrs: &HashMap<String, Vec<u8>>,
+    ) -> Result<XattrsApplied> {
+        (**self).set_xattrs(path, xattrs).await
+    }
+
+    async fn set_acls(
+        &self,
+        path: &Path,
+        acls: &[u8],
+        policy: AclMapPolicy,
+    ) -> Result<AclsApplied> {
+        (**self).set_acls(path, acls, policy).await
+    }
+
+    async fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<std::time::SystemTime>,
+        crtime: Option<std::time::SystemTime>,
+    ) -> Result<()> {
+        (**self).set_times(path, atime, crtime).await
+    }
+
+    async fn set_permissions(&self, path: &Path, update: PermsUpdate) -> Result<()> {
+        (**self).set_permissions(path, update).await
+    }
+
     async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
         (**self).read_file(path).await
     }
@@ -303,10 +766,54 @@ impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
         &self,
         source: &Path,
         dest: &Path,
-        progress_callback: Option<std::sync::Arc<dyn Fn(u64, u64) + Send + Sync>>,
+        progress_callback: Option<ProgressCallback>,
     ) -> Result<TransferResult> {
         (**self)
             .copy_file_streaming(source, dest, progress_callback)
             .await
     }
+
+    fn is_remote(&self) -> bool {
+        (**self).is_remote()
+    }
+
+    fn is_remote_destination(&self) -> bool {
+        (**self).is_remote_destination()
+    }
+
+    async fn download_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        (**self).download_file(source, dest).await
+    }
+
+    async fn download_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        (**self)
+            .download_file_resumable(source, dest, progress, partial_dir)
+            .await
+    }
+
+    async fn try_lock(&self, dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+        (**self).try_lock(dest, owner).await
+    }
+
+    async fn unlock(&self, dest: &Path, owner: &LockInfo) -> Result<bool> {
+        (**self).unlock(dest, owner).await
+    }
+
+    async fn check_writable(&self, dest: &Path) -> Result<()> {
+        (**self).check_writable(dest).await
+    }
+
+    async fn remote_version(&self) -> Result<Option<String>> {
+        (**self).remote_version().await
+    }
+
+    async fn clock_skew_ms(&self) -> Option<i64> {
+        (**self).clock_skew_ms().await
+    }
 }
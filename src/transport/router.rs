@@ -1,13 +1,24 @@
 use super::{
-    dual::DualTransport, local::LocalTransport, s3::S3Transport, ssh::SshTransport, TransferResult,
-    Transport,
+    daemon::DaemonTransport, dual::DualTransport, local::LocalTransport, s3::S3Transport,
+    ssh::SshTransport, PathStat, ProgressCallback, TransferResult, Transport, TransportOptions,
 };
+use crate::acl::AclMapPolicy;
+use crate::cli::DanglingMode;
+use crate::compress::CompressionDetection;
 use crate::error::Result;
+use crate::fs_util::{AclsApplied, XattrsApplied};
 use crate::integrity::{ChecksumType, IntegrityVerifier};
+use crate::owner::PermsUpdate;
 use crate::path::SyncPath;
-use crate::ssh::config::{parse_ssh_config, SshConfig};
+use crate::quic;
+use crate::ssh;
+use crate::ssh::config::host_config;
+use crate::ssh::host_info::host_key;
+use crate::sync::lock::LockInfo;
+use crate::sync::staging::StagedMove;
 use async_trait::async_trait;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Router that dispatches to the appropriate transport based on path types
 ///
@@ -26,61 +37,154 @@ impl TransportRouter {
     /// - Remote → Local: Use DualTransport (SSH for source, Local for dest)
     /// - Local → Remote: Use DualTransport (Local for source, SSH for dest)
     /// - Remote → Remote: Not supported yet (would require two SSH connections)
+    /// - Local ↔ sy daemon module: Use DualTransport with DaemonTransport for the
+    ///   daemon side (secret from `SY_DAEMON_SECRET`), over QUIC with --quic
     ///
     /// `pool_size` controls the number of SSH connections in the pool for parallel transfers.
     /// Should typically match the number of parallel workers.
+    ///
+    /// `follow_links` makes local scans descend into symlinked directories (--links follow).
+    ///
+    /// `dangling_links` decides what local scans do with symlinks whose target
+    /// doesn't exist (--dangling-links).
+    ///
+    /// `max_depth` limits how deep local and SSH source scans recurse (--max-depth).
+    ///
+    /// `compression_detection` decides which SSH transfers are compressed, in both
+    /// directions (--compress, --compression-detection).
+    ///
+    /// `remote_binary` is where sy-remote lives on the SSH host (--remote-binary);
+    /// without it, sy-remote is looked for on the remote PATH and in common
+    /// install locations.
+    ///
+    /// `quic` reaches daemon paths over QUIC with these options (--quic),
+    /// instead of over TCP.
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         source: &SyncPath,
         destination: &SyncPath,
         checksum_type: ChecksumType,
         verify_on_write: bool,
         pool_size: usize,
+        follow_links: bool,
+        dangling_links: DanglingMode,
+        max_depth: Option<usize>,
+        compression_detection: CompressionDetection,
+        remote_binary: Option<&str>,
+        options: TransportOptions,
+        ssh: &ssh::config::ClientOptions,
+        quic: Option<&quic::ClientOptions>,
     ) -> Result<Self> {
-        let verifier = IntegrityVerifier::new(checksum_type, verify_on_write);
+        let verifier = IntegrityVerifier::new(checksum_type, verify_on_write)
+            .open_noatime(options.open_noatime);
 
         match (source, destination) {
             (SyncPath::Local(_), SyncPath::Local(_)) => {
                 // Both local: use local transport
-                Ok(TransportRouter::Local(LocalTransport::with_verifier(
-                    verifier,
-                )))
+                Ok(TransportRouter::Local(
+                    LocalTransport::with_verifier(verifier)
+                        .follow_links(follow_links)
+                        .dangling_links(dangling_links)
+                        .max_depth(max_depth)
+                        .options(options),
+                ))
             }
             (SyncPath::Local(_), SyncPath::Remote { host, user, .. }) => {
                 // Local → Remote: use DualTransport
-                let config = if let Some(user) = user {
-                    SshConfig {
-                        hostname: host.clone(),
-                        user: user.clone(),
-                        ..Default::default()
-                    }
-                } else {
-                    parse_ssh_config(host)?
-                };
-
-                let source_transport = Box::new(LocalTransport::with_verifier(verifier.clone()));
-                let dest_transport =
-                    Box::new(SshTransport::with_pool_size(&config, pool_size).await?);
+                let config = host_config(host, user.as_deref(), ssh)?;
+
+                let source_transport = Box::new(
+                    LocalTransport::with_verifier(verifier.clone())
+                        .follow_links(follow_links)
+                        .dangling_links(dangling_links)
+                        .max_depth(max_depth)
+                        .options(options),
+                );
+                let dest_transport = Box::new(
+                    SshTransport::with_pool_size(&config, pool_size)
+                        .await?
+                        .host_info(host_key(host, user.as_deref()))
+                        .remote_binary(remote_binary)
+                        .await
+                        .compression_detection(compression_detection)
+                        .options(options),
+                );
                 let dual = DualTransport::new(source_transport, dest_transport);
                 Ok(TransportRouter::Dual(dual))
             }
             (SyncPath::Remote { host, user, .. }, SyncPath::Local(_)) => {
                 // Remote → Local: use DualTransport
-                let config = if let Some(user) = user {
-                    SshConfig {
-                        hostname: host.clone(),
-                        user: user.clone(),
-                        ..Default::default()
-                    }
-                } else {
-                    parse_ssh_config(host)?
-                };
-
-                let source_transport =
-                    Box::new(SshTransport::with_pool_size(&config, pool_size).await?);
-                let dest_transport = Box::new(LocalTransport::with_verifier(verifier));
+                let config = host_config(host, user.as_deref(), ssh)?;
+
+                let source_transport = Box::new(
+                    SshTransport::with_pool_size(&config, pool_size)
+                        .await?
+                        .host_info(host_key(host, user.as_deref()))
+                        .remote_binary(remote_binary)
+                        .await
+                        .max_depth(max_depth)
+                        .compression_detection(compression_detection)
+                        .options(options),
+                );
+                let dest_transport =
+                    Box::new(LocalTransport::with_verifier(verifier).options(options));
+                let dual = DualTransport::new(source_transport, dest_transport);
+                Ok(TransportRouter::Dual(dual))
+            }
+            (
+                SyncPath::Local(_),
+                SyncPath::Daemon {
+                    host,
+                    port,
+                    user,
+                    module,
+                    ..
+                },
+            ) => {
+                // Local → daemon: use DualTransport
+                let source_transport = Box::new(
+                    LocalTransport::with_verifier(verifier.clone())
+                        .follow_links(follow_links)
+                        .dangling_links(dangling_links)
+                        .max_depth(max_depth)
+                        .options(options),
+                );
+                let dest_transport = Box::new(
+                    connect_daemon(host, *port, user.as_deref(), module, quic)
+                        .await?
+                        .options(options),
+                );
                 let dual = DualTransport::new(source_transport, dest_transport);
                 Ok(TransportRouter::Dual(dual))
             }
+            (
+                SyncPath::Daemon {
+                    host,
+                    port,
+                    user,
+                    module,
+                    ..
+                },
+                SyncPath::Local(_),
+            ) => {
+                // Daemon → local: use DualTransport
+                let source_transport = Box::new(
+                    connect_daemon(host, *port, user.as_deref(), module, quic)
+                        .await?
+                        .max_depth(max_depth)
+                        .options(options),
+                );
+                let dest_transport =
+                    Box::new(LocalTransport::with_verifier(verifier).options(options));
+                let dual = DualTransport::new(source_transport, dest_transport);
+                Ok(TransportRouter::Dual(dual))
+            }
+            (SyncPath::Daemon { .. }, _) | (_, SyncPath::Daemon { .. }) => {
+                // Daemon ↔ SSH/S3/daemon: not supported
+                Err(crate::error::SyncError::Io(std::io::Error::other(
+                    "sy daemon modules can only be synced to or from a local path",
+                )))
+            }
             (SyncPath::Remote { .. }, SyncPath::Remote { .. }) => {
                 // Both remote: not supported yet
                 Err(crate::error::SyncError::Io(std::io::Error::other(
@@ -103,7 +207,8 @@ impl TransportRouter {
                     region.clone(),
                     endpoint.clone(),
                 )
-                .await?;
+                .await?
+                .options(options);
                 Ok(TransportRouter::S3(s3_transport))
             }
             (
@@ -122,7 +227,8 @@ impl TransportRouter {
                     region.clone(),
                     endpoint.clone(),
                 )
-                .await?;
+                .await?
+                .options(options);
                 Ok(TransportRouter::S3(s3_transport))
             }
             (SyncPath::S3 { .. }, SyncPath::S3 { .. }) => {
@@ -140,6 +246,37 @@ impl TransportRouter {
             }
         }
     }
+
+    /// The transport that reads the source, for work that only involves the
+    /// source side (DualTransport stats and checks the destination)
+    pub fn source_transport(&self) -> &dyn Transport {
+        match self {
+            TransportRouter::Local(t) => t,
+            TransportRouter::Dual(t) => t.source(),
+            TransportRouter::S3(t) => t,
+        }
+    }
+}
+
+/// Connect to a daemon module, over QUIC to `sy-remote serve-quic` with --quic
+async fn connect_daemon(
+    host: &str,
+    port: Option<u16>,
+    user: Option<&str>,
+    module: &str,
+    quic: Option<&quic::ClientOptions>,
+) -> Result<DaemonTransport> {
+    let secret = std::env::var(crate::daemon::SECRET_ENV).ok();
+    match quic {
+        Some(options) => {
+            let port = port.unwrap_or(quic::DEFAULT_PORT);
+            DaemonTransport::connect_quic(host, port, user, module, secret, options).await
+        }
+        None => {
+            let port = port.unwrap_or(crate::daemon::DEFAULT_PORT);
+            DaemonTransport::connect(host, port, user, module, secret).await
+        }
+    }
 }
 
 #[async_trait]
@@ -152,6 +289,28 @@ impl Transport for TransportRouter {
         }
     }
 
+    async fn scan_destination(&self, path: &Path) -> Result<Vec<crate::sync::scanner::FileEntry>> {
+        match self {
+            TransportRouter::Local(t) => t.scan_destination(path).await,
+            TransportRouter::Dual(t) => t.scan_destination(path).await,
+            TransportRouter::S3(t) => t.scan_destination(path).await,
+        }
+    }
+
+    async fn scan_with_errors(
+        &self,
+        path: &Path,
+    ) -> Result<(
+        Vec<crate::sync::scanner::FileEntry>,
+        Vec<crate::sync::scanner::ScanError>,
+    )> {
+        match self {
+            TransportRouter::Local(t) => t.scan_with_errors(path).await,
+            TransportRouter::Dual(t) => t.scan_with_errors(path).await,
+            TransportRouter::S3(t) => t.scan_with_errors(path).await,
+        }
+    }
+
     async fn exists(&self, path: &Path) -> Result<bool> {
         match self {
             TransportRouter::Local(t) => t.exists(path).await,
@@ -176,6 +335,14 @@ impl Transport for TransportRouter {
         }
     }
 
+    async fn stat_batch(&self, paths: &[PathBuf]) -> Result<Vec<Option<PathStat>>> {
+        match self {
+            TransportRouter::Local(t) => t.stat_batch(paths).await,
+            TransportRouter::Dual(t) => t.stat_batch(paths).await,
+            TransportRouter::S3(t) => t.stat_batch(paths).await,
+        }
+    }
+
     async fn create_dir_all(&self, path: &Path) -> Result<()> {
         match self {
             TransportRouter::Local(t) => t.create_dir_all(path).await,
@@ -184,6 +351,14 @@ impl Transport for TransportRouter {
         }
     }
 
+    async fn create_dirs(&self, paths: &[PathBuf]) -> Result<()> {
+        match self {
+            TransportRouter::Local(t) => t.create_dirs(paths).await,
+            TransportRouter::Dual(t) => t.create_dirs(paths).await,
+            TransportRouter::S3(t) => t.create_dirs(paths).await,
+        }
+    }
+
     async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
         match self {
             TransportRouter::Local(t) => t.copy_file(source, dest).await,
@@ -192,6 +367,42 @@ impl Transport for TransportRouter {
         }
     }
 
+    async fn copy_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        match self {
+            TransportRouter::Local(t) => t.copy_file_with_progress(source, dest, progress).await,
+            TransportRouter::Dual(t) => t.copy_file_with_progress(source, dest, progress).await,
+            TransportRouter::S3(t) => t.copy_file_with_progress(source, dest, progress).await,
+        }
+    }
+
+    async fn copy_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        match self {
+            TransportRouter::Local(t) => {
+                t.copy_file_resumable(source, dest, progress, partial_dir)
+                    .await
+            }
+            TransportRouter::Dual(t) => {
+                t.copy_file_resumable(source, dest, progress, partial_dir)
+                    .await
+            }
+            TransportRouter::S3(t) => {
+                t.copy_file_resumable(source, dest, progress, partial_dir)
+                    .await
+            }
+        }
+    }
+
     async fn sync_file_with_delta(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
         match self {
             TransportRouter::Local(t) => t.sync_file_with_delta(source, dest).await,
@@ -208,6 +419,22 @@ impl Transport for TransportRouter {
         }
     }
 
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        match self {
+            TransportRouter::Local(t) => t.rename(from, to).await,
+            TransportRouter::Dual(t) => t.rename(from, to).await,
+            TransportRouter::S3(t) => t.rename(from, to).await,
+        }
+    }
+
+    async fn commit_staged(&self, moves: &[StagedMove]) -> Result<usize> {
+        match self {
+            TransportRouter::Local(t) => t.commit_staged(moves).await,
+            TransportRouter::Dual(t) => t.commit_staged(moves).await,
+            TransportRouter::S3(t) => t.commit_staged(moves).await,
+        }
+    }
+
     async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
         match self {
             TransportRouter::Local(t) => t.create_hardlink(source, dest).await,
@@ -223,4 +450,173 @@ impl Transport for TransportRouter {
             TransportRouter::S3(t) => t.create_symlink(target, dest).await,
         }
     }
+
+    async fn set_xattrs(
+        &self,
+        path: &Path,
+        xattrs: &HashMap<String, Vec<u8>>,
+    ) -> Result<XattrsApplied> {
+        match self {
+            TransportRouter::Local(t) => t.set_xattrs(path, xattrs).await,
+            TransportRouter::Dual(t) => t.set_xattrs(path, xattrs).await,
+            TransportRouter::S3(t) => t.set_xattrs(path, xattrs).await,
+        }
+    }
+
+    async fn set_acls(
+        &self,
+        path: &Path,
+        acls: &[u8],
+        policy: AclMapPolicy,
+    ) -> Result<AclsApplied> {
+        match self {
+            TransportRouter::Local(t) => t.set_acls(path, acls, policy).await,
+            TransportRouter::Dual(t) => t.set_acls(path, acls, policy).await,
+            TransportRouter::S3(t) => t.set_acls(path, acls, policy).await,
+        }
+    }
+
+    async fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<std::time::SystemTime>,
+        crtime: Option<std::time::SystemTime>,
+    ) -> Result<()> {
+        match self {
+            TransportRouter::Local(t) => t.set_times(path, atime, crtime).await,
+            TransportRouter::Dual(t) => t.set_times(path, atime, crtime).await,
+            TransportRouter::S3(t) => t.set_times(path, atime, crtime).await,
+        }
+    }
+
+    async fn set_permissions(&self, path: &Path, update: PermsUpdate) -> Result<()> {
+        match self {
+            TransportRouter::Local(t) => t.set_permissions(path, update).await,
+            TransportRouter::Dual(t) => t.set_permissions(path, update).await,
+            TransportRouter::S3(t) => t.set_permissions(path, update).await,
+        }
+    }
+
+    async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        match self {
+            TransportRouter::Local(t) => t.read_file(path).await,
+            TransportRouter::Dual(t) => t.read_file(path).await,
+            TransportRouter::S3(t) => t.read_file(path).await,
+        }
+    }
+
+    async fn write_file(
+        &self,
+        path: &Path,
+        data: &[u8],
+        mtime: std::time::SystemTime,
+    ) -> Result<()> {
+        match self {
+            TransportRouter::Local(t) => t.write_file(path, data, mtime).await,
+            TransportRouter::Dual(t) => t.write_file(path, data, mtime).await,
+            TransportRouter::S3(t) => t.write_file(path, data, mtime).await,
+        }
+    }
+
+    fn is_remote(&self) -> bool {
+        match self {
+            TransportRouter::Local(t) => t.is_remote(),
+            TransportRouter::Dual(t) => t.is_remote(),
+            TransportRouter::S3(t) => t.is_remote(),
+        }
+    }
+
+    fn is_remote_destination(&self) -> bool {
+        match self {
+            TransportRouter::Local(t) => t.is_remote_destination(),
+            TransportRouter::Dual(t) => t.is_remote_destination(),
+            TransportRouter::S3(t) => t.is_remote_destination(),
+        }
+    }
+
+    async fn download_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        match self {
+            TransportRouter::Local(t) => t.download_file(source, dest).await,
+            TransportRouter::Dual(t) => t.download_file(source, dest).await,
+            TransportRouter::S3(t) => t.download_file(source, dest).await,
+        }
+    }
+
+    async fn download_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        match self {
+            TransportRouter::Local(t) => {
+                t.download_file_with_progress(source, dest, progress).await
+            }
+            TransportRouter::Dual(t) => t.download_file_with_progress(source, dest, progress).await,
+            TransportRouter::S3(t) => t.download_file_with_progress(source, dest, progress).await,
+        }
+    }
+
+    async fn download_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        match self {
+            TransportRouter::Local(t) => {
+                t.download_file_resumable(source, dest, progress, partial_dir)
+                    .await
+            }
+            TransportRouter::Dual(t) => {
+                t.download_file_resumable(source, dest, progress, partial_dir)
+                    .await
+            }
+            TransportRouter::S3(t) => {
+                t.download_file_resumable(source, dest, progress, partial_dir)
+                    .await
+            }
+        }
+    }
+
+    async fn try_lock(&self, dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+        match self {
+            TransportRouter::Local(t) => t.try_lock(dest, owner).await,
+            TransportRouter::Dual(t) => t.try_lock(dest, owner).await,
+            TransportRouter::S3(t) => t.try_lock(dest, owner).await,
+        }
+    }
+
+    async fn unlock(&self, dest: &Path, owner: &LockInfo) -> Result<bool> {
+        match self {
+            TransportRouter::Local(t) => t.unlock(dest, owner).await,
+            TransportRouter::Dual(t) => t.unlock(dest, owner).await,
+            TransportRouter::S3(t) => t.unlock(dest, owner).await,
+        }
+    }
+
+    async fn check_writable(&self, dest: &Path) -> Result<()> {
+        match self {
+            TransportRouter::Local(t) => t.check_writable(dest).await,
+            TransportRouter::Dual(t) => t.check_writable(dest).await,
+            TransportRouter::S3(t) => t.check_writable(dest).await,
+        }
+    }
+
+    async fn remote_version(&self) -> Result<Option<String>> {
+        match self {
+            TransportRouter::Local(t) => t.remote_version().await,
+            TransportRouter::Dual(t) => t.remote_version().await,
+            TransportRouter::S3(t) => t.remote_version().await,
+        }
+    }
+
+    async fn clock_skew_ms(&self) -> Option<i64> {
+        match self {
+            TransportRouter::Local(t) => t.clock_skew_ms().await,
+            TransportRouter::Dual(t) => t.clock_skew_ms().await,
+            TransportRouter::S3(t) => t.clock_skew_ms().await,
+        }
+    }
 }
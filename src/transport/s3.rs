@@ -1,5 +1,7 @@
-use super::{FileInfo, TransferResult, Transport};
+use super::{FileInfo, TransferResult, Transport, TransportOptions};
 use crate::error::{Result, SyncError};
+use crate::owner::PermsUpdate;
+use crate::sync::lock::LockInfo;
 use crate::sync::scanner::FileEntry;
 use async_trait::async_trait;
 use aws_sdk_s3::Client;
@@ -14,6 +16,7 @@ pub struct S3Transport {
     client: Client,
     bucket: String,
     prefix: String, // Key prefix for all operations
+    options: TransportOptions,
 }
 
 impl S3Transport {
@@ -58,9 +61,16 @@ impl S3Transport {
             client,
             bucket,
             prefix,
+            options: TransportOptions::default(),
         })
     }
 
+    /// How local files are read for upload (--open-noatime)
+    pub fn options(mut self, options: TransportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     /// Convert a local path to an S3 key
     fn path_to_key(&self, path: &Path) -> String {
         let path_str = path.to_string_lossy();
@@ -87,15 +97,14 @@ impl S3Transport {
 
     /// Upload a large file using multipart upload
     ///
-    /// This is required for files >5GB and recommended for files >100MB
+    /// This is required for files >5GB and recommended for files >100MB.
+    /// A failed upload is aborted, so its parts don't linger in the bucket.
     async fn copy_file_multipart(
         &self,
         source: &Path,
         dest: &Path,
         total_size: u64,
     ) -> Result<TransferResult> {
-        use tokio::io::AsyncReadExt;
-
         let key = self.path_to_key(dest);
 
         // Start multipart upload
@@ -117,30 +126,65 @@ impl S3Transport {
             .upload_id()
             .ok_or_else(|| SyncError::Io(std::io::Error::other("No upload ID returned")))?;
 
-        // Upload parts (5MB chunks, S3 minimum)
-        const PART_SIZE: usize = 5 * 1024 * 1024; // 5 MB
-        let mut file = tokio::fs::File::open(source).await?;
+        if let Err(e) = self
+            .upload_parts(source, &key, upload_id, part_size(total_size))
+            .await
+        {
+            if let Err(abort_err) = self
+                .client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(&key)
+                .upload_id(upload_id)
+                .send()
+                .await
+            {
+                tracing::warn!("Failed to abort multipart upload of {}: {}", key, abort_err);
+            }
+            return Err(e);
+        }
+
+        Ok(TransferResult::new(total_size))
+    }
+
+    /// Upload `source` in parts of `part_size` bytes and complete the upload
+    async fn upload_parts(
+        &self,
+        source: &Path,
+        key: &str,
+        upload_id: &str,
+        part_size: usize,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let mut file = tokio::fs::File::from_std(crate::fs_util::open_source(
+            source,
+            self.options.open_noatime,
+        )?);
         let mut part_number = 1;
         let mut parts = Vec::new();
-        let mut buffer = vec![0u8; PART_SIZE];
+        let mut buffer = Vec::with_capacity(part_size);
 
         loop {
-            let bytes_read = file.read(&mut buffer).await?;
-            if bytes_read == 0 {
+            // Fill the whole part: only the last one may be under the minimum
+            buffer.clear();
+            (&mut file)
+                .take(part_size as u64)
+                .read_to_end(&mut buffer)
+                .await?;
+            if buffer.is_empty() {
                 break; // EOF
             }
 
-            let part_data = &buffer[..bytes_read];
-
             // Upload this part
             let upload_part_response = self
                 .client
                 .upload_part()
                 .bucket(&self.bucket)
-                .key(&key)
+                .key(key)
                 .upload_id(upload_id)
                 .part_number(part_number)
-                .body(ByteStream::from(part_data.to_vec()))
+                .body(ByteStream::from(buffer.clone()))
                 .send()
                 .await
                 .map_err(|e| {
@@ -173,7 +217,7 @@ impl S3Transport {
         self.client
             .complete_multipart_upload()
             .bucket(&self.bucket)
-            .key(&key)
+            .key(key)
             .upload_id(upload_id)
             .multipart_upload(completed_upload)
             .send()
@@ -185,10 +229,22 @@ impl S3Transport {
                 )))
             })?;
 
-        Ok(TransferResult::new(total_size))
+        Ok(())
     }
 }
 
+/// Smallest part S3 accepts, other than the last
+const MIN_PART_SIZE: u64 = 5 * 1024 * 1024; // 5 MB
+
+/// Most parts S3 accepts in one multipart upload
+const MAX_PARTS: u64 = 10_000;
+
+/// Part size for uploading `total_size` bytes: the 5MB minimum, or larger
+/// when the file wouldn't fit in 10,000 parts (files over ~48GB)
+fn part_size(total_size: u64) -> usize {
+    MIN_PART_SIZE.max(total_size.div_ceil(MAX_PARTS)) as usize
+}
+
 #[async_trait]
 impl Transport for S3Transport {
     async fn scan(&self, _path: &Path) -> Result<Vec<FileEntry>> {
@@ -249,6 +305,9 @@ impl Transport for S3Transport {
                     nlink: 1,
                     acls: None,
                     bsd_flags: None,
+                    atime: None,
+                    crtime: None,
+                    perms: None,
                 });
             }
 
@@ -399,6 +458,11 @@ impl Transport for S3Transport {
         )))
     }
 
+    async fn set_permissions(&self, _path: &Path, _update: PermsUpdate) -> Result<()> {
+        // Objects have no Unix permissions or owners
+        Ok(())
+    }
+
     async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
         let key = self.path_to_key(path);
 
@@ -450,4 +514,36 @@ impl Transport for S3Transport {
         let info = self.file_info(path).await?;
         Ok(info.modified)
     }
+
+    async fn try_lock(&self, dest: &Path, _owner: &LockInfo) -> Result<Option<LockInfo>> {
+        // No atomic create-if-absent on plain PutObject, so S3 destinations aren't locked
+        tracing::debug!("Skipping sync lock for S3 destination {}", dest.display());
+        Ok(None)
+    }
+
+    async fn unlock(&self, _dest: &Path, _owner: &LockInfo) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn check_writable(&self, _dest: &Path) -> Result<()> {
+        // Bucket permissions only show on the first PutObject
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_size() {
+        assert_eq!(part_size(200 * 1024 * 1024), MIN_PART_SIZE as usize);
+        assert_eq!(part_size(MIN_PART_SIZE * MAX_PARTS), MIN_PART_SIZE as usize);
+
+        // 100GB needs parts over 5MB to stay within 10,000
+        let size = 100 * 1024 * 1024 * 1024;
+        let part = part_size(size) as u64;
+        assert!(part > MIN_PART_SIZE);
+        assert!(size.div_ceil(part) <= MAX_PARTS);
+    }
 }
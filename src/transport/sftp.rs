@@ -0,0 +1,308 @@
+use super::PathStat;
+use crate::error::{Result, SyncError};
+use crate::owner::{FilePerms, PermsUpdate};
+use crate::sync::scanner::{FileEntry, ScanError};
+use ssh2::{ErrorCode, FileStat, FileType, Sftp};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Remote operations over plain SFTP, for SSH hosts without sy-remote (e.g.
+// appliances and accounts jailed to internal-sftp). No remote command is
+// run, so there is no delta sync, compression, xattrs, ACLs or locking.
+
+/// SFTP status for a path that doesn't exist (SSH_FX_NO_SUCH_FILE)
+const NO_SUCH_FILE: i32 = 2;
+
+fn is_not_found(e: &ssh2::Error) -> bool {
+    matches!(e.code(), ErrorCode::SFTP(NO_SUCH_FILE))
+}
+
+fn sftp_error(action: &str, path: &Path, e: ssh2::Error) -> SyncError {
+    let kind = if is_not_found(&e) {
+        std::io::ErrorKind::NotFound
+    } else {
+        std::io::ErrorKind::Other
+    };
+    SyncError::Io(std::io::Error::new(
+        kind,
+        format!("Failed to {} {}: {}", action, path.display(), e),
+    ))
+}
+
+fn from_secs(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// Kind of a socket, FIFO or device node, which can't be copied like a file
+fn special_kind(file_type: FileType) -> Option<&'static str> {
+    match file_type {
+        FileType::NamedPipe => Some("FIFO"),
+        FileType::Socket => Some("socket"),
+        FileType::BlockDevice => Some("block device"),
+        FileType::CharDevice => Some("character device"),
+        _ => None,
+    }
+}
+
+/// Scanned entry for `path` below `root`, from its (not followed) stat
+fn file_entry(root: &Path, path: PathBuf, stat: &FileStat, target: Option<PathBuf>) -> FileEntry {
+    let size = stat.size.unwrap_or(0);
+    FileEntry {
+        relative_path: path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+        path,
+        size,
+        modified: from_secs(stat.mtime.unwrap_or(0)),
+        is_dir: stat.is_dir(),
+        is_symlink: stat.file_type().is_symlink(),
+        symlink_target: target,
+        is_sparse: false,
+        allocated_size: size,
+        xattrs: None,
+        inode: None,
+        nlink: 1,
+        acls: None,
+        bsd_flags: None,
+        atime: stat.atime.map(from_secs),
+        crtime: None,
+        // SFTP has no user or group names, so ids can't be mapped by name
+        perms: match (stat.perm, stat.uid, stat.gid) {
+            (Some(perm), Some(uid), Some(gid)) => Some(FilePerms {
+                mode: perm & 0o7777,
+                uid,
+                gid,
+            }),
+            _ => None,
+        },
+    }
+}
+
+/// Scan the tree below `root`
+///
+/// Like sy-remote's scan, symlinks aren't followed, directories deeper than
+/// `max_depth` are listed but not descended into, and special files and
+/// unreadable subdirectories are reported as scan errors. `.gitignore` files
+/// aren't read.
+pub(super) fn scan(
+    sftp: &Sftp,
+    root: &Path,
+    max_depth: Option<usize>,
+) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    // Directories still to list, with the depth of their children
+    let mut pending = vec![(root.to_path_buf(), 1)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let listing = match sftp.readdir(&dir) {
+            Ok(listing) => listing,
+            Err(e) if dir == root => {
+                return Err(SyncError::ReadDirError {
+                    path: dir,
+                    source: e.into(),
+                })
+            }
+            Err(e) => {
+                errors.push(ScanError {
+                    path: dir,
+                    error: e.to_string(),
+                    special: false,
+                });
+                continue;
+            }
+        };
+
+        for (path, stat) in listing {
+            if let Some(kind) = special_kind(stat.file_type()) {
+                tracing::debug!("Skipping special file ({}): {}", kind, path.display());
+                errors.push(ScanError {
+                    path,
+                    error: kind.to_string(),
+                    special: true,
+                });
+                continue;
+            }
+
+            let target = if stat.file_type().is_symlink() {
+                sftp.readlink(&path).ok()
+            } else {
+                None
+            };
+            if stat.is_dir() && max_depth.is_none_or(|max| depth < max) {
+                pending.push((path.clone(), depth + 1));
+            }
+            entries.push(file_entry(root, path, &stat, target));
+        }
+    }
+
+    Ok((entries, errors))
+}
+
+/// Stat each path (following symlinks), None where it's missing or unreadable
+pub(super) fn stat_batch(sftp: &Sftp, paths: &[PathBuf]) -> Vec<Option<PathStat>> {
+    paths
+        .iter()
+        .map(|path| {
+            let stat = sftp.stat(path).ok()?;
+            Some(PathStat {
+                size: stat.size.unwrap_or(0),
+                modified: from_secs(stat.mtime?),
+                is_dir: stat.is_dir(),
+            })
+        })
+        .collect()
+}
+
+pub(super) fn exists(sftp: &Sftp, path: &Path) -> bool {
+    sftp.stat(path).is_ok()
+}
+
+/// Create `path` and any missing parents, like `mkdir -p`
+pub(super) fn mkdir_all(sftp: &Sftp, path: &Path) -> Result<()> {
+    if path.as_os_str().is_empty() || sftp.stat(path).is_ok_and(|stat| stat.is_dir()) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        mkdir_all(sftp, parent)?;
+    }
+    match sftp.mkdir(path, 0o755) {
+        Ok(()) => Ok(()),
+        // Created by a parallel transfer in the meantime
+        Err(_) if sftp.stat(path).is_ok_and(|stat| stat.is_dir()) => Ok(()),
+        Err(e) => Err(sftp_error("create directory", path, e)),
+    }
+}
+
+/// Remove a file, symlink or (recursively) a directory, like `rm -f`/`rm -rf`
+pub(super) fn remove(sftp: &Sftp, path: &Path, is_dir: bool) -> Result<()> {
+    let result = if is_dir {
+        remove_tree(sftp, path)
+    } else {
+        sftp.unlink(path)
+    };
+    match result {
+        Err(e) if !is_not_found(&e) => Err(sftp_error("remove", path, e)),
+        _ => Ok(()),
+    }
+}
+
+fn remove_tree(sftp: &Sftp, dir: &Path) -> std::result::Result<(), ssh2::Error> {
+    for (path, stat) in sftp.readdir(dir)? {
+        // readdir doesn't follow symlinks, so a link to a directory is unlinked
+        if stat.is_dir() {
+            remove_tree(sftp, &path)?;
+        } else {
+            sftp.unlink(&path)?;
+        }
+    }
+    sftp.rmdir(dir)
+}
+
+/// Move `from` to `to`, creating `to`'s parent
+pub(super) fn rename(sftp: &Sftp, from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        mkdir_all(sftp, parent)?;
+    }
+    sftp.rename(from, to, None)
+        .map_err(|e| sftp_error("move", from, e))
+}
+
+/// Create a symlink at `dest` pointing to `target`, creating its parent
+pub(super) fn symlink(sftp: &Sftp, target: &Path, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        mkdir_all(sftp, parent)?;
+    }
+    sftp.symlink(target, dest)
+        .map_err(|e| sftp_error("create symlink", dest, e))
+}
+
+/// Set the access time of `path`, keeping its modification time
+///
+/// SFTP sets both together, so the current mtime is read first.
+pub(super) fn set_atime(sftp: &Sftp, path: &Path, atime: u64) -> Result<()> {
+    let stat = sftp.stat(path).map_err(|e| sftp_error("stat", path, e))?;
+    sftp.setstat(
+        path,
+        FileStat {
+            size: None,
+            uid: None,
+            gid: None,
+            perm: None,
+            atime: Some(atime),
+            mtime: stat.mtime,
+        },
+    )
+    .map_err(|e| sftp_error("set times on", path, e))
+}
+
+/// Set the permission bits, owner and/or group of `path`
+///
+/// SFTP carries no names, so owners and groups are set by id. Owner and group
+/// are set together, so the current ones are read first.
+pub(super) fn set_perms(sftp: &Sftp, path: &Path, update: &PermsUpdate) -> Result<()> {
+    let (uid, gid) = if update.uid.is_some() != update.gid.is_some() {
+        let stat = sftp.stat(path).map_err(|e| sftp_error("stat", path, e))?;
+        (update.uid.or(stat.uid), update.gid.or(stat.gid))
+    } else {
+        (update.uid, update.gid)
+    };
+    sftp.setstat(
+        path,
+        FileStat {
+            size: None,
+            uid,
+            gid,
+            perm: update.mode,
+            atime: None,
+            mtime: None,
+        },
+    )
+    .map_err(|e| sftp_error("set permissions on", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(perm: u32, size: u64) -> FileStat {
+        FileStat {
+            size: Some(size),
+            uid: None,
+            gid: None,
+            perm: Some(perm),
+            atime: Some(100),
+            mtime: Some(200),
+        }
+    }
+
+    #[test]
+    fn test_file_entry() {
+        let root = Path::new("/data");
+
+        let file = file_entry(root, root.join("a/b.txt"), &stat(0o100644, 42), None);
+        assert_eq!(file.relative_path, PathBuf::from("a/b.txt"));
+        assert_eq!(file.size, 42);
+        assert_eq!(file.modified, from_secs(200));
+        assert_eq!(file.atime, Some(from_secs(100)));
+        assert!(!file.is_dir && !file.is_symlink);
+
+        let dir = file_entry(root, root.join("a"), &stat(0o040755, 4096), None);
+        assert!(dir.is_dir);
+
+        let link = file_entry(
+            root,
+            root.join("link"),
+            &stat(0o120777, 5),
+            Some(PathBuf::from("a/b.txt")),
+        );
+        assert!(link.is_symlink && !link.is_dir);
+        assert_eq!(link.symlink_target, Some(PathBuf::from("a/b.txt")));
+    }
+
+    #[test]
+    fn test_special_kind() {
+        assert_eq!(special_kind(stat(0o010644, 0).file_type()), Some("FIFO"));
+        assert_eq!(special_kind(stat(0o140755, 0).file_type()), Some("socket"));
+        assert_eq!(special_kind(stat(0o100644, 0).file_type()), None);
+        assert_eq!(special_kind(stat(0o040755, 0).file_type()), None);
+    }
+}
@@ -1,18 +1,36 @@
-use super::{TransferResult, Transport};
-use crate::compress::{compress, should_compress_smart, Compression, CompressionDetection};
-use crate::delta::{calculate_block_size, generate_delta_streaming, BlockChecksum, DeltaOp};
+use super::{sftp, ProgressCallback, TransferResult, Transport, TransportOptions};
+use crate::acl::AclMapPolicy;
+use crate::compress::{
+    compress, compression_decision, decompress, decompress_stream, Compression,
+    CompressionDecision, CompressionDetection, CompressionReason, DEFAULT_PROBE_SIZE,
+};
+use crate::delta::pipeline::{self, DeltaSyncResult};
+use crate::delta::WeakHash;
 use crate::error::{Result, SyncError};
+use crate::fs_util::{AclsApplied, XattrsApplied};
+use crate::owner::{NamedPerms, PermsUpdate};
+use crate::ssh::command::RemoteCommand;
 use crate::ssh::config::SshConfig;
 use crate::ssh::connect;
-use crate::sync::scanner::FileEntry;
+use crate::ssh::host_info::{self, HostInfo};
+use crate::ssh::system::{SshBackend, SystemSsh};
+use crate::sync::lock::LockInfo;
+use crate::sync::output::MetadataError;
+use crate::sync::resume::Checkpointer;
+use crate::sync::scanner::{FileEntry, ScanError};
+use crate::sync::staging::StagedMove;
+use crate::temp_file;
+use crate::wire::{self, BatchFile, Capabilities, ScanOutput, WireFormat};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use ssh2::Session;
-use std::io::Read;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Read};
 use std::path::{Path, PathBuf};
+use std::process::Child;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, UNIX_EPOCH};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
 // Temporary inlined sparse detection (module resolution issue workaround)
 #[cfg(unix)]
@@ -27,11 +45,11 @@ struct DataRegion {
 
 /// Detect data regions in a sparse file using SEEK_HOLE/SEEK_DATA
 #[cfg(unix)]
-fn detect_data_regions(path: &Path) -> std::io::Result<Vec<DataRegion>> {
+fn detect_data_regions(path: &Path, noatime: bool) -> std::io::Result<Vec<DataRegion>> {
     const SEEK_DATA: i32 = 3;
     const SEEK_HOLE: i32 = 4;
 
-    let file = std::fs::File::open(path)?;
+    let file = crate::fs_util::open_source(path, noatime)?;
     let file_size = file.metadata()?.len();
 
     if file_size == 0 {
@@ -87,28 +105,812 @@ fn detect_data_regions(path: &Path) -> std::io::Result<Vec<DataRegion>> {
     Ok(regions)
 }
 
+/// Metadata sent to `sy-remote set-metadata` on stdin
 #[derive(Debug, Serialize, Deserialize)]
-struct ScanOutput {
-    entries: Vec<FileEntryJson>,
+struct MetadataInput {
+    #[serde(default)]
+    xattrs: Option<Vec<(String, String)>>, // (key, base64-encoded value)
+    #[serde(default)]
+    acls: Option<String>, // ACL text format (one per line)
+    #[serde(default)]
+    acl_map: Option<AclMapPolicy>, // Handling of ACLs from a different ACL model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    atime: Option<i64>, // Access time to restore (seconds since epoch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crtime: Option<i64>, // Creation time to restore (seconds since epoch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    perms: Option<NamedPerms>, // Mode, owner and group to set
+}
+
+/// Result printed by `sy-remote set-metadata`
+#[derive(Debug, Default, Deserialize)]
+struct MetadataOutput {
+    #[serde(default)]
+    xattrs_applied: usize,
+    #[serde(default)]
+    acl_entries_applied: usize,
+    /// Privileged xattrs the remote side lacked the privileges to write
+    #[serde(default)]
+    xattrs_skipped_privileged: Vec<String>,
+    /// Xattrs and ACL entries the remote side couldn't apply, and why
+    #[serde(default)]
+    metadata_failed: Vec<MetadataError>,
+}
+
+/// Output of `sy-remote lock`: the current holder, or null if acquired
+#[derive(Debug, Deserialize)]
+struct LockOutput {
+    holder: Option<LockInfo>,
+}
+
+/// Output of `sy-remote unlock`
+#[derive(Debug, Deserialize)]
+struct UnlockOutput {
+    released: bool,
+}
+
+/// Output of `sy-remote check-writable`
+#[derive(Debug, Deserialize)]
+struct CheckWritableOutput {
+    unwritable: Option<Unwritable>,
+}
+
+/// Directory `sy-remote check-writable` couldn't write, and what to do about it
+#[derive(Debug, Deserialize)]
+struct Unwritable {
+    path: PathBuf,
+    reason: String,
 }
 
+/// One entry printed by `sy-remote stat-batch` (null for missing paths)
 #[derive(Debug, Serialize, Deserialize)]
-struct FileEntryJson {
-    path: String,
+struct StatBatchEntry {
     size: u64,
     mtime: i64,
     is_dir: bool,
-    // Extended metadata for full preservation
-    is_symlink: bool,
-    symlink_target: Option<String>,
-    is_sparse: bool,
-    allocated_size: u64,
+}
+
+/// Header line emitted by `sy-remote send-file` ahead of the file payload
+#[derive(Debug, Serialize, Deserialize)]
+struct SendFileHeader {
+    compression: String,
+    size: u64,
+    /// None for a payload compressed as it was sent (`--stream`)
     #[serde(default)]
-    xattrs: Option<Vec<(String, String)>>, // (key, base64-encoded value)
-    inode: Option<u64>,
-    nlink: u64,
+    payload_size: Option<u64>,
+    mtime: Option<u64>,
+    /// Why sy-remote chose the codec (missing from older versions)
     #[serde(default)]
-    acls: Option<String>, // ACL text format (one per line)
+    reason: Option<CompressionReason>,
+    #[serde(default)]
+    sample_ratio: Option<f64>,
+}
+
+impl SendFileHeader {
+    /// The remote's compression decision, if it reported one
+    fn decision(&self) -> Option<CompressionDecision> {
+        Some(CompressionDecision {
+            compression: self.compression.parse().ok()?,
+            reason: self.reason?,
+            sample_ratio: self.sample_ratio,
+        })
+    }
+}
+
+/// `mkdir -p` for a remote directory
+fn mkdir_command(path: &Path) -> String {
+    RemoteCommand::new("mkdir")
+        .arg("-p")
+        .end_of_options()
+        .path(path)
+        .to_string()
+}
+
+/// Where sy-remote is looked for when it isn't on the remote PATH
+///
+/// Commands run over SSH get a non-login shell, which often skips the profile
+/// that adds these directories to PATH.
+const REMOTE_BINARY_LOCATIONS: &[&str] = &[
+    "$HOME/.cargo/bin/sy-remote",
+    "$HOME/.local/bin/sy-remote",
+    "/usr/local/bin/sy-remote",
+    "/opt/homebrew/bin/sy-remote",
+    "/opt/sy/sy-remote",
+];
+
+/// Shell script printing the sy-remote to run (nothing if there is none)
+fn locate_remote_binary_script() -> String {
+    let mut script = "command -v sy-remote".to_string();
+    for location in REMOTE_BINARY_LOCATIONS {
+        script.push_str(&format!(
+            " || {{ [ -x \"{0}\" ] && echo \"{0}\"; }}",
+            location
+        ));
+    }
+    script.push_str(" || true");
+    script
+}
+
+/// Counts the bytes taken from the reader it wraps
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.count += amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// Write the file in a `sy-remote send-file` reply to `dest`, decompressing
+/// the payload as it arrives
+///
+/// `dest` already holds the file's first `offset` bytes, the reply the rest.
+/// Chunks go through `download_chunks`, which reports `progress` (bytes so
+/// far, file size) and takes checkpoints. Returns the header, the file's
+/// size and the number of payload bytes that crossed the wire.
+fn receive_send_file(
+    reply: &mut impl BufRead,
+    source_path: &Path,
+    dest: &mut std::fs::File,
+    dest_path: &Path,
+    offset: u64,
+    progress: &dyn Fn(u64, u64),
+    checkpoints: Option<&mut Checkpointer>,
+) -> Result<(SendFileHeader, u64, u64)> {
+    let mut line = Vec::new();
+    reply.read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        return Err(SyncError::Io(std::io::Error::other(
+            "Invalid send-file output: missing header",
+        )));
+    }
+    let header: SendFileHeader = serde_json::from_slice(&line).map_err(|e| {
+        SyncError::Io(std::io::Error::other(format!(
+            "Failed to parse send-file header: {}",
+            e
+        )))
+    })?;
+    let compression: Compression = header
+        .compression
+        .parse()
+        .map_err(|e: String| SyncError::Io(std::io::Error::other(e)))?;
+
+    let progress = |bytes: u64| progress(bytes, header.size);
+    let mut payload = CountingReader {
+        inner: reply,
+        count: 0,
+    };
+    let decompress_failed = |e: std::io::Error| {
+        SyncError::Io(std::io::Error::other(format!(
+            "Failed to decompress send-file payload: {}",
+            e
+        )))
+    };
+    let size = match compression {
+        Compression::None => download_chunks(
+            &mut (&mut payload).take(header.payload_size.unwrap_or(u64::MAX)),
+            source_path,
+            dest,
+            dest_path,
+            offset,
+            &progress,
+            checkpoints,
+        )?,
+        Compression::Zstd => download_chunks(
+            &mut decompress_stream(&mut payload).map_err(decompress_failed)?,
+            source_path,
+            dest,
+            dest_path,
+            offset,
+            &progress,
+            checkpoints,
+        )?,
+        // Only sy-remote from before streamed downloads sends lz4, in one block
+        Compression::Lz4 => {
+            let mut compressed = Vec::new();
+            (&mut payload)
+                .take(header.payload_size.unwrap_or(u64::MAX))
+                .read_to_end(&mut compressed)?;
+            let data = decompress(&compressed, compression).map_err(decompress_failed)?;
+            download_chunks(
+                &mut &data[..],
+                source_path,
+                dest,
+                dest_path,
+                offset,
+                &progress,
+                checkpoints,
+            )?
+        }
+    };
+
+    // A reply cut short decodes to fewer bytes than the file has
+    if size != header.size {
+        return Err(SyncError::Io(std::io::Error::other(format!(
+            "Truncated send-file output for {}: expected {} bytes, got {}",
+            source_path.display(),
+            header.size,
+            size
+        ))));
+    }
+    let transferred = payload.count;
+    Ok((header, size, transferred))
+}
+
+/// Stream `source` to an open remote file, both positioned at `offset`,
+/// returning the file's final size and the xxh3 of the bytes sent
+///
+/// Writes are pipelined. libssh2 sends the data of one write as several
+/// SFTP requests and returns as soon as the first are acknowledged, leaving
+/// the rest in flight; the next write must pass that unacknowledged data
+/// again, and may append more. So each write gets the unacknowledged data
+/// topped up from `source` to `window` bytes, which keeps a full window of
+/// requests outstanding instead of draining the pipe after every chunk.
+///
+/// `progress` gets the file's size so far after every acknowledged write.
+/// With a checkpointer the remote file is flushed and checkpointed every
+/// `CHECKPOINT_INTERVAL` acknowledged bytes, so an interrupted upload can
+/// continue.
+#[allow(clippy::too_many_arguments)]
+fn upload_chunks(
+    source: &mut impl Read,
+    source_path: &Path,
+    remote: &mut impl std::io::Write,
+    dest_path: &Path,
+    offset: u64,
+    window: usize,
+    progress: &dyn Fn(u64),
+    mut checkpoints: Option<&mut Checkpointer>,
+) -> Result<(u64, u64)> {
+    let write_error = |e: std::io::Error| {
+        SyncError::Io(std::io::Error::other(format!(
+            "Failed to write to remote file {}: {}",
+            dest_path.display(),
+            e
+        )))
+    };
+
+    // Data read but not yet acknowledged is buffer[start..end]
+    let mut buffer = vec![0u8; window * 2];
+    let (mut start, mut end) = (0, 0);
+    let mut eof = false;
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    let mut bytes_written = offset;
+
+    loop {
+        // Top the window up from the source
+        while !eof && end - start < window {
+            if start + window > buffer.len() {
+                buffer.copy_within(start..end, 0);
+                end -= start;
+                start = 0;
+            }
+            let bytes_read = source.read(&mut buffer[end..start + window]).map_err(|e| {
+                SyncError::Io(std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to read from {}: {}", source_path.display(), e),
+                ))
+            })?;
+            if bytes_read == 0 {
+                eof = true;
+            } else {
+                hasher.update(&buffer[end..end + bytes_read]);
+                end += bytes_read;
+            }
+        }
+
+        if start == end {
+            break; // EOF, and everything acknowledged
+        }
+
+        let acknowledged = match remote.write(&buffer[start..end]) {
+            Ok(0) => return Err(write_error(std::io::ErrorKind::WriteZero.into())),
+            Ok(acknowledged) => acknowledged,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(write_error(e)),
+        };
+        start += acknowledged;
+        bytes_written += acknowledged as u64;
+        progress(bytes_written);
+
+        if let Some(checkpointer) = checkpoints.as_deref_mut() {
+            if checkpointer.due(bytes_written) {
+                remote.flush().map_err(|e| {
+                    SyncError::Io(std::io::Error::other(format!(
+                        "Failed to flush remote file {}: {}",
+                        dest_path.display(),
+                        e
+                    )))
+                })?;
+                checkpointer.checkpoint(bytes_written, &buffer[..start]);
+            }
+        }
+    }
+
+    Ok((bytes_written, hasher.digest()))
+}
+
+/// Stream an open remote file into `dest`, both positioned at `offset`,
+/// returning the file's final size
+///
+/// With a checkpointer `dest` is synced to disk and checkpointed every
+/// `CHECKPOINT_INTERVAL` bytes, so an interrupted download can continue.
+fn download_chunks(
+    remote: &mut impl Read,
+    source_path: &Path,
+    dest: &mut std::fs::File,
+    dest_path: &Path,
+    offset: u64,
+    progress: &dyn Fn(u64),
+    mut checkpoints: Option<&mut Checkpointer>,
+) -> Result<u64> {
+    use std::io::Write;
+
+    // Stream in 64KB chunks
+    const CHUNK_SIZE: usize = 64 * 1024;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut total_bytes = offset;
+
+    loop {
+        let bytes_read = remote.read(&mut buffer).map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!(
+                    "Failed to read from remote {}: {}",
+                    source_path.display(),
+                    e
+                ),
+            ))
+        })?;
+
+        if bytes_read == 0 {
+            break;
+        }
+
+        dest.write_all(&buffer[..bytes_read]).map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to write to {}: {}", dest_path.display(), e),
+            ))
+        })?;
+
+        total_bytes += bytes_read as u64;
+        progress(total_bytes);
+
+        if let Some(checkpointer) = checkpoints.as_deref_mut() {
+            if checkpointer.due(total_bytes) {
+                dest.sync_data().map_err(|e| {
+                    SyncError::Io(std::io::Error::new(
+                        e.kind(),
+                        format!("Failed to sync {}: {}", dest_path.display(), e),
+                    ))
+                })?;
+                checkpointer.checkpoint(total_bytes, &buffer[..bytes_read]);
+            }
+        }
+    }
+
+    dest.flush().map_err(|e| {
+        SyncError::Io(std::io::Error::new(
+            e.kind(),
+            format!("Failed to flush {}: {}", dest_path.display(), e),
+        ))
+    })?;
+
+    Ok(total_bytes)
+}
+
+/// Download a remote file through `dest_path`'s temp file, continuing from
+/// its checkpoint when one matches, and rename it into place
+///
+/// Returns the file's size and the offset the download started at. The temp
+/// file is kept when the download fails after a checkpoint.
+fn download_resumable(
+    remote: &mut ssh2::File,
+    source_path: &Path,
+    dest_path: &Path,
+    progress: &dyn Fn(u64),
+    checkpointer: &mut Checkpointer,
+) -> Result<(u64, u64)> {
+    use std::io::{Seek, SeekFrom};
+
+    let temp = temp_file::temp_path(dest_path);
+    let offset = checkpointer.resume_offset(|start, len| {
+        let mut file = std::fs::File::open(&temp).ok()?;
+        file.seek(SeekFrom::Start(start)).ok()?;
+        let mut data = vec![0u8; len];
+        file.read_exact(&mut data).ok()?;
+        Some(data)
+    });
+    progress(offset);
+
+    let result = (|| -> Result<u64> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(offset == 0)
+            .open(&temp)
+            .map_err(|e| {
+                SyncError::Io(std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to create file {}: {}", dest_path.display(), e),
+                ))
+            })?;
+        if offset > 0 {
+            file.seek(SeekFrom::Start(offset))?;
+            remote.seek(SeekFrom::Start(offset))?;
+        }
+        let size = download_chunks(
+            remote,
+            source_path,
+            &mut file,
+            dest_path,
+            offset,
+            progress,
+            Some(&mut *checkpointer),
+        )?;
+        std::fs::rename(&temp, dest_path)?;
+        Ok(size)
+    })();
+
+    if result.is_err() && !checkpointer.has_checkpoint() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result.map(|size| (size, offset))
+}
+
+/// `len` bytes of a remote file from `start`, None if it's shorter or unreadable
+fn read_remote_range(sftp: &ssh2::Sftp, path: &Path, start: u64, len: usize) -> Option<Vec<u8>> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = sftp.open(path).ok()?;
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut data = vec![0u8; len];
+    file.read_exact(&mut data).ok()?;
+    Some(data)
+}
+
+/// Move an uploaded temp file over `dest_path`
+///
+/// SFTP v3 servers (OpenSSH) refuse to rename over an existing file, so the
+/// old file is removed first when the plain rename fails. The temp file is
+/// removed if it can't be moved.
+fn rename_into_place(sftp: &ssh2::Sftp, temp_path: &Path, dest_path: &Path) -> Result<()> {
+    let flags = ssh2::RenameFlags::OVERWRITE | ssh2::RenameFlags::ATOMIC;
+    if sftp.rename(temp_path, dest_path, Some(flags)).is_ok() {
+        return Ok(());
+    }
+    let _ = sftp.unlink(dest_path);
+    sftp.rename(temp_path, dest_path, Some(flags)).map_err(|e| {
+        let _ = sftp.unlink(temp_path);
+        SyncError::Io(std::io::Error::other(format!(
+            "Failed to move {} into place: {}",
+            dest_path.display(),
+            e
+        )))
+    })
+}
+
+/// Upload chunk size forced with --chunk-size, 0 to tune it per host
+static CHUNK_SIZE_OVERRIDE: AtomicUsize = AtomicUsize::new(0);
+
+/// Uploads smaller than this are too short to time the link with
+const CHUNK_TUNE_MIN_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Use `size` bytes per SFTP write for every upload (--chunk-size), instead
+/// of tuning it to each host's round trip and throughput
+pub fn set_chunk_size(size: Option<usize>) {
+    CHUNK_SIZE_OVERRIDE.store(size.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Connections one large upload is split across (--streams)
+static STREAMS: AtomicUsize = AtomicUsize::new(1);
+
+/// Smallest part of a file worth its own connection
+const STRIPE_MIN_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Send each large upload over up to `streams` pooled connections at once
+/// (--streams); the pool needs at least that many connections
+pub fn set_streams(streams: usize) {
+    STREAMS.store(streams.max(1), Ordering::Relaxed);
+}
+
+/// Into how many parts an upload of `file_size` bytes is split when
+/// `streams` connections may carry it: one per stream, each at least
+/// `STRIPE_MIN_BYTES`
+fn stripe_count(file_size: u64, streams: usize) -> usize {
+    (file_size / STRIPE_MIN_BYTES).min(streams as u64).max(1) as usize
+}
+
+/// Write `len` bytes of `source`, from `start`, to the same range of the
+/// remote file `path` over `session` (one part of a --streams upload)
+#[allow(clippy::too_many_arguments)]
+fn upload_stripe(
+    session: Arc<Mutex<PooledSession>>,
+    source: &Path,
+    path: &Path,
+    start: u64,
+    len: u64,
+    window: usize,
+    noatime: bool,
+    progress: &dyn Fn(u64),
+) -> Result<()> {
+    use std::io::{Seek, SeekFrom};
+
+    let session = session.lock().map_err(|e| {
+        SyncError::Io(std::io::Error::other(format!(
+            "Failed to lock session: {}",
+            e
+        )))
+    })?;
+    let sftp = session.sftp().map_err(|e| {
+        SyncError::Io(std::io::Error::other(format!(
+            "Failed to create SFTP session: {}",
+            e
+        )))
+    })?;
+
+    let source_file = crate::fs_util::open_source(source, noatime)
+        .and_then(|mut file| file.seek(SeekFrom::Start(start)).map(|_| file))
+        .map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to open source file {}: {}", source.display(), e),
+            ))
+        })?;
+    let mut remote_file = sftp
+        .open_mode(path, ssh2::OpenFlags::WRITE, 0o644, ssh2::OpenType::File)
+        .map_err(std::io::Error::from)
+        .and_then(|mut file| file.seek(SeekFrom::Start(start)).map(|_| file))
+        .map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to open remote file {}: {}",
+                path.display(),
+                e
+            )))
+        })?;
+
+    let mut stripe = source_file.take(len);
+    let (written, _) = upload_chunks(
+        &mut stripe,
+        source,
+        &mut remote_file,
+        path,
+        start,
+        window,
+        progress,
+        None,
+    )?;
+    if written != start + len {
+        return Err(SyncError::Io(std::io::Error::other(format!(
+            "{} changed size during upload",
+            source.display()
+        ))));
+    }
+    Ok(())
+}
+
+/// Remote round trips per host since startup, for `--perf`
+static HOST_ROUND_TRIPS: Mutex<BTreeMap<String, RoundTrips>> = Mutex::new(BTreeMap::new());
+
+/// Round trips made to one host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundTrips {
+    /// Remote commands run (sy-remote invocations)
+    pub commands: u64,
+    /// SFTP sessions opened (streaming transfers, reads, stats)
+    pub sftp_sessions: u64,
+}
+
+impl RoundTrips {
+    /// Round trips made between `earlier` and this snapshot
+    pub fn since(&self, earlier: &RoundTrips) -> RoundTrips {
+        RoundTrips {
+            commands: self.commands.saturating_sub(earlier.commands),
+            sftp_sessions: self.sftp_sessions.saturating_sub(earlier.sftp_sessions),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.commands + self.sftp_sessions
+    }
+}
+
+/// Snapshot of the process-wide per-host round-trip counters
+pub fn round_trip_stats() -> BTreeMap<String, RoundTrips> {
+    HOST_ROUND_TRIPS.lock().unwrap().clone()
+}
+
+fn record_round_trip(host: &str, count: impl FnOnce(&mut RoundTrips)) {
+    let mut hosts = HOST_ROUND_TRIPS.lock().unwrap();
+    count(hosts.entry(host.to_string()).or_default());
+}
+
+/// How a pooled session reaches its host
+enum Connection {
+    Libssh2(Session),
+    /// Each command is an `ssh` process sharing one master connection
+    System(SystemSsh),
+}
+
+/// SSH session tagged with its host, so round trips can be counted per host
+struct PooledSession {
+    connection: Connection,
+    host: String,
+}
+
+impl PooledSession {
+    /// Start a remote command, counting the round trip
+    fn exec(&self, command: &str) -> Result<RemoteChannel> {
+        record_round_trip(&self.host, |trips| trips.commands += 1);
+        match &self.connection {
+            Connection::Libssh2(session) => {
+                let mut channel = session.channel_session().map_err(|e| {
+                    SyncError::Io(std::io::Error::other(format!(
+                        "Failed to create channel: {}",
+                        e
+                    )))
+                })?;
+                channel.exec(command).map_err(|e| {
+                    SyncError::Io(std::io::Error::other(format!(
+                        "Failed to execute command: {}",
+                        e
+                    )))
+                })?;
+                Ok(RemoteChannel::Libssh2(channel))
+            }
+            Connection::System(ssh) => {
+                ssh.spawn(command).map(RemoteChannel::Process).map_err(|e| {
+                    SyncError::Io(std::io::Error::new(
+                        e.kind(),
+                        format!("Failed to execute command: {}", e),
+                    ))
+                })
+            }
+        }
+    }
+
+    /// Open an SFTP session, counting the round trip
+    fn sftp(&self) -> std::result::Result<ssh2::Sftp, ssh2::Error> {
+        match &self.connection {
+            Connection::Libssh2(session) => {
+                record_round_trip(&self.host, |trips| trips.sftp_sessions += 1);
+                session.sftp()
+            }
+            Connection::System(_) => Err(ssh2::Error::new(
+                ssh2::ErrorCode::Session(-1),
+                "SFTP is unavailable with --ssh-backend system",
+            )),
+        }
+    }
+}
+
+/// A running remote command: its stdout is read and its stdin written
+/// through `Read` and `Write`
+enum RemoteChannel {
+    Libssh2(ssh2::Channel),
+    Process(Child),
+}
+
+impl RemoteChannel {
+    /// Whatever the command wrote to stderr, read once its stdout is done
+    fn stderr(&mut self) -> String {
+        let mut stderr = String::new();
+        let _ = match self {
+            RemoteChannel::Libssh2(channel) => channel.stderr().read_to_string(&mut stderr),
+            RemoteChannel::Process(child) => match child.stderr.as_mut() {
+                Some(pipe) => pipe.read_to_string(&mut stderr),
+                None => Ok(0),
+            },
+        };
+        stderr
+    }
+
+    /// Close the command's stdin
+    fn send_eof(&mut self) -> std::io::Result<()> {
+        match self {
+            RemoteChannel::Libssh2(channel) => channel.send_eof().map_err(std::io::Error::from),
+            RemoteChannel::Process(child) => {
+                drop(child.stdin.take());
+                Ok(())
+            }
+        }
+    }
+
+    /// Separate handles on stdout and stdin, for exchanges that read and
+    /// write at once; dropping the writer closes stdin for a process only,
+    /// so call `send_eof` as well
+    fn split(&mut self) -> (Box<dyn Read>, Box<dyn std::io::Write>) {
+        match self {
+            RemoteChannel::Libssh2(channel) => {
+                (Box::new(channel.stream(0)), Box::new(channel.stream(0)))
+            }
+            RemoteChannel::Process(child) => (
+                Box::new(child.stdout.take().expect("stdout is piped")),
+                Box::new(child.stdin.take().expect("stdin is piped")),
+            ),
+        }
+    }
+
+    /// Wait for the command to exit and return its exit status
+    fn finish(&mut self) -> Result<i32> {
+        match self {
+            RemoteChannel::Libssh2(channel) => {
+                channel.wait_close().map_err(|e| {
+                    SyncError::Io(std::io::Error::other(format!(
+                        "Failed to close channel: {}",
+                        e
+                    )))
+                })?;
+                channel.exit_status().map_err(|e| {
+                    SyncError::Io(std::io::Error::other(format!(
+                        "Failed to get exit status: {}",
+                        e
+                    )))
+                })
+            }
+            RemoteChannel::Process(child) => {
+                drop(child.stdin.take());
+                let status = child.wait().map_err(|e| {
+                    SyncError::Io(std::io::Error::other(format!(
+                        "Failed to get exit status: {}",
+                        e
+                    )))
+                })?;
+                // Killed by a signal counts as ssh's own failure code
+                Ok(status.code().unwrap_or(255))
+            }
+        }
+    }
+}
+
+impl Read for RemoteChannel {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            RemoteChannel::Libssh2(channel) => channel.read(buf),
+            RemoteChannel::Process(child) => match child.stdout.as_mut() {
+                Some(stdout) => stdout.read(buf),
+                None => Ok(0),
+            },
+        }
+    }
+}
+
+impl std::io::Write for RemoteChannel {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            RemoteChannel::Libssh2(channel) => std::io::Write::write(channel, buf),
+            RemoteChannel::Process(child) => match child.stdin.as_mut() {
+                Some(stdin) => std::io::Write::write(stdin, buf),
+                None => Err(std::io::ErrorKind::BrokenPipe.into()),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            RemoteChannel::Libssh2(channel) => std::io::Write::flush(channel),
+            RemoteChannel::Process(child) => match child.stdin.as_mut() {
+                Some(stdin) => std::io::Write::flush(stdin),
+                None => Ok(()),
+            },
+        }
+    }
 }
 
 /// Connection pool for parallel SSH operations
@@ -116,8 +918,10 @@ struct FileEntryJson {
 /// Manages multiple SSH sessions to enable true parallel file transfers.
 /// Workers round-robin through the pool to avoid serialization on a single session.
 struct ConnectionPool {
-    sessions: Vec<Arc<Mutex<Session>>>,
+    sessions: Vec<Arc<Mutex<PooledSession>>>,
     next_index: AtomicUsize,
+    /// Sessions run the system ssh, which has no SFTP
+    system: bool,
 }
 
 impl ConnectionPool {
@@ -131,11 +935,31 @@ impl ConnectionPool {
 
         let mut sessions = Vec::with_capacity(pool_size);
 
+        // The system ssh multiplexes its commands over one connection itself
+        let system = if config.backend == SshBackend::System {
+            tracing::debug!("Connecting with the system ssh");
+            Some(SystemSsh::connect(config).await?)
+        } else {
+            None
+        };
+
+        if system.is_none() {
+            connect::warn_unsupported(config);
+        }
+
         // Create pool_size SSH connections
         for i in 0..pool_size {
-            tracing::debug!("Creating SSH connection {}/{} for pool", i + 1, pool_size);
-            let session = connect::connect(config).await?;
-            sessions.push(Arc::new(Mutex::new(session)));
+            let connection = match &system {
+                Some(ssh) => Connection::System(ssh.clone()),
+                None => {
+                    tracing::debug!("Creating SSH connection {}/{} for pool", i + 1, pool_size);
+                    Connection::Libssh2(connect::connect(config).await?)
+                }
+            };
+            sessions.push(Arc::new(Mutex::new(PooledSession {
+                connection,
+                host: config.hostname.clone(),
+            })));
         }
 
         tracing::info!(
@@ -146,13 +970,14 @@ impl ConnectionPool {
         Ok(Self {
             sessions,
             next_index: AtomicUsize::new(0),
+            system: system.is_some(),
         })
     }
 
     /// Get a session from the pool using round-robin selection
     ///
     /// This ensures even distribution of work across all connections.
-    fn get_session(&self) -> Arc<Mutex<Session>> {
+    fn get_session(&self) -> Arc<Mutex<PooledSession>> {
         let index = self.next_index.fetch_add(1, Ordering::Relaxed) % self.sessions.len();
         Arc::clone(&self.sessions[index])
     }
@@ -163,35 +988,953 @@ impl ConnectionPool {
     }
 }
 
-pub struct SshTransport {
-    connection_pool: Arc<ConnectionPool>,
-    remote_binary_path: String,
-}
+/// Files up to this size are sent many to a `sy-remote receive-batch`
+/// command instead of one SFTP upload each
+const BATCH_FILE_SIZE: u64 = 4 * 1024;
+/// Most files sent in one batch
+const BATCH_MAX_FILES: usize = 256;
+/// Most file contents sent in one batch
+const BATCH_MAX_BYTES: usize = 1024 * 1024;
+/// How long a batch waits for more files before it's sent
+const BATCH_DELAY: Duration = Duration::from_millis(5);
+
+/// Told whether its file was written, or why not
+type BatchWaiter = tokio::sync::oneshot::Sender<std::result::Result<(), String>>;
+
+/// Small files waiting to be sent together
+#[derive(Default)]
+struct PendingBatch {
+    /// Bumped each time the batch is taken, so a late timer can't send the
+    /// next one early
+    id: u64,
+    files: Vec<BatchFile>,
+    waiters: Vec<BatchWaiter>,
+    bytes: usize,
+}
+
+impl PendingBatch {
+    fn take(&mut self) -> (Vec<BatchFile>, Vec<BatchWaiter>) {
+        self.id += 1;
+        self.bytes = 0;
+        (
+            std::mem::take(&mut self.files),
+            std::mem::take(&mut self.waiters),
+        )
+    }
+}
+
+/// Outcome of a batched upload of `source`, from its batch's answer (None
+/// if the batch was dropped unsent)
+fn batch_result(
+    source: &Path,
+    size: u64,
+    result: Option<std::result::Result<(), String>>,
+) -> Result<TransferResult> {
+    match result {
+        Some(Ok(())) => Ok(TransferResult::new(size)),
+        Some(Err(message)) => Err(SyncError::Io(std::io::Error::other(format!(
+            "Failed to upload {}: {}",
+            source.display(),
+            message
+        )))),
+        None => Err(SyncError::Io(std::io::Error::other(format!(
+            "Batched upload of {} was dropped",
+            source.display()
+        )))),
+    }
+}
+
+pub struct SshTransport {
+    connection_pool: Arc<ConnectionPool>,
+    remote_binary_path: String,
+    max_depth: Option<usize>,
+    compression_detection: CompressionDetection,
+    /// What sy-remote supports, asked on first use
+    capabilities: tokio::sync::OnceCell<Capabilities>,
+    /// How far the remote clock runs ahead, measured along with `capabilities`
+    clock_skew_ms: std::sync::OnceLock<i64>,
+    /// No sy-remote on the host: every operation goes through SFTP alone
+    sftp_only: bool,
+    /// Small files queued for the next `receive-batch`
+    pending_batch: Mutex<PendingBatch>,
+    /// Name of the host's cached info (see `ssh::host_info`), None to keep none
+    host_key: Option<String>,
+    /// Bytes of SFTP writes in flight when uploading, retuned as uploads are timed
+    chunk_size: AtomicUsize,
+    /// Round trip of an SFTP request, measured when first needed
+    rtt_ms: tokio::sync::OnceCell<u64>,
+    options: TransportOptions,
+}
+
+impl SshTransport {
+    /// Create a new SSH transport with a single connection (backward compatibility)
+    pub async fn new(config: &SshConfig) -> Result<Self> {
+        Self::with_pool_size(config, 1).await
+    }
+
+    /// Create a new SSH transport with a connection pool
+    ///
+    /// `pool_size` should typically match the number of parallel workers.
+    /// For sequential operations, use pool_size=1.
+    pub async fn with_pool_size(config: &SshConfig, pool_size: usize) -> Result<Self> {
+        let connection_pool = ConnectionPool::new(config, pool_size).await?;
+        Ok(Self {
+            connection_pool: Arc::new(connection_pool),
+            remote_binary_path: "sy-remote".to_string(),
+            max_depth: None,
+            compression_detection: CompressionDetection::Auto,
+            capabilities: tokio::sync::OnceCell::new(),
+            clock_skew_ms: std::sync::OnceLock::new(),
+            sftp_only: false,
+            pending_batch: Mutex::new(PendingBatch::default()),
+            host_key: None,
+            // 256KB until this host's link is known
+            chunk_size: AtomicUsize::new(host_info::DEFAULT_CHUNK_SIZE),
+            rtt_ms: tokio::sync::OnceCell::new(),
+            options: TransportOptions::default(),
+        })
+    }
+
+    /// Use and keep what earlier runs learned about this host, under `key`
+    /// (see `ssh::host_info::host_key`)
+    ///
+    /// Call before `remote_binary`, which then skips its probes while the
+    /// cached info is fresh. Uploads start with the chunk size picked for the
+    /// bandwidth the last sizable sync measured, and the host's round trip
+    /// isn't measured again.
+    pub fn host_info(mut self, key: String) -> Self {
+        if let Some(info) = HostInfo::load(&key) {
+            if let Some(chunk_size) = info.chunk_size {
+                tracing::debug!("Using {} byte upload chunks for {}", chunk_size, key);
+                self.chunk_size = AtomicUsize::new(chunk_size);
+            }
+            if let Some(rtt_ms) = info.rtt_ms {
+                let _ = self.rtt_ms.set(rtt_ms);
+            }
+        }
+        self.host_key = Some(key);
+        self
+    }
+
+    /// Limit remote scan recursion depth (--max-depth)
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// How to decide whether file transfers are compressed (--compress, --compression-detection)
+    pub fn compression_detection(mut self, mode: CompressionDetection) -> Self {
+        self.compression_detection = mode;
+        self
+    }
+
+    /// How files are written (--inplace)
+    pub fn options(mut self, options: TransportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Run sy-remote from `path` (--remote-binary), or find it on the remote host
+    ///
+    /// Without a path, sy-remote is looked up on the remote PATH and then in
+    /// the usual install locations. If neither has it (or the account can't
+    /// run commands at all) but SFTP works, the transport falls back to SFTP
+    /// alone: no delta sync, compression, xattrs, ACLs or locking. Otherwise
+    /// plain `sy-remote` is kept, so the first remote command reports it
+    /// missing.
+    pub async fn remote_binary(mut self, path: Option<&str>) -> Self {
+        if let Some(path) = path {
+            self.remote_binary_path = path.to_string();
+            // Cached capabilities may belong to another sy-remote
+            self.host_key = None;
+            return self;
+        }
+
+        let cached = self.host_key.as_deref().and_then(HostInfo::load);
+        if let Some(info) = cached.filter(|info| info.sftp_only || info.remote_binary.is_some()) {
+            tracing::debug!(
+                "Using cached host info for {} (--refresh-host-info probes again)",
+                info.host
+            );
+            self.sftp_only = info.sftp_only;
+            if let Some(found) = info.remote_binary {
+                self.remote_binary_path = found;
+            }
+            if let Some(capabilities) = info.capabilities {
+                let _ = self.capabilities.set(capabilities);
+            }
+            if let Some(skew_ms) = info.clock_skew_ms {
+                let _ = self.clock_skew_ms.set(skew_ms);
+            }
+            return self;
+        }
+
+        let command = RemoteCommand::new("sh")
+            .arg("-c")
+            .arg(locate_remote_binary_script())
+            .to_string();
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command(session, &command)
+        })
+        .await;
+
+        match output {
+            Ok(Ok(output)) => match output.lines().map(str::trim).find(|l| !l.is_empty()) {
+                Some(found) => {
+                    tracing::debug!("Using sy-remote at {}", found);
+                    self.remote_binary_path = found.to_string();
+                    self.cache_probe(Some(found), false);
+                    return self;
+                }
+                None => tracing::debug!(
+                    "sy-remote not found on the remote PATH or in common install locations"
+                ),
+            },
+            Ok(Err(e)) => tracing::debug!("Failed to locate sy-remote: {}", e),
+            Err(e) => tracing::debug!("Failed to locate sy-remote: {}", e),
+        }
+
+        // A restricted sy-remote refuses the lookup too, but has no SFTP
+        if self.with_sftp(|_| Ok(())).await.is_ok() {
+            tracing::info!(
+                "sy-remote not found on the remote host, using SFTP only \
+                 (no delta sync, compression, xattrs, ACLs or locking)"
+            );
+            self.sftp_only = true;
+            self.cache_probe(None, true);
+        }
+        self
+    }
+
+    /// Cache where sy-remote was found, forgetting capabilities and clock
+    /// skew cached for the host before
+    fn cache_probe(&self, remote_binary: Option<&str>, sftp_only: bool) {
+        if let Some(key) = &self.host_key {
+            HostInfo::update(key, true, |info| {
+                info.remote_binary = remote_binary.map(str::to_string);
+                info.sftp_only = sftp_only;
+                info.capabilities = None;
+                info.clock_skew_ms = None;
+            });
+        }
+    }
+
+    /// Run `work` with an SFTP session from the pool, on a blocking thread
+    async fn with_sftp<T: Send + 'static>(
+        &self,
+        work: impl FnOnce(&ssh2::Sftp) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let session_arc = self.connection_pool.get_session();
+        tokio::task::spawn_blocking(move || {
+            let session = session_arc.lock().map_err(|e| {
+                SyncError::Io(std::io::Error::other(format!(
+                    "Failed to lock session: {}",
+                    e
+                )))
+            })?;
+            let sftp = session.sftp().map_err(|e| {
+                SyncError::Io(std::io::Error::other(format!(
+                    "Failed to create SFTP session: {}",
+                    e
+                )))
+            })?;
+            work(&sftp)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+    }
+
+    /// Bytes of SFTP writes to keep in flight for the next upload
+    fn upload_chunk_size(&self) -> usize {
+        match CHUNK_SIZE_OVERRIDE.load(Ordering::Relaxed) {
+            0 => self.chunk_size.load(Ordering::Relaxed),
+            forced => forced,
+        }
+    }
+
+    /// Round trip of an SFTP request, timing one the first time
+    ///
+    /// Hosts that don't answer in time count as 20 ms away.
+    async fn rtt_ms(&self) -> u64 {
+        *self
+            .rtt_ms
+            .get_or_init(|| async {
+                let timed = self
+                    .with_sftp(|sftp| {
+                        let started = Instant::now();
+                        sftp.stat(Path::new("."))
+                            .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?;
+                        Ok(started.elapsed().as_millis() as u64)
+                    })
+                    .await;
+                let rtt_ms = match timed {
+                    Ok(rtt_ms) => rtt_ms,
+                    Err(e) => {
+                        tracing::debug!("Failed to time the round trip: {}", e);
+                        20
+                    }
+                };
+                tracing::debug!("Round trip to the remote host: {} ms", rtt_ms);
+                if let Some(key) = &self.host_key {
+                    HostInfo::update(key, false, |info| info.rtt_ms = Some(rtt_ms));
+                }
+                rtt_ms
+            })
+            .await
+    }
+
+    /// Retune the upload chunk size from an upload that sent `bytes` in
+    /// `elapsed`, unless --chunk-size fixed it
+    ///
+    /// Each upload is one stream, so parallel uploads each get a share of
+    /// the link and chunks sized for that share.
+    async fn tune_chunk_size(&self, bytes: u64, elapsed: Duration) {
+        if CHUNK_SIZE_OVERRIDE.load(Ordering::Relaxed) != 0
+            || bytes < CHUNK_TUNE_MIN_BYTES
+            || elapsed.is_zero()
+        {
+            return;
+        }
+        let rtt_ms = self.rtt_ms().await;
+        let bandwidth = (bytes as f64 / elapsed.as_secs_f64()) as u64;
+        let chunk_size = host_info::chunk_size_for(bandwidth, rtt_ms);
+        if self.chunk_size.swap(chunk_size, Ordering::Relaxed) != chunk_size {
+            tracing::debug!(
+                "Upload chunk size now {} bytes ({} bytes/s, {} ms round trip)",
+                chunk_size,
+                bandwidth,
+                rtt_ms
+            );
+        }
+    }
+
+    /// `sy-remote <subcommand>`, ready for arguments
+    fn remote_command(&self, subcommand: &str) -> RemoteCommand {
+        RemoteCommand::new(&self.remote_binary_path).arg(subcommand)
+    }
+
+    /// Get the number of connections in the pool
+    pub fn pool_size(&self) -> usize {
+        self.connection_pool.size()
+    }
+
+    /// Capabilities of sy-remote, asking it once per transport
+    ///
+    /// sy-remote versions without `capabilities` only speak JSON and Adler-32.
+    /// The remote clock it reports is compared with the local one halfway
+    /// through the round-trip.
+    async fn capabilities(&self) -> &Capabilities {
+        self.capabilities
+            .get_or_init(|| async {
+                if self.sftp_only {
+                    return Capabilities::default();
+                }
+                let command = self.remote_command("capabilities").to_string();
+                let sent_ms = chrono::Utc::now().timestamp_millis();
+                let output = tokio::task::spawn_blocking({
+                    let session = self.connection_pool.get_session();
+                    move || Self::execute_command(session, &command)
+                })
+                .await;
+                let received_ms = chrono::Utc::now().timestamp_millis();
+
+                let answered: Option<Capabilities> = match output {
+                    Ok(Ok(output)) => serde_json::from_str(output.trim()).ok(),
+                    _ => None,
+                };
+                let capabilities = answered.clone().unwrap_or_default();
+                let skew_ms = capabilities
+                    .clock_ms
+                    .map(|remote_ms| remote_ms - (sent_ms + received_ms) / 2);
+                if let Some(skew_ms) = skew_ms {
+                    tracing::debug!("Remote clock is {} ms ahead", skew_ms);
+                    let _ = self.clock_skew_ms.set(skew_ms);
+                }
+                if let (Some(key), Some(answered)) = (&self.host_key, answered) {
+                    HostInfo::update(key, false, |info| {
+                        info.capabilities = Some(answered);
+                        info.clock_skew_ms = skew_ms;
+                    });
+                }
+                tracing::debug!(
+                    "Using {} wire format and {} delta checksums with sy-remote",
+                    capabilities.negotiate().as_str(),
+                    capabilities.negotiate_weak_hash().as_str()
+                );
+                capabilities
+            })
+            .await
+    }
+
+    /// Wire format for scans and delta sync
+    async fn wire_format(&self) -> WireFormat {
+        self.capabilities().await.negotiate()
+    }
+
+    /// Queue a small file for the next `receive-batch` and wait for it to be
+    /// written
+    ///
+    /// The batch is sent once it's full, or `BATCH_DELAY` after its first
+    /// file was queued, so a run of small files from parallel transfers costs
+    /// one remote command instead of an SFTP open, write and rename each.
+    async fn copy_small_file(
+        &self,
+        source: &Path,
+        dest: &Path,
+        metadata: &std::fs::Metadata,
+    ) -> Result<TransferResult> {
+        let data = tokio::fs::read(source).await.map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read {}: {}", source.display(), e),
+            ))
+        })?;
+        let size = data.len() as u64;
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs() as i64);
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        let (tx, mut rx) = tokio::sync::oneshot::channel();
+        let (id, full) = {
+            let mut pending = self.pending_batch.lock().unwrap();
+            pending.bytes += data.len();
+            pending.files.push(BatchFile {
+                path: dest.to_string_lossy().to_string(),
+                mode,
+                mtime,
+                data,
+            });
+            pending.waiters.push(tx);
+            let full = pending.files.len() >= BATCH_MAX_FILES || pending.bytes >= BATCH_MAX_BYTES;
+            (pending.id, full)
+        };
+
+        if full {
+            self.flush_batch(id).await;
+        } else {
+            tokio::select! {
+                result = &mut rx => return batch_result(source, size, result.ok()),
+                _ = tokio::time::sleep(BATCH_DELAY) => self.flush_batch(id).await,
+            }
+        }
+        batch_result(source, size, rx.await.ok())
+    }
+
+    /// Send the pending batch, unless batch `id` was already taken
+    async fn flush_batch(&self, id: u64) {
+        let (files, waiters) = {
+            let mut pending = self.pending_batch.lock().unwrap();
+            if pending.id != id || pending.files.is_empty() {
+                return;
+            }
+            pending.take()
+        };
+
+        let count = files.len();
+        match self.send_batch(files).await {
+            Ok(errors) if errors.len() == count => {
+                for (waiter, error) in waiters.into_iter().zip(errors) {
+                    let _ = waiter.send(error.map_or(Ok(()), Err));
+                }
+            }
+            Ok(errors) => {
+                let message = format!(
+                    "receive-batch answered for {} of {} files",
+                    errors.len(),
+                    count
+                );
+                for waiter in waiters {
+                    let _ = waiter.send(Err(message.clone()));
+                }
+            }
+            Err(e) => {
+                for waiter in waiters {
+                    let _ = waiter.send(Err(e.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Write `files` with one `sy-remote receive-batch`, returning its error
+    /// message per file
+    async fn send_batch(&self, files: Vec<BatchFile>) -> Result<Vec<Option<String>>> {
+        tracing::debug!("Sending {} small files in one batch", files.len());
+        let format = self.wire_format().await;
+        let payload = wire::encode(format, &files)?;
+        let mut command = self
+            .remote_command("receive-batch")
+            .option("--format", format.as_str());
+        if self.options.inplace {
+            command = command.arg("--inplace");
+        }
+        let command = command.to_string();
+
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command_with_stdin(session, &command, &payload)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+
+        serde_json::from_str(output.trim()).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to parse receive-batch output: {}",
+                e
+            )))
+        })
+    }
+
+    fn execute_command(session: Arc<Mutex<PooledSession>>, command: &str) -> Result<String> {
+        let session = session.lock().map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to lock session: {}",
+                e
+            )))
+        })?;
+
+        let mut channel = session.exec(command)?;
+
+        let mut output = String::new();
+        channel.read_to_string(&mut output).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to read command output: {}",
+                e
+            )))
+        })?;
+
+        let stderr = channel.stderr();
+
+        let exit_status = channel.finish()?;
+
+        if exit_status != 0 {
+            return Err(SyncError::Io(std::io::Error::other(format!(
+                "Command '{}' failed with exit code {}\nstdout: {}\nstderr: {}",
+                command, exit_status, output, stderr
+            ))));
+        }
+
+        Ok(output)
+    }
+
+    /// Run `sy-remote send-file` and write the file it sends to `dest` (see
+    /// `receive_send_file`), failing if the command does
+    #[allow(clippy::too_many_arguments)]
+    fn run_send_file(
+        session: Arc<Mutex<PooledSession>>,
+        command: &str,
+        source: &Path,
+        dest: &mut std::fs::File,
+        dest_path: &Path,
+        offset: u64,
+        progress: &dyn Fn(u64, u64),
+        checkpoints: Option<&mut Checkpointer>,
+    ) -> Result<(SendFileHeader, u64, u64)> {
+        let session = session.lock().map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to lock session: {}",
+                e
+            )))
+        })?;
+
+        let mut channel = session.exec(command)?;
+        let mut reply = std::io::BufReader::new(&mut channel);
+        // Nothing at all comes back when the command fails; stderr says why
+        let received = if reply.fill_buf()?.is_empty() {
+            None
+        } else {
+            Some(receive_send_file(
+                &mut reply,
+                source,
+                dest,
+                dest_path,
+                offset,
+                progress,
+                checkpoints,
+            )?)
+        };
+        drop(reply);
+
+        let stderr = channel.stderr();
+        let exit_status = channel.finish()?;
+        if exit_status != 0 {
+            return Err(SyncError::Io(std::io::Error::other(format!(
+                "Command '{}' failed with exit code {}\nstderr: {}",
+                command, exit_status, stderr
+            ))));
+        }
+        received.ok_or_else(|| {
+            SyncError::Io(std::io::Error::other(
+                "Invalid send-file output: missing header",
+            ))
+        })
+    }
+
+    /// Pull `source` into `dest` with `sy-remote send-file`, calling
+    /// `progress_callback` as it arrives
+    ///
+    /// The payload is decompressed and written to the temp file chunk by
+    /// chunk, so neither host holds the file in memory. With `--resume`,
+    /// large downloads are checkpointed and continue from their temp file
+    /// (sy-remote from before streamed downloads sends the whole file).
+    /// Checkpoints go to `partial_dir`, None to take none.
+    async fn download(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress_callback: Option<ProgressCallback>,
+        partial_dir: Option<&Path>,
+    ) -> Result<TransferResult> {
+        if self.sftp_only {
+            return self
+                .download_sftp(source, dest, progress_callback, partial_dir)
+                .await;
+        }
+
+        // Older sy-remote versions don't know these options, so defaults aren't passed
+        let mut command = self.remote_command("send-file");
+        if self.compression_detection != CompressionDetection::Auto {
+            command = command.option(
+                "--compression-detection",
+                self.compression_detection.as_str(),
+            );
+        }
+        if self.options.compression_probe_size != DEFAULT_PROBE_SIZE {
+            command = command.option(
+                "--compression-probe-size",
+                self.options.compression_probe_size,
+            );
+        }
+
+        // Checkpoints only count for the source version they were taken from
+        let streamed = self.capabilities().await.streamed_send;
+        let mut checkpointer = None;
+        let mut version = (0, 0);
+        if let Some(dir) = partial_dir.filter(|_| streamed && !self.options.inplace) {
+            if let Some(stat) = self
+                .stat_batch(&[source.to_path_buf()])
+                .await?
+                .pop()
+                .flatten()
+            {
+                let mtime = stat
+                    .modified
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                version = (stat.size, mtime);
+                checkpointer = Checkpointer::new(dir, dest, stat.size, mtime);
+            }
+        }
+        if streamed {
+            command = command.arg("--stream");
+        }
+
+        let source_buf = source.to_path_buf();
+        let dest_buf = dest.to_path_buf();
+        let session_arc = self.connection_pool.get_session();
+        let inplace = self.options.inplace;
+
+        tokio::task::spawn_blocking(move || {
+            // Create parent directories if needed
+            if let Some(parent) = dest_buf.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    SyncError::Io(std::io::Error::new(
+                        e.kind(),
+                        format!(
+                            "Failed to create parent directory {}: {}",
+                            parent.display(),
+                            e
+                        ),
+                    ))
+                })?;
+            }
+
+            let progress = |bytes: u64, total: u64| {
+                if let Some(ref callback) = progress_callback {
+                    callback(bytes, total);
+                }
+            };
+            let create_failed = |e: std::io::Error| {
+                SyncError::Io(std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to create file {}: {}", dest_buf.display(), e),
+                ))
+            };
+
+            let (header, size, transferred, offset) = match checkpointer.as_mut() {
+                Some(checkpointer) => {
+                    use std::io::{Seek, SeekFrom};
+
+                    let temp = temp_file::temp_path(&dest_buf);
+                    let offset = checkpointer.resume_offset(|start, len| {
+                        let mut file = std::fs::File::open(&temp).ok()?;
+                        file.seek(SeekFrom::Start(start)).ok()?;
+                        let mut data = vec![0u8; len];
+                        file.read_exact(&mut data).ok()?;
+                        Some(data)
+                    });
+                    let command = command
+                        .option("--offset", offset)
+                        .path(&source_buf)
+                        .to_string();
+
+                    let result = (|| -> Result<_> {
+                        let mut file = std::fs::OpenOptions::new()
+                            .write(true)
+                            .create(true)
+                            .truncate(offset == 0)
+                            .open(&temp)
+                            .map_err(create_failed)?;
+                        file.seek(SeekFrom::Start(offset))?;
+                        let received = Self::run_send_file(
+                            session_arc,
+                            &command,
+                            &source_buf,
+                            &mut file,
+                            &dest_buf,
+                            offset,
+                            &progress,
+                            Some(&mut *checkpointer),
+                        )?;
+                        // The bytes kept from before must be from the same version
+                        let same_version =
+                            received.0.size == version.0 && received.0.mtime == Some(version.1);
+                        if offset > 0 && !same_version {
+                            return Err(SyncError::Io(std::io::Error::other(format!(
+                                "{} changed while its download was resumed; it starts over next time",
+                                source_buf.display()
+                            ))));
+                        }
+                        std::fs::rename(&temp, &dest_buf)?;
+                        Ok(received)
+                    })();
+                    if result.is_err() && !checkpointer.has_checkpoint() {
+                        let _ = std::fs::remove_file(&temp);
+                    }
+                    let (header, size, transferred) = result?;
+                    (header, size, transferred, offset)
+                }
+                // Written to a temp file and renamed into place, unless --inplace
+                None => {
+                    let command = command.path(&source_buf).to_string();
+                    let (header, size, transferred) =
+                        temp_file::write_atomically(&dest_buf, inplace, |write_path| {
+                            let mut file =
+                                std::fs::File::create(write_path).map_err(create_failed)?;
+                            Self::run_send_file(
+                                session_arc,
+                                &command,
+                                &source_buf,
+                                &mut file,
+                                &dest_buf,
+                                0,
+                                &progress,
+                                None,
+                            )
+                        })?;
+                    (header, size, transferred, 0)
+                }
+            };
+            if let Some(checkpointer) = checkpointer {
+                checkpointer.finish();
+            }
+
+            if let Some(mtime) = header.mtime {
+                let mtime_systime = UNIX_EPOCH + Duration::from_secs(mtime);
+                filetime::set_file_mtime(
+                    &dest_buf,
+                    filetime::FileTime::from_system_time(mtime_systime),
+                )?;
+            }
+
+            let decision = header.decision();
+            let result = if header.compression == Compression::None.as_str() {
+                tracing::debug!(
+                    "Downloaded {} ({} bytes, uncompressed)",
+                    source_buf.display(),
+                    size - offset
+                );
+                let mut result = TransferResult::new(size);
+                if offset > 0 {
+                    result.transferred_bytes = Some(transferred);
+                }
+                result
+            } else {
+                tracing::info!(
+                    "Downloaded {} ({} bytes compressed, {:.1}x reduction)",
+                    source_buf.display(),
+                    transferred,
+                    (size - offset) as f64 / transferred.max(1) as f64
+                );
+                TransferResult::with_compression(size, transferred)
+            };
+            match decision {
+                Some(decision) => {
+                    tracing::debug!("Remote chose {} for {}", decision, source_buf.display());
+                    Ok(result.with_decision(decision))
+                }
+                None => Ok(result),
+            }
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+    }
+
+    /// Pull `source` into `dest` over SFTP, calling `progress_callback` as it
+    /// arrives
+    ///
+    /// Large downloads are checkpointed into `partial_dir` (--resume), so one
+    /// cut short continues from its temp file on the next run.
+    async fn download_sftp(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress_callback: Option<ProgressCallback>,
+        partial_dir: Option<&Path>,
+    ) -> Result<TransferResult> {
+        let source_buf = source.to_path_buf();
+        let dest_buf = dest.to_path_buf();
+        let session_arc = self.connection_pool.get_session();
+        let inplace = self.options.inplace;
+        let partial_dir = partial_dir.map(Path::to_path_buf);
+
+        tokio::task::spawn_blocking(move || {
+            let session = session_arc.lock().map_err(|e| {
+                SyncError::Io(std::io::Error::other(format!(
+                    "Failed to lock session: {}",
+                    e
+                )))
+            })?;
+
+            let sftp = session.sftp().map_err(|e| {
+                SyncError::Io(std::io::Error::other(format!(
+                    "Failed to create SFTP session: {}",
+                    e
+                )))
+            })?;
+
+            // Get file stats for mtime and size
+            let stat = sftp.stat(&source_buf).map_err(|e| {
+                SyncError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Failed to stat remote file {}: {}", source_buf.display(), e),
+                ))
+            })?;
+
+            let file_size = stat.size.unwrap_or(0);
+            let mtime = stat.mtime.ok_or_else(|| {
+                SyncError::Io(std::io::Error::other(format!(
+                    "Remote file {} has no mtime",
+                    source_buf.display()
+                )))
+            })?;
+
+            // Open remote file for streaming read
+            let mut remote_file = sftp.open(&source_buf).map_err(|e| {
+                SyncError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Failed to open remote file {}: {}", source_buf.display(), e),
+                ))
+            })?;
+
+            // Create parent directories if needed
+            if let Some(parent) = dest_buf.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    SyncError::Io(std::io::Error::new(
+                        e.kind(),
+                        format!(
+                            "Failed to create parent directory {}: {}",
+                            parent.display(),
+                            e
+                        ),
+                    ))
+                })?;
+            }
+
+            let progress = |bytes: u64| {
+                if let Some(ref callback) = progress_callback {
+                    callback(bytes, file_size);
+                }
+            };
+            progress(0);
+
+            // Large downloads are checkpointed, so one cut short continues from
+            // its temp file on the next run (--resume)
+            let mut checkpointer = match &partial_dir {
+                Some(dir) if !inplace => Checkpointer::new(dir, &dest_buf, file_size, mtime),
+                _ => None,
+            };
+
+            let (total_bytes, offset) = match checkpointer.as_mut() {
+                Some(checkpointer) => download_resumable(
+                    &mut remote_file,
+                    &source_buf,
+                    &dest_buf,
+                    &progress,
+                    checkpointer,
+                )?,
+                // Written to a temp file and renamed into place, unless --inplace
+                None => {
+                    let total_bytes =
+                        temp_file::write_atomically(&dest_buf, inplace, |write_path| {
+                            let mut dest_file = std::fs::File::create(write_path).map_err(|e| {
+                                SyncError::Io(std::io::Error::new(
+                                    e.kind(),
+                                    format!("Failed to create file {}: {}", dest_buf.display(), e),
+                                ))
+                            })?;
+                            download_chunks(
+                                &mut remote_file,
+                                &source_buf,
+                                &mut dest_file,
+                                &dest_buf,
+                                0,
+                                &progress,
+                                None,
+                            )
+                        })?;
+                    (total_bytes, 0)
+                }
+            };
+            if let Some(checkpointer) = checkpointer {
+                checkpointer.finish();
+            }
+
+            // Set mtime
+            let mtime_systime = UNIX_EPOCH + Duration::from_secs(mtime);
+            filetime::set_file_mtime(
+                &dest_buf,
+                filetime::FileTime::from_system_time(mtime_systime),
+            )?;
 
-impl SshTransport {
-    /// Create a new SSH transport with a single connection (backward compatibility)
-    pub async fn new(config: &SshConfig) -> Result<Self> {
-        Self::with_pool_size(config, 1).await
-    }
+            tracing::debug!(
+                "Streamed {} bytes from {} to {}",
+                total_bytes - offset,
+                source_buf.display(),
+                dest_buf.display()
+            );
 
-    /// Create a new SSH transport with a connection pool
-    ///
-    /// `pool_size` should typically match the number of parallel workers.
-    /// For sequential operations, use pool_size=1.
-    pub async fn with_pool_size(config: &SshConfig, pool_size: usize) -> Result<Self> {
-        let connection_pool = ConnectionPool::new(config, pool_size).await?;
-        Ok(Self {
-            connection_pool: Arc::new(connection_pool),
-            remote_binary_path: "sy-remote".to_string(),
+            let mut result = TransferResult::new(total_bytes);
+            if offset > 0 {
+                result.transferred_bytes = Some(total_bytes - offset);
+            }
+            Ok(result)
         })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
     }
 
-    /// Get the number of connections in the pool
-    pub fn pool_size(&self) -> usize {
-        self.connection_pool.size()
-    }
-
-    fn execute_command(session: Arc<Mutex<Session>>, command: &str) -> Result<String> {
+    /// Execute a command and capture its raw stdout (binary-safe)
+    fn execute_command_bytes(session: Arc<Mutex<PooledSession>>, command: &str) -> Result<Vec<u8>> {
         let session = session.lock().map_err(|e| {
             SyncError::Io(std::io::Error::other(format!(
                 "Failed to lock session: {}",
@@ -199,58 +1942,123 @@ impl SshTransport {
             )))
         })?;
 
-        let mut channel = session.channel_session().map_err(|e| {
-            SyncError::Io(std::io::Error::other(format!(
-                "Failed to create channel: {}",
-                e
-            )))
-        })?;
-
-        channel.exec(command).map_err(|e| {
-            SyncError::Io(std::io::Error::other(format!(
-                "Failed to execute command: {}",
-                e
-            )))
-        })?;
+        let mut channel = session.exec(command)?;
 
-        let mut output = String::new();
-        channel.read_to_string(&mut output).map_err(|e| {
+        let mut output = Vec::new();
+        channel.read_to_end(&mut output).map_err(|e| {
             SyncError::Io(std::io::Error::other(format!(
                 "Failed to read command output: {}",
                 e
             )))
         })?;
 
-        let mut stderr = String::new();
-        let _ = channel.stderr().read_to_string(&mut stderr);
+        let stderr = channel.stderr();
+
+        let exit_status = channel.finish()?;
+
+        if exit_status != 0 {
+            return Err(SyncError::Io(std::io::Error::other(format!(
+                "Command '{}' failed with exit code {}\nstderr: {}",
+                command, exit_status, stderr
+            ))));
+        }
+
+        Ok(output)
+    }
+
+    /// Run one pipelined `sy-remote delta-sync` exchange for `source`
+    ///
+    /// Block checksums stream back while the remote side is still hashing, and
+    /// delta frames are written as they are generated, so checksumming, delta
+    /// generation and application overlap within a single command. Returns
+    /// None, leaving the destination untouched, if it is missing or too small
+    /// for delta sync to pay off.
+    fn run_delta_sync(
+        session: Arc<Mutex<PooledSession>>,
+        command: &str,
+        source: &Path,
+        format: WireFormat,
+        weak_hash: WeakHash,
+        noatime: bool,
+    ) -> Result<Option<DeltaSyncResult>> {
+        use std::io::BufReader;
 
-        channel.wait_close().map_err(|e| {
+        let session = session.lock().map_err(|e| {
             SyncError::Io(std::io::Error::other(format!(
-                "Failed to close channel: {}",
+                "Failed to lock session: {}",
                 e
             )))
         })?;
 
-        let exit_status = channel.exit_status().map_err(|e| {
+        let mut channel = session.exec(command)?;
+
+        let exchange_error = |channel: &mut RemoteChannel, stage: &str, e: std::io::Error| {
+            let stderr = channel.stderr();
             SyncError::Io(std::io::Error::other(format!(
-                "Failed to get exit status: {}",
-                e
+                "Delta sync failed while {}: {}\nstderr: {}",
+                stage, e, stderr
             )))
+        };
+
+        let (stdout, stdin) = channel.split();
+        let mut reader = BufReader::new(stdout);
+        let (header, checksums) = match pipeline::read_checksums(format, &mut reader) {
+            Ok(received) => received,
+            Err(e) => return Err(exchange_error(&mut channel, "reading checksums", e)),
+        };
+
+        if header.dest_size < pipeline::MIN_DELTA_DEST_SIZE {
+            // Closing stdin without an end frame makes the remote side discard its output
+            drop(stdin);
+            let _ = channel.send_eof();
+            let _ = channel.finish();
+            return Ok(None);
+        }
+
+        tracing::debug!(
+            "Received {} remote checksums, streaming delta...",
+            checksums.len()
+        );
+        if let Err(e) = pipeline::send_delta(
+            format,
+            weak_hash,
+            source,
+            &checksums,
+            header.block_size,
+            noatime,
+            stdin,
+        ) {
+            return Err(exchange_error(&mut channel, "sending delta", e));
+        }
+
+        channel.send_eof().map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!("Failed to send EOF: {}", e)))
         })?;
 
+        let result = pipeline::read_result(format, &mut reader);
+
+        let stderr = channel.stderr();
+
+        let exit_status = channel.finish()?;
+
         if exit_status != 0 {
             return Err(SyncError::Io(std::io::Error::other(format!(
-                "Command '{}' failed with exit code {}\nstdout: {}\nstderr: {}",
-                command, exit_status, output, stderr
+                "Command '{}' failed with exit code {}\nstderr: {}",
+                command, exit_status, stderr
             ))));
         }
 
-        Ok(output)
+        result.map(Some).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to read delta-sync result: {}",
+                e
+            )))
+        })
     }
 
     /// Execute a command with stdin data (binary-safe)
     fn execute_command_with_stdin(
-        session: Arc<Mutex<Session>>,
+        session: Arc<Mutex<PooledSession>>,
         command: &str,
         stdin_data: &[u8],
     ) -> Result<String> {
@@ -263,19 +2071,7 @@ impl SshTransport {
             )))
         })?;
 
-        let mut channel = session.channel_session().map_err(|e| {
-            SyncError::Io(std::io::Error::other(format!(
-                "Failed to create channel: {}",
-                e
-            )))
-        })?;
-
-        channel.exec(command).map_err(|e| {
-            SyncError::Io(std::io::Error::other(format!(
-                "Failed to execute command: {}",
-                e
-            )))
-        })?;
+        let mut channel = session.exec(command)?;
 
         // Write binary data to stdin
         channel.write_all(stdin_data).map_err(|e| {
@@ -299,31 +2095,156 @@ impl SshTransport {
             )))
         })?;
 
-        let mut stderr = String::new();
-        let _ = channel.stderr().read_to_string(&mut stderr);
+        let stderr = channel.stderr();
+
+        let exit_status = channel.finish()?;
+
+        if exit_status != 0 {
+            return Err(SyncError::Io(std::io::Error::other(format!(
+                "Command '{}' failed with exit code {}\nstdout: {}\nstderr: {}",
+                command, exit_status, output, stderr
+            ))));
+        }
 
-        channel.wait_close().map_err(|e| {
+        Ok(output)
+    }
+
+    /// Run `sy-remote lock`/`unlock` with the lock owner as JSON on stdin
+    async fn run_lock_command(
+        &self,
+        subcommand: &str,
+        dest: &Path,
+        owner: &LockInfo,
+    ) -> Result<String> {
+        let command = self.remote_command(subcommand).path(dest).to_string();
+        let payload = serde_json::to_vec(owner).map_err(|e| {
             SyncError::Io(std::io::Error::other(format!(
-                "Failed to close channel: {}",
+                "Failed to serialize lock info: {}",
                 e
             )))
         })?;
 
-        let exit_status = channel.exit_status().map_err(|e| {
+        tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command_with_stdin(session, &command, &payload)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+    }
+
+    /// Create many remote directories in one round-trip via `sy-remote mkdirs`
+    async fn mkdirs_remote(&self, paths: &[PathBuf]) -> Result<()> {
+        let command = self.remote_command("mkdirs").to_string();
+        let paths_json: Vec<_> = paths.iter().map(|p| p.to_string_lossy()).collect();
+        let payload = serde_json::to_vec(&paths_json).map_err(|e| {
             SyncError::Io(std::io::Error::other(format!(
-                "Failed to get exit status: {}",
+                "Failed to serialize directory paths: {}",
                 e
             )))
         })?;
 
-        if exit_status != 0 {
-            return Err(SyncError::Io(std::io::Error::other(format!(
-                "Command '{}' failed with exit code {}\nstdout: {}\nstderr: {}",
-                command, exit_status, output, stderr
-            ))));
+        tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command_with_stdin(session, &command, &payload)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+
+        Ok(())
+    }
+
+    /// Stat many remote paths in one round-trip via `sy-remote stat-batch`
+    async fn stat_batch_remote(&self, paths: &[PathBuf]) -> Result<Vec<Option<super::PathStat>>> {
+        let command = self.remote_command("stat-batch").to_string();
+        let paths_json: Vec<_> = paths.iter().map(|p| p.to_string_lossy()).collect();
+        let payload = serde_json::to_vec(&paths_json).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to serialize stat paths: {}",
+                e
+            )))
+        })?;
+
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command_with_stdin(session, &command, &payload)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+
+        let entries: Vec<Option<StatBatchEntry>> =
+            serde_json::from_str(output.trim()).map_err(|e| {
+                SyncError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Invalid stat-batch output: {}", e),
+                ))
+            })?;
+        if entries.len() != paths.len() {
+            return Err(SyncError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "stat-batch returned {} entries for {} paths",
+                    entries.len(),
+                    paths.len()
+                ),
+            )));
         }
 
-        Ok(output)
+        Ok(entries
+            .into_iter()
+            .map(|entry| {
+                entry.map(|e| super::PathStat {
+                    size: e.size,
+                    modified: UNIX_EPOCH + Duration::from_secs(e.mtime.max(0) as u64),
+                    is_dir: e.is_dir,
+                })
+            })
+            .collect())
+    }
+
+    /// Apply xattrs/ACLs to a remote file via `sy-remote set-metadata`
+    async fn set_remote_metadata(
+        &self,
+        path: &Path,
+        input: MetadataInput,
+    ) -> Result<MetadataOutput> {
+        if self.sftp_only {
+            // SFTP can only set times, and not creation times
+            if input.xattrs.is_some() || input.acls.is_some() {
+                tracing::debug!(
+                    "Skipping xattrs and ACLs for {} (no sy-remote)",
+                    path.display()
+                );
+            }
+            if let Some(atime) = input.atime {
+                let path = path.to_path_buf();
+                self.with_sftp(move |sftp| sftp::set_atime(sftp, &path, atime.max(0) as u64))
+                    .await?;
+            }
+            return Ok(MetadataOutput::default());
+        }
+
+        let command = self.remote_command("set-metadata").path(path).to_string();
+        let payload = serde_json::to_vec(&input).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to serialize metadata: {}",
+                e
+            )))
+        })?;
+
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command_with_stdin(session, &command, &payload)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+
+        tracing::debug!(
+            "Applied metadata to remote {}: {}",
+            path.display(),
+            output.trim()
+        );
+        // Older sy-remote versions print fewer fields; nothing was skipped then
+        Ok(serde_json::from_str(output.trim()).unwrap_or_default())
     }
 
     /// Copy a sparse file over SSH by transferring only data regions
@@ -336,6 +2257,8 @@ impl SshTransport {
         let dest_path = dest.to_path_buf();
         let session_arc = self.connection_pool.get_session();
         let remote_binary = self.remote_binary_path.clone();
+        let inplace = self.options.inplace;
+        let noatime = self.options.open_noatime;
 
         tokio::task::spawn_blocking(move || {
             // Get source metadata
@@ -353,7 +2276,7 @@ impl SshTransport {
             let file_size = metadata.len();
 
             // Detect data regions in the sparse file
-            let data_regions = detect_data_regions(&source_path).map_err(|e| {
+            let data_regions = detect_data_regions(&source_path, noatime).map_err(|e| {
                 SyncError::Io(std::io::Error::new(
                     e.kind(),
                     format!(
@@ -406,18 +2329,22 @@ impl SshTransport {
                 .map(|d| d.as_secs());
 
             // Build command
-            let dest_path_str = dest_path.to_string_lossy();
-            let mtime_arg = mtime_secs
-                .map(|s| format!("--mtime {}", s))
-                .unwrap_or_default();
-
-            let command = format!(
-                "{} receive-sparse-file {} --total-size {} --regions '{}' {}",
-                remote_binary, dest_path_str, file_size, regions_json, mtime_arg
-            );
+            let mut command = RemoteCommand::new(&remote_binary)
+                .arg("receive-sparse-file")
+                .path(&dest_path)
+                .option("--total-size", file_size)
+                .option("--regions", &regions_json);
+            if let Some(mtime) = mtime_secs {
+                command = command.option("--mtime", mtime);
+            }
+            // Older sy-remote versions don't know --inplace, so it's only passed when set
+            if inplace {
+                command = command.arg("--inplace");
+            }
+            let command = command.to_string();
 
             // Open source file for reading
-            let mut source_file = std::fs::File::open(&source_path).map_err(|e| {
+            let mut source_file = crate::fs_util::open_source(&source_path, noatime).map_err(|e| {
                 SyncError::Io(std::io::Error::new(
                     e.kind(),
                     format!("Failed to open {}: {}", source_path.display(), e),
@@ -498,123 +2425,193 @@ impl SshTransport {
                 literal_bytes: None,
                 transferred_bytes: Some(response.bytes_written),
                 compression_used: false,
+                compression_decision: None,
             })
         })
         .await
         .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
     }
-}
 
-#[async_trait]
-impl Transport for SshTransport {
-    async fn scan(&self, path: &Path) -> Result<Vec<FileEntry>> {
-        let path_str = path.to_string_lossy();
-        let command = format!("{} scan {}", self.remote_binary_path, path_str);
+    /// Into how many parts an upload of `source` is split (--streams), or
+    /// None to send it over one connection
+    ///
+    /// Only SFTP uploads that won't be compressed are split, and only over a
+    /// pool of separate connections: the system ssh carries them all over one.
+    async fn stripes_for(&self, source: &Path) -> Option<usize> {
+        let streams = STREAMS
+            .load(Ordering::Relaxed)
+            .min(self.connection_pool.size());
+        if streams < 2 || self.connection_pool.system {
+            return None;
+        }
+        let file_size = std::fs::metadata(source).ok()?.len();
+        let stripes = stripe_count(file_size, streams);
+        if stripes < 2 || (!self.sftp_only && self.capabilities().await.restricted) {
+            return None;
+        }
 
-        let output = tokio::task::spawn_blocking({
-            let session = self.connection_pool.get_session();
-            let cmd = command.clone();
-            move || Self::execute_command(session, &cmd)
-        })
-        .await
-        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+        let filename = source.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let detection = if self.sftp_only {
+            CompressionDetection::Never
+        } else {
+            self.compression_detection
+        };
+        let decision = compression_decision(
+            Some(source),
+            filename,
+            file_size,
+            false,
+            detection,
+            self.options.compression_probe_size,
+            self.options.open_noatime,
+        );
+        (decision.compression == Compression::None).then_some(stripes)
+    }
 
-        let scan_output: ScanOutput = serde_json::from_str(&output).map_err(|e| {
-            SyncError::Io(std::io::Error::other(format!(
-                "Failed to parse JSON: {}",
-                e
-            )))
+    /// Upload `source` in `stripes` parts sent at once, each over its own
+    /// connection from the pool and into its own range of the remote file
+    ///
+    /// The parts are written to the usual temp file (unless --inplace),
+    /// which is renamed into place once all of them arrived. Striped uploads
+    /// aren't checkpointed for --resume.
+    async fn upload_striped(
+        &self,
+        source: &Path,
+        dest: &Path,
+        stripes: usize,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<TransferResult> {
+        let metadata = std::fs::metadata(source).map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to get metadata for {}: {}", source.display(), e),
+            ))
         })?;
+        let file_size = metadata.len();
+        let mtime_secs = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+        let write_path = if self.options.inplace {
+            dest.to_path_buf()
+        } else {
+            temp_file::temp_path(dest)
+        };
+        tracing::debug!(
+            "Uploading {} ({} bytes) in {} parallel streams",
+            source.display(),
+            file_size,
+            stripes
+        );
 
-        let entries: Result<Vec<FileEntry>> = scan_output
-            .entries
-            .into_iter()
-            .map(|e| {
-                let modified = UNIX_EPOCH + Duration::from_secs(e.mtime.max(0) as u64);
-
-                // Decode xattrs from base64 if present
-                let xattrs = e.xattrs.map(|xattr_vec| {
-                    xattr_vec
-                        .into_iter()
-                        .filter_map(|(key, base64_val)| {
-                            use base64::{engine::general_purpose, Engine as _};
-                            match general_purpose::STANDARD.decode(base64_val) {
-                                Ok(decoded) => Some((key, decoded)),
-                                Err(e) => {
-                                    tracing::warn!("Failed to decode xattr {}: {}", key, e);
-                                    None
-                                }
-                            }
-                        })
-                        .collect()
-                });
-
-                // Decode ACLs from text format
-                let acls = e.acls.map(|acl_text| acl_text.into_bytes());
-
-                Ok(FileEntry {
-                    path: PathBuf::from(&e.path),
-                    relative_path: PathBuf::from(&e.path)
-                        .strip_prefix(path)
-                        .unwrap_or(Path::new(&e.path))
-                        .to_path_buf(),
-                    size: e.size,
-                    modified,
-                    is_dir: e.is_dir,
-                    is_symlink: e.is_symlink,
-                    symlink_target: e.symlink_target.map(PathBuf::from),
-                    is_sparse: e.is_sparse,
-                    allocated_size: e.allocated_size,
-                    xattrs,
-                    inode: e.inode,
-                    nlink: e.nlink,
-                    acls,
-                    bsd_flags: None, // TODO: Serialize BSD flags in SSH protocol
+        // Create the file once, empty, before the parts open it for writing
+        self.with_sftp({
+            let write_path = write_path.clone();
+            move |sftp| {
+                sftp.create(&write_path).map(drop).map_err(|e| {
+                    SyncError::Io(std::io::Error::other(format!(
+                        "Failed to create remote file {}: {}",
+                        write_path.display(),
+                        e
+                    )))
+                })
+            }
+        })
+        .await?;
+
+        // Each part reports its own position; the callback gets their sum
+        let sent = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let chunk_size = self.upload_chunk_size();
+        let noatime = self.options.open_noatime;
+        let started = Instant::now();
+        let parts: Vec<_> = (0..stripes as u64)
+            .map(|i| {
+                let start = file_size * i / stripes as u64;
+                let len = file_size * (i + 1) / stripes as u64 - start;
+                let session = self.connection_pool.get_session();
+                let source = source.to_path_buf();
+                let write_path = write_path.clone();
+                let sent = Arc::clone(&sent);
+                let progress_callback = progress_callback.clone();
+                tokio::task::spawn_blocking(move || {
+                    let position = std::cell::Cell::new(start);
+                    let progress = |bytes: u64| {
+                        let delta = bytes - position.replace(bytes);
+                        let total = sent.fetch_add(delta, Ordering::Relaxed) + delta;
+                        if let Some(ref callback) = progress_callback {
+                            callback(total, file_size);
+                        }
+                    };
+                    upload_stripe(
+                        session,
+                        &source,
+                        &write_path,
+                        start,
+                        len,
+                        chunk_size,
+                        noatime,
+                        &progress,
+                    )
                 })
             })
             .collect();
 
-        entries
-    }
-
-    async fn exists(&self, path: &Path) -> Result<bool> {
-        let path_str = path.to_string_lossy();
-        let command = format!("test -e {} && echo 'exists' || echo 'not found'", path_str);
-
-        let output = tokio::task::spawn_blocking({
-            let session = self.connection_pool.get_session();
-            let cmd = command.clone();
-            move || Self::execute_command(session, &cmd)
-        })
-        .await
-        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
-
-        Ok(output.trim() == "exists")
-    }
-
-    async fn metadata(&self, _path: &Path) -> Result<std::fs::Metadata> {
-        // For now, return error - metadata is complex to bridge from remote to local
-        Err(SyncError::Io(std::io::Error::other(
-            "SSH transport metadata requires local Metadata struct which doesn't work for remote files"
-        )))
-    }
-
-    async fn create_dir_all(&self, path: &Path) -> Result<()> {
-        let path_str = path.to_string_lossy();
-        let command = format!("mkdir -p '{}'", path_str);
+        let mut failure = None;
+        for part in futures::future::join_all(parts).await {
+            let result = part.map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())));
+            if let Err(e) = result.and_then(|result| result) {
+                failure.get_or_insert(e);
+            }
+        }
+        let elapsed = started.elapsed();
 
-        tokio::task::spawn_blocking({
-            let session = self.connection_pool.get_session();
-            let cmd = command.clone();
-            move || Self::execute_command(session, &cmd)
+        let dest_path = dest.to_path_buf();
+        self.with_sftp(move |sftp| {
+            if let Some(e) = failure {
+                if write_path != dest_path {
+                    let _ = sftp.unlink(&write_path);
+                }
+                return Err(e);
+            }
+            if let Some(mtime) = mtime_secs {
+                let _ = sftp.setstat(
+                    &write_path,
+                    ssh2::FileStat {
+                        size: Some(file_size),
+                        uid: None,
+                        gid: None,
+                        perm: None,
+                        atime: Some(mtime),
+                        mtime: Some(mtime),
+                    },
+                );
+            }
+            if write_path != dest_path {
+                rename_into_place(sftp, &write_path, &dest_path)?;
+            }
+            Ok(())
         })
-        .await
-        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+        .await?;
 
-        Ok(())
+        // Every stream had a share of the link
+        self.tune_chunk_size(file_size / stripes as u64, elapsed)
+            .await;
+        Ok(TransferResult::new(file_size))
     }
 
-    async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+    /// Upload a local file, reporting the bytes acknowledged so far to
+    /// `progress_callback` as SFTP writes complete
+    ///
+    /// Large uploads are checkpointed into `partial_dir` (--resume), None to
+    /// take no checkpoints.
+    async fn upload_file(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress_callback: Option<ProgressCallback>,
+        partial_dir: Option<&Path>,
+    ) -> Result<TransferResult> {
         // Check if file is sparse and try sparse transfer first
         #[cfg(unix)]
         {
@@ -623,7 +2620,8 @@ impl Transport for SshTransport {
             if let Ok(metadata) = std::fs::metadata(source) {
                 let file_size = metadata.len();
                 let allocated_size = metadata.blocks() * 512;
-                let is_sparse = allocated_size < file_size && file_size > 0;
+                // Sparse transfers need sy-remote
+                let is_sparse = !self.sftp_only && allocated_size < file_size && file_size > 0;
 
                 if is_sparse {
                     // Try sparse transfer
@@ -650,12 +2648,45 @@ impl Transport for SshTransport {
             }
         }
 
+        // Small files go many to one remote command
+        if !self.sftp_only && self.capabilities().await.batch_receive {
+            if let Ok(metadata) = std::fs::metadata(source) {
+                if metadata.len() <= BATCH_FILE_SIZE {
+                    return self.copy_small_file(source, dest, &metadata).await;
+                }
+            }
+        }
+
+        // Large files can go over several connections at once (--streams)
+        if let Some(stripes) = self.stripes_for(source).await {
+            return self
+                .upload_striped(source, dest, stripes, progress_callback)
+                .await;
+        }
+
         let source_path = source.to_path_buf();
         let dest_path = dest.to_path_buf();
         let session_arc = self.connection_pool.get_session();
         let remote_binary = self.remote_binary_path.clone();
+        let chunk_size = self.upload_chunk_size();
+        let inplace = self.options.inplace;
+        let noatime = self.options.open_noatime;
+        let probe_size = self.options.compression_probe_size;
+        let partial_dir = partial_dir.map(Path::to_path_buf);
+        // Bytes the SFTP upload sent and how long that took, for tuning
+        let timing: Arc<Mutex<Option<(u64, Duration)>>> = Arc::default();
+        let upload_timing = Arc::clone(&timing);
+        // A restricted sy-remote or the system ssh has no SFTP, so every
+        // upload goes through receive-file
+        let compression_detection = if self.sftp_only {
+            CompressionDetection::Never
+        } else if self.connection_pool.system || self.capabilities().await.restricted {
+            CompressionDetection::Always
+        } else {
+            self.compression_detection
+        };
 
-        tokio::task::spawn_blocking(move || {
+        let result = tokio::task::spawn_blocking(move || {
             // Get source metadata for mtime and size
             let metadata = std::fs::metadata(&source_path).map_err(|e| {
                 SyncError::Io(std::io::Error::new(
@@ -675,15 +2706,16 @@ impl Transport for SshTransport {
                 .unwrap_or("");
 
             // Determine if compression would be beneficial using smart detection
-            // Use content-based detection with Auto mode (default)
-            // TODO: Thread compression_detection mode from CLI through transport
-            let compression_mode = should_compress_smart(
+            let decision = compression_decision(
                 Some(&source_path),
                 filename,
                 file_size,
                 false, // SSH transfers are always remote (not local)
-                CompressionDetection::Auto,
+                compression_detection,
+                probe_size,
+                noatime,
             );
+            let compression_mode = decision.compression;
 
             // Use compressed transfer for compressible files, SFTP for others
             match compression_mode {
@@ -692,7 +2724,7 @@ impl Transport for SshTransport {
                         "File {}: {} bytes, using compressed transfer ({})",
                         filename,
                         file_size,
-                        compression_mode.as_str()
+                        decision
                     );
 
                     // Read entire file (compression only used for smaller files)
@@ -706,7 +2738,8 @@ impl Transport for SshTransport {
                     let uncompressed_size = file_data.len();
 
                     // Compress the data
-                    let compressed_data = compress(&file_data, compression_mode).map_err(|e| {
+                    let compressed = crate::cpu::install(|| compress(&file_data, compression_mode));
+                    let compressed_data = compressed.map_err(|e| {
                         SyncError::Io(std::io::Error::other(format!(
                             "Failed to compress {}: {}",
                             source_path.display(),
@@ -733,15 +2766,16 @@ impl Transport for SshTransport {
                         .map(|d| d.as_secs());
 
                     // Send via receive-file command with stdin
-                    let dest_path_str = dest_path.to_string_lossy();
-                    let mtime_arg = mtime_secs
-                        .map(|s| format!("--mtime {}", s))
-                        .unwrap_or_default();
-
-                    let command = format!(
-                        "{} receive-file {} {}",
-                        remote_binary, dest_path_str, mtime_arg
-                    );
+                    let mut command = RemoteCommand::new(&remote_binary)
+                        .arg("receive-file")
+                        .path(&dest_path);
+                    if let Some(mtime) = mtime_secs {
+                        command = command.option("--mtime", mtime);
+                    }
+                    if inplace {
+                        command = command.arg("--inplace");
+                    }
+                    let command = command.to_string();
 
                     let output = Self::execute_command_with_stdin(
                         Arc::clone(&session_arc),
@@ -772,13 +2806,15 @@ impl Transport for SshTransport {
                     Ok(TransferResult::with_compression(
                         result.bytes_written,
                         compressed_size as u64,
-                    ))
+                    )
+                    .with_decision(decision))
                 }
                 Compression::None => {
                     tracing::debug!(
-                        "File {}: {} bytes, using SFTP streaming (incompressible or too large)",
+                        "File {}: {} bytes, using SFTP streaming ({})",
                         filename,
-                        file_size
+                        file_size,
+                        decision
                     );
 
                     let session = session_arc.lock().map_err(|e| {
@@ -789,16 +2825,17 @@ impl Transport for SshTransport {
                     })?;
 
                     // Open source file for streaming
-                    let mut source_file = std::fs::File::open(&source_path).map_err(|e| {
-                        SyncError::Io(std::io::Error::new(
-                            e.kind(),
-                            format!(
-                                "Failed to open source file {}: {}",
-                                source_path.display(),
-                                e
-                            ),
-                        ))
-                    })?;
+                    let mut source_file = crate::fs_util::open_source(&source_path, noatime)
+                        .map_err(|e| {
+                            SyncError::Io(std::io::Error::new(
+                                e.kind(),
+                                format!(
+                                    "Failed to open source file {}: {}",
+                                    source_path.display(),
+                                    e
+                                ),
+                            ))
+                        })?;
 
                     // Get SFTP session
                     let sftp = session.sftp().map_err(|e| {
@@ -808,8 +2845,52 @@ impl Transport for SshTransport {
                         )))
                     })?;
 
-                    // Write to remote file
-                    let mut remote_file = sftp.create(&dest_path).map_err(|e| {
+                    // Write to a temp file renamed into place once complete, so a
+                    // failed upload never leaves a partial file (unless --inplace)
+                    let write_path = if inplace {
+                        dest_path.clone()
+                    } else {
+                        temp_file::temp_path(&dest_path)
+                    };
+
+                    // Large uploads are checkpointed, so one cut short continues
+                    // from its temp file on the next run (--resume)
+                    let mtime_secs = metadata
+                        .modified()
+                        .ok()
+                        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                        .map(|d| d.as_secs());
+                    let mut checkpointer = match (mtime_secs, &partial_dir) {
+                        (Some(mtime), Some(dir)) if write_path != dest_path => {
+                            Checkpointer::new(dir, &dest_path, file_size, mtime)
+                        }
+                        _ => None,
+                    };
+                    let offset = checkpointer.as_mut().map_or(0, |checkpointer| {
+                        checkpointer.resume_offset(|start, len| {
+                            read_remote_range(&sftp, &write_path, start, len)
+                        })
+                    });
+
+                    // Resumed uploads continue both files at the checkpoint
+                    let opened = if offset > 0 {
+                        use std::io::{Seek, SeekFrom};
+                        sftp.open_mode(
+                            &write_path,
+                            ssh2::OpenFlags::WRITE,
+                            0o644,
+                            ssh2::OpenType::File,
+                        )
+                        .map_err(std::io::Error::from)
+                        .and_then(|mut file| {
+                            file.seek(SeekFrom::Start(offset))?;
+                            source_file.seek(SeekFrom::Start(offset))?;
+                            Ok(file)
+                        })
+                    } else {
+                        sftp.create(&write_path).map_err(std::io::Error::from)
+                    };
+                    let mut remote_file = opened.map_err(|e| {
                         SyncError::Io(std::io::Error::other(format!(
                             "Failed to create remote file {}: {}",
                             dest_path.display(),
@@ -817,276 +2898,345 @@ impl Transport for SshTransport {
                         )))
                     })?;
 
-                    // Stream file in chunks with checksum calculation
-                    // 256KB optimal for modern networks (research: SFTP performance)
-                    const CHUNK_SIZE: usize = 256 * 1024; // 256KB chunks
-                    let mut buffer = vec![0u8; CHUNK_SIZE];
-                    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
-                    let mut bytes_written = 0u64;
-
-                    loop {
-                        let bytes_read = std::io::Read::read(&mut source_file, &mut buffer)
-                            .map_err(|e| {
-                                SyncError::Io(std::io::Error::new(
-                                    e.kind(),
-                                    format!("Failed to read from {}: {}", source_path.display(), e),
-                                ))
-                            })?;
-
-                        if bytes_read == 0 {
-                            break; // EOF
+                    let progress = |bytes: u64| {
+                        if let Some(ref callback) = progress_callback {
+                            callback(bytes, file_size);
                         }
-
-                        // Update checksum
-                        hasher.update(&buffer[..bytes_read]);
-
-                        // Write chunk to remote
-                        std::io::Write::write_all(&mut remote_file, &buffer[..bytes_read])
-                            .map_err(|e| {
-                                SyncError::Io(std::io::Error::other(format!(
-                                    "Failed to write to remote file {}: {}",
-                                    dest_path.display(),
-                                    e
-                                )))
-                            })?;
-
-                        bytes_written += bytes_read as u64;
-                    }
-
-                    let checksum = hasher.digest();
+                    };
+                    progress(offset);
+
+                    let started = Instant::now();
+                    let streamed = upload_chunks(
+                        &mut source_file,
+                        &source_path,
+                        &mut remote_file,
+                        &dest_path,
+                        offset,
+                        chunk_size,
+                        &progress,
+                        checkpointer.as_mut(),
+                    );
+                    drop(remote_file);
+                    let (bytes_written, checksum) = match streamed {
+                        Ok(streamed) => {
+                            *upload_timing.lock().unwrap() =
+                                Some((streamed.0 - offset, started.elapsed()));
+                            streamed
+                        }
+                        Err(e) => {
+                            let resumable = checkpointer
+                                .as_ref()
+                                .is_some_and(Checkpointer::has_checkpoint);
+                            if write_path != dest_path && !resumable {
+                                let _ = sftp.unlink(&write_path);
+                            }
+                            return Err(e);
+                        }
+                    };
 
                     tracing::debug!(
-                        "Transferred {} ({} bytes, xxh3: {:x})",
+                        "Transferred {} ({} bytes, {} sent, xxh3 of sent: {:x})",
                         source_path.display(),
                         bytes_written,
+                        bytes_written - offset,
                         checksum
                     );
 
                     // Set modification time
-                    if let Ok(modified) = metadata.modified() {
-                        if let Ok(duration) = modified.duration_since(UNIX_EPOCH) {
-                            let mtime = duration.as_secs();
-                            let atime = mtime;
-                            let _ = sftp.setstat(
-                                &dest_path,
-                                ssh2::FileStat {
-                                    size: Some(bytes_written),
-                                    uid: None,
-                                    gid: None,
-                                    perm: None,
-                                    atime: Some(atime),
-                                    mtime: Some(mtime),
-                                },
-                            );
-                        }
+                    if let Some(mtime) = mtime_secs {
+                        let atime = mtime;
+                        let _ = sftp.setstat(
+                            &write_path,
+                            ssh2::FileStat {
+                                size: Some(bytes_written),
+                                uid: None,
+                                gid: None,
+                                perm: None,
+                                atime: Some(atime),
+                                mtime: Some(mtime),
+                            },
+                        );
+                    }
+
+                    if write_path != dest_path {
+                        rename_into_place(&sftp, &write_path, &dest_path)?;
+                    }
+                    if let Some(checkpointer) = checkpointer {
+                        checkpointer.finish();
                     }
 
-                    Ok(TransferResult::new(bytes_written))
+                    let mut result = TransferResult::new(bytes_written).with_decision(decision);
+                    if offset > 0 {
+                        result.transferred_bytes = Some(bytes_written - offset);
+                    }
+                    Ok(result)
                 }
             }
         })
         .await
-        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+
+        let timed = *timing.lock().unwrap();
+        if let Some((bytes, elapsed)) = timed {
+            self.tune_chunk_size(bytes, elapsed).await;
+        }
+        Ok(result)
     }
+}
 
-    async fn sync_file_with_delta(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
-        // Check if remote destination exists
-        if !self.exists(dest).await? {
-            tracing::debug!("Remote destination doesn't exist, using full copy");
-            return self.copy_file(source, dest).await;
+#[async_trait]
+impl Transport for SshTransport {
+    async fn scan(&self, path: &Path) -> Result<Vec<FileEntry>> {
+        let (entries, errors) = self.scan_with_errors(path).await?;
+
+        // Strict scan: the first unreadable entry fails the whole scan, while
+        // special files are skipped like the local scanner does
+        if let Some(err) = errors.into_iter().find(|e| !e.special) {
+            return Err(SyncError::ReadDirError {
+                path: err.path,
+                source: std::io::Error::other(err.error),
+            });
         }
 
-        // Get source size
-        let source_meta = std::fs::metadata(source).map_err(|e| {
-            SyncError::Io(std::io::Error::new(
-                e.kind(),
-                format!("Failed to get source metadata: {}", e),
-            ))
-        })?;
-        let source_size = source_meta.len();
+        Ok(entries)
+    }
 
-        let source_path = source.to_path_buf();
-        let dest_path = dest.to_path_buf();
-        let remote_binary = self.remote_binary_path.clone();
-        let session_clone = self.connection_pool.get_session();
+    async fn scan_with_errors(&self, path: &Path) -> Result<(Vec<FileEntry>, Vec<ScanError>)> {
+        if self.sftp_only {
+            let root = path.to_path_buf();
+            let max_depth = self.max_depth;
+            return self
+                .with_sftp(move |sftp| sftp::scan(sftp, &root, max_depth))
+                .await;
+        }
 
-        tokio::task::spawn_blocking({
-            let session_arc = session_clone;
-            move || {
-                let session = session_arc.lock().map_err(|e| {
-                    SyncError::Io(std::io::Error::other(format!(
-                        "Failed to lock session: {}",
-                        e
-                    )))
-                })?;
+        let mut command = self.remote_command("scan").path(path);
+        if let Some(depth) = self.max_depth {
+            command = command.option("--max-depth", depth);
+        }
+        let format = self.wire_format().await;
+        if format != WireFormat::Json {
+            command = command.option("--format", format.as_str());
+        }
+        let command = command.to_string();
 
-                let sftp = session.sftp().map_err(|e| {
-                    SyncError::Io(std::io::Error::other(format!(
-                        "Failed to create SFTP session: {}",
-                        e
-                    )))
-                })?;
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            let cmd = command.clone();
+            move || Self::execute_command_bytes(session, &cmd)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
 
-                // Get remote file size
-                let remote_stat = sftp.stat(&dest_path).map_err(|e| {
-                    SyncError::Io(std::io::Error::other(format!(
-                        "Failed to stat remote file {}: {}",
-                        dest_path.display(),
-                        e
-                    )))
-                })?;
+        let scan_output: ScanOutput = wire::decode(format, &output).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to parse scan output: {}",
+                e
+            )))
+        })?;
 
-                let dest_size = remote_stat.size.unwrap_or(0);
+        let ids = scan_output.id_map(self.options.numeric_ids);
+        let entries = scan_output
+            .entries
+            .into_iter()
+            .map(|e| e.into_entry(path, &ids))
+            .collect();
 
-                // Skip delta if destination is too small
-                if dest_size < 4096 {
-                    tracing::debug!("Remote destination too small for delta sync, using full copy");
-                    drop(session);
-                    return Err(SyncError::Io(std::io::Error::other(
-                        "Destination too small, caller should use copy_file",
-                    )));
-                }
+        let errors = scan_output
+            .errors
+            .into_iter()
+            .map(|e| ScanError {
+                path: PathBuf::from(e.path),
+                error: e.error,
+                special: e.special,
+            })
+            .collect();
 
-                // Calculate block size
-                let block_size = calculate_block_size(dest_size);
+        Ok((entries, errors))
+    }
 
-                // Compute checksums on remote side (avoid downloading entire file!)
-                tracing::debug!("Computing remote checksums via sy-remote...");
-                drop(session); // Unlock session before remote command
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        if self.sftp_only {
+            let path = path.to_path_buf();
+            return self
+                .with_sftp(move |sftp| Ok(sftp::exists(sftp, &path)))
+                .await;
+        }
 
-                let dest_path_str = dest_path.to_string_lossy();
-                let command = format!(
-                    "{} checksums {} --block-size {}",
-                    remote_binary, dest_path_str, block_size
-                );
+        let command = format!(
+            "{} && echo 'exists' || echo 'not found'",
+            RemoteCommand::new("test").arg("-e").path(path)
+        );
 
-                let output = tokio::task::block_in_place(|| {
-                    Self::execute_command(Arc::clone(&session_arc), &command)
-                })?;
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            let cmd = command.clone();
+            move || Self::execute_command(session, &cmd)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
 
-                let dest_checksums: Vec<BlockChecksum> =
-                    serde_json::from_str(&output).map_err(|e| {
-                        SyncError::Io(std::io::Error::other(format!(
-                            "Failed to parse remote checksums: {}",
-                            e
-                        )))
-                    })?;
+        Ok(output.trim() == "exists")
+    }
 
-                // Generate delta with streaming (constant memory)
-                tracing::debug!("Generating delta with streaming...");
-                let delta = generate_delta_streaming(&source_path, &dest_checksums, block_size)
-                    .map_err(|e| SyncError::CopyError {
-                        path: source_path.clone(),
-                        source: e,
-                    })?;
+    async fn metadata(&self, _path: &Path) -> Result<std::fs::Metadata> {
+        // For now, return error - metadata is complex to bridge from remote to local
+        Err(SyncError::Io(std::io::Error::other(
+            "SSH transport metadata requires local Metadata struct which doesn't work for remote files"
+        )))
+    }
 
-                // Calculate compression ratio
-                let literal_bytes: u64 = delta
-                    .ops
-                    .iter()
-                    .filter_map(|op| {
-                        if let DeltaOp::Data(data) = op {
-                            Some(data.len() as u64)
-                        } else {
-                            None
-                        }
-                    })
-                    .sum();
+    async fn create_dir_all(&self, path: &Path) -> Result<()> {
+        if self.sftp_only {
+            let path = path.to_path_buf();
+            return self
+                .with_sftp(move |sftp| sftp::mkdir_all(sftp, &path))
+                .await;
+        }
 
-                let compression_ratio = if source_size > 0 {
-                    (literal_bytes as f64 / source_size as f64) * 100.0
-                } else {
-                    0.0
-                };
+        let command = mkdir_command(path);
 
-                // Serialize delta to JSON
-                let delta_json = serde_json::to_string(&delta).map_err(|e| {
-                    SyncError::Io(std::io::Error::other(format!(
-                        "Failed to serialize delta: {}",
-                        e
-                    )))
-                })?;
+        tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            let cmd = command.clone();
+            move || Self::execute_command(session, &cmd)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
 
-                // Compress delta JSON (typically 5-10x reduction for JSON data)
-                let uncompressed_size = delta_json.len();
-                let compressed_delta =
-                    compress(delta_json.as_bytes(), Compression::Zstd).map_err(|e| {
-                        SyncError::Io(std::io::Error::other(format!(
-                            "Failed to compress delta: {}",
-                            e
-                        )))
-                    })?;
-                let compressed_size = compressed_delta.len();
+        Ok(())
+    }
 
-                tracing::debug!(
-                    "Delta: {} ops, {} bytes JSON, {} bytes compressed ({:.1}x)",
-                    delta.ops.len(),
-                    uncompressed_size,
-                    compressed_size,
-                    uncompressed_size as f64 / compressed_size as f64
-                );
+    async fn create_dirs(&self, paths: &[PathBuf]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        if self.sftp_only {
+            let paths = paths.to_vec();
+            return self
+                .with_sftp(move |sftp| {
+                    paths
+                        .iter()
+                        .try_for_each(|path| sftp::mkdir_all(sftp, path))
+                })
+                .await;
+        }
 
-                // Apply delta on remote side (avoids uploading full file!)
-                // Send compressed delta via stdin to avoid command line length limits
-                tracing::debug!("Sending compressed delta to remote for application...");
-                let temp_remote_path = format!("{}.sy-tmp", dest_path.display());
-                let command = format!(
-                    "{} apply-delta {} {}",
-                    remote_binary, dest_path_str, temp_remote_path
-                );
+        if let Err(e) = self.mkdirs_remote(paths).await {
+            // Older sy-remote without mkdirs: one mkdir -p per directory
+            tracing::debug!(
+                "mkdirs unavailable, falling back to per-directory mkdir: {}",
+                e
+            );
+            for path in paths {
+                self.create_dir_all(path).await?;
+            }
+        }
+        Ok(())
+    }
 
-                let output = tokio::task::block_in_place(|| {
-                    Self::execute_command_with_stdin(
-                        Arc::clone(&session_arc),
-                        &command,
-                        &compressed_delta,
-                    )
-                })?;
+    async fn copy_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        self.upload_file(source, dest, None, None).await
+    }
 
-                #[derive(Deserialize)]
-                struct ApplyStats {
-                    operations_count: usize,
-                    literal_bytes: u64,
-                }
+    async fn copy_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        self.upload_file(source, dest, Some(progress), None).await
+    }
 
-                let stats: ApplyStats = serde_json::from_str(&output).map_err(|e| {
-                    SyncError::Io(std::io::Error::other(format!(
-                        "Failed to parse apply-delta output: {}",
-                        e
-                    )))
-                })?;
+    async fn copy_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        self.upload_file(source, dest, progress, Some(partial_dir))
+            .await
+    }
 
-                // Rename temp file to final destination (atomic)
-                let rename_command = format!("mv '{}' '{}'", temp_remote_path, dest_path_str);
-                tokio::task::block_in_place(|| {
-                    Self::execute_command(Arc::clone(&session_arc), &rename_command)
-                })?;
+    async fn sync_file_with_delta(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        if self.sftp_only {
+            return self.copy_file(source, dest).await;
+        }
 
-                tracing::info!(
-                    "Delta sync: {} ops, {:.1}% literal data, transferred ~{} bytes (delta only)",
-                    stats.operations_count,
-                    compression_ratio,
-                    literal_bytes
-                );
+        // Get source size
+        let source_meta = std::fs::metadata(source).map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to get source metadata: {}", e),
+            ))
+        })?;
+        let source_size = source_meta.len();
 
-                Ok::<TransferResult, SyncError>(TransferResult::with_delta(
-                    source_size, // Full file size
-                    stats.operations_count,
-                    stats.literal_bytes,
-                ))
+        // One remote command streams checksums back and applies the delta as it
+        // arrives (see delta::pipeline)
+        let source_path = source.to_path_buf();
+        let capabilities = self.capabilities().await;
+        let format = capabilities.negotiate();
+        let weak_hash = capabilities.negotiate_weak_hash();
+        let mut command = self.remote_command("delta-sync").path(dest);
+        if format != WireFormat::Json {
+            command = command.option("--format", format.as_str());
+        }
+        if weak_hash != WeakHash::default() {
+            command = command.option("--weak-hash", weak_hash.as_str());
+        }
+        let command = command.to_string();
+        let noatime = self.options.open_noatime;
+
+        let result = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || {
+                Self::run_delta_sync(session, &command, &source_path, format, weak_hash, noatime)
             }
         })
         .await
-        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
-    }
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
 
-    async fn remove(&self, path: &Path, is_dir: bool) -> Result<()> {
-        let path_str = path.to_string_lossy();
-        let command = if is_dir {
-            format!("rm -rf '{}'", path_str)
+        let Some(result) = result else {
+            tracing::debug!(
+                "Remote destination missing or too small for delta sync, using full copy"
+            );
+            return self.copy_file(source, dest).await;
+        };
+
+        let literal_ratio = if source_size > 0 {
+            (result.literal_bytes as f64 / source_size as f64) * 100.0
         } else {
-            format!("rm -f '{}'", path_str)
+            0.0
         };
+        tracing::info!(
+            "Delta sync: {} ops, {:.1}% literal data, transferred ~{} bytes (delta only)",
+            result.operations_count,
+            literal_ratio,
+            result.literal_bytes
+        );
+
+        Ok(TransferResult::with_delta(
+            source_size, // Full file size
+            result.operations_count,
+            result.literal_bytes,
+        ))
+    }
+
+    async fn remove(&self, path: &Path, is_dir: bool) -> Result<()> {
+        if self.sftp_only {
+            let path = path.to_path_buf();
+            return self
+                .with_sftp(move |sftp| sftp::remove(sftp, &path, is_dir))
+                .await;
+        }
+
+        let command = RemoteCommand::new("rm")
+            .arg(if is_dir { "-rf" } else { "-f" })
+            .end_of_options()
+            .path(path)
+            .to_string();
 
         tokio::task::spawn_blocking({
             let session = self.connection_pool.get_session();
@@ -1099,14 +3249,62 @@ impl Transport for SshTransport {
         Ok(())
     }
 
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let (from, to) = (from.to_path_buf(), to.to_path_buf());
+        self.with_sftp(move |sftp| sftp::rename(sftp, &from, &to))
+            .await
+    }
+
+    async fn commit_staged(&self, moves: &[StagedMove]) -> Result<usize> {
+        if self.sftp_only {
+            return Err(SyncError::Io(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "--delay-updates needs sy-remote on the remote host",
+            )));
+        }
+        if !self.capabilities().await.staged_commit {
+            return Err(SyncError::Io(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "sy-remote on the remote host is too old for --delay-updates; update it",
+            )));
+        }
+        if moves.is_empty() {
+            return Ok(0);
+        }
+
+        let command = self.remote_command("commit-staged").to_string();
+        let payload = serde_json::to_vec(moves).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to serialize staged moves: {}",
+                e
+            )))
+        })?;
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command_with_stdin(session, &command, &payload)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
+
+        output.trim().parse().map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Unexpected commit-staged output {:?}: {}",
+                output.trim(),
+                e
+            )))
+        })
+    }
+
     async fn create_hardlink(&self, source: &Path, dest: &Path) -> Result<()> {
-        let source_str = source.to_string_lossy();
-        let dest_str = dest.to_string_lossy();
+        if self.sftp_only {
+            return Err(SyncError::Io(std::io::Error::other(
+                "Hardlinks need sy-remote on the remote host",
+            )));
+        }
 
         // Ensure parent directory exists
         if let Some(parent) = dest.parent() {
-            let parent_str = parent.to_string_lossy();
-            let mkdir_cmd = format!("mkdir -p '{}'", parent_str);
+            let mkdir_cmd = mkdir_command(parent);
             tokio::task::spawn_blocking({
                 let session = self.connection_pool.get_session();
                 move || Self::execute_command(session, &mkdir_cmd)
@@ -1117,7 +3315,11 @@ impl Transport for SshTransport {
 
         // Create hardlink using ln command
         // Retry if source doesn't exist yet (can happen in parallel execution)
-        let command = format!("ln '{}' '{}'", source_str, dest_str);
+        let command = RemoteCommand::new("ln")
+            .end_of_options()
+            .path(source)
+            .path(dest)
+            .to_string();
         let max_retries = 10;
         let mut last_error = None;
 
@@ -1131,7 +3333,11 @@ impl Transport for SshTransport {
             .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
             {
                 Ok(_) => {
-                    tracing::debug!("Created hardlink: {} -> {}", dest_str, source_str);
+                    tracing::debug!(
+                        "Created hardlink: {} -> {}",
+                        dest.display(),
+                        source.display()
+                    );
                     return Ok(());
                 }
                 Err(e) => {
@@ -1159,13 +3365,16 @@ impl Transport for SshTransport {
     }
 
     async fn create_symlink(&self, target: &Path, dest: &Path) -> Result<()> {
-        let target_str = target.to_string_lossy();
-        let dest_str = dest.to_string_lossy();
+        if self.sftp_only {
+            let (target, dest) = (target.to_path_buf(), dest.to_path_buf());
+            return self
+                .with_sftp(move |sftp| sftp::symlink(sftp, &target, &dest))
+                .await;
+        }
 
         // Ensure parent directory exists
         if let Some(parent) = dest.parent() {
-            let parent_str = parent.to_string_lossy();
-            let mkdir_cmd = format!("mkdir -p '{}'", parent_str);
+            let mkdir_cmd = mkdir_command(parent);
             tokio::task::spawn_blocking({
                 let session = self.connection_pool.get_session();
                 move || Self::execute_command(session, &mkdir_cmd)
@@ -1175,7 +3384,12 @@ impl Transport for SshTransport {
         }
 
         // Create symlink using ln -s command
-        let command = format!("ln -s '{}' '{}'", target_str, dest_str);
+        let command = RemoteCommand::new("ln")
+            .arg("-s")
+            .end_of_options()
+            .path(target)
+            .path(dest)
+            .to_string();
 
         tokio::task::spawn_blocking({
             let session = self.connection_pool.get_session();
@@ -1185,7 +3399,129 @@ impl Transport for SshTransport {
         .await
         .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
 
-        tracing::debug!("Created symlink: {} -> {}", dest_str, target_str);
+        tracing::debug!(
+            "Created symlink: {} -> {}",
+            dest.display(),
+            target.display()
+        );
+        Ok(())
+    }
+
+    async fn set_xattrs(
+        &self,
+        path: &Path,
+        xattrs: &HashMap<String, Vec<u8>>,
+    ) -> Result<XattrsApplied> {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let encoded = xattrs
+            .iter()
+            .map(|(key, value)| (key.clone(), general_purpose::STANDARD.encode(value)))
+            .collect();
+
+        self.set_remote_metadata(
+            path,
+            MetadataInput {
+                xattrs: Some(encoded),
+                acls: None,
+                acl_map: None,
+                atime: None,
+                crtime: None,
+                perms: None,
+            },
+        )
+        .await
+        .map(|output| XattrsApplied {
+            applied: output.xattrs_applied,
+            skipped_privileged: output.xattrs_skipped_privileged,
+            failed: output.metadata_failed,
+        })
+    }
+
+    async fn set_acls(
+        &self,
+        path: &Path,
+        acls: &[u8],
+        policy: AclMapPolicy,
+    ) -> Result<AclsApplied> {
+        let acls = String::from_utf8(acls.to_vec()).map_err(|e| {
+            SyncError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid ACL text for {}: {}", path.display(), e),
+            ))
+        })?;
+
+        self.set_remote_metadata(
+            path,
+            MetadataInput {
+                xattrs: None,
+                acls: Some(acls),
+                acl_map: Some(policy),
+                atime: None,
+                crtime: None,
+                perms: None,
+            },
+        )
+        .await
+        .map(|output| AclsApplied {
+            applied: output.acl_entries_applied,
+            failed: output.metadata_failed,
+        })
+    }
+
+    async fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<std::time::SystemTime>,
+        crtime: Option<std::time::SystemTime>,
+    ) -> Result<()> {
+        let secs = |time: std::time::SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0)
+        };
+
+        self.set_remote_metadata(
+            path,
+            MetadataInput {
+                xattrs: None,
+                acls: None,
+                acl_map: None,
+                atime: atime.map(secs),
+                crtime: crtime.map(secs),
+                perms: None,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn set_permissions(&self, path: &Path, update: PermsUpdate) -> Result<()> {
+        if self.sftp_only {
+            let path = path.to_path_buf();
+            return self
+                .with_sftp(move |sftp| sftp::set_perms(sftp, &path, &update))
+                .await;
+        }
+        if !self.capabilities().await.perms {
+            return Err(SyncError::Io(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "sy-remote on the destination is too old to set permissions and owners",
+            )));
+        }
+
+        self.set_remote_metadata(
+            path,
+            MetadataInput {
+                xattrs: None,
+                acls: None,
+                acl_map: None,
+                atime: None,
+                crtime: None,
+                perms: Some(NamedPerms::new(&update, self.options.numeric_ids)),
+            },
+        )
+        .await?;
         Ok(())
     }
 
@@ -1286,6 +3622,26 @@ impl Transport for SshTransport {
         .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
     }
 
+    async fn stat_batch(&self, paths: &[PathBuf]) -> Result<Vec<Option<super::PathStat>>> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if !self.sftp_only {
+            match self.stat_batch_remote(paths).await {
+                Ok(stats) => return Ok(stats),
+                // Older sy-remote without stat-batch: stat each path over SFTP
+                Err(e) => tracing::debug!(
+                    "stat-batch unavailable, falling back to per-file stat: {}",
+                    e
+                ),
+            }
+        }
+        let paths = paths.to_vec();
+        self.with_sftp(move |sftp| Ok(sftp::stat_batch(sftp, &paths)))
+            .await
+    }
+
     async fn file_info(&self, path: &Path) -> Result<super::FileInfo> {
         let path_buf = path.to_path_buf();
         let session_arc = self.connection_pool.get_session();
@@ -1341,135 +3697,126 @@ impl Transport for SshTransport {
         &self,
         source: &Path,
         dest: &Path,
-        progress_callback: Option<std::sync::Arc<dyn Fn(u64, u64) + Send + Sync>>,
+        progress_callback: Option<ProgressCallback>,
     ) -> Result<TransferResult> {
-        let source_buf = source.to_path_buf();
-        let dest_buf = dest.to_path_buf();
-        let session_arc = self.connection_pool.get_session();
-
-        tokio::task::spawn_blocking(move || {
-            let session = session_arc.lock().map_err(|e| {
-                SyncError::Io(std::io::Error::other(format!(
-                    "Failed to lock session: {}",
-                    e
-                )))
-            })?;
+        self.download_sftp(source, dest, progress_callback, None)
+            .await
+    }
 
-            let sftp = session.sftp().map_err(|e| {
-                SyncError::Io(std::io::Error::other(format!(
-                    "Failed to create SFTP session: {}",
-                    e
-                )))
-            })?;
+    fn is_remote(&self) -> bool {
+        true
+    }
 
-            // Get file stats for mtime and size
-            let stat = sftp.stat(&source_buf).map_err(|e| {
-                SyncError::Io(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    format!("Failed to stat remote file {}: {}", source_buf.display(), e),
-                ))
-            })?;
+    async fn try_lock(&self, dest: &Path, owner: &LockInfo) -> Result<Option<LockInfo>> {
+        if self.sftp_only {
+            // The lock needs an atomic create-if-absent that only sy-remote provides
+            tracing::debug!("Skipping sync lock for {} (no sy-remote)", dest.display());
+            return Ok(None);
+        }
 
-            let file_size = stat.size.unwrap_or(0);
-            let mtime = stat.mtime.ok_or_else(|| {
-                SyncError::Io(std::io::Error::other(format!(
-                    "Remote file {} has no mtime",
-                    source_buf.display()
-                )))
-            })?;
+        let output = self.run_lock_command("lock", dest, owner).await?;
+        let result: LockOutput = serde_json::from_str(output.trim()).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to parse lock output: {}",
+                e
+            )))
+        })?;
+        Ok(result.holder)
+    }
 
-            // Open remote file for streaming read
-            let mut remote_file = sftp.open(&source_buf).map_err(|e| {
-                SyncError::Io(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    format!("Failed to open remote file {}: {}", source_buf.display(), e),
-                ))
-            })?;
+    async fn unlock(&self, dest: &Path, owner: &LockInfo) -> Result<bool> {
+        if self.sftp_only {
+            return Ok(true);
+        }
 
-            // Create parent directories if needed
-            if let Some(parent) = dest_buf.parent() {
-                std::fs::create_dir_all(parent).map_err(|e| {
-                    SyncError::Io(std::io::Error::new(
-                        e.kind(),
-                        format!(
-                            "Failed to create parent directory {}: {}",
-                            parent.display(),
-                            e
-                        ),
-                    ))
-                })?;
-            }
+        let output = self.run_lock_command("unlock", dest, owner).await?;
+        let result: UnlockOutput = serde_json::from_str(output.trim()).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to parse unlock output: {}",
+                e
+            )))
+        })?;
+        Ok(result.released)
+    }
 
-            // Create local destination file
-            let mut dest_file = std::fs::File::create(&dest_buf).map_err(|e| {
-                SyncError::Io(std::io::Error::new(
-                    e.kind(),
-                    format!("Failed to create file {}: {}", dest_buf.display(), e),
-                ))
-            })?;
+    async fn check_writable(&self, dest: &Path) -> Result<()> {
+        // Older sy-remote versions can't probe; transfers still report failures
+        if self.sftp_only || !self.capabilities().await.write_probe {
+            return Ok(());
+        }
 
-            // Stream in 64KB chunks
-            const CHUNK_SIZE: usize = 64 * 1024;
-            let mut buffer = vec![0u8; CHUNK_SIZE];
-            let mut total_bytes = 0u64;
+        let command = self.remote_command("check-writable").path(dest).to_string();
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command(session, &command)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
 
-            if let Some(ref callback) = progress_callback {
-                callback(0, file_size);
+        let result: CheckWritableOutput = serde_json::from_str(output.trim()).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to parse check-writable output: {}",
+                e
+            )))
+        })?;
+        match result.unwritable {
+            Some(Unwritable { path, reason }) => {
+                Err(SyncError::DestinationNotWritable { path, reason })
             }
+            None => Ok(()),
+        }
+    }
 
-            loop {
-                let bytes_read =
-                    std::io::Read::read(&mut remote_file, &mut buffer).map_err(|e| {
-                        SyncError::Io(std::io::Error::new(
-                            e.kind(),
-                            format!("Failed to read from remote {}: {}", source_buf.display(), e),
-                        ))
-                    })?;
-
-                if bytes_read == 0 {
-                    break;
-                }
-
-                std::io::Write::write_all(&mut dest_file, &buffer[..bytes_read]).map_err(|e| {
-                    SyncError::Io(std::io::Error::new(
-                        e.kind(),
-                        format!("Failed to write to {}: {}", dest_buf.display(), e),
-                    ))
-                })?;
+    async fn clock_skew_ms(&self) -> Option<i64> {
+        self.capabilities().await;
+        self.clock_skew_ms.get().copied()
+    }
 
-                total_bytes += bytes_read as u64;
-                if let Some(ref callback) = progress_callback {
-                    callback(total_bytes, file_size);
-                }
-            }
+    async fn remote_version(&self) -> Result<Option<String>> {
+        if self.sftp_only {
+            return Ok(None);
+        }
 
-            std::io::Write::flush(&mut dest_file).map_err(|e| {
-                SyncError::Io(std::io::Error::new(
-                    e.kind(),
-                    format!("Failed to flush {}: {}", dest_buf.display(), e),
-                ))
-            })?;
+        // Asked afresh: the cached capabilities fall back to defaults on failure
+        let command = self.remote_command("capabilities").to_string();
+        let output = tokio::task::spawn_blocking({
+            let session = self.connection_pool.get_session();
+            move || Self::execute_command(session, &command)
+        })
+        .await
+        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))??;
 
-            drop(dest_file);
+        let capabilities: Capabilities = serde_json::from_str(output.trim()).map_err(|e| {
+            SyncError::Io(std::io::Error::other(format!(
+                "Failed to parse sy-remote capabilities (too old to report them?): {}",
+                e
+            )))
+        })?;
+        Ok(Some(capabilities.version))
+    }
 
-            // Set mtime
-            let mtime_systime = UNIX_EPOCH + Duration::from_secs(mtime);
-            filetime::set_file_mtime(
-                &dest_buf,
-                filetime::FileTime::from_system_time(mtime_systime),
-            )?;
+    async fn download_file(&self, source: &Path, dest: &Path) -> Result<TransferResult> {
+        self.download(source, dest, None, None).await
+    }
 
-            tracing::debug!(
-                "Streamed {} bytes from {} to {}",
-                total_bytes,
-                source_buf.display(),
-                dest_buf.display()
-            );
+    async fn download_file_with_progress(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: ProgressCallback,
+    ) -> Result<TransferResult> {
+        self.download(source, dest, Some(progress), None).await
+    }
 
-            Ok(TransferResult::new(total_bytes))
-        })
-        .await
-        .map_err(|e| SyncError::Io(std::io::Error::other(e.to_string())))?
+    async fn download_file_resumable(
+        &self,
+        source: &Path,
+        dest: &Path,
+        progress: Option<ProgressCallback>,
+        partial_dir: &Path,
+    ) -> Result<TransferResult> {
+        self.download(source, dest, progress, Some(partial_dir))
+            .await
     }
 }
 
@@ -1487,6 +3834,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_locate_remote_binary_script() {
+        let script = locate_remote_binary_script();
+        assert!(script.starts_with("command -v sy-remote || "));
+        assert!(script.contains(
+            r#"{ [ -x "$HOME/.cargo/bin/sy-remote" ] && echo "$HOME/.cargo/bin/sy-remote"; }"#
+        ));
+        assert!(script.ends_with(" || true"));
+    }
+
     #[test]
     fn test_connection_pool_size() {
         let pool = create_test_pool(0);
@@ -1567,4 +3924,178 @@ mod tests {
         // This doesn't require a real SSH connection - just testing the API exists
         // (Actual connection pooling tested in integration tests with real SSH)
     }
+
+    /// `receive_send_file` over `reply` into a temp file holding `existing`,
+    /// returning the file's contents, the payload bytes and the header
+    fn receive(reply: &[u8], existing: &[u8]) -> Result<(Vec<u8>, u64, SendFileHeader)> {
+        use std::io::Write;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("file");
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(existing).unwrap();
+        let (header, size, transferred) = receive_send_file(
+            &mut &reply[..],
+            Path::new("/remote/file"),
+            &mut file,
+            &path,
+            existing.len() as u64,
+            &|_, _| {},
+            None,
+        )?;
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(data.len() as u64, size);
+        Ok((data, transferred, header))
+    }
+
+    fn send_file_reply(header: &SendFileHeader, payload: &[u8]) -> Vec<u8> {
+        let mut output = serde_json::to_vec(header).unwrap();
+        output.push(b'\n');
+        output.extend_from_slice(payload);
+        output
+    }
+
+    #[test]
+    fn test_receive_send_file_compressed() {
+        let content = b"repetitive content ".repeat(1000);
+        let payload = compress(&content, Compression::Zstd).unwrap();
+        let header = SendFileHeader {
+            compression: "zstd".to_string(),
+            size: content.len() as u64,
+            payload_size: Some(payload.len() as u64),
+            mtime: Some(1_700_000_000),
+            reason: Some(CompressionReason::Compressible),
+            sample_ratio: Some(0.25),
+        };
+
+        let (data, transferred, decoded_header) =
+            receive(&send_file_reply(&header, &payload), b"").unwrap();
+        assert_eq!(data, content);
+        assert_eq!(transferred, payload.len() as u64);
+        assert!(transferred < content.len() as u64);
+        assert_eq!(decoded_header.mtime, Some(1_700_000_000));
+        let decision = decoded_header.decision().unwrap();
+        assert_eq!(decision.compression, Compression::Zstd);
+        assert_eq!(decision.reason, CompressionReason::Compressible);
+        assert_eq!(decision.sample_ratio, Some(0.25));
+    }
+
+    #[test]
+    fn test_receive_send_file_streamed_from_offset() {
+        let content = b"repetitive content ".repeat(1000);
+        let (existing, rest) = content.split_at(100);
+        let payload = compress(rest, Compression::Zstd).unwrap();
+        // A streamed payload's size isn't in the header
+        let header = SendFileHeader {
+            compression: "zstd".to_string(),
+            size: content.len() as u64,
+            payload_size: None,
+            mtime: None,
+            reason: None,
+            sample_ratio: None,
+        };
+
+        let reply = send_file_reply(&header, &payload);
+        let (data, transferred, _) = receive(&reply, existing).unwrap();
+        assert_eq!(data, content);
+        assert_eq!(transferred, payload.len() as u64);
+
+        // Cut short, it decodes to fewer bytes than the file has
+        assert!(receive(&reply[..reply.len() - 10], existing).is_err());
+    }
+
+    #[test]
+    fn test_receive_send_file_uncompressed() {
+        let header = SendFileHeader {
+            compression: "none".to_string(),
+            size: 5,
+            payload_size: Some(5),
+            mtime: None,
+            reason: None,
+            sample_ratio: None,
+        };
+
+        let (data, transferred, decoded_header) =
+            receive(&send_file_reply(&header, b"hello"), b"").unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(transferred, 5);
+        // Headers without a reason (older sy-remote) carry no decision
+        assert!(decoded_header.decision().is_none());
+    }
+
+    #[test]
+    fn test_receive_send_file_truncated() {
+        let header = SendFileHeader {
+            compression: "none".to_string(),
+            size: 10,
+            payload_size: Some(10),
+            mtime: None,
+            reason: None,
+            sample_ratio: None,
+        };
+
+        assert!(receive(&send_file_reply(&header, b"short"), b"").is_err());
+        assert!(receive(b"no header", b"").is_err());
+    }
+
+    /// Remote file acknowledging at most `ack` bytes per write, like a
+    /// pipelined SFTP write, recording how much each write was offered
+    struct PipelinedWriter {
+        ack: usize,
+        data: Vec<u8>,
+        offered: Vec<usize>,
+    }
+
+    impl std::io::Write for PipelinedWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.offered.push(buf.len());
+            let n = buf.len().min(self.ack);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_upload_chunks_keeps_window_in_flight() {
+        let source: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let mut remote = PipelinedWriter {
+            ack: 300,
+            data: Vec::new(),
+            offered: Vec::new(),
+        };
+
+        let (size, checksum) = upload_chunks(
+            &mut source.as_slice(),
+            Path::new("src"),
+            &mut remote,
+            Path::new("dest"),
+            0,
+            1024,
+            &|_| {},
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(size, source.len() as u64);
+        assert_eq!(checksum, xxhash_rust::xxh3::xxh3_64(&source));
+        assert_eq!(remote.data, source);
+        // Every write until the source runs out offers a full window
+        let full = remote.offered.iter().take_while(|&&n| n == 1024).count();
+        assert!(full >= (source.len() - 1024) / 300);
+        assert!(remote.offered[full..].windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn test_stripe_count() {
+        const MB: u64 = 1024 * 1024;
+        assert_eq!(stripe_count(10 * MB, 8), 1);
+        assert_eq!(stripe_count(32 * MB, 8), 2);
+        assert_eq!(stripe_count(100 * MB, 4), 4);
+        assert_eq!(stripe_count(10 * 1024 * MB, 8), 8);
+        assert_eq!(stripe_count(0, 8), 1);
+    }
 }
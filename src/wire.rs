@@ -0,0 +1,599 @@
+use crate::delta::WeakHash;
+use crate::owner::{self, FilePerms, IdMap};
+use crate::sync::scanner::FileEntry;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version of the binary encoding, sent as the first byte of every binary
+/// payload or stream so a mismatched sy-remote fails loudly instead of
+/// misreading data
+///
+/// Bump whenever a binary-encoded struct changes shape; the version is also
+/// part of the advertised capability, so mismatched builds fall back to JSON.
+pub const BINARY_VERSION: u8 = 3;
+
+/// Binary frames larger than this are treated as corrupt
+const MAX_FRAME_SIZE: u32 = 256 * 1024 * 1024;
+
+/// Encoding of structured data exchanged with sy-remote
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WireFormat {
+    /// JSON, understood by every sy-remote version
+    #[default]
+    Json,
+    /// Version byte followed by bincode (no base64, no text numbers)
+    Binary,
+}
+
+impl WireFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "json",
+            WireFormat::Binary => "binary",
+        }
+    }
+
+    /// Token advertised in `Capabilities::formats`
+    ///
+    /// Binary includes the encoding version: bincode has no optional fields,
+    /// so only builds with the same version can use it.
+    pub fn capability(&self) -> String {
+        match self {
+            WireFormat::Json => self.as_str().to_string(),
+            WireFormat::Binary => format!("{}-v{}", self.as_str(), BINARY_VERSION),
+        }
+    }
+}
+
+/// Printed by `sy-remote capabilities` to negotiate the wire format
+///
+/// The default is what a sy-remote without `capabilities` supports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub version: String,
+    /// Supported wire formats (strings, so unknown future formats don't break parsing)
+    pub formats: Vec<String>,
+    /// Supported delta sync weak checksums (missing before Buzhash was added)
+    #[serde(default)]
+    pub weak_hashes: Vec<String>,
+    /// Served by `sy-remote restrict`, which can't offer SFTP
+    #[serde(default)]
+    pub restricted: bool,
+    /// Answers `check-writable` (missing before the destination preflight)
+    #[serde(default)]
+    pub write_probe: bool,
+    /// sy-remote's clock when it answered, in milliseconds since the Unix
+    /// epoch (missing before clock skew detection)
+    #[serde(default)]
+    pub clock_ms: Option<i64>,
+    /// Answers `receive-batch` (missing before small-file batching)
+    #[serde(default)]
+    pub batch_receive: bool,
+    /// Sets mode, owner and group in `set-metadata` (missing before
+    /// ownership preservation)
+    #[serde(default)]
+    pub perms: bool,
+    /// Answers `commit-staged` (missing before --delay-updates)
+    #[serde(default)]
+    pub staged_commit: bool,
+    /// Takes `--stream` and `--offset` in `send-file` (missing before
+    /// streamed downloads)
+    #[serde(default)]
+    pub streamed_send: bool,
+}
+
+impl Capabilities {
+    /// Capabilities of this build
+    #[allow(dead_code)] // Used by sy-remote
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            formats: vec![
+                WireFormat::Json.capability(),
+                WireFormat::Binary.capability(),
+            ],
+            weak_hashes: vec![
+                WeakHash::Adler32.as_str().to_string(),
+                WeakHash::Buzhash.as_str().to_string(),
+            ],
+            restricted: false,
+            write_probe: true,
+            clock_ms: None,
+            batch_receive: true,
+            perms: true,
+            staged_commit: true,
+            streamed_send: true,
+        }
+    }
+
+    /// Most compact format both sides understand
+    pub fn negotiate(&self) -> WireFormat {
+        let binary = WireFormat::Binary.capability();
+        if self.formats.iter().any(|f| *f == binary) {
+            WireFormat::Binary
+        } else {
+            WireFormat::Json
+        }
+    }
+
+    /// Fastest delta sync weak checksum both sides understand
+    pub fn negotiate_weak_hash(&self) -> WeakHash {
+        if self
+            .weak_hashes
+            .iter()
+            .any(|h| h == WeakHash::Buzhash.as_str())
+        {
+            WeakHash::Buzhash
+        } else {
+            WeakHash::Adler32
+        }
+    }
+}
+
+/// Scan result printed by `sy-remote scan`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanOutput {
+    pub entries: Vec<WireFileEntry>,
+    #[serde(default)]
+    pub errors: Vec<WireScanError>, // Entries that couldn't be read (scan continued)
+    /// Names of the entries' owners and groups, as (id, name), for the
+    /// receiving side to map ids by name (missing before ownership preservation)
+    #[serde(default)]
+    pub users: Vec<(u32, String)>,
+    #[serde(default)]
+    pub groups: Vec<(u32, String)>,
+}
+
+impl ScanOutput {
+    /// Scan result for `entries`, naming their owners and groups
+    pub fn new(entries: Vec<WireFileEntry>, errors: Vec<WireScanError>) -> Self {
+        let (users, groups) = owner::names_of(entries.iter().filter_map(WireFileEntry::perms));
+        Self {
+            entries,
+            errors,
+            users,
+            groups,
+        }
+    }
+
+    /// Mapping of the scanned ids to this machine's; none with `numeric_ids`
+    pub fn id_map(&self, numeric_ids: bool) -> IdMap {
+        if numeric_ids {
+            return IdMap::default();
+        }
+        IdMap::new(&self.users, &self.groups)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WireScanError {
+    pub path: String,
+    pub error: String,
+    #[serde(default)]
+    pub special: bool, // Socket, FIFO or device node (`error` is its kind)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WireFileEntry {
+    pub path: String,
+    pub size: u64,
+    pub mtime: i64,
+    pub is_dir: bool,
+    // Extended metadata for full preservation
+    pub is_symlink: bool,
+    pub symlink_target: Option<String>,
+    pub is_sparse: bool,
+    pub allocated_size: u64,
+    #[serde(default, with = "xattr_values")]
+    pub xattrs: Option<Vec<(String, Vec<u8>)>>, // (key, value); base64 values in JSON
+    pub inode: Option<u64>,
+    pub nlink: u64,
+    #[serde(default)]
+    pub acls: Option<String>, // ACL text format (one per line)
+    #[serde(default)]
+    pub atime: Option<i64>, // Seconds since UNIX epoch
+    #[serde(default)]
+    pub crtime: Option<i64>, // Seconds since UNIX epoch (None if not recorded)
+    #[serde(default)]
+    pub mode: Option<u32>, // Permission bits (None from Windows)
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+}
+
+impl From<FileEntry> for WireFileEntry {
+    fn from(e: FileEntry) -> Self {
+        let secs = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64
+        };
+
+        WireFileEntry {
+            path: e.path.to_string_lossy().to_string(),
+            size: e.size,
+            mtime: secs(e.modified),
+            is_dir: e.is_dir,
+            is_symlink: e.is_symlink,
+            symlink_target: e.symlink_target.map(|p| p.to_string_lossy().to_string()),
+            is_sparse: e.is_sparse,
+            allocated_size: e.allocated_size,
+            xattrs: e.xattrs.map(|xattrs| xattrs.into_iter().collect()),
+            inode: e.inode,
+            nlink: e.nlink,
+            // ACLs travel as text
+            acls: e.acls.and_then(|acls| String::from_utf8(acls).ok()),
+            atime: e.atime.map(secs),
+            crtime: e.crtime.map(secs),
+            mode: e.perms.map(|perms| perms.mode),
+            uid: e.perms.map(|perms| perms.uid),
+            gid: e.perms.map(|perms| perms.gid),
+        }
+    }
+}
+
+impl WireFileEntry {
+    fn perms(&self) -> Option<FilePerms> {
+        Some(FilePerms {
+            mode: self.mode?,
+            uid: self.uid?,
+            gid: self.gid?,
+        })
+    }
+
+    /// Scanned entry, with its relative path taken below the scanned `root`
+    /// and its owner and group mapped by `ids`
+    pub fn into_entry(self, root: &Path, ids: &IdMap) -> FileEntry {
+        let perms = self.perms().map(|perms| ids.map(perms));
+        let from_secs = |secs: i64| UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64);
+        let path = PathBuf::from(self.path);
+
+        FileEntry {
+            relative_path: path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+            path,
+            size: self.size,
+            modified: from_secs(self.mtime),
+            is_dir: self.is_dir,
+            is_symlink: self.is_symlink,
+            symlink_target: self.symlink_target.map(PathBuf::from),
+            is_sparse: self.is_sparse,
+            allocated_size: self.allocated_size,
+            xattrs: self.xattrs.map(|xattrs| xattrs.into_iter().collect()),
+            inode: self.inode,
+            nlink: self.nlink,
+            acls: self.acls.map(String::into_bytes),
+            bsd_flags: None, // TODO: Serialize BSD flags in SSH protocol
+            atime: self.atime.map(from_secs),
+            crtime: self.crtime.map(from_secs),
+            perms,
+        }
+    }
+}
+
+/// One small file of a `sy-remote receive-batch` payload
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchFile {
+    pub path: String,
+    pub mode: Option<u32>, // Unix permission bits (None from Windows)
+    pub mtime: i64,        // Seconds since UNIX epoch
+    pub data: Vec<u8>,
+}
+
+/// Xattr values as base64 strings in JSON and raw bytes in binary
+mod xattr_values {
+    use base64::{engine::general_purpose, Engine as _};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    type Xattrs = Option<Vec<(String, Vec<u8>)>>;
+
+    pub fn serialize<S: Serializer>(xattrs: &Xattrs, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let encoded: Option<Vec<(&str, String)>> = xattrs.as_ref().map(|xattrs| {
+                xattrs
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), general_purpose::STANDARD.encode(value)))
+                    .collect()
+            });
+            encoded.serialize(serializer)
+        } else {
+            xattrs.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Xattrs, D::Error> {
+        if deserializer.is_human_readable() {
+            let encoded: Option<Vec<(String, String)>> = Option::deserialize(deserializer)?;
+            encoded
+                .map(|xattrs| {
+                    xattrs
+                        .into_iter()
+                        .map(|(key, value)| {
+                            let decoded = general_purpose::STANDARD
+                                .decode(value)
+                                .map_err(serde::de::Error::custom)?;
+                            Ok((key, decoded))
+                        })
+                        .collect()
+                })
+                .transpose()
+        } else {
+            Xattrs::deserialize(deserializer)
+        }
+    }
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Encode a whole payload (e.g. a scan result)
+#[allow(dead_code)] // Used by sy-remote
+pub fn encode<T: Serialize>(format: WireFormat, value: &T) -> io::Result<Vec<u8>> {
+    match format {
+        WireFormat::Json => Ok(serde_json::to_vec(value)?),
+        WireFormat::Binary => {
+            let mut out = vec![BINARY_VERSION];
+            bincode::serialize_into(&mut out, value).map_err(invalid_data)?;
+            Ok(out)
+        }
+    }
+}
+
+/// Decode a payload produced by [`encode`]
+pub fn decode<T: DeserializeOwned>(format: WireFormat, bytes: &[u8]) -> io::Result<T> {
+    match format {
+        WireFormat::Json => Ok(serde_json::from_slice(bytes)?),
+        WireFormat::Binary => {
+            check_version(bytes.first().copied())?;
+            bincode::deserialize(&bytes[1..]).map_err(invalid_data)
+        }
+    }
+}
+
+fn check_version(version: Option<u8>) -> io::Result<()> {
+    match version {
+        Some(BINARY_VERSION) => Ok(()),
+        Some(other) => Err(invalid_data(format!(
+            "Unsupported binary wire version {} (expected {})",
+            other, BINARY_VERSION
+        ))),
+        None => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Empty binary payload",
+        )),
+    }
+}
+
+/// Start a frame stream (binary streams open with the version byte)
+pub fn write_stream_start<W: Write>(format: WireFormat, output: &mut W) -> io::Result<()> {
+    match format {
+        WireFormat::Json => Ok(()),
+        WireFormat::Binary => output.write_all(&[BINARY_VERSION]),
+    }
+}
+
+/// Check the start of a frame stream written by [`write_stream_start`]
+pub fn read_stream_start<R: Read>(format: WireFormat, input: &mut R) -> io::Result<()> {
+    match format {
+        WireFormat::Json => Ok(()),
+        WireFormat::Binary => {
+            let mut version = [0u8; 1];
+            input.read_exact(&mut version)?;
+            check_version(Some(version[0]))
+        }
+    }
+}
+
+/// Write one frame: a JSON line, or a little-endian u32 length and bincode
+pub fn write_frame<W: Write, T: Serialize>(
+    format: WireFormat,
+    output: &mut W,
+    value: &T,
+) -> io::Result<()> {
+    match format {
+        WireFormat::Json => {
+            serde_json::to_writer(&mut *output, value)?;
+            output.write_all(b"\n")
+        }
+        WireFormat::Binary => {
+            let payload = bincode::serialize(value).map_err(invalid_data)?;
+            let len = u32::try_from(payload.len())
+                .ok()
+                .filter(|len| *len <= MAX_FRAME_SIZE)
+                .ok_or_else(|| invalid_data(format!("Frame too large: {} bytes", payload.len())))?;
+            output.write_all(&len.to_le_bytes())?;
+            output.write_all(&payload)
+        }
+    }
+}
+
+/// Read one frame written by [`write_frame`] (None at a clean end of stream)
+pub fn read_frame<R: BufRead, T: DeserializeOwned>(
+    format: WireFormat,
+    input: &mut R,
+) -> io::Result<Option<T>> {
+    match format {
+        WireFormat::Json => {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            Ok(Some(serde_json::from_str(line.trim())?))
+        }
+        WireFormat::Binary => {
+            if input.fill_buf()?.is_empty() {
+                return Ok(None);
+            }
+            let mut len = [0u8; 4];
+            input.read_exact(&mut len)?;
+            let len = u32::from_le_bytes(len);
+            if len > MAX_FRAME_SIZE {
+                return Err(invalid_data(format!("Frame too large: {} bytes", len)));
+            }
+            let mut payload = vec![0u8; len as usize];
+            input.read_exact(&mut payload)?;
+            bincode::deserialize(&payload)
+                .map(Some)
+                .map_err(invalid_data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> WireFileEntry {
+        WireFileEntry {
+            path: "/data/file.bin".to_string(),
+            size: 1234,
+            mtime: 1_700_000_000,
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            is_sparse: false,
+            allocated_size: 4096,
+            xattrs: Some(vec![("user.tag".to_string(), vec![0, 159, 255])]),
+            inode: Some(42),
+            nlink: 1,
+            acls: None,
+            atime: Some(1_700_000_100),
+            crtime: None,
+            mode: Some(0o640),
+            uid: Some(1000),
+            gid: Some(100),
+        }
+    }
+
+    #[test]
+    fn test_scan_output_roundtrip_both_formats() {
+        for format in [WireFormat::Json, WireFormat::Binary] {
+            let output = ScanOutput {
+                entries: vec![entry()],
+                errors: vec![WireScanError {
+                    path: "/data/locked".to_string(),
+                    error: "Permission denied".to_string(),
+                    special: false,
+                }],
+                users: vec![(1000, "alice".to_string())],
+                groups: Vec::new(),
+            };
+            let decoded: ScanOutput = decode(format, &encode(format, &output).unwrap()).unwrap();
+            assert_eq!(decoded.entries[0].xattrs, entry().xattrs);
+            assert_eq!(decoded.entries[0].inode, Some(42));
+            assert_eq!(decoded.entries[0].atime, Some(1_700_000_100));
+            assert_eq!(decoded.errors[0].path, "/data/locked");
+            assert_eq!(decoded.users, output.users);
+            let perms = decoded.entries[0].perms().unwrap();
+            assert_eq!((perms.mode, perms.uid, perms.gid), (0o640, 1000, 100));
+        }
+    }
+
+    #[test]
+    fn test_json_from_older_remote_has_no_perms() {
+        let json = br#"{"entries":[{"path":"/data/a","size":1,"mtime":0,"is_dir":false,
+            "is_symlink":false,"symlink_target":null,"is_sparse":false,"allocated_size":1,
+            "inode":null,"nlink":1}]}"#;
+        let output: ScanOutput = decode(WireFormat::Json, json).unwrap();
+        assert!(output.users.is_empty());
+        let entry = output.entries.into_iter().next().unwrap();
+        assert_eq!(
+            entry
+                .into_entry(Path::new("/data"), &IdMap::default())
+                .perms,
+            None
+        );
+    }
+
+    #[test]
+    fn test_json_xattrs_stay_base64() {
+        let json = String::from_utf8(encode(WireFormat::Json, &entry()).unwrap()).unwrap();
+        assert!(json.contains(r#"["user.tag","AJ//"]"#));
+    }
+
+    #[test]
+    fn test_binary_is_smaller_than_json() {
+        let output = ScanOutput::new((0..100).map(|_| entry()).collect(), Vec::new());
+        let json = encode(WireFormat::Json, &output).unwrap();
+        let binary = encode(WireFormat::Binary, &output).unwrap();
+        assert!(binary.len() < json.len());
+    }
+
+    #[test]
+    fn test_binary_version_mismatch_is_rejected() {
+        let mut bytes = encode(WireFormat::Binary, &entry()).unwrap();
+        bytes[0] = BINARY_VERSION + 1;
+        let err = decode::<WireFileEntry>(WireFormat::Binary, &bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut stream: &[u8] = &[BINARY_VERSION + 1];
+        assert!(read_stream_start(WireFormat::Binary, &mut stream).is_err());
+    }
+
+    #[test]
+    fn test_frames_roundtrip_both_formats() {
+        for format in [WireFormat::Json, WireFormat::Binary] {
+            let mut stream = Vec::new();
+            write_stream_start(format, &mut stream).unwrap();
+            write_frame(format, &mut stream, &(1u64, "one".to_string())).unwrap();
+            write_frame(format, &mut stream, &(2u64, "two".to_string())).unwrap();
+
+            let mut input = &stream[..];
+            read_stream_start(format, &mut input).unwrap();
+            let first: Option<(u64, String)> = read_frame(format, &mut input).unwrap();
+            let second: Option<(u64, String)> = read_frame(format, &mut input).unwrap();
+            let end: Option<(u64, String)> = read_frame(format, &mut input).unwrap();
+            assert_eq!(first, Some((1, "one".to_string())));
+            assert_eq!(second, Some((2, "two".to_string())));
+            assert_eq!(end, None);
+        }
+    }
+
+    #[test]
+    fn test_negotiate_prefers_binary() {
+        assert_eq!(Capabilities::current().negotiate(), WireFormat::Binary);
+
+        let old = Capabilities {
+            version: "0.0.1".to_string(),
+            formats: vec!["json".to_string(), "future".to_string()],
+            weak_hashes: Vec::new(),
+            restricted: false,
+            write_probe: false,
+            clock_ms: None,
+            batch_receive: false,
+            perms: false,
+            staged_commit: false,
+            streamed_send: false,
+        };
+        assert_eq!(old.negotiate(), WireFormat::Json);
+    }
+
+    #[test]
+    fn test_negotiate_weak_hash() {
+        assert_eq!(
+            Capabilities::current().negotiate_weak_hash(),
+            WeakHash::Buzhash
+        );
+
+        // sy-remote from before weak hashes were listed (and before the
+        // binary encoding was versioned, so it falls back to JSON)
+        let old: Capabilities =
+            serde_json::from_str(r#"{"version":"0.1.0","formats":["json","binary"]}"#).unwrap();
+        assert_eq!(old.negotiate(), WireFormat::Json);
+        assert_eq!(old.negotiate_weak_hash(), WeakHash::Adler32);
+        assert!(!old.restricted);
+        assert!(!old.write_probe);
+        assert_eq!(old.clock_ms, None);
+        assert!(!old.batch_receive);
+        assert!(!old.staged_commit);
+        assert!(!old.streamed_send);
+        assert_eq!(Capabilities::default().negotiate(), WireFormat::Json);
+    }
+}